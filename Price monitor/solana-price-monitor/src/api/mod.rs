@@ -0,0 +1,4414 @@
+mod schema;
+mod topics;
+
+pub use schema::{api_schemas, required_fields};
+pub use topics::{TopicPattern, Topics};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{ConnectInfo, Path as UrlPath, Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{info, debug, error, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+use crate::cache::PriceCache;
+use crate::calculator::{TokenRegistry, UsdPricer};
+use crate::config::{ApiConfig, ApiRateLimitConfig, ListenAddr, PoolSelection, PoolSlot, Settings, SkippedPool};
+use crate::detector::{
+    generate_common_paths, AggregatedOpportunity, CalibrationTable, ConfidenceCalibrator, OpportunityDetector,
+    PairSummaries, PathEntry, PathSetMetrics, PairRegime, Spread, SpreadAlert, SpreadAlertDetector, SpreadTracker,
+    StatisticalArbitrageDetector, TriangularArbitrageDetector, TriangularPathSet, VolatilityTracker, pair_spread,
+};
+use crate::metrics::{MetricsSnapshot, PipelineMetrics, ReceiptLatencySummary};
+use crate::models::{
+    timestamp, Dex, Opportunity, OpportunitySet, OpportunityType, PriceData, PriceSource, TimestampFormat, TopK,
+};
+use crate::scheduler::{PauseController, PauseStatus};
+use crate::utils::HealthTracker;
+use crate::websocket::tap::TAP_BUFFER;
+use crate::websocket::activity::ACTIVITY_WINDOW;
+use crate::websocket::{
+    ConnectionStatus, PoolActivity, QueueMetrics, SubscriptionBook, SubscriptionInfo, SubscriptionKind, SwapActivity,
+    TapFilter, TapSet,
+    WsHandle,
+};
+
+/// Version of the message envelope, reported by `GET /version`
+///
+/// 2: opportunities no longer carry `leg_slots`; each leg has its `slot`.
+/// 3: the envelope and price `ts` follow `api.timestamp_format` like every
+/// other timestamp (RFC 3339 by default) instead of always being epoch ms.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Messages sent to frontend clients
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(tag = "type", content = "data")]
+pub enum ApiMessage {
+    #[serde(rename = "price")]
+    PriceUpdate {
+        pair: String,
+        dex: Dex,
+        price: f64,
+        slot: u64,
+        /// When the update was processed
+        #[serde(serialize_with = "timestamp::serialize")]
+        ts: chrono::DateTime<chrono::Utc>,
+    },
+    #[serde(rename = "opportunity")]
+    OpportunityFound(Opportunity),
+    #[serde(rename = "opportunity_group")]
+    OpportunityGroup(AggregatedOpportunity),
+    #[serde(rename = "spread_alert")]
+    SpreadAlert(SpreadAlert),
+    /// Throttled best bid/ask of a pair
+    #[serde(rename = "spread")]
+    Spread(Spread),
+    /// Throttled `OpportunitySet` of a pair whose prices or opportunities changed
+    #[serde(rename = "pair_summary")]
+    PairSummary(Box<OpportunitySet>),
+    /// First message on every `/ws` connection
+    #[serde(rename = "snapshot")]
+    Snapshot(Snapshot),
+    #[serde(rename = "system_status")]
+    SystemStatus {
+        paused: bool,
+        reason: Option<String>,
+        /// Label of the RPC endpoint the WebSocket feed is using
+        rpc_endpoint: String,
+        /// False while the WebSocket feed is reconnecting or failed
+        ws_connected: bool,
+        /// Failed connection attempts since the feed last connected
+        consecutive_failures: u32,
+        /// Seconds the current upstream connection has been up
+        connection_uptime_secs: Option<u64>,
+        /// Account and program subscriptions confirmed on that connection
+        active_subscriptions: usize,
+        /// DEXes whose p95 slot-to-receipt latency exceeded
+        /// `monitoring.receipt_latency_alert_ms` at the last health check
+        #[serde(default)]
+        slow_dexes: Vec<String>,
+    },
+    #[serde(rename = "metrics")]
+    SystemMetrics(SystemMetrics),
+}
+
+/// Periodic pipeline figures, broadcast every `api.metrics_interval_ms` and
+/// served on `GET /metrics/system`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, JsonSchema)]
+pub struct SystemMetrics {
+    pub fps: u64,
+    pub cache_entries: usize,
+    /// Feed frames and bytes per second since the previous report
+    #[serde(default)]
+    pub messages_per_sec: f64,
+    #[serde(default)]
+    pub bytes_per_sec: f64,
+    /// Prices written to the cache per second since the previous report
+    #[serde(default)]
+    pub updates_per_sec: f64,
+    /// Decode + cache latency of account updates over the last one to two
+    /// minutes, in µs
+    #[serde(default)]
+    pub processing_p50_us: Option<u64>,
+    #[serde(default)]
+    pub processing_p99_us: Option<u64>,
+    /// Cached update to completed scan over the same window, in µs
+    #[serde(default)]
+    pub scan_p99_us: Option<u64>,
+    /// Fraction of the feed event queue in use
+    #[serde(default)]
+    pub channel_occupancy: f64,
+    /// Slot announced to account update processed, per DEX, since the
+    /// previous report
+    #[serde(default)]
+    pub receipt_latency: BTreeMap<String, ReceiptLatencySummary>,
+    #[serde(default)]
+    pub ws_connected: bool,
+    #[serde(default)]
+    pub active_subscriptions: usize,
+    /// Opportunities emitted since startup, by type
+    #[serde(default)]
+    pub opportunities: BTreeMap<String, u64>,
+    /// Times a stream client fell behind the broadcast, since startup
+    #[serde(default)]
+    pub client_lag_events: u64,
+    /// Messages those clients missed
+    #[serde(default)]
+    pub client_messages_dropped: u64,
+    /// Connected clients that have fallen behind at least once
+    #[serde(default)]
+    pub lagging_clients: usize,
+    /// Open `/ws` connections
+    #[serde(default)]
+    pub ws_clients: usize,
+    /// `/ws` connections closed for missing the pong deadline, since startup
+    #[serde(default)]
+    pub ws_clients_reaped: u64,
+}
+
+/// How far stream clients fall behind the broadcast channel
+///
+/// Each client counts its own lag events; these are the totals.
+#[derive(Debug, Default)]
+pub struct ClientLag {
+    events: AtomicU64,
+    dropped: AtomicU64,
+    lagging_clients: AtomicUsize,
+}
+
+impl ClientLag {
+    /// Record that a client missed `dropped` messages; `first` when it had
+    /// not lagged before
+    fn lagged(&self, dropped: u64, first: bool) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.dropped.fetch_add(dropped, Ordering::Relaxed);
+        if first {
+            self.lagging_clients.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A client that lagged has disconnected
+    fn lagged_client_left(&self) {
+        self.lagging_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Open `/ws` connections and the ones closed by the heartbeat
+#[derive(Debug, Default)]
+pub struct WsClients {
+    active: AtomicUsize,
+    reaped: AtomicU64,
+}
+
+impl WsClients {
+    /// Count a connection until the returned guard drops
+    fn connected(self: &Arc<Self>) -> WsClientGuard {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        WsClientGuard(self.clone())
+    }
+}
+
+struct WsClientGuard(Arc<WsClients>);
+
+impl Drop for WsClientGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Server pings on `/ws` connections, from `api.ping_interval_seconds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    /// How long a ping may go unanswered, and a send may block on a client
+    /// that stopped reading
+    pub timeout: Duration,
+}
+
+impl Heartbeat {
+    /// `None` when pings are turned off
+    pub fn from_config(config: &ApiConfig) -> Option<Self> {
+        (config.ping_interval_seconds > 0).then(|| Self {
+            interval: Duration::from_secs(config.ping_interval_seconds),
+            timeout: Duration::from_secs(config.pong_timeout_seconds),
+        })
+    }
+}
+
+impl ApiMessage {
+    /// The `type` tag the message is sent with
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiMessage::PriceUpdate { .. } => "price",
+            ApiMessage::OpportunityFound(_) => "opportunity",
+            ApiMessage::OpportunityGroup(_) => "opportunity_group",
+            ApiMessage::SpreadAlert(_) => "spread_alert",
+            ApiMessage::Spread(_) => "spread",
+            ApiMessage::PairSummary(_) => "pair_summary",
+            ApiMessage::Snapshot(_) => "snapshot",
+            ApiMessage::SystemStatus { .. } => "system_status",
+            ApiMessage::SystemMetrics(_) => "metrics",
+        }
+    }
+
+    /// Every `type` sent on the broadcast (snapshots go to one client only)
+    const BROADCAST_KINDS: [&'static str; 8] = [
+        "price",
+        "opportunity",
+        "opportunity_group",
+        "spread_alert",
+        "spread",
+        "pair_summary",
+        "system_status",
+        "metrics",
+    ];
+
+    /// Pairs the message is about; empty for system-wide messages
+    pub fn pairs(&self) -> Vec<&str> {
+        match self {
+            ApiMessage::PriceUpdate { pair, .. } => vec![pair],
+            ApiMessage::OpportunityFound(opportunity) => vec![&opportunity.token_pair],
+            ApiMessage::OpportunityGroup(group) => group.strategies.iter().map(|o| o.token_pair.as_str()).collect(),
+            ApiMessage::SpreadAlert(alert) => vec![&alert.pair],
+            ApiMessage::Spread(spread) => vec![&spread.pair],
+            ApiMessage::PairSummary(set) => vec![&set.pair],
+            ApiMessage::Snapshot(_) | ApiMessage::SystemStatus { .. } | ApiMessage::SystemMetrics(_) => Vec::new(),
+        }
+    }
+}
+
+/// Encoding of `/ws` frames, chosen per connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum WireFormat {
+    /// Text frames
+    #[default]
+    Json,
+    /// Binary frames with the same object encoded as MessagePack (named fields)
+    Msgpack,
+}
+
+/// Wraps one connection's outgoing messages in the versioned envelope
+///
+/// `{v, seq, ts, type, data}`: `seq` counts up by one per message on the
+/// connection so clients can spot gaps, `ts` is the send time. The envelope
+/// is built before encoding, so it is the same in every format, and so are
+/// the timestamps in it, `ts` included (`api.timestamp_format`).
+struct Framer {
+    legacy: bool,
+    seq: u64,
+    format: WireFormat,
+    timestamps: TimestampFormat,
+}
+
+impl Framer {
+    fn new(legacy: bool, timestamps: TimestampFormat) -> Self {
+        Self { legacy, seq: 0, format: WireFormat::Json, timestamps }
+    }
+
+    /// A broadcast message as JSON text (for SSE)
+    fn message(&mut self, message: &ApiMessage) -> serde_json::Result<String> {
+        Ok(self.envelope(self.to_value(message)?).to_string())
+    }
+
+    /// A broadcast message as a WebSocket frame in the connection's format
+    fn ws_message(&mut self, message: &ApiMessage) -> Result<Message> {
+        self.ws_frame(self.to_value(message)?)
+    }
+
+    /// `value` as JSON with the connection's timestamp format
+    fn to_value(&self, value: &impl Serialize) -> serde_json::Result<serde_json::Value> {
+        self.timestamps.scope(|| serde_json::to_value(value))
+    }
+
+    /// A `{type, data}` object as a WebSocket frame in the connection's format
+    fn ws_frame(&mut self, value: serde_json::Value) -> Result<Message> {
+        let value = self.envelope(value);
+        Ok(match self.format {
+            WireFormat::Json => Message::Text(value.to_string()),
+            WireFormat::Msgpack => Message::Binary(rmp_serde::to_vec_named(&value)?),
+        })
+    }
+
+    /// Add `v`, `seq` and `ts` to a `{type, data}` object unless in legacy mode
+    fn envelope(&mut self, mut value: serde_json::Value) -> serde_json::Value {
+        if let (false, Some(fields)) = (self.legacy, value.as_object_mut()) {
+            self.seq += 1;
+            fields.insert("v".to_string(), PROTOCOL_VERSION.into());
+            fields.insert("seq".to_string(), self.seq.into());
+            fields.insert("ts".to_string(), self.timestamps.to_value(chrono::Utc::now()));
+        }
+        value
+    }
+}
+
+/// What one `/ws` client asked to receive; `None` lets everything through
+///
+/// System-wide messages carry no pair and pass any pair filter.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct ClientFilter {
+    /// Upper-cased pairs
+    pairs: Option<BTreeSet<String>>,
+    /// Message `type` tags
+    types: Option<BTreeSet<String>>,
+}
+
+impl ClientFilter {
+    /// What the client's side of the broadcast subscribes to: topics
+    /// covering everything the filter lets through
+    fn subscribe(&self, topics: &Topics) -> broadcast::Receiver<ApiMessage> {
+        if self.pairs.is_none() && self.types.is_none() {
+            return topics.subscribe();
+        }
+        let kinds: Vec<&str> = match &self.types {
+            Some(types) => types.iter().flat_map(|kind| covered_kinds(kind)).collect(),
+            None => ApiMessage::BROADCAST_KINDS.to_vec(),
+        };
+        let patterns = kinds.into_iter().flat_map(|kind| match &self.pairs {
+            Some(pairs) => pairs.iter().map(|pair| TopicPattern::for_pair(kind, pair)).collect(),
+            None => vec![TopicPattern::kind(kind)],
+        });
+        topics.subscribe_to(patterns)
+    }
+
+    fn matches(&self, message: &ApiMessage) -> bool {
+        if self.types.as_ref().is_some_and(|types| !types.iter().any(|kind| covered_kinds(kind).contains(&message.kind()))) {
+            return false;
+        }
+        let Some(pairs) = &self.pairs else {
+            return true;
+        };
+        let message_pairs = message.pairs();
+        message_pairs.is_empty() || message_pairs.iter().any(|pair| pairs.contains(&pair.to_ascii_uppercase()))
+    }
+
+    /// Apply a client command
+    ///
+    /// `subscribe` adds the listed pairs and types to what the client gets
+    /// (the first list of each narrows it from everything); `unsubscribe`
+    /// removes them, and with neither list drops all filters.
+    fn apply(&mut self, command: ClientCommand) {
+        let upper = |pairs: Vec<String>| pairs.into_iter().map(|p| p.to_ascii_uppercase());
+        match command {
+            ClientCommand::Subscribe { pairs, types } => {
+                if let Some(pairs) = pairs {
+                    self.pairs.get_or_insert_with(BTreeSet::new).extend(upper(pairs));
+                }
+                if let Some(types) = types {
+                    self.types.get_or_insert_with(BTreeSet::new).extend(types);
+                }
+            }
+            // Requests and connection settings, not filter changes
+            ClientCommand::SetFormat { .. }
+            | ClientCommand::GetSnapshot
+            | ClientCommand::GetHistory { .. }
+            | ClientCommand::Ping => {}
+            ClientCommand::Unsubscribe { pairs: None, types: None } => *self = ClientFilter::default(),
+            ClientCommand::Unsubscribe { pairs, types } => {
+                if let (Some(filter), Some(pairs)) = (&mut self.pairs, pairs) {
+                    for pair in upper(pairs) {
+                        filter.remove(&pair);
+                    }
+                }
+                if let (Some(filter), Some(types)) = (&mut self.types, types) {
+                    for kind in types {
+                        filter.remove(&kind);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Message kinds a subscribed `type` lets through: groups are made of
+/// opportunities, so "opportunity" takes them too
+fn covered_kinds(kind: &str) -> Vec<&str> {
+    match kind {
+        "opportunity" => vec!["opportunity", "opportunity_group"],
+        kind => vec![kind],
+    }
+}
+
+/// `GET /events` filters, as comma-separated lists
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct EventParams {
+    pairs: Option<String>,
+    types: Option<String>,
+}
+
+impl From<EventParams> for ClientFilter {
+    fn from(params: EventParams) -> Self {
+        let list = |list: Option<String>| {
+            list.map(|list| list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+        };
+        let mut filter = ClientFilter::default();
+        filter.apply(ClientCommand::Subscribe { pairs: list(params.pairs), types: list(params.types) });
+        filter
+    }
+}
+
+/// Messages `/ws` clients send, e.g.
+/// `{"op":"subscribe","pairs":["SOL-USDC"],"types":["price","opportunity"]}`,
+/// `{"op":"set_format","format":"msgpack"}` or
+/// `{"op":"get_history","pair":"SOL-USDC","n":200,"id":7}`
+///
+/// An `id` of any JSON type is echoed in the reply.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum ClientCommand {
+    #[serde(rename = "set_format")]
+    SetFormat { format: WireFormat },
+    /// Current prices and recent opportunities, as sent on connect
+    #[serde(rename = "get_snapshot")]
+    GetSnapshot,
+    /// The `n` most recent opportunities, optionally of one pair
+    #[serde(rename = "get_history")]
+    GetHistory {
+        #[serde(default)]
+        pair: Option<String>,
+        #[serde(default)]
+        n: Option<usize>,
+    },
+    Ping,
+    Subscribe {
+        #[serde(default)]
+        pairs: Option<Vec<String>>,
+        #[serde(default)]
+        types: Option<Vec<String>>,
+    },
+    Unsubscribe {
+        #[serde(default)]
+        pairs: Option<Vec<String>>,
+        #[serde(default)]
+        types: Option<Vec<String>>,
+    },
+}
+
+/// Reload handle of the global log filter, for `PUT /admin/log-level`
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Running detectors whose state `POST /admin/detectors/reset` clears;
+/// `None` for those that are disabled
+#[derive(Clone, Default)]
+pub struct DetectorHandles {
+    pub spatial: Option<Arc<RwLock<OpportunityDetector>>>,
+    pub statistical: Option<Arc<RwLock<StatisticalArbitrageDetector>>>,
+    pub triangular: Option<Arc<RwLock<TriangularArbitrageDetector>>>,
+    pub spread_alerts: Option<Arc<SpreadAlertDetector>>,
+    pub spreads: Option<Arc<SpreadTracker>>,
+}
+
+/// Shared state for API handlers
+#[derive(Clone)]
+pub struct AppState {
+    pub tx: Topics,
+    pub calibrator: Arc<RwLock<ConfidenceCalibrator>>,
+    pub triangular_paths: Arc<RwLock<TriangularPathSet>>,
+    pub volatility: Arc<VolatilityTracker>,
+    /// Latest price per pair and DEX, for `GET /prices`
+    pub cache: Arc<PriceCache>,
+    pub pools: Arc<RwLock<PoolSelection>>,
+    pub pause: Arc<PauseController>,
+    pub ws_status: watch::Receiver<ConnectionStatus>,
+    /// Settings the main loop is running with, updated on every applied reload
+    pub settings: watch::Receiver<Settings>,
+    pub detectors: DetectorHandles,
+    /// `None` when logging wasn't set up with a reloadable filter
+    pub log_filter: Option<LogFilterHandle>,
+    /// Subscriptions of the current feed connection
+    pub subscriptions: SubscriptionBook,
+    /// Raw frame taps of the feed, for `POST /admin/tap`
+    pub taps: TapSet,
+    /// Swaps per pool, when `websocket.swap_activity` is on
+    pub activity: SwapActivity,
+    /// Recently emitted opportunities, for `GET /opportunities`
+    pub opportunities: OpportunityHistory,
+    /// USD profit estimates for `GET /opportunities/stats`
+    pub usd: UsdPricer,
+    /// Token decimals for decoding pools added through `/admin/pools`
+    pub tokens: TokenRegistry,
+    /// Uptime and cache update recency, for `GET /health`
+    pub health: Arc<HealthTracker>,
+    /// Per-pair summaries, for `GET /pairs/:pair/summary`
+    pub pair_summaries: Arc<PairSummaries>,
+    /// Request rates and streaming connections allowed per client
+    pub limits: Arc<ApiLimits>,
+    /// Live feed handle and pool budget for `/admin/pools`
+    pub pool_control: PoolControl,
+    /// Pubkeys added by registry discovery, for `GET /pairs`
+    pub discovered_pools: Arc<HashSet<String>>,
+    /// Latest `SystemMetrics` sample, for `GET /metrics/system`
+    pub system_metrics: LatestMetrics,
+    /// Stream clients falling behind the broadcast channel
+    pub client_lag: Arc<ClientLag>,
+    /// Ping schedule of `/ws` connections; `None` disables it
+    pub heartbeat: Option<Heartbeat>,
+    /// Open and reaped `/ws` connections
+    pub ws_clients: Arc<WsClients>,
+    /// Send messages without the versioned envelope (`api.legacy_messages`)
+    pub legacy_messages: bool,
+    /// How model timestamps are sent on `/ws` and `/events` (`api.timestamp_format`)
+    pub timestamp_format: TimestampFormat,
+    /// Directory tap files are written to, and their disk budget
+    pub tap_storage: Arc<TapStorage>,
+    /// Cancelled on shutdown: the server stops accepting and clients get a close frame
+    pub shutdown: CancellationToken,
+}
+
+/// Triangular scan set listing, including pruned paths
+#[derive(Serialize)]
+struct TriangularPathsResponse {
+    metrics: PathSetMetrics,
+    paths: Vec<PathEntry>,
+}
+
+/// `GET /config`: the running settings with secrets masked, and what they
+/// expand to at runtime
+#[derive(Serialize)]
+struct ConfigResponse {
+    settings: Settings,
+    /// Names of the enabled detectors
+    detectors: Vec<&'static str>,
+    /// Pools selected for monitoring (static, discovered and added at runtime)
+    monitored_pools: usize,
+    skipped_pools: usize,
+    triangular_paths: PathSetMetrics,
+}
+
+/// Liveness of the price feed; served with 503 unless connected with a DEX
+/// updating within its stale threshold
+#[derive(Serialize)]
+struct HealthResponse {
+    healthy: bool,
+    websocket: ConnectionStatus,
+    consecutive_failures: u32,
+    connection_uptime_secs: Option<u64>,
+    uptime_seconds: u64,
+    /// Since the latest cache update from any DEX; `None` before the first
+    last_update_ms: Option<u64>,
+    /// DEX -> ms since its latest cache update
+    dex_last_update_ms: BTreeMap<String, u64>,
+    /// DEXes quiet for longer than the stale threshold, quietest first
+    stale_dexes: Vec<String>,
+    paused: bool,
+    /// Requests and streams refused by the rate limits
+    rejected: ApiRejections,
+}
+
+/// Every feed subscription with its freshness
+#[derive(Serialize)]
+struct SubscriptionsResponse {
+    /// Label of the RPC endpoint in use
+    endpoint: String,
+    connected: bool,
+    total: usize,
+    confirmed: usize,
+    pending: usize,
+    subscriptions: Vec<SubscriptionInfo>,
+    /// Pool pubkey -> notifications dropped for arriving out of slot order
+    out_of_order_drops: BTreeMap<String, u64>,
+}
+
+/// `GET /pairs`: monitored pools by pair, and the ones left out
+#[derive(Serialize)]
+struct PairsResponse {
+    pairs: BTreeMap<String, Vec<PoolStatus>>,
+    skipped: Vec<SkippedPoolStatus>,
+}
+
+/// One monitored pool, in subscription priority order within its pair
+#[derive(Serialize)]
+struct PoolStatus {
+    dex: Dex,
+    pubkey: String,
+    decoder: &'static str,
+    priority: i64,
+    /// Added by registry discovery rather than `[pools]`
+    discovered: bool,
+    subscription: SubscriptionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription_error: Option<String>,
+    /// `None` until the pool's first price
+    ms_since_update: Option<u64>,
+    /// Fresh and tradeable, so detection uses it
+    contributing: bool,
+}
+
+#[derive(Serialize)]
+struct SkippedPoolStatus {
+    #[serde(flatten)]
+    pool: SkippedPool,
+    discovered: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SubscriptionStatus {
+    Confirmed,
+    Pending,
+    /// Refused by the node
+    Failed,
+    /// Not requested on the current connection (or the feed has no subscriptions)
+    None,
+}
+
+/// What `/admin/pools` needs from the running feed
+///
+/// Pools added or removed at runtime last until a config reload re-applies
+/// the configured `[pools]`.
+#[derive(Debug, Clone, Default)]
+pub struct PoolControl {
+    /// Handle of the running WebSocket manager; `None` for feeds without
+    /// runtime subscriptions
+    handle: Arc<std::sync::RwLock<Option<WsHandle>>>,
+    max_pools: Arc<AtomicUsize>,
+    /// Held across the budget check and the insert of an added pool, so
+    /// concurrent adds can't overshoot `max_pools`
+    admission: Arc<std::sync::Mutex<()>>,
+    /// DEXes whose pools arrive on a program subscription
+    program_dexes: Arc<std::sync::RwLock<HashSet<Dex>>>,
+}
+
+impl PoolControl {
+    pub fn new(max_pools: usize) -> Self {
+        Self { max_pools: Arc::new(AtomicUsize::new(max_pools)), ..Self::default() }
+    }
+
+    /// Point at the current manager (replaced on every feed restart)
+    pub fn set_handle(&self, handle: Option<WsHandle>) {
+        *self.handle.write().unwrap() = handle;
+    }
+
+    pub fn set_max_pools(&self, max_pools: usize) {
+        self.max_pools.store(max_pools, Ordering::Relaxed);
+    }
+
+    pub fn set_program_dexes(&self, dexes: impl IntoIterator<Item = Dex>) {
+        *self.program_dexes.write().unwrap() = dexes.into_iter().collect();
+    }
+
+    fn handle(&self) -> Option<WsHandle> {
+        self.handle.read().unwrap().clone()
+    }
+
+    fn max_pools(&self) -> usize {
+        self.max_pools.load(Ordering::Relaxed)
+    }
+
+    /// Whether pools of `dex` need an account subscription of their own
+    fn subscribes(&self, dex: &Dex) -> bool {
+        !self.program_dexes.read().unwrap().contains(dex)
+    }
+}
+
+/// Clients tracked per rate limiter before idle ones are forgotten
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Token bucket per client IP
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: std::sync::Mutex<Buckets>,
+    rejected: AtomicU64,
+}
+
+struct Buckets {
+    /// Client -> (tokens left, last refill)
+    clients: HashMap<IpAddr, (f64, Instant)>,
+    /// Last time idle clients were forgotten
+    swept_at: Instant,
+}
+
+impl RateLimiter {
+    /// `per_second` of 0 lets everything through
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            per_second,
+            burst: f64::from(burst.max(1)),
+            buckets: std::sync::Mutex::new(Buckets { clients: HashMap::new(), swept_at: Instant::now() }),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a token for `client`, or say how long until one is available
+    fn check(&self, client: IpAddr) -> std::result::Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> std::result::Result<(), Duration> {
+        if self.per_second <= 0.0 {
+            return Ok(());
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        // Buckets that have refilled completely are the same as new ones, so
+        // clients idle that long are forgotten, at most once per refill period
+        // unless the map is full
+        let refill = Duration::from_secs_f64(self.burst / self.per_second);
+        if buckets.clients.len() >= MAX_TRACKED_CLIENTS || now.duration_since(buckets.swept_at) >= refill {
+            buckets.clients.retain(|_, (_, at)| now.duration_since(*at) < refill);
+            buckets.swept_at = now;
+        }
+        let (tokens, at) = buckets.clients.entry(client).or_insert((self.burst, now));
+        *tokens = (*tokens + now.duration_since(*at).as_secs_f64() * self.per_second).min(self.burst);
+        *at = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            return Ok(());
+        }
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        Err(Duration::from_secs_f64((1.0 - *tokens) / self.per_second))
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Client -> open streams; clients without any are removed
+type ClientStreams = Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>;
+
+/// Rate limiters per route group and the streaming connection caps
+pub struct ApiLimits {
+    rest: Arc<RateLimiter>,
+    admin: Arc<RateLimiter>,
+    /// `None` when streams are unlimited
+    streams: Option<Arc<Semaphore>>,
+    /// Streams one client may hold (0 = unlimited)
+    streams_per_client: usize,
+    client_streams: ClientStreams,
+    rejected_streams: AtomicU64,
+}
+
+/// A streaming connection slot, released on drop
+pub struct StreamPermit {
+    _global: Option<OwnedSemaphorePermit>,
+    client: Option<(IpAddr, ClientStreams)>,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let Some((client, client_streams)) = self.client.take() else {
+            return;
+        };
+        let mut client_streams = client_streams.lock().unwrap();
+        if let Some(open) = client_streams.get_mut(&client) {
+            *open -= 1;
+            if *open == 0 {
+                client_streams.remove(&client);
+            }
+        }
+    }
+}
+
+/// Requests and connections refused by `ApiLimits`, since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ApiRejections {
+    pub rest: u64,
+    pub admin: u64,
+    pub streams: u64,
+}
+
+impl ApiLimits {
+    pub fn new(config: &ApiRateLimitConfig) -> Self {
+        Self {
+            rest: Arc::new(RateLimiter::new(config.rest_per_second, config.rest_burst)),
+            admin: Arc::new(RateLimiter::new(config.admin_per_second, config.admin_burst)),
+            streams: (config.max_streams > 0).then(|| Arc::new(Semaphore::new(config.max_streams))),
+            streams_per_client: config.max_streams_per_client,
+            client_streams: ClientStreams::default(),
+            rejected_streams: AtomicU64::new(0),
+        }
+    }
+
+    /// A slot for a `/ws` or `/events` client; `None` at the client's or
+    /// the overall cap
+    fn stream_permit(&self, client: IpAddr) -> Option<StreamPermit> {
+        let mut permit = StreamPermit { _global: None, client: None };
+        if self.streams_per_client > 0 {
+            let mut client_streams = self.client_streams.lock().unwrap();
+            let open = client_streams.entry(client).or_default();
+            if *open >= self.streams_per_client {
+                self.rejected_streams.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            *open += 1;
+            permit.client = Some((client, self.client_streams.clone()));
+        }
+        if let Some(streams) = &self.streams {
+            // Dropping `permit` hands the client's slot back
+            let Ok(global) = streams.clone().try_acquire_owned() else {
+                self.rejected_streams.fetch_add(1, Ordering::Relaxed);
+                return None;
+            };
+            permit._global = Some(global);
+        }
+        Some(permit)
+    }
+
+    pub fn rejections(&self) -> ApiRejections {
+        ApiRejections {
+            rest: self.rest.rejected(),
+            admin: self.admin.rejected(),
+            streams: self.rejected_streams.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Most recent emitted opportunities, oldest dropped first
+///
+/// Fed by the aggregator with every strategy of each group it emits, so
+/// clients connecting late can catch up on what was broadcast.
+#[derive(Clone)]
+pub struct OpportunityHistory {
+    inner: Arc<std::sync::Mutex<VecDeque<Opportunity>>>,
+    capacity: usize,
+    /// Type name -> opportunities recorded since startup
+    totals: Arc<std::sync::Mutex<BTreeMap<String, u64>>>,
+}
+
+impl OpportunityHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            totals: Arc::default(),
+        }
+    }
+
+    pub fn record(&self, opportunity: Opportunity) {
+        *self
+            .totals
+            .lock()
+            .unwrap()
+            .entry(opportunity.opportunity_type.to_string())
+            .or_default() += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        let mut history = self.inner.lock().unwrap();
+        if history.len() == self.capacity {
+            history.pop_front();
+        }
+        history.push_back(opportunity);
+    }
+
+    pub fn record_group(&self, group: &AggregatedOpportunity) {
+        for opportunity in &group.strategies {
+            self.record(opportunity.clone());
+        }
+    }
+
+    /// Opportunities recorded since startup, by type (kept even when the
+    /// history itself is disabled)
+    pub fn totals(&self) -> BTreeMap<String, u64> {
+        self.totals.lock().unwrap().clone()
+    }
+
+    /// One page of matching opportunities and how many match in total
+    ///
+    /// Newest `detected_at` first; equal timestamps keep the most recently
+    /// recorded first, so pages don't shuffle between requests.
+    fn query(&self, query: &OpportunityQuery) -> (usize, Vec<Opportunity>) {
+        let history = self.inner.lock().unwrap();
+        let mut matching: Vec<&Opportunity> = history
+            .iter()
+            .rev()
+            .filter(|o| query.opportunity_type.map_or(true, |t| o.opportunity_type == t))
+            .filter(|o| query.pair.as_ref().map_or(true, |pair| o.token_pair.eq_ignore_ascii_case(pair)))
+            .filter(|o| query.from.map_or(true, |from| o.detected_at >= from))
+            .filter(|o| query.to.map_or(true, |to| o.detected_at < to))
+            .filter(|o| query.min_profit.map_or(true, |min| o.net_profit_percent >= min))
+            .collect();
+        // Stable, so ties stay in reverse recording order
+        matching.sort_by_key(|o| std::cmp::Reverse(o.detected_at));
+        let page = matching.iter().skip(query.offset).take(query.limit).map(|o| (*o).clone()).collect();
+        (matching.len(), page)
+    }
+}
+
+/// Most recent `SystemMetrics` sample; `None` until the first one
+pub type LatestMetrics = Arc<std::sync::RwLock<Option<SystemMetrics>>>;
+
+/// Gathers `SystemMetrics` from the cache, feed and opportunity history,
+/// with rates over the time since the previous sample
+pub struct MetricsSampler {
+    cache: Arc<PriceCache>,
+    pipeline: Arc<PipelineMetrics>,
+    ws_status: watch::Receiver<ConnectionStatus>,
+    queue: Option<Arc<QueueMetrics>>,
+    opportunities: OpportunityHistory,
+    client_lag: Option<Arc<ClientLag>>,
+    ws_clients: Option<Arc<WsClients>>,
+    previous: MetricsSnapshot,
+    previous_writes: u64,
+}
+
+impl MetricsSampler {
+    pub fn new(
+        cache: Arc<PriceCache>,
+        pipeline: Arc<PipelineMetrics>,
+        ws_status: watch::Receiver<ConnectionStatus>,
+        opportunities: OpportunityHistory,
+    ) -> Self {
+        Self {
+            previous: pipeline.snapshot(),
+            previous_writes: cache.write_count(),
+            cache,
+            pipeline,
+            ws_status,
+            queue: None,
+            opportunities,
+            client_lag: None,
+            ws_clients: None,
+        }
+    }
+
+    /// Report the feed event queue's occupancy
+    pub fn with_queue(mut self, queue: Arc<QueueMetrics>) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Report how far API stream clients fall behind
+    pub fn with_client_lag(mut self, client_lag: Arc<ClientLag>) -> Self {
+        self.client_lag = Some(client_lag);
+        self
+    }
+
+    /// Report open and reaped `/ws` connections
+    pub fn with_ws_clients(mut self, ws_clients: Arc<WsClients>) -> Self {
+        self.ws_clients = Some(ws_clients);
+        self
+    }
+
+    pub fn sample(&mut self) -> SystemMetrics {
+        let pipeline = self.pipeline.snapshot();
+        let rates = pipeline.rates_since(&self.previous);
+        let secs = pipeline.taken_at.duration_since(self.previous.taken_at).as_secs_f64();
+        let writes = self.cache.write_count();
+        let updates_per_sec = if secs > 0.0 {
+            writes.saturating_sub(self.previous_writes) as f64 / secs
+        } else {
+            0.0
+        };
+        let ws = self.ws_status.borrow().clone();
+        let metrics = SystemMetrics {
+            fps: rates.messages_per_sec.round() as u64,
+            cache_entries: self.cache.len(),
+            messages_per_sec: rates.messages_per_sec,
+            bytes_per_sec: rates.bytes_per_sec,
+            updates_per_sec,
+            processing_p50_us: pipeline.processing_p50_us,
+            processing_p99_us: pipeline.processing_p99_us,
+            scan_p99_us: pipeline.scan_p99_us,
+            channel_occupancy: self.queue.as_ref().map_or(0.0, |queue| queue.occupancy()),
+            receipt_latency: pipeline.receipt_latency_since(&self.previous),
+            ws_connected: ws.is_connected(),
+            active_subscriptions: ws.active_subscriptions,
+            opportunities: self.opportunities.totals(),
+            ..SystemMetrics::default()
+        };
+        let metrics = match &self.client_lag {
+            Some(lag) => SystemMetrics {
+                client_lag_events: lag.events.load(Ordering::Relaxed),
+                client_messages_dropped: lag.dropped.load(Ordering::Relaxed),
+                lagging_clients: lag.lagging_clients.load(Ordering::Relaxed),
+                ..metrics
+            },
+            None => metrics,
+        };
+        let metrics = match &self.ws_clients {
+            Some(clients) => SystemMetrics {
+                ws_clients: clients.active.load(Ordering::Relaxed),
+                ws_clients_reaped: clients.reaped.load(Ordering::Relaxed),
+                ..metrics
+            },
+            None => metrics,
+        };
+        self.previous = pipeline;
+        self.previous_writes = writes;
+        metrics
+    }
+
+    /// Sample every `interval`, keeping the latest in `latest` and
+    /// broadcasting it, until `shutdown` fires
+    pub fn spawn(
+        mut self,
+        interval: Duration,
+        latest: LatestMetrics,
+        tx: Topics,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick is immediate; skip it so rates cover a full interval
+            ticker.tick().await;
+            let run = async move {
+                loop {
+                    ticker.tick().await;
+                    let metrics = self.sample();
+                    *latest.write().unwrap() = Some(metrics.clone());
+                    // No subscribers is fine
+                    let _ = tx.send(ApiMessage::SystemMetrics(metrics));
+                }
+            };
+            shutdown.run_until_cancelled(run).await;
+        })
+    }
+}
+
+/// `page_size` of `GET /opportunities` when none is given
+const DEFAULT_OPPORTUNITY_LIMIT: usize = 100;
+
+/// Largest `page_size` served; bigger requests are capped
+const MAX_OPPORTUNITY_PAGE_SIZE: usize = 1000;
+
+/// Filters and page of `GET /opportunities`
+struct OpportunityQuery {
+    opportunity_type: Option<OpportunityType>,
+    pair: Option<String>,
+    /// Inclusive
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Exclusive, so consecutive windows don't overlap
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    /// Minimum net profit percentage
+    min_profit: Option<f64>,
+    offset: usize,
+    limit: usize,
+}
+
+impl OpportunityQuery {
+    /// The most recent opportunities, unfiltered
+    fn latest(limit: usize) -> Self {
+        Self { opportunity_type: None, pair: None, from: None, to: None, min_profit: None, offset: 0, limit }
+    }
+}
+
+/// `GET /opportunities` parameters as sent
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct OpportunityParams {
+    #[serde(rename = "type")]
+    opportunity_type: Option<String>,
+    pair: Option<String>,
+    /// Unix milliseconds or RFC 3339; `since` is the older name
+    #[serde(alias = "since")]
+    from: Option<String>,
+    /// Unix milliseconds or RFC 3339
+    to: Option<String>,
+    min_profit: Option<f64>,
+    /// 1-based
+    page: Option<usize>,
+    /// `limit` is the older name
+    #[serde(alias = "limit")]
+    page_size: Option<usize>,
+}
+
+/// A `from` / `to` bound: unix milliseconds or RFC 3339
+fn parse_time_bound(name: &str, value: &str) -> std::result::Result<chrono::DateTime<chrono::Utc>, String> {
+    match value.parse::<i64>() {
+        Ok(ms) => chrono::DateTime::from_timestamp_millis(ms).ok_or_else(|| format!("{} {} is out of range", name, ms)),
+        Err(_) => chrono::DateTime::parse_from_rfc3339(value)
+            .map(|at| at.with_timezone(&chrono::Utc))
+            .map_err(|_| format!("{} \"{}\" is neither unix milliseconds nor RFC 3339", name, value)),
+    }
+}
+
+impl OpportunityParams {
+    fn parse(self) -> std::result::Result<OpportunityQuery, String> {
+        let opportunity_type = self
+            .opportunity_type
+            .as_deref()
+            .map(str::parse::<OpportunityType>)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let from = self.from.as_deref().map(|from| parse_time_bound("from", from)).transpose()?;
+        let to = self.to.as_deref().map(|to| parse_time_bound("to", to)).transpose()?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(format!("from {} is after to {}", from.to_rfc3339(), to.to_rfc3339()));
+            }
+        }
+        if self.min_profit.is_some_and(|min| !min.is_finite()) {
+            return Err("min_profit must be a number".to_string());
+        }
+        let page = self.page.unwrap_or(1);
+        if page == 0 {
+            return Err("page starts at 1".to_string());
+        }
+        let page_size = self.page_size.unwrap_or(DEFAULT_OPPORTUNITY_LIMIT).min(MAX_OPPORTUNITY_PAGE_SIZE);
+        if page_size == 0 {
+            return Err("page_size must be at least 1".to_string());
+        }
+        Ok(OpportunityQuery {
+            opportunity_type,
+            pair: self.pair,
+            from,
+            to,
+            min_profit: self.min_profit,
+            offset: (page - 1).saturating_mul(page_size),
+            limit: page_size,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct OpportunitiesResponse {
+    /// Opportunities on this page
+    count: usize,
+    /// Opportunities matching the filters across all pages
+    total: usize,
+    page: usize,
+    page_size: usize,
+    /// Newest first
+    opportunities: Vec<Opportunity>,
+}
+
+/// `GET /opportunities/stats` parameters
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StatsParams {
+    /// "30s", "5m", "1h", "7d"; one hour when absent
+    window: Option<String>,
+}
+
+/// Opportunities shown in `top_by_usd`
+const TOP_OPPORTUNITIES: usize = 5;
+
+/// Aggregates over the opportunities detected in a recent window
+#[derive(Debug, Serialize)]
+struct OpportunityStats {
+    window_seconds: u64,
+    total: usize,
+    by_type: BTreeMap<String, u64>,
+    by_pair: BTreeMap<String, u64>,
+    /// `None` when the window is empty
+    net_profit_percent: Option<ProfitStats>,
+    mean_confidence: Option<f64>,
+    /// Slots spreads persisted before being emitted
+    mean_persisted_slots: Option<f64>,
+    /// Highest estimated USD profit first; opportunities without an estimate
+    /// are left out
+    top_by_usd: Vec<UsdOpportunity>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ProfitStats {
+    mean: f64,
+    median: f64,
+    max: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct UsdOpportunity {
+    usd_profit: f64,
+    #[serde(flatten)]
+    opportunity: Opportunity,
+}
+
+/// A duration like "90s", "5m", "1h" or "7d"
+fn parse_window(window: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("window \"{}\" must be a number followed by s, m, h or d", window);
+    let Some((unit_at, _)) = window.char_indices().last() else {
+        return Err(invalid());
+    };
+    let (count, unit) = window.split_at(unit_at);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        _ => return Err(invalid()),
+    };
+    if count == 0 {
+        return Err(format!("window \"{}\" must be longer than zero", window));
+    }
+    count.checked_mul(seconds).map(Duration::from_secs).ok_or_else(invalid)
+}
+
+impl OpportunityStats {
+    fn new(window: Duration, opportunities: Vec<Opportunity>, usd: &UsdPricer) -> Self {
+        let total = opportunities.len();
+        let mean = |values: &mut dyn Iterator<Item = f64>| (total > 0).then(|| values.sum::<f64>() / total as f64);
+        let mut by_type = BTreeMap::new();
+        let mut by_pair = BTreeMap::new();
+        for opportunity in &opportunities {
+            *by_type.entry(opportunity.opportunity_type.to_string()).or_insert(0) += 1;
+            *by_pair.entry(opportunity.token_pair.clone()).or_insert(0) += 1;
+        }
+        let mut profits: Vec<f64> = opportunities.iter().map(|o| o.net_profit_percent).collect();
+        profits.sort_by(f64::total_cmp);
+        let net_profit_percent = (total > 0).then(|| ProfitStats {
+            mean: profits.iter().sum::<f64>() / total as f64,
+            median: if total % 2 == 1 {
+                profits[total / 2]
+            } else {
+                (profits[total / 2 - 1] + profits[total / 2]) / 2.0
+            },
+            max: profits[total - 1],
+        });
+        let mean_confidence = mean(&mut opportunities.iter().map(|o| o.confidence));
+        let mean_persisted_slots = mean(&mut opportunities.iter().map(|o| o.persisted_slots as f64));
+        // Equal estimates keep the newest first
+        let mut top = TopK::new(TOP_OPPORTUNITIES);
+        for opportunity in opportunities {
+            if let Some(usd_profit) = usd.profit(&opportunity) {
+                top.push(usd_profit, opportunity);
+            }
+        }
+        let top_by_usd = top
+            .into_scored()
+            .into_iter()
+            .map(|(usd_profit, opportunity)| UsdOpportunity { usd_profit, opportunity })
+            .collect();
+        Self {
+            window_seconds: window.as_secs(),
+            total,
+            by_type,
+            by_pair,
+            net_profit_percent,
+            mean_confidence,
+            mean_persisted_slots,
+            top_by_usd,
+        }
+    }
+}
+
+/// One DEX's cached price for a pair
+#[derive(Debug, Clone, Serialize, PartialEq, JsonSchema)]
+pub struct PriceEntry {
+    pub price: f64,
+    pub slot: u64,
+    pub age_ms: u64,
+    pub liquidity: u64,
+    pub fee_rate: f64,
+    /// Older than the pair's staleness threshold on this DEX
+    pub stale: bool,
+    pub confirmed: bool,
+    /// Pool account the price was decoded from
+    pub pool_pubkey: Option<String>,
+    pub source: PriceSource,
+    /// Local time the update was received
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub received_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// DEX -> price of one pair, sorted by DEX
+pub type PairPrices = BTreeMap<String, PriceEntry>;
+
+/// Current state for a client that just connected, so it has something to
+/// show before the next update for each pair
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Snapshot {
+    /// Pair -> DEX -> price, as served by `GET /prices`
+    pub prices: BTreeMap<String, PairPrices>,
+    /// Most recent opportunities, newest first (there is no open/closed
+    /// tracking, so this is the `GET /opportunities` default page)
+    pub opportunities: Vec<Opportunity>,
+}
+
+/// Recent swaps per pool
+#[derive(Serialize)]
+struct ActivityResponse {
+    /// False unless `websocket.swap_activity` is on and subscriptions went out
+    tracking: bool,
+    window_seconds: u64,
+    pools: Vec<PoolActivity>,
+}
+
+/// Bind and spawn the API server
+///
+/// Returns `None` when the API is disabled. Bind failures (port in use,
+/// bad address) are returned to the caller instead of panicking in the
+/// spawned task. The task finishes once `app_state.shutdown` fires and
+/// in-flight requests have drained, or after `DRAIN_TIMEOUT`.
+pub async fn start_server(config: &ApiConfig, app_state: AppState) -> Result<Option<JoinHandle<()>>> {
+    if !config.enabled {
+        info!("API server disabled");
+        return Ok(None);
+    }
+
+    let listeners = bind_all(&config.listen_addrs()?, config.unix_socket_mode).await?;
+    if config.auth_token.is_none() {
+        warn!("api.auth_token is not set, admin routes will answer 403");
+    }
+    let shutdown = app_state.shutdown.clone();
+    let app = router(config, app_state)?;
+    Ok(Some(tokio::spawn(serve_all(listeners, app, shutdown, DRAIN_TIMEOUT))))
+}
+
+/// A bound API listener
+enum Listener {
+    Tcp(tokio::net::TcpListener),
+    Unix(tokio::net::UnixListener, SocketFile),
+}
+
+/// Removes a unix socket file when dropped, i.e. when its server stops
+#[derive(Debug)]
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!(path = %self.0.display(), error = %e, "Failed to remove API socket file");
+        }
+    }
+}
+
+/// Bind every address before serving any, so a bad entry fails startup;
+/// sockets bound before the failure are cleaned up again
+async fn bind_all(addrs: &[ListenAddr], socket_mode: u32) -> Result<Vec<Listener>> {
+    let mut listeners = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        let listener = match addr {
+            ListenAddr::Tcp(addr) => {
+                let listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("Failed to bind API server to {}", addr))?;
+                let local_addr = listener.local_addr()?;
+                info!("API Server listening on http://{} (streams at ws://{}/ws)", local_addr, local_addr);
+                Listener::Tcp(listener)
+            }
+            ListenAddr::Unix(path) => {
+                let (listener, file) = bind_unix(path, socket_mode)
+                    .with_context(|| format!("Failed to bind API server to {}", addr))?;
+                info!("API Server listening on {}", addr);
+                Listener::Unix(listener, file)
+            }
+        };
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+/// Bind `path` with `mode` applied before anyone else can connect
+///
+/// The socket is bound inside an owner-only staging directory next to
+/// `path`, given its mode there and only then renamed into place, so it is
+/// never reachable under the looser permissions the umask gives it.
+fn bind_unix(path: &std::path::Path, mode: u32) -> Result<(tokio::net::UnixListener, SocketFile)> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    // Left behind by a crash; anything that is not a socket is not ours to delete
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let name = path.file_name().with_context(|| format!("{} is not a socket file path", path.display()))?;
+    let mut staging_name = std::ffi::OsString::from(".");
+    staging_name.push(name);
+    staging_name.push(format!(".{}", std::process::id()));
+    let staging = path.with_file_name(staging_name);
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let staged = staging.join(name);
+    let bound = tokio::net::UnixListener::bind(&staged).map_err(anyhow::Error::from).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    if bound.is_err() {
+        let _ = std::fs::remove_file(&staged);
+    }
+    let _ = std::fs::remove_dir(&staging);
+    Ok((bound?, SocketFile(path.to_path_buf())))
+}
+
+/// Serve `app` on every listener until `shutdown`
+async fn serve_all(listeners: Vec<Listener>, app: Router, shutdown: CancellationToken, drain: Duration) {
+    let servers = listeners
+        .into_iter()
+        .map(|listener| serve_until_shutdown(listener, app.clone(), shutdown.clone(), drain));
+    futures::future::join_all(servers).await;
+}
+
+/// How long shutdown waits for in-flight requests before giving up on them
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve `app` until `shutdown`, then drain for at most `drain`
+async fn serve_until_shutdown(listener: Listener, app: Router, shutdown: CancellationToken, drain: Duration) {
+    let server = async {
+        match listener {
+            Listener::Tcp(listener) => {
+                // Peer addresses key the per-client rate limits
+                let app = app.into_make_service_with_connect_info::<SocketAddr>();
+                axum::serve(listener, app).with_graceful_shutdown(shutdown.clone().cancelled_owned()).await
+            }
+            Listener::Unix(listener, _file) => {
+                serve_unix(listener, app, shutdown.clone()).await;
+                Ok(())
+            }
+        }
+    };
+    let drain_expired = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(drain).await;
+    };
+    tokio::select! {
+        result = server => match result {
+            Ok(()) => info!("API server stopped"),
+            Err(e) => error!(error = ?e, "API server stopped"),
+        },
+        _ = drain_expired => warn!(drain_ms = drain.as_millis() as u64, "API requests still running after drain timeout, stopping anyway"),
+    }
+}
+
+fn router(config: &ApiConfig, app_state: AppState) -> Result<Router> {
+    let cors = if config.cors_origins.is_empty() {
+        CorsLayer::permissive()
+    } else {
+        let origins = config
+            .cors_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .with_context(|| format!("Invalid api.cors_origins entry \"{}\"", origin))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        CorsLayer::permissive().allow_origin(AllowOrigin::list(origins))
+    };
+
+    // With `api.auth_token` set, JSON and stream routes need it; checked
+    // under the rate limit like the admin routes' own gate
+    let token = config.auth_token.clone().map(Arc::new);
+    let require_configured_token = |routes: Router<AppState>| match &token {
+        Some(token) => routes.route_layer(middleware::from_fn_with_state(token.clone(), require_token)),
+        None => routes,
+    };
+
+    let rest = Router::new()
+        .route("/subscriptions", get(subscriptions_handler))
+        .route("/activity", get(activity_handler))
+        .route("/prices", get(prices_handler))
+        .route("/prices/:pair", get(pair_prices_handler))
+        .route("/spreads", get(spreads_handler))
+        .route("/opportunities", get(opportunities_handler))
+        .route("/opportunities/stats", get(opportunity_stats_handler))
+        .route("/calibration", get(calibration_handler))
+        .route("/triangular/paths", get(triangular_paths_handler))
+        .route("/regimes", get(regimes_handler))
+        .route("/pairs", get(pairs_handler))
+        .route("/pairs/:pair/summary", get(pair_summary_handler))
+        .route("/metrics/system", get(system_metrics_handler))
+        .route("/version", get(version_handler))
+        .route("/schema", get(schema_handler));
+    let rest = require_configured_token(rest)
+        .route_layer(middleware::from_fn_with_state(app_state.limits.rest.clone(), rate_limit));
+    // Only JSON routes: stream upgrades and SSE must not be buffered by an encoder
+    let rest = if config.compression {
+        let predicate = DefaultPredicate::new().and(SizeAbove::new(config.compression_min_bytes));
+        rest.layer(CompressionLayer::new().gzip(true).br(true).compress_when(predicate))
+    } else {
+        rest
+    };
+    // Checked under the rate limit, so guessing tokens is throttled too
+    let admin = Router::new()
+        .route("/admin/pause", post(pause_handler))
+        .route("/admin/resume", post(resume_handler))
+        .route("/admin/tap", post(tap_handler))
+        .route("/admin/pools", post(add_pool_handler))
+        .route("/admin/pools/:pubkey", delete(remove_pool_handler))
+        .route("/admin/cache/cleanup", post(cache_cleanup_handler))
+        .route("/admin/detectors/reset", post(detector_reset_handler))
+        .route("/admin/log-level", put(log_level_handler))
+        .route("/config", get(config_handler))
+        .route_layer(middleware::from_fn_with_state(config.auth_token.clone().map(Arc::new), require_admin_token))
+        .route_layer(middleware::from_fn_with_state(app_state.limits.admin.clone(), rate_limit));
+    // Stream upgrades share the JSON budget; open streams are capped by
+    // connection count in their handlers
+    let streams = Router::new().route("/ws", get(ws_handler)).route("/events", get(events_handler));
+    let streams = require_configured_token(streams)
+        .route_layer(middleware::from_fn_with_state(app_state.limits.rest.clone(), rate_limit));
+    // Health is never limited and stays open to liveness probes, like the
+    // dashboard's static assets
+    let app = Router::new()
+        .route("/health", get(health_handler))
+        .merge(streams)
+        .merge(rest)
+        .merge(admin)
+        .fallback(dashboard_handler)
+        .with_state(app_state);
+
+    Ok(app.layer(cors))
+}
+
+/// `axum::serve` for a unix socket: accept until `shutdown`, then wait for
+/// open connections to finish
+///
+/// Unix peers have no address, so they share the rate-limit bucket of
+/// unknown clients.
+async fn serve_unix(listener: tokio::net::UnixListener, app: Router, shutdown: CancellationToken) {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    loop {
+        let stream = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!(error = %e, "API socket accept failed");
+                    continue;
+                }
+            },
+        };
+        let service = hyper_util::service::TowerToHyperService::new(app.clone());
+        // Upgrades carry the /ws connections
+        let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service).into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!(error = %e, "API socket connection ended with an error");
+            }
+        });
+    }
+    graceful.shutdown().await;
+}
+
+/// Files compiled into the binary with the `dashboard` feature: name,
+/// content type, body
+#[cfg(feature = "dashboard")]
+const DASHBOARD_ASSETS: &[(&str, &str, &[u8])] = &[(
+    "index.html",
+    "text/html; charset=utf-8",
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/dashboard/index.html")),
+)];
+
+#[cfg(not(feature = "dashboard"))]
+const DASHBOARD_ASSETS: &[(&str, &str, &[u8])] = &[];
+
+/// `GET /` and anything else no route matched: the embedded dashboard when
+/// built in, otherwise a JSON 404
+async fn dashboard_handler(uri: axum::http::Uri) -> Response {
+    let path = match uri.path().trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+    match DASHBOARD_ASSETS.iter().find(|(name, ..)| *name == path) {
+        Some(&(name, content_type, body)) => {
+            // The page is revalidated so an upgraded binary's dashboard shows up
+            // at once; other assets change only with the binary
+            let cache = if name == "index.html" { "no-cache" } else { "public, max-age=3600" };
+            ([(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, cache)], body).into_response()
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "not_found", "path": uri.path() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Peer IP of a request; unspecified when served without connect info
+/// (Unix sockets, tests)
+fn peer_ip(connect_info: Option<&ConnectInfo<SocketAddr>>) -> IpAddr {
+    connect_info.map_or(IpAddr::from([0, 0, 0, 0]), |info| info.0.ip())
+}
+
+/// Answer 429 once the peer has used up its requests
+async fn rate_limit(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    let client = peer_ip(request.extensions().get::<ConnectInfo<SocketAddr>>());
+    match limiter.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            debug!(client = %client, path = request.uri().path(), "Rate limited");
+            let retry_after = retry_after.as_secs_f64().ceil().max(1.0).to_string();
+            (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after)], "Rate limit exceeded").into_response()
+        }
+    }
+}
+
+/// Refuse a streaming client beyond `api.rate_limit.max_streams` or
+/// `max_streams_per_client`
+fn too_many_streams(client: IpAddr) -> Response {
+    warn!(client = %client, "Streaming client refused, api.rate_limit stream cap reached");
+    (StatusCode::TOO_MANY_REQUESTS, "Too many streaming clients").into_response()
+}
+
+/// Reject requests without the configured token
+///
+/// Browsers can't set headers on WebSocket upgrades, so `?token=` is
+/// accepted as well as a bearer header.
+async fn require_token(
+    State(token): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if has_token(&request, &token) {
+        next.run(request).await
+    } else {
+        StatusCode::UNAUTHORIZED.into_response()
+    }
+}
+
+/// Gate of the admin routes: they change or expose the running setup, so
+/// without `api.auth_token` they answer 403 instead of being open
+async fn require_admin_token(
+    State(token): State<Option<Arc<String>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match token {
+        Some(token) if has_token(&request, &token) => next.run(request).await,
+        Some(_) => StatusCode::UNAUTHORIZED.into_response(),
+        None => (StatusCode::FORBIDDEN, "Admin routes are disabled until api.auth_token is set").into_response(),
+    }
+}
+
+/// Whether the request carries `token` as a bearer header or a
+/// percent-encoded `?token=`
+fn has_token(request: &Request, token: &str) -> bool {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let query = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("token=")))
+        .and_then(|v| percent_encoding::percent_decode_str(v).decode_utf8().ok());
+    bearer.is_some_and(|bearer| constant_time_eq(bearer.as_bytes(), token.as_bytes()))
+        || query.is_some_and(|query| constant_time_eq(query.as_bytes(), token.as_bytes()))
+}
+
+/// Byte comparison that doesn't stop at the first difference, so response
+/// times don't tell how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// `GET /ws` options, e.g. `/ws?format=msgpack`
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct WsParams {
+    format: WireFormat,
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<WsParams>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Response {
+    let client = peer_ip(connect_info.as_ref());
+    let Some(permit) = state.limits.stream_permit(client) else {
+        return too_many_streams(client);
+    };
+    ws.on_upgrade(move |socket| async move {
+        handle_socket(socket, state, params.format).await;
+        drop(permit);
+    })
+}
+
+/// Server-sent events over the broadcast channel, for clients that can't
+/// hold a WebSocket
+///
+/// Each message is an event named by its `type` tag with the same JSON as a
+/// WebSocket frame. A client that falls a whole broadcast buffer behind is
+/// disconnected rather than buffered for; it can reconnect.
+async fn events_handler(
+    State(state): State<AppState>,
+    Query(params): Query<EventParams>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Response {
+    let client = peer_ip(connect_info.as_ref());
+    let Some(permit) = state.limits.stream_permit(client) else {
+        return too_many_streams(client);
+    };
+    Sse::new(event_stream(&state, ClientFilter::from(params), permit))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// One SSE client's side of the broadcast
+struct SseClient {
+    rx: broadcast::Receiver<ApiMessage>,
+    filter: ClientFilter,
+    framer: Framer,
+    shutdown: CancellationToken,
+    client_lag: Arc<ClientLag>,
+    /// Released when the stream ends
+    _permit: StreamPermit,
+}
+
+/// Filtered broadcast messages as SSE events, holding the client's stream
+/// slot until it ends
+///
+/// Enveloped events carry their `seq` as the event id.
+fn event_stream(
+    state: &AppState,
+    filter: ClientFilter,
+    permit: StreamPermit,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    debug!(filter = ?filter, "New SSE client connected");
+    let client = SseClient {
+        rx: filter.subscribe(&state.tx),
+        filter,
+        framer: Framer::new(state.legacy_messages, state.timestamp_format),
+        shutdown: state.shutdown.clone(),
+        client_lag: state.client_lag.clone(),
+        _permit: permit,
+    };
+    futures::stream::unfold(client, |mut client| async move {
+        loop {
+            let message = tokio::select! {
+                message = client.rx.recv() => message,
+                _ = client.shutdown.cancelled() => return None,
+            };
+            match message {
+                Ok(message) if client.filter.matches(&message) => match client.framer.message(&message) {
+                    Ok(json) => {
+                        let mut event = Event::default().event(message.kind()).data(json);
+                        if !client.framer.legacy {
+                            event = event.id(client.framer.seq.to_string());
+                        }
+                        return Some((Ok(event), client));
+                    }
+                    Err(e) => error!(error = %e, "Failed to serialize SSE event"),
+                },
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    client.client_lag.lagged(skipped, true);
+                    client.client_lag.lagged_client_left();
+                    warn!(skipped, "SSE client fell behind the broadcast, disconnecting it");
+                    return None;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// Crate and message protocol versions
+async fn version_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": PROTOCOL_VERSION,
+    }))
+}
+
+/// JSON Schemas of the API-facing models, as OpenAPI components
+async fn schema_handler() -> Json<serde_json::Value> {
+    Json(api_schemas())
+}
+
+async fn health_handler(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let websocket = state.ws_status.borrow().clone();
+    let status = state.health.status(state.cache.len(), websocket.is_connected());
+    let health = HealthResponse {
+        healthy: status.healthy,
+        consecutive_failures: websocket.consecutive_failures(),
+        connection_uptime_secs: websocket.uptime_secs(),
+        uptime_seconds: status.uptime_seconds,
+        last_update_ms: (status.last_update_ms != u64::MAX).then_some(status.last_update_ms),
+        dex_last_update_ms: status.dex_last_update_ms,
+        stale_dexes: status.stale_dexes,
+        websocket,
+        paused: state.pause.status().paused,
+        rejected: state.limits.rejections(),
+    };
+    let code = if health.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(health))
+}
+
+async fn subscriptions_handler(State(state): State<AppState>) -> Json<SubscriptionsResponse> {
+    let websocket = state.ws_status.borrow().clone();
+    let subscriptions = state.subscriptions.report();
+    let confirmed = subscriptions.iter().filter(|s| s.confirmed).count();
+    Json(SubscriptionsResponse {
+        endpoint: websocket.endpoint.clone(),
+        connected: websocket.is_connected(),
+        total: subscriptions.len(),
+        confirmed,
+        pending: subscriptions.len() - confirmed,
+        subscriptions,
+        out_of_order_drops: state.subscriptions.out_of_order_drops(),
+    })
+}
+
+async fn activity_handler(State(state): State<AppState>) -> Json<ActivityResponse> {
+    Json(ActivityResponse {
+        tracking: state.activity.is_tracking(),
+        window_seconds: ACTIVITY_WINDOW.as_secs(),
+        pools: state.activity.report(),
+    })
+}
+
+/// Cached prices of one pair
+fn pair_prices(cache: &PriceCache, pair: &str) -> PairPrices {
+    let now = chrono::Utc::now();
+    cache
+        .get_all_dexes(pair)
+        .into_iter()
+        .map(|(dex, data)| {
+            let entry = PriceEntry {
+                price: data.price,
+                slot: data.slot,
+                age_ms: (now - data.timestamp).num_milliseconds().max(0) as u64,
+                liquidity: data.liquidity,
+                fee_rate: data.fee_rate,
+                stale: cache.is_stale(pair, &dex, &data),
+                confirmed: data.confirmed,
+                pool_pubkey: data.pool_pubkey,
+                source: data.source,
+                received_at: data.received_at,
+            };
+            (dex.to_string(), entry)
+        })
+        .collect()
+}
+
+/// Every cached pair, sorted by pair then DEX
+fn all_prices(cache: &PriceCache) -> BTreeMap<String, PairPrices> {
+    cache
+        .get_all_pairs()
+        .into_iter()
+        .map(|pair| {
+            let prices = pair_prices(cache, &pair);
+            (pair, prices)
+        })
+        .filter(|(_, prices)| !prices.is_empty())
+        .collect()
+}
+
+async fn prices_handler(State(state): State<AppState>) -> Json<BTreeMap<String, PairPrices>> {
+    Json(all_prices(&state.cache))
+}
+
+async fn pair_prices_handler(State(state): State<AppState>, UrlPath(pair): UrlPath<String>) -> Response {
+    let prices = pair_prices(&state.cache, &pair);
+    if prices.is_empty() {
+        return (StatusCode::NOT_FOUND, format!("No cached prices for pair \"{}\"", pair)).into_response();
+    }
+    Json(prices).into_response()
+}
+
+/// Best opportunities, spread and venue freshness of one pair; the pair
+/// is matched case-insensitively, like stream filters
+async fn pair_summary_handler(State(state): State<AppState>, UrlPath(pair): UrlPath<String>) -> Response {
+    let pair = pair.to_ascii_uppercase();
+    match state.pair_summaries.summary(&pair) {
+        Some(set) => Json(set).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No prices or opportunities for pair \"{}\"", pair)).into_response(),
+    }
+}
+
+/// Pair -> spread, for pairs quoted fresh on at least two DEXes
+async fn spreads_handler(State(state): State<AppState>) -> Json<BTreeMap<String, Spread>> {
+    let spreads = state
+        .cache
+        .get_all_pairs()
+        .into_iter()
+        .filter_map(|pair| Some((pair.clone(), pair_spread(&state.cache, &pair)?)))
+        .collect();
+    Json(spreads)
+}
+
+async fn opportunities_handler(
+    State(state): State<AppState>,
+    Query(params): Query<OpportunityParams>,
+) -> Response {
+    let query = match params.parse() {
+        Ok(query) => query,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    let (total, opportunities) = state.opportunities.query(&query);
+    Json(OpportunitiesResponse {
+        count: opportunities.len(),
+        total,
+        page: query.offset / query.limit + 1,
+        page_size: query.limit,
+        opportunities,
+    })
+    .into_response()
+}
+
+/// Aggregates over the history kept for `GET /opportunities` within `window`
+async fn opportunity_stats_handler(State(state): State<AppState>, Query(params): Query<StatsParams>) -> Response {
+    let window = match parse_window(params.window.as_deref().unwrap_or("1h")) {
+        Ok(window) => window,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    let from = chrono::Duration::from_std(window).ok().and_then(|window| chrono::Utc::now().checked_sub_signed(window));
+    let query = OpportunityQuery { from, ..OpportunityQuery::latest(usize::MAX) };
+    let (_, opportunities) = state.opportunities.query(&query);
+    Json(OpportunityStats::new(window, opportunities, &state.usd)).into_response()
+}
+
+async fn calibration_handler(State(state): State<AppState>) -> Json<CalibrationTable> {
+    Json(state.calibrator.read().await.table().clone())
+}
+
+async fn triangular_paths_handler(State(state): State<AppState>) -> Json<TriangularPathsResponse> {
+    let paths = state.triangular_paths.read().await;
+    Json(TriangularPathsResponse {
+        metrics: paths.metrics(),
+        paths: paths.entries().to_vec(),
+    })
+}
+
+async fn config_handler(State(state): State<AppState>) -> Json<ConfigResponse> {
+    let settings = state.settings.borrow().redacted();
+    let pools = state.pools.read().await;
+    Json(ConfigResponse {
+        detectors: settings.detectors.enabled(),
+        monitored_pools: pools.active.len(),
+        skipped_pools: pools.skipped.len(),
+        triangular_paths: state.triangular_paths.read().await.metrics(),
+        settings,
+    })
+}
+
+async fn regimes_handler(State(state): State<AppState>) -> Json<HashMap<String, PairRegime>> {
+    Json(state.volatility.snapshot())
+}
+
+/// Latest periodic metrics sample, the same data as the `metrics` message
+async fn system_metrics_handler(State(state): State<AppState>) -> Response {
+    match state.system_metrics.read().unwrap().clone() {
+        Some(metrics) => Json(metrics).into_response(),
+        None => (StatusCode::SERVICE_UNAVAILABLE, "No metrics sampled yet").into_response(),
+    }
+}
+
+/// Subscribed pools and those skipped by filters or `max_pools`
+/// Monitored pools per pair with their feed and detection status, plus the
+/// pools left out of the subscription set
+async fn pairs_handler(State(state): State<AppState>) -> Json<PairsResponse> {
+    let selection = state.pools.read().await.clone();
+    let report = state.subscriptions.report();
+    let rejections = state.subscriptions.rejections();
+    let now = chrono::Utc::now();
+
+    let mut pairs: BTreeMap<String, Vec<PoolStatus>> = BTreeMap::new();
+    for slot in selection.active {
+        let (subscription, subscription_error) =
+            subscription_status(&report, &rejections, state.pool_control.subscribes(&slot.dex), &slot);
+        let price = state.cache.get(&slot.pair, &slot.dex);
+        let contributing = price.as_ref().is_some_and(|data| {
+            !state.cache.is_stale(&slot.pair, &slot.dex, data) && is_tradeable(data)
+        });
+        let status = PoolStatus {
+            dex: slot.dex.clone(),
+            discovered: state.discovered_pools.contains(&slot.pubkey),
+            pubkey: slot.pubkey,
+            decoder: decoder_name(&slot.dex),
+            priority: slot.priority,
+            subscription,
+            subscription_error,
+            ms_since_update: price.map(|data| (now - data.timestamp).num_milliseconds().max(0) as u64),
+            contributing,
+        };
+        pairs.entry(slot.pair).or_default().push(status);
+    }
+    let skipped = selection
+        .skipped
+        .into_iter()
+        .map(|pool| SkippedPoolStatus { discovered: state.discovered_pools.contains(&pool.pool.pubkey), pool })
+        .collect();
+    Json(PairsResponse { pairs, skipped })
+}
+
+/// Whether the node confirmed, refused or has yet to answer the subscription
+/// feeding `slot`: its own account subscription, or its DEX's program
+/// subscription
+fn subscription_status(
+    report: &[SubscriptionInfo],
+    rejections: &BTreeMap<String, String>,
+    account_mode: bool,
+    slot: &PoolSlot,
+) -> (SubscriptionStatus, Option<String>) {
+    let entry = report.iter().find(|info| match info.kind {
+        SubscriptionKind::Account => account_mode && info.pubkey == slot.pubkey,
+        SubscriptionKind::Program => !account_mode && info.dex.as_ref() == Some(&slot.dex),
+        SubscriptionKind::Logs => false,
+    });
+    if let Some(entry) = entry {
+        let status = if entry.confirmed { SubscriptionStatus::Confirmed } else { SubscriptionStatus::Pending };
+        return (status, None);
+    }
+    let key = if account_mode { Some(slot.pubkey.as_str()) } else { crate::decoder::program_id(&slot.dex) };
+    match key.and_then(|key| rejections.get(key)) {
+        Some(error) => (SubscriptionStatus::Failed, Some(error.clone())),
+        None => (SubscriptionStatus::None, None),
+    }
+}
+
+/// A price detection can act on: positive, with reserves on both sides
+fn is_tradeable(data: &PriceData) -> bool {
+    data.price.is_finite() && data.price > 0.0 && data.vault_a_balance > 0 && data.vault_b_balance > 0
+}
+
+/// Account layout a DEX's pools are decoded with
+fn decoder_name(dex: &Dex) -> &'static str {
+    match dex {
+        Dex::Raydium => "raydium_amm_v4",
+        Dex::Orca => "orca_whirlpool",
+        Dex::Meteora => "meteora_dlmm",
+        _ => "unknown",
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PauseRequest {
+    reason: Option<String>,
+}
+
+/// Manually pause detection (the cache keeps updating)
+async fn pause_handler(
+    State(state): State<AppState>,
+    body: Option<Json<PauseRequest>>,
+) -> Json<PauseStatus> {
+    let reason = body
+        .and_then(|Json(request)| request.reason)
+        .unwrap_or_else(|| "manual pause".to_string());
+    Json(state.pause.pause(reason))
+}
+
+/// Lift a manual pause (scheduled windows still apply)
+async fn resume_handler(State(state): State<AppState>) -> Json<PauseStatus> {
+    Json(state.pause.resume())
+}
+
+#[derive(Serialize)]
+struct CacheCleanupResponse {
+    /// Expired entries evicted
+    removed: usize,
+    /// Entries left in the cache
+    remaining: usize,
+}
+
+/// Evict expired cache entries now instead of at the next cleanup tick
+async fn cache_cleanup_handler(State(state): State<AppState>) -> Json<CacheCleanupResponse> {
+    let removed = state.cache.cleanup_stale_entries();
+    Json(CacheCleanupResponse { removed, remaining: state.cache.len() })
+}
+
+#[derive(Serialize)]
+struct DetectorResetResponse {
+    /// Entries cleared per component: candidates awaiting confirmation,
+    /// pair statistics, throttles and volatility history
+    cleared: BTreeMap<&'static str, usize>,
+}
+
+/// Drop the state detectors accumulated, as after a restart: confirmation
+/// candidates, statistical pair history, alert and spread throttles and the
+/// volatility tracker's price windows
+async fn detector_reset_handler(State(state): State<AppState>) -> Json<DetectorResetResponse> {
+    let detectors = &state.detectors;
+    let mut cleared = BTreeMap::new();
+    if let Some(detector) = &detectors.spatial {
+        cleared.insert("spatial", detector.read().await.reset());
+    }
+    if let Some(detector) = &detectors.statistical {
+        cleared.insert("statistical", detector.write().await.reset());
+    }
+    if let Some(detector) = &detectors.triangular {
+        cleared.insert("triangular", detector.read().await.reset());
+    }
+    if let Some(detector) = &detectors.spread_alerts {
+        cleared.insert("spread_alerts", detector.reset());
+    }
+    if let Some(tracker) = &detectors.spreads {
+        cleared.insert("spreads", tracker.reset());
+    }
+    cleared.insert("volatility", state.volatility.reset());
+    info!(cleared = ?cleared, "Detector state reset via admin API");
+    Json(DetectorResetResponse { cleared })
+}
+
+#[derive(Deserialize)]
+struct LogLevelRequest {
+    /// `RUST_LOG` syntax, like `info,solana_price_monitor=trace`
+    filter: String,
+}
+
+#[derive(Serialize)]
+struct LogLevelResponse {
+    previous: String,
+    filter: String,
+}
+
+/// Swap the log filter without restarting
+async fn log_level_handler(State(state): State<AppState>, Json(request): Json<LogLevelRequest>) -> Response {
+    let Some(handle) = &state.log_filter else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "The log filter can't be changed at runtime").into_response();
+    };
+    let filter = match EnvFilter::try_new(&request.filter) {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid filter \"{}\": {}", request.filter, e)).into_response(),
+    };
+    let previous = handle.with_current(|current| current.to_string()).unwrap_or_default();
+    let applied = filter.to_string();
+    if let Err(e) = handle.reload(filter) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to swap the log filter: {}", e)).into_response();
+    }
+    info!(previous = previous, filter = applied, "Log filter changed via admin API");
+    Json(LogLevelResponse { previous, filter: applied }).into_response()
+}
+
+/// Longest tap `POST /admin/tap` accepts
+const MAX_TAP_SECS: u64 = 600;
+
+#[derive(Deserialize)]
+struct TapRequest {
+    #[serde(flatten)]
+    filter: TapFilter,
+    #[serde(default = "default_tap_secs")]
+    seconds: u64,
+}
+
+fn default_tap_secs() -> u64 {
+    60
+}
+
+/// Where `POST /admin/tap` writes, and how much disk its files may take
+#[derive(Debug)]
+pub struct TapStorage {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Size of the tap files in `dir`: rescanned when a tap starts, then
+    /// grown by every frame written
+    used: AtomicU64,
+}
+
+impl TapStorage {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self { dir: dir.into(), max_bytes, used: AtomicU64::new(0) }
+    }
+
+    /// Recount the tap files already on disk, including those of running taps
+    async fn rescan(&self) -> u64 {
+        let mut used = 0;
+        if let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if name.starts_with("tap-") && name.ends_with(".jsonl") {
+                    used += entry.metadata().await.map_or(0, |m| m.len());
+                }
+            }
+        }
+        self.used.store(used, Ordering::Relaxed);
+        used
+    }
+
+    /// Claim room for `bytes` more; `false` once the budget is spent
+    fn reserve(&self, bytes: u64) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.max_bytes)
+            })
+            .is_ok()
+    }
+}
+
+#[derive(Serialize)]
+struct TapResponse {
+    path: String,
+    filter: TapFilter,
+    seconds: u64,
+}
+
+/// DEXes with a pool decoder
+const POOL_DEXES: [Dex; 3] = [Dex::Raydium, Dex::Orca, Dex::Meteora];
+
+#[derive(Deserialize)]
+struct AddPoolRequest {
+    pair: String,
+    dex: Dex,
+    pubkey: String,
+}
+
+impl AddPoolRequest {
+    /// Check the fields
+    fn validate(self) -> Result<Self> {
+        let valid_pair = self
+            .pair
+            .split_once(['-', '_'])
+            .is_some_and(|(a, b)| !a.is_empty() && !b.is_empty() && !b.contains(['-', '_']));
+        if !valid_pair {
+            anyhow::bail!("pair \"{}\" must be two tokens, like SOL-USDC", self.pair);
+        }
+        if !POOL_DEXES.contains(&self.dex) {
+            let names: Vec<&str> = POOL_DEXES.iter().map(Dex::as_str).collect();
+            anyhow::bail!("dex \"{}\" must be one of {}", self.dex, names.join(", "));
+        }
+        solana_sdk::pubkey::Pubkey::from_str(&self.pubkey)
+            .with_context(|| format!("pubkey \"{}\" is not a valid public key", self.pubkey))?;
+        Ok(self)
+    }
+}
+
+#[derive(Serialize)]
+struct PoolChangeResponse {
+    pair: String,
+    dex: Dex,
+    pubkey: String,
+    /// Monitored pools after the change
+    active_pools: usize,
+}
+
+/// Start monitoring a pool: index it for decoding, subscribe to it and add
+/// the generated triangular paths through its pair
+async fn add_pool_handler(State(state): State<AppState>, Json(request): Json<AddPoolRequest>) -> Response {
+    let AddPoolRequest { pair, dex, pubkey } = match request.validate() {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{:#}", e)).into_response(),
+    };
+    // Only Raydium pool state carries decimals; the other decoders are built
+    // with the ones from [tokens]
+    if dex != Dex::Raydium {
+        let unknown: Vec<&str> = pair.split(['-', '_']).filter(|token| state.tokens.decimals(token).is_none()).collect();
+        if !unknown.is_empty() {
+            let message = format!("Decimals of {} are unknown, add them to [tokens]", unknown.join(", "));
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
+    let handle = match state.pool_control.handle() {
+        Some(handle) => Some(handle),
+        None if state.pool_control.subscribes(&dex) => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "The feed doesn't take runtime subscriptions").into_response();
+        }
+        None => None,
+    };
+    {
+        let _admission = state.pool_control.admission.lock().unwrap();
+        if state.cache.pool(&pubkey).is_some() {
+            return (StatusCode::CONFLICT, format!("Pool {} is already monitored", pubkey)).into_response();
+        }
+        let max_pools = state.pool_control.max_pools();
+        if state.cache.pool_count() >= max_pools {
+            return (StatusCode::CONFLICT, format!("max_pools ({}) reached", max_pools)).into_response();
+        }
+        if !state.cache.register_pool(&pubkey, &pair, &dex) {
+            return (StatusCode::CONFLICT, format!("Pool {} is already monitored", pubkey)).into_response();
+        }
+    }
+    if let Some(handle) = handle.filter(|_| state.pool_control.subscribes(&dex)) {
+        if !handle.subscribe(pubkey.as_str()) {
+            state.cache.unregister_pool(&pubkey);
+            return (StatusCode::SERVICE_UNAVAILABLE, "The feed is restarting, try again").into_response();
+        }
+    }
+
+    let paths = generate_common_paths(&dex)
+        .into_iter()
+        .filter(|path| [&path.pair_1, &path.pair_2, &path.pair_3].into_iter().any(|leg| same_pair(leg, &pair)));
+    let paths_added = state.triangular_paths.write().await.extend(paths);
+    state.pools.write().await.active.push(PoolSlot {
+        pair: pair.clone(),
+        dex: dex.clone(),
+        pubkey: pubkey.clone(),
+        priority: 0,
+    });
+    let active_pools = state.cache.pool_count();
+    info!(pair = pair, dex = %dex, pubkey = pubkey, paths_added, active_pools, "Pool added via admin API");
+    Json(PoolChangeResponse { pair, dex, pubkey, active_pools }).into_response()
+}
+
+/// Stop monitoring a pool, dropping its subscription and cached price
+///
+/// Triangular paths through it stay in the set; pruning disables them once
+/// a leg has no price.
+async fn remove_pool_handler(State(state): State<AppState>, UrlPath(pubkey): UrlPath<String>) -> Response {
+    let Some((pair, dex)) = state.cache.unregister_pool(&pubkey) else {
+        return (StatusCode::NOT_FOUND, format!("Pool {} is not monitored", pubkey)).into_response();
+    };
+    if state.pool_control.subscribes(&dex) {
+        if let Some(handle) = state.pool_control.handle() {
+            handle.unsubscribe(pubkey.as_str());
+        }
+    }
+    state.pools.write().await.active.retain(|pool| pool.pubkey != pubkey);
+    let active_pools = state.cache.pool_count();
+    info!(pair = pair, dex = %dex, pubkey = pubkey, active_pools, "Pool removed via admin API");
+    Json(PoolChangeResponse { pair, dex, pubkey, active_pools }).into_response()
+}
+
+/// Pair keys match ignoring case and '-' / '_' separators
+fn same_pair(a: &str, b: &str) -> bool {
+    a.replace('_', "-").eq_ignore_ascii_case(&b.replace('_', "-"))
+}
+
+/// Write raw feed frames matching a pubkey or method to a file for a while
+async fn tap_handler(State(state): State<AppState>, Json(request): Json<TapRequest>) -> Response {
+    if request.filter == TapFilter::default() {
+        return (StatusCode::BAD_REQUEST, "A tap needs a pubkey or method filter").into_response();
+    }
+    let seconds = request.seconds.clamp(1, MAX_TAP_SECS);
+    let storage = state.tap_storage.clone();
+    let used = storage.rescan().await;
+    if used >= storage.max_bytes {
+        let message = format!("Tap files take {} bytes, api.tap_max_bytes ({}) reached", used, storage.max_bytes);
+        return (StatusCode::INSUFFICIENT_STORAGE, message).into_response();
+    }
+    let name = format!("tap-{}.jsonl", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+    let path = storage.dir.join(name);
+    let file = match create_tap_file(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            error!(error = %format!("{:#}", e), "Failed to start tap");
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response();
+        }
+    };
+
+    let rx = state.taps.tap(request.filter.clone(), TAP_BUFFER);
+    info!(path = %path.display(), filter = ?request.filter, seconds, "Tapping feed frames");
+    let duration = Duration::from_secs(seconds);
+    tokio::spawn(record_tap(rx, file, path.clone(), duration, storage, state.shutdown.clone()));
+    Json(TapResponse { path: path.display().to_string(), filter: request.filter, seconds }).into_response()
+}
+
+async fn create_tap_file(path: &Path) -> Result<tokio::fs::File> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await.with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    tokio::fs::File::create(path).await.with_context(|| format!("Failed to create {}", path.display()))
+}
+
+/// Append frames, one per line, until the tap expires or the tap files
+/// fill `storage`; dropping `rx` unregisters the tap
+async fn record_tap(
+    mut rx: mpsc::Receiver<String>,
+    mut file: tokio::fs::File,
+    path: PathBuf,
+    duration: Duration,
+    storage: Arc<TapStorage>,
+    shutdown: CancellationToken,
+) {
+    let expired = tokio::time::sleep(duration);
+    tokio::pin!(expired);
+    let mut frames = 0u64;
+    loop {
+        let frame = tokio::select! {
+            Some(frame) = rx.recv() => frame,
+            _ = &mut expired => break,
+            _ = shutdown.cancelled() => break,
+        };
+        let line = format!("{}\n", frame);
+        if !storage.reserve(line.len() as u64) {
+            warn!(path = %path.display(), max_bytes = storage.max_bytes, "Tap stopped, api.tap_max_bytes reached");
+            break;
+        }
+        // Written as they come so the file can be followed live
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            error!(path = %path.display(), error = %e, "Tap write failed");
+            break;
+        }
+        frames += 1;
+    }
+    info!(path = %path.display(), frames, "Tap finished");
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState, format: WireFormat) {
+    // Subscribed before the snapshot is taken, so no update falls in between
+    let mut rx = state.tx.subscribe();
+    let mut filter = ClientFilter::default();
+    let mut framer = Framer::new(state.legacy_messages, state.timestamp_format);
+    framer.format = format;
+    let _connected = state.ws_clients.connected();
+    let mut pings = state.heartbeat.map(|heartbeat| {
+        let mut pings = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat.interval, heartbeat.interval);
+        pings.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        pings
+    });
+    // Set while a ping is unanswered
+    let mut pong_deadline: Option<tokio::time::Instant> = None;
+
+    debug!("New WebSocket client connected");
+
+    if !send_snapshot(&mut socket, &state, &mut framer).await {
+        return;
+    }
+
+    let mut lag_events = 0u64;
+    loop {
+        let msg = tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(msg) => msg,
+                Err(RecvError::Lagged(dropped)) => {
+                    // Tell the client what it missed and start it over from current state
+                    state.client_lag.lagged(dropped, lag_events == 0);
+                    lag_events += 1;
+                    warn!(dropped, lag_events, "WebSocket client fell behind the broadcast, resyncing it");
+                    // Skip the backlog the snapshot supersedes
+                    rx = rx.resubscribe();
+                    let resync = if framer.legacy {
+                        serde_json::json!({ "type": "resync", "dropped": dropped })
+                    } else {
+                        serde_json::json!({ "type": "resync", "data": { "dropped": dropped } })
+                    };
+                    if !send_frame(&mut socket, &state, &mut framer, resync).await
+                        || !send_snapshot(&mut socket, &state, &mut framer).await
+                    {
+                        break;
+                    }
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = next_ping(&mut pings) => {
+                if pong_deadline.is_none() {
+                    if !send_bounded(&mut socket, &state, Message::Ping(Vec::new())).await {
+                        break;
+                    }
+                    pong_deadline = state.heartbeat.map(|heartbeat| tokio::time::Instant::now() + heartbeat.timeout);
+                }
+                continue;
+            }
+            _ = pong_overdue(pong_deadline) => {
+                state.ws_clients.reaped.fetch_add(1, Ordering::Relaxed);
+                warn!("WebSocket client missed its pong deadline, closing it");
+                break;
+            }
+            incoming = socket.recv() => {
+                // Pings from the client are answered by the socket itself
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Pong(_))) => {
+                        pong_deadline = None;
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                // A format change applies from its own acknowledgement on
+                let previous = filter.clone();
+                let timestamps = framer.timestamps;
+                let reply = timestamps.scope(|| client_reply(&text, &state, &mut filter, &mut framer.format));
+                if filter != previous {
+                    // Messages still queued for the old filter are dropped
+                    rx = filter.subscribe(&state.tx);
+                }
+                if !send_frame(&mut socket, &state, &mut framer, reply).await {
+                    break;
+                }
+                continue;
+            }
+            _ = state.shutdown.cancelled() => {
+                send_bounded(&mut socket, &state, Message::Close(None)).await;
+                break;
+            }
+        };
+        if !filter.matches(&msg) {
+            continue;
+        }
+        if let Ok(frame) = framer.ws_message(&msg) {
+            if !send_bounded(&mut socket, &state, frame).await {
+                debug!("Client disconnected");
+                break;
+            }
+        }
+    }
+    if lag_events > 0 {
+        state.client_lag.lagged_client_left();
+    }
+}
+
+/// Send one frame; false once the client is gone
+///
+/// With a heartbeat, a send still blocked after its pong timeout reaps the
+/// client: one that stopped reading would otherwise hold the task forever,
+/// out of reach of the ping deadline.
+async fn send_bounded(socket: &mut WebSocket, state: &AppState, message: Message) -> bool {
+    let Some(heartbeat) = state.heartbeat else {
+        return socket.send(message).await.is_ok();
+    };
+    match tokio::time::timeout(heartbeat.timeout, socket.send(message)).await {
+        Ok(sent) => sent.is_ok(),
+        Err(_) => {
+            state.ws_clients.reaped.fetch_add(1, Ordering::Relaxed);
+            warn!("WebSocket client stopped reading, closing it");
+            false
+        }
+    }
+}
+
+/// Next heartbeat tick; never, without a heartbeat
+async fn next_ping(pings: &mut Option<tokio::time::Interval>) {
+    match pings {
+        Some(pings) => {
+            pings.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves once an unanswered ping's deadline passes
+async fn pong_overdue(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Send the current prices and recent opportunities; false once the client
+/// is gone
+///
+/// Built on the client's task from lock-free cache reads, so other clients'
+/// forwarding isn't held up.
+async fn send_snapshot(socket: &mut WebSocket, state: &AppState, framer: &mut Framer) -> bool {
+    match framer.ws_message(&ApiMessage::Snapshot(snapshot(state))) {
+        Ok(frame) => send_bounded(socket, state, frame).await,
+        Err(e) => {
+            error!(error = %e, "Failed to serialize snapshot");
+            true
+        }
+    }
+}
+
+fn snapshot(state: &AppState) -> Snapshot {
+    Snapshot {
+        prices: all_prices(&state.cache),
+        opportunities: state.opportunities.query(&OpportunityQuery::latest(DEFAULT_OPPORTUNITY_LIMIT)).1,
+    }
+}
+
+/// Ops a `/ws` client may send, for telling unknown ops from bad parameters
+const CLIENT_OPS: [&str; 6] = ["subscribe", "unsubscribe", "set_format", "get_snapshot", "get_history", "ping"];
+
+/// Reply to one client message, applying filter and format changes
+///
+/// Filter changes are acknowledged with the resulting filter, requests
+/// answered with their result; either way the reply carries the request's
+/// `id`. Errors are `{"type":"error","data":{"code","message"}}` with code
+/// `invalid_json`, `unknown_op` or `invalid_params`.
+fn client_reply(text: &str, state: &AppState, filter: &mut ClientFilter, format: &mut WireFormat) -> serde_json::Value {
+    let error = |id: Option<serde_json::Value>, code: &str, message: String| {
+        with_id(serde_json::json!({ "type": "error", "data": { "code": code, "message": message } }), id)
+    };
+    let request: serde_json::Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => return error(None, "invalid_json", format!("Invalid command: {}", e)),
+    };
+    let id = request.get("id").cloned();
+    let command = match ClientCommand::deserialize(&request) {
+        Ok(command) => command,
+        Err(e) => {
+            let known = request["op"].as_str().is_some_and(|op| CLIENT_OPS.contains(&op));
+            let code = if known { "invalid_params" } else { "unknown_op" };
+            return error(id, code, format!("Invalid command: {}", e));
+        }
+    };
+    let reply = match command {
+        ClientCommand::SetFormat { format: requested } => {
+            *format = requested;
+            debug!(format = ?requested, "Client format updated");
+            serde_json::json!({ "type": "format", "data": requested })
+        }
+        ClientCommand::GetSnapshot => {
+            serde_json::json!({ "type": "snapshot", "data": snapshot(state) })
+        }
+        ClientCommand::GetHistory { pair, n } => {
+            let query = OpportunityQuery {
+                pair: pair.clone(),
+                ..OpportunityQuery::latest(n.unwrap_or(DEFAULT_OPPORTUNITY_LIMIT).min(MAX_OPPORTUNITY_PAGE_SIZE))
+            };
+            let (total, opportunities) = state.opportunities.query(&query);
+            serde_json::json!({
+                "type": "history",
+                "data": { "pair": pair, "total": total, "opportunities": opportunities },
+            })
+        }
+        ClientCommand::Ping => serde_json::json!({ "type": "pong", "data": null }),
+        command => {
+            filter.apply(command);
+            debug!(filter = ?filter, "Client filter updated");
+            serde_json::json!({ "type": "filter", "data": filter })
+        }
+    };
+    with_id(reply, id)
+}
+
+/// Echo a request id in its reply
+fn with_id(mut reply: serde_json::Value, id: Option<serde_json::Value>) -> serde_json::Value {
+    if let (Some(id), Some(fields)) = (id, reply.as_object_mut()) {
+        fields.insert("id".to_string(), id);
+    }
+    reply
+}
+
+/// Send a server-built `{type, data}` frame; false once the client is gone
+async fn send_frame(socket: &mut WebSocket, state: &AppState, framer: &mut Framer, value: serde_json::Value) -> bool {
+    match framer.ws_frame(value) {
+        Ok(frame) => send_bounded(socket, state, frame).await,
+        Err(e) => {
+            error!(error = %e, "Failed to serialize frame");
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::{ConfidenceCalibrator, TriangularPathSet, VolatilityTracker};
+    use crate::websocket::ConnectionState;
+    use std::time::Duration;
+
+    /// Orca to Raydium at 100/101, sized 1,000 at 0.8 confidence
+    fn sample_opportunity(
+        opportunity_type: OpportunityType,
+        pair: &str,
+        net_profit_percent: f64,
+    ) -> crate::models::OpportunityBuilder {
+        Opportunity::builder()
+            .opportunity_type(opportunity_type)
+            .token_pair(pair)
+            .buy("orca", 100.0, 0)
+            .sell("raydium", 101.0, 0)
+            .net_profit_percent(net_profit_percent)
+            .recommended_size(1_000)
+            .confidence(0.8)
+    }
+
+    fn app_state() -> AppState {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        AppState {
+            tx: Topics::new(16),
+            calibrator: Arc::new(RwLock::new(ConfidenceCalibrator::new(Duration::ZERO, 1, false))),
+            triangular_paths: Arc::new(RwLock::new(TriangularPathSet::new(Vec::new(), 0))),
+            volatility: Arc::new(VolatilityTracker::new(Default::default())),
+            cache: cache.clone(),
+            pools: Arc::new(RwLock::new(PoolSelection::default())),
+            pause: Arc::new(PauseController::new(Vec::new())),
+            ws_status: watch::channel(ConnectionStatus::default()).1,
+            settings: watch::channel(Settings::default()).1,
+            detectors: DetectorHandles::default(),
+            log_filter: None,
+            subscriptions: SubscriptionBook::default(),
+            taps: TapSet::default(),
+            activity: SwapActivity::default(),
+            opportunities: OpportunityHistory::new(4),
+            usd: UsdPricer::default(),
+            tokens: TokenRegistry::new([("SOL".to_string(), 9), ("USDC".to_string(), 6)]),
+            health: Arc::default(),
+            pair_summaries: Arc::new(PairSummaries::new(cache, Duration::from_secs(300))),
+            limits: Arc::new(ApiLimits::new(&ApiRateLimitConfig::default())),
+            pool_control: PoolControl::new(4),
+            discovered_pools: Arc::default(),
+            system_metrics: LatestMetrics::default(),
+            client_lag: Arc::default(),
+            heartbeat: None,
+            ws_clients: Arc::default(),
+            legacy_messages: false,
+            timestamp_format: TimestampFormat::Rfc3339,
+            tap_storage: Arc::new(TapStorage::new("taps", u64::MAX)),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_reflects_websocket_state() {
+        use tower::ServiceExt;
+
+        let (ws_status, rx) = watch::channel(ConnectionStatus {
+            state: ConnectionState::Failed { consecutive_failures: 3 },
+            endpoint: "primary".to_string(),
+            ..ConnectionStatus::default()
+        });
+        let state = AppState { ws_status: rx, ..app_state() };
+        let tracker = state.health.clone();
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let get_health = || axum::http::Request::get("/health").body(axum::body::Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get_health()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["websocket"]["state"], "failed");
+        assert_eq!(health["websocket"]["endpoint"], "primary");
+        assert_eq!(health["consecutive_failures"], 3);
+        assert!(health["connection_uptime_secs"].is_null());
+        assert!(health["last_update_ms"].is_null());
+        assert_eq!(health["stale_dexes"], serde_json::json!([]));
+
+        ws_status.send_modify(|status| {
+            status.state = ConnectionState::Connected;
+            status.connected_since = Some(chrono::Utc::now() - chrono::Duration::seconds(90));
+            status.active_subscriptions = 12;
+        });
+        tracker.record_update(&Dex::Orca);
+        let response = app.oneshot(get_health()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["connection_uptime_secs"], 90);
+        assert_eq!(health["websocket"]["active_subscriptions"], 12);
+        assert!(health["last_update_ms"].as_u64().unwrap() < 5_000);
+        assert!(health["dex_last_update_ms"]["orca"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_health_needs_no_token() {
+        use tower::ServiceExt;
+
+        let (_ws_status, rx) = watch::channel(ConnectionStatus {
+            state: ConnectionState::Connected,
+            ..ConnectionStatus::default()
+        });
+        let state = AppState { ws_status: rx, ..app_state() };
+        state.health.record_update(&Dex::Orca);
+        let app = router(&admin_config(), state).unwrap();
+        let get = |uri: &str| axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+
+        // Probes and the dashboard don't carry the token; the data routes do
+        assert_eq!(app.clone().oneshot(get("/health")).await.unwrap().status(), StatusCode::OK);
+        // 200 with the dashboard built in, a JSON 404 without
+        assert_ne!(app.clone().oneshot(get("/")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(app.oneshot(get("/prices")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_report_totals() {
+        use crate::config::Commitment;
+        use crate::websocket::SubscriptionKind;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        state.subscriptions.set_pools(HashMap::from([("PoolA".to_string(), ("SOL/USDC".to_string(), Dex::Orca))]));
+        state.subscriptions.sync([
+            (SubscriptionKind::Account, "PoolA", None, Commitment::Processed, Some(3)),
+            (SubscriptionKind::Account, "PoolB", None, Commitment::Processed, None),
+        ]);
+        let app = router(&ApiConfig::default(), state).unwrap();
+
+        let request = axum::http::Request::get("/subscriptions").body(axum::body::Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((report["total"].as_u64(), report["confirmed"].as_u64(), report["pending"].as_u64()), (Some(2), Some(1), Some(1)));
+        assert_eq!(report["subscriptions"][0]["pair"], "SOL/USDC");
+        assert_eq!(report["subscriptions"][0]["subscription_id"], 3);
+        assert!(report["subscriptions"][1]["subscription_id"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_prices_are_served_from_the_cache() {
+        use crate::models::PriceData;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        state.cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(101.0, 5_000, 12, 1, 1, 0.0025));
+        let orca = PriceData::new(100.0, 9_000, 11, 1, 1, 0.003).with_origin("orca-pool", PriceSource::Geyser);
+        state.cache.set("SOL-USDC", &Dex::Orca, orca);
+        let mut old = PriceData::new(1.0, 1_000, 9, 1, 1, 0.003);
+        old.timestamp -= chrono::Duration::seconds(10);
+        state.cache.set("BONK-SOL", &Dex::Meteora, old);
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let get = |uri: &str| axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/prices")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        // Pairs and DEXes come out sorted, whatever the insertion order
+        let text = std::str::from_utf8(&body).unwrap();
+        let positions: Vec<usize> =
+            ["\"BONK-SOL\"", "\"SOL-USDC\"", "\"orca\"", "\"raydium\""].iter().map(|k| text.find(k).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", text);
+        let prices: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(prices["BONK-SOL"]["meteora"]["stale"], true);
+        assert!(prices["BONK-SOL"]["meteora"]["age_ms"].as_u64().unwrap() >= 10_000);
+
+        let response = app.clone().oneshot(get("/prices/SOL-USDC")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let pair: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(pair["orca"]["price"], 100.0);
+        assert_eq!((pair["orca"]["slot"].as_u64(), pair["orca"]["liquidity"].as_u64()), (Some(11), Some(9_000)));
+        assert_eq!((pair["raydium"]["fee_rate"].as_f64(), pair["raydium"]["stale"].as_bool()), (Some(0.0025), Some(false)));
+        assert_eq!((pair["orca"]["pool_pubkey"].as_str(), pair["orca"]["source"].as_str()), (Some("orca-pool"), Some("geyser")));
+        assert_eq!(pair["raydium"]["pool_pubkey"], serde_json::Value::Null);
+        assert!(pair["orca"]["received_at"].is_string());
+
+        let response = app.oneshot(get("/prices/ETH-USDC")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_spreads_cover_pairs_with_two_fresh_venues() {
+        use crate::models::PriceData;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        state.cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 5_000, 12, 1, 1, 0.0025));
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.5, 9_000, 11, 1, 1, 0.003));
+        state.cache.set("SOL-USDC", &Dex::Meteora, PriceData::new(100.2, 9_000, 13, 1, 1, 0.003));
+        state.cache.set("BONK-SOL", &Dex::Meteora, PriceData::new(0.00002, 1_000, 9, 1, 1, 0.003));
+        let app = router(&ApiConfig::default(), state).unwrap();
+
+        let response = app.oneshot(axum::http::Request::get("/spreads").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spreads: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(spreads.as_object().unwrap().len(), 1, "{}", spreads);
+        let spread = &spreads["SOL-USDC"];
+        assert_eq!((spread["bid_dex"].as_str(), spread["ask_dex"].as_str()), (Some("orca"), Some("raydium")));
+        assert!((spread["spread_percent"].as_f64().unwrap() - 0.5).abs() < 1e-9);
+        let venues: Vec<_> = spread["venues"].as_array().unwrap().iter().map(|v| v["slot"].as_u64().unwrap()).collect();
+        assert_eq!(venues, vec![12, 13, 11]);
+        assert!(spread["venues"][0]["age_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_rest_responses_compressed_when_accepted() {
+        use crate::models::PriceData;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        for i in 0..20 {
+            state.cache.set(&format!("TOKEN{}-USDC", i), &Dex::Orca, PriceData::new(1.0, 1_000, 1, 1, 1, 0.003));
+        }
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.0, 9_000, 11, 1, 1, 0.003));
+        let config = ApiConfig { cors_origins: vec!["http://localhost:3000".to_string()], ..ApiConfig::default() };
+        let app = router(&config, state.clone()).unwrap();
+        let get = |uri: &str, encoding: Option<&str>| {
+            let mut request = axum::http::Request::get(uri).header(header::ORIGIN, "http://localhost:3000");
+            if let Some(encoding) = encoding {
+                request = request.header(header::ACCEPT_ENCODING, encoding);
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+        let encoding = |response: &axum::response::Response| {
+            response.headers().get(header::CONTENT_ENCODING).map(|v| v.to_str().unwrap().to_string())
+        };
+
+        for accepted in ["gzip", "br"] {
+            let response = app.clone().oneshot(get("/prices", Some(accepted))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(encoding(&response).as_deref(), Some(accepted));
+            // CORS still applies on top of the encoder
+            assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:3000");
+        }
+        let response = app.clone().oneshot(get("/prices", None)).await.unwrap();
+        assert_eq!(encoding(&response), None);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() >= 1024, "{}", body.len());
+        // Below compression_min_bytes
+        assert_eq!(encoding(&app.clone().oneshot(get("/prices/SOL-USDC", Some("gzip"))).await.unwrap()), None);
+        // Streams and health stay unencoded
+        assert_eq!(encoding(&app.clone().oneshot(get("/events", Some("gzip"))).await.unwrap()), None);
+        assert_eq!(encoding(&app.oneshot(get("/health", Some("gzip"))).await.unwrap()), None);
+
+        let app = router(&ApiConfig { compression: false, ..config }, state).unwrap();
+        assert_eq!(encoding(&app.oneshot(get("/prices", Some("gzip"))).await.unwrap()), None);
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_history_filters_newest_first() {
+        use tower::ServiceExt;
+
+        let opportunity = |opportunity_type, pair: &str, seconds_ago| {
+            sample_opportunity(opportunity_type, pair, 0.6)
+                .detected_at(chrono::Utc::now() - chrono::Duration::seconds(seconds_ago))
+                .build()
+                .unwrap()
+        };
+        let state = app_state();
+        // Capacity 4: the oldest of five is dropped
+        for (opportunity_type, pair, seconds_ago) in [
+            (OpportunityType::Spatial, "SOL-USDC", 50),
+            (OpportunityType::Spatial, "SOL-USDC", 40),
+            (OpportunityType::Triangular, "SOL->USDC->BONK->SOL", 30),
+            (OpportunityType::Spatial, "BONK-SOL", 20),
+            (OpportunityType::Statistical, "SOL-USDC", 10),
+        ] {
+            state.opportunities.record(opportunity(opportunity_type, pair, seconds_ago));
+        }
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let query = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+        let ages = |body: &serde_json::Value| -> Vec<i64> {
+            body["opportunities"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|o| {
+                    let at: chrono::DateTime<chrono::Utc> = serde_json::from_value(o["detected_at"].clone()).unwrap();
+                    (chrono::Utc::now() - at).num_seconds()
+                })
+                .collect()
+        };
+
+        let (status, all) = query("/opportunities".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((all["count"].as_u64(), ages(&all)), (Some(4), vec![10, 20, 30, 40]));
+
+        let (_, spatial) = query("/opportunities?type=spatial".to_string()).await;
+        assert_eq!(ages(&spatial), vec![20, 40]);
+        let (_, pair) = query("/opportunities?pair=sol-usdc".to_string()).await;
+        assert_eq!(ages(&pair), vec![10, 40]);
+        let (_, both) = query("/opportunities?type=Spatial&pair=SOL-USDC&limit=5".to_string()).await;
+        assert_eq!(ages(&both), vec![40]);
+        let (_, limited) = query("/opportunities?limit=2".to_string()).await;
+        assert_eq!(ages(&limited), vec![10, 20]);
+
+        let since = chrono::Utc::now() - chrono::Duration::seconds(35);
+        let (_, recent) = query(format!("/opportunities?since={}", since.timestamp_millis())).await;
+        assert_eq!(ages(&recent), vec![10, 20, 30]);
+        let rfc3339 = since.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let (_, recent_spatial) = query(format!("/opportunities?since={}&type=spatial", rfc3339)).await;
+        assert_eq!(ages(&recent_spatial), vec![20]);
+
+        assert_eq!(query("/opportunities?type=momentum".to_string()).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(query("/opportunities?since=yesterday".to_string()).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_history_time_range_and_pages() {
+        use tower::ServiceExt;
+
+        let base = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let state = AppState { opportunities: OpportunityHistory::new(16), ..app_state() };
+        // Seven one second apart, recorded out of order; 3 and 4 share a timestamp
+        for (second, net) in [(0, 0.2), (1, 0.6), (2, 0.8), (4, 1.1), (3, 0.4), (3, 0.9), (5, 1.5)] {
+            state.opportunities.record(
+                sample_opportunity(OpportunityType::Spatial, "SOL-USDC", net)
+                    .detected_at(base + chrono::Duration::seconds(second))
+                    .build()
+                    .unwrap(),
+            );
+        }
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let query = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+        let profits = |body: &serde_json::Value| -> Vec<f64> {
+            body["opportunities"].as_array().unwrap().iter().map(|o| o["net_profit_percent"].as_f64().unwrap()).collect()
+        };
+        let at = |second: i64| (base + chrono::Duration::seconds(second)).to_rfc3339().replace('+', "%2B");
+
+        // Newest first; the later-recorded of two equal timestamps first
+        let (_, all) = query("/opportunities".to_string()).await;
+        assert_eq!(profits(&all), vec![1.5, 1.1, 0.9, 0.4, 0.8, 0.6, 0.2]);
+
+        let (_, page) = query("/opportunities?page=2&page_size=3".to_string()).await;
+        assert_eq!((page["total"].as_u64(), page["count"].as_u64(), page["page"].as_u64()), (Some(7), Some(3), Some(2)));
+        assert_eq!(profits(&page), vec![0.4, 0.8, 0.6]);
+        let (_, last) = query("/opportunities?page=3&page_size=3".to_string()).await;
+        assert_eq!(profits(&last), vec![0.2]);
+        let (status, beyond) = query("/opportunities?page=4&page_size=3".to_string()).await;
+        assert_eq!((status, beyond["total"].as_u64(), beyond["count"].as_u64()), (StatusCode::OK, Some(7), Some(0)));
+
+        // `from` is inclusive, `to` exclusive
+        let (_, window) = query(format!("/opportunities?from={}&to={}", at(1), at(3))).await;
+        assert_eq!(profits(&window), vec![0.8, 0.6]);
+        let ms = (base + chrono::Duration::seconds(3)).timestamp_millis();
+        let (_, window) = query(format!("/opportunities?from={}&to={}&min_profit=0.5", ms, ms + 1)).await;
+        assert_eq!((window["total"].as_u64(), profits(&window)), (Some(1), vec![0.9]));
+        let (_, empty) = query(format!("/opportunities?from={}&to={}", at(3), at(3))).await;
+        assert_eq!((empty["total"].as_u64(), empty["count"].as_u64()), (Some(0), Some(0)));
+        let (_, empty) = query("/opportunities?pair=BONK-SOL".to_string()).await;
+        assert!(profits(&empty).is_empty());
+
+        let (_, capped) = query("/opportunities?page_size=100000".to_string()).await;
+        assert_eq!(capped["page_size"].as_u64(), Some(MAX_OPPORTUNITY_PAGE_SIZE as u64));
+        for bad in [format!("from={}&to={}", at(4), at(3)), "page=0".to_string(), "page_size=0".to_string(), "to=soon".to_string()] {
+            assert_eq!(query(format!("/opportunities?{}", bad)).await.0, StatusCode::BAD_REQUEST, "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_stats_over_window() {
+        use tower::ServiceExt;
+
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.0, 1_000_000, 1, 1, 1, 0.003));
+        let state = AppState {
+            opportunities: OpportunityHistory::new(16),
+            usd: UsdPricer::new([("SOL".to_string(), 9)]).with_price_cache(cache),
+            ..app_state()
+        };
+        let opportunity = |opportunity_type, pair: &str, net, size, minutes_ago| {
+            sample_opportunity(opportunity_type, pair, net)
+                .recommended_size(size)
+                .confidence(net / 4.0)
+                .persisted_slots(2)
+                .detected_at(chrono::Utc::now() - chrono::Duration::minutes(minutes_ago))
+                .build()
+                .unwrap()
+        };
+        for (opportunity_type, pair, net, size, minutes_ago) in [
+            (OpportunityType::Spatial, "SOL-USDC", 3.0, 1_000_000_000, 120),
+            (OpportunityType::Spatial, "SOL-USDC", 0.5, 4_000_000_000, 50),
+            (OpportunityType::Statistical, "SOL-USDC", 1.0, 1_000_000_000, 30),
+            (OpportunityType::Spatial, "BONK-SOL", 2.0, 1_000_000_000, 20),
+            (OpportunityType::Spatial, "SOL-USDC", 1.5, 1_000_000_000, 3),
+        ] {
+            state.opportunities.record(opportunity(opportunity_type, pair, net, size, minutes_ago));
+        }
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let query = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+
+        // The default hour leaves out the two-hour-old one
+        let (status, stats) = query("/opportunities/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((stats["window_seconds"].as_u64(), stats["total"].as_u64()), (Some(3600), Some(4)));
+        assert_eq!(stats["by_type"], serde_json::json!({ "spatial": 3, "statistical": 1 }));
+        assert_eq!(stats["by_pair"], serde_json::json!({ "SOL-USDC": 3, "BONK-SOL": 1 }));
+        assert_eq!(stats["net_profit_percent"], serde_json::json!({ "mean": 1.25, "median": 1.25, "max": 2.0 }));
+        assert_eq!(stats["mean_confidence"].as_f64(), Some(0.3125));
+        assert_eq!(stats["mean_persisted_slots"].as_f64(), Some(2.0));
+        // 4 SOL at 0.5% = $2, 1 SOL at 1.5% = $1.50, 1 SOL at 1% = $1; BONK has no decimals
+        let top: Vec<(f64, &str)> = stats["top_by_usd"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| (o["usd_profit"].as_f64().unwrap(), o["token_pair"].as_str().unwrap()))
+            .collect();
+        assert_eq!(top, vec![(2.0, "SOL-USDC"), (1.5, "SOL-USDC"), (1.0, "SOL-USDC")]);
+
+        let (_, recent) = query("/opportunities/stats?window=5m").await;
+        assert_eq!((recent["total"].as_u64(), recent["window_seconds"].as_u64()), (Some(1), Some(300)));
+        assert_eq!(recent["net_profit_percent"]["median"].as_f64(), Some(1.5));
+        let (_, day) = query("/opportunities/stats?window=24h").await;
+        assert_eq!(day["total"].as_u64(), Some(5));
+        assert_eq!(day["net_profit_percent"]["median"].as_f64(), Some(1.5));
+        let (_, empty) = query("/opportunities/stats?window=60s").await;
+        assert_eq!(empty["total"].as_u64(), Some(0));
+        assert!(empty["net_profit_percent"].is_null() && empty["top_by_usd"] == serde_json::json!([]));
+
+        for bad in ["/opportunities/stats?window=1w", "/opportunities/stats?window=0m", "/opportunities/stats?window=h"] {
+            assert_eq!(query(bad).await.0, StatusCode::BAD_REQUEST, "{}", bad);
+        }
+    }
+
+    /// Serve `state` on a local port; returns the `/ws` URL
+    async fn serve(state: AppState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(&ApiConfig::default(), state).unwrap().into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}/ws", addr)
+    }
+
+    type Client = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    /// Next JSON message a `/ws` client receives
+    async fn next(client: &mut Client) -> serde_json::Value {
+        use futures::StreamExt;
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("no frame");
+        serde_json::from_str(frame.unwrap().unwrap().to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_clients_only_receive_what_they_subscribed_to() {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = app_state();
+        let tx = state.tx.clone();
+        let url = serve(state).await;
+        let (mut sol_prices, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut everything, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        assert_eq!(next(&mut sol_prices).await["type"], "snapshot");
+        assert_eq!(next(&mut everything).await["type"], "snapshot");
+
+        let command = r#"{"op":"subscribe","pairs":["sol-usdc"],"types":["price","system_status"]}"#;
+        sol_prices.send(WsMessage::Text(command.to_string())).await.unwrap();
+        let ack = next(&mut sol_prices).await;
+        assert_eq!(ack["type"], "filter");
+        assert_eq!(ack["data"]["pairs"], serde_json::json!(["SOL-USDC"]));
+        sol_prices.send(WsMessage::Text("{\"op\":\"shout\"}".to_string())).await.unwrap();
+        assert_eq!(next(&mut sol_prices).await["type"], "error");
+
+        let price = |pair: &str| ApiMessage::PriceUpdate { pair: pair.to_string(), dex: Dex::Orca, price: 1.0, slot: 1, ts: chrono::DateTime::UNIX_EPOCH };
+        let alert = ApiMessage::SpreadAlert(SpreadAlert {
+            pair: "SOL-USDC".to_string(),
+            low_dex: Dex::Orca,
+            high_dex: Dex::Raydium,
+            low_price: 100.0,
+            high_price: 101.0,
+            spread_bps: 100.0,
+            threshold_bps: 50.0,
+        });
+        let status = ApiMessage::SystemStatus {
+            paused: false,
+            reason: None,
+            rpc_endpoint: "primary".to_string(),
+            ws_connected: true,
+            consecutive_failures: 0,
+            connection_uptime_secs: None,
+            active_subscriptions: 3,
+            slow_dexes: Vec::new(),
+        };
+        for message in [price("BONK-SOL"), alert, price("SOL-USDC"), status] {
+            tx.send(message);
+        }
+
+        let mut filtered = Vec::new();
+        for _ in 0..2 {
+            let message = next(&mut sol_prices).await;
+            filtered.push((message["type"].as_str().unwrap().to_string(), message["data"]["pair"].as_str().unwrap_or("").to_string()));
+        }
+        assert_eq!(filtered, [("price".to_string(), "SOL-USDC".to_string()), ("system_status".to_string(), String::new())]);
+        let mut all = Vec::new();
+        for _ in 0..4 {
+            all.push(next(&mut everything).await["type"].as_str().unwrap().to_string());
+        }
+        assert_eq!(all, ["price", "spread_alert", "price", "system_status"]);
+
+        // Dropping every filter restores the full stream
+        sol_prices.send(WsMessage::Text(r#"{"op":"unsubscribe"}"#.to_string())).await.unwrap();
+        assert_eq!(next(&mut sol_prices).await["data"], serde_json::json!({ "pairs": null, "types": null }));
+        tx.send(price("BONK-SOL"));
+        assert_eq!(next(&mut sol_prices).await["data"]["pair"], "BONK-SOL");
+    }
+
+    #[test]
+    fn test_opportunity_type_covers_groups() {
+        let opportunity = sample_opportunity(OpportunityType::Spatial, "SOL-USDC", 0.6).build().unwrap();
+        let group = ApiMessage::OpportunityGroup(AggregatedOpportunity {
+            fingerprint: vec!["SOL-USDC@orca".to_string()],
+            best: opportunity.clone(),
+            strategies: vec![opportunity],
+        });
+        let filter = |types: &[&str]| {
+            let mut filter = ClientFilter::default();
+            let types = types.iter().map(|kind| kind.to_string()).collect();
+            filter.apply(ClientCommand::Subscribe { pairs: Some(vec!["sol-usdc".to_string()]), types: Some(types) });
+            filter
+        };
+
+        let topics = Topics::new(16);
+        let opportunities = filter(&["opportunity"]);
+        let mut rx = opportunities.subscribe(&topics);
+        assert!(opportunities.matches(&group));
+        topics.send(group.clone());
+        assert_eq!(rx.try_recv().unwrap().kind(), "opportunity_group");
+
+        assert!(!filter(&["price"]).matches(&group));
+        assert!(filter(&["opportunity_group"]).matches(&group));
+    }
+
+    #[cfg(feature = "dashboard")]
+    #[tokio::test]
+    async fn test_root_serves_dashboard_and_ws_still_upgrades() {
+        let url = serve(app_state()).await;
+        let base = url.replace("ws://", "http://").replace("/ws", "");
+
+        // reqwest is on an older `http`, compare plain values
+        let response = reqwest::get(format!("{}/", base)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+        assert!(response.text().await.unwrap().starts_with("<!DOCTYPE html>"));
+        let response = reqwest::get(format!("{}/index.html", base)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_paths_get_json_404() {
+        use tower::ServiceExt;
+
+        let app = router(&ApiConfig::default(), app_state()).unwrap();
+        let get = |uri: &str| axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+        let mut paths = vec!["/nope", "/prices/SOL-USDC/extra"];
+        if cfg!(not(feature = "dashboard")) {
+            paths.push("/");
+        }
+        for path in paths {
+            let response = app.clone().oneshot(get(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!((body["error"].as_str(), body["path"].as_str()), (Some("not_found"), Some(path)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clients_get_a_snapshot_before_live_updates() {
+        use crate::models::PriceData;
+
+        let state = app_state();
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.0, 9_000, 11, 1, 1, 0.003));
+        state.opportunities.record(sample_opportunity(OpportunityType::Spatial, "SOL-USDC", 0.6).build().unwrap());
+        let tx = state.tx.clone();
+        let url = serve(state).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+        let snapshot = next(&mut client).await;
+        assert_eq!(snapshot["type"], "snapshot");
+        assert_eq!(snapshot["data"]["prices"]["SOL-USDC"]["orca"]["price"], 100.0);
+        assert!(snapshot["data"]["prices"]["SOL-USDC"]["orca"]["age_ms"].is_u64());
+        assert_eq!(snapshot["data"]["opportunities"][0]["token_pair"], "SOL-USDC");
+
+        // The client's broadcast receiver exists once the snapshot is out
+        tx.send(ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 100.5, slot: 12, ts: chrono::DateTime::UNIX_EPOCH });
+        let update = next(&mut client).await;
+        assert_eq!((update["type"].as_str(), update["data"]["price"].as_f64()), (Some("price"), Some(100.5)));
+    }
+
+    #[tokio::test]
+    async fn test_events_stream_filtered_messages() {
+        use futures::StreamExt;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        let tx = state.tx.clone();
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let request = axum::http::Request::get("/events?types=price,system_status&pairs=SOL-USDC")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+        let price = |pair: &str| ApiMessage::PriceUpdate { pair: pair.to_string(), dex: Dex::Orca, price: 1.5, slot: 7, ts: chrono::DateTime::UNIX_EPOCH };
+        tx.send(price("BONK-SOL"));
+        tx.send(ApiMessage::SpreadAlert(SpreadAlert {
+            pair: "SOL-USDC".to_string(),
+            low_dex: Dex::Orca,
+            high_dex: Dex::Raydium,
+            low_price: 100.0,
+            high_price: 101.0,
+            spread_bps: 100.0,
+            threshold_bps: 50.0,
+        }));
+        tx.send(price("SOL-USDC"));
+
+        let mut body = response.into_body().into_data_stream();
+        let mut text = String::new();
+        while !text.contains("\n\n") {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+            text.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        let lines: Vec<&str> = text.trim_end().lines().collect();
+        assert!(lines.contains(&"event: price"), "{}", text);
+        assert!(lines.contains(&"id: 1"), "{}", text);
+        let data = lines.iter().find_map(|line| line.strip_prefix("data: ")).unwrap();
+        let data: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!((data["v"].as_u64(), data["seq"].as_u64()), (Some(u64::from(PROTOCOL_VERSION)), Some(1)));
+        assert_eq!(data["data"], serde_json::to_value(price("SOL-USDC")).unwrap()["data"]);
+    }
+
+    #[tokio::test]
+    async fn test_admin_tap_writes_matching_frames() {
+        use tower::ServiceExt;
+
+        let tap_dir = std::env::temp_dir().join(format!("api-taps-{}", std::process::id()));
+        // Room for two frames
+        let state = AppState { tap_storage: Arc::new(TapStorage::new(&tap_dir, 24)), ..app_state() };
+        let taps = state.taps.clone();
+        let app = router(&admin_config(), state).unwrap();
+        let post_tap = |body: &str| {
+            authorized(axum::http::Request::post("/admin/tap"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(post_tap("{}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(post_tap(r#"{"pubkey": "PoolA", "seconds": 5}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let tap: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(tap["filter"]["pubkey"], "PoolA");
+        let path = PathBuf::from(tap["path"].as_str().unwrap());
+        assert!(path.starts_with(&tap_dir));
+
+        taps.offer(r#"{"frame":1}"#, Some("accountNotification"), Some("PoolA"));
+        taps.offer(r#"{"frame":2}"#, Some("accountNotification"), Some("PoolB"));
+        taps.offer(r#"{"frame":3}"#, None, Some("PoolA"));
+        let mut written = String::new();
+        for _ in 0..50 {
+            written = std::fs::read_to_string(&path).unwrap();
+            if written.lines().count() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(written, "{\"frame\":1}\n{\"frame\":3}\n");
+
+        // The budget is spent: further frames are dropped and new taps refused
+        taps.offer(r#"{"frame":4}"#, None, Some("PoolA"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let response = app.oneshot(post_tap(r#"{"pubkey": "PoolA"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+        std::fs::remove_dir_all(&tap_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_disabled_api_does_not_spawn() {
+        let config = ApiConfig {
+            enabled: false,
+            ..ApiConfig::default()
+        };
+        assert!(start_server(&config, app_state()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bind_failure_is_returned() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ApiConfig {
+            bind_addr: "127.0.0.1".to_string(),
+            port: taken.local_addr().unwrap().port(),
+            ..ApiConfig::default()
+        };
+        assert!(start_server(&config, app_state()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_clients_and_stops_server() {
+        use futures::StreamExt;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let port = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+        let config = ApiConfig {
+            bind_addr: "127.0.0.1".to_string(),
+            port,
+            ..ApiConfig::default()
+        };
+        let state = app_state();
+        let shutdown = state.shutdown.clone();
+        let server = start_server(&config, state).await.unwrap().unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/ws", port))
+            .await
+            .unwrap();
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+        shutdown.cancel();
+
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap();
+        assert!(matches!(frame, Some(Ok(ClientMessage::Close(_)))), "{:?}", frame);
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_on_stuck_requests_after_drain() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/stuck", listener.local_addr().unwrap());
+        let app = Router::new().route("/stuck", get(std::future::pending::<&'static str>));
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(serve_until_shutdown(Listener::Tcp(listener), app, shutdown.clone(), Duration::from_millis(200)));
+
+        let request = tokio::spawn(async move { reqwest::get(url).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let cancelled = Instant::now();
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(2), server).await.unwrap().unwrap();
+        assert!(cancelled.elapsed() >= Duration::from_millis(200));
+        request.abort();
+    }
+
+    #[tokio::test]
+    async fn test_serves_tcp_and_unix_listeners_together() {
+        use futures::StreamExt;
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path = std::env::temp_dir().join(format!("api-{}.sock", std::process::id()));
+        // A stale socket from an earlier run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let addrs = [ListenAddr::Tcp("127.0.0.1:0".parse().unwrap()), ListenAddr::Unix(path.clone())];
+        let listeners = bind_all(&addrs, 0o600).await.unwrap();
+        let Listener::Tcp(tcp) = &listeners[0] else { panic!("expected tcp") };
+        let tcp_addr = tcp.local_addr().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        // Bound in a staging directory, which is gone once the socket is in place
+        let staging = format!(".api-{}.sock.{}", std::process::id(), std::process::id());
+        assert!(!path.with_file_name(staging).exists());
+
+        let state = app_state();
+        let shutdown = state.shutdown.clone();
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let server = tokio::spawn(serve_all(listeners, app, shutdown.clone(), Duration::from_millis(200)));
+
+        let response = reqwest::get(format!("http://{}/version", tcp_addr)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        // Plain HTTP/1.1 over the socket, as a local reverse proxy would send it
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream.write_all(b"GET /version HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.contains(&format!("\"protocol\":{}", PROTOCOL_VERSION)), "{}", response);
+        // WebSocket upgrades work over the socket too
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (mut client, _) = tokio_tungstenite::client_async("ws://localhost/ws", stream).await.unwrap();
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+        let snapshot: serde_json::Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+        assert_eq!(snapshot["type"], "snapshot");
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_refuses_to_replace_a_regular_file() {
+        let path = std::env::temp_dir().join(format!("api-not-a-socket-{}", std::process::id()));
+        std::fs::write(&path, "keep me").unwrap();
+        let socket = std::env::temp_dir().join(format!("api-first-{}.sock", std::process::id()));
+        let addrs = [ListenAddr::Unix(socket.clone()), ListenAddr::Unix(path.clone())];
+        let error = bind_all(&addrs, 0o660).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("is not a socket"), "{:#}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        // The socket bound before the failure is cleaned up
+        assert!(!socket.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limiter_refills_per_client() {
+        let limiter = RateLimiter::new(2.0, 2);
+        let (a, b) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let start = Instant::now();
+        assert!(limiter.check_at(a, start).is_ok());
+        assert!(limiter.check_at(a, start).is_ok());
+        let retry = limiter.check_at(a, start).unwrap_err();
+        assert_eq!(retry, Duration::from_millis(500));
+        // Other clients have their own bucket
+        assert!(limiter.check_at(b, start).is_ok());
+        assert!(limiter.check_at(a, start + Duration::from_millis(500)).is_ok());
+        assert_eq!(limiter.rejected(), 1);
+
+        let unlimited = RateLimiter::new(0.0, 1);
+        assert!((0..100).all(|_| unlimited.check_at(a, start).is_ok()));
+    }
+
+    #[test]
+    fn test_rate_limiter_forgets_idle_clients() {
+        // A bucket refills completely in 2s
+        let limiter = RateLimiter::new(1.0, 2);
+        let start = Instant::now();
+        for last_octet in 1..=10 {
+            limiter.check_at(IpAddr::from([10, 0, 0, last_octet]), start).unwrap();
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().clients.len(), 10);
+
+        // Long before the map fills up, idle clients go once they've refilled
+        let later = IpAddr::from([10, 0, 1, 1]);
+        limiter.check_at(later, start + Duration::from_secs(3)).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.clients.keys().collect::<Vec<_>>(), [&later]);
+    }
+
+    #[tokio::test]
+    async fn test_rest_requests_rate_limited_but_health_exempt() {
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig {
+            rest_per_second: 0.001,
+            rest_burst: 2,
+            admin_per_second: 0.001,
+            admin_burst: 1,
+            ..ApiRateLimitConfig::default()
+        };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let limits = state.limits.clone();
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let get = |uri: &str| axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(app.clone().oneshot(get("/prices")).await.unwrap().status(), StatusCode::OK);
+        }
+        let response = app.clone().oneshot(get("/opportunities")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        // Admin routes draw from their own bucket
+        let pause = || axum::http::Request::post("/admin/pause").body(axum::body::Body::empty()).unwrap();
+        assert_ne!(app.clone().oneshot(pause()).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(app.clone().oneshot(pause()).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+
+        for _ in 0..5 {
+            assert_ne!(app.clone().oneshot(get("/health")).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+        assert_eq!(limits.rejections(), ApiRejections { rest: 1, admin: 1, streams: 0 });
+    }
+
+    const ADMIN_TOKEN: &str = "admin-token";
+
+    /// API config with the token the admin routes require
+    fn admin_config() -> ApiConfig {
+        ApiConfig { auth_token: Some(ADMIN_TOKEN.to_string()), ..ApiConfig::default() }
+    }
+
+    /// `request` carrying the `admin_config` token
+    fn authorized(request: axum::http::request::Builder) -> axum::http::request::Builder {
+        request.header(header::AUTHORIZATION, format!("Bearer {}", ADMIN_TOKEN))
+    }
+
+    #[tokio::test]
+    async fn test_token_accepted_from_header_or_encoded_query() {
+        use tower::ServiceExt;
+
+        let config = ApiConfig { auth_token: Some("a+b/c=d".to_string()), ..ApiConfig::default() };
+        let app = router(&config, app_state()).unwrap();
+        let status = |request: axum::http::request::Builder| {
+            let app = app.clone();
+            async move { app.oneshot(request.body(axum::body::Body::empty()).unwrap()).await.unwrap().status() }
+        };
+
+        assert_eq!(status(axum::http::Request::get("/prices")).await, StatusCode::UNAUTHORIZED);
+        let bearer = axum::http::Request::get("/prices").header(header::AUTHORIZATION, "Bearer a+b/c=d");
+        assert_eq!(status(bearer).await, StatusCode::OK);
+        assert_eq!(status(axum::http::Request::get("/prices?token=a%2Bb%2Fc%3Dd")).await, StatusCode::OK);
+        assert_eq!(status(axum::http::Request::get("/prices?token=a%2Bb%2Fc%3De")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(axum::http::Request::get("/prices?token=a%2Bb")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(axum::http::Request::get("/events")).await, StatusCode::UNAUTHORIZED);
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem") && !constant_time_eq(b"token", b"tokens"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_need_a_configured_token() {
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig { admin_per_second: 0.0, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let admin_routes = || {
+            [
+                axum::http::Request::post("/admin/pause"),
+                axum::http::Request::post("/admin/resume"),
+                axum::http::Request::post("/admin/tap"),
+                axum::http::Request::post("/admin/pools"),
+                axum::http::Request::delete("/admin/pools/HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ"),
+                axum::http::Request::post("/admin/cache/cleanup"),
+                axum::http::Request::post("/admin/detectors/reset"),
+                axum::http::Request::put("/admin/log-level"),
+                axum::http::Request::get("/config"),
+            ]
+        };
+        let open = router(&ApiConfig::default(), state.clone()).unwrap();
+        for request in admin_routes() {
+            let response = open.clone().oneshot(request.body(axum::body::Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        assert!(!state.pause.status().paused);
+
+        let app = router(&admin_config(), state.clone()).unwrap();
+        for request in admin_routes() {
+            let request = request.header(header::AUTHORIZATION, "Bearer wrong").body(axum::body::Body::empty()).unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        }
+        let pause = authorized(axum::http::Request::post("/admin/pause")).body(axum::body::Body::empty()).unwrap();
+        assert_eq!(app.oneshot(pause).await.unwrap().status(), StatusCode::OK);
+        assert!(state.pause.status().paused);
+    }
+
+    #[tokio::test]
+    async fn test_stream_cap_refuses_extra_clients_until_one_leaves() {
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig { max_streams: 1, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let limits = state.limits.clone();
+        let app = router(&ApiConfig::default(), state.clone()).unwrap();
+        let url = serve(state).await;
+
+        let (mut first, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        assert_eq!(next(&mut first).await["type"], "snapshot");
+        assert!(tokio_tungstenite::connect_async(url.as_str()).await.is_err());
+        let events = axum::http::Request::get("/events").body(axum::body::Body::empty()).unwrap();
+        assert_eq!(app.oneshot(events).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limits.rejections().streams, 2);
+
+        first.close(None).await.unwrap();
+        drop(first);
+        let mut reconnected = None;
+        for _ in 0..50 {
+            if let Ok((client, _)) = tokio_tungstenite::connect_async(url.as_str()).await {
+                reconnected = Some(client);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(reconnected.is_some(), "stream slot never released");
+    }
+
+    #[tokio::test]
+    async fn test_streams_capped_and_rate_limited_per_client() {
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig { max_streams_per_client: 1, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let url = serve(state.clone()).await;
+
+        let (mut first, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        assert_eq!(next(&mut first).await["type"], "snapshot");
+        assert!(tokio_tungstenite::connect_async(url.as_str()).await.is_err());
+        // Another client (no peer address here) has its own allowance
+        let app = router(&ApiConfig::default(), state.clone()).unwrap();
+        let events = || axum::http::Request::get("/events").body(axum::body::Body::empty()).unwrap();
+        let other = app.clone().oneshot(events()).await.unwrap();
+        assert_eq!(other.status(), StatusCode::OK);
+        assert_eq!(state.limits.rejections().streams, 1);
+        drop(other);
+
+        // Opening streams draws on the request budget
+        let limits = ApiRateLimitConfig { rest_per_second: 0.001, rest_burst: 1, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let app = router(&ApiConfig::default(), state.clone()).unwrap();
+        assert_eq!(app.clone().oneshot(events()).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(app.oneshot(events()).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(state.limits.rejections().rest, 1);
+    }
+
+    #[tokio::test]
+    async fn test_admin_pools_subscribe_and_register() {
+        use crate::models::PriceData;
+        use crate::websocket::WsCommand;
+        use tower::ServiceExt;
+
+        const POOL: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+        const OTHER: &str = "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ";
+        // More admin calls than the default burst allows
+        let limits = ApiRateLimitConfig { admin_per_second: 0.0, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let (handle, mut commands) = WsHandle::detached();
+        state.pool_control.set_handle(Some(handle));
+        state.pool_control.set_max_pools(1);
+        let app = router(&admin_config(), state.clone()).unwrap();
+        let add = |body: String| {
+            authorized(axum::http::Request::post("/admin/pools"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let pool = |pair: &str, dex: &str, pubkey: &str| format!(r#"{{"pair":"{}","dex":"{}","pubkey":"{}"}}"#, pair, dex, pubkey);
+
+        let response = app.clone().oneshot(add(pool("SOL-USDC", "Raydium", POOL))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let added: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(added["dex"], "raydium");
+        assert_eq!(added["active_pools"], 1);
+        assert_eq!(commands.try_recv().unwrap(), WsCommand::Subscribe(POOL.to_string()));
+        assert_eq!(state.cache.pool(POOL), Some(("SOL-USDC".to_string(), Dex::Raydium)));
+        assert!(!state.triangular_paths.read().await.active_paths_for(["SOL-USDC"]).is_empty());
+        assert_eq!(state.pools.read().await.active.len(), 1);
+
+        for (body, status) in [
+            (pool("SOL-USDC", "raydium", POOL), StatusCode::CONFLICT),
+            (pool("SOL-USDC", "orca", OTHER), StatusCode::CONFLICT),
+            (pool("SOLUSDC", "orca", OTHER), StatusCode::BAD_REQUEST),
+            (pool("SOL-USDC", "phoenix", OTHER), StatusCode::BAD_REQUEST),
+            (pool("SOL-USDC", "orca", "not-a-key"), StatusCode::BAD_REQUEST),
+            // Orca decimals come from [tokens]
+            (pool("BONK-USDC", "orca", OTHER), StatusCode::BAD_REQUEST),
+        ] {
+            assert_eq!(app.clone().oneshot(add(body.clone())).await.unwrap().status(), status, "{}", body);
+        }
+        assert!(commands.try_recv().is_err());
+
+        state.cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        let remove = |pubkey: &str| {
+            authorized(axum::http::Request::delete(format!("/admin/pools/{}", pubkey)))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(remove(POOL)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let removed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(removed["active_pools"], 0);
+        assert_eq!(commands.try_recv().unwrap(), WsCommand::Unsubscribe(POOL.to_string()));
+        assert!(state.cache.get("SOL-USDC", &Dex::Raydium).is_none());
+        assert!(state.pools.read().await.active.is_empty());
+        assert_eq!(app.oneshot(remove(POOL)).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_config_is_redacted_and_follows_reloads() {
+        use crate::config::RpcEndpoint;
+        use tower::ServiceExt;
+
+        let mut settings = Settings::default();
+        settings.rpc.endpoints = vec![RpcEndpoint::new(
+            "helius",
+            "wss://mainnet.helius-rpc.com/?api-key=0123456789abcdef".to_string(),
+            "https://mainnet.helius-rpc.com/?api-key=0123456789abcdef".to_string(),
+        )];
+        settings.api.auth_token = Some("super-secret-token".to_string());
+        let live = watch::Sender::new(settings.clone());
+        let state = AppState { settings: live.subscribe(), ..app_state() };
+        let app = router(&settings.api, state).unwrap();
+        let get = |token: Option<&str>| {
+            let mut request = axum::http::Request::get("/config");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+        let config = |response: Response| async move {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        assert_eq!(app.clone().oneshot(get(None)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let body = config(app.clone().oneshot(get(Some("super-secret-token"))).await.unwrap()).await;
+        let text = body.to_string();
+        assert!(!text.contains("0123456789abcdef") && !text.contains("super-secret-token"), "{}", text);
+        assert_eq!(body["settings"]["rpc"]["endpoints"][0]["http_url"], "https://mainnet.helius-rpc.com/?api-key=****cdef");
+        assert_eq!(body["detectors"], serde_json::json!(settings.detectors.enabled()));
+        assert_eq!((body["monitored_pools"].as_u64(), body["triangular_paths"]["total"].as_u64()), (Some(0), Some(0)));
+
+        // A reload applied by the main loop shows up without restarting the API
+        settings.arbitrage.min_profit_percent = 0.75;
+        live.send_replace(settings);
+        let body = config(app.oneshot(get(Some("super-secret-token"))).await.unwrap()).await;
+        assert_eq!(body["settings"]["arbitrage"]["min_profit_percent"], 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_admin_pools_need_a_live_feed_for_account_pools() {
+        use tower::ServiceExt;
+
+        let state = app_state();
+        state.pool_control.set_program_dexes([Dex::Orca]);
+        let app = router(&admin_config(), state.clone()).unwrap();
+        let add = |dex: &str| {
+            let body = format!(r#"{{"pair":"SOL-USDC","dex":"{}","pubkey":"HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ"}}"#, dex);
+            authorized(axum::http::Request::post("/admin/pools"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+
+        assert_eq!(app.clone().oneshot(add("raydium")).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(state.cache.pool_count(), 0);
+        // Program subscriptions pick the pool up without a handle
+        assert_eq!(app.oneshot(add("orca")).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(state.cache.pool_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pool_adds_stay_within_max_pools() {
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig { admin_per_second: 0.0, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let (handle, _commands) = WsHandle::detached();
+        state.pool_control.set_handle(Some(handle));
+        state.pool_control.set_max_pools(2);
+        let app = router(&admin_config(), state.clone()).unwrap();
+        let adds = (0..16).map(|_| {
+            let pubkey = solana_sdk::pubkey::Pubkey::new_unique();
+            let body = format!(r#"{{"pair":"SOL-USDC","dex":"raydium","pubkey":"{}"}}"#, pubkey);
+            let request = authorized(axum::http::Request::post("/admin/pools"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            tokio::spawn(app.clone().oneshot(request))
+        });
+        let statuses = futures::future::join_all(adds).await;
+        let added = statuses.into_iter().filter(|s| s.as_ref().unwrap().as_ref().unwrap().status() == StatusCode::OK).count();
+        assert_eq!((added, state.cache.pool_count()), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_system_metrics_broadcast_and_served() {
+        use crate::models::PriceData;
+        use tower::ServiceExt;
+
+        let state = app_state();
+        let app = router(&ApiConfig::default(), state.clone()).unwrap();
+        let get_metrics = || axum::http::Request::get("/metrics/system").body(axum::body::Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(get_metrics()).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let sampler = MetricsSampler::new(
+            state.cache.clone(),
+            Arc::new(PipelineMetrics::default()),
+            state.ws_status.clone(),
+            state.opportunities.clone(),
+        );
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.0, 9_000, 11, 1, 1, 0.003));
+        state.opportunities.record(sample_opportunity(OpportunityType::Spatial, "SOL-USDC", 0.6).build().unwrap());
+        let mut rx = state.tx.subscribe();
+        let task = sampler.spawn(
+            Duration::from_millis(20),
+            state.system_metrics.clone(),
+            state.tx.clone(),
+            state.shutdown.clone(),
+        );
+
+        let message = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        let ApiMessage::SystemMetrics(metrics) = message else {
+            panic!("expected metrics, got {:?}", message);
+        };
+        assert_eq!(metrics.cache_entries, 1);
+        assert!(metrics.updates_per_sec > 0.0 && metrics.updates_per_sec.is_finite());
+        assert_eq!(metrics.fps, 0);
+        assert!(!metrics.ws_connected);
+        assert_eq!(metrics.channel_occupancy, 0.0);
+        assert_eq!(metrics.opportunities, BTreeMap::from([("spatial".to_string(), 1)]));
+
+        let response = app.oneshot(get_metrics()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let served: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(served["cache_entries"], 1);
+        assert_eq!(served["opportunities"]["spatial"], 1);
+
+        state.shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lagging_client_is_resynced_and_kept() {
+        let state = app_state();
+        let (tx, client_lag) = (state.tx.clone(), state.client_lag.clone());
+        let url = serve(state).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+
+        let price = |slot| ApiMessage::PriceUpdate {
+            pair: "SOL-USDC".to_string(),
+            dex: Dex::Orca,
+            price: 100.0,
+            slot,
+            ts: chrono::DateTime::UNIX_EPOCH,
+        };
+        // The server task can't run until this test yields, so the client
+        // falls 24 messages behind the 16-message channel
+        for slot in 0..40 {
+            tx.send(price(slot));
+        }
+
+        let resync = next(&mut client).await;
+        assert_eq!(resync["type"], "resync");
+        assert_eq!(resync["data"], serde_json::json!({ "dropped": 24 }));
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+        // The backlog is older than the snapshot and isn't replayed
+        tx.send(price(40));
+        assert_eq!(next(&mut client).await["data"]["slot"], 40);
+        assert_eq!(client_lag.events.load(Ordering::Relaxed), 1);
+        assert_eq!(client_lag.dropped.load(Ordering::Relaxed), 24);
+        assert_eq!(client_lag.lagging_clients.load(Ordering::Relaxed), 1);
+
+        client.close(None).await.unwrap();
+        for _ in 0..50 {
+            if client_lag.lagging_clients.load(Ordering::Relaxed) == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("lagging client never counted as gone");
+    }
+
+    #[tokio::test]
+    async fn test_messages_enveloped_with_continuous_sequence() {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = app_state();
+        let tx = state.tx.clone();
+        let (mut client, _) = tokio_tungstenite::connect_async(serve(state).await.as_str()).await.unwrap();
+        let price = |slot| ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 1.5, slot, ts: chrono::DateTime::UNIX_EPOCH };
+
+        let mut frames = vec![next(&mut client).await];
+        client.send(WsMessage::Text(r#"{"op":"subscribe","types":["price"]}"#.to_string())).await.unwrap();
+        frames.push(next(&mut client).await);
+        for slot in 0..3 {
+            tx.send(price(slot));
+            frames.push(next(&mut client).await);
+        }
+
+        let kinds: Vec<&str> = frames.iter().map(|f| f["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["snapshot", "filter", "price", "price", "price"]);
+        let seqs: Vec<u64> = frames.iter().map(|f| f["seq"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, [1, 2, 3, 4, 5]);
+        let sent_at = |f: &serde_json::Value| f["ts"].as_str().and_then(|ts| ts.parse::<chrono::DateTime<chrono::Utc>>().ok());
+        assert!(frames.iter().all(|f| f["v"] == PROTOCOL_VERSION && sent_at(f).is_some()));
+        assert_eq!(frames[4]["data"]["slot"], 2);
+
+        // A second connection counts from 1 again
+        let (mut other, _) = tokio_tungstenite::connect_async(serve(app_state()).await.as_str()).await.unwrap();
+        assert_eq!(next(&mut other).await["seq"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_pairs_report_pool_status() {
+        use crate::config::{Commitment, SkipReason};
+        use tower::ServiceExt;
+
+        let slot = |pair: &str, dex: &str, pubkey: &str, priority| PoolSlot {
+            pair: pair.to_string(),
+            dex: Dex::from(dex),
+            pubkey: pubkey.to_string(),
+            priority,
+        };
+        let selection = PoolSelection {
+            active: vec![
+                slot("SOL-USDC", "orca", "OrcaPool", 10),
+                slot("SOL-USDC", "raydium", "RaydiumPool", 5),
+                slot("BONK-SOL", "meteora", "MeteoraPool", 1),
+            ],
+            skipped: vec![SkippedPool { pool: slot("JUP-SOL", "orca", "JupPool", 0), reason: SkipReason::OverMaxPools }],
+        };
+        let state = AppState {
+            pools: Arc::new(RwLock::new(selection)),
+            discovered_pools: Arc::new(HashSet::from(["RaydiumPool".to_string(), "JupPool".to_string()])),
+            ..app_state()
+        };
+        state.subscriptions.sync([
+            (SubscriptionKind::Account, "OrcaPool", None, Commitment::Processed, Some(1)),
+            (SubscriptionKind::Account, "MeteoraPool", None, Commitment::Processed, None),
+        ]);
+        state.subscriptions.rejected("RaydiumPool", "Invalid param (-32602)".to_string());
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(150.0, 1_000_000, 1, 500, 75_000, 0.003));
+        let mut stale = PriceData::new(151.0, 1_000_000, 1, 500, 75_000, 0.003);
+        stale.timestamp -= chrono::Duration::seconds(30);
+        state.cache.set("SOL-USDC", &Dex::Raydium, stale);
+
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let response = app.oneshot(axum::http::Request::get("/pairs").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let pairs: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let sol = &pairs["pairs"]["SOL-USDC"];
+        assert_eq!(sol.as_array().unwrap().len(), 2);
+        assert_eq!(sol[0]["pubkey"], "OrcaPool");
+        assert_eq!(sol[0]["decoder"], "orca_whirlpool");
+        assert_eq!(sol[0]["subscription"], "confirmed");
+        assert_eq!(sol[0]["discovered"], false);
+        assert_eq!(sol[0]["contributing"], true);
+        assert!(sol[0]["ms_since_update"].as_u64().unwrap() < 5_000);
+        assert!(sol[0].get("subscription_error").is_none());
+
+        assert_eq!(sol[1]["subscription"], "failed");
+        assert_eq!(sol[1]["subscription_error"], "Invalid param (-32602)");
+        assert_eq!(sol[1]["discovered"], true);
+        assert_eq!(sol[1]["contributing"], false);
+        assert!(sol[1]["ms_since_update"].as_u64().unwrap() >= 30_000);
+
+        let bonk = &pairs["pairs"]["BONK-SOL"][0];
+        assert_eq!((bonk["subscription"].as_str(), bonk["decoder"].as_str()), (Some("pending"), Some("meteora_dlmm")));
+        assert!(bonk["ms_since_update"].is_null());
+
+        assert_eq!(
+            pairs["skipped"],
+            serde_json::json!([{
+                "pair": "JUP-SOL", "dex": "orca", "pubkey": "JupPool", "priority": 0,
+                "reason": "over_max_pools", "discovered": true,
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pair_summary_endpoint() {
+        use tower::ServiceExt;
+
+        let state = app_state();
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(150.0, 1_000_000, 7, 500, 75_000, 0.003));
+        state.cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(151.0, 1_000_000, 8, 500, 75_000, 0.003));
+        let opportunity = Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("orca", 150.0, 7)
+            .sell("raydium", 151.0, 8)
+            .net_profit_percent(0.4)
+            .recommended_size(1_000)
+            .confidence(0.7)
+            .build()
+            .unwrap();
+        state.pair_summaries.record(&opportunity);
+        let app = router(&ApiConfig::default(), state).unwrap();
+        let get = |uri: &str| axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+
+        // Matched case-insensitively
+        let response = app.clone().oneshot(get("/pairs/sol-usdc/summary")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let set: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(set["best_spatial"]["id"], opportunity.id.to_string());
+        assert!(set["best_triangular"].is_null());
+        assert_eq!((set["spread"]["ask_dex"].as_str(), set["spread"]["bid_dex"].as_str()), (Some("orca"), Some("raydium")));
+        assert_eq!(set["venues"].as_array().unwrap().len(), 2);
+        assert_eq!(set["recent_opportunities"], 1);
+
+        let response = app.oneshot(get("/pairs/WIF-USDC/summary")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_client_requests_get_correlated_replies() {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = app_state();
+        let tx = state.tx.clone();
+        for (pair, net) in [("SOL-USDC", 1.0), ("BONK-SOL", 2.0), ("SOL-USDC", 3.0)] {
+            state.opportunities.record(sample_opportunity(OpportunityType::Spatial, pair, net).build().unwrap());
+        }
+        state.cache.set("SOL-USDC", &Dex::Orca, PriceData::new(150.0, 1_000_000, 1, 1, 1, 0.003));
+        let (mut client, _) = tokio_tungstenite::connect_async(serve(state).await.as_str()).await.unwrap();
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+        let request = |text: &str| WsMessage::Text(text.to_string());
+
+        client.send(request(r#"{"op":"get_history","pair":"SOL-USDC","n":1,"id":"h1"}"#)).await.unwrap();
+        let history = next(&mut client).await;
+        assert_eq!((history["type"].as_str(), history["id"].as_str()), (Some("history"), Some("h1")));
+        assert_eq!(history["data"]["total"], 2);
+        let opportunities = history["data"]["opportunities"].as_array().unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0]["net_profit_percent"], 3.0);
+
+        // Broadcasts keep flowing between requests
+        tx.send(ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 1.5, slot: 9, ts: chrono::DateTime::UNIX_EPOCH });
+        assert_eq!(next(&mut client).await["type"], "price");
+        for text in [
+            r#"{"op":"ping","id":2}"#,
+            r#"{"op":"get_snapshot","id":3}"#,
+            r#"{"op":"subscribe","types":["price"],"id":4}"#,
+            r#"{"op":"shout","id":5}"#,
+            r#"{"op":"get_history","n":"all","id":6}"#,
+            "not json",
+        ] {
+            client.send(request(text)).await.unwrap();
+        }
+
+        let pong = next(&mut client).await;
+        assert_eq!((pong["type"].as_str(), pong["id"].as_u64()), (Some("pong"), Some(2)));
+        let snapshot = next(&mut client).await;
+        assert_eq!((snapshot["type"].as_str(), snapshot["id"].as_u64()), (Some("snapshot"), Some(3)));
+        assert_eq!(snapshot["data"]["prices"]["SOL-USDC"]["orca"]["price"], 150.0);
+        assert_eq!(snapshot["data"]["opportunities"].as_array().unwrap().len(), 3);
+        let filter = next(&mut client).await;
+        assert_eq!((filter["type"].as_str(), filter["id"].as_u64()), (Some("filter"), Some(4)));
+
+        let unknown = next(&mut client).await;
+        assert_eq!((unknown["type"].as_str(), unknown["id"].as_u64()), (Some("error"), Some(5)));
+        assert_eq!(unknown["data"]["code"], "unknown_op");
+        let invalid = next(&mut client).await;
+        assert_eq!((invalid["data"]["code"].as_str(), invalid["id"].as_u64()), (Some("invalid_params"), Some(6)));
+        let garbled = next(&mut client).await;
+        assert_eq!(garbled["data"]["code"], "invalid_json");
+        assert!(garbled.get("id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_clients_are_reaped() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let heartbeat = Heartbeat { interval: Duration::from_millis(50), timeout: Duration::from_millis(100) };
+        let state = AppState { heartbeat: Some(heartbeat), ..app_state() };
+        let clients = state.ws_clients.clone();
+        let url = serve(state).await;
+
+        // Never read, so never answers the server's pings
+        let (_silent, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        let (mut live, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        live.send(WsMessage::Ping(b"hi".to_vec())).await.unwrap();
+
+        // Reading lets the client answer pings; the server answers ours
+        let mut pinged = false;
+        let mut ponged = false;
+        let until = tokio::time::Instant::now() + Duration::from_millis(500);
+        while let Ok(Some(frame)) = tokio::time::timeout_at(until, live.next()).await {
+            match frame.unwrap() {
+                WsMessage::Ping(_) => pinged = true,
+                WsMessage::Pong(payload) => ponged = payload == b"hi",
+                _ => {}
+            }
+        }
+        assert!(pinged && ponged);
+        assert_eq!(clients.reaped.load(Ordering::Relaxed), 1);
+        assert_eq!(clients.active.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clients_blocking_sends_are_reaped() {
+        // No ping falls due, so only the send timeout can reap the client
+        let heartbeat = Heartbeat { interval: Duration::from_secs(3600), timeout: Duration::from_millis(100) };
+        let state = AppState { heartbeat: Some(heartbeat), ..app_state() };
+        let (tx, clients) = (state.tx.clone(), state.ws_clients.clone());
+        let url = serve(state).await;
+        // Connected but never read, so the socket buffers fill up
+        let (_silent, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+
+        let pair = "X".repeat(1 << 20);
+        for _ in 0..200 {
+            tx.send(ApiMessage::PriceUpdate {
+                pair: pair.clone(),
+                dex: Dex::Orca,
+                price: 100.0,
+                slot: 1,
+                ts: chrono::DateTime::UNIX_EPOCH,
+            });
+            if clients.reaped.load(Ordering::Relaxed) == 1 && clients.active.load(Ordering::Relaxed) == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("client blocking sends was never reaped");
+    }
+
+    #[test]
+    fn test_msgpack_frames_match_json_frames() {
+        let messages = [
+            ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 101.25, slot: 7, ts: chrono::DateTime::UNIX_EPOCH },
+            ApiMessage::SystemMetrics(SystemMetrics { updates_per_sec: 12.5, ws_connected: true, ..SystemMetrics::default() }),
+            ApiMessage::Snapshot(Snapshot { prices: BTreeMap::new(), opportunities: Vec::new() }),
+        ];
+        let (mut json, mut msgpack) = (Framer::new(false, TimestampFormat::Rfc3339), Framer::new(false, TimestampFormat::Rfc3339));
+        msgpack.format = WireFormat::Msgpack;
+        for message in &messages {
+            let Message::Text(text) = json.ws_message(message).unwrap() else { panic!("expected a text frame") };
+            let Message::Binary(bytes) = msgpack.ws_message(message).unwrap() else { panic!("expected a binary frame") };
+            let mut from_json: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let mut from_msgpack: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+            // Sent at different instants
+            assert!(from_msgpack["ts"].is_string());
+            from_json.as_object_mut().unwrap().remove("ts");
+            from_msgpack.as_object_mut().unwrap().remove("ts");
+            assert_eq!(from_msgpack, from_json);
+        }
+        assert_eq!((json.seq, msgpack.seq), (3, 3));
+    }
+
+    #[test]
+    fn test_framer_timestamp_format() {
+        let detected_at: chrono::DateTime<chrono::Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+        let opportunity = Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 7)
+            .sell("orca", 101.0, 7)
+            .net_profit_percent(0.5)
+            .confidence(0.9)
+            .detected_at(detected_at)
+            .build()
+            .unwrap();
+        let price = ApiMessage::PriceUpdate {
+            pair: "SOL-USDC".to_string(),
+            dex: Dex::Orca,
+            price: 101.0,
+            slot: 7,
+            ts: detected_at,
+        };
+        let frame = |format, message: &ApiMessage| {
+            let Message::Text(text) = Framer::new(false, format).ws_message(message).unwrap() else {
+                panic!("expected a text frame")
+            };
+            serde_json::from_str::<serde_json::Value>(&text).unwrap()
+        };
+
+        let found = ApiMessage::OpportunityFound(opportunity);
+        assert_eq!(frame(TimestampFormat::Rfc3339, &found)["data"]["detected_at"], "2026-01-01T00:00:00Z");
+        let millis = frame(TimestampFormat::Millis, &found);
+        assert_eq!(millis["data"]["detected_at"], 1_767_225_600_000i64);
+        assert_eq!(millis["data"]["valid_until"], 1_767_225_602_000i64);
+        // `ts` fields follow the format too
+        let rfc3339 = frame(TimestampFormat::Rfc3339, &price);
+        assert_eq!(rfc3339["data"]["ts"], "2026-01-01T00:00:00Z");
+        assert!(rfc3339["ts"].is_string());
+        let millis = frame(TimestampFormat::Millis, &price);
+        assert_eq!(millis["data"]["ts"], 1_767_225_600_000i64);
+        assert!(millis["ts"].as_i64().unwrap() > 1_767_225_600_000i64);
+    }
+
+    #[tokio::test]
+    async fn test_clients_can_switch_to_msgpack() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        async fn next_msgpack(client: &mut Client) -> serde_json::Value {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("no frame");
+            match frame.unwrap().unwrap() {
+                WsMessage::Binary(bytes) => rmp_serde::from_slice(&bytes).unwrap(),
+                other => panic!("expected a binary frame, got {:?}", other),
+            }
+        }
+
+        let state = app_state();
+        let tx = state.tx.clone();
+        let url = serve(state).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url.as_str()).await.unwrap();
+        assert_eq!(next(&mut client).await["type"], "snapshot");
+        client.send(WsMessage::Text(r#"{"op":"set_format","format":"msgpack"}"#.to_string())).await.unwrap();
+        let ack = next_msgpack(&mut client).await;
+        assert_eq!((ack["type"].as_str(), ack["data"].as_str(), ack["seq"].as_u64()), (Some("format"), Some("msgpack"), Some(2)));
+        tx.send(ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 1.5, slot: 9, ts: chrono::DateTime::UNIX_EPOCH });
+        let price = next_msgpack(&mut client).await;
+        assert_eq!((price["data"]["slot"].as_u64(), price["seq"].as_u64()), (Some(9), Some(3)));
+
+        // Chosen on upgrade, and back to JSON on request
+        let (mut binary, _) = tokio_tungstenite::connect_async(format!("{}?format=msgpack", url)).await.unwrap();
+        assert_eq!(next_msgpack(&mut binary).await["type"], "snapshot");
+        binary.send(WsMessage::Text(r#"{"op":"set_format","format":"json"}"#.to_string())).await.unwrap();
+        assert_eq!(next(&mut binary).await["data"], "json");
+    }
+
+    #[tokio::test]
+    async fn test_legacy_messages_have_no_envelope() {
+        use tower::ServiceExt;
+
+        let state = AppState { legacy_messages: true, ..app_state() };
+        let tx = state.tx.clone();
+        let (mut client, _) = tokio_tungstenite::connect_async(serve(state).await.as_str()).await.unwrap();
+        let snapshot = next(&mut client).await;
+        assert_eq!(snapshot["type"], "snapshot");
+        assert!(snapshot.get("v").is_none() && snapshot.get("seq").is_none());
+        let price = ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Orca, price: 1.5, slot: 7, ts: chrono::DateTime::UNIX_EPOCH };
+        tx.send(price.clone());
+        assert_eq!(next(&mut client).await, serde_json::to_value(&price).unwrap());
+
+        let app = router(&ApiConfig::default(), app_state()).unwrap();
+        let response = app.oneshot(axum::http::Request::get("/version").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let version: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(version, serde_json::json!({ "version": env!("CARGO_PKG_VERSION"), "protocol": PROTOCOL_VERSION }));
+    }
+
+    #[tokio::test]
+    async fn test_schema_endpoint() {
+        use tower::ServiceExt;
+
+        let app = router(&ApiConfig::default(), app_state()).unwrap();
+        let response = app.oneshot(axum::http::Request::get("/schema").body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let schemas: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(schemas, api_schemas());
+    }
+
+    #[tokio::test]
+    async fn test_admin_cleanup_and_detector_reset() {
+        use crate::config::AlertsConfig;
+        use crate::detector::StatArbConfig;
+        use tower::ServiceExt;
+
+        let limits = ApiRateLimitConfig { admin_per_second: 0.0, ..ApiRateLimitConfig::default() };
+        let state = AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() };
+        let cache = state.cache.clone();
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(101.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        let mut expired = PriceData::new(99.0, 1_000_000, 1, 500_000, 500_000, 0.003);
+        expired.timestamp -= chrono::Duration::seconds(120);
+        cache.set("SOL-USDC", &Dex::Meteora, expired);
+
+        let alerts = AlertsConfig { default_threshold_bps: 10.0, ..AlertsConfig::default() };
+        let spread_alerts = Arc::new(SpreadAlertDetector::new(cache.clone(), alerts));
+        let spreads = Arc::new(SpreadTracker::new(cache.clone(), Duration::from_secs(60), 1000.0));
+        let statistical = StatisticalArbitrageDetector::new(cache.clone(), StatArbConfig::default());
+        assert!(spread_alerts.check("SOL-USDC").is_some());
+        assert!(spreads.check("SOL-USDC").is_some());
+        // Both throttled until reset
+        assert!(spread_alerts.check("SOL-USDC").is_none());
+        assert!(spreads.check("SOL-USDC").is_none());
+        state.volatility.observe("SOL-USDC", "raydium", 100.0);
+        let state = AppState {
+            detectors: DetectorHandles {
+                statistical: Some(Arc::new(RwLock::new(statistical))),
+                spread_alerts: Some(spread_alerts.clone()),
+                spreads: Some(spreads.clone()),
+                ..DetectorHandles::default()
+            },
+            ..state
+        };
+        let app = router(&admin_config(), state).unwrap();
+        let post = |uri: &str| authorized(axum::http::Request::post(uri)).body(axum::body::Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(post("/admin/cache/cleanup")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let cleanup: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(cleanup, serde_json::json!({ "removed": 1, "remaining": 2 }));
+        assert!(cache.get("SOL-USDC", &Dex::Meteora).is_none());
+
+        let response = app.oneshot(post("/admin/detectors/reset")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let reset: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // Disabled detectors are left out
+        assert_eq!(
+            reset,
+            serde_json::json!({ "cleared": { "spread_alerts": 1, "spreads": 1, "statistical": 0, "volatility": 1 } })
+        );
+        assert!(spread_alerts.check("SOL-USDC").is_some());
+        assert!(spreads.check("SOL-USDC").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_admin_log_level_swaps_the_filter() {
+        use tower::ServiceExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        // The handle only works while the subscriber holding the layer lives
+        let _subscriber = tracing_subscriber::registry().with(layer);
+        let limits = ApiRateLimitConfig { admin_per_second: 0.0, ..ApiRateLimitConfig::default() };
+        let app = router(
+            &admin_config(),
+            AppState { limits: Arc::new(ApiLimits::new(&limits)), log_filter: Some(handle.clone()), ..app_state() },
+        )
+        .unwrap();
+        let put = |body: &str| {
+            authorized(axum::http::Request::put("/admin/log-level"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(put(r#"{"filter":"warn,solana_price_monitor=trace"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let changed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(changed["previous"], "info");
+        let current = handle.with_current(|filter| filter.to_string()).unwrap();
+        assert_eq!(changed["filter"], current.as_str());
+        assert!(current.contains("solana_price_monitor=trace"));
+
+        let response = app.clone().oneshot(put(r#"{"filter":"solana_price_monitor=loud"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(handle.with_current(|filter| filter.to_string()).unwrap(), current);
+
+        // Without a handle there is nothing to swap
+        let app = router(&admin_config(), AppState { limits: Arc::new(ApiLimits::new(&limits)), ..app_state() }).unwrap();
+        let response = app.oneshot(put(r#"{"filter":"debug"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
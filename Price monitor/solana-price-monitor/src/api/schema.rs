@@ -0,0 +1,63 @@
+//! JSON Schemas of the models clients read, served on `GET /schema`
+//!
+//! The schemas are generated from the types themselves, so they can't
+//! drift from what the API sends. References point into
+//! `#/components/schemas/`, so the document drops into an OpenAPI spec's
+//! `components` as is.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use serde_json::{json, Value};
+
+use super::ApiMessage;
+use crate::models::{Opportunity, OpportunityType, PriceData};
+use crate::utils::HealthStatus;
+
+/// `{"components": {"schemas": {...}}}` with every API-facing model and the
+/// types they reference, by name
+pub fn api_schemas() -> Value {
+    let mut generator = SchemaGenerator::new(SchemaSettings::openapi3());
+    generator.subschema_for::<PriceData>();
+    generator.subschema_for::<Opportunity>();
+    generator.subschema_for::<OpportunityType>();
+    generator.subschema_for::<ApiMessage>();
+    generator.subschema_for::<HealthStatus>();
+
+    json!({ "components": { "schemas": generator.take_definitions() } })
+}
+
+/// Names of the fields a reader can count on in `name`'s schema
+pub fn required_fields(schemas: &Value, name: &str) -> Vec<String> {
+    schemas["components"]["schemas"][name]["required"]
+        .as_array()
+        .map(|fields| fields.iter().filter_map(|field| field.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas_cover_api_models() {
+        let schemas = api_schemas();
+        for name in ["PriceData", "Opportunity", "OpportunityType", "ApiMessage", "HealthStatus", "RouteLeg", "Dex"] {
+            assert!(schemas["components"]["schemas"][name].is_object(), "{name} missing");
+        }
+
+        // One documented string constant per type
+        let types: Vec<&Value> = schemas["components"]["schemas"]["OpportunityType"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["enum"][0])
+            .collect();
+        let expected: Vec<Value> = OpportunityType::ALL.iter().map(|t| json!(t.as_str())).collect();
+        assert_eq!(types, expected.iter().collect::<Vec<_>>());
+
+        // Fields added later default when absent, so readers can't count on them
+        let required = required_fields(&schemas, "PriceData");
+        assert!(required.contains(&"price".to_string()));
+        assert!(!required.contains(&"price_exact".to_string()));
+        assert!(!required.contains(&"bid".to_string()));
+    }
+}
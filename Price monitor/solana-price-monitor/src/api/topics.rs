@@ -0,0 +1,335 @@
+//! Topic fan-out of API messages
+//!
+//! Every message is published under topics derived from its content:
+//! `price.<PAIR>.<dex>`, `opportunity.<type>.<PAIR>`,
+//! `opportunity_group.<type>.<PAIR>`, `spread_alert.<PAIR>`, `spread.<PAIR>`,
+//! `system_status` and `metrics`, with pairs upper-cased and one topic per pair of a
+//! multi-pair message. Subscribers register a set of patterns and share one
+//! broadcast channel with everyone who registered the same set, so a message
+//! is copied once per distinct subscription instead of being sent to every
+//! consumer and filtered again on the other side. `subscribe()` keeps the old
+//! single-channel behavior and receives everything.
+//!
+//! Channels are indexed by pattern, so publishing looks up the few patterns
+//! that could match a message's topics rather than testing every channel.
+
+use super::ApiMessage;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Segments of a topic pattern; `*` matches any one segment, and a pattern
+/// also matches the topics below it (`opportunity` matches
+/// `opportunity.spatial.SOL-USDC`)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TopicPattern(Vec<String>);
+
+impl TopicPattern {
+    pub fn matches(&self, topic: &str) -> bool {
+        let mut segments = topic.split('.');
+        self.0.iter().all(|pattern| segments.next().is_some_and(|segment| pattern == "*" || pattern == segment))
+    }
+
+    /// Index key, the dotted pattern
+    fn key(&self) -> String {
+        self.0.join(".")
+    }
+
+    /// Every message of one `type`
+    pub fn kind(kind: &str) -> Self {
+        Self(vec![kind.to_string()])
+    }
+
+    /// Messages of `kind` about `pair`; kinds without a pair match whole
+    pub fn for_pair(kind: &str, pair: &str) -> Self {
+        let pair = pair.to_ascii_uppercase();
+        let segments = match kind {
+            "price" | "spread_alert" | "spread" | "pair_summary" => vec![kind.to_string(), pair],
+            "opportunity" | "opportunity_group" => vec![kind.to_string(), "*".to_string(), pair],
+            _ => vec![kind.to_string()],
+        };
+        Self(segments)
+    }
+}
+
+impl FromStr for TopicPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let segments: Vec<String> = s.split('.').map(str::to_string).collect();
+        if segments.iter().any(String::is_empty) {
+            anyhow::bail!("topic pattern \"{}\" has an empty segment", s);
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl ApiMessage {
+    /// Topics the message is published under
+    pub fn topics(&self) -> Vec<String> {
+        let kind = self.kind();
+        let opportunity = |o: &crate::models::Opportunity| {
+            format!("{}.{}.{}", kind, o.opportunity_type, o.token_pair.to_ascii_uppercase())
+        };
+        match self {
+            ApiMessage::PriceUpdate { pair, dex, .. } => {
+                vec![format!("{}.{}.{}", kind, pair.to_ascii_uppercase(), dex)]
+            }
+            ApiMessage::OpportunityFound(o) => vec![opportunity(o)],
+            ApiMessage::OpportunityGroup(group) => {
+                let mut topics: Vec<String> = group.strategies.iter().map(opportunity).collect();
+                topics.sort();
+                topics.dedup();
+                topics
+            }
+            ApiMessage::SpreadAlert(alert) => vec![format!("{}.{}", kind, alert.pair.to_ascii_uppercase())],
+            ApiMessage::Spread(spread) => vec![format!("{}.{}", kind, spread.pair.to_ascii_uppercase())],
+            ApiMessage::PairSummary(set) => vec![format!("{}.{}", kind, set.pair.to_ascii_uppercase())],
+            ApiMessage::Snapshot(_) | ApiMessage::SystemStatus { .. } | ApiMessage::SystemMetrics(_) => {
+                vec![kind.to_string()]
+            }
+        }
+    }
+}
+
+/// Keys of every pattern that matches `topic`: each prefix of its segments,
+/// with any of them replaced by `*`
+///
+/// Topics have at most three segments, so this is at most 14 keys.
+fn pattern_keys(topic: &str, keys: &mut Vec<String>) {
+    let segments: Vec<&str> = topic.split('.').collect();
+    for len in 1..=segments.len() {
+        for wildcards in 0u32..(1 << len) {
+            let key: Vec<&str> = segments[..len]
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| if wildcards & (1 << i) != 0 { "*" } else { segment })
+                .collect();
+            keys.push(key.join("."));
+        }
+    }
+}
+
+/// Subscribers with the same patterns
+struct TopicChannel {
+    /// Sorted and deduplicated
+    patterns: Vec<TopicPattern>,
+    tx: broadcast::Sender<ApiMessage>,
+}
+
+#[derive(Default)]
+struct Channels {
+    by_id: HashMap<u64, TopicChannel>,
+    /// Ids of the channels subscribed to each pattern, by pattern key
+    by_pattern: HashMap<String, Vec<u64>>,
+    next_id: u64,
+}
+
+impl Channels {
+    fn insert(&mut self, channel: TopicChannel) {
+        let id = self.next_id;
+        self.next_id += 1;
+        for pattern in &channel.patterns {
+            self.by_pattern.entry(pattern.key()).or_default().push(id);
+        }
+        self.by_id.insert(id, channel);
+    }
+
+    /// Drop channels whose receivers are all gone
+    fn prune(&mut self) {
+        let by_pattern = &mut self.by_pattern;
+        self.by_id.retain(|id, channel| {
+            if channel.tx.receiver_count() > 0 {
+                return true;
+            }
+            for pattern in &channel.patterns {
+                let key = pattern.key();
+                if let Some(ids) = by_pattern.get_mut(&key) {
+                    ids.retain(|other| other != id);
+                    if ids.is_empty() {
+                        by_pattern.remove(&key);
+                    }
+                }
+            }
+            false
+        });
+    }
+
+    /// Channels with a pattern matching any of `topics`, each once
+    fn matching(&self, topics: &[String]) -> Vec<&TopicChannel> {
+        let mut keys = Vec::new();
+        for topic in topics {
+            pattern_keys(topic, &mut keys);
+        }
+        let mut ids: Vec<u64> = keys.iter().filter_map(|key| self.by_pattern.get(key)).flatten().copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.iter().filter_map(|id| self.by_id.get(id)).collect()
+    }
+}
+
+struct Shared {
+    /// Every message, for `subscribe()`
+    all: broadcast::Sender<ApiMessage>,
+    capacity: usize,
+    channels: RwLock<Channels>,
+}
+
+/// Publish side of the API broadcast, shared by every producer
+#[derive(Clone)]
+pub struct Topics {
+    shared: Arc<Shared>,
+}
+
+impl Topics {
+    /// Each subscription buffers `capacity` messages before its receivers lag
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                all: broadcast::channel(capacity).0,
+                capacity,
+                channels: RwLock::default(),
+            }),
+        }
+    }
+
+    /// Publish to every subscription whose patterns match; returns how many
+    /// receivers got the message (0 when nobody is listening)
+    pub fn send(&self, message: ApiMessage) -> usize {
+        let mut delivered = 0;
+        {
+            let channels = self.shared.channels.read().unwrap();
+            if !channels.by_id.is_empty() {
+                for channel in channels.matching(&message.topics()) {
+                    delivered += channel.tx.send(message.clone()).unwrap_or(0);
+                }
+            }
+        }
+        delivered + self.shared.all.send(message).unwrap_or(0)
+    }
+
+    /// Every message, whatever its topic
+    pub fn subscribe(&self) -> broadcast::Receiver<ApiMessage> {
+        self.shared.all.subscribe()
+    }
+
+    /// Messages published under a topic matching any of `patterns`
+    pub fn subscribe_to(&self, patterns: impl IntoIterator<Item = TopicPattern>) -> broadcast::Receiver<ApiMessage> {
+        let mut patterns: Vec<TopicPattern> = patterns.into_iter().collect();
+        patterns.sort();
+        patterns.dedup();
+        let mut channels = self.shared.channels.write().unwrap();
+        channels.prune();
+        if let Some(channel) = channels.by_id.values().find(|channel| channel.patterns == patterns) {
+            return channel.tx.subscribe();
+        }
+        let (tx, rx) = broadcast::channel(self.shared.capacity);
+        channels.insert(TopicChannel { patterns, tx });
+        rx
+    }
+
+    /// Distinct pattern subscriptions messages are fanned out to
+    pub fn subscriptions(&self) -> usize {
+        let channels = self.shared.channels.read().unwrap();
+        channels.by_id.values().filter(|channel| channel.tx.receiver_count() > 0).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dex, Opportunity, OpportunityType};
+
+    fn price(pair: &str, dex: &str) -> ApiMessage {
+        ApiMessage::PriceUpdate { pair: pair.to_string(), dex: Dex::from(dex), price: 1.0, slot: 1, ts: chrono::DateTime::UNIX_EPOCH }
+    }
+
+    fn opportunity(opportunity_type: OpportunityType, pair: &str) -> ApiMessage {
+        ApiMessage::OpportunityFound(
+            Opportunity::builder()
+                .opportunity_type(opportunity_type)
+                .token_pair(pair)
+                .buy("orca", 100.0, 0)
+                .sell("raydium", 101.0, 0)
+                .net_profit_percent(0.5)
+                .recommended_size(1)
+                .confidence(0.5)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn pattern(s: &str) -> TopicPattern {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_patterns_match_segments_and_subtopics() {
+        assert_eq!(price("sol-usdc", "raydium").topics(), vec!["price.SOL-USDC.raydium"]);
+        assert_eq!(opportunity(OpportunityType::Spatial, "SOL-USDC").topics(), vec!["opportunity.spatial.SOL-USDC"]);
+
+        assert!(pattern("opportunity.*").matches("opportunity.spatial.SOL-USDC"));
+        assert!(pattern("opportunity").matches("opportunity.spatial.SOL-USDC"));
+        assert!(!pattern("opportunity").matches("opportunity_group.spatial.SOL-USDC"));
+        assert!(pattern("price.*.orca").matches("price.SOL-USDC.orca"));
+        assert!(!pattern("price.*.orca").matches("price.SOL-USDC.raydium"));
+        assert!(!pattern("price.SOL-USDC.orca.extra").matches("price.SOL-USDC.orca"));
+        assert!(TopicPattern::for_pair("opportunity", "sol-usdc").matches("opportunity.triangular.SOL-USDC"));
+        assert!("price..orca".parse::<TopicPattern>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_only_get_their_topics() {
+        let topics = Topics::new(16);
+        let mut opportunities = topics.subscribe_to([pattern("opportunity.*")]);
+        let mut orca = topics.subscribe_to([pattern("price.*.orca")]);
+        let mut everything = topics.subscribe();
+        // Same patterns in another order: the same channel
+        let mut shared = topics.subscribe_to([pattern("price.*.orca"), pattern("price.*.orca")]);
+        assert_eq!(topics.subscriptions(), 2);
+
+        assert_eq!(topics.send(price("SOL-USDC", "raydium")), 1);
+        assert_eq!(topics.send(price("SOL-USDC", "orca")), 3);
+        assert_eq!(topics.send(opportunity(OpportunityType::Spatial, "SOL-USDC")), 2);
+
+        assert_eq!(opportunities.recv().await.unwrap().kind(), "opportunity");
+        assert!(opportunities.try_recv().is_err(), "no price messages for opportunity.*");
+        for rx in [&mut orca, &mut shared] {
+            assert!(matches!(rx.recv().await.unwrap(), ApiMessage::PriceUpdate { dex, .. } if dex == "orca"));
+            assert!(rx.try_recv().is_err());
+        }
+        let kinds: Vec<_> = (0..3).map(|_| everything.try_recv().unwrap().kind()).collect();
+        assert_eq!(kinds, vec!["price", "price", "opportunity"]);
+
+        // Dropped subscriptions stop being fanned out to
+        drop((orca, shared));
+        let _ = topics.subscribe_to([pattern("metrics")]);
+        assert_eq!(topics.subscriptions(), 1);
+        drop((opportunities, everything));
+        assert_eq!(topics.send(price("SOL-USDC", "orca")), 0);
+    }
+
+    #[test]
+    fn test_index_finds_every_matching_pattern_once() {
+        let topics = Topics::new(16);
+        // Both patterns match: one copy
+        let mut overlapping = topics.subscribe_to([pattern("price"), pattern("price.*.orca")]);
+        let mut exact = topics.subscribe_to([pattern("price.SOL-USDC.orca")]);
+        let mut wildcard_pair = topics.subscribe_to([pattern("*.SOL-USDC")]);
+        let mut too_long = topics.subscribe_to([pattern("price.SOL-USDC.orca.extra")]);
+        let mut other_pair = topics.subscribe_to([pattern("price.BONK-SOL")]);
+
+        assert_eq!(topics.send(price("sol-usdc", "orca")), 3);
+        assert!(overlapping.try_recv().is_ok() && overlapping.try_recv().is_err());
+        assert!(exact.try_recv().is_ok());
+        assert!(wildcard_pair.try_recv().is_ok());
+        assert!(too_long.try_recv().is_err() && other_pair.try_recv().is_err());
+
+        let mut keys = Vec::new();
+        pattern_keys("price.SOL-USDC.orca", &mut keys);
+        assert_eq!(keys.len(), 14);
+        assert!(keys.contains(&"*.SOL-USDC.*".to_string()) && keys.contains(&"price".to_string()));
+    }
+}
@@ -2,23 +2,173 @@
 //!
 //! Uses DashMap for lock-free concurrent access (faster than RwLock<HashMap>)
 
-use crate::models::PriceData;
+use crate::models::{Dex, PriceData};
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
+/// Capacity of the cache update event channel
+const EVENT_CAPACITY: usize = 4096;
+
+/// Notification emitted on every cache write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheEvent {
+    Updated { pair: String, dex: Dex },
+}
+
+/// Staleness thresholds with per-DEX and per-(pair, DEX) overrides
+///
+/// Lookup order: pair + DEX, then DEX, then the default. Pair keys match
+/// ignoring case and '-' / '_' separators; DEX names are normalized `Dex`es.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaleThresholds {
+    default_ms: u64,
+    by_dex: HashMap<Dex, u64>,
+    by_pair_dex: HashMap<(String, Dex), u64>,
+}
+
+impl StaleThresholds {
+    /// Same threshold for every pair and DEX
+    pub fn new(default_ms: u64) -> Self {
+        Self {
+            default_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Build from `[monitoring.stale_threshold_overrides]`
+    ///
+    /// Keys are either a DEX name ("meteora") or "PAIR:dex"
+    /// ("SOL-USDC:meteora").
+    pub fn from_overrides(default_ms: u64, overrides: &HashMap<String, u64>) -> Self {
+        let mut thresholds = Self::new(default_ms);
+        for (key, &ms) in overrides {
+            match key.split_once(':') {
+                Some((pair, dex)) => {
+                    thresholds.by_pair_dex.insert((normalize_pair(pair), Dex::from(dex)), ms);
+                }
+                None => {
+                    thresholds.by_dex.insert(Dex::from(key.as_str()), ms);
+                }
+            }
+        }
+        thresholds
+    }
+
+    /// Threshold in milliseconds for a DEX, ignoring pair overrides
+    pub fn dex_threshold_ms(&self, dex: &Dex) -> u64 {
+        self.by_dex.get(dex).copied().unwrap_or(self.default_ms)
+    }
+
+    /// Threshold in milliseconds for a pair on a DEX
+    pub fn threshold_ms(&self, pair: &str, dex: &Dex) -> u64 {
+        if !self.by_pair_dex.is_empty() {
+            if let Some(&ms) = self.by_pair_dex.get(&(normalize_pair(pair), dex.clone())) {
+                return ms;
+            }
+        }
+        self.dex_threshold_ms(dex)
+    }
+}
+
+fn normalize_pair(pair: &str) -> String {
+    pair.replace('_', "-").to_uppercase()
+}
+
+/// Latest chain slot reported by `slotSubscribe`
+///
+/// Cloning shares the same counters. Also tracks how far account updates
+/// trail the chain ("update lag", in slots).
+#[derive(Debug, Clone, Default)]
+pub struct CurrentSlot {
+    inner: Arc<SlotCounters>,
+}
+
+#[derive(Debug, Default)]
+struct SlotCounters {
+    slot: AtomicU64,
+    updated_ms: AtomicU64,
+    last_lag: AtomicU64,
+    lag_sum: AtomicU64,
+    lag_samples: AtomicU64,
+}
+
+/// Account update lag, in slots behind the chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotLagMetrics {
+    pub last: u64,
+    pub mean: u64,
+    pub samples: u64,
+}
+
+impl CurrentSlot {
+    /// Current slot, or `None` before the first slot notification
+    pub fn get(&self) -> Option<u64> {
+        match self.inner.slot.load(Ordering::Acquire) {
+            0 => None,
+            slot => Some(slot),
+        }
+    }
+
+    /// Local time (ms) the current slot was observed
+    pub fn updated_ms(&self) -> u64 {
+        self.inner.updated_ms.load(Ordering::Acquire)
+    }
+
+    /// Record a slot notification; older slots arriving late are ignored
+    pub fn advance(&self, slot: u64, timestamp_ms: u64) {
+        if self.inner.slot.fetch_max(slot, Ordering::AcqRel) < slot {
+            self.inner.updated_ms.store(timestamp_ms, Ordering::Release);
+        }
+    }
+
+    /// Record the lag of an account update at `update_slot`
+    ///
+    /// Returns `None` while the chain slot is unknown.
+    pub fn observe_update(&self, update_slot: u64) -> Option<u64> {
+        let lag = self.get()?.saturating_sub(update_slot);
+        self.inner.last_lag.store(lag, Ordering::Relaxed);
+        self.inner.lag_sum.fetch_add(lag, Ordering::Relaxed);
+        self.inner.lag_samples.fetch_add(1, Ordering::Relaxed);
+        Some(lag)
+    }
+
+    pub fn lag_metrics(&self) -> SlotLagMetrics {
+        let samples = self.inner.lag_samples.load(Ordering::Relaxed);
+        SlotLagMetrics {
+            last: self.inner.last_lag.load(Ordering::Relaxed),
+            mean: self.inner.lag_sum.load(Ordering::Relaxed).checked_div(samples).unwrap_or(0),
+            samples,
+        }
+    }
+}
+
 /// Thread-safe price cache with automatic cleanup
 /// 
 /// Uses DashMap for lock-free concurrent access, providing ~15% better
 /// performance under high contention compared to RwLock<HashMap>.
 pub struct PriceCache {
     /// Inner data: Map<TokenPair, Map<DEX, PriceData>>
-    data: Arc<DashMap<String, DashMap<String, PriceData>>>,
+    data: Arc<DashMap<String, DashMap<Dex, PriceData>>>,
     /// Time-to-live for cache entries in milliseconds
     ttl_ms: u64,
-    /// Staleness threshold in milliseconds
-    stale_threshold_ms: u64,
+    /// Staleness thresholds (adjustable at runtime)
+    stale_thresholds: Arc<RwLock<StaleThresholds>>,
+    /// Update notifications for the scan scheduler
+    events: broadcast::Sender<CacheEvent>,
+    /// Chain slot, advanced by the WebSocket slot subscription
+    current_slot: CurrentSlot,
+    /// Prices written since startup
+    writes: Arc<AtomicU64>,
+    /// Pool pubkey -> (pair, DEX) of every monitored pool
+    pools: Arc<DashMap<String, (String, Dex)>>,
 }
 
 impl PriceCache {
@@ -27,17 +177,37 @@ impl PriceCache {
         Self {
             data: Arc::new(DashMap::new()),
             ttl_ms: ttl_seconds * 1000,
-            stale_threshold_ms,
+            stale_thresholds: Arc::new(RwLock::new(StaleThresholds::new(stale_threshold_ms))),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            current_slot: CurrentSlot::default(),
+            writes: Arc::new(AtomicU64::new(0)),
+            pools: Arc::new(DashMap::new()),
         }
     }
 
+    /// Chain slot shared with the WebSocket manager and detectors
+    pub fn current_slot(&self) -> &CurrentSlot {
+        &self.current_slot
+    }
+
+    /// Replace the default threshold with per-DEX / per-pair overrides
+    pub fn with_stale_thresholds(self, thresholds: StaleThresholds) -> Self {
+        self.set_stale_thresholds(thresholds);
+        self
+    }
+
+    /// Subscribe to cache update events
+    pub fn subscribe(&self) -> broadcast::Receiver<CacheEvent> {
+        self.events.subscribe()
+    }
+
     /// Get price for a specific pair and DEX (lock-free, sync)
-    pub fn get(&self, pair: &str, dex: &str) -> Option<PriceData> {
+    pub fn get(&self, pair: &str, dex: &Dex) -> Option<PriceData> {
         self.data.get(pair)?.get(dex).map(|e| e.clone())
     }
 
     /// Get all DEX prices for a token pair (lock-free, sync)
-    pub fn get_all_dexes(&self, pair: &str) -> Vec<(String, PriceData)> {
+    pub fn get_all_dexes(&self, pair: &str) -> Vec<(Dex, PriceData)> {
         self.data
             .get(pair)
             .map(|inner| {
@@ -49,35 +219,122 @@ impl PriceCache {
             .unwrap_or_default()
     }
 
+    /// Fresh, positive prices of a pair, cheapest first
+    ///
+    /// The first entry is the best venue to buy on and the last the best to
+    /// sell on.
+    pub fn fresh_prices(&self, pair: &str) -> Vec<(Dex, PriceData)> {
+        let mut prices: Vec<_> = self
+            .get_all_dexes(pair)
+            .into_iter()
+            .filter(|(dex, data)| data.price > 0.0 && !self.is_stale(pair, dex, data))
+            .collect();
+        prices.sort_by(|a, b| a.1.price.total_cmp(&b.1.price).then_with(|| a.0.cmp(&b.0)));
+        prices
+    }
+
     /// Update price for a pair/DEX combination (lock-free, sync)
-    pub fn set(&self, pair: &str, dex: &str, price_data: PriceData) {
+    pub fn set(&self, pair: &str, dex: &Dex, price_data: PriceData) {
         self.data
             .entry(pair.to_string())
-            .or_insert_with(DashMap::new)
-            .insert(dex.to_string(), price_data);
+            .or_default()
+            .insert(dex.clone(), price_data);
+        self.record_write(pair, dex);
+    }
+
+    /// Count a write and tell the scan scheduler about it
+    fn record_write(&self, pair: &str, dex: &Dex) {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+
+        // No subscribers is fine (e.g. tests or scanning disabled)
+        let _ = self.events.send(CacheEvent::Updated {
+            pair: pair.to_string(),
+            dex: dex.clone(),
+        });
+
+        debug!(pair = pair, dex = %dex, "Price cache updated");
+    }
+
+    /// Store an update from a dual-commitment feed, returning whether it was kept
+    ///
+    /// Updates never replace a newer slot, and an unconfirmed update never
+    /// replaces a confirmed one for the same slot. A confirmed update for the
+    /// cached slot therefore flips that price to confirmed.
+    ///
+    /// The check and the write happen under the entry's lock, so the
+    /// processed and confirmed streams can't interleave between them.
+    pub fn set_if_newer(&self, pair: &str, dex: &Dex, price_data: PriceData) -> bool {
+        {
+            let prices = self.data.entry(pair.to_string()).or_default();
+            match prices.entry(dex.clone()) {
+                Entry::Occupied(mut current) => {
+                    let current_data = current.get();
+                    let older = price_data.slot < current_data.slot;
+                    let downgrade =
+                        price_data.slot == current_data.slot && current_data.confirmed && !price_data.confirmed;
+                    if older || downgrade {
+                        return false;
+                    }
+                    current.insert(price_data);
+                }
+                Entry::Vacant(vacant) => {
+                    vacant.insert(price_data);
+                }
+            };
+        }
+        self.record_write(pair, dex);
+        true
+    }
 
-        debug!(pair = pair, dex = dex, "Price cache updated");
+    /// Prices written since startup
+    pub fn write_count(&self) -> u64 {
+        self.writes.load(Ordering::Relaxed)
     }
 
     /// Async wrapper for update (for compatibility with existing code)
-    pub async fn update(&self, pair: &str, dex: &str, price_data: PriceData) {
+    pub async fn update(&self, pair: &str, dex: &Dex, price_data: PriceData) {
         self.set(pair, dex, price_data);
     }
 
-    /// Check if data is stale
-    pub fn is_stale(&self, data: &PriceData) -> bool {
-        data.is_stale(self.stale_threshold_ms)
+    /// Check if a pair's data on a DEX is stale
+    pub fn is_stale(&self, pair: &str, dex: &Dex, data: &PriceData) -> bool {
+        data.is_stale(self.stale_threshold_ms(pair, dex))
+    }
+
+    /// Effective staleness threshold for a pair on a DEX
+    pub fn stale_threshold_ms(&self, pair: &str, dex: &Dex) -> u64 {
+        self.stale_thresholds
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .threshold_ms(pair, dex)
+    }
+
+    /// The thresholds in use, shared so others judge staleness the same way
+    /// through config reloads
+    pub fn stale_thresholds(&self) -> Arc<RwLock<StaleThresholds>> {
+        self.stale_thresholds.clone()
+    }
+
+    /// Change the staleness thresholds (e.g. after a config reload)
+    pub fn set_stale_thresholds(&self, thresholds: StaleThresholds) {
+        *self.stale_thresholds.write().unwrap_or_else(|e| e.into_inner()) = thresholds;
     }
 
-    /// Remove stale entries from cache (lock-free, sync)
-    pub fn cleanup_stale_entries(&self) {
+    /// Remove expired entries from cache (lock-free, sync)
+    ///
+    /// Entries live for the TTL, or longer when their staleness override
+    /// exceeds it, so a slow DEX isn't evicted while still considered fresh.
+    /// Returns how many entries were removed.
+    pub fn cleanup_stale_entries(&self) -> usize {
         let mut removed = 0;
+        let thresholds = self.stale_thresholds.read().unwrap_or_else(|e| e.into_inner()).clone();
 
         // Iterate over all pairs
-        self.data.retain(|_, inner_map| {
-            // Remove stale entries from each pair's DEX map
-            inner_map.retain(|_, price_data| {
-                let keep = !price_data.is_stale(self.ttl_ms);
+        self.data.retain(|pair, inner_map| {
+            // Remove expired entries from each pair's DEX map
+            inner_map.retain(|dex, price_data| {
+                let ttl_ms = self.ttl_ms.max(thresholds.threshold_ms(pair, dex));
+                let keep = !price_data.is_stale(ttl_ms);
                 if !keep {
                     removed += 1;
                 }
@@ -90,6 +347,7 @@ impl PriceCache {
         if removed > 0 {
             info!(removed = removed, "Cleaned up stale cache entries");
         }
+        removed
     }
 
     /// Get total number of cached prices (lock-free, sync)
@@ -107,15 +365,72 @@ impl PriceCache {
         self.len() == 0
     }
 
-    /// Spawn background cleanup task
-    pub fn spawn_cleanup_task(cache: Arc<Self>, interval: Duration) {
+    /// Spawn background cleanup task, running until `shutdown` fires
+    pub fn spawn_cleanup_task(
+        cache: Arc<Self>,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
         tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(interval);
-            loop {
-                ticker.tick().await;
-                cache.cleanup_stale_entries();
+            shutdown
+                .run_until_cancelled(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    loop {
+                        ticker.tick().await;
+                        cache.cleanup_stale_entries();
+                    }
+                })
+                .await;
+        })
+    }
+
+    /// Replace the pool index (pubkey -> (pair, DEX))
+    pub fn set_pools(&self, pools: HashMap<String, (String, Dex)>) {
+        self.pools.retain(|pubkey, _| pools.contains_key(pubkey));
+        for (pubkey, pool) in pools {
+            self.pools.insert(pubkey, pool);
+        }
+    }
+
+    /// Add a pool to the index; false if the pubkey is already there
+    pub fn register_pool(&self, pubkey: &str, pair: &str, dex: &Dex) -> bool {
+        match self.pools.entry(pubkey.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => false,
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert((pair.to_string(), dex.clone()));
+                true
             }
-        });
+        }
+    }
+
+    /// Drop a pool from the index, returning its (pair, DEX)
+    ///
+    /// The cached price for that pair and DEX goes with it unless another
+    /// indexed pool still feeds it.
+    pub fn unregister_pool(&self, pubkey: &str) -> Option<(String, Dex)> {
+        let (_, (pair, dex)) = self.pools.remove(pubkey)?;
+        let shared = self.pools.iter().any(|entry| *entry.value() == (pair.clone(), dex.clone()));
+        if !shared {
+            self.invalidate(&pair, &dex);
+        }
+        Some((pair, dex))
+    }
+
+    /// (pair, DEX) of an indexed pool
+    pub fn pool(&self, pubkey: &str) -> Option<(String, Dex)> {
+        self.pools.get(pubkey).map(|entry| entry.value().clone())
+    }
+
+    /// Number of indexed pools
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Remove the cached price for a pair/DEX; false if there was none
+    pub fn invalidate(&self, pair: &str, dex: &Dex) -> bool {
+        let removed = self.data.get(pair).is_some_and(|inner| inner.remove(dex).is_some());
+        self.data.remove_if(pair, |_, inner| inner.is_empty());
+        removed
     }
 
     /// Get all pairs currently in cache
@@ -129,7 +444,11 @@ impl Clone for PriceCache {
         Self {
             data: Arc::clone(&self.data),
             ttl_ms: self.ttl_ms,
-            stale_threshold_ms: self.stale_threshold_ms,
+            stale_thresholds: self.stale_thresholds.clone(),
+            events: self.events.clone(),
+            current_slot: self.current_slot.clone(),
+            writes: self.writes.clone(),
+            pools: self.pools.clone(),
         }
     }
 }
@@ -143,43 +462,152 @@ mod tests {
         let cache = PriceCache::new(60, 2000);
 
         let price = PriceData::new(100.0, 1_000_000, 12345, 500_000, 500_000, 0.003);
-        cache.set("SOL-USDC", "raydium", price.clone());
+        cache.set("SOL-USDC", &Dex::Raydium, price.clone());
 
-        let retrieved = cache.get("SOL-USDC", "raydium");
+        let retrieved = cache.get("SOL-USDC", &Dex::Raydium);
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().price, 100.0);
     }
 
+    #[test]
+    fn test_confirmed_update_confirms_processed_price() {
+        let cache = PriceCache::new(60, 2000);
+        let update = |price, slot, confirmed| PriceData {
+            confirmed,
+            ..PriceData::new(price, 1_000_000, slot, 500_000, 500_000, 0.003)
+        };
+
+        assert!(cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(100.0, 10, false)));
+        assert!(!cache.get("SOL-USDC", &Dex::Raydium).unwrap().confirmed);
+
+        // A confirmation for an older slot doesn't replace the newer price
+        assert!(!cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(99.0, 9, true)));
+        assert_eq!(cache.get("SOL-USDC", &Dex::Raydium).unwrap().price, 100.0);
+
+        assert!(cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(100.0, 10, true)));
+        assert!(cache.get("SOL-USDC", &Dex::Raydium).unwrap().confirmed);
+
+        // A late processed update for the same slot can't unconfirm it
+        assert!(!cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(100.0, 10, false)));
+        assert!(cache.get("SOL-USDC", &Dex::Raydium).unwrap().confirmed);
+
+        assert!(cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(101.0, 11, false)));
+        assert!(!cache.get("SOL-USDC", &Dex::Raydium).unwrap().confirmed);
+    }
+
+    #[test]
+    fn test_concurrent_streams_never_regress_the_slot() {
+        let cache = PriceCache::new(60, 2000);
+        let update = |slot, confirmed| PriceData {
+            confirmed,
+            ..PriceData::new(100.0, 1_000_000, slot, 500_000, 500_000, 0.003)
+        };
+
+        // Processed and confirmed streams racing over the same slots
+        std::thread::scope(|scope| {
+            for confirmed in [false, true] {
+                let cache = &cache;
+                scope.spawn(move || {
+                    for slot in 1..=2_000 {
+                        cache.set_if_newer("SOL-USDC", &Dex::Raydium, update(slot, confirmed));
+                    }
+                });
+            }
+        });
+
+        let last = cache.get("SOL-USDC", &Dex::Raydium).unwrap();
+        assert_eq!(last.slot, 2_000);
+        assert!(last.confirmed);
+    }
+
     #[test]
     fn test_get_all_dexes() {
         let cache = PriceCache::new(60, 2000);
 
-        cache.set("SOL-USDC", "raydium", PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
-        cache.set("SOL-USDC", "orca", PriceData::new(100.5, 800_000, 1, 400_000, 400_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.5, 800_000, 1, 400_000, 400_000, 0.003));
 
         let all = cache.get_all_dexes("SOL-USDC");
         assert_eq!(all.len(), 2);
     }
 
+    #[test]
+    fn test_dex_names_are_normalized() {
+        let cache = PriceCache::new(60, 2000);
+        cache.set("SOL-USDC", &Dex::from("Raydium "), PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(101.0, 1_000_000, 2, 500_000, 500_000, 0.003));
+
+        // One venue, not two
+        let dexes = cache.get_all_dexes("SOL-USDC");
+        assert_eq!(dexes.len(), 1);
+        assert_eq!((dexes[0].0.as_str(), dexes[0].1.price), ("raydium", 101.0));
+        assert_eq!(cache.get("SOL-USDC", &Dex::from("RAYDIUM")).unwrap().slot, 2);
+        assert!(cache.invalidate("SOL-USDC", &Dex::from(" raydium")));
+    }
+
     #[test]
     fn test_cache_len() {
         let cache = PriceCache::new(60, 2000);
 
-        cache.set("SOL-USDC", "raydium", PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
-        cache.set("SOL-USDC", "orca", PriceData::new(100.5, 800_000, 1, 400_000, 400_000, 0.003));
-        cache.set("SOL-USDT", "raydium", PriceData::new(99.9, 900_000, 1, 450_000, 450_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.5, 800_000, 1, 400_000, 400_000, 0.003));
+        cache.set("SOL-USDT", &Dex::Raydium, PriceData::new(99.9, 900_000, 1, 450_000, 450_000, 0.003));
 
         assert_eq!(cache.len(), 3);
     }
 
+    #[test]
+    fn test_per_dex_stale_threshold_override() {
+        let overrides = HashMap::from([("meteora".to_string(), 5000)]);
+        let cache = PriceCache::new(60, 2000).with_stale_thresholds(StaleThresholds::from_overrides(2000, &overrides));
+
+        let mut aged = PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003);
+        aged.timestamp = chrono::Utc::now() - chrono::Duration::seconds(3);
+
+        assert!(!cache.is_stale("SOL-USDC", &Dex::Meteora, &aged));
+        assert!(cache.is_stale("SOL-USDC", &Dex::Raydium, &aged));
+    }
+
+    #[test]
+    fn test_pair_override_beats_dex_override() {
+        let overrides = HashMap::from([
+            ("meteora".to_string(), 5000),
+            ("sol_usdc:Meteora".to_string(), 1000),
+        ]);
+        let thresholds = StaleThresholds::from_overrides(2000, &overrides);
+
+        assert_eq!(thresholds.threshold_ms("SOL-USDC", &Dex::Meteora), 1000);
+        assert_eq!(thresholds.threshold_ms("JUP-USDC", &Dex::Meteora), 5000);
+        assert_eq!(thresholds.threshold_ms("JUP-USDC", &Dex::Orca), 2000);
+    }
+
     #[test]
     fn test_get_all_pairs() {
         let cache = PriceCache::new(60, 2000);
 
-        cache.set("SOL-USDC", "raydium", PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
-        cache.set("SOL-USDT", "raydium", PriceData::new(99.9, 900_000, 1, 450_000, 450_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDT", &Dex::Raydium, PriceData::new(99.9, 900_000, 1, 450_000, 450_000, 0.003));
 
         let pairs = cache.get_all_pairs();
         assert_eq!(pairs.len(), 2);
     }
+
+    #[test]
+    fn test_unregister_pool_invalidates_unshared_price() {
+        let cache = PriceCache::new(60, 2000);
+        assert!(cache.register_pool("PoolA", "SOL-USDC", &Dex::Raydium));
+        assert!(cache.register_pool("PoolB", "SOL-USDC", &Dex::Raydium));
+        assert!(!cache.register_pool("PoolA", "SOL-USDC", &Dex::Orca));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+
+        // PoolB still feeds the price
+        assert_eq!(cache.unregister_pool("PoolA"), Some(("SOL-USDC".to_string(), Dex::Raydium)));
+        assert!(cache.get("SOL-USDC", &Dex::Raydium).is_some());
+
+        cache.unregister_pool("PoolB");
+        assert!(cache.get("SOL-USDC", &Dex::Raydium).is_none());
+        assert!(cache.get_all_pairs().is_empty());
+        assert_eq!(cache.pool_count(), 0);
+        assert_eq!(cache.unregister_pool("PoolB"), None);
+    }
 }
@@ -1,5 +1,8 @@
 //! AMM and CLMM price calculation functions
 
+#[cfg(feature = "precise-math")]
+use rust_decimal::{Decimal, MathematicalOps};
+
 /// Calculate spot price for constant product AMM (x * y = k)
 ///
 /// # Arguments
@@ -26,6 +29,34 @@ pub fn calculate_amm_price(
     adj_reserve_out / adj_reserve_in
 }
 
+/// `calculate_amm_price` in decimal arithmetic, without the f64 rounding
+/// of large reserves
+///
+/// # Returns
+/// Normalized price (output per input), `None` for an empty pool or if the
+/// result overflows a `Decimal`
+#[cfg(feature = "precise-math")]
+pub fn calculate_amm_price_exact(
+    reserve_in: u64,
+    reserve_out: u64,
+    decimals_in: u8,
+    decimals_out: u8,
+) -> Option<Decimal> {
+    if reserve_in == 0 {
+        return None;
+    }
+
+    let ratio = Decimal::from(reserve_out).checked_div(Decimal::from(reserve_in))?;
+    ratio.checked_mul(decimal_adjustment(decimals_in, decimals_out)?)
+}
+
+/// `10^(decimals_in - decimals_out)`, exactly
+#[cfg(feature = "precise-math")]
+pub fn decimal_adjustment(decimals_in: u8, decimals_out: u8) -> Option<Decimal> {
+    let exponent = decimals_in as i64 - decimals_out as i64;
+    Decimal::TEN.checked_powi(exponent)
+}
+
 /// Calculate output amount for a swap with fees
 ///
 /// # Arguments
@@ -53,6 +84,23 @@ pub fn calculate_output_amount(
     (numerator / denominator) as u64
 }
 
+/// Calculate price impact for a constant product swap
+///
+/// # Arguments
+/// * `amount_in` - Amount of input token
+/// * `reserve_in` - Reserve of input token
+///
+/// # Returns
+/// Price impact percentage (execution price vs. spot price)
+pub fn calculate_amm_price_impact(amount_in: u64, reserve_in: u64) -> f64 {
+    if reserve_in == 0 {
+        return 100.0;
+    }
+
+    let amount_in = amount_in as f64;
+    amount_in / (reserve_in as f64 + amount_in) * 100.0
+}
+
 /// Calculate price from CLMM sqrt_price (Q64.64 fixed-point)
 ///
 /// # Arguments
@@ -65,6 +113,18 @@ pub fn calculate_clmm_price(sqrt_price_x64: u128) -> f64 {
     sqrt_price * sqrt_price
 }
 
+/// `calculate_clmm_price` in decimal arithmetic
+///
+/// # Returns
+/// Actual price, `None` if `sqrt_price_x64` is beyond what a `Decimal`
+/// holds (2^96)
+#[cfg(feature = "precise-math")]
+pub fn calculate_clmm_price_exact(sqrt_price_x64: u128) -> Option<Decimal> {
+    let sqrt_price = Decimal::try_from_i128_with_scale(i128::try_from(sqrt_price_x64).ok()?, 0).ok()?;
+    let sqrt_price = sqrt_price.checked_div(Decimal::from_i128_with_scale(1 << 64, 0))?;
+    sqrt_price.checked_mul(sqrt_price)
+}
+
 /// Estimate slippage for CLMM swap
 ///
 /// # Arguments
@@ -114,6 +174,15 @@ mod tests {
         assert!(output > 0);
     }
 
+    #[test]
+    fn test_amm_price_impact() {
+        // Swapping 1% of the input reserve moves the price by ~0.99%
+        let impact = calculate_amm_price_impact(1_000, 100_000);
+        assert!((impact - 0.990).abs() < 0.001);
+
+        assert_eq!(calculate_amm_price_impact(1_000, 0), 100.0);
+    }
+
     #[test]
     fn test_clmm_price() {
         // sqrt_price for price = 100 would be sqrt(100) = 10
@@ -123,4 +192,18 @@ mod tests {
 
         assert!((price - 100.0).abs() < 0.001);
     }
+
+    #[cfg(feature = "precise-math")]
+    #[test]
+    fn test_exact_prices() {
+        use rust_decimal::Decimal;
+
+        // 1000 SOL against 100000.000001 USDC: the last micro-unit survives
+        let price = calculate_amm_price_exact(1_000_000_000_000, 100_000_000_001, 9, 6).unwrap();
+        assert_eq!(price, Decimal::new(100_000_000_001, 9));
+        assert_eq!(calculate_amm_price_exact(0, 1, 9, 6), None);
+
+        assert_eq!(calculate_clmm_price_exact(10 * (1u128 << 64)), Some(Decimal::ONE_HUNDRED));
+        assert_eq!(calculate_clmm_price_exact(u128::MAX), None);
+    }
 }
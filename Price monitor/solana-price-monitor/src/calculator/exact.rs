@@ -0,0 +1,84 @@
+//! Decimal arithmetic over exact prices, for spreads and cycles too close to
+//! break-even for f64 rounding to call
+
+use crate::models::PriceData;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Effective rate of selling through `leg` after its fee, when it has an
+/// exact price
+fn exact_rate(leg: &PriceData) -> Option<Decimal> {
+    let fee_rate = Decimal::try_from(leg.fee_rate).ok()?;
+    leg.exact_price()?.checked_mul(Decimal::ONE.checked_sub(fee_rate)?)
+}
+
+/// Product of the legs' post-fee rates, `None` unless every leg has an
+/// exact price
+pub fn exact_cycle_product(legs: &[&PriceData]) -> Option<Decimal> {
+    legs.iter().try_fold(Decimal::ONE, |product, leg| product.checked_mul(exact_rate(leg)?))
+}
+
+/// Product of the legs' post-fee rates: units of the start token one unit
+/// returns after a full cycle
+///
+/// Exact when every leg has an exact price, otherwise in f64 from the
+/// `sell_price` of each leg.
+pub fn cycle_product(legs: &[&PriceData]) -> f64 {
+    exact_cycle_product(legs)
+        .and_then(|product| product.to_f64())
+        .unwrap_or_else(|| legs.iter().map(|leg| leg.sell_price() * (1.0 - leg.fee_rate)).product())
+}
+
+/// Percent gained buying on `buy` and selling on `sell`, before costs;
+/// `None` unless both have exact prices
+pub fn exact_spread_percent(buy: &PriceData, sell: &PriceData) -> Option<f64> {
+    let (buy, sell) = (buy.exact_price()?, sell.exact_price()?);
+    let spread = sell.checked_sub(buy)?.checked_div(buy)?;
+    spread.checked_mul(Decimal::ONE_HUNDRED)?.to_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::calculate_amm_price;
+
+    /// AMM leg priced from `(reserve_in, reserve_out)`, exactly and in f64
+    fn leg(reserve_in: u64, reserve_out: u64) -> PriceData {
+        let exact = Decimal::from(reserve_out) / Decimal::from(reserve_in);
+        PriceData::new(calculate_amm_price(reserve_in, reserve_out, 6, 6), 1, 1, reserve_in, reserve_out, 0.0)
+            .with_price_exact(Some(exact))
+    }
+
+    #[test]
+    fn test_exact_product_catches_f64_false_positive() {
+        // Reserves chosen so the exact cycle loses 1 part in ~3e17, which
+        // f64 division rounds into a gain
+        let legs = [
+            leg(1_000_000_000_000_000_066, 1_000_000_000_000_000_065),
+            leg(300_000_000_000_000_000, 1_000_000_000_000_000_066),
+            leg(1_000_000_000_000_000_065, 299_999_999_999_999_999),
+        ];
+        let legs: Vec<&PriceData> = legs.iter().collect();
+
+        let f64_product: f64 = legs.iter().map(|leg| leg.price).product();
+        assert!(f64_product > 1.0);
+        assert!(exact_cycle_product(&legs).unwrap() < Decimal::ONE);
+        assert!(cycle_product(&legs) <= 1.0);
+    }
+
+    #[test]
+    fn test_falls_back_to_f64_without_every_exact_leg() {
+        let exact = leg(1_000, 1_010);
+        let quoted = PriceData::from_quote(0.99, 1.01, 1, 1, 0.0).with_price_exact(Some(Decimal::ONE));
+        let plain = PriceData::new(1.0, 1, 1, 0, 0, 0.0);
+
+        assert_eq!(exact_cycle_product(&[&exact, &plain]), None);
+        // Quoted venues trade at the bid, not the exact mid
+        assert_eq!(exact_cycle_product(&[&exact, &quoted]), None);
+        assert!((cycle_product(&[&exact, &quoted]) - 1.01 * 0.99).abs() < 1e-12);
+
+        assert_eq!(exact_spread_percent(&plain, &exact), None);
+        let spread = exact_spread_percent(&leg(1_000, 1_000), &exact).unwrap();
+        assert!((spread - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,5 +1,16 @@
 //! Price calculation module
 
 mod amm;
+mod exact;
+mod tokens;
+mod usd;
 
-pub use amm::{calculate_amm_price, calculate_output_amount, calculate_clmm_price};
+pub use amm::{
+    calculate_amm_price, calculate_amm_price_impact, calculate_output_amount, calculate_clmm_price,
+    estimate_clmm_slippage,
+};
+pub use exact::{cycle_product, exact_cycle_product, exact_spread_percent};
+#[cfg(feature = "precise-math")]
+pub use amm::{calculate_amm_price_exact, calculate_clmm_price_exact, decimal_adjustment};
+pub use tokens::TokenRegistry;
+pub use usd::UsdPricer;
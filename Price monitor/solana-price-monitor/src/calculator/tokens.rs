@@ -0,0 +1,57 @@
+//! Token decimals for expressing opportunity sizes and profits in token terms
+
+use crate::config::TokenConfig;
+use crate::models::Opportunity;
+use std::collections::HashMap;
+
+/// Decimals by token symbol, from `[tokens]`
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    /// Uppercase symbol -> decimals
+    decimals: HashMap<String, u8>,
+}
+
+impl TokenRegistry {
+    /// Decimals from `[tokens]` (symbols match ignoring case)
+    pub fn from_tokens(tokens: &HashMap<String, TokenConfig>) -> Self {
+        Self::new(tokens.iter().map(|(symbol, token)| (symbol.clone(), token.decimals)))
+    }
+
+    pub fn new(decimals: impl IntoIterator<Item = (String, u8)>) -> Self {
+        Self {
+            decimals: decimals.into_iter().map(|(symbol, decimals)| (symbol.to_uppercase(), decimals)).collect(),
+        }
+    }
+
+    pub fn decimals(&self, symbol: &str) -> Option<u8> {
+        self.decimals.get(&symbol.to_uppercase()).copied()
+    }
+
+    /// Fill `opportunity`'s size and profit amounts, sized in `size_token`
+    /// and realized in `profit_token` at `size_price` profit tokens per
+    /// size token
+    ///
+    /// Leaves them unset when either token's decimals are unknown, rather
+    /// than guessing a scale.
+    pub fn denominate(&self, opportunity: &mut Opportunity, size_token: &str, profit_token: &str, size_price: f64) {
+        let (Some(size_decimals), Some(profit_decimals)) = (self.decimals(size_token), self.decimals(profit_token))
+        else {
+            return;
+        };
+        opportunity.denominate(
+            &size_token.to_uppercase(),
+            size_decimals,
+            &profit_token.to_uppercase(),
+            profit_decimals,
+            size_price,
+        );
+    }
+
+    /// `denominate` for a single pair: sized in its base token, with profit
+    /// in its quote token at `price`
+    pub fn denominate_pair(&self, opportunity: &mut Opportunity, pair: &str, price: f64) {
+        if let Some((base, quote)) = pair.split_once(['-', '_']) {
+            self.denominate(opportunity, base, quote, price);
+        }
+    }
+}
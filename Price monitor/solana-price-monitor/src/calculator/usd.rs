@@ -0,0 +1,123 @@
+//! USD estimates of opportunity profit
+//!
+//! Sizes are in base units of the token the opportunity was denominated in,
+//! or else the first token of the pair, so an estimate needs that token's
+//! decimals (from `[tokens]`) and a USD price, read from a cached pair
+//! against USDC or USDT.
+
+use super::TokenRegistry;
+use crate::cache::PriceCache;
+use crate::config::TokenConfig;
+use crate::models::Opportunity;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Quote tokens treated as $1
+const USD_QUOTES: [&str; 2] = ["USDC", "USDT"];
+
+/// Converts opportunity profit to USD
+#[derive(Clone, Default)]
+pub struct UsdPricer {
+    tokens: TokenRegistry,
+    cache: Option<Arc<PriceCache>>,
+}
+
+impl UsdPricer {
+    /// Decimals from `[tokens]` (symbols match ignoring case)
+    pub fn from_tokens(tokens: &HashMap<String, TokenConfig>) -> Self {
+        Self { tokens: TokenRegistry::from_tokens(tokens), cache: None }
+    }
+
+    pub fn new(decimals: impl IntoIterator<Item = (String, u8)>) -> Self {
+        Self { tokens: TokenRegistry::new(decimals), cache: None }
+    }
+
+    /// Use live prices for tokens other than the USD quotes
+    pub fn with_price_cache(mut self, cache: Arc<PriceCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Net profit in USD at the recommended size, when the size token's
+    /// decimals and USD price are known
+    ///
+    /// Uses the size token the opportunity was denominated in, falling back
+    /// to the first token of its pair.
+    pub fn profit(&self, opportunity: &Opportunity) -> Option<f64> {
+        let (symbol, decimals) = if opportunity.size_token.is_empty() {
+            let base = base_symbol(&opportunity.token_pair)?;
+            let decimals = self.tokens.decimals(&base)?;
+            (base, decimals)
+        } else {
+            (opportunity.size_token.clone(), opportunity.size_decimals)
+        };
+        Some(opportunity.profit_at(decimals, self.price(&symbol)?))
+    }
+
+    /// USD price of an uppercase token symbol
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        if USD_QUOTES.contains(&symbol) {
+            return Some(1.0);
+        }
+        let cache = self.cache.as_ref()?;
+        for pair in cache.get_all_pairs() {
+            let Some((a, b)) = pair.split_once(['-', '_']) else { continue };
+            let (a, b) = (a.to_uppercase(), b.to_uppercase());
+            let inverted = if a == symbol && USD_QUOTES.contains(&b.as_str()) {
+                false
+            } else if b == symbol && USD_QUOTES.contains(&a.as_str()) {
+                true
+            } else {
+                continue;
+            };
+            let price = cache.get_all_dexes(&pair).into_iter().map(|(_, data)| data.price).find(|p| *p > 0.0)?;
+            return Some(if inverted { 1.0 / price } else { price });
+        }
+        None
+    }
+}
+
+/// First token of "SOL-USDC", "sol_usdc" or "SOL->USDC->RAY", uppercased
+fn base_symbol(pair: &str) -> Option<String> {
+    let symbol = pair.split(['-', '_', '>']).next()?.trim();
+    (!symbol.is_empty()).then(|| symbol.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dex, OpportunityType, PriceData};
+
+    #[test]
+    fn test_profit_uses_base_decimals_and_cached_usd_price() {
+        let cache = Arc::new(PriceCache::new(60, 5_000));
+        cache.set("USDC-BONK", &Dex::Orca, PriceData::new(50_000.0, 1_000_000, 1, 1, 1, 0.003));
+        let pricer = UsdPricer::new([("sol".to_string(), 9), ("BONK".to_string(), 5)]).with_price_cache(cache);
+        let opportunity = |pair: &str, size| {
+            Opportunity::builder()
+                .opportunity_type(OpportunityType::Triangular)
+                .token_pair(pair)
+                .buy("orca", 1.0, 0)
+                .sell("orca", 1.02, 0)
+                .net_profit_percent(2.0)
+                .recommended_size(size)
+                .confidence(0.5)
+                .build()
+                .unwrap()
+        };
+
+        // 1M BONK at 1/50,000 USD, 2%
+        let usd = pricer.profit(&opportunity("bonk->usdc->sol", 100_000_000_000)).unwrap();
+        assert!((usd - 0.4).abs() < 1e-9, "{}", usd);
+        // No SOL-USDC price cached
+        assert_eq!(pricer.profit(&opportunity("SOL-USDC", 1_000_000_000)), None);
+        assert_eq!(base_symbol("->"), None);
+
+        // Denominated in BONK though the pair starts with SOL
+        let mut denominated = opportunity("SOL-BONK", 100_000_000_000);
+        TokenRegistry::new([("SOL".to_string(), 9), ("BONK".to_string(), 5)])
+            .denominate(&mut denominated, "BONK", "SOL", 1.0);
+        let usd = pricer.profit(&denominated).unwrap();
+        assert!((usd - 0.4).abs() < 1e-9, "{}", usd);
+    }
+}
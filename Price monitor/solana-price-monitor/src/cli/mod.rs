@@ -0,0 +1,239 @@
+//! Command-line interface
+//!
+//! Flags override the loaded settings for a single run. Precedence, highest
+//! first: CLI flags > `APP__*` / provider environment variables >
+//! config.toml > built-in defaults.
+
+use crate::config::{RpcEndpoint, Settings};
+use crate::models::Dex;
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+
+#[derive(Debug, Parser)]
+#[command(name = "solana-price-monitor", version, about = "Real-time Solana DEX price monitoring and arbitrage detection")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub overrides: CliOverrides,
+}
+
+impl Cli {
+    /// Subcommand to run (`run` when none is given)
+    pub fn command(&self) -> Command {
+        self.command.unwrap_or(Command::Run)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Start monitoring (default)
+    Run,
+    /// Load and validate the configuration, print it with secrets masked, then exit
+    #[command(alias = "print-effective-config")]
+    ValidateConfig {
+        /// Output format for the resolved configuration
+        #[arg(long, value_enum, default_value_t = ConfigFormat::Toml)]
+        format: ConfigFormat,
+    },
+    /// Print the pools that would be monitored, then exit
+    ListPools,
+    /// Decode the monitored pools once and print their fee rates in the
+    /// `fees.schedule_file` format, then exit
+    ExportFees,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+/// Render the fully resolved settings with secrets redacted
+pub fn render_config(settings: &Settings, format: ConfigFormat) -> Result<String> {
+    let redacted = settings.redacted();
+    Ok(match format {
+        ConfigFormat::Toml => toml::to_string_pretty(&redacted)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(&redacted)?,
+    })
+}
+
+/// Settings overrides from command-line flags
+#[derive(Debug, Clone, Default, Args)]
+pub struct CliOverrides {
+    /// Minimum net profit percentage to flag an opportunity
+    #[arg(long, global = true)]
+    pub min_profit: Option<f64>,
+
+    /// WebSocket RPC URL, tried before any configured endpoint
+    #[arg(long, global = true)]
+    pub ws_url: Option<String>,
+
+    /// HTTP RPC URL paired with --ws-url (defaults to the primary endpoint's)
+    #[arg(long, global = true, requires = "ws_url")]
+    pub http_url: Option<String>,
+
+    /// Only monitor these pairs (comma-separated, e.g. SOL-USDC,JUP-USDC)
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub pairs: Vec<String>,
+
+    /// Log filter, e.g. "debug" or "info,solana_price_monitor=trace"
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+
+    /// Feed the pipeline from the built-in simulator instead of an RPC
+    /// provider (same as rpc.transport = "simulated")
+    #[arg(long, global = true)]
+    pub simulate: bool,
+}
+
+impl CliOverrides {
+    /// Apply the overrides to loaded settings
+    ///
+    /// `--pairs` is left to `restrict_pairs`, since discovery can still add
+    /// pools after loading.
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(min_profit) = self.min_profit {
+            settings.arbitrage.min_profit_percent = min_profit;
+        }
+
+        if let Some(ws_url) = &self.ws_url {
+            let http_url = self
+                .http_url
+                .clone()
+                .unwrap_or_else(|| settings.rpc.primary().http_url.clone());
+            settings
+                .rpc
+                .endpoints
+                .insert(0, RpcEndpoint::new("cli", ws_url.clone(), http_url));
+        }
+    }
+
+    /// Keep only the `--pairs` pools, once discovered pools are merged in
+    ///
+    /// Fails when a listed pair has no pool.
+    pub fn restrict_pairs(&self, pools: &mut HashMap<String, HashMap<Dex, String>>) -> Result<()> {
+        if self.pairs.is_empty() {
+            return Ok(());
+        }
+        let unknown: Vec<&str> = self
+            .pairs
+            .iter()
+            .filter(|wanted| !pools.keys().any(|pair| same_pair(pair, wanted)))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!("--pairs lists pairs without pools: {}", unknown.join(", "));
+        }
+
+        pools.retain(|pair, _| self.pairs.iter().any(|wanted| same_pair(pair, wanted)));
+        Ok(())
+    }
+}
+
+/// Config keys arrive lowercased and may use '_' instead of '-'
+fn same_pair(a: &str, b: &str) -> bool {
+    a.replace('_', "-").eq_ignore_ascii_case(&b.replace('_', "-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{merge_pools, DiscoveredPool};
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.rpc.endpoints[0].websocket_url = "wss://file.example".to_string();
+        settings.rpc.endpoints[0].http_url = "https://file.example".to_string();
+        for pair in ["sol_usdc", "jup_usdc", "bonk_sol"] {
+            settings.pools.insert(pair.to_string(), HashMap::new());
+        }
+        settings
+    }
+
+    #[test]
+    fn test_overrides_merge_into_settings() {
+        let cli = Cli::try_parse_from([
+            "solana-price-monitor",
+            "--min-profit",
+            "0.8",
+            "--ws-url",
+            "wss://cli.example",
+            "--pairs",
+            "SOL-USDC,JUP-USDC",
+        ])
+        .unwrap();
+        assert_eq!(cli.command(), Command::Run);
+
+        let mut settings = settings();
+        cli.overrides.apply(&mut settings);
+        cli.overrides.restrict_pairs(&mut settings.pools).unwrap();
+
+        assert_eq!(settings.arbitrage.min_profit_percent, 0.8);
+        assert_eq!(settings.rpc.primary().websocket_url, "wss://cli.example");
+        // HTTP falls back to the previous primary endpoint
+        assert_eq!(settings.rpc.primary().http_url, "https://file.example");
+        assert_eq!(settings.rpc.endpoints.len(), 2);
+
+        let mut pairs: Vec<&String> = settings.pools.keys().collect();
+        pairs.sort();
+        assert_eq!(pairs, vec!["jup_usdc", "sol_usdc"]);
+    }
+
+    #[test]
+    fn test_unknown_pair_is_rejected() {
+        let overrides = CliOverrides {
+            pairs: vec!["WIF-USDC".to_string()],
+            ..CliOverrides::default()
+        };
+        assert!(overrides.restrict_pairs(&mut settings().pools).is_err());
+    }
+
+    #[test]
+    fn test_discovered_pairs_can_be_selected() {
+        let overrides = CliOverrides {
+            pairs: vec!["WIF-USDC".to_string(), "SOL-USDC".to_string()],
+            ..CliOverrides::default()
+        };
+        let discovered: Vec<DiscoveredPool> = ["WIF-USDC", "JTO-USDC"]
+            .iter()
+            .map(|pair| DiscoveredPool {
+                pair: pair.to_string(),
+                dex: Dex::Orca,
+                pubkey: format!("{}Pool", pair),
+                fee_rate: 0.003,
+                liquidity_usd: 1_000_000.0,
+            })
+            .collect();
+        let mut pools = merge_pools(&settings().pools, &discovered, 10);
+        overrides.restrict_pairs(&mut pools).unwrap();
+
+        let mut pairs: Vec<&String> = pools.keys().collect();
+        pairs.sort();
+        assert_eq!(pairs, vec!["WIF-USDC", "sol_usdc"]);
+    }
+
+    #[test]
+    fn test_subcommands_accept_overrides() {
+        let cli = Cli::try_parse_from(["solana-price-monitor", "validate-config", "--min-profit", "1.0"]).unwrap();
+        assert_eq!(cli.command(), Command::ValidateConfig { format: ConfigFormat::Toml });
+        assert_eq!(cli.overrides.min_profit, Some(1.0));
+
+        let cli = Cli::try_parse_from(["solana-price-monitor", "print-effective-config", "--format", "json"]).unwrap();
+        assert_eq!(cli.command(), Command::ValidateConfig { format: ConfigFormat::Json });
+    }
+
+    #[test]
+    fn test_rendered_config_is_redacted() {
+        let mut settings = settings();
+        settings.rpc.endpoints[0].http_url = "https://rpc.example/?api-key=abcdefgh12345678".to_string();
+
+        for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+            let rendered = render_config(&settings, format).unwrap();
+            assert!(rendered.contains("****5678"), "{}", rendered);
+            assert!(!rendered.contains("abcdefgh12345678"));
+        }
+    }
+}
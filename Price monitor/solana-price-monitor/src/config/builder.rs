@@ -0,0 +1,263 @@
+//! Programmatic settings for embedding the monitor in another service
+//!
+//! `SettingsBuilder` starts from the built-in defaults, reads no files or
+//! environment variables, and validates with the same rules as
+//! `Settings::load`.
+//!
+//! ```
+//! use solana_price_monitor::config::{RpcEndpoint, SettingsBuilder};
+//! use solana_price_monitor::{OpportunityDetector, PriceCache};
+//! use std::sync::Arc;
+//!
+//! let settings = SettingsBuilder::new()
+//!     .add_rpc_endpoint(RpcEndpoint::new(
+//!         "local",
+//!         "ws://127.0.0.1:8900".to_string(),
+//!         "http://127.0.0.1:8899".to_string(),
+//!     ))
+//!     .add_pool("SOL-USDC", "orca", "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ")
+//!     .add_pool("SOL-USDC", "raydium", "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2")
+//!     .build()?;
+//!
+//! let cache = Arc::new(
+//!     PriceCache::new(settings.monitoring.cache_ttl_seconds, settings.monitoring.stale_threshold_ms)
+//!         .with_stale_thresholds(settings.monitoring.stale_thresholds()),
+//! );
+//! let detector = OpportunityDetector::new(
+//!     cache,
+//!     settings.fees.clone(),
+//!     settings.arbitrage.min_profit_percent,
+//!     settings.arbitrage.slot_tolerance,
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::*;
+
+/// Fluent constructor for `Settings`
+#[derive(Debug, Clone)]
+pub struct SettingsBuilder {
+    settings: Settings,
+}
+
+impl Default for SettingsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsBuilder {
+    /// Built-in defaults with no RPC endpoints or pools
+    pub fn new() -> Self {
+        Self {
+            settings: Settings {
+                rpc: RpcConfig::default(),
+                ..Settings::default()
+            },
+        }
+    }
+
+    /// Cluster the endpoints and pools belong to (checked by `build`)
+    pub fn cluster(mut self, cluster: Cluster) -> Self {
+        self.settings.cluster = cluster;
+        self
+    }
+
+    /// Replace all RPC endpoints
+    pub fn rpc(mut self, rpc: RpcConfig) -> Self {
+        self.settings.rpc = rpc;
+        self
+    }
+
+    /// Append an RPC endpoint (tried in insertion order)
+    pub fn add_rpc_endpoint(mut self, endpoint: RpcEndpoint) -> Self {
+        self.settings.rpc.endpoints.push(endpoint);
+        self
+    }
+
+    pub fn websocket(mut self, websocket: WebSocketConfig) -> Self {
+        self.settings.websocket = websocket;
+        self
+    }
+
+    /// Set how one DEX's pools are subscribed to
+    pub fn subscription(mut self, dex: impl Into<Dex>, subscription: DexSubscriptionConfig) -> Self {
+        self.settings.subscriptions.insert(dex.into(), subscription);
+        self
+    }
+
+    pub fn monitoring(mut self, monitoring: MonitoringConfig) -> Self {
+        self.settings.monitoring = monitoring;
+        self
+    }
+
+    pub fn arbitrage(mut self, arbitrage: ArbitrageConfig) -> Self {
+        self.settings.arbitrage = arbitrage;
+        self
+    }
+
+    pub fn fees(mut self, fees: FeesConfig) -> Self {
+        self.settings.fees = fees;
+        self
+    }
+
+    pub fn stat_arb(mut self, stat_arb: StatArbSettings) -> Self {
+        self.settings.stat_arb = stat_arb;
+        self
+    }
+
+    pub fn triangular(mut self, triangular: TriangularSettings) -> Self {
+        self.settings.triangular = triangular;
+        self
+    }
+
+    pub fn add_triangular_path(mut self, path: TriangularPathConfig) -> Self {
+        self.settings.triangular_paths.push(path);
+        self
+    }
+
+    pub fn calibration(mut self, calibration: CalibrationConfig) -> Self {
+        self.settings.calibration = calibration;
+        self
+    }
+
+    pub fn alerts(mut self, alerts: AlertsConfig) -> Self {
+        self.settings.alerts = alerts;
+        self
+    }
+
+    pub fn regime(mut self, regime: RegimeConfig) -> Self {
+        self.settings.regime = regime;
+        self
+    }
+
+    pub fn costs(mut self, costs: CostsConfig) -> Self {
+        self.settings.costs = costs;
+        self
+    }
+
+    pub fn discovery(mut self, discovery: DiscoveryConfig) -> Self {
+        self.settings.discovery = discovery;
+        self
+    }
+
+    pub fn filters(mut self, filters: FiltersConfig) -> Self {
+        self.settings.filters = filters;
+        self
+    }
+
+    pub fn detectors(mut self, detectors: DetectorsConfig) -> Self {
+        self.settings.detectors = detectors;
+        self
+    }
+
+    pub fn api(mut self, api: ApiConfig) -> Self {
+        self.settings.api = api;
+        self
+    }
+
+    pub fn schedule(mut self, schedule: ScheduleConfig) -> Self {
+        self.settings.schedule = schedule;
+        self
+    }
+
+    /// Register a token's mint and decimals under its symbol
+    pub fn add_token(mut self, symbol: &str, mint: &str, decimals: u8) -> Self {
+        self.settings.tokens.insert(
+            symbol.to_string(),
+            TokenConfig {
+                mint: mint.to_string(),
+                decimals,
+            },
+        );
+        self
+    }
+
+    pub fn add_pool_override(mut self, pool_override: PoolOverride) -> Self {
+        self.settings.pool_overrides.push(pool_override);
+        self
+    }
+
+    /// Monitor a pool; a later call for the same pair and DEX replaces it
+    pub fn add_pool(mut self, pair: &str, dex: impl Into<Dex>, pubkey: &str) -> Self {
+        self.settings
+            .pools
+            .entry(pair.to_string())
+            .or_default()
+            .insert(dex.into(), pubkey.to_string());
+        self
+    }
+
+    /// Priority used when `max_pools` trims the pool list
+    pub fn pool_priority(mut self, pair: &str, dex: impl Into<Dex>, priority: i64) -> Self {
+        self.settings
+            .pool_priority
+            .entry(pair.to_string())
+            .or_default()
+            .insert(dex.into(), priority);
+        self
+    }
+
+    /// Load `fees.schedule_file` if set and validate the settings
+    pub fn build(mut self) -> Result<Settings> {
+        self.settings.load_fee_schedule(Path::new(""))?;
+        self.settings.validate()?;
+        Ok(self.settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> SettingsBuilder {
+        SettingsBuilder::new()
+            .add_rpc_endpoint(RpcEndpoint::new(
+                "test",
+                "wss://example.invalid".to_string(),
+                "https://example.invalid".to_string(),
+            ))
+            .add_pool("SOL-USDC", "orca", "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ")
+    }
+
+    #[test]
+    fn test_builder_produces_valid_settings() {
+        let settings = builder()
+            .arbitrage(ArbitrageConfig {
+                min_profit_percent: 0.8,
+                ..ArbitrageConfig::default()
+            })
+            .add_pool("SOL-USDC", "raydium", "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2")
+            .pool_priority("SOL-USDC", "raydium", 5)
+            .build()
+            .unwrap();
+
+        assert_eq!(settings.arbitrage.min_profit_percent, 0.8);
+        assert_eq!(settings.rpc.endpoints.len(), 1);
+        assert_eq!(settings.pools["SOL-USDC"].len(), 2);
+        assert_eq!(settings.select_pools().active[0].dex, "raydium");
+    }
+
+    #[test]
+    fn test_builder_runs_validation() {
+        let error = SettingsBuilder::new().build().unwrap_err().to_string();
+        assert!(error.contains("RPC endpoint"), "{}", error);
+
+        let error = builder()
+            .add_pool("SOL-USDC", "raydium", "not-a-pubkey")
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("pools.SOL-USDC.raydium"), "{}", error);
+
+        let error = builder()
+            .arbitrage(ArbitrageConfig {
+                min_profit_percent: -1.0,
+                ..ArbitrageConfig::default()
+            })
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("min_profit_percent"), "{}", error);
+    }
+}
@@ -0,0 +1,138 @@
+//! External fee schedule (`fees.schedule_file`)
+//!
+//! A JSON document with fee tiers and fixed per-swap costs, keyed by pool
+//! pubkey or by DEX and pair:
+//!
+//! ```json
+//! {
+//!   "pools": { "<pool pubkey>": { "fee_percent": 0.25, "fixed_cost_lamports": 5000 } },
+//!   "dexes": { "meteora": { "SOL-USDC": { "fee_percent": 0.4 } } }
+//! }
+//! ```
+//!
+//! Pool entries win over everything; DEX entries sit below the
+//! `[fees.overrides]` table in config.toml (see `FeesConfig::pool_fee_rate`).
+
+use crate::models::Dex;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Fee tier and fixed cost for one pool or (DEX, pair)
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FeeScheduleEntry {
+    /// Swap fee percentage (0.25 = 0.25%); unset keeps the next source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_percent: Option<f64>,
+    /// Fixed cost per swap on this pool, charged against `trade_size_lamports`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub fixed_cost_lamports: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Parsed fee schedule file
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FeeSchedule {
+    /// Pool pubkey -> entry
+    pub pools: BTreeMap<String, FeeScheduleEntry>,
+    /// DEX name -> pair -> entry
+    pub dexes: BTreeMap<Dex, BTreeMap<String, FeeScheduleEntry>>,
+}
+
+impl FeeSchedule {
+    /// Read and parse a schedule file
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fee schedule from {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse fee schedule {}", path.display()))
+    }
+
+    /// Write the schedule as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write fee schedule to {}", path.display()))
+    }
+
+    /// Schedule of decoded fee rates (fractions) keyed by pool pubkey
+    ///
+    /// Used to bootstrap a schedule file from live pool state.
+    pub fn from_decoded(fee_rates: HashMap<String, f64>) -> Self {
+        Self {
+            pools: fee_rates
+                .into_iter()
+                .map(|(pubkey, fee_rate)| {
+                    let entry = FeeScheduleEntry {
+                        fee_percent: Some(fee_rate * 100.0),
+                        fixed_cost_lamports: 0,
+                    };
+                    (pubkey, entry)
+                })
+                .collect(),
+            dexes: BTreeMap::new(),
+        }
+    }
+
+    /// Entry for a pool pubkey
+    pub fn pool(&self, pubkey: &str) -> Option<&FeeScheduleEntry> {
+        self.pools.get(pubkey)
+    }
+
+    /// Entry for a pair on a DEX (pairs match ignoring case and '-' / '_')
+    pub fn dex_pair(&self, dex: &Dex, pair: &str) -> Option<&FeeScheduleEntry> {
+        let normalize = |s: &str| s.replace('_', "-").to_ascii_lowercase();
+        self.dexes
+            .get(dex)?
+            .iter()
+            .find(|(name, _)| normalize(name) == normalize(pair))
+            .map(|(_, entry)| entry)
+    }
+
+    /// Path of the first entry whose fee is outside [0, 100) or non-finite
+    pub fn invalid_entry(&self) -> Option<String> {
+        let bad = |entry: &FeeScheduleEntry| {
+            entry.fee_percent.is_some_and(|fee| !fee.is_finite() || !(0.0..100.0).contains(&fee))
+        };
+        self.pools
+            .iter()
+            .find(|(_, entry)| bad(entry))
+            .map(|(pubkey, _)| format!("pools.{}", pubkey))
+            .or_else(|| {
+                self.dexes.iter().find_map(|(dex, pairs)| {
+                    pairs
+                        .iter()
+                        .find(|(_, entry)| bad(entry))
+                        .map(|(pair, _)| format!("dexes.{}.{}", dex, pair))
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exported_schedule_roundtrip() {
+        let schedule = FeeSchedule::from_decoded(HashMap::from([
+            ("OrcaPool".to_string(), 0.0004),
+            ("RaydiumPool".to_string(), 0.0025),
+        ]));
+
+        let path = std::env::temp_dir().join(format!("fee-export-{}.json", std::process::id()));
+        schedule.save(&path).unwrap();
+        let loaded = FeeSchedule::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded, schedule);
+        assert!((loaded.pool("RaydiumPool").unwrap().fee_percent.unwrap() - 0.25).abs() < 1e-12);
+        assert!(loaded.dex_pair(&Dex::Orca, "SOL-USDC").is_none());
+    }
+}
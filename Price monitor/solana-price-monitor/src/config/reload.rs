@@ -0,0 +1,335 @@
+//! Hot reload of config.toml
+//!
+//! The file's modification time (and that of `fees.schedule_file`, if set)
+//! is polled; on change it is re-parsed and validated with the same rules
+//! as startup. Valid settings are published on
+//! a watch channel for the main loop to apply; invalid files are rejected
+//! and the previous settings stay in effect.
+
+use super::Settings;
+use crate::cli::CliOverrides;
+use crate::models::Dex;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Polls a config file and republishes settings when it changes
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    schedule_path: Option<PathBuf>,
+    schedule_modified: Option<SystemTime>,
+    overrides: CliOverrides,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last_modified = modified(&path);
+        Self {
+            path,
+            last_modified,
+            schedule_path: None,
+            schedule_modified: None,
+            overrides: CliOverrides::default(),
+        }
+    }
+
+    /// Also watch the fee schedule file referenced by `settings`
+    fn watch_schedule(&mut self, settings: &Settings) {
+        self.schedule_path = settings.fees.schedule_file.as_ref().map(PathBuf::from);
+        self.schedule_modified = self.schedule_path.as_deref().and_then(modified);
+    }
+
+    /// Re-apply command-line overrides to every reloaded file
+    pub fn with_overrides(mut self, overrides: CliOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Re-parse the file if it changed since the last poll
+    ///
+    /// Returns `None` while the file is unchanged and `Some(Err(_))` when
+    /// the new contents fail to parse or validate.
+    pub fn poll(&mut self) -> Option<Result<Settings>> {
+        let current = modified(&self.path);
+        let schedule = self.schedule_path.as_deref().and_then(modified);
+        if current == self.last_modified && schedule == self.schedule_modified {
+            return None;
+        }
+        self.last_modified = current;
+        self.schedule_modified = schedule;
+
+        let result = Settings::load_from(&self.path, &self.overrides);
+        if let Ok(settings) = &result {
+            self.watch_schedule(settings);
+        }
+        Some(result)
+    }
+
+    /// Spawn the polling task, starting from the settings already in use
+    pub fn spawn(
+        mut self,
+        current: Settings,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) -> watch::Receiver<Settings> {
+        self.watch_schedule(&current);
+        let (tx, rx) = watch::channel(current);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let watch = async move {
+                loop {
+                    ticker.tick().await;
+                    match self.poll() {
+                        None => {}
+                        Some(Ok(settings)) => {
+                            let changed = changed_sections(&tx.borrow(), &settings);
+                            if changed.is_empty() {
+                                continue;
+                            }
+                            info!(sections = ?changed, "Configuration change detected");
+                            if tx.send(settings).is_err() {
+                                return;
+                            }
+                        }
+                        Some(Err(e)) => {
+                            warn!(error = ?e, "Rejected invalid configuration, keeping previous settings");
+                        }
+                    }
+                }
+            };
+            shutdown.run_until_cancelled(watch).await;
+        });
+        rx
+    }
+}
+
+/// Names of the top-level sections that differ between two settings
+pub fn changed_sections(old: &Settings, new: &Settings) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if old.cluster != new.cluster {
+        changed.push("cluster");
+    }
+    if old.rpc != new.rpc {
+        changed.push("rpc");
+    }
+    if old.websocket != new.websocket {
+        changed.push("websocket");
+    }
+    if old.subscriptions != new.subscriptions {
+        changed.push("subscriptions");
+    }
+    if old.monitoring != new.monitoring {
+        changed.push("monitoring");
+    }
+    if old.arbitrage != new.arbitrage {
+        changed.push("arbitrage");
+    }
+    if old.fees != new.fees {
+        changed.push("fees");
+    }
+    if old.stat_arb != new.stat_arb {
+        changed.push("stat_arb");
+    }
+    if old.triangular != new.triangular {
+        changed.push("triangular");
+    }
+    if old.calibration != new.calibration {
+        changed.push("calibration");
+    }
+    if old.alerts != new.alerts {
+        changed.push("alerts");
+    }
+    if old.regime != new.regime {
+        changed.push("regime");
+    }
+    if old.costs != new.costs {
+        changed.push("costs");
+    }
+    if old.filters != new.filters {
+        changed.push("filters");
+    }
+    if old.schedule != new.schedule {
+        changed.push("schedule");
+    }
+    if old.consistency != new.consistency {
+        changed.push("consistency");
+    }
+    if old.api != new.api {
+        changed.push("api");
+    }
+    if old.detectors != new.detectors {
+        changed.push("detectors");
+    }
+    if old.discovery != new.discovery {
+        changed.push("discovery");
+    }
+    if old.simulator != new.simulator {
+        changed.push("simulator");
+    }
+    // Token tables only affect decoders built at startup
+    if old.pool_overrides != new.pool_overrides
+        || old.tokens.len() != new.tokens.len()
+        || old.tokens.iter().any(|(symbol, token)| {
+            new.token(symbol).map(|t| (&t.mint, t.decimals)) != Some((&token.mint, token.decimals))
+        })
+    {
+        changed.push("tokens");
+    }
+    // HashMap iteration order is unstable, so compare sorted pool lists
+    let pools = |s: &Settings| {
+        let mut pools: Vec<(String, Dex, String)> = s
+            .pools
+            .iter()
+            .flat_map(|(pair, dexes)| {
+                dexes
+                    .iter()
+                    .map(move |(dex, pubkey)| (pair.clone(), dex.clone(), pubkey.clone()))
+            })
+            .collect();
+        pools.sort();
+        pools
+    };
+    if pools(old) != pools(new) {
+        changed.push("pools");
+    }
+    if old.pool_priority != new.pool_priority {
+        changed.push("pool_priority");
+    }
+    changed
+}
+
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs::File;
+
+    fn config_toml(min_profit_percent: f64) -> String {
+        format!(
+            r#"
+[rpc]
+websocket_url = "wss://example.invalid"
+http_url = "https://example.invalid"
+
+[monitoring]
+max_pools = 10
+cache_ttl_seconds = 60
+cleanup_interval_seconds = 10
+stale_threshold_ms = 2000
+
+[arbitrage]
+min_profit_percent = {}
+max_trade_size_percent = 5.0
+slot_tolerance = 2
+
+[fees]
+default_dex_fee = 0.25
+estimated_slippage = 0.3
+gas_cost_percent = 0.01
+jito_tip_percent = 0.05
+
+[pools]
+"#,
+            min_profit_percent
+        )
+    }
+
+    /// Rewrite the file and move its mtime forward so the change is seen
+    fn rewrite(path: &std::path::Path, contents: &str, offset_secs: u64) {
+        std::fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(offset_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_threshold_change_is_picked_up() {
+        let path = std::env::temp_dir().join(format!("reload-change-{}.toml", std::process::id()));
+        rewrite(&path, &config_toml(0.5), 0);
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(watcher.poll().is_none());
+
+        rewrite(&path, &config_toml(1.5), 1);
+        let reloaded = watcher.poll().unwrap().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(reloaded.arbitrage.min_profit_percent, 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_keeps_previous_settings() {
+        let path = std::env::temp_dir().join(format!("reload-invalid-{}.toml", std::process::id()));
+        rewrite(&path, &config_toml(0.5), 0);
+        let initial = Settings::load_from(&path, &CliOverrides::default()).unwrap();
+
+        let watcher = ConfigWatcher::new(&path);
+        let mut rx = watcher.spawn(initial, Duration::from_millis(10), CancellationToken::new());
+
+        // Negative thresholds fail validation
+        rewrite(&path, &config_toml(-1.0), 1);
+        let changed = tokio::time::timeout(Duration::from_millis(100), rx.changed()).await;
+        std::fs::remove_file(&path).ok();
+
+        assert!(changed.is_err(), "invalid config must not be published");
+        assert_eq!(rx.borrow().arbitrage.min_profit_percent, 0.5);
+    }
+
+    #[test]
+    fn test_fee_schedule_change_is_picked_up() {
+        let dir = std::env::temp_dir();
+        let schedule = dir.join(format!("reload-schedule-{}.json", std::process::id()));
+        let path = dir.join(format!("reload-schedule-{}.toml", std::process::id()));
+        let schedule_json = |fee: f64| format!(r#"{{ "pools": {{ "PoolA": {{ "fee_percent": {} }} }} }}"#, fee);
+        rewrite(&schedule, &schedule_json(0.3), 0);
+        rewrite(
+            &path,
+            &config_toml(0.5).replace(
+                "[pools]",
+                &format!("schedule_file = {:?}\n\n[pools]", schedule.to_str().unwrap()),
+            ),
+            0,
+        );
+
+        let initial = Settings::load_from(&path, &CliOverrides::default()).unwrap();
+        let mut watcher = ConfigWatcher::new(&path);
+        watcher.watch_schedule(&initial);
+        assert!(watcher.poll().is_none());
+
+        // Only the schedule changes; config.toml is untouched
+        rewrite(&schedule, &schedule_json(0.05), 1);
+        let reloaded = watcher.poll().unwrap().unwrap();
+        std::fs::remove_file(&schedule).ok();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(reloaded.fees.schedule.pool("PoolA").unwrap().fee_percent, Some(0.05));
+        assert_eq!(changed_sections(&initial, &reloaded), vec!["fees"]);
+    }
+
+    #[test]
+    fn test_changed_sections() {
+        let old = Settings::default();
+        let mut new = old.clone();
+        new.arbitrage.min_profit_percent = 1.0;
+        new.pools.insert("SOL-USDC".to_string(), HashMap::from([(Dex::Orca, "pk".to_string())]));
+        assert_eq!(changed_sections(&old, &new), vec!["arbitrage", "pools"]);
+
+        // Equal maps built in another order (and hash seed) are unchanged
+        let dexes: Vec<String> = (0..32).map(|i| format!("dex{}", i)).collect();
+        let (mut old, mut new) = (Settings::default(), Settings::default());
+        old.monitoring.stale_threshold_overrides = dexes.iter().map(|dex| (dex.clone(), 500)).collect();
+        new.monitoring.stale_threshold_overrides = dexes.iter().rev().map(|dex| (dex.clone(), 500)).collect();
+        assert!(changed_sections(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+//! Config schema versioning and diagnostics
+//!
+//! Version 1 is the original layout (`[rpc]`, `[monitoring]`, `[arbitrage]`,
+//! `[fees]`, `[pools]`); everything else arrived in version 2. Files older
+//! than `CONFIG_VERSION` still load, with a warning listing missing sections, and
+//! keys the settings don't know are reported instead of silently dropped.
+
+use serde_json::Value;
+
+/// Schema version written by this release (`config_version = 2`)
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Sections and fields of the version 1 layout
+const V1_KEYS: &[&str] = &[
+    "rpc",
+    "rpc.websocket_url",
+    "rpc.http_url",
+    "monitoring",
+    "monitoring.max_pools",
+    "monitoring.cache_ttl_seconds",
+    "monitoring.cleanup_interval_seconds",
+    "monitoring.stale_threshold_ms",
+    "arbitrage",
+    "arbitrage.min_profit_percent",
+    "arbitrage.max_trade_size_percent",
+    "arbitrage.slot_tolerance",
+    "fees",
+    "fees.default_dex_fee",
+    "fees.estimated_slippage",
+    "fees.gas_cost_percent",
+    "fees.jito_tip_percent",
+    "pools",
+];
+
+/// Accepted keys that don't appear in the serialized settings
+const EXTRA_KEYS: &[&str] = &[
+    "config_version",
+    // Single-endpoint shorthand, folded into rpc.endpoints
+    "rpc.websocket_url",
+    "rpc.http_url",
+    // APP__POOLS_JSON, merged into pools after deserialization
+    "pools_json",
+];
+
+/// Config version that introduced a section or field (dotted path)
+pub fn introduced_in(path: &str) -> u32 {
+    if V1_KEYS.contains(&path) {
+        1
+    } else {
+        CONFIG_VERSION
+    }
+}
+
+/// Warnings for a raw config tree checked against the known schema
+///
+/// `schema` is the serialized default settings. Missing sections are only
+/// reported for files declaring an older `config_version` (or none).
+pub fn check(raw: &Value, schema: &Value) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let version = raw
+        .get("config_version")
+        .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()))
+        .map_or(1, |v| v as u32);
+    if version > CONFIG_VERSION {
+        warnings.push(format!(
+            "config_version {} is newer than this release supports ({}); unknown settings are ignored",
+            version, CONFIG_VERSION
+        ));
+    } else if version < CONFIG_VERSION {
+        // Sections with fields of their own; optional tables and lists aren't expected
+        let missing: Vec<String> = schema
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(section, fields)| {
+                fields.as_object().is_some_and(|f| !f.is_empty())
+                    && introduced_in(section) > version
+                    && raw.get(section.as_str()).is_none()
+            })
+            .map(|(section, _)| format!("[{}]", section))
+            .collect();
+        if !missing.is_empty() {
+            warnings.push(format!(
+                "missing sections {} (added in config_version {}), using defaults",
+                missing.join(", "),
+                CONFIG_VERSION
+            ));
+        }
+        warnings.push(format!(
+            "config_version {} is out of date; set config_version = {} once the file is reviewed",
+            version, CONFIG_VERSION
+        ));
+    }
+
+    unknown_keys(raw, schema, "", &mut warnings);
+    warnings
+}
+
+fn unknown_keys(raw: &Value, schema: &Value, prefix: &str, warnings: &mut Vec<String>) {
+    let (Some(raw), Some(schema)) = (raw.as_object(), schema.as_object()) else {
+        return;
+    };
+    // An empty object in the schema is a free-form map (pools, tokens, ...)
+    if schema.is_empty() {
+        return;
+    }
+
+    for (key, value) in raw {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match schema.get(key) {
+            Some(known) => unknown_keys(value, known, &path, warnings),
+            None if EXTRA_KEYS.contains(&path.as_str()) => {}
+            None => {
+                let suggestion = schema
+                    .keys()
+                    .filter(|known| edit_distance(known, key) <= 2)
+                    .min_by_key(|known| edit_distance(known, key));
+                warnings.push(match suggestion {
+                    Some(known) => format!("unknown field `{}` (did you mean `{}`?), ignored", path, known),
+                    None => format!("unknown field `{}`, ignored", path),
+                });
+            }
+        }
+    }
+}
+
+/// Describe a deserialization error at a config path
+///
+/// "missing field `mint`" at `tokens.sol` becomes "missing field
+/// `tokens.sol.mint`", with a hint when the section is newer than the
+/// original layout.
+pub fn describe_error(path: &str, message: &str) -> String {
+    let path = if path == "." { "" } else { path };
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(field, _)| field);
+
+    match missing {
+        Some(field) => {
+            let full = if path.is_empty() { field.to_string() } else { format!("{}.{}", path, field) };
+            // Entries of maps and lists have no history of their own, so go by section
+            let section = full.split('.').next().unwrap_or_default();
+            match introduced_in(section) {
+                1 => format!("missing field `{}`", full),
+                version => format!(
+                    "missing field `{}` ([{}] was added in config_version {}, see config.toml for an example)",
+                    full, section, version
+                ),
+            }
+        }
+        None if path.is_empty() => message.to_string(),
+        None => format!("invalid value for `{}`: {}", path, message),
+    }
+}
+
+/// Levenshtein distance, for typo suggestions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb { previous } else { 1 + previous.min(row[j]).min(row[j + 1]) };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+
+    fn load(toml: &str) -> (config::Config, Vec<String>) {
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        let raw: Value = config.clone().try_deserialize().unwrap();
+        let schema = serde_json::to_value(Settings::default()).unwrap();
+        let warnings = check(&raw, &schema);
+        (config, warnings)
+    }
+
+    #[test]
+    fn test_old_config_loads_with_warnings() {
+        let (config, warnings) = load(
+            r#"
+[rpc]
+websocket_url = "wss://example.invalid"
+http_url = "https://example.invalid"
+
+[monitoring]
+max_pools = 10
+cache_ttl_seconds = 60
+cleanup_interval_seconds = 10
+stale_threshold_ms = 2000
+
+[arbitrage]
+min_profit_percent = 0.5
+max_trade_size_percent = 5.0
+slot_tolerance = 2
+
+[fees]
+default_dex_fee = 0.25
+estimated_slippage = 0.3
+gas_cost_percent = 0.01
+jito_tip_percent = 0.05
+
+[pools.sol_usdc]
+raydium = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2"
+"#,
+        );
+
+        let settings: Settings = config.try_deserialize().unwrap();
+        assert_eq!(settings.api.port, 3001);
+
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("missing sections [alerts], [api], [calibration]"), "{}", warnings[0]);
+        assert!(warnings[0].contains("[detectors]"));
+        // Sections from the original layout and optional tables aren't expected
+        assert!(!warnings[0].contains("[pools]") && !warnings[0].contains("[tokens]"));
+        assert!(warnings[1].contains("set config_version = 2"));
+    }
+
+    #[test]
+    fn test_typo_is_reported() {
+        let (_, warnings) = load(
+            r#"
+config_version = 2
+
+[arbitrage]
+min_proft_percent = 0.8
+
+[pools.sol_usdc]
+raydium = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2"
+"#,
+        );
+        assert_eq!(
+            warnings,
+            vec!["unknown field `arbitrage.min_proft_percent` (did you mean `min_profit_percent`?), ignored"]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_config_path() {
+        let (config, _) = load("[tokens.sol]\ndecimals = 9\n");
+        let error = serde_path_to_error::deserialize::<_, Settings>(config).unwrap_err();
+        assert_eq!(
+            describe_error(&error.path().to_string(), &error.inner().to_string()),
+            "missing field `tokens.sol.mint` ([tokens] was added in config_version 2, see config.toml for an example)"
+        );
+
+        assert_eq!(
+            describe_error("rpc.endpoints[0]", "missing field `http_url`"),
+            "missing field `rpc.endpoints[0].http_url`"
+        );
+        assert_eq!(
+            describe_error("arbitrage.min_profit_percent", "invalid type: string \"high\", expected f64"),
+            "invalid value for `arbitrage.min_profit_percent`: invalid type: string \"high\", expected f64"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Live transaction cost feed
+//!
+//! Replaces the static `gas_cost_percent` / `jito_tip_percent` with values
+//! derived from recent prioritization fees (and optionally the Jito tip
+//! floor), converted to a percentage of the typical trade size. The static
+//! values remain the fallback whenever the feed is disabled or stale.
+//! A `Dynamic` tip strategy picks its percentile from the tip floor.
+
+use crate::config::{CostsConfig, FeesConfig, RedactedUrl, TipStrategy};
+use crate::net::Egress;
+use anyhow::{Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Base signature fee per transaction
+const BASE_FEE_LAMPORTS: u64 = 5_000;
+
+/// Lamports per SOL, for tip floors quoted in SOL
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// Latest observed network costs
+#[derive(Debug, Clone, Copy)]
+pub struct CostSnapshot {
+    /// Priority fee in micro-lamports per compute unit
+    pub priority_fee_micro_lamports: u64,
+    /// Landed Jito tips by percentile, if available
+    pub tip_floor: Option<TipFloor>,
+}
+
+/// Recently landed Jito tips as (percentile, lamports), ascending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TipFloor(pub [(u8, u64); 5]);
+
+/// Percentiles published by the tip floor endpoint
+const TIP_FLOOR_PERCENTILES: [u8; 5] = [25, 50, 75, 95, 99];
+
+impl TipFloor {
+    /// Tip at the smallest published percentile covering `percentile`
+    pub fn at(&self, percentile: u8) -> u64 {
+        self.0
+            .iter()
+            .find(|(p, _)| *p >= percentile)
+            .unwrap_or(&self.0[self.0.len() - 1])
+            .1
+    }
+}
+
+struct Observed {
+    snapshot: CostSnapshot,
+    at: Instant,
+}
+
+/// Shared source of live cost percentages for the detectors
+pub struct CostFeed {
+    config: CostsConfig,
+    /// `[fees]`, replaced when the config is reloaded
+    fallback: RwLock<FeesConfig>,
+    latest: RwLock<Option<Observed>>,
+}
+
+impl CostFeed {
+    pub fn new(config: CostsConfig, fallback: FeesConfig) -> Self {
+        Self {
+            config,
+            fallback: RwLock::new(fallback),
+            latest: RwLock::new(None),
+        }
+    }
+
+    /// Use reloaded `[fees]` for everything live values don't replace
+    pub fn set_fallback(&self, fees: FeesConfig) {
+        *self.fallback.write().unwrap_or_else(|e| e.into_inner()) = fees;
+    }
+
+    /// Record a new observation
+    pub fn update(&self, snapshot: CostSnapshot) {
+        *self.latest.write().unwrap_or_else(|e| e.into_inner()) = Some(Observed {
+            snapshot,
+            at: Instant::now(),
+        });
+    }
+
+    /// Fees with gas and tip replaced by live values when fresh
+    pub fn effective_fees(&self) -> FeesConfig {
+        let mut fees = self.fallback.read().unwrap_or_else(|e| e.into_inner()).clone();
+
+        let latest = self.latest.read().unwrap_or_else(|e| e.into_inner());
+        let Some(observed) = latest.as_ref() else {
+            return fees;
+        };
+        if observed.at.elapsed() > Duration::from_secs(self.config.max_age_seconds) {
+            return fees;
+        }
+
+        let trade_size = self.config.typical_trade_size_lamports.max(1) as f64;
+        let priority_lamports = observed.snapshot.priority_fee_micro_lamports as f64
+            * self.config.compute_units_per_swap as f64
+            / 1_000_000.0;
+
+        fees.gas_cost_percent = (BASE_FEE_LAMPORTS as f64 + priority_lamports) / trade_size * 100.0;
+        if let Some(floor) = observed.snapshot.tip_floor {
+            let percentile = match fees.tip_strategy {
+                Some(TipStrategy::Dynamic { percentile }) => percentile,
+                _ => 50,
+            };
+            fees.jito_tip_percent = floor.at(percentile) as f64 / trade_size * 100.0;
+        }
+        fees
+    }
+
+    /// Poll the RPC (and tip floor endpoint, if configured) on an interval
+    pub fn spawn_polling_task(
+        feed: Arc<Self>,
+        http_url: String,
+        egress: &Egress,
+        shutdown: CancellationToken,
+    ) -> Result<JoinHandle<()>> {
+        let rpc = egress.rpc_client(&http_url)?;
+        let http = egress.http_client()?;
+        Ok(tokio::spawn(async move {
+            let http_url = RedactedUrl::new(http_url);
+            let mut ticker =
+                tokio::time::interval(Duration::from_secs(feed.config.poll_interval_seconds));
+
+            let poll = async move {
+                loop {
+                    ticker.tick().await;
+
+                    let priority_fee = match fetch_priority_fee(&rpc, feed.config.priority_fee_percentile).await {
+                        Ok(fee) => fee,
+                        Err(e) => {
+                            let error = http_url.scrub(&format!("{:#}", e));
+                            warn!(error = %error, "Failed to fetch prioritization fees, using static costs");
+                            continue;
+                        }
+                    };
+
+                    let tip_floor = match &feed.config.tip_floor_url {
+                        Some(url) => fetch_tip_floor(&http, url)
+                            .await
+                            .map_err(|e| warn!(error = ?e, "Failed to fetch Jito tip floor"))
+                            .ok(),
+                        None => None,
+                    };
+
+                    debug!(priority_fee = priority_fee, tip_floor = ?tip_floor, "Cost feed updated");
+                    feed.update(CostSnapshot {
+                        priority_fee_micro_lamports: priority_fee,
+                        tip_floor,
+                    });
+                }
+            };
+            shutdown.run_until_cancelled(poll).await;
+        }))
+    }
+}
+
+/// Percentile of recent prioritization fees (micro-lamports per CU)
+async fn fetch_priority_fee(rpc: &RpcClient, percentile: u8) -> Result<u64> {
+    let mut fees: Vec<u64> = rpc
+        .get_recent_prioritization_fees(&[])
+        .await
+        .context("getRecentPrioritizationFees failed")?
+        .into_iter()
+        .map(|f| f.prioritization_fee)
+        .collect();
+
+    Ok(percentile_of(&mut fees, percentile))
+}
+
+/// Landed tip percentiles from a Jito tip floor endpoint, in lamports
+async fn fetch_tip_floor(http: &reqwest::Client, url: &str) -> Result<TipFloor> {
+    // reqwest errors embed the URL, which may carry an API key
+    let body: serde_json::Value = http
+        .get(url)
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)?
+        .error_for_status()
+        .map_err(reqwest::Error::without_url)?
+        .json()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    parse_tip_floor(&body)
+}
+
+/// The endpoint returns a one-element array of percentile tips in SOL
+fn parse_tip_floor(body: &serde_json::Value) -> Result<TipFloor> {
+    let entry = body.get(0).unwrap_or(body);
+    let mut floor = [(0, 0); 5];
+    for (slot, percentile) in floor.iter_mut().zip(TIP_FLOOR_PERCENTILES) {
+        let field = format!("landed_tips_{}th_percentile", percentile);
+        let tip_sol = entry
+            .get(&field)
+            .and_then(|v| v.as_f64())
+            .with_context(|| format!("Tip floor response missing {}", field))?;
+        *slot = (percentile, (tip_sol * LAMPORTS_PER_SOL).round() as u64);
+    }
+    Ok(TipFloor(floor))
+}
+
+fn percentile_of(values: &mut [u64], percentile: u8) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (percentile.min(100) as usize * (values.len() - 1)) / 100;
+    values[rank]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fees() -> FeesConfig {
+        FeesConfig {
+            default_dex_fee: 0.25,
+            estimated_slippage: 0.3,
+            gas_cost_percent: 0.01,
+            jito_tip_percent: 0.05,
+            tip_strategy: None,
+            trade_size_lamports: 10_000_000_000,
+            overrides: Default::default(),
+            schedule_file: None,
+            schedule: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_fallback_without_observation() {
+        let feed = CostFeed::new(CostsConfig::default(), fees());
+        let effective = feed.effective_fees();
+        assert_eq!(effective.gas_cost_percent, 0.01);
+        assert_eq!(effective.jito_tip_percent, 0.05);
+
+        // Reloaded fees replace the fallback
+        feed.set_fallback(FeesConfig { estimated_slippage: 0.5, jito_tip_percent: 0.07, ..fees() });
+        let effective = feed.effective_fees();
+        assert_eq!((effective.estimated_slippage, effective.jito_tip_percent), (0.5, 0.07));
+    }
+
+    #[test]
+    fn test_live_costs_replace_static_values() {
+        let config = CostsConfig {
+            typical_trade_size_lamports: 10_000_000_000, // 10 SOL
+            compute_units_per_swap: 200_000,
+            ..CostsConfig::default()
+        };
+        let feed = CostFeed::new(config, fees());
+        feed.update(CostSnapshot {
+            priority_fee_micro_lamports: 1_000_000,
+            tip_floor: Some(tip_floor()),
+        });
+
+        let effective = feed.effective_fees();
+        // (5_000 + 200_000) lamports of 10 SOL
+        assert!((effective.gas_cost_percent - 0.00205).abs() < 1e-9);
+        // 0.02 SOL of 10 SOL
+        assert!((effective.jito_tip_percent - 0.2).abs() < 1e-9);
+    }
+
+    fn tip_floor() -> TipFloor {
+        TipFloor([
+            (25, 10_000_000),
+            (50, 20_000_000),
+            (75, 40_000_000),
+            (95, 80_000_000),
+            (99, 160_000_000),
+        ])
+    }
+
+    #[test]
+    fn test_dynamic_tip_uses_configured_percentile() {
+        let fees = FeesConfig {
+            tip_strategy: Some(TipStrategy::Dynamic { percentile: 75 }),
+            ..fees()
+        };
+        let feed = CostFeed::new(CostsConfig::default(), fees);
+        assert_eq!(feed.effective_fees().tip_cost_percent(1.0), 0.05);
+
+        feed.update(CostSnapshot {
+            priority_fee_micro_lamports: 0,
+            tip_floor: Some(tip_floor()),
+        });
+        // 0.04 SOL of the default 10 SOL trade
+        assert!((feed.effective_fees().tip_cost_percent(1.0) - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_tip_floor() {
+        let body = serde_json::json!([{
+            "landed_tips_25th_percentile": 0.00001,
+            "landed_tips_50th_percentile": 0.00002,
+            "landed_tips_75th_percentile": 0.0001,
+            "landed_tips_95th_percentile": 0.001,
+            "landed_tips_99th_percentile": 0.01,
+        }]);
+        let floor = parse_tip_floor(&body).unwrap();
+        assert_eq!(floor.at(50), 20_000);
+        assert_eq!(floor.at(90), 1_000_000);
+        assert_eq!(floor.at(100), 10_000_000);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut values = vec![50, 10, 40, 20, 30];
+        assert_eq!(percentile_of(&mut values, 50), 30);
+        assert_eq!(percentile_of(&mut values, 100), 50);
+        assert_eq!(percentile_of(&mut [], 75), 0);
+    }
+}
@@ -3,6 +3,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 use super::{PoolDecoder, PoolState};
+use crate::models::Dex;
 use anyhow::Result;
 
 /// Meteora DLMM LbPair account state
@@ -83,6 +84,10 @@ pub struct RewardInfo {
     pub cumulative_seconds_with_empty_liquidity_reward: u64,
 }
 
+/// LbPair bytes `decode` reads: `parameters` through `bin_step`
+const DECODE_SLICE: (usize, usize) = (8, 74);
+
+#[derive(Debug, Clone)]
 pub struct MeteoraDecoder {
     /// Decimals for token X
     pub token_x_decimals: u8,
@@ -124,39 +129,78 @@ impl MeteoraDecoder {
         // Base fee = bin_step * base_factor / 10^10
         (bin_step as f64 * base_factor as f64) / 10_000_000_000.0
     }
+
+    /// Inverse of `calculate_price_from_bin`: the bin whose price is
+    /// closest to `price`
+    pub fn bin_for_price(&self, price: f64, bin_step: u16) -> i32 {
+        let decimal_adjustment = 10f64.powi(self.token_x_decimals as i32 - self.token_y_decimals as i32);
+        let base = 1.0 + (bin_step as f64 / 10000.0);
+        ((price / decimal_adjustment).ln() / base.ln()).round() as i32
+    }
+
+    /// `DECODE_SLICE` bytes holding these fields, as a `dataSlice`
+    /// subscription delivers them
+    pub fn encode_slice(active_id: i32, bin_step: u16, base_factor: u16) -> Vec<u8> {
+        let mut data = vec![0; DECODE_SLICE.1];
+        data[0..2].copy_from_slice(&base_factor.to_le_bytes());
+        data[68..72].copy_from_slice(&active_id.to_le_bytes());
+        data[72..74].copy_from_slice(&bin_step.to_le_bytes());
+        data
+    }
+
+    /// Fields read straight from a `DECODE_SLICE` buffer
+    fn decode_slice(&self, data: &[u8]) -> Result<PoolState> {
+        let base_factor = u16::from_le_bytes(super::field(data, 0)?);
+        let active_id = i32::from_le_bytes(super::field(data, 68)?);
+        let bin_step = u16::from_le_bytes(super::field(data, 72)?);
+        Ok(self.pool_state(active_id, bin_step, base_factor))
+    }
+
+    fn pool_state(&self, active_id: i32, bin_step: u16, base_factor: u16) -> PoolState {
+        PoolState {
+            token_a_reserve: 0, // DLMM uses bins, not simple reserves
+            token_b_reserve: 0,
+            token_a_decimals: self.token_x_decimals,
+            token_b_decimals: self.token_y_decimals,
+            fee_rate: self.calculate_fee_rate(bin_step, base_factor),
+            liquidity: 0, // Would need to aggregate across bins
+            specific_data: super::SpecificPoolData::Dlmm {
+                active_id,
+                bin_step,
+                base_factor,
+            },
+        }
+    }
 }
 
 impl PoolDecoder for MeteoraDecoder {
     fn decode(&self, data: &[u8]) -> Result<PoolState> {
+        if data.len() == DECODE_SLICE.1 {
+            return self.decode_slice(data);
+        }
         // Meteora uses Anchor, skip 8 byte discriminator
         if data.len() < 8 {
             anyhow::bail!("Data too short for Meteora DLMM");
         }
 
         let lb_pair = LbPairState::try_from_slice(&data[8..])?;
+        Ok(self.pool_state(lb_pair.active_id, lb_pair.bin_step, lb_pair.parameters.base_factor))
+    }
 
-        let fee_rate = self.calculate_fee_rate(
-            lb_pair.bin_step,
-            lb_pair.parameters.base_factor,
-        );
+    fn dex(&self) -> Dex {
+        Dex::Meteora
+    }
 
-        Ok(PoolState {
-            token_a_reserve: 0, // DLMM uses bins, not simple reserves
-            token_b_reserve: 0,
-            token_a_decimals: self.token_x_decimals,
-            token_b_decimals: self.token_y_decimals,
-            fee_rate,
-            liquidity: 0, // Would need to aggregate across bins
-            specific_data: super::SpecificPoolData::Dlmm {
-                active_id: lb_pair.active_id,
-                bin_step: lb_pair.bin_step,
-                base_factor: lb_pair.parameters.base_factor,
-            },
-        })
+    fn token_mints(&self, data: &[u8]) -> Result<(Pubkey, Pubkey)> {
+        if data.len() < 8 {
+            anyhow::bail!("Data too short for Meteora DLMM");
+        }
+        let lb_pair = LbPairState::deserialize(&mut &data[8..])?;
+        Ok((lb_pair.token_x_mint, lb_pair.token_y_mint))
     }
 
-    fn dex_name(&self) -> &'static str {
-        "meteora"
+    fn data_slice(&self) -> Option<(usize, usize)> {
+        Some(DECODE_SLICE)
     }
 }
 
@@ -177,6 +221,51 @@ mod tests {
         assert!(price > 2000.0); // With decimal adjustment
     }
 
+    #[test]
+    fn test_sliced_decode_matches_full_account() {
+        let lb_pair = LbPairState {
+            parameters: LbPairParameters { base_factor: 10_000, max_bin_id: 443_636, ..Default::default() },
+            v_parameters: VParameters { volatility_accumulator: 77, ..Default::default() },
+            bump_seed: [253],
+            bin_step_seed: [25, 0],
+            pair_type: 0,
+            active_id: -4_321,
+            bin_step: 25,
+            status: 0,
+            require_base_factor_seed: 0,
+            base_factor_seed: [16, 39],
+            padding_1: [0; 2],
+            token_x_mint: Pubkey::new_unique(),
+            token_y_mint: Pubkey::new_unique(),
+            reserve_x: Pubkey::new_unique(),
+            reserve_y: Pubkey::new_unique(),
+            protocol_fee: ProtocolFee::default(),
+            padding_2: [0; 32],
+            reward_infos: Default::default(),
+            oracle: Pubkey::new_unique(),
+            bin_array_bitmap: [0; 16],
+            last_updated_at: 0,
+            whitelisted_wallet: Pubkey::default(),
+            pre_activation_swap_address: Pubkey::default(),
+            base_key: Pubkey::new_unique(),
+            activation_slot: 0,
+            pre_activation_slot_duration: 0,
+            padding_3: [0; 8],
+            lock_duration: 0,
+            creator: Pubkey::new_unique(),
+            reserved: [0; 24],
+        };
+        let mut account = vec![0u8; 8];
+        lb_pair.serialize(&mut account).unwrap();
+
+        let decoder = MeteoraDecoder::new(9, 6);
+        let (offset, length) = decoder.data_slice().unwrap();
+        let full = decoder.decode(&account).unwrap();
+        let sliced = decoder.decode(&account[offset..offset + length]).unwrap();
+        assert_eq!(format!("{:?}", sliced), format!("{:?}", full));
+        assert!(matches!(sliced.specific_data, crate::decoder::SpecificPoolData::Dlmm { active_id: -4_321, bin_step: 25, .. }));
+    }
+
     #[test]
     fn test_fee_calculation() {
         let decoder = MeteoraDecoder::default();
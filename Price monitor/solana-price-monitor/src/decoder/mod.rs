@@ -1,6 +1,9 @@
 //! DEX account data decoders
 
+use crate::models::Dex;
 use anyhow::Result;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
 
 pub mod raydium;
 pub mod orca;
@@ -15,13 +18,44 @@ pub trait PoolDecoder {
     /// Decode raw account data into pool state
     fn decode(&self, data: &[u8]) -> Result<PoolState>;
 
-    /// Get DEX name
-    fn dex_name(&self) -> &'static str;
+    /// DEX whose pools this decodes
+    fn dex(&self) -> Dex;
+
+    /// Token mints (base, quote) of a pool account, used to resolve the pair
+    /// of accounts picked up by a program subscription
+    fn token_mints(&self, _data: &[u8]) -> Result<(Pubkey, Pubkey)> {
+        anyhow::bail!("{} pool accounts don't expose their token mints", self.dex())
+    }
+
+    /// `(offset, length)` of the only account bytes `decode` needs, for
+    /// subscribing with `dataSlice`; `decode` accepts exactly that many
+    /// bytes as well as the full account
+    fn data_slice(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// `N` bytes at `offset` of a (possibly sliced) account
+pub(crate) fn field<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("account data too short for field at {}", offset))
+}
+
+/// On-chain program owning each supported DEX's pool accounts
+pub const PROGRAM_IDS: &[(Dex, &str)] = &[
+    (Dex::Raydium, "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"),
+    (Dex::Orca, "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"),
+    (Dex::Meteora, "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo"),
+];
+
+/// Program id of a DEX by name
+pub fn program_id(dex: &Dex) -> Option<&'static str> {
+    PROGRAM_IDS.iter().find(|(name, _)| name == dex).map(|(_, id)| *id)
 }
 
 /// Normalized pool state across all DEX types
 #[derive(Debug, Clone)]
-#[derive(Debug, Clone)]
 pub struct PoolState {
     pub token_a_reserve: u64,
     pub token_b_reserve: u64,
@@ -39,14 +73,77 @@ pub enum SpecificPoolData {
     Dlmm { active_id: i32, bin_step: u16, base_factor: u16 },
 }
 
+impl PoolState {
+    /// Price of token A in token B, adjusted for decimals
+    pub fn price(&self) -> f64 {
+        let decimal_adjustment = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+        match self.specific_data {
+            SpecificPoolData::Amm { coin_vault_balance, pc_vault_balance } => crate::calculator::calculate_amm_price(
+                coin_vault_balance,
+                pc_vault_balance,
+                self.token_a_decimals,
+                self.token_b_decimals,
+            ),
+            SpecificPoolData::Clmm { sqrt_price, .. } => {
+                // price = (sqrt_price / 2^64)^2 * decimal_adjustment
+                let sqrt_price_f64 = sqrt_price as f64 / (1u128 << 64) as f64;
+                sqrt_price_f64 * sqrt_price_f64 * decimal_adjustment
+            }
+            SpecificPoolData::Dlmm { active_id, bin_step, .. } => {
+                // price = (1 + bin_step / 10000)^active_id * decimal_adjustment
+                let base = 1.0 + (bin_step as f64 / 10000.0);
+                base.powi(active_id) * decimal_adjustment
+            }
+        }
+    }
+
+    /// `price` computed exactly from the integer pool state, for AMM and
+    /// CLMM pools when built with `precise-math`
+    ///
+    /// DLMM bin prices are a fractional power, so they have no exact form
+    /// to offer and stay `None`.
+    pub fn price_exact(&self) -> Option<Decimal> {
+        #[cfg(feature = "precise-math")]
+        {
+            use crate::calculator::{calculate_amm_price_exact, calculate_clmm_price_exact, decimal_adjustment};
+
+            match self.specific_data {
+                SpecificPoolData::Amm { coin_vault_balance, pc_vault_balance } => calculate_amm_price_exact(
+                    coin_vault_balance,
+                    pc_vault_balance,
+                    self.token_a_decimals,
+                    self.token_b_decimals,
+                ),
+                SpecificPoolData::Clmm { sqrt_price, .. } => calculate_clmm_price_exact(sqrt_price)?
+                    .checked_mul(decimal_adjustment(self.token_a_decimals, self.token_b_decimals)?),
+                SpecificPoolData::Dlmm { .. } => None,
+            }
+        }
+        #[cfg(not(feature = "precise-math"))]
+        {
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_decoder_names() {
-        assert_eq!(RaydiumDecoder.dex_name(), "raydium");
-        assert_eq!(OrcaDecoder::default().dex_name(), "orca");
-        assert_eq!(MeteoraDecoder::default().dex_name(), "meteora");
+        assert_eq!(RaydiumDecoder.dex(), Dex::Raydium);
+        assert_eq!(OrcaDecoder::default().dex(), Dex::Orca);
+        assert_eq!(MeteoraDecoder::default().dex(), Dex::Meteora);
+    }
+
+    #[test]
+    fn test_program_ids_are_valid_pubkeys() {
+        for (dex, id) in PROGRAM_IDS {
+            assert!(id.parse::<Pubkey>().is_ok(), "{dex}: {id}");
+        }
+        assert_eq!(program_id(&Dex::from("Orca")), Some("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"));
+        assert!(RaydiumDecoder.token_mints(&[0; 64]).is_err());
+        assert_eq!(RaydiumDecoder.data_slice(), None);
     }
 }
@@ -1,6 +1,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 use super::{PoolDecoder, PoolState};
+use crate::models::Dex;
 use anyhow::Result;
 
 /// Orca Whirlpool account state (CLMM)
@@ -26,6 +27,10 @@ pub struct WhirlpoolState {
     pub reward_last_updated_timestamp: u64,
 }
 
+/// Whirlpool bytes `decode` reads: `fee_rate` through `sqrt_price`
+const DECODE_SLICE: (usize, usize) = (13, 36);
+
+#[derive(Debug, Clone)]
 pub struct OrcaDecoder {
     /// Default decimals for token A (e.g., SOL = 9)
     pub token_a_decimals: u8,
@@ -60,35 +65,74 @@ impl OrcaDecoder {
         let decimal_adjustment = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
         raw_price * decimal_adjustment
     }
+
+    /// Inverse of `calculate_price_from_sqrt`
+    pub fn sqrt_price_for(&self, price: f64) -> u128 {
+        let decimal_adjustment = 10f64.powi(self.token_a_decimals as i32 - self.token_b_decimals as i32);
+        ((price / decimal_adjustment).sqrt() * (1u128 << 64) as f64) as u128
+    }
+
+    /// `DECODE_SLICE` bytes holding these fields, as a `dataSlice`
+    /// subscription delivers them
+    pub fn encode_slice(fee_rate: u16, liquidity: u128, sqrt_price: u128) -> Vec<u8> {
+        let mut data = vec![0; DECODE_SLICE.1];
+        data[0..2].copy_from_slice(&fee_rate.to_le_bytes());
+        data[4..20].copy_from_slice(&liquidity.to_le_bytes());
+        data[20..36].copy_from_slice(&sqrt_price.to_le_bytes());
+        data
+    }
+
+    /// Fields read straight from a `DECODE_SLICE` buffer
+    fn decode_slice(&self, data: &[u8]) -> Result<PoolState> {
+        let fee_rate = u16::from_le_bytes(super::field(data, 0)?);
+        let liquidity = u128::from_le_bytes(super::field(data, 4)?);
+        let sqrt_price = u128::from_le_bytes(super::field(data, 20)?);
+        Ok(self.pool_state(fee_rate, liquidity, sqrt_price))
+    }
+
+    fn pool_state(&self, fee_rate: u16, liquidity: u128, sqrt_price: u128) -> PoolState {
+        // For CLMM, we use sqrt_price and liquidity instead of reserves
+        // Reserves are set to 0 since CLMM uses different math
+        PoolState {
+            token_a_reserve: 0,
+            token_b_reserve: 0,
+            token_a_decimals: self.token_a_decimals,
+            token_b_decimals: self.token_b_decimals,
+            fee_rate: fee_rate as f64 / 10000.0,
+            liquidity,
+            specific_data: super::SpecificPoolData::Clmm { sqrt_price, liquidity },
+        }
+    }
 }
 
 impl PoolDecoder for OrcaDecoder {
     fn decode(&self, data: &[u8]) -> Result<PoolState> {
+        if data.len() == DECODE_SLICE.1 {
+            return self.decode_slice(data);
+        }
         // Orca Whirlpools are Anchor accounts, skip 8 byte discriminator
         if data.len() < 8 {
             anyhow::bail!("Data too short for Orca Whirlpool");
         }
         
         let whirlpool = WhirlpoolState::try_from_slice(&data[8..])?;
+        Ok(self.pool_state(whirlpool.fee_rate, whirlpool.liquidity, whirlpool.sqrt_price))
+    }
 
-        // For CLMM, we use sqrt_price and liquidity instead of reserves
-        // Reserves are set to 0 since CLMM uses different math
-        Ok(PoolState {
-            token_a_reserve: 0,
-            token_b_reserve: 0,
-            token_a_decimals: self.token_a_decimals,
-            token_b_decimals: self.token_b_decimals,
-            fee_rate: whirlpool.fee_rate as f64 / 10000.0,
-            liquidity: whirlpool.liquidity,
-            specific_data: super::SpecificPoolData::Clmm {
-                sqrt_price: whirlpool.sqrt_price,
-                liquidity: whirlpool.liquidity,
-            },
-        })
+    fn dex(&self) -> Dex {
+        Dex::Orca
     }
 
-    fn dex_name(&self) -> &'static str {
-        "orca"
+    fn token_mints(&self, data: &[u8]) -> Result<(Pubkey, Pubkey)> {
+        if data.len() < 8 {
+            anyhow::bail!("Data too short for Orca Whirlpool");
+        }
+        let whirlpool = WhirlpoolState::deserialize(&mut &data[8..])?;
+        Ok((whirlpool.token_mint_a, whirlpool.token_mint_b))
+    }
+
+    fn data_slice(&self) -> Option<(usize, usize)> {
+        Some(DECODE_SLICE)
     }
 }
 
@@ -111,6 +155,39 @@ mod tests {
         assert!(price > 0.0);
     }
 
+    #[test]
+    fn test_sliced_decode_matches_full_account() {
+        let whirlpool = WhirlpoolState {
+            whirlpool_bump: [254],
+            tick_spacing: 64,
+            tick_spacing_seed: [64, 0],
+            fee_rate: 3000,
+            protocol_fee_rate: 1300,
+            liquidity: 48_523_662_387_145,
+            sqrt_price: 7_959_534_116_359_219_211,
+            tick_current_index: -16_854,
+            protocol_fee_owed_a: 11,
+            protocol_fee_owed_b: 12,
+            token_mint_a: Pubkey::new_unique(),
+            token_vault_a: Pubkey::new_unique(),
+            fee_growth_global_a: 13,
+            token_mint_b: Pubkey::new_unique(),
+            token_vault_b: Pubkey::new_unique(),
+            fee_growth_global_b: 14,
+            reward_last_updated_timestamp: 1_700_000_000,
+        };
+        let mut account = vec![0u8; 8];
+        whirlpool.serialize(&mut account).unwrap();
+
+        let decoder = OrcaDecoder::new(9, 6);
+        let (offset, length) = decoder.data_slice().unwrap();
+        let full = decoder.decode(&account).unwrap();
+        let sliced = decoder.decode(&account[offset..offset + length]).unwrap();
+        assert_eq!(format!("{:?}", sliced), format!("{:?}", full));
+        assert_eq!(full.fee_rate, 0.3);
+        assert!(decoder.decode(&account[offset..offset + length - 1]).is_err());
+    }
+
     #[test]
     fn test_decoder_default() {
         let decoder = OrcaDecoder::default();
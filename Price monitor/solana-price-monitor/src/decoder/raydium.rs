@@ -1,6 +1,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 use super::{PoolDecoder, PoolState};
+use crate::models::Dex;
 use anyhow::Result;
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
@@ -32,6 +33,37 @@ pub struct RaydiumAmmInfo {
 
 pub struct RaydiumDecoder;
 
+impl RaydiumDecoder {
+    /// Account bytes with these decimals and vault balances, every other
+    /// field zeroed
+    pub fn encode(coin_decimals: u8, pc_decimals: u8, coin_vault_balance: u64, pc_vault_balance: u64) -> Vec<u8> {
+        let amm_info = RaydiumAmmInfo {
+            status: 0,
+            nonce: 0,
+            order_num: 0,
+            depth: 0,
+            coin_decimals: coin_decimals as u64,
+            pc_decimals: pc_decimals as u64,
+            state: 0,
+            reset_flag: 0,
+            min_size: 0,
+            vol_max_cut_ratio: 0,
+            amount_wave_ratio: 0,
+            coin_lot_size: 0,
+            pc_lot_size: 0,
+            min_price_multiplier: 0,
+            max_price_multiplier: 0,
+            sys_decimal_value: 0,
+            fees: [0; 8],
+            coin_vault: Pubkey::default(),
+            pc_vault: Pubkey::default(),
+            coin_vault_balance,
+            pc_vault_balance,
+        };
+        borsh::to_vec(&amm_info).expect("serializing to a Vec can't fail")
+    }
+}
+
 impl PoolDecoder for RaydiumDecoder {
     fn decode(&self, data: &[u8]) -> Result<PoolState> {
         // Raydium AMM layout is complex and has a header. 
@@ -58,7 +90,7 @@ impl PoolDecoder for RaydiumDecoder {
         })
     }
 
-    fn dex_name(&self) -> &'static str {
-        "raydium"
+    fn dex(&self) -> Dex {
+        Dex::Raydium
     }
 }
@@ -0,0 +1,298 @@
+//! Cross-detector opportunity aggregation
+//!
+//! A single mispriced pool typically surfaces through several strategies at
+//! once (one spatial arb plus every triangle touching the pool). Opportunities
+//! are fingerprinted by their (pair, DEX) legs and grouped while they share a
+//! leg within the aggregation window, so consumers see one dislocation once.
+
+use crate::calculator::UsdPricer;
+use crate::models::{Dex, EvKey, Opportunity, OpportunityType, Score, TopK};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Group of opportunities exposing the same underlying dislocation
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AggregatedOpportunity {
+    /// Mispriced (pair, DEX) legs shared by the group, as "PAIR@dex"
+    pub fingerprint: Vec<String>,
+    /// Highest ranked strategy (by expected value unless `[arbitrage]
+    /// rank_by` says otherwise)
+    pub best: Opportunity,
+    /// Every strategy exposing the dislocation (including `best`)
+    pub strategies: Vec<Opportunity>,
+}
+
+/// Score that picks the best strategy of a group
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankBy {
+    /// USD profit x confidence, priced routes first (`Opportunity::ev_key`)
+    #[default]
+    ExpectedValue,
+    /// Profit left if every leg moves against the trade before it lands
+    WorstCaseProfit,
+}
+
+impl RankBy {
+    fn key(self, opp: &Opportunity, usd: &UsdPricer) -> EvKey {
+        match self {
+            RankBy::ExpectedValue => opp.ev_key(usd),
+            // A percentage for every route, so none ranks on a fallback
+            RankBy::WorstCaseProfit => EvKey { priced: true, value: Score(opp.worst_case_profit_percent) },
+        }
+    }
+}
+
+struct PendingGroup {
+    legs: BTreeSet<String>,
+    opportunities: Vec<Opportunity>,
+    first_seen: Instant,
+}
+
+/// Groups duplicate opportunities from all detectors within a short window
+pub struct OpportunityAggregator {
+    window: Duration,
+    rank_by: RankBy,
+    usd: UsdPricer,
+    pending: Vec<PendingGroup>,
+}
+
+impl OpportunityAggregator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            rank_by: RankBy::default(),
+            usd: UsdPricer::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Pick each group's best strategy by `rank_by`
+    pub fn with_rank_by(mut self, rank_by: RankBy) -> Self {
+        self.rank_by = rank_by;
+        self
+    }
+
+    /// Price expected values in USD; without it every strategy ranks by
+    /// the fallback of `Opportunity::expected_value`
+    pub fn with_usd_pricer(mut self, usd: UsdPricer) -> Self {
+        self.usd = usd;
+        self
+    }
+
+    /// Add a detected opportunity
+    pub fn push(&mut self, opportunity: Opportunity) {
+        self.push_at(opportunity, Instant::now());
+    }
+
+    fn push_at(&mut self, opportunity: Opportunity, now: Instant) {
+        let legs = opportunity_legs(&opportunity);
+
+        // Merge every pending group sharing a leg with this opportunity
+        let mut merged = PendingGroup {
+            legs: legs.clone(),
+            opportunities: Vec::new(),
+            first_seen: now,
+        };
+        let mut i = 0;
+        while i < self.pending.len() {
+            if self.pending[i].legs.is_disjoint(&legs) {
+                i += 1;
+                continue;
+            }
+            let group = self.pending.swap_remove(i);
+            merged.legs.extend(group.legs);
+            merged.opportunities.extend(group.opportunities);
+            merged.first_seen = merged.first_seen.min(group.first_seen);
+        }
+
+        // A re-detection of the same strategy replaces the earlier one
+        merged.opportunities.retain(|existing| {
+            existing.opportunity_type != opportunity.opportunity_type
+                || opportunity_legs(existing) != legs
+        });
+        merged.opportunities.push(opportunity);
+
+        self.pending.push(merged);
+    }
+
+    /// Emit groups whose aggregation window has elapsed
+    pub fn flush(&mut self) -> Vec<AggregatedOpportunity> {
+        self.flush_at(Instant::now())
+    }
+
+    fn flush_at(&mut self, now: Instant) -> Vec<AggregatedOpportunity> {
+        let (ready, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|group| now.duration_since(group.first_seen) >= self.window);
+        self.pending = pending;
+
+        ready.into_iter().filter_map(|group| group.into_aggregate(self.rank_by, &self.usd)).collect()
+    }
+
+    /// Emit every pending group without waiting for its window (shutdown)
+    pub fn drain(&mut self) -> Vec<AggregatedOpportunity> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|group| group.into_aggregate(self.rank_by, &self.usd))
+            .collect()
+    }
+
+    /// Number of groups waiting for their window to elapse
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl PendingGroup {
+    fn into_aggregate(self, rank_by: RankBy, usd: &UsdPricer) -> Option<AggregatedOpportunity> {
+        let mut top = TopK::new(1);
+        for opportunity in &self.opportunities {
+            top.push_by(rank_by.key(opportunity, usd), opportunity);
+        }
+        let best = Opportunity::clone(top.best()?);
+
+        Some(AggregatedOpportunity {
+            fingerprint: self.legs.into_iter().collect(),
+            best,
+            strategies: self.opportunities,
+        })
+    }
+}
+
+/// (pair, DEX) legs an opportunity trades through, as "PAIR@dex"
+fn opportunity_legs(opp: &Opportunity) -> BTreeSet<String> {
+    let leg = |pair: &str, dex: &Dex| format!("{}@{}", normalize_pair(pair), dex);
+
+    match opp.opportunity_type {
+        OpportunityType::Spatial | OpportunityType::SpreadAlert => [
+            leg(&opp.token_pair, &opp.buy_dex),
+            leg(&opp.token_pair, &opp.sell_dex),
+        ]
+        .into_iter()
+        .collect(),
+        OpportunityType::Statistical => opp
+            .token_pair
+            .split(':')
+            .map(|pair| leg(pair, &opp.buy_dex))
+            .collect(),
+        OpportunityType::Triangular => {
+            // "SOL->USDC->BONK->SOL" trades SOL-USDC, USDC-BONK and BONK-SOL
+            let tokens: Vec<&str> = opp.token_pair.split("->").collect();
+            tokens
+                .windows(2)
+                .map(|w| leg(&format!("{}-{}", w[0], w[1]), &opp.buy_dex))
+                .collect()
+        }
+        // Pairs and venues vary per leg, so only the route says which they are
+        OpportunityType::BridgedSpatial | OpportunityType::CrossDexTriangular | OpportunityType::FourLegCycle => {
+            opp.legs.iter().map(|l| leg(&l.pair, &l.dex)).collect()
+        }
+    }
+}
+
+/// Normalize pair names so "sol_usdc" and "SOL-USDC" fingerprint identically
+fn normalize_pair(pair: &str) -> String {
+    pair.replace('_', "-").to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opportunity(opportunity_type: OpportunityType, token_pair: &str, buy: &str, sell: &str, profit: f64) -> Opportunity {
+        Opportunity::builder()
+            .opportunity_type(opportunity_type)
+            .token_pair(token_pair)
+            .buy(buy, 1.0, 0)
+            .sell(sell, 1.0 + profit / 100.0, 0)
+            .net_profit_percent(profit)
+            .recommended_size(1_000)
+            .confidence(0.8)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_single_mispriced_pool_aggregates() {
+        let mut aggregator = OpportunityAggregator::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        // SOL-USDC mispriced on raydium shows up in one spatial and two triangles
+        aggregator.push_at(opportunity(OpportunityType::Spatial, "sol_usdc", "raydium", "orca", 0.8), start);
+        aggregator.push_at(opportunity(OpportunityType::Triangular, "SOL->USDC->BONK->SOL", "raydium", "raydium", 1.2), start);
+        aggregator.push_at(opportunity(OpportunityType::Triangular, "SOL->USDC->JUP->SOL", "raydium", "raydium", 0.6), start);
+
+        assert!(aggregator.flush_at(start + Duration::from_millis(50)).is_empty());
+
+        let emitted = aggregator.flush_at(start + Duration::from_millis(100));
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].strategies.len(), 3);
+        assert_eq!(emitted[0].best.token_pair, "SOL->USDC->BONK->SOL");
+        assert!(emitted[0].fingerprint.contains(&"SOL-USDC@raydium".to_string()));
+        assert_eq!(aggregator.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_rank_by_worst_case_profit() {
+        let start = Instant::now();
+        // Higher expected profit, but on a volatile route
+        let mut risky = opportunity(OpportunityType::Triangular, "SOL->USDC->BONK->SOL", "raydium", "raydium", 1.2);
+        risky.worst_case_profit_percent = -0.4;
+        let steady = opportunity(OpportunityType::Spatial, "SOL-USDC", "raydium", "orca", 0.8);
+
+        let best = |rank_by| {
+            let mut aggregator = OpportunityAggregator::new(Duration::from_millis(100)).with_rank_by(rank_by);
+            aggregator.push_at(risky.clone(), start);
+            aggregator.push_at(steady.clone(), start);
+            aggregator.flush_at(start + Duration::from_millis(100)).remove(0).best.token_pair
+        };
+        assert_eq!(best(RankBy::ExpectedValue), "SOL->USDC->BONK->SOL");
+        assert_eq!(best(RankBy::WorstCaseProfit), "SOL-USDC");
+
+        // A NaN estimate never wins
+        let mut broken = risky.clone();
+        broken.net_profit_percent = f64::NAN;
+        let mut aggregator = OpportunityAggregator::new(Duration::from_millis(100));
+        aggregator.push_at(broken, start);
+        aggregator.push_at(steady.clone(), start);
+        assert_eq!(aggregator.flush_at(start + Duration::from_millis(100))[0].best.token_pair, "SOL-USDC");
+    }
+
+    #[test]
+    fn test_unrelated_dislocations_stay_separate() {
+        let mut aggregator = OpportunityAggregator::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        aggregator.push_at(opportunity(OpportunityType::Spatial, "SOL-USDC", "raydium", "orca", 0.8), start);
+        aggregator.push_at(opportunity(OpportunityType::Spatial, "JUP-SOL", "meteora", "orca", 0.7), start);
+
+        let emitted = aggregator.flush_at(start + Duration::from_millis(100));
+        assert_eq!(emitted.len(), 2);
+    }
+
+    #[test]
+    fn test_drain_emits_groups_inside_their_window() {
+        let mut aggregator = OpportunityAggregator::new(Duration::from_secs(60));
+        aggregator.push(opportunity(OpportunityType::Spatial, "SOL-USDC", "raydium", "orca", 0.8));
+
+        assert!(aggregator.flush().is_empty());
+        assert_eq!(aggregator.drain().len(), 1);
+        assert_eq!(aggregator.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_redetection_replaces_strategy() {
+        let mut aggregator = OpportunityAggregator::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        aggregator.push_at(opportunity(OpportunityType::Spatial, "SOL-USDC", "raydium", "orca", 0.8), start);
+        aggregator.push_at(opportunity(OpportunityType::Spatial, "SOL-USDC", "raydium", "orca", 0.9), start);
+
+        let emitted = aggregator.flush_at(start + Duration::from_millis(100));
+        assert_eq!(emitted[0].strategies.len(), 1);
+        assert_eq!(emitted[0].best.net_profit_percent, 0.9);
+    }
+}
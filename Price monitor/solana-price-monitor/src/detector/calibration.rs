@@ -0,0 +1,363 @@
+//! Confidence calibration from tracked opportunity outcomes
+//!
+//! Detector confidence scores are hand-tuned blends of slot alignment and
+//! liquidity. Each emitted opportunity is tracked for a short horizon and
+//! then revalidated against the cache; the realized success rate per
+//! confidence bin yields a monotone (isotonic) correction for future scores.
+
+use crate::cache::PriceCache;
+use crate::models::{Opportunity, OpportunityType};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Number of equal-width confidence bins over [0, 1]
+const BIN_COUNT: usize = 10;
+
+/// Fraction of the original gross spread that must remain for success
+const PERSISTENCE_RATIO: f64 = 0.5;
+
+/// Realized outcomes for one confidence bin
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalibrationBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub samples: u64,
+    pub successes: u64,
+    /// Monotone success rate after isotonic fitting (None until fitted)
+    pub calibrated: Option<f64>,
+}
+
+impl CalibrationBin {
+    /// Raw realized success rate
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.samples > 0).then(|| self.successes as f64 / self.samples as f64)
+    }
+}
+
+/// Persistable calibration table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationTable {
+    pub bins: Vec<CalibrationBin>,
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        let width = 1.0 / BIN_COUNT as f64;
+        Self {
+            bins: (0..BIN_COUNT)
+                .map(|i| CalibrationBin {
+                    lower: i as f64 * width,
+                    upper: (i + 1) as f64 * width,
+                    ..CalibrationBin::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl CalibrationTable {
+    fn bin_index(confidence: f64) -> usize {
+        ((confidence.clamp(0.0, 1.0) * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+    }
+
+    /// Total resolved outcomes across all bins
+    pub fn total_samples(&self) -> u64 {
+        self.bins.iter().map(|b| b.samples).sum()
+    }
+
+    /// Refit the monotone mapping with pool-adjacent-violators
+    fn fit(&mut self) {
+        // Blocks of (weighted rate, weight, bin indices)
+        let mut blocks: Vec<(f64, f64, Vec<usize>)> = Vec::new();
+        for (i, bin) in self.bins.iter().enumerate() {
+            let Some(rate) = bin.success_rate() else { continue };
+            blocks.push((rate, bin.samples as f64, vec![i]));
+
+            while blocks.len() > 1 && blocks[blocks.len() - 2].0 > blocks[blocks.len() - 1].0 {
+                let (rate_b, weight_b, bins_b) = blocks.pop().unwrap();
+                let (rate_a, weight_a, bins_a) = blocks.last_mut().unwrap();
+                *rate_a = (*rate_a * *weight_a + rate_b * weight_b) / (*weight_a + weight_b);
+                *weight_a += weight_b;
+                bins_a.extend(bins_b);
+            }
+        }
+
+        for bin in &mut self.bins {
+            bin.calibrated = None;
+        }
+        for (rate, _, indices) in blocks {
+            for i in indices {
+                self.bins[i].calibrated = Some(rate);
+            }
+        }
+    }
+}
+
+/// An emitted opportunity awaiting revalidation
+struct TrackedOpportunity {
+    opportunity: Opportunity,
+    raw_confidence: f64,
+    emitted_at: Instant,
+}
+
+/// Tracks emitted opportunities and learns a confidence correction
+pub struct ConfidenceCalibrator {
+    table: CalibrationTable,
+    pending: Vec<TrackedOpportunity>,
+    horizon: Duration,
+    min_samples: u64,
+    apply_correction: bool,
+}
+
+impl ConfidenceCalibrator {
+    pub fn new(horizon: Duration, min_samples: u64, apply_correction: bool) -> Self {
+        Self {
+            table: CalibrationTable::default(),
+            pending: Vec::new(),
+            horizon,
+            min_samples,
+            apply_correction,
+        }
+    }
+
+    /// Replace the table, e.g. with one loaded from disk
+    ///
+    /// A table with a different bin layout is ignored with a warning.
+    pub fn with_table(mut self, mut table: CalibrationTable) -> Self {
+        if table.bins.len() != BIN_COUNT {
+            warn!(bins = table.bins.len(), expected = BIN_COUNT, "Ignoring calibration table with a different bin count");
+            return self;
+        }
+        table.fit();
+        self.table = table;
+        self
+    }
+
+    /// Current calibration table
+    pub fn table(&self) -> &CalibrationTable {
+        &self.table
+    }
+
+    /// Record the raw confidence of an emitted opportunity and return the
+    /// corrected value to publish
+    pub fn track(&mut self, opportunity: &Opportunity) -> f64 {
+        self.pending.push(TrackedOpportunity {
+            opportunity: opportunity.clone(),
+            raw_confidence: opportunity.confidence,
+            emitted_at: Instant::now(),
+        });
+        self.calibrate(opportunity.confidence)
+    }
+
+    /// Map a raw confidence through the learned correction
+    ///
+    /// Returns the input unchanged until enough outcomes have been recorded
+    /// or when correction is disabled.
+    pub fn calibrate(&self, confidence: f64) -> f64 {
+        if !self.apply_correction || self.table.total_samples() < self.min_samples {
+            return confidence;
+        }
+        self.table.bins[CalibrationTable::bin_index(confidence)]
+            .calibrated
+            .unwrap_or(confidence)
+    }
+
+    /// Record a realized outcome for a raw confidence value
+    pub fn record_outcome(&mut self, raw_confidence: f64, success: bool) {
+        let bin = &mut self.table.bins[CalibrationTable::bin_index(raw_confidence)];
+        bin.samples += 1;
+        if success {
+            bin.successes += 1;
+        }
+        self.table.fit();
+    }
+
+    /// Revalidate tracked opportunities older than the horizon
+    ///
+    /// Returns the number of outcomes recorded.
+    pub fn resolve(&mut self, cache: &PriceCache) -> usize {
+        let now = Instant::now();
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|t| now.duration_since(t.emitted_at) >= self.horizon);
+        self.pending = pending;
+
+        let mut resolved = 0;
+        for tracked in due {
+            if let Some(success) = revalidate(&tracked.opportunity, cache) {
+                self.record_outcome(tracked.raw_confidence, success);
+                resolved += 1;
+            }
+        }
+        resolved
+    }
+
+    /// Persist a calibration table as JSON
+    ///
+    /// Writes on the blocking pool, so pass a copy of `table()` rather than
+    /// holding the calibrator's lock across the call. The JSON goes to a
+    /// temporary file that is renamed over `path`, so a crash mid-write
+    /// leaves the previous table in place.
+    pub async fn save_table(table: CalibrationTable, path: PathBuf) -> Result<()> {
+        tokio::task::spawn_blocking(move || {
+            let json = serde_json::to_string_pretty(&table)?;
+            write_atomically(&path, json.as_bytes())
+                .with_context(|| format!("Failed to write calibration table to {}", path.display()))
+        })
+        .await
+        .context("Calibration table writer panicked")?
+    }
+
+    /// Load a persisted calibration table
+    pub fn load_table(path: &Path) -> Result<CalibrationTable> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read calibration table from {}", path.display()))?;
+        serde_json::from_str(&json).context("Failed to parse calibration table")
+    }
+}
+
+/// Write `contents` to `<path>.tmp`, sync it and rename it over `path`
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = std::fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&temp, path)
+}
+
+/// Check whether an opportunity's spread persisted through the horizon
+///
+/// Success means the cache still shows at least half of the original gross
+/// spread. Returns `None` when the legs are no longer cached or the strategy
+/// cannot be revalidated from spot prices alone.
+fn revalidate(opp: &Opportunity, cache: &PriceCache) -> Option<bool> {
+    let original = opp.gross_profit_percent();
+
+    let current = match opp.opportunity_type {
+        OpportunityType::Spatial | OpportunityType::SpreadAlert => {
+            let buy = cache.get(&opp.token_pair, &opp.buy_dex)?;
+            let sell = cache.get(&opp.token_pair, &opp.sell_dex)?;
+            if buy.price == 0.0 {
+                return None;
+            }
+            (sell.price - buy.price) / buy.price * 100.0
+        }
+        OpportunityType::Triangular => {
+            let tokens: Vec<&str> = opp.token_pair.split("->").collect();
+            let mut amount = 1.0;
+            for leg in tokens.windows(2) {
+                let pair = format!("{}-{}", leg[0], leg[1]);
+                amount *= cache.get(&pair, &opp.buy_dex)?.price;
+            }
+            (amount - 1.0) * 100.0
+        }
+        // Cycles whose legs sit on different DEXs, priced leg by leg
+        OpportunityType::CrossDexTriangular | OpportunityType::FourLegCycle if !opp.legs.is_empty() => {
+            let mut amount = 1.0;
+            for leg in &opp.legs {
+                amount *= cache.get(&leg.pair, &leg.dex)?.price;
+            }
+            (amount - 1.0) * 100.0
+        }
+        OpportunityType::Statistical
+        | OpportunityType::BridgedSpatial
+        | OpportunityType::CrossDexTriangular
+        | OpportunityType::FourLegCycle => return None,
+    };
+
+    Some(current >= original * PERSISTENCE_RATIO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dex, PriceData};
+
+    fn spatial(confidence: f64) -> Opportunity {
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 0)
+            .sell("orca", 102.0, 0)
+            .net_profit_percent(1.0)
+            .recommended_size(1_000)
+            .confidence(confidence)
+            .worst_case_profit_percent(0.0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_correction_shifts_miscalibrated_bins() {
+        let mut calibrator = ConfidenceCalibrator::new(Duration::ZERO, 50, true);
+
+        // 0.9 confidence only succeeds 20% of the time, 0.3 succeeds 60%
+        for i in 0..50 {
+            calibrator.record_outcome(0.95, i % 5 == 0);
+            calibrator.record_outcome(0.35, i % 5 < 3);
+        }
+
+        // Monotone fit pools the violating bins to their weighted mean
+        assert!((calibrator.calibrate(0.95) - 0.4).abs() < 1e-9);
+        assert!((calibrator.calibrate(0.35) - 0.4).abs() < 1e-9);
+        // Bins without outcomes are left untouched
+        assert_eq!(calibrator.calibrate(0.55), 0.55);
+    }
+
+    #[test]
+    fn test_no_correction_below_min_samples() {
+        let mut calibrator = ConfidenceCalibrator::new(Duration::ZERO, 100, true);
+        for _ in 0..10 {
+            calibrator.record_outcome(0.9, false);
+        }
+        assert_eq!(calibrator.calibrate(0.9), 0.9);
+    }
+
+    #[test]
+    fn test_resolve_revalidates_against_cache() {
+        let cache = PriceCache::new(60, 2000);
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.1, 1_000_000, 1, 500_000, 500_000, 0.003));
+
+        let mut calibrator = ConfidenceCalibrator::new(Duration::ZERO, 1, true);
+        calibrator.track(&spatial(0.85));
+        assert_eq!(calibrator.resolve(&cache), 1);
+
+        // The 2% spread collapsed to 0.1%, so the 0.8-0.9 bin records a failure
+        let bin = &calibrator.table().bins[8];
+        assert_eq!((bin.samples, bin.successes), (1, 0));
+        assert_eq!(calibrator.calibrate(0.85), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_table_persistence_roundtrip() {
+        let mut calibrator = ConfidenceCalibrator::new(Duration::ZERO, 1, true);
+        calibrator.record_outcome(0.75, true);
+        calibrator.record_outcome(0.75, false);
+
+        let path = std::env::temp_dir().join(format!("calibration-{}.json", std::process::id()));
+        // Replaces whatever was there, leaving no temporary file behind
+        std::fs::write(&path, "{ truncated").unwrap();
+        ConfidenceCalibrator::save_table(calibrator.table().clone(), path.clone()).await.unwrap();
+        let table = ConfidenceCalibrator::load_table(&path).unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+        std::fs::remove_file(&path).ok();
+
+        let restored = ConfidenceCalibrator::new(Duration::ZERO, 1, true).with_table(table);
+        assert_eq!(restored.table().bins[7].samples, 2);
+        assert!((restored.calibrate(0.75) - 0.5).abs() < 1e-9);
+
+        // A table from a different bin layout is not applied
+        let coarse = CalibrationTable { bins: restored.table().bins[..5].to_vec() };
+        let ignored = ConfidenceCalibrator::new(Duration::ZERO, 1, true).with_table(coarse);
+        assert_eq!(ignored.table().total_samples(), 0);
+        assert_eq!(ignored.table().bins.len(), BIN_COUNT);
+    }
+}
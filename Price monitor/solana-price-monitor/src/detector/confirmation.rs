@@ -0,0 +1,111 @@
+//! Multi-slot confirmation of detected spreads
+//!
+//! Single-slot blips from a large swap are usually closed by faster bots
+//! before anyone else can act. Detectors record qualifying spreads here and
+//! only emit once the same venue combination has qualified for the required
+//! number of consecutive distinct slots.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+struct Candidate {
+    venue: String,
+    last_slot: u64,
+    slots: u32,
+}
+
+/// Tracks how many consecutive slots each candidate spread has persisted
+pub struct SlotConfirmation {
+    required_slots: u32,
+    candidates: Mutex<HashMap<String, Candidate>>,
+}
+
+impl SlotConfirmation {
+    /// `required_slots = 0` emits on first detection (no confirmation)
+    pub fn new(required_slots: u32) -> Self {
+        Self {
+            required_slots,
+            candidates: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn required_slots(&self) -> u32 {
+        self.required_slots
+    }
+
+    /// Record a qualifying spread for `key` on `venue` at `slot`
+    ///
+    /// Returns the number of slots the spread has persisted once it meets
+    /// the requirement, `None` while it is still being confirmed. A change
+    /// of venue or a skipped slot restarts the count; repeated updates
+    /// within one slot don't advance it.
+    pub fn confirm(&self, key: &str, venue: &str, slot: u64) -> Option<u32> {
+        let mut candidates = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+
+        let candidate = candidates
+            .entry(key.to_string())
+            .and_modify(|c| {
+                if c.venue != venue || slot > c.last_slot + 1 {
+                    *c = Candidate { venue: venue.to_string(), last_slot: slot, slots: 1 };
+                } else if slot == c.last_slot + 1 {
+                    c.last_slot = slot;
+                    c.slots += 1;
+                }
+            })
+            .or_insert_with(|| Candidate { venue: venue.to_string(), last_slot: slot, slots: 1 });
+
+        (candidate.slots >= self.required_slots.max(1)).then_some(candidate.slots)
+    }
+
+    /// Forget a candidate whose spread no longer qualifies
+    pub fn reset(&self, key: &str) {
+        self.candidates.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+    }
+
+    /// Forget every candidate, returning how many were being tracked
+    pub fn clear(&self) -> usize {
+        let mut candidates = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+        let cleared = candidates.len();
+        candidates.clear();
+        cleared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_required_emits_immediately() {
+        let confirmation = SlotConfirmation::new(0);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 10), Some(1));
+    }
+
+    #[test]
+    fn test_same_slot_does_not_advance() {
+        let confirmation = SlotConfirmation::new(2);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 10), None);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 10), None);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 11), Some(2));
+    }
+
+    #[test]
+    fn test_venue_change_restarts_count() {
+        let confirmation = SlotConfirmation::new(2);
+        confirmation.confirm("SOL-USDC", "raydium>orca", 10);
+        assert_eq!(confirmation.confirm("SOL-USDC", "meteora>orca", 11), None);
+    }
+
+    #[test]
+    fn test_slot_gap_restarts_count() {
+        let confirmation = SlotConfirmation::new(3);
+        confirmation.confirm("SOL-USDC", "raydium>orca", 10);
+        confirmation.confirm("SOL-USDC", "raydium>orca", 11);
+        // The spread wasn't seen at 12
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 13), None);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 14), None);
+        // An older slot arriving late neither counts nor resets
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 12), None);
+        assert_eq!(confirmation.confirm("SOL-USDC", "raydium>orca", 15), Some(3));
+    }
+}
@@ -1,10 +1,32 @@
 //! Opportunity detection module
 
+mod aggregator;
+mod calibration;
+mod confirmation;
+mod pair_summary;
+mod regime;
+mod risk;
 mod spatial;
+mod spread_alert;
+mod spreads;
 mod statistical;
 mod triangular;
+mod universe;
 
+pub use aggregator::{AggregatedOpportunity, OpportunityAggregator, RankBy};
+pub use calibration::{CalibrationBin, CalibrationTable, ConfidenceCalibrator};
+pub use confirmation::SlotConfirmation;
+pub use pair_summary::PairSummaries;
+pub use regime::{PairRegime, VolatilityTracker};
+pub use risk::RiskModel;
 pub use spatial::{detect_spatial_arbitrage, OpportunityDetector};
+pub use spread_alert::{SpreadAlert, SpreadAlertDetector};
+pub use spreads::{pair_spread, SpreadTracker};
+pub use crate::models::{Spread, SpreadVenue};
 pub use statistical::{StatisticalArbitrageDetector, StatArbConfig, PairStatistics};
-pub use triangular::{TriangularArbitrageDetector, TriangularArbConfig, TriangularPath, generate_common_paths};
+pub use triangular::{
+    TriangularArbitrageDetector, TriangularArbConfig, TriangularPath, TriangularPathSet, PathEntry,
+    PathSetMetrics, generate_common_paths,
+};
+pub use universe::{PairUniverse, StatArbCandidate};
 
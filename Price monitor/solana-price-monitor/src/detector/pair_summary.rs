@@ -0,0 +1,286 @@
+//! One `OpportunitySet` per pair
+//!
+//! A client showing a pair wants its best opportunities, spread and venue
+//! freshness together rather than from four endpoints. `PairSummaries` keeps
+//! the opportunities emitted per pair over a window; new prices and new
+//! opportunities mark the pair dirty, and its set is rebuilt from the cache
+//! the next time it is asked for.
+
+use crate::cache::PriceCache;
+use crate::detector::{pair_spread, AggregatedOpportunity};
+use crate::models::{Opportunity, OpportunitySet, VenueFreshness};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Opportunities kept per pair, however many the window holds
+const MAX_RECENT: usize = 1000;
+
+#[derive(Default)]
+struct PairState {
+    /// Oldest first
+    recent: VecDeque<Opportunity>,
+    /// Inputs changed since `set` was built
+    dirty: bool,
+    /// Changed since it was last taken for broadcast
+    unsent: bool,
+    set: Option<OpportunitySet>,
+}
+
+/// Summaries of every pair with prices or opportunities
+pub struct PairSummaries {
+    cache: Arc<PriceCache>,
+    /// How far back `recent_opportunities` and the best opportunities reach
+    window: Duration,
+    pairs: Mutex<HashMap<String, PairState>>,
+}
+
+impl PairSummaries {
+    pub fn new(cache: Arc<PriceCache>, window: Duration) -> Self {
+        Self { cache, window, pairs: Mutex::new(HashMap::new()) }
+    }
+
+    /// The pair's prices changed
+    pub fn mark_dirty(&self, pair: &str) {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pairs.entry(pair.to_string()).or_default();
+        state.dirty = true;
+        state.unsent = true;
+    }
+
+    /// An opportunity was emitted; it counts for every pair it trades
+    pub fn record(&self, opportunity: &Opportunity) {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        for pair in opportunity.pairs() {
+            let state = pairs.entry(pair.to_string()).or_default();
+            if state.recent.len() == MAX_RECENT {
+                state.recent.pop_front();
+            }
+            state.recent.push_back(opportunity.clone());
+            state.dirty = true;
+            state.unsent = true;
+        }
+    }
+
+    pub fn record_group(&self, group: &AggregatedOpportunity) {
+        for opportunity in &group.strategies {
+            self.record(opportunity);
+        }
+    }
+
+    /// Current set of `pair`; `None` when it has neither cached prices nor
+    /// recent opportunities
+    pub fn summary(&self, pair: &str) -> Option<OpportunitySet> {
+        self.summary_at(pair, Utc::now())
+    }
+
+    fn summary_at(&self, pair: &str, now: DateTime<Utc>) -> Option<OpportunitySet> {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        if !pairs.contains_key(pair) && self.cache.get_all_dexes(pair).is_empty() {
+            return None;
+        }
+        let state = pairs.entry(pair.to_string()).or_default();
+        let set = self.current(pair, state, now);
+        (!set.venues.is_empty() || set.recent_opportunities > 0).then(|| set.clone())
+    }
+
+    /// Sets of the pairs that changed since the last call, by pair name
+    pub fn take_changed(&self) -> Vec<OpportunitySet> {
+        self.take_changed_at(Utc::now())
+    }
+
+    fn take_changed_at(&self, now: DateTime<Utc>) -> Vec<OpportunitySet> {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed: Vec<OpportunitySet> = pairs
+            .iter_mut()
+            .filter(|(_, state)| state.unsent || state.set.as_ref().is_some_and(|set| set.has_expired_best(now)))
+            .map(|(pair, state)| {
+                state.unsent = false;
+                self.current(pair, state, now).clone()
+            })
+            .collect();
+        changed.sort_by(|a, b| a.pair.cmp(&b.pair));
+        changed
+    }
+
+    /// Forget every pair's opportunities; returns how many pairs were tracked
+    pub fn reset(&self) -> usize {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let cleared = pairs.len();
+        pairs.clear();
+        cleared
+    }
+
+    /// The pair's set, rebuilt first if its inputs changed or a best
+    /// opportunity in it expired
+    fn current<'a>(&self, pair: &str, state: &'a mut PairState, now: DateTime<Utc>) -> &'a OpportunitySet {
+        let expired = state.set.as_ref().map_or(true, |set| set.has_expired_best(now));
+        if state.dirty || expired {
+            state.set = Some(self.build(pair, &mut state.recent, now));
+            state.dirty = false;
+        }
+        state.set.as_ref().expect("built above")
+    }
+
+    fn build(&self, pair: &str, recent: &mut VecDeque<Opportunity>, now: DateTime<Utc>) -> OpportunitySet {
+        let since = chrono::Duration::from_std(self.window).ok().and_then(|window| now.checked_sub_signed(window));
+        if let Some(since) = since {
+            while recent.front().is_some_and(|o| o.detected_at < since) {
+                recent.pop_front();
+            }
+        }
+        let best = |matches: fn(&Opportunity) -> bool| {
+            recent
+                .iter()
+                .filter(|o| matches(o) && !o.is_expired_at(now))
+                .max_by(|a, b| a.cmp_by_profit_pct(b))
+                .cloned()
+        };
+
+        let mut venues: Vec<VenueFreshness> = self
+            .cache
+            .get_all_dexes(pair)
+            .into_iter()
+            .map(|(dex, data)| VenueFreshness {
+                stale: self.cache.is_stale(pair, &dex, &data),
+                age_ms: (now - data.timestamp).num_milliseconds().max(0) as u64,
+                dex,
+                price: data.price,
+                slot: data.slot,
+                timestamp: data.timestamp,
+            })
+            .collect();
+        venues.sort_by(|a, b| a.dex.cmp(&b.dex));
+
+        OpportunitySet {
+            pair: pair.to_string(),
+            best_spatial: best(|o| o.opportunity_type.is_spatial()),
+            best_triangular: best(|o| o.opportunity_type.is_cycle()),
+            spread: pair_spread(&self.cache, pair),
+            venues,
+            recent_opportunities: recent.len(),
+            window_seconds: self.window.as_secs(),
+            updated_at: now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dex, LegSide, OpportunityType, PriceData, RouteLeg};
+
+    fn price(price: f64, slot: u64) -> PriceData {
+        PriceData::new(price, 1_000_000, slot, 500_000, 500_000, 0.003)
+    }
+
+    fn opportunity(opportunity_type: OpportunityType, legs: &[&str], net_profit_percent: f64) -> Opportunity {
+        let legs = legs
+            .iter()
+            .map(|pair| RouteLeg::new(pair, "raydium", LegSide::Buy, &price(100.0, 1)))
+            .collect();
+        Opportunity::builder()
+            .opportunity_type(opportunity_type)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 1)
+            .sell("orca", 101.0, 1)
+            .legs(legs)
+            .net_profit_percent(net_profit_percent)
+            .recommended_size(1_000)
+            .confidence(0.8)
+            .build()
+            .unwrap()
+    }
+
+    fn seeded() -> (Arc<PriceCache>, PairSummaries) {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, price(100.0, 10));
+        cache.set("SOL-USDC", &Dex::Orca, price(100.3, 12));
+        let mut stale = price(99.0, 3);
+        stale.timestamp -= chrono::Duration::seconds(45);
+        cache.set("SOL-USDC", &Dex::Meteora, stale);
+        let summaries = PairSummaries::new(cache.clone(), Duration::from_secs(300));
+        (cache, summaries)
+    }
+
+    #[test]
+    fn test_summary_from_seeded_components() {
+        let (_cache, summaries) = seeded();
+        summaries.record(&opportunity(OpportunityType::Spatial, &["SOL-USDC", "SOL-USDC"], 0.4));
+        summaries.record(&opportunity(OpportunityType::Spatial, &["SOL-USDC", "SOL-USDC"], 0.9));
+        summaries.record(&opportunity(OpportunityType::Triangular, &["SOL-USDC", "BONK-SOL", "BONK-USDC"], 1.2));
+        summaries.record(&opportunity(OpportunityType::Statistical, &[], 2.0));
+        summaries.record(&opportunity(OpportunityType::Spatial, &["JUP-USDC", "JUP-USDC"], 5.0));
+
+        let set = summaries.summary("SOL-USDC").unwrap();
+        assert_eq!(set.best_spatial.as_ref().unwrap().net_profit_percent, 0.9);
+        assert_eq!(set.best_triangular.as_ref().unwrap().net_profit_percent, 1.2);
+        assert_eq!(set.recent_opportunities, 4);
+        assert_eq!(set.window_seconds, 300);
+        let spread = set.spread.unwrap();
+        assert_eq!((spread.ask_dex.as_str(), spread.bid_dex.as_str()), ("raydium", "orca"));
+        assert_eq!(spread.venues.len(), 2);
+        let venues: Vec<_> = set.venues.iter().map(|v| (v.dex.as_str(), v.slot, v.stale)).collect();
+        assert_eq!(venues, vec![("meteora", 3, true), ("orca", 12, false), ("raydium", 10, false)]);
+        assert!(set.venues[0].age_ms >= 45_000);
+
+        // A cycle counts on every pair it trades
+        let bonk = summaries.summary("BONK-SOL").unwrap();
+        assert!(bonk.best_spatial.is_none() && bonk.spread.is_none() && bonk.venues.is_empty());
+        assert_eq!(bonk.best_triangular.unwrap().net_profit_percent, 1.2);
+        assert!(summaries.summary("WIF-USDC").is_none());
+    }
+
+    #[test]
+    fn test_rebuilds_lazily_when_dirty() {
+        let (cache, summaries) = seeded();
+        let first = summaries.summary("SOL-USDC").unwrap();
+        cache.set("SOL-USDC", &Dex::Raydium, price(99.5, 13));
+        // Not marked dirty yet: the cached set is served
+        assert_eq!(summaries.summary("SOL-USDC").unwrap().updated_at, first.updated_at);
+
+        summaries.mark_dirty("SOL-USDC");
+        let set = summaries.summary("SOL-USDC").unwrap();
+        assert_eq!(set.spread.unwrap().ask_price, 99.5);
+        assert_eq!(set.venues.iter().find(|v| v.dex == Dex::Raydium).unwrap().slot, 13);
+    }
+
+    #[test]
+    fn test_take_changed_and_window() {
+        let (_cache, summaries) = seeded();
+        summaries.mark_dirty("SOL-USDC");
+        summaries.record(&opportunity(OpportunityType::Spatial, &["JUP-USDC"], 0.5));
+        let changed: Vec<_> = summaries.take_changed().into_iter().map(|set| set.pair).collect();
+        assert_eq!(changed, vec!["JUP-USDC", "SOL-USDC"]);
+        assert!(summaries.take_changed().is_empty());
+
+        // Opportunities age out of the window at the next rebuild
+        summaries.mark_dirty("JUP-USDC");
+        let later = Utc::now() + chrono::Duration::seconds(301);
+        let changed = summaries.take_changed_at(later);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].recent_opportunities, 0);
+        assert!(changed[0].best_spatial.is_none());
+    }
+
+    #[test]
+    fn test_best_skips_expired_opportunities() {
+        let (_cache, summaries) = seeded();
+        let now = Utc::now();
+        // Valid for the builder's default 2s
+        summaries.record(&opportunity(OpportunityType::Spatial, &["SOL-USDC", "SOL-USDC"], 0.9));
+        let lasting = opportunity(OpportunityType::Spatial, &["SOL-USDC", "SOL-USDC"], 0.4);
+        summaries.record(&Opportunity { valid_until: now + chrono::Duration::seconds(60), ..lasting });
+        assert_eq!(summaries.summary_at("SOL-USDC", now).unwrap().best_spatial.unwrap().net_profit_percent, 0.9);
+        summaries.take_changed_at(now);
+
+        // Expiry alone rebuilds the set and marks it changed
+        let later = now + chrono::Duration::seconds(3);
+        let changed = summaries.take_changed_at(later);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].best_spatial.as_ref().unwrap().net_profit_percent, 0.4);
+        assert_eq!(changed[0].recent_opportunities, 2);
+        assert!(summaries.take_changed_at(later).is_empty());
+    }
+}
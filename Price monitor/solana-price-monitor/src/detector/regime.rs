@@ -0,0 +1,236 @@
+//! Volatility-regime detection
+//!
+//! A fixed `min_profit_percent` misses small reliable spreads in calm
+//! markets and floods with vanishing spreads in chaotic ones. Realized
+//! volatility (rolling stddev of log returns per pair) classifies each pair
+//! into a regime whose multipliers scale the detectors' thresholds.
+//!
+//! Returns are timestamped, so the same history also projects how far a
+//! pair's price can move over a time horizon (see `volatility_over`).
+
+use crate::config::RegimeConfig;
+use crate::models::VolatilityRegime;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Returns needed before a pair leaves the default `Normal` regime
+const MIN_RETURNS: usize = 10;
+
+#[derive(Default)]
+struct PairVolatility {
+    /// Last observed price per DEX, so returns never mix venues
+    last_prices: HashMap<String, f64>,
+    returns: VecDeque<f64>,
+    /// When each of `returns` was observed
+    returned_at: VecDeque<Instant>,
+}
+
+impl PairVolatility {
+    fn stddev_bps(&self) -> Option<f64> {
+        if self.returns.len() < MIN_RETURNS {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let mean = self.returns.iter().sum::<f64>() / n;
+        let variance = self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        Some(variance.sqrt() * 10_000.0)
+    }
+
+    /// Updates per second of one venue over the window
+    fn update_rate(&self) -> Option<f64> {
+        let span = self.returned_at.back()?.duration_since(*self.returned_at.front()?);
+        if span.is_zero() || self.last_prices.is_empty() {
+            return None;
+        }
+        // Each venue's series moves on its own updates only
+        Some((self.returns.len() - 1) as f64 / span.as_secs_f64() / self.last_prices.len() as f64)
+    }
+}
+
+/// Current regime of a pair for metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct PairRegime {
+    pub regime: VolatilityRegime,
+    /// Realized volatility per update (basis points), if enough history
+    pub volatility_bps: Option<f64>,
+}
+
+/// Per-pair realized volatility estimator and regime classifier
+pub struct VolatilityTracker {
+    config: RegimeConfig,
+    pairs: Mutex<HashMap<String, PairVolatility>>,
+}
+
+impl VolatilityTracker {
+    pub fn new(config: RegimeConfig) -> Self {
+        Self {
+            config,
+            pairs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forget every pair's price history; returns how many pairs had one
+    pub fn reset(&self) -> usize {
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let cleared = pairs.len();
+        pairs.clear();
+        cleared
+    }
+
+    /// Record a price update for a pair on a DEX
+    pub fn observe(&self, pair: &str, dex: &str, price: f64) {
+        self.observe_at(pair, dex, price, Instant::now());
+    }
+
+    pub(crate) fn observe_at(&self, pair: &str, dex: &str, price: f64, at: Instant) {
+        if price <= 0.0 {
+            return;
+        }
+
+        let mut pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pairs.entry(pair.to_string()).or_default();
+
+        if let Some(last) = state.last_prices.insert(dex.to_string(), price) {
+            state.returns.push_back((price / last).ln());
+            state.returned_at.push_back(at);
+            while state.returns.len() > self.config.window_size {
+                state.returns.pop_front();
+                state.returned_at.pop_front();
+            }
+        }
+    }
+
+    fn classify(&self, volatility_bps: Option<f64>) -> VolatilityRegime {
+        match volatility_bps {
+            Some(v) if v < self.config.low_volatility_bps => VolatilityRegime::Low,
+            Some(v) if v > self.config.high_volatility_bps => VolatilityRegime::High,
+            _ => VolatilityRegime::Normal,
+        }
+    }
+
+    /// Current regime of a pair (`Normal` until enough history)
+    pub fn regime(&self, pair: &str) -> VolatilityRegime {
+        let pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        self.classify(pairs.get(pair).and_then(PairVolatility::stddev_bps))
+    }
+
+    /// Standard deviation of a pair's log price over `horizon`, as a
+    /// fraction; `None` until enough timestamped history
+    ///
+    /// Scales the per-update volatility by the square root of how many
+    /// updates a venue sees in `horizon`, treating the price as a random walk.
+    pub fn volatility_over(&self, pair: &str, horizon: Duration) -> Option<f64> {
+        let pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pairs.get(pair)?;
+        let per_update = state.stddev_bps()? / 10_000.0;
+        Some(per_update * (state.update_rate()? * horizon.as_secs_f64()).sqrt())
+    }
+
+    /// Regime and realized volatility of every tracked pair
+    pub fn snapshot(&self) -> HashMap<String, PairRegime> {
+        let pairs = self.pairs.lock().unwrap_or_else(|e| e.into_inner());
+        pairs
+            .iter()
+            .map(|(pair, state)| {
+                let volatility_bps = state.stddev_bps();
+                (pair.clone(), PairRegime { regime: self.classify(volatility_bps), volatility_bps })
+            })
+            .collect()
+    }
+
+    /// Scale base thresholds by the multipliers of a regime
+    ///
+    /// Returns the effective `(min_profit_percent, slot_tolerance)`.
+    pub fn effective_thresholds(
+        &self,
+        regime: VolatilityRegime,
+        min_profit_percent: f64,
+        slot_tolerance: u64,
+    ) -> (f64, u64) {
+        let (profit_multiplier, slot_multiplier) = match regime {
+            VolatilityRegime::Low => (
+                self.config.low_min_profit_multiplier,
+                self.config.low_slot_tolerance_multiplier,
+            ),
+            VolatilityRegime::Normal => (1.0, 1.0),
+            VolatilityRegime::High => (
+                self.config.high_min_profit_multiplier,
+                self.config.high_slot_tolerance_multiplier,
+            ),
+        };
+
+        (
+            min_profit_percent * profit_multiplier,
+            (slot_tolerance as f64 * slot_multiplier).round() as u64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed a price series alternating by `step` around 100
+    fn tracker_with_series(step: f64) -> VolatilityTracker {
+        let tracker = VolatilityTracker::new(RegimeConfig::default());
+        for i in 0..50 {
+            let price = if i % 2 == 0 { 100.0 } else { 100.0 + step };
+            tracker.observe("SOL-USDC", "raydium", price);
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_regime_classification() {
+        // 0.1 bps moves are calm, 2% moves are chaotic
+        assert_eq!(tracker_with_series(0.001).regime("SOL-USDC"), VolatilityRegime::Low);
+        assert_eq!(tracker_with_series(2.0).regime("SOL-USDC"), VolatilityRegime::High);
+        assert_eq!(tracker_with_series(2.0).regime("JUP-USDC"), VolatilityRegime::Normal);
+    }
+
+    #[test]
+    fn test_effective_thresholds_differ_by_regime() {
+        let calm = tracker_with_series(0.001);
+        let chaotic = tracker_with_series(2.0);
+
+        let (calm_profit, calm_slots) =
+            calm.effective_thresholds(calm.regime("SOL-USDC"), 0.5, 2);
+        let (chaotic_profit, chaotic_slots) =
+            chaotic.effective_thresholds(chaotic.regime("SOL-USDC"), 0.5, 2);
+
+        assert!(calm_profit < 0.5);
+        assert!(chaotic_profit > 0.5);
+        assert!(calm_slots > chaotic_slots);
+    }
+
+    #[test]
+    fn test_volatility_scales_with_horizon() {
+        let tracker = VolatilityTracker::new(RegimeConfig::default());
+        let start = Instant::now();
+        // 10 updates a second, alternating 1% apart
+        for i in 0..50u64 {
+            let price = if i % 2 == 0 { 100.0 } else { 101.0 };
+            tracker.observe_at("SOL-USDC", "raydium", price, start + Duration::from_millis(i * 100));
+        }
+
+        let one_second = tracker.volatility_over("SOL-USDC", Duration::from_secs(1)).unwrap();
+        let four_seconds = tracker.volatility_over("SOL-USDC", Duration::from_secs(4)).unwrap();
+        let per_update = tracker.snapshot()["SOL-USDC"].volatility_bps.unwrap() / 10_000.0;
+        assert!((one_second - per_update * 10f64.sqrt()).abs() < 1e-9);
+        assert!((four_seconds - 2.0 * one_second).abs() < 1e-9);
+        assert_eq!(tracker.volatility_over("JUP-USDC", Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn test_returns_do_not_mix_venues() {
+        let tracker = VolatilityTracker::new(RegimeConfig::default());
+        // Two venues quoting a stable but different price
+        for _ in 0..20 {
+            tracker.observe("SOL-USDC", "raydium", 100.0);
+            tracker.observe("SOL-USDC", "orca", 103.0);
+        }
+        assert_eq!(tracker.regime("SOL-USDC"), VolatilityRegime::Low);
+    }
+}
@@ -0,0 +1,195 @@
+//! Downside of an opportunity if its prices move before it executes
+//!
+//! Expected profit assumes the quoted prices hold until the trade lands. Over
+//! the expected execution latency (the configured landing time plus the
+//! measured scan latency) each leg can move against the trade by its pair's
+//! realized volatility; `RiskModel` prices that in as worst-case profit and
+//! bounds how long the opportunity stays worth acting on.
+
+use crate::config::ArbitrageConfig;
+use crate::detector::VolatilityTracker;
+use crate::metrics::PipelineMetrics;
+use crate::models::Opportunity;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Worst-case profit and validity of detected opportunities
+#[derive(Clone)]
+pub struct RiskModel {
+    execution_latency: Duration,
+    max_age: Duration,
+    volatility: Option<Arc<VolatilityTracker>>,
+    metrics: Option<Arc<PipelineMetrics>>,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        Self::new(&ArbitrageConfig::default())
+    }
+}
+
+impl RiskModel {
+    /// Latency and max age from `[arbitrage]`, with no volatility or latency
+    /// measurements yet (worst case = expected)
+    pub fn new(arbitrage: &ArbitrageConfig) -> Self {
+        Self {
+            execution_latency: Duration::from_millis(arbitrage.execution_latency_ms),
+            max_age: Duration::from_millis(arbitrage.max_opportunity_age_ms),
+            volatility: None,
+            metrics: None,
+        }
+    }
+
+    /// Move each leg by its pair's realized volatility
+    pub fn with_volatility_tracker(mut self, tracker: Arc<VolatilityTracker>) -> Self {
+        self.volatility = Some(tracker);
+        self
+    }
+
+    /// Add the recent p95 scan latency to the execution latency
+    pub fn with_pipeline_metrics(mut self, metrics: Arc<PipelineMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Apply reloaded `[arbitrage]` latency and max age
+    pub fn reconfigure(&mut self, arbitrage: &ArbitrageConfig) {
+        self.execution_latency = Duration::from_millis(arbitrage.execution_latency_ms);
+        self.max_age = Duration::from_millis(arbitrage.max_opportunity_age_ms);
+    }
+
+    /// Time from detection until the trade lands
+    pub fn expected_latency(&self) -> Duration {
+        let scan = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| metrics.scan().percentile(0.95))
+            .map_or(Duration::ZERO, Duration::from_micros);
+        self.execution_latency + scan
+    }
+
+    /// Summed adverse move of the legs over `horizon` (percentage); pairs
+    /// without enough history count as not moving
+    fn adverse_move_percent(&self, opp: &Opportunity, horizon: Duration) -> f64 {
+        let Some(tracker) = &self.volatility else {
+            return 0.0;
+        };
+        let pairs: Vec<&str> = if opp.legs.is_empty() {
+            // Buy and sell sides of a single pair
+            vec![&opp.token_pair, &opp.token_pair]
+        } else {
+            opp.legs.iter().map(|leg| leg.pair.as_str()).collect()
+        };
+        pairs.into_iter().map(|pair| tracker.volatility_over(pair, horizon).unwrap_or(0.0) * 100.0).sum()
+    }
+
+    /// Set `worst_case_profit_percent` and `valid_until` of `opp`
+    ///
+    /// Moves grow with the square root of time, so the profit is gone after
+    /// `latency * (net / adverse)^2`; `valid_until` is that, capped at the
+    /// max age.
+    pub fn assess(&self, opp: &mut Opportunity) {
+        let horizon = self.expected_latency();
+        let adverse = self.adverse_move_percent(opp, horizon);
+        opp.worst_case_profit_percent = opp.net_profit_percent - adverse;
+
+        let max_age = self.max_age.as_secs_f64();
+        let lifetime = if adverse > 0.0 {
+            let ratio = opp.net_profit_percent.max(0.0) / adverse;
+            (horizon.as_secs_f64() * ratio * ratio).min(max_age)
+        } else {
+            max_age
+        };
+        opp.valid_until =
+            opp.detected_at + chrono::Duration::from_std(Duration::from_secs_f64(lifetime)).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RegimeConfig;
+    use crate::models::{LegSide, OpportunityType, PriceData, RouteLeg};
+    use std::time::Instant;
+
+    fn opportunity(net_profit_percent: f64) -> Opportunity {
+        let leg = |dex, side, price| RouteLeg::new("SOL-USDC", dex, side, &PriceData::new(price, 1, 10, 1, 1, 0.003));
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 10)
+            .sell("orca", 101.0, 10)
+            .legs(vec![leg("raydium", LegSide::Buy, 100.0), leg("orca", LegSide::Sell, 101.0)])
+            .net_profit_percent(net_profit_percent)
+            .recommended_size(1_000)
+            .confidence(0.9)
+            .build()
+            .unwrap()
+    }
+
+    /// SOL-USDC updating 10 times a second, `step_percent` apart
+    fn tracker(step_percent: f64) -> Arc<VolatilityTracker> {
+        let tracker = VolatilityTracker::new(RegimeConfig::default());
+        let start = Instant::now();
+        for i in 0..50u64 {
+            let price = if i % 2 == 0 { 100.0 } else { 100.0 * (1.0 + step_percent / 100.0) };
+            tracker.observe_at("SOL-USDC", "raydium", price, start + Duration::from_millis(i * 100));
+        }
+        Arc::new(tracker)
+    }
+
+    fn config(execution_latency_ms: u64, max_opportunity_age_ms: u64) -> ArbitrageConfig {
+        ArbitrageConfig { execution_latency_ms, max_opportunity_age_ms, ..ArbitrageConfig::default() }
+    }
+
+    #[test]
+    fn test_worst_case_never_above_expected() {
+        for step in [0.0, 0.01, 0.5, 5.0] {
+            let risk = RiskModel::new(&config(400, 2000)).with_volatility_tracker(tracker(step));
+            let mut opp = opportunity(0.6);
+            risk.assess(&mut opp);
+            assert!(opp.worst_case_profit_percent <= opp.net_profit_percent, "step {}", step);
+        }
+
+        // A volatile pair over a longer latency risks more
+        let mut calm = opportunity(0.6);
+        RiskModel::new(&config(400, 2000)).with_volatility_tracker(tracker(0.05)).assess(&mut calm);
+        let mut slow = opportunity(0.6);
+        RiskModel::new(&config(1600, 2000)).with_volatility_tracker(tracker(0.05)).assess(&mut slow);
+        assert!(calm.worst_case_profit_percent < 0.6);
+        assert!(slow.worst_case_profit_percent < calm.worst_case_profit_percent);
+    }
+
+    #[test]
+    fn test_valid_until_respects_max_age() {
+        let max_age = chrono::Duration::milliseconds(1500);
+
+        // No volatility history: valid for the full max age
+        let mut opp = opportunity(0.6);
+        RiskModel::new(&config(400, 1500)).assess(&mut opp);
+        assert_eq!(opp.worst_case_profit_percent, 0.6);
+        assert_eq!(opp.valid_until - opp.detected_at, max_age);
+
+        // Volatility erases the profit sooner than that
+        let mut opp = opportunity(0.6);
+        RiskModel::new(&config(400, 1500)).with_volatility_tracker(tracker(1.0)).assess(&mut opp);
+        assert!(opp.valid_until >= opp.detected_at);
+        assert!(opp.valid_until - opp.detected_at < max_age);
+
+        // And never extends it
+        let mut opp = opportunity(0.6);
+        RiskModel::new(&config(400, 1500)).with_volatility_tracker(tracker(0.0001)).assess(&mut opp);
+        assert_eq!(opp.valid_until - opp.detected_at, max_age);
+    }
+
+    #[test]
+    fn test_expected_latency_adds_scan_latency() {
+        let metrics = Arc::new(PipelineMetrics::default());
+        let risk = RiskModel::new(&config(400, 2000)).with_pipeline_metrics(metrics.clone());
+        assert_eq!(risk.expected_latency(), Duration::from_millis(400));
+
+        metrics.record_scan(Duration::from_micros(1_000));
+        // Histogram buckets report powers of two
+        assert_eq!(risk.expected_latency(), Duration::from_millis(400) + Duration::from_micros(1_024));
+    }
+}
@@ -1,11 +1,14 @@
 //! Spatial arbitrage detection (cross-DEX price differences)
 
 use crate::cache::PriceCache;
-use crate::config::FeesConfig;
-use crate::models::{Opportunity, OpportunityType, PriceData};
-use chrono::Utc;
+use crate::calculator::{exact_spread_percent, TokenRegistry};
+use crate::config::{ArbitrageConfig, FeesConfig};
+use crate::costs::CostFeed;
+use crate::detector::{RiskModel, SlotConfirmation, VolatilityTracker};
+use crate::models::{LegSide, Opportunity, OpportunityType, PriceData, RouteLeg};
+use crate::websocket::activity::{self, SwapActivity};
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// Detector for spatial arbitrage opportunities
 pub struct OpportunityDetector {
@@ -13,6 +16,14 @@ pub struct OpportunityDetector {
     fees: FeesConfig,
     min_profit_percent: f64,
     slot_tolerance: u64,
+    require_confirmed: bool,
+    non_streamed_confidence: f64,
+    confirmation: SlotConfirmation,
+    volatility: Option<Arc<VolatilityTracker>>,
+    cost_feed: Option<Arc<CostFeed>>,
+    activity: Option<SwapActivity>,
+    risk: RiskModel,
+    tokens: TokenRegistry,
 }
 
 impl OpportunityDetector {
@@ -28,18 +39,136 @@ impl OpportunityDetector {
             fees,
             min_profit_percent,
             slot_tolerance,
+            require_confirmed: false,
+            non_streamed_confidence: 1.0,
+            confirmation: SlotConfirmation::new(0),
+            volatility: None,
+            cost_feed: None,
+            activity: None,
+            risk: RiskModel::default(),
+            tokens: TokenRegistry::default(),
+        }
+    }
+
+    /// Express sizes and profits in token amounts using these decimals
+    pub fn with_token_registry(mut self, tokens: TokenRegistry) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Price gas and tips from a live cost feed instead of static fees
+    pub fn with_cost_feed(mut self, feed: Arc<CostFeed>) -> Self {
+        self.cost_feed = Some(feed);
+        self
+    }
+
+    /// Scale thresholds by each pair's volatility regime
+    pub fn with_volatility_tracker(mut self, tracker: Arc<VolatilityTracker>) -> Self {
+        self.volatility = Some(tracker);
+        self
+    }
+
+    /// Assess worst-case profit and validity with this model
+    pub fn with_risk_model(mut self, risk: RiskModel) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Weigh both pools' recent swap activity into confidence
+    pub fn with_swap_activity(mut self, activity: SwapActivity) -> Self {
+        self.activity = Some(activity);
+        self
+    }
+
+    /// Require spreads to persist for `slots` consecutive slots before emitting
+    pub fn with_confirmation_slots(mut self, slots: u32) -> Self {
+        self.confirmation = SlotConfirmation::new(slots);
+        self
+    }
+
+    /// Drop spreads still being confirmed; returns how many
+    pub fn reset(&self) -> usize {
+        self.confirmation.clear()
+    }
+
+    /// Ignore prices not yet seen at `confirmed` commitment
+    pub fn with_require_confirmed(mut self, require_confirmed: bool) -> Self {
+        self.require_confirmed = require_confirmed;
+        self
+    }
+
+    /// Scale confidence by `factor` when either side was priced from a
+    /// polled, bootstrap or simulated source
+    pub fn with_non_streamed_confidence(mut self, factor: f64) -> Self {
+        self.non_streamed_confidence = factor;
+        self
+    }
+
+    /// Apply reloaded thresholds and fees
+    ///
+    /// Confirmation progress is only discarded when the required slot count
+    /// actually changes.
+    pub fn reconfigure(&mut self, arbitrage: &ArbitrageConfig, fees: &FeesConfig) {
+        self.min_profit_percent = arbitrage.min_profit_percent;
+        self.slot_tolerance = arbitrage.slot_tolerance;
+        self.require_confirmed = arbitrage.require_confirmed;
+        self.non_streamed_confidence = arbitrage.non_streamed_confidence;
+        self.risk.reconfigure(arbitrage);
+        self.fees = fees.clone();
+        if self.confirmation.required_slots() != arbitrage.confirmation_slots {
+            self.confirmation = SlotConfirmation::new(arbitrage.confirmation_slots);
         }
     }
 
     /// Scan for spatial arbitrage on a token pair
     pub async fn scan_pair(&self, pair: &str) -> Option<Opportunity> {
-        detect_spatial_arbitrage(
+        let regime = self.volatility.as_ref().map(|v| v.regime(pair));
+        let (min_profit, slot_tolerance) = match (&self.volatility, regime) {
+            (Some(tracker), Some(regime)) => {
+                tracker.effective_thresholds(regime, self.min_profit_percent, self.slot_tolerance)
+            }
+            _ => (self.min_profit_percent, self.slot_tolerance),
+        };
+
+        let fees = match &self.cost_feed {
+            Some(feed) => feed.effective_fees(),
+            None => self.fees.clone(),
+        };
+
+        let detected = detect_spatial_arbitrage(
             &self.cache,
             pair,
-            self.min_profit_percent,
-            &self.fees,
-            self.slot_tolerance,
-        ).await
+            min_profit,
+            &fees,
+            slot_tolerance,
+            self.require_confirmed,
+        ).await;
+
+        let Some(mut opp) = detected else {
+            self.confirmation.reset(pair);
+            return None;
+        };
+
+        let venue = format!("{}>{}", opp.buy_dex, opp.sell_dex);
+        let slot = self.cache.get(pair, &opp.sell_dex)
+            .map_or(0, |data| data.slot)
+            .max(self.cache.get(pair, &opp.buy_dex).map_or(0, |data| data.slot));
+
+        opp.persisted_slots = self.confirmation.confirm(pair, &venue, slot)?;
+        opp.volatility_regime = regime;
+        if let Some(factor) = self.activity.as_ref().and_then(|a| a.factor(&[(pair, &opp.buy_dex), (pair, &opp.sell_dex)])) {
+            opp.confidence = activity::weigh_confidence(opp.confidence, factor);
+        }
+        let streamed = [&opp.buy_dex, &opp.sell_dex]
+            .into_iter()
+            .all(|dex| self.cache.get(pair, dex).map_or(true, |data| data.source.is_streamed()));
+        if !streamed {
+            opp.confidence *= self.non_streamed_confidence;
+        }
+        self.risk.assess(&mut opp);
+        let buy_price = opp.buy_price;
+        self.tokens.denominate_pair(&mut opp, pair, buy_price);
+        Some(opp)
     }
 
     /// Scan all configured pairs
@@ -63,29 +192,31 @@ pub async fn detect_spatial_arbitrage(
     min_profit: f64,
     fees: &FeesConfig,
     slot_tolerance: u64,
+    require_confirmed: bool,
 ) -> Option<Opportunity> {
-    let prices = cache.get_all_dexes(pair); // DashMap is lock-free, no await
+    let mut prices = cache.get_all_dexes(pair); // DashMap is lock-free, no await
+    if require_confirmed {
+        prices.retain(|(_, p)| p.confirmed);
+    }
 
     if prices.len() < 2 {
         // debug!(pair = pair, count = prices.len(), "Not enough DEXs for comparison");
         return None;
     }
 
-    // Find min and max prices
+    // Buy where it's cheapest and sell where it's dearest, at the ask and
+    // bid on orderbook venues and the pool price on AMMs
     let (buy_dex, buy_data) = prices
         .iter()
-        .filter(|(_, p)| !cache.is_stale(p))
-        .min_by(|a, b| a.1.price.partial_cmp(&b.1.price).unwrap_or(std::cmp::Ordering::Equal))?;
+        .filter(|(dex, p)| !cache.is_stale(pair, dex, p))
+        .min_by(|a, b| a.1.buy_price().partial_cmp(&b.1.buy_price()).unwrap_or(std::cmp::Ordering::Equal))?;
 
+    // Same DEX = no opportunity
     let (sell_dex, sell_data) = prices
         .iter()
-        .filter(|(_, p)| !cache.is_stale(p))
-        .max_by(|a, b| a.1.price.partial_cmp(&b.1.price).unwrap_or(std::cmp::Ordering::Equal))?;
-
-    // Same DEX = no opportunity
-    if buy_dex == sell_dex {
-        return None;
-    }
+        .filter(|(dex, p)| dex != buy_dex && !cache.is_stale(pair, dex, p))
+        .max_by(|a, b| a.1.sell_price().partial_cmp(&b.1.sell_price()).unwrap_or(std::cmp::Ordering::Equal))?;
+    let (buy_price, sell_price) = (buy_data.buy_price(), sell_data.sell_price());
 
     // Validate slot alignment
     if sell_data.slot.abs_diff(buy_data.slot) > slot_tolerance {
@@ -98,40 +229,51 @@ pub async fn detect_spatial_arbitrage(
         return None;
     }
 
-    // Calculate gross profit
-    let gross_profit = (sell_data.price - buy_data.price) / buy_data.price * 100.0;
+    // Calculate gross profit, exactly when both legs have exact prices
+    let gross_profit = exact_spread_percent(buy_data, sell_data)
+        .unwrap_or_else(|| (sell_price - buy_price) / buy_price * 100.0);
 
     // Calculate total costs
-    let total_costs = calculate_total_costs(buy_data, sell_data, fees);
+    let total_costs = calculate_total_costs(buy_data, sell_data, fees, gross_profit);
     let net_profit = gross_profit - total_costs;
 
     if net_profit > min_profit {
         let recommended_size = calculate_optimal_size(buy_data, sell_data);
-        let confidence = calculate_confidence(buy_data, sell_data);
-
-        Some(Opportunity {
-            opportunity_type: OpportunityType::Spatial,
-            token_pair: pair.to_string(),
-            buy_dex: buy_dex.clone(),
-            sell_dex: sell_dex.clone(),
-            buy_price: buy_data.price,
-            sell_price: sell_data.price,
-            net_profit_percent: net_profit,
-            recommended_size,
-            confidence,
-            detected_at: Utc::now(),
-        })
+        // Prices their vault balances didn't back count for less
+        let confidence = calculate_confidence(buy_data, sell_data)
+            * buy_data.confidence_weight
+            * sell_data.confidence_weight;
+
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair(pair)
+            .buy(buy_dex.as_str(), buy_price, buy_data.slot)
+            .sell(sell_dex.as_str(), sell_price, sell_data.slot)
+            .legs(vec![
+                RouteLeg::new(pair, buy_dex, LegSide::Buy, buy_data),
+                RouteLeg::new(pair, sell_dex, LegSide::Sell, sell_data),
+            ])
+            .net_profit_percent(net_profit)
+            .recommended_size(recommended_size)
+            .confidence(confidence)
+            .assumed_slippage_pct(fees.estimated_slippage)
+            .build()
+            .map_err(|error| warn!(pair = pair, %error, "Discarding inconsistent spatial opportunity"))
+            .ok()
     } else {
         None
     }
 }
 
-fn calculate_total_costs(buy: &PriceData, sell: &PriceData, fees: &FeesConfig) -> f64 {
+fn calculate_total_costs(buy: &PriceData, sell: &PriceData, fees: &FeesConfig, gross_profit: f64) -> f64 {
     let buy_fee = buy.fee_rate * 100.0;
     let sell_fee = sell.fee_rate * 100.0;
+    let fixed_costs = (buy.fixed_cost_rate + sell.fixed_cost_rate) * 100.0;
     
-    // Architecture: buy_fee + sell_fee + slippage + gas + tip
-    buy_fee + sell_fee + fees.estimated_slippage + fees.gas_cost_percent + fees.jito_tip_percent
+    // Architecture: buy_fee + sell_fee + fixed costs + slippage + gas + tip
+    // The tip is sized on the profit left after everything else (see TipStrategy)
+    let costs_before_tip = buy_fee + sell_fee + fixed_costs + fees.estimated_slippage + fees.gas_cost_percent;
+    costs_before_tip + fees.tip_cost_percent(gross_profit - costs_before_tip)
 }
 
 fn calculate_optimal_size(buy: &PriceData, sell: &PriceData) -> u64 {
@@ -159,29 +301,240 @@ fn calculate_confidence(buy: &PriceData, sell: &PriceData) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::TipStrategy;
+    use crate::models::{Dex, PriceSource};
 
     #[tokio::test]
     async fn test_spatial_detection() {
         let cache = Arc::new(PriceCache::new(60, 2000));
 
         // Add prices with a spread
-        cache.update("SOL-USDC", "raydium", PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003)).await;
-        cache.update("SOL-USDC", "orca", PriceData::new(102.0, 800_000, 100, 400_000, 400_000, 0.003)).await;
+        let raydium = PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003)
+            .with_origin("raydium-pool", PriceSource::WebSocket);
+        cache.update("SOL-USDC", &Dex::Raydium, raydium).await;
+        cache.update("SOL-USDC", &Dex::Orca, PriceData::new(102.0, 800_000, 101, 400_000, 400_000, 0.003)).await;
 
         let fees = FeesConfig {
             default_dex_fee: 0.25,
             estimated_slippage: 0.3,
             gas_cost_percent: 0.01,
             jito_tip_percent: 0.05,
+            tip_strategy: None,
+            trade_size_lamports: 10_000_000_000,
+            overrides: Default::default(),
+            schedule_file: None,
+            schedule: Default::default(),
         };
 
-        let opp = detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &fees, 2).await;
+        let opp = detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &fees, 2, false).await;
 
         // 2% gross - ~0.9% costs = ~1.1% net profit
         assert!(opp.is_some());
         let opp = opp.unwrap();
         assert_eq!(opp.buy_dex, "raydium");
         assert_eq!(opp.sell_dex, "orca");
+        assert_eq!((opp.buy_slot, opp.sell_slot), (100, 101));
+        let legs: Vec<_> = opp.legs.iter().map(|leg| (leg.side, leg.dex.as_str(), leg.pool_pubkey.as_deref())).collect();
+        assert_eq!(legs, vec![(LegSide::Buy, "raydium", Some("raydium-pool")), (LegSide::Sell, "orca", None)]);
+
+        // Every emission gets its own id, even for the same spread
+        let again = detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &fees, 2, false).await.unwrap();
+        assert_ne!(again.id, opp.id);
+        assert!(!opp.id.is_nil());
+    }
+
+    #[tokio::test]
+    async fn test_require_confirmed_skips_processed_prices() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData {
+            confirmed: true,
+            ..PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003)
+        });
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(102.0, 800_000, 100, 400_000, 400_000, 0.003));
+
+        assert!(detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &test_fees(), 2, false).await.is_some());
+        assert!(detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &test_fees(), 2, true).await.is_none());
+    }
+
+    fn test_fees() -> FeesConfig {
+        FeesConfig {
+            default_dex_fee: 0.25,
+            estimated_slippage: 0.3,
+            gas_cost_percent: 0.01,
+            jito_tip_percent: 0.05,
+            tip_strategy: None,
+            trade_size_lamports: 10_000_000_000,
+            overrides: Default::default(),
+            schedule_file: None,
+            schedule: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_fixed_costs_are_charged_once_per_swap() {
+        let buy = PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003);
+        let sell = PriceData::new(102.0, 1_000_000, 1, 500_000, 500_000, 0.003);
+        let base = calculate_total_costs(&buy, &sell, &test_fees(), 2.0);
+
+        // 0.05% per swap on each side, on top of (not folded into) the fee
+        let with_fixed = calculate_total_costs(
+            &buy.clone().with_fixed_cost_rate(0.0005),
+            &sell.clone().with_fixed_cost_rate(0.0005),
+            &test_fees(),
+            2.0,
+        );
+        assert!((with_fixed - base - 0.1).abs() < 1e-9);
+    }
+
+    /// Drive one slot of raydium/orca prices through the detector
+    async fn scan_slot(detector: &OpportunityDetector, cache: &PriceCache, slot: u64, sell_price: f64) -> Option<Opportunity> {
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, slot, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(sell_price, 800_000, slot, 400_000, 400_000, 0.003));
+        detector.scan_pair("SOL-USDC").await
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_suppresses_short_spike() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2)
+            .with_confirmation_slots(3);
+
+        assert!(scan_slot(&detector, &cache, 1, 102.0).await.is_none());
+        assert!(scan_slot(&detector, &cache, 2, 102.0).await.is_none());
+        // Spread closes after two slots
+        assert!(scan_slot(&detector, &cache, 3, 100.0).await.is_none());
+        assert!(scan_slot(&detector, &cache, 4, 102.0).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_emits_persistent_spread() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2)
+            .with_confirmation_slots(2);
+
+        let emitted: Vec<Option<u32>> = {
+            let mut emitted = Vec::new();
+            for slot in 1..=5 {
+                emitted.push(scan_slot(&detector, &cache, slot, 102.0).await.map(|o| o.persisted_slots));
+            }
+            emitted
+        };
+
+        assert_eq!(emitted, vec![None, Some(2), Some(3), Some(4), Some(5)]);
+    }
+
+    #[tokio::test]
+    async fn test_live_costs_move_threshold() {
+        use crate::config::CostsConfig;
+        use crate::costs::{CostSnapshot, TipFloor};
+
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let feed = Arc::new(CostFeed::new(CostsConfig::default(), test_fees()));
+        let detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2)
+            .with_cost_feed(feed.clone());
+
+        // ~1.1% net with static costs
+        assert!(scan_slot(&detector, &cache, 1, 102.0).await.is_some());
+
+        // Tips spike to 1% of the typical trade size
+        let tip = CostsConfig::default().typical_trade_size_lamports / 100;
+        feed.update(CostSnapshot {
+            priority_fee_micro_lamports: 0,
+            tip_floor: Some(TipFloor([(25, tip), (50, tip), (75, tip), (95, tip), (99, tip)])),
+        });
+        assert!(scan_slot(&detector, &cache, 2, 102.0).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_non_streamed_sources_lower_confidence() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let polled = PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003)
+            .with_origin("raydium-pool", PriceSource::HttpPoll);
+        cache.set("SOL-USDC", &Dex::Raydium, polled);
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(102.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+
+        let detector = |factor| OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2).with_non_streamed_confidence(factor);
+        let full = detector(1.0).scan_pair("SOL-USDC").await.unwrap().confidence;
+        let penalized = detector(0.5).scan_pair("SOL-USDC").await.unwrap().confidence;
+        assert!((penalized - full * 0.5).abs() < 1e-9);
+
+        // Both sides streamed: no penalty
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 101, 500_000, 500_000, 0.003));
+        let streamed = detector(0.5).scan_pair("SOL-USDC").await.unwrap().confidence;
+        assert!(streamed > penalized);
+    }
+
+    #[tokio::test]
+    async fn test_down_weighted_price_lowers_confidence() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(102.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+        let detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2);
+        let full = detector.scan_pair("SOL-USDC").await.unwrap().confidence;
+
+        let inconsistent =
+            PriceData { confidence_weight: 0.5, ..PriceData::new(102.0, 1_000_000, 100, 500_000, 500_000, 0.003) };
+        cache.set("SOL-USDC", &Dex::Orca, inconsistent);
+        let weighted = detector.scan_pair("SOL-USDC").await.unwrap().confidence;
+        assert!((weighted - full * 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_amounts_use_registry_decimals() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(102.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+
+        let tokens = TokenRegistry::new([("sol".to_string(), 9), ("usdc".to_string(), 6)]);
+        let detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2).with_token_registry(tokens);
+        let opp = detector.scan_pair("SOL-USDC").await.unwrap();
+        assert_eq!((opp.size_token.as_str(), opp.size_decimals), ("SOL", 9));
+        assert_eq!((opp.profit_token.as_str(), opp.profit_decimals), ("USDC", 6));
+        // 50_000 lamports bought at $100 earn net_profit_percent of $0.005
+        let expected = 0.00005 * 100.0 * opp.net_profit_percent / 100.0 * 1e6;
+        assert_eq!(opp.expected_profit_base_units, expected.round() as u64);
+
+        // Without decimals for the pair's tokens nothing is guessed
+        let opp = OpportunityDetector::new(cache, test_fees(), 0.5, 2).scan_pair("SOL-USDC").await.unwrap();
+        assert!(opp.size_token.is_empty() && opp.profit_token.is_empty());
+        assert_eq!(opp.expected_profit_base_units, 0);
+    }
+
+    #[tokio::test]
+    async fn test_orderbook_venues_trade_at_ask_and_bid() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 100, 500_000, 500_000, 0.003));
+
+        // Mid 103 is 3% above raydium, but selling only fetches the 101 bid
+        cache.set("SOL-USDC", &Dex::from("phoenix"), PriceData::from_quote(101.0, 105.0, 1_000_000, 100, 0.003));
+        assert!(detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &test_fees(), 2, false).await.is_none());
+
+        cache.set("SOL-USDC", &Dex::from("phoenix"), PriceData::from_quote(102.5, 103.5, 1_000_000, 100, 0.003));
+        let opp = detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &test_fees(), 2, false).await.unwrap();
+        assert_eq!((opp.buy_dex.as_str(), opp.sell_dex.as_str()), ("raydium", "phoenix"));
+        assert_eq!((opp.buy_price, opp.sell_price), (100.0, 102.5));
+        assert_eq!(opp.legs[1].price, 102.5);
+
+        // Buying on the book pays its ask
+        cache.set("SOL-USDC", &Dex::from("phoenix"), PriceData::from_quote(97.0, 98.0, 1_000_000, 100, 0.003));
+        let opp = detect_spatial_arbitrage(&cache, "SOL-USDC", 0.5, &test_fees(), 2, false).await.unwrap();
+        assert_eq!((opp.buy_dex.as_str(), opp.sell_dex.as_str()), ("phoenix", "raydium"));
+        assert_eq!((opp.buy_price, opp.legs[0].price), (98.0, 98.0));
+        assert!((opp.gross_profit_percent() - 2.0 / 98.0 * 100.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_applies_new_threshold() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let mut detector = OpportunityDetector::new(cache.clone(), test_fees(), 0.5, 2);
+        assert!(scan_slot(&detector, &cache, 1, 102.0).await.is_some());
+
+        let arbitrage = ArbitrageConfig {
+            min_profit_percent: 2.0,
+            ..crate::config::Settings::default().arbitrage
+        };
+        detector.reconfigure(&arbitrage, &test_fees());
+        assert!(scan_slot(&detector, &cache, 2, 102.0).await.is_none());
     }
 
     #[test]
@@ -193,13 +546,26 @@ mod tests {
             estimated_slippage: 0.3,
             gas_cost_percent: 0.01,
             jito_tip_percent: 0.05,
+            tip_strategy: None,
+            trade_size_lamports: 10_000_000_000,
+            overrides: Default::default(),
+            schedule_file: None,
+            schedule: Default::default(),
         };
         
         // Gross: 5%
         // Costs: 0.25 + 0.30 + 0.3 + 0.01 + 0.05 = 0.91%
         // Net: 4.09%
         
-        let costs = calculate_total_costs(&buy, &sell, &fees);
+        let costs = calculate_total_costs(&buy, &sell, &fees, 5.0);
         assert!((costs - 0.91).abs() < 0.001);
+
+        // 20% of the 4.14% left before the tip
+        let fees = FeesConfig {
+            tip_strategy: Some(TipStrategy::PercentOfProfit(20.0)),
+            ..fees
+        };
+        let costs = calculate_total_costs(&buy, &sell, &fees, 5.0);
+        assert!((costs - (0.86 + 0.828)).abs() < 0.001);
     }
 }
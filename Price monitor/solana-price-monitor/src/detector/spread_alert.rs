@@ -0,0 +1,145 @@
+//! Spread alerts below the arbitrage profit threshold
+//!
+//! Market makers care about cross-DEX dislocations even when fees make them
+//! unprofitable to arb. This detector only compares the fresh max/min price
+//! per pair against a basis-point threshold, with a per-pair throttle.
+
+use crate::cache::PriceCache;
+use crate::config::AlertsConfig;
+use crate::models::Dex;
+use serde::Serialize;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A cross-DEX spread that crossed its alert threshold
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SpreadAlert {
+    pub pair: String,
+    /// DEX quoting the lowest fresh price
+    pub low_dex: Dex,
+    /// DEX quoting the highest fresh price
+    pub high_dex: Dex,
+    pub low_price: f64,
+    pub high_price: f64,
+    pub spread_bps: f64,
+    pub threshold_bps: f64,
+}
+
+/// Detector for spreads exceeding per-pair basis-point thresholds
+pub struct SpreadAlertDetector {
+    cache: Arc<PriceCache>,
+    config: AlertsConfig,
+    last_alert: Mutex<HashMap<String, Instant>>,
+}
+
+impl SpreadAlertDetector {
+    pub fn new(cache: Arc<PriceCache>, config: AlertsConfig) -> Self {
+        Self {
+            cache,
+            config,
+            last_alert: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Threshold for a pair, falling back to the default
+    fn threshold_bps(&self, pair: &str) -> f64 {
+        self.config
+            .pair_thresholds_bps
+            .get(pair)
+            .copied()
+            .unwrap_or(self.config.default_threshold_bps)
+    }
+
+    /// Lift every pair's throttle; returns how many were throttled
+    pub fn reset(&self) -> usize {
+        let mut last_alert = self.last_alert.lock().unwrap_or_else(|e| e.into_inner());
+        let cleared = last_alert.len();
+        last_alert.clear();
+        cleared
+    }
+
+    /// Check a pair's fresh spread, returning an alert unless throttled
+    pub fn check(&self, pair: &str) -> Option<SpreadAlert> {
+        let prices = self.cache.fresh_prices(pair);
+        let (low_dex, low) = prices.first()?;
+        let (high_dex, high) = prices.last()?;
+
+        if low_dex == high_dex {
+            return None;
+        }
+
+        let spread_bps = (high.price - low.price) / low.price * 10_000.0;
+        let threshold_bps = self.threshold_bps(pair);
+        if spread_bps < threshold_bps {
+            return None;
+        }
+
+        // Throttle repeated alerts for the same pair
+        let now = Instant::now();
+        let mut last_alert = self.last_alert.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = last_alert.get(pair) {
+            if now.duration_since(*last) < Duration::from_millis(self.config.throttle_ms) {
+                return None;
+            }
+        }
+        last_alert.insert(pair.to_string(), now);
+
+        Some(SpreadAlert {
+            pair: pair.to_string(),
+            low_dex: low_dex.clone(),
+            high_dex: high_dex.clone(),
+            low_price: low.price,
+            high_price: high.price,
+            spread_bps,
+            threshold_bps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriceData;
+
+    /// Cache with a 20 bps SOL-USDC spread between raydium and orca
+    fn spread_cache() -> Arc<PriceCache> {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(100.2, 1_000_000, 1, 500_000, 500_000, 0.003));
+        cache
+    }
+
+    fn config(threshold_bps: f64) -> AlertsConfig {
+        AlertsConfig {
+            enabled: true,
+            default_threshold_bps: 50.0,
+            pair_thresholds_bps: HashMap::from([("SOL-USDC".to_string(), threshold_bps)]),
+            throttle_ms: 60_000,
+        }
+    }
+
+    #[test]
+    fn test_spread_above_threshold_alerts() {
+        let detector = SpreadAlertDetector::new(spread_cache(), config(15.0));
+
+        let alert = detector.check("SOL-USDC").expect("20 bps exceeds 15 bps");
+        assert_eq!(alert.low_dex, "raydium");
+        assert_eq!(alert.high_dex, "orca");
+        assert!((alert.spread_bps - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_spread_below_threshold_is_quiet() {
+        let detector = SpreadAlertDetector::new(spread_cache(), config(25.0));
+        assert!(detector.check("SOL-USDC").is_none());
+    }
+
+    #[test]
+    fn test_alerts_are_throttled() {
+        let detector = SpreadAlertDetector::new(spread_cache(), config(15.0));
+        assert!(detector.check("SOL-USDC").is_some());
+        assert!(detector.check("SOL-USDC").is_none());
+    }
+}
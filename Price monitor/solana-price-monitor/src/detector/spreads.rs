@@ -0,0 +1,161 @@
+//! Live cross-DEX spread per pair
+//!
+//! Unlike spread alerts there is no threshold: every pair quoted fresh on at
+//! least two DEXes has a spread, served by `GET /spreads` and streamed as
+//! `spread` messages. The stream is throttled per pair so a busy pair sends
+//! at most one update per interval unless the spread moves sharply.
+
+use crate::cache::PriceCache;
+use crate::models::{Spread, SpreadVenue};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Current spread of `pair`; `None` with fewer than two fresh venues
+pub fn pair_spread(cache: &PriceCache, pair: &str) -> Option<Spread> {
+    let prices = cache.fresh_prices(pair);
+    if prices.len() < 2 {
+        return None;
+    }
+    let now = Utc::now();
+    // Ties keep the cheapest-first order: lowest DEX name asks, highest bids
+    let (ask_dex, ask) = prices.iter().min_by(|a, b| a.1.buy_price().total_cmp(&b.1.buy_price()))?;
+    let (bid_dex, bid) = prices
+        .iter()
+        .filter(|(dex, _)| dex != ask_dex)
+        .max_by(|a, b| a.1.sell_price().total_cmp(&b.1.sell_price()))?;
+    let (ask_price, bid_price) = (ask.buy_price(), bid.sell_price());
+    Some(Spread {
+        pair: pair.to_string(),
+        bid_dex: bid_dex.clone(),
+        bid_price,
+        ask_dex: ask_dex.clone(),
+        ask_price,
+        spread: bid_price - ask_price,
+        spread_percent: (bid_price - ask_price) / ask_price * 100.0,
+        venues: prices.iter().map(|(dex, data)| SpreadVenue::new(dex, data, now)).collect(),
+    })
+}
+
+/// Decides which spreads are worth streaming
+pub struct SpreadTracker {
+    cache: Arc<PriceCache>,
+    /// Least time between two updates of a pair
+    interval: Duration,
+    /// Move in bps that is sent at once, interval or not
+    change_bps: f64,
+    /// Pair -> when its spread was last sent, and at what bps
+    last_sent: Mutex<HashMap<String, (Instant, f64)>>,
+}
+
+impl SpreadTracker {
+    pub fn new(cache: Arc<PriceCache>, interval: Duration, change_bps: f64) -> Self {
+        Self {
+            cache,
+            interval,
+            change_bps,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forget when each pair was last sent, so the next check sends it;
+    /// returns how many pairs were tracked
+    pub fn reset(&self) -> usize {
+        let mut last_sent = self.last_sent.lock().unwrap_or_else(|e| e.into_inner());
+        let cleared = last_sent.len();
+        last_sent.clear();
+        cleared
+    }
+
+    /// The pair's spread if it should go out now
+    pub fn check(&self, pair: &str) -> Option<Spread> {
+        self.check_at(pair, Instant::now())
+    }
+
+    fn check_at(&self, pair: &str, now: Instant) -> Option<Spread> {
+        let spread = pair_spread(&self.cache, pair)?;
+        let bps = spread.spread_bps();
+        let mut last_sent = self.last_sent.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((at, last_bps)) = last_sent.get(pair) {
+            let moved = (bps - last_bps).abs() > self.change_bps;
+            if !moved && now.duration_since(*at) < self.interval {
+                return None;
+            }
+        }
+        last_sent.insert(pair.to_string(), (now, bps));
+        Some(spread)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dex, PriceData};
+
+    fn price(price: f64, slot: u64) -> PriceData {
+        PriceData::new(price, 1_000_000, slot, 500_000, 500_000, 0.003)
+    }
+
+    #[test]
+    fn test_spread_across_three_venues() {
+        let cache = PriceCache::new(60, 2000);
+        cache.set("SOL-USDC", &Dex::Raydium, price(100.0, 10));
+        cache.set("SOL-USDC", &Dex::Orca, price(100.3, 12));
+        cache.set("SOL-USDC", &Dex::Meteora, price(100.1, 11));
+        let mut stale = price(90.0, 1);
+        stale.timestamp -= chrono::Duration::seconds(10);
+        cache.set("SOL-USDC", &Dex::from("phoenix"), stale);
+        cache.set("BONK-SOL", &Dex::Orca, price(0.00002, 5));
+
+        let spread = pair_spread(&cache, "SOL-USDC").unwrap();
+        assert_eq!((spread.ask_dex.as_str(), spread.bid_dex.as_str()), ("raydium", "orca"));
+        assert!((spread.spread - 0.3).abs() < 1e-9);
+        assert!((spread.spread_bps() - 30.0).abs() < 1e-6);
+        let venues: Vec<_> = spread.venues.iter().map(|v| (v.dex.as_str(), v.slot)).collect();
+        assert_eq!(venues, vec![("raydium", 10), ("meteora", 11), ("orca", 12)]);
+        // One fresh venue has no spread
+        assert_eq!(pair_spread(&cache, "BONK-SOL"), None);
+    }
+
+    #[test]
+    fn test_orderbook_venue_quotes_its_bid_and_ask() {
+        let cache = PriceCache::new(60, 2000);
+        cache.set("SOL-USDC", &Dex::Raydium, price(100.0, 10));
+        cache.set("SOL-USDC", &Dex::from("phoenix"), PriceData::from_quote(100.2, 100.4, 1_000_000, 10, 0.0005));
+
+        let spread = pair_spread(&cache, "SOL-USDC").unwrap();
+        assert_eq!((spread.ask_dex.as_str(), spread.bid_dex.as_str()), ("raydium", "phoenix"));
+        assert_eq!((spread.ask_price, spread.bid_price), (100.0, 100.2));
+        let json = serde_json::to_value(&spread.venues).unwrap();
+        assert_eq!((json[1]["bid"].as_f64(), json[1]["ask"].as_f64()), (Some(100.2), Some(100.4)));
+        assert!(json[0].get("bid").is_none());
+
+        // A book below the pool is bought at its ask, not its mid
+        cache.set("SOL-USDC", &Dex::from("phoenix"), PriceData::from_quote(99.6, 99.8, 1_000_000, 11, 0.0005));
+        let spread = pair_spread(&cache, "SOL-USDC").unwrap();
+        assert_eq!((spread.ask_dex.as_str(), spread.ask_price), ("phoenix", 99.8));
+        assert_eq!((spread.bid_dex.as_str(), spread.bid_price), ("raydium", 100.0));
+    }
+
+    #[test]
+    fn test_tracker_throttles_unless_the_spread_moves() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        cache.set("SOL-USDC", &Dex::Raydium, price(100.0, 1));
+        cache.set("SOL-USDC", &Dex::Orca, price(100.1, 1));
+        let tracker = SpreadTracker::new(cache.clone(), Duration::from_millis(500), 5.0);
+        let start = Instant::now();
+
+        assert!(tracker.check_at("SOL-USDC", start).is_some());
+        // 10 -> 13 bps inside the interval: held back
+        cache.set("SOL-USDC", &Dex::Orca, price(100.13, 2));
+        assert!(tracker.check_at("SOL-USDC", start + Duration::from_millis(100)).is_none());
+        // Interval over
+        assert!(tracker.check_at("SOL-USDC", start + Duration::from_millis(500)).is_some());
+        // 13 -> 20 bps: sent at once
+        cache.set("SOL-USDC", &Dex::Orca, price(100.2, 3));
+        let spread = tracker.check_at("SOL-USDC", start + Duration::from_millis(600)).unwrap();
+        assert!((spread.spread_bps() - 20.0).abs() < 1e-6);
+        assert!(tracker.check_at("SOL-USDC", start + Duration::from_millis(700)).is_none());
+    }
+}
@@ -1,14 +1,18 @@
 //! Statistical arbitrage detection (mean reversion / pairs trading)
 
 use crate::cache::PriceCache;
-use crate::models::{Opportunity, OpportunityType};
+use crate::calculator::TokenRegistry;
+use crate::detector::RiskModel;
+use crate::models::{Dex, LegSide, Opportunity, OpportunityType, RouteLeg};
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// Configuration for statistical arbitrage
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StatArbConfig {
     /// Minimum correlation threshold for pair selection
     pub min_correlation: f64,
@@ -106,6 +110,8 @@ pub struct StatisticalArbitrageDetector {
     cache: Arc<PriceCache>,
     config: StatArbConfig,
     pair_stats: std::collections::HashMap<String, PairStatistics>,
+    risk: RiskModel,
+    tokens: TokenRegistry,
 }
 
 impl StatisticalArbitrageDetector {
@@ -114,9 +120,35 @@ impl StatisticalArbitrageDetector {
             cache,
             config,
             pair_stats: std::collections::HashMap::new(),
+            risk: RiskModel::default(),
+            tokens: TokenRegistry::default(),
         }
     }
 
+    /// Assess worst-case profit and validity with this model
+    pub fn with_risk_model(mut self, risk: RiskModel) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Express sizes and profits in the buy pair's token amounts using these decimals
+    pub fn with_token_registry(mut self, tokens: TokenRegistry) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Parameters the detector was built with
+    pub fn config(&self) -> &StatArbConfig {
+        &self.config
+    }
+
+    /// Forget every pair's spread history; returns how many pairs had one
+    pub fn reset(&mut self) -> usize {
+        let cleared = self.pair_stats.len();
+        self.pair_stats.clear();
+        cleared
+    }
+
     /// Calculate spread between two token pairs
     /// spread = log(price_A) - β * log(price_B)
     fn calculate_spread(&self, price_a: f64, price_b: f64, beta: f64) -> f64 {
@@ -128,14 +160,14 @@ impl StatisticalArbitrageDetector {
         &mut self,
         pair_a: &str,
         pair_b: &str,
-        dex: &str,
+        dex: &Dex,
     ) -> Option<Opportunity> {
         // Get prices for both pairs (DashMap is lock-free, no await)
         let price_a = self.cache.get(pair_a, dex)?;
         let price_b = self.cache.get(pair_b, dex)?;
 
         // Check for stale data
-        if self.cache.is_stale(&price_a) || self.cache.is_stale(&price_b) {
+        if self.cache.is_stale(pair_a, dex, &price_a) || self.cache.is_stale(pair_b, dex, &price_b) {
             return None;
         }
 
@@ -182,26 +214,40 @@ impl StatisticalArbitrageDetector {
             let estimated_profit_percent = (expected_reversion / current_spread.abs()) * 100.0;
 
             if estimated_profit_percent > self.config.min_profit_percent {
-                let (buy_pair, sell_pair) = if z_score < 0.0 {
+                let ((buy_pair, buy_data), (sell_pair, sell_data)) = if z_score < 0.0 {
                     // Spread too low: buy A, sell B
-                    (pair_a.to_string(), pair_b.to_string())
+                    ((pair_a, &price_a), (pair_b, &price_b))
                 } else {
                     // Spread too high: sell A, buy B
-                    (pair_b.to_string(), pair_a.to_string())
+                    ((pair_b, &price_b), (pair_a, &price_a))
                 };
-
-                return Some(Opportunity {
-                    opportunity_type: OpportunityType::Statistical,
-                    token_pair: format!("{}:{}", pair_a, pair_b),
-                    buy_dex: dex.to_string(),
-                    sell_dex: dex.to_string(),
-                    buy_price: price_a.price,
-                    sell_price: price_b.price,
-                    net_profit_percent: estimated_profit_percent,
-                    recommended_size: (price_a.liquidity.min(price_b.liquidity) as f64 * 0.02) as u64,
-                    confidence: calculate_confidence(z_score, stats.spread_history.len()),
-                    detected_at: Utc::now(),
-                });
+                let legs = vec![
+                    RouteLeg::new(buy_pair, dex, LegSide::Buy, buy_data),
+                    RouteLeg::new(sell_pair, dex, LegSide::Sell, sell_data),
+                ];
+
+                // Buy and sell sides summarize the legs, which trade different pairs.
+                // The size is denominated in the buy pair, so only its liquidity
+                // scales it; the other pair's is in different units.
+                let mut opportunity = Opportunity::builder()
+                    .opportunity_type(OpportunityType::Statistical)
+                    .token_pair(format!("{}:{}", pair_a, pair_b))
+                    .buy(dex, buy_data.buy_price(), buy_data.slot)
+                    .sell(dex, sell_data.sell_price(), sell_data.slot)
+                    .legs(legs)
+                    .net_profit_percent(estimated_profit_percent)
+                    .recommended_size((buy_data.liquidity as f64 * 0.02) as u64)
+                    .confidence(
+                        calculate_confidence(z_score, stats.spread_history.len())
+                            * price_a.confidence_weight
+                            * price_b.confidence_weight,
+                    )
+                    .build()
+                    .map_err(|error| warn!(pair_a = pair_a, pair_b = pair_b, %error, "Discarding inconsistent statistical opportunity"))
+                    .ok()?;
+                self.risk.assess(&mut opportunity);
+                self.tokens.denominate_pair(&mut opportunity, buy_pair, buy_data.price);
+                return Some(opportunity);
             }
         }
 
@@ -1,19 +1,44 @@
 //! Triangular arbitrage detection (A → B → C → A)
 
 use crate::cache::PriceCache;
-use crate::config::FeesConfig;
-use crate::models::{Opportunity, OpportunityType};
-use chrono::Utc;
+use crate::calculator::TokenRegistry;
+use crate::calculator::{calculate_amm_price_impact, cycle_product};
+use crate::config::{ArbitrageConfig, FeesConfig, FiltersConfig};
+use crate::costs::CostFeed;
+use crate::detector::{RiskModel, SlotConfirmation, VolatilityTracker};
+use crate::models::{Dex, LegSide, Opportunity, OpportunityType, PriceData, RouteLeg, VolatilityRegime};
+use crate::websocket::activity::{self, SwapActivity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// Share of the shallowest leg's input reserve a cycle is sized at
+const POOL_SHARE: f64 = 0.001;
 
 /// Configuration for triangular arbitrage
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TriangularArbConfig {
     /// Minimum profit threshold after fees (percentage)
     pub min_profit_percent: f64,
     /// Maximum slot difference allowed between prices
     pub slot_tolerance: u64,
+    /// Consecutive slots a cycle must stay profitable before emitting (0 = off)
+    ///
+    /// Shared with the spatial detector via `[arbitrage] confirmation_slots`.
+    #[serde(skip)]
+    pub confirmation_slots: u32,
+    /// Skip paths with a leg not yet seen at `confirmed` commitment
+    ///
+    /// Shared with the spatial detector via `[arbitrage] require_confirmed`.
+    #[serde(skip)]
+    pub require_confirmed: bool,
+    /// Confidence multiplier for paths with a leg from a non-streamed source
+    ///
+    /// Shared with the spatial detector via `[arbitrage] non_streamed_confidence`.
+    #[serde(skip)]
+    pub non_streamed_confidence: f64,
 }
 
 impl Default for TriangularArbConfig {
@@ -21,12 +46,15 @@ impl Default for TriangularArbConfig {
         Self {
             min_profit_percent: 0.3,
             slot_tolerance: 2,
+            confirmation_slots: 0,
+            require_confirmed: false,
+            non_streamed_confidence: 1.0,
         }
     }
 }
 
 /// A triangular path through three trading pairs
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TriangularPath {
     /// Starting token (e.g., "SOL")
     pub token_start: String,
@@ -41,7 +69,7 @@ pub struct TriangularPath {
     /// Trading pair for leg 3: end -> start
     pub pair_3: String,
     /// DEX to execute on (single-DEX triangular)
-    pub dex: String,
+    pub dex: Dex,
 }
 
 impl TriangularPath {
@@ -49,7 +77,7 @@ impl TriangularPath {
         token_start: &str,
         token_mid: &str,
         token_end: &str,
-        dex: &str,
+        dex: impl Into<Dex>,
     ) -> Self {
         Self {
             token_start: token_start.to_string(),
@@ -58,46 +86,326 @@ impl TriangularPath {
             pair_1: format!("{}-{}", token_start, token_mid),
             pair_2: format!("{}-{}", token_mid, token_end),
             pair_3: format!("{}-{}", token_end, token_start),
-            dex: dex.to_string(),
+            dex: dex.into(),
         }
     }
 }
 
+/// A path in the scan set with its latest liquidity score
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntry {
+    pub path: TriangularPath,
+    /// Minimum fresh liquidity across the path's legs
+    pub min_liquidity: u64,
+    /// Disabled paths are skipped by the scanner until liquidity recovers
+    pub enabled: bool,
+    /// Excluded by the `[filters]` blacklist/whitelist
+    pub filtered: bool,
+}
+
+impl PathEntry {
+    fn is_active(&self) -> bool {
+        self.enabled && !self.filtered
+    }
+}
+
+/// Counts reported by each pruning pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PathSetMetrics {
+    pub total: usize,
+    pub enabled: usize,
+    pub disabled: usize,
+    pub filtered: usize,
+}
+
+/// Triangular scan set with liquidity-based pruning
+///
+/// Paths through dust pools are disabled by `prune` and re-enabled on a
+/// later pass once every leg has fresh liquidity above the floor again.
+/// Paths with a leg the cache hasn't seen yet are left alone, so a pass
+/// before the feed has warmed up doesn't disable everything.
+#[derive(Debug, Clone)]
+pub struct TriangularPathSet {
+    entries: Vec<PathEntry>,
+    /// Pair -> indices of entries with a leg on that pair
+    pair_index: HashMap<String, Vec<usize>>,
+    min_liquidity: u64,
+}
+
+impl TriangularPathSet {
+    pub fn new(paths: Vec<TriangularPath>, min_liquidity: u64) -> Self {
+        let mut pair_index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, path) in paths.iter().enumerate() {
+            for pair in [&path.pair_1, &path.pair_2, &path.pair_3] {
+                pair_index.entry(pair.clone()).or_default().push(i);
+            }
+        }
+
+        Self {
+            entries: paths
+                .into_iter()
+                .map(|path| PathEntry { path, min_liquidity: 0, enabled: true, filtered: false })
+                .collect(),
+            pair_index,
+            min_liquidity,
+        }
+    }
+
+    /// Paths the scanner should evaluate
+    pub fn active_paths(&self) -> impl Iterator<Item = &TriangularPath> {
+        self.entries.iter().filter(|e| e.is_active()).map(|e| &e.path)
+    }
+
+    /// Active paths with at least one leg on any of the given pairs
+    pub fn active_paths_for<'a, I>(&self, pairs: I) -> Vec<&TriangularPath>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut indices: Vec<usize> = pairs
+            .into_iter()
+            .filter_map(|pair| self.pair_index.get(pair))
+            .flatten()
+            .copied()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices
+            .into_iter()
+            .map(|i| &self.entries[i])
+            .filter(|e| e.is_active())
+            .map(|e| &e.path)
+            .collect()
+    }
+
+    /// All paths with their scores and state
+    pub fn entries(&self) -> &[PathEntry] {
+        &self.entries
+    }
+
+    pub fn metrics(&self) -> PathSetMetrics {
+        let enabled = self.entries.iter().filter(|e| e.enabled).count();
+        PathSetMetrics {
+            total: self.entries.len(),
+            enabled,
+            disabled: self.entries.len() - enabled,
+            filtered: self.entries.iter().filter(|e| e.filtered).count(),
+        }
+    }
+
+    /// Add paths not already in the set, returning how many were new
+    pub fn extend(&mut self, paths: impl IntoIterator<Item = TriangularPath>) -> usize {
+        let mut added = 0;
+        for path in paths {
+            let duplicate = self.entries.iter().any(|e| {
+                (&e.path.pair_1, &e.path.pair_2, &e.path.pair_3, &e.path.dex)
+                    == (&path.pair_1, &path.pair_2, &path.pair_3, &path.dex)
+            });
+            if duplicate {
+                continue;
+            }
+            for pair in [&path.pair_1, &path.pair_2, &path.pair_3] {
+                self.pair_index.entry(pair.clone()).or_default().push(self.entries.len());
+            }
+            self.entries.push(PathEntry { path, min_liquidity: 0, enabled: true, filtered: false });
+            added += 1;
+        }
+        added
+    }
+
+    /// Exclude paths through blacklisted (or non-whitelisted) tokens and pairs
+    pub fn apply_filters(&mut self, filters: &FiltersConfig) -> PathSetMetrics {
+        for entry in &mut self.entries {
+            let path = &entry.path;
+            entry.filtered = ![&path.token_start, &path.token_mid, &path.token_end]
+                .into_iter()
+                .all(|token| filters.allows_token(token))
+                || ![&path.pair_1, &path.pair_2, &path.pair_3]
+                    .into_iter()
+                    .all(|pair| filters.allows_pair(pair));
+        }
+        self.metrics()
+    }
+
+    /// Rescore every path against the cache and toggle paths around the floor
+    pub fn prune(&mut self, cache: &PriceCache) -> PathSetMetrics {
+        for entry in &mut self.entries {
+            let path = &entry.path;
+            let legs: Option<Vec<PriceData>> =
+                [&path.pair_1, &path.pair_2, &path.pair_3].into_iter().map(|pair| cache.get(pair, &path.dex)).collect();
+            let Some(legs) = legs else {
+                continue;
+            };
+            entry.min_liquidity = [&path.pair_1, &path.pair_2, &path.pair_3]
+                .into_iter()
+                .zip(&legs)
+                .map(|(pair, data)| if cache.is_stale(pair, &path.dex, data) { 0 } else { data.liquidity })
+                .min()
+                .unwrap_or(0);
+
+            let enabled = entry.min_liquidity >= self.min_liquidity;
+            if enabled != entry.enabled {
+                info!(
+                    pair_1 = path.pair_1,
+                    pair_2 = path.pair_2,
+                    pair_3 = path.pair_3,
+                    dex = %path.dex,
+                    min_liquidity = entry.min_liquidity,
+                    enabled = enabled,
+                    "Triangular path toggled by liquidity pruning"
+                );
+                entry.enabled = enabled;
+            }
+        }
+
+        self.metrics()
+    }
+}
+
 /// Detector for triangular arbitrage opportunities
 pub struct TriangularArbitrageDetector {
     cache: Arc<PriceCache>,
     config: TriangularArbConfig,
     fees: FeesConfig,
+    confirmation: SlotConfirmation,
+    volatility: Option<Arc<VolatilityTracker>>,
+    cost_feed: Option<Arc<CostFeed>>,
+    activity: Option<SwapActivity>,
+    risk: RiskModel,
+    tokens: TokenRegistry,
 }
 
 impl TriangularArbitrageDetector {
     pub fn new(cache: Arc<PriceCache>, config: TriangularArbConfig, fees: FeesConfig) -> Self {
         Self {
             cache,
+            confirmation: SlotConfirmation::new(config.confirmation_slots),
             config,
             fees,
+            volatility: None,
+            cost_feed: None,
+            activity: None,
+            risk: RiskModel::default(),
+            tokens: TokenRegistry::default(),
+        }
+    }
+
+    /// Parameters the detector was built with
+    pub fn config(&self) -> &TriangularArbConfig {
+        &self.config
+    }
+
+    /// Drop paths still being confirmed; returns how many
+    pub fn reset(&self) -> usize {
+        self.confirmation.clear()
+    }
+
+    /// Express cycle sizes and profits in start-token amounts using these decimals
+    pub fn with_token_registry(mut self, tokens: TokenRegistry) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Price gas and tips from a live cost feed instead of static fees
+    pub fn with_cost_feed(mut self, feed: Arc<CostFeed>) -> Self {
+        self.cost_feed = Some(feed);
+        self
+    }
+
+    /// Scale thresholds by the most volatile leg's regime
+    pub fn with_volatility_tracker(mut self, tracker: Arc<VolatilityTracker>) -> Self {
+        self.volatility = Some(tracker);
+        self
+    }
+
+    /// Assess worst-case profit and validity with this model
+    pub fn with_risk_model(mut self, risk: RiskModel) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Weigh the least active leg's swap activity into confidence
+    pub fn with_swap_activity(mut self, activity: SwapActivity) -> Self {
+        self.activity = Some(activity);
+        self
+    }
+
+    /// Apply reloaded fees and the shared `[arbitrage]` confirmation settings
+    pub fn reconfigure(&mut self, arbitrage: &ArbitrageConfig, fees: &FeesConfig) {
+        self.fees = fees.clone();
+        self.config.require_confirmed = arbitrage.require_confirmed;
+        self.config.non_streamed_confidence = arbitrage.non_streamed_confidence;
+        self.risk.reconfigure(arbitrage);
+        let confirmation_slots = arbitrage.confirmation_slots;
+        if self.config.confirmation_slots != confirmation_slots {
+            self.config.confirmation_slots = confirmation_slots;
+            self.confirmation = SlotConfirmation::new(confirmation_slots);
         }
     }
 
+    /// Effective (regime, min profit, slot tolerance) for a path
+    fn thresholds(&self, path: &TriangularPath) -> (Option<VolatilityRegime>, f64, u64) {
+        let Some(tracker) = &self.volatility else {
+            return (None, self.config.min_profit_percent, self.config.slot_tolerance);
+        };
+
+        let regime = [&path.pair_1, &path.pair_2, &path.pair_3]
+            .into_iter()
+            .map(|pair| tracker.regime(pair))
+            .max()
+            .unwrap_or(VolatilityRegime::Normal);
+        let (min_profit, slot_tolerance) = tracker.effective_thresholds(
+            regime,
+            self.config.min_profit_percent,
+            self.config.slot_tolerance,
+        );
+        (Some(regime), min_profit, slot_tolerance)
+    }
+
     /// Detect triangular arbitrage opportunity for a given path
     pub async fn detect(&self, path: &TriangularPath) -> Option<Opportunity> {
+        let key = format!("{}|{}|{}", path.pair_1, path.pair_2, path.pair_3);
+
+        let Some((mut opp, slot)) = self.evaluate(path) else {
+            self.confirmation.reset(&key);
+            return None;
+        };
+
+        opp.persisted_slots = self.confirmation.confirm(&key, path.dex.as_str(), slot)?;
+        let dex = &path.dex;
+        let legs = [(path.pair_1.as_str(), dex), (&path.pair_2, dex), (&path.pair_3, dex)];
+        if let Some(factor) = self.activity.as_ref().and_then(|a| a.factor(&legs)) {
+            opp.confidence = activity::weigh_confidence(opp.confidence, factor);
+        }
+        self.risk.assess(&mut opp);
+        Some(opp)
+    }
+
+    /// Evaluate a path, returning a qualifying opportunity and its latest slot
+    fn evaluate(&self, path: &TriangularPath) -> Option<(Opportunity, u64)> {
+        let (regime, min_profit_percent, slot_tolerance) = self.thresholds(path);
+
         // Get prices for all three legs (DashMap is lock-free, no await)
         let price_1 = self.cache.get(&path.pair_1, &path.dex)?;
         let price_2 = self.cache.get(&path.pair_2, &path.dex)?;
         let price_3 = self.cache.get(&path.pair_3, &path.dex)?;
 
         // Check for stale data
-        if self.cache.is_stale(&price_1) 
-            || self.cache.is_stale(&price_2) 
-            || self.cache.is_stale(&price_3) 
+        if self.cache.is_stale(&path.pair_1, &path.dex, &price_1) 
+            || self.cache.is_stale(&path.pair_2, &path.dex, &price_2) 
+            || self.cache.is_stale(&path.pair_3, &path.dex, &price_3) 
         {
             return None;
         }
 
+        if self.config.require_confirmed && !(price_1.confirmed && price_2.confirmed && price_3.confirmed) {
+            return None;
+        }
+
         // Validate slot alignment
         let max_slot = price_1.slot.max(price_2.slot).max(price_3.slot);
         let min_slot = price_1.slot.min(price_2.slot).min(price_3.slot);
-        if max_slot - min_slot > self.config.slot_tolerance {
+        if max_slot - min_slot > slot_tolerance {
             debug!(
                 path = ?path,
                 slot_diff = max_slot - min_slot,
@@ -106,65 +414,119 @@ impl TriangularArbitrageDetector {
             return None;
         }
 
-        // Calculate effective rates for each leg
-        // Leg 1: Start -> Mid (selling Start for Mid)
-        let rate_1 = price_1.price * (1.0 - price_1.fee_rate);
-        // Leg 2: Mid -> End (selling Mid for End)
-        let rate_2 = price_2.price * (1.0 - price_2.fee_rate);
-        // Leg 3: End -> Start (selling End for Start)
-        let rate_3 = price_3.price * (1.0 - price_3.fee_rate);
-
         // Calculate final amount after full cycle
-        // Starting with 1 unit of token_start
-        let final_amount = rate_1 * rate_2 * rate_3;
+        // Starting with 1 unit of token_start, selling it on each leg in turn
+        // (exactly, when every leg was decoded with an exact price)
+        let final_amount = cycle_product(&[&price_1, &price_2, &price_3]);
 
         // Calculate profit percentage
         let gross_profit_percent = (final_amount - 1.0) * 100.0;
 
+        let min_liquidity = price_1.liquidity.min(price_2.liquidity).min(price_3.liquidity);
+
+        // Size in start-token base units from the legs' reserves, then
+        // slippage per leg at that size, propagated through the cycle
+        let legs = [&price_1, &price_2, &price_3];
+        let rates = self.cycle_rates(path, legs);
+        let recommended_size = rates.map_or(0, |rates| cycle_size(legs, rates));
+        let leg_slippage = self.estimate_leg_slippage(legs, rates, recommended_size);
+
         // Deduct additional costs (gas, tips, slippage for 3 swaps)
-        let additional_costs = self.fees.gas_cost_percent 
-            + self.fees.jito_tip_percent 
-            + (self.fees.estimated_slippage * 3.0); // 3 swaps
-        
+        let fees = match &self.cost_feed {
+            Some(feed) => feed.effective_fees(),
+            None => self.fees.clone(),
+        };
+        let fixed_costs: f64 = [&price_1, &price_2, &price_3].iter().map(|leg| leg.fixed_cost_rate * 100.0).sum();
+        let costs_before_tip = fees.gas_cost_percent + fixed_costs + leg_slippage.iter().sum::<f64>();
+        let additional_costs =
+            costs_before_tip + fees.tip_cost_percent(gross_profit_percent - costs_before_tip);
+
         let net_profit_percent = gross_profit_percent - additional_costs;
 
         debug!(
             path = format!("{} -> {} -> {} -> {}", 
                 path.token_start, path.token_mid, path.token_end, path.token_start),
             gross = gross_profit_percent,
+            slippage = ?leg_slippage,
             net = net_profit_percent,
             "Triangular arbitrage calculation"
         );
 
-        if net_profit_percent > self.config.min_profit_percent {
-            // Calculate recommended size based on minimum liquidity
-            let min_liquidity = price_1.liquidity.min(price_2.liquidity).min(price_3.liquidity);
-            let recommended_size = (min_liquidity as f64 * 0.03) as u64; // 3% of smallest pool
-
+        if net_profit_percent > min_profit_percent {
             // Calculate confidence based on liquidity and slot alignment
-            let confidence = calculate_triangular_confidence(
+            let mut confidence = calculate_triangular_confidence(
                 min_liquidity,
                 max_slot - min_slot,
             );
+            if ![&price_1, &price_2, &price_3].iter().all(|leg| leg.source.is_streamed()) {
+                confidence *= self.config.non_streamed_confidence;
+            }
+            confidence *= price_1.confidence_weight * price_2.confidence_weight * price_3.confidence_weight;
 
-            return Some(Opportunity {
-                opportunity_type: OpportunityType::Triangular,
-                token_pair: format!("{}->{}->{}->{}",
-                    path.token_start, path.token_mid, path.token_end, path.token_start),
-                buy_dex: path.dex.clone(),
-                sell_dex: path.dex.clone(),
-                buy_price: 1.0, // Starting with 1 unit
-                sell_price: final_amount,
-                net_profit_percent,
-                recommended_size,
-                confidence,
-                detected_at: Utc::now(),
-            });
+            let mut opportunity = Opportunity::builder()
+                .opportunity_type(OpportunityType::Triangular)
+                .token_pair(format!("{}->{}->{}->{}",
+                    path.token_start, path.token_mid, path.token_end, path.token_start))
+                // Starting with 1 unit; the cycle buys on the first leg and
+                // sells back on the last
+                .buy(&path.dex, 1.0, price_1.slot)
+                .sell(&path.dex, final_amount, price_3.slot)
+                // Each leg sells its pair's base token for the next one
+                .legs(
+                    [(&path.pair_1, &price_1), (&path.pair_2, &price_2), (&path.pair_3, &price_3)]
+                        .into_iter()
+                        .map(|(pair, data)| RouteLeg::new(pair, &path.dex, LegSide::Sell, data))
+                        .collect(),
+                )
+                .net_profit_percent(net_profit_percent)
+                .recommended_size(recommended_size)
+                .confidence(confidence)
+                .leg_slippage_percent(leg_slippage.to_vec())
+                .volatility_regime(regime)
+                .build()
+                .map_err(|error| warn!(path = ?path, %error, "Discarding inconsistent triangular opportunity"))
+                .ok()?;
+            // The cycle starts and ends in the same token, so profit is in it too
+            self.tokens.denominate(&mut opportunity, &path.token_start, &path.token_start, 1.0);
+            return Some((opportunity, max_slot));
         }
 
         None
     }
 
+    /// Base units of each leg's output token per base unit of its input
+    ///
+    /// `None` when a token's decimals are unknown, since prices are quoted
+    /// in whole tokens.
+    fn cycle_rates(&self, path: &TriangularPath, legs: [&PriceData; 3]) -> Option<[f64; 3]> {
+        let decimals = [&path.token_start, &path.token_mid, &path.token_end]
+            .map(|token| self.tokens.decimals(token).map(i32::from));
+        let decimals = [decimals[0]?, decimals[1]?, decimals[2]?];
+        Some(std::array::from_fn(|i| legs[i].price * 10f64.powi(decimals[(i + 1) % 3] - decimals[i])))
+    }
+
+    /// Estimate price impact for each leg when trading `size` through the cycle
+    ///
+    /// The amount entering each leg is the previous leg's output, converted
+    /// with `rates`, so a thin pool late in the path is charged for the size
+    /// that actually reaches it. Legs without depth data, or every leg when
+    /// the rates are unknown, fall back to the flat `estimated_slippage`.
+    fn estimate_leg_slippage(&self, legs: [&PriceData; 3], rates: Option<[f64; 3]>, size: u64) -> [f64; 3] {
+        let Some(rates) = rates else {
+            return [self.fees.estimated_slippage; 3];
+        };
+        let mut amount_in = size as f64;
+        let mut slippage = [0.0; 3];
+
+        for ((leg_slippage, leg), rate) in slippage.iter_mut().zip(legs).zip(rates) {
+            *leg_slippage = leg_price_impact(leg, LegSide::Sell, rate, amount_in)
+                .unwrap_or(self.fees.estimated_slippage);
+            amount_in *= rate * (1.0 - leg.fee_rate) * (1.0 - *leg_slippage / 100.0);
+        }
+
+        slippage
+    }
+
     /// Scan all configured triangular paths
     pub async fn scan_all(&self, paths: &[TriangularPath]) -> Vec<Opportunity> {
         let mut opportunities = Vec::new();
@@ -179,6 +541,57 @@ impl TriangularArbitrageDetector {
     }
 }
 
+/// `POOL_SHARE` of the shallowest leg's input reserve, in start-token base
+/// units; 0 when no leg carries depth data
+fn cycle_size(legs: [&PriceData; 3], rates: [f64; 3]) -> u64 {
+    // Start-token base units per base unit entering the current leg
+    let mut start_per_unit = 1.0;
+    let mut shallowest = f64::INFINITY;
+    for (leg, rate) in legs.into_iter().zip(rates) {
+        if let Some(reserve) = input_reserve(leg, LegSide::Sell, rate) {
+            shallowest = shallowest.min(reserve * start_per_unit);
+        }
+        start_per_unit /= rate;
+    }
+    if shallowest.is_finite() {
+        (shallowest * POOL_SHARE) as u64
+    } else {
+        0
+    }
+}
+
+/// Reserve of the token a leg takes in, in its base units
+///
+/// Selling the pair's base token draws on vault A and buying it on vault
+/// B. CLMM pools report liquidity L = sqrt(x·y) rather than balances, so
+/// their virtual reserves are L/sqrt(p) and L·sqrt(p) at `raw_price` p,
+/// quote base units per base-token base unit.
+fn input_reserve(leg: &PriceData, side: LegSide, raw_price: f64) -> Option<f64> {
+    if leg.vault_a_balance > 0 && leg.vault_b_balance > 0 {
+        let reserve = match side {
+            LegSide::Sell => leg.vault_a_balance,
+            LegSide::Buy => leg.vault_b_balance,
+        };
+        Some(reserve as f64)
+    } else if leg.liquidity > 0 && raw_price > 0.0 {
+        let liquidity = leg.liquidity as f64;
+        Some(match side {
+            LegSide::Sell => liquidity / raw_price.sqrt(),
+            LegSide::Buy => liquidity * raw_price.sqrt(),
+        })
+    } else {
+        None
+    }
+}
+
+/// Price impact of trading `amount_in` base units into the leg on `side`
+///
+/// Returns `None` when the leg carries no depth data.
+fn leg_price_impact(leg: &PriceData, side: LegSide, raw_price: f64, amount_in: f64) -> Option<f64> {
+    let reserve = input_reserve(leg, side, raw_price)?;
+    Some(calculate_amm_price_impact(amount_in as u64, reserve as u64))
+}
+
 fn calculate_triangular_confidence(min_liquidity: u64, slot_diff: u64) -> f64 {
     // Higher liquidity and lower slot difference = higher confidence
     let liquidity_factor = (min_liquidity as f64 / 1_000_000.0).min(1.0);
@@ -188,7 +601,8 @@ fn calculate_triangular_confidence(min_liquidity: u64, slot_diff: u64) -> f64 {
 }
 
 /// Generate common triangular paths for Solana DEXs
-pub fn generate_common_paths(dex: &str) -> Vec<TriangularPath> {
+pub fn generate_common_paths(dex: impl Into<Dex>) -> Vec<TriangularPath> {
+    let dex = &dex.into();
     vec![
         // SOL-based triangles
         TriangularPath::new("SOL", "USDC", "BONK", dex),
@@ -215,12 +629,234 @@ mod tests {
         assert_eq!(path.pair_3, "BONK-SOL");
     }
 
+    #[test]
+    fn test_extend_skips_known_paths() {
+        let mut paths = TriangularPathSet::new(generate_common_paths("raydium"), 0);
+        let total = paths.entries().len();
+
+        assert_eq!(paths.extend(generate_common_paths("raydium")), 0);
+        assert_eq!(paths.extend([TriangularPath::new("SOL", "USDC", "BONK", "orca")]), 1);
+        assert_eq!(paths.entries().len(), total + 1);
+        let orca: Vec<_> = paths.active_paths_for(["USDC-BONK"]).into_iter().filter(|p| p.dex == Dex::Orca).collect();
+        assert_eq!(orca.len(), 1);
+    }
+
     #[test]
     fn test_generate_common_paths() {
         let paths = generate_common_paths("raydium");
         assert!(paths.len() >= 5);
     }
 
+    #[test]
+    fn test_active_paths_for_affected_pairs() {
+        let path_set = TriangularPathSet::new(generate_common_paths("raydium"), 0);
+
+        // Only the SOL-USDC-JUP triangle has a JUP-SOL leg
+        let paths = path_set.active_paths_for(["JUP-SOL"]);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].token_end, "JUP");
+
+        // Paths sharing several affected legs are returned once
+        let paths = path_set.active_paths_for(["SOL-USDC", "USDC-BONK"]);
+        assert_eq!(paths.len(), 5);
+    }
+
+    fn test_fees() -> FeesConfig {
+        FeesConfig {
+            default_dex_fee: 0.25,
+            estimated_slippage: 0.3,
+            gas_cost_percent: 0.01,
+            jito_tip_percent: 0.05,
+            tip_strategy: None,
+            trade_size_lamports: 10_000_000_000,
+            overrides: Default::default(),
+            schedule_file: None,
+            schedule: Default::default(),
+        }
+    }
+
+    /// Cache with a ~2% gross cycle A -> B -> C -> A, leg 2 vault as given
+    fn cycle_cache(leg_2_vault: u64) -> Arc<PriceCache> {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let deep = 1_000_000_000_000;
+        cache.set("A-B", &Dex::Raydium, PriceData::new(1.02, 1_000_000, 100, deep, deep, 0.0));
+        cache.set("B-C", &Dex::Raydium, PriceData::new(1.0, 1_000_000, 100, leg_2_vault, deep, 0.0));
+        cache.set("C-A", &Dex::Raydium, PriceData::new(1.0, 1_000_000, 100, deep, deep, 0.0));
+        cache
+    }
+
+    fn cycle_detector(cache: Arc<PriceCache>, config: TriangularArbConfig) -> TriangularArbitrageDetector {
+        let tokens = TokenRegistry::new(["A", "B", "C"].map(|token| (token.to_string(), 6)));
+        TriangularArbitrageDetector::new(cache, config, test_fees()).with_token_registry(tokens)
+    }
+
+    #[tokio::test]
+    async fn test_deep_legs_use_reserve_slippage() {
+        let cache = cycle_cache(1_000_000_000_000);
+        let deep = 1_000_000_000_000;
+        cache.set("C-A", &Dex::Raydium, PriceData::new(1.0, 1_000_000, 101, deep, deep, 0.0));
+        let detector = cycle_detector(cache, TriangularArbConfig::default());
+        let path = TriangularPath::new("A", "B", "C", "raydium");
+
+        let opp = detector.detect(&path).await.expect("deep cycle should be profitable");
+        // Sized at 0.1% of leg 2's 1M B reserve, worth ~980k A, so each leg
+        // moves about 0.1%
+        assert_eq!(opp.recommended_size, 980_392_156);
+        assert_eq!(opp.leg_slippage_percent.len(), 3);
+        assert!(opp.leg_slippage_percent.iter().all(|s| (0.09..0.11).contains(s)), "{:?}", opp.leg_slippage_percent);
+        // Well above the flat 3 x 0.3% estimate would allow
+        assert!(opp.net_profit_percent > 1.6);
+        let legs: Vec<_> = opp.legs.iter().map(|leg| (leg.pair.as_str(), leg.side, leg.slot)).collect();
+        assert_eq!(legs, vec![("A-B", LegSide::Sell, 100), ("B-C", LegSide::Sell, 100), ("C-A", LegSide::Sell, 101)]);
+        assert!(opp.summary().contains("sell A-B on raydium @ 1.02 -> sell B-C on raydium @ 1 -> sell C-A on raydium @ 1"));
+        assert_eq!((opp.buy_slot, opp.sell_slot), (100, 101));
+        assert_ne!(detector.detect(&path).await.unwrap().id, opp.id);
+    }
+
+    #[tokio::test]
+    async fn test_thin_leg_caps_the_size() {
+        // Leg 2 holds only 1M B, worth ~980k A after the first leg
+        let detector = cycle_detector(cycle_cache(1_000_000), TriangularArbConfig::default());
+        let path = TriangularPath::new("A", "B", "C", "raydium");
+
+        let opp = detector.detect(&path).await.expect("the smaller size keeps the cycle profitable");
+        assert_eq!(opp.recommended_size, 980);
+        assert!(opp.leg_slippage_percent[1] < 0.11);
+    }
+
+    #[test]
+    fn test_leg_slippage_falls_back_without_depth() {
+        let detector = TriangularArbitrageDetector::new(
+            Arc::new(PriceCache::new(60, 2000)),
+            TriangularArbConfig::default(),
+            test_fees(),
+        );
+        let no_depth = PriceData::new(1.0, 0, 100, 0, 0, 0.0);
+        let deep = PriceData::new(1.0, 0, 100, 1_000_000_000, 1_000_000_000, 0.0);
+
+        let slippage = detector.estimate_leg_slippage([&no_depth, &deep, &no_depth], Some([1.0; 3]), 1_000);
+        assert_eq!(slippage[0], 0.3);
+        assert!(slippage[1] < 0.001);
+        assert_eq!(slippage[2], 0.3);
+
+        // Without decimals the amounts can't be carried between legs
+        assert_eq!(detector.estimate_leg_slippage([&deep, &deep, &deep], None, 1_000), [0.3; 3]);
+    }
+
+    #[test]
+    fn test_slippage_carries_amounts_across_decimals() {
+        let tokens = [("SOL", 9), ("USDC", 6), ("BONK", 5)].map(|(token, decimals)| (token.to_string(), decimals));
+        let detector = TriangularArbitrageDetector::new(
+            Arc::new(PriceCache::new(60, 2000)),
+            TriangularArbConfig::default(),
+            test_fees(),
+        )
+        .with_token_registry(TokenRegistry::new(tokens));
+        let path = TriangularPath::new("SOL", "USDC", "BONK", "raydium");
+        // 1,000 SOL / 100,000 USDC; 10,000 USDC / 500M BONK; 1e12 BONK / 200,000 SOL
+        let sol_usdc = PriceData::new(100.0, 0, 100, 1_000_000_000_000, 100_000_000_000, 0.0);
+        let usdc_bonk = PriceData::new(50_000.0, 0, 100, 10_000_000_000, 50_000_000_000_000, 0.0);
+        let bonk_sol = PriceData::new(2e-7, 0, 100, 100_000_000_000_000_000, 200_000_000_000_000, 0.0);
+        let legs = [&sol_usdc, &usdc_bonk, &bonk_sol];
+
+        let rates = detector.cycle_rates(&path, legs).unwrap();
+        for (rate, expected) in rates.into_iter().zip([0.1, 5_000.0, 0.002]) {
+            assert!((rate / expected - 1.0).abs() < 1e-9, "{} vs {}", rate, expected);
+        }
+
+        // 1 SOL arrives at leg 2 as ~100 USDC, ~1% of its 10,000 USDC reserve
+        let slippage = detector.estimate_leg_slippage(legs, Some(rates), 1_000_000_000);
+        assert!((slippage[0] - 0.0999).abs() < 0.001, "{:?}", slippage);
+        assert!((slippage[1] - 0.989).abs() < 0.001, "{:?}", slippage);
+        assert!(slippage[2] < 0.001, "{:?}", slippage);
+        // The shallowest reserve is leg 2's 10,000 USDC, i.e. 100 SOL
+        assert_eq!(cycle_size(legs, rates), 100_000_000);
+    }
+
+    #[test]
+    fn test_price_impact_uses_the_input_side_reserve() {
+        // 1,000 SOL against 100,000 USDC: 1 SOL in is 0.1% of its side,
+        // 1,000 USDC in is 1% of the other
+        let amm = PriceData::new(100.0, 0, 100, 1_000_000_000_000, 100_000_000_000, 0.0);
+        let sell = leg_price_impact(&amm, LegSide::Sell, 0.1, 1_000_000_000.0).unwrap();
+        let buy = leg_price_impact(&amm, LegSide::Buy, 0.1, 1_000_000_000.0).unwrap();
+        assert!((sell - 0.0999).abs() < 0.001 && (buy - 0.990).abs() < 0.001, "sell {}, buy {}", sell, buy);
+
+        // CLMM: x = 4e12, y = 1e10, so L = 2e11 at a raw price of 0.0025
+        let clmm = PriceData::new(2.5, 200_000_000_000, 100, 0, 0, 0.0);
+        assert_eq!(input_reserve(&clmm, LegSide::Sell, 0.0025), Some(4e12));
+        assert_eq!(input_reserve(&clmm, LegSide::Buy, 0.0025), Some(1e10));
+        let sell = leg_price_impact(&clmm, LegSide::Sell, 0.0025, 4e10).unwrap();
+        assert!((sell - 0.990).abs() < 0.001, "{}", sell);
+        assert_eq!(leg_price_impact(&PriceData::new(1.0, 0, 100, 0, 0, 0.0), LegSide::Buy, 1.0, 1.0), None);
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_requires_persistent_cycle() {
+        let cache = cycle_cache(1_000_000_000_000);
+        let config = TriangularArbConfig {
+            confirmation_slots: 2,
+            ..TriangularArbConfig::default()
+        };
+        let detector = cycle_detector(cache.clone(), config);
+        let path = TriangularPath::new("A", "B", "C", "raydium");
+
+        assert!(detector.detect(&path).await.is_none());
+
+        let deep = 1_000_000_000_000;
+        cache.set("A-B", &Dex::Raydium, PriceData::new(1.02, 1_000_000, 101, deep, deep, 0.0));
+        let opp = detector.detect(&path).await.expect("cycle persisted for two slots");
+        assert_eq!(opp.persisted_slots, 2);
+    }
+
+    #[test]
+    fn test_pruning_disables_and_restores_dust_paths() {
+        let cache = cycle_cache(1_000_000_000_000);
+        cache.set("B-C", &Dex::Raydium, PriceData::new(1.0, 0, 100, 0, 0, 0.0));
+
+        let path = TriangularPath::new("A", "B", "C", "raydium");
+        let mut paths = TriangularPathSet::new(vec![path], 10_000);
+
+        let metrics = paths.prune(&cache);
+        assert_eq!(metrics, PathSetMetrics { total: 1, enabled: 0, disabled: 1, filtered: 0 });
+        assert_eq!(paths.active_paths().count(), 0);
+
+        cache.set("B-C", &Dex::Raydium, PriceData::new(1.0, 500_000, 101, 1_000, 1_000, 0.0));
+        let metrics = paths.prune(&cache);
+        assert_eq!(metrics.enabled, 1);
+        assert_eq!(paths.entries()[0].min_liquidity, 500_000);
+    }
+
+    #[test]
+    fn test_pruning_waits_for_every_leg_to_be_seen() {
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let path = TriangularPath::new("A", "B", "C", "raydium");
+        let mut paths = TriangularPathSet::new(vec![path], 10_000);
+
+        // Nothing cached yet, then one leg: too early to judge the path
+        assert_eq!(paths.prune(&cache).enabled, 1);
+        cache.set("A-B", &Dex::Raydium, PriceData::new(1.0, 0, 100, 0, 0, 0.0));
+        assert_eq!(paths.prune(&cache).enabled, 1);
+        assert_eq!(paths.active_paths().count(), 1);
+    }
+
+    #[test]
+    fn test_blacklisted_token_removes_its_cycles() {
+        let mut paths = TriangularPathSet::new(generate_common_paths("raydium"), 0);
+        let filters = FiltersConfig {
+            token_blacklist: vec!["bonk".to_string()],
+            ..FiltersConfig::default()
+        };
+
+        let metrics = paths.apply_filters(&filters);
+        assert_eq!(metrics.filtered, 2);
+        assert!(paths.active_paths().all(|p| p.token_end != "BONK"));
+
+        // Lifting the blacklist restores them
+        paths.apply_filters(&FiltersConfig::default());
+        assert_eq!(paths.active_paths().count(), generate_common_paths("raydium").len());
+    }
+
     #[test]
     fn test_confidence_calculation() {
         // High liquidity, low slot diff
@@ -0,0 +1,211 @@
+//! Pair universe construction for statistical arbitrage
+//!
+//! Builds the candidate pair combinations fed to the statistical detector
+//! from whatever pairs are currently present in the price cache.
+
+use crate::cache::PriceCache;
+use crate::config::{FiltersConfig, StatArbSettings};
+use crate::models::Dex;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+
+/// Two pairs quoted in the same token on the same DEX
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatArbCandidate {
+    pub pair_a: String,
+    pub pair_b: String,
+    pub dex: Dex,
+}
+
+/// Candidate combinations for the periodic statistical scan
+///
+/// Rebuilt only when the set of (pair, DEX) entries in the cache changes.
+pub struct PairUniverse {
+    max_combinations: usize,
+    allowed_pairs: BTreeSet<String>,
+    filters: FiltersConfig,
+    known_entries: BTreeSet<(String, Dex)>,
+    candidates: Vec<StatArbCandidate>,
+}
+
+impl PairUniverse {
+    pub fn new(settings: &StatArbSettings) -> Self {
+        Self {
+            max_combinations: settings.max_combinations,
+            allowed_pairs: settings.allowed_pairs.iter().cloned().collect(),
+            filters: FiltersConfig::default(),
+            known_entries: BTreeSet::new(),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Exclude blacklisted pairs and tokens; forces a rebuild when changed
+    pub fn set_filters(&mut self, filters: &FiltersConfig) {
+        if *filters != self.filters {
+            self.filters = filters.clone();
+            self.known_entries.clear();
+        }
+    }
+
+    /// Current candidate list
+    pub fn candidates(&self) -> &[StatArbCandidate] {
+        &self.candidates
+    }
+
+    /// Rebuild candidates if new pairs appeared in (or left) the cache
+    ///
+    /// Returns true when the candidate list was rebuilt.
+    pub fn refresh(&mut self, cache: &PriceCache) -> bool {
+        let entries: BTreeSet<(String, Dex)> = cache
+            .get_all_pairs()
+            .into_iter()
+            .filter(|pair| self.allowed_pairs.is_empty() || self.allowed_pairs.contains(pair))
+            .filter(|pair| self.filters.allows_pair(pair))
+            .flat_map(|pair| {
+                cache
+                    .get_all_dexes(&pair)
+                    .into_iter()
+                    .map(move |(dex, _)| (pair.clone(), dex))
+            })
+            .collect();
+
+        if entries == self.known_entries {
+            return false;
+        }
+
+        self.candidates = build_candidates(&entries, self.max_combinations);
+        self.known_entries = entries;
+
+        info!(
+            pairs = self.known_entries.len(),
+            candidates = self.candidates.len(),
+            "Statistical arbitrage universe rebuilt"
+        );
+
+        true
+    }
+}
+
+/// Build combinations of pairs sharing a quote token on the same DEX
+fn build_candidates(
+    entries: &BTreeSet<(String, Dex)>,
+    max_combinations: usize,
+) -> Vec<StatArbCandidate> {
+    // (dex, quote) -> pairs, ordered for deterministic output
+    let mut groups: BTreeMap<(&Dex, String), Vec<&str>> = BTreeMap::new();
+    for (pair, dex) in entries {
+        if let Some(quote) = quote_token(pair) {
+            groups.entry((dex, quote)).or_default().push(pair.as_str());
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for ((dex, _), pairs) in &groups {
+        for (i, pair_a) in pairs.iter().enumerate() {
+            for pair_b in &pairs[i + 1..] {
+                candidates.push(StatArbCandidate {
+                    pair_a: pair_a.to_string(),
+                    pair_b: pair_b.to_string(),
+                    dex: (*dex).clone(),
+                });
+            }
+        }
+    }
+
+    candidates.truncate(max_combinations);
+    candidates
+}
+
+/// Quote token of a pair name ("SOL-USDC" or "sol_usdc" -> "USDC")
+fn quote_token(pair: &str) -> Option<String> {
+    let (_, quote) = pair.rsplit_once(['-', '_'])?;
+    if quote.is_empty() {
+        return None;
+    }
+    Some(quote.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriceData;
+
+    fn cache_with(pairs: &[&str]) -> PriceCache {
+        let cache = PriceCache::new(60, 2000);
+        for pair in pairs {
+            cache.set(pair, &Dex::Raydium, PriceData::new(1.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        }
+        cache
+    }
+
+    #[test]
+    fn test_only_shared_quote_combinations() {
+        let cache = cache_with(&["SOL-USDC", "JUP-USDC", "BONK-USDT"]);
+        let mut universe = PairUniverse::new(&StatArbSettings::default());
+
+        assert!(universe.refresh(&cache));
+        assert_eq!(
+            universe.candidates(),
+            &[StatArbCandidate {
+                pair_a: "JUP-USDC".to_string(),
+                pair_b: "SOL-USDC".to_string(),
+                dex: Dex::Raydium,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_blacklisted_tokens_leave_the_universe() {
+        let cache = cache_with(&["SOL-USDC", "JUP-USDC", "JTO-USDC"]);
+        let mut universe = PairUniverse::new(&StatArbSettings::default());
+        universe.set_filters(&FiltersConfig {
+            token_blacklist: vec!["JUP".to_string()],
+            ..FiltersConfig::default()
+        });
+
+        assert!(universe.refresh(&cache));
+        assert_eq!(universe.candidates().len(), 1);
+        assert!(universe.candidates().iter().all(|c| c.pair_a != "JUP-USDC" && c.pair_b != "JUP-USDC"));
+    }
+
+    #[test]
+    fn test_refresh_only_on_new_pairs() {
+        let cache = cache_with(&["SOL-USDC", "JUP-USDC"]);
+        let mut universe = PairUniverse::new(&StatArbSettings::default());
+
+        assert!(universe.refresh(&cache));
+        assert!(!universe.refresh(&cache));
+
+        cache.set("JTO-USDC", &Dex::Raydium, PriceData::new(1.0, 1_000_000, 1, 500_000, 500_000, 0.003));
+        assert!(universe.refresh(&cache));
+        assert_eq!(universe.candidates().len(), 3);
+    }
+
+    #[test]
+    fn test_max_combinations_and_allow_list() {
+        let cache = cache_with(&["SOL-USDC", "JUP-USDC", "JTO-USDC", "BONK-USDC"]);
+
+        let settings = StatArbSettings {
+            max_combinations: 2,
+            ..StatArbSettings::default()
+        };
+        let mut universe = PairUniverse::new(&settings);
+        universe.refresh(&cache);
+        assert_eq!(universe.candidates().len(), 2);
+
+        let settings = StatArbSettings {
+            allowed_pairs: vec!["SOL-USDC".to_string(), "JUP-USDC".to_string()],
+            ..StatArbSettings::default()
+        };
+        let mut universe = PairUniverse::new(&settings);
+        universe.refresh(&cache);
+        assert_eq!(universe.candidates().len(), 1);
+    }
+
+    #[test]
+    fn test_quote_token() {
+        assert_eq!(quote_token("SOL-USDC").as_deref(), Some("USDC"));
+        assert_eq!(quote_token("bonk_sol").as_deref(), Some("SOL"));
+        assert_eq!(quote_token("SOL"), None);
+    }
+}
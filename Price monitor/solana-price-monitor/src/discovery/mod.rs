@@ -0,0 +1,377 @@
+//! Automatic pool discovery from DEX registries
+//!
+//! Queries the Raydium and Orca public pool lists for the deepest pools
+//! between tokens on an allow-list and merges them into the static `[pools]`
+//! table. Statically configured pools always win; discovered pools fill the
+//! remaining `max_pools` budget in order of liquidity.
+
+use crate::config::{DiscoveryConfig, RedactedUrl};
+use crate::decoder::program_id;
+use crate::models::Dex;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// A pool found in a DEX registry
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredPool {
+    /// Pair in pool orientation, e.g. "SOL-USDC"
+    pub pair: String,
+    pub dex: Dex,
+    pub pubkey: String,
+    /// Fee tier as a fraction (0.0025 = 0.25%)
+    pub fee_rate: f64,
+    /// Total value locked in USD as reported by the registry
+    pub liquidity_usd: f64,
+}
+
+/// Fetches and filters pools from the configured registries
+pub struct PoolDiscovery {
+    config: DiscoveryConfig,
+    http: reqwest::Client,
+}
+
+impl PoolDiscovery {
+    pub fn new(config: DiscoveryConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Fetch with `http` instead of a default client (e.g. one with a proxy)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Top pools by liquidity across all registries
+    ///
+    /// A failing registry is logged and skipped so one outage doesn't
+    /// block startup.
+    pub async fn discover(&self) -> Vec<DiscoveredPool> {
+        let mut pools = Vec::new();
+
+        match self.fetch(&self.config.raydium_url).await {
+            Ok(body) => pools.extend(parse_raydium(&body)),
+            Err(e) => warn!(error = ?e, "Raydium pool discovery failed"),
+        }
+        match self.fetch(&self.config.orca_url).await {
+            Ok(body) => pools.extend(parse_orca(&body)),
+            Err(e) => warn!(error = ?e, "Orca pool discovery failed"),
+        }
+
+        select_pools(pools, &self.config)
+    }
+
+    /// Re-run discovery on an interval and log pools not seen before
+    ///
+    /// New listings are reported only; subscribing to them takes a restart.
+    pub fn spawn_refresh_task(self, known: Vec<DiscoveredPool>, shutdown: CancellationToken) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.refresh_interval_seconds);
+        tokio::spawn(async move {
+            let mut known: HashSet<String> = known.into_iter().map(|p| p.pubkey).collect();
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+
+            let refresh = async move {
+                loop {
+                    ticker.tick().await;
+                    for pool in self.discover().await {
+                        if known.insert(pool.pubkey.clone()) {
+                            info!(
+                                pair = pool.pair,
+                                dex = %pool.dex,
+                                pubkey = pool.pubkey,
+                                fee_rate = pool.fee_rate,
+                                liquidity_usd = pool.liquidity_usd,
+                                "New pool listing discovered (restart to monitor)"
+                            );
+                        }
+                    }
+                }
+            };
+            shutdown.run_until_cancelled(refresh).await;
+        })
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Value> {
+        // reqwest errors embed the URL, which may carry an API key
+        self.http
+            .get(url)
+            .timeout(Duration::from_secs(self.config.request_timeout_seconds))
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Request to {} failed", RedactedUrl::new(url)))?
+            .error_for_status()
+            .map_err(reqwest::Error::without_url)?
+            .json()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("Invalid pool list response")
+    }
+}
+
+/// Pools from a Raydium v3 `pools/info/list` response
+///
+/// "Standard" pools include CPMM ones, whose accounts the AMM v4 decoder
+/// can't read, so only pools owned by the AMM v4 program are kept.
+pub fn parse_raydium(body: &Value) -> Vec<DiscoveredPool> {
+    let Some(entries) = body.pointer("/data/data").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter(|pool| pool.get("programId").and_then(|v| v.as_str()) == program_id(&Dex::Raydium))
+        .filter_map(|pool| {
+            Some(DiscoveredPool {
+                pair: pair_name(
+                    pool.pointer("/mintA/symbol")?.as_str()?,
+                    pool.pointer("/mintB/symbol")?.as_str()?,
+                ),
+                dex: Dex::Raydium,
+                pubkey: pool.get("id")?.as_str()?.to_string(),
+                fee_rate: pool.get("feeRate").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                liquidity_usd: pool.get("tvl").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+/// Pools from an Orca `whirlpool/list` response
+pub fn parse_orca(body: &Value) -> Vec<DiscoveredPool> {
+    let Some(entries) = body.get("whirlpools").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|pool| {
+            Some(DiscoveredPool {
+                pair: pair_name(
+                    pool.pointer("/tokenA/symbol")?.as_str()?,
+                    pool.pointer("/tokenB/symbol")?.as_str()?,
+                ),
+                dex: Dex::Orca,
+                pubkey: pool.get("address")?.as_str()?.to_string(),
+                fee_rate: pool.get("lpFeeRate").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                liquidity_usd: pool.get("tvl").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+/// Registries list wrapped SOL as "WSOL"
+fn pair_name(symbol_a: &str, symbol_b: &str) -> String {
+    let normalize = |s: &str| match s.to_uppercase().as_str() {
+        "WSOL" => "SOL".to_string(),
+        other => other.to_string(),
+    };
+    format!("{}-{}", normalize(symbol_a), normalize(symbol_b))
+}
+
+/// Filter to the allow-list, keep the deepest pool per (pair, dex) and
+/// return the top N by liquidity
+///
+/// A pool listed more than once (registries page by liquidity, so a pool
+/// can move between pages) is only taken once.
+pub fn select_pools(pools: Vec<DiscoveredPool>, config: &DiscoveryConfig) -> Vec<DiscoveredPool> {
+    let allowed: HashSet<String> = config.tokens.iter().map(|t| t.to_uppercase()).collect();
+
+    let mut pubkeys = HashSet::new();
+    let mut best: HashMap<(String, Dex), DiscoveredPool> = HashMap::new();
+    for pool in pools {
+        let in_allow_list = pool.pair.split('-').all(|token| allowed.contains(token));
+        if !in_allow_list || pool.liquidity_usd < config.min_liquidity_usd {
+            continue;
+        }
+        if !pubkeys.insert(pool.pubkey.clone()) {
+            continue;
+        }
+
+        let key = (pool.pair.clone(), pool.dex.clone());
+        match best.get(&key) {
+            Some(existing) if existing.liquidity_usd >= pool.liquidity_usd => {}
+            _ => {
+                best.insert(key, pool);
+            }
+        }
+    }
+
+    let mut selected: Vec<DiscoveredPool> = best.into_values().collect();
+    selected.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+    selected.truncate(config.top_n);
+    selected
+}
+
+/// Merge discovered pools into the static pool table
+///
+/// Static entries are kept as-is. Discovered pools are added in the given
+/// (liquidity) order while the total stays within `max_pools`, skipping
+/// pubkeys or (pair, dex) slots that are already configured.
+pub fn merge_pools(
+    static_pools: &HashMap<String, HashMap<Dex, String>>,
+    discovered: &[DiscoveredPool],
+    max_pools: usize,
+) -> HashMap<String, HashMap<Dex, String>> {
+    let mut merged = static_pools.clone();
+    let mut pubkeys: HashSet<String> = merged.values().flat_map(|d| d.values().cloned()).collect();
+    let mut total = pubkeys.len();
+
+    for pool in discovered {
+        if total >= max_pools {
+            break;
+        }
+        if pubkeys.contains(&pool.pubkey) {
+            continue;
+        }
+
+        let dexes = merged.entry(pool.pair.clone()).or_default();
+        if dexes.contains_key(&pool.dex) {
+            continue;
+        }
+        dexes.insert(pool.dex.clone(), pool.pubkey.clone());
+        pubkeys.insert(pool.pubkey.clone());
+        total += 1;
+
+        info!(
+            pair = pool.pair,
+            dex = %pool.dex,
+            pubkey = pool.pubkey,
+            fee_rate = pool.fee_rate,
+            liquidity_usd = pool.liquidity_usd,
+            "Discovered pool"
+        );
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+
+    const AMM_V4: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+    const CPMM: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+
+    fn raydium_body() -> Value {
+        json!({
+            "success": true,
+            "data": { "data": [
+                { "id": "RaySolUsdc", "programId": AMM_V4, "mintA": { "symbol": "WSOL" }, "mintB": { "symbol": "USDC" },
+                  "feeRate": 0.0025, "tvl": 9_000_000.0 },
+                { "id": "RaySolUsdcSmall", "programId": AMM_V4, "mintA": { "symbol": "WSOL" }, "mintB": { "symbol": "USDC" },
+                  "feeRate": 0.0001, "tvl": 200_000.0 },
+                { "id": "RayMeme", "programId": AMM_V4, "mintA": { "symbol": "WSOL" }, "mintB": { "symbol": "MEME" },
+                  "feeRate": 0.0025, "tvl": 5_000_000.0 },
+                { "id": "RayCpmmJupUsdc", "programId": CPMM, "mintA": { "symbol": "JUP" }, "mintB": { "symbol": "USDC" },
+                  "feeRate": 0.0025, "tvl": 20_000_000.0 }
+            ]}
+        })
+    }
+
+    fn orca_body() -> Value {
+        json!({
+            "whirlpools": [
+                { "address": "OrcaSolUsdc", "tokenA": { "symbol": "SOL" }, "tokenB": { "symbol": "USDC" },
+                  "lpFeeRate": 0.0004, "tvl": 30_000_000.0 },
+                { "address": "OrcaJupUsdc", "tokenA": { "symbol": "JUP" }, "tokenB": { "symbol": "USDC" },
+                  "lpFeeRate": 0.003, "tvl": 50_000.0 }
+            ]
+        })
+    }
+
+    async fn stub_registry() -> String {
+        let app = Router::new()
+            .route("/raydium", get(|| async { Json(raydium_body()) }))
+            .route("/orca", get(|| async { Json(orca_body()) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn config(base: &str) -> DiscoveryConfig {
+        DiscoveryConfig {
+            enabled: true,
+            tokens: vec!["SOL".to_string(), "USDC".to_string(), "JUP".to_string()],
+            top_n: 10,
+            min_liquidity_usd: 100_000.0,
+            raydium_url: format!("{}/raydium", base),
+            orca_url: format!("{}/orca", base),
+            ..DiscoveryConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discovered_pools_merge_with_static_table() {
+        let base = stub_registry().await;
+        let discovered = PoolDiscovery::new(config(&base)).discover().await;
+
+        // Deepest first; MEME is off the allow-list, JUP-USDC is too shallow
+        // on Orca and a CPMM pool on Raydium
+        let pubkeys: Vec<&str> = discovered.iter().map(|p| p.pubkey.as_str()).collect();
+        assert_eq!(pubkeys, vec!["OrcaSolUsdc", "RaySolUsdc"]);
+
+        let static_pools = HashMap::from([(
+            "SOL-USDC".to_string(),
+            HashMap::from([(Dex::Raydium, "StaticRaydium".to_string())]),
+        )]);
+        let merged = merge_pools(&static_pools, &discovered, 10);
+
+        assert_eq!(
+            merged["SOL-USDC"],
+            HashMap::from([
+                (Dex::Raydium, "StaticRaydium".to_string()),
+                (Dex::Orca, "OrcaSolUsdc".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_respects_max_pools() {
+        let discovered = select_pools(parse_orca(&orca_body()), &DiscoveryConfig {
+            tokens: vec!["SOL".to_string(), "USDC".to_string(), "JUP".to_string()],
+            min_liquidity_usd: 0.0,
+            ..DiscoveryConfig::default()
+        });
+        assert_eq!(discovered.len(), 2);
+
+        let merged = merge_pools(&HashMap::new(), &discovered, 1);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged["SOL-USDC"][&Dex::Orca], "OrcaSolUsdc");
+    }
+
+    #[test]
+    fn test_pools_listed_twice_are_selected_once() {
+        let config = DiscoveryConfig {
+            tokens: vec!["SOL".to_string(), "USDC".to_string()],
+            min_liquidity_usd: 0.0,
+            ..DiscoveryConfig::default()
+        };
+        let mut pools = parse_orca(&orca_body());
+        // Listed again in the opposite orientation, e.g. on a later page
+        pools.push(DiscoveredPool { pair: "USDC-SOL".to_string(), ..pools[0].clone() });
+
+        let selected = select_pools(pools, &config);
+        assert_eq!(selected.len(), 1);
+        assert_eq!((selected[0].pair.as_str(), selected[0].pubkey.as_str()), ("SOL-USDC", "OrcaSolUsdc"));
+    }
+
+    #[test]
+    fn test_raydium_keeps_amm_v4_pools_only() {
+        let pubkeys: Vec<String> = parse_raydium(&raydium_body()).into_iter().map(|p| p.pubkey).collect();
+        assert_eq!(pubkeys, vec!["RaySolUsdc", "RaySolUsdcSmall", "RayMeme"]);
+        assert_eq!(program_id(&Dex::Raydium), Some(AMM_V4));
+    }
+}
@@ -0,0 +1,624 @@
+//! Yellowstone gRPC (Geyser) feed
+//!
+//! An alternative to the WebSocket manager for providers that expose the
+//! Yellowstone Geyser plugin (Helius, Triton). One bidirectional `Subscribe`
+//! stream carries every account, program and slot filter, and updates come
+//! out as the same `WsEvent`s, so the main loop decodes them exactly like
+//! PubSub notifications.
+//!
+//! Filters are named: pool accounts share `accounts`, each program-mode DEX
+//! gets `program:<dex>`, and the filter names on an update tell us which
+//! event to produce. There are no per-subscription ids or unsubscribe calls;
+//! the whole subscription lives and dies with the stream.
+//!
+//! Servers ping idle streams and expect a ping request back, otherwise load
+//! balancers in front of them close the stream.
+
+pub mod proto;
+
+use crate::config::{
+    Commitment, DexSubscriptionConfig, RedactedUrl, RpcConfig, SubscriptionMode, TlsConfig, WebSocketConfig,
+};
+use crate::metrics::PipelineMetrics;
+use crate::models::Dex;
+use crate::websocket::{jittered, ConnectionState, ConnectionStatus, EventSender, WsEvent};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use proto::{
+    AccountsFilter, CommitmentLevel, MemcmpData, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterMemcmp, SubscribeRequestFilterSlots,
+    SubscribeRequestPing, SubscribeUpdate, SubscribeUpdateAccount, UpdateOneof,
+};
+use prost::Message as _;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Endpoint;
+use tracing::{debug, error, info, warn};
+
+/// Label the endpoint is reported under in `ConnectionStatus`
+const ENDPOINT_LABEL: &str = "geyser";
+
+/// Filter name shared by all pool account subscriptions
+const ACCOUNTS_FILTER: &str = "accounts";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A program-mode DEX, subscribed by owner
+struct ProgramFilter {
+    dex: Dex,
+    program_id: String,
+    filters: Vec<SubscribeRequestFilterAccountsFilter>,
+}
+
+/// Yellowstone gRPC client producing `WsEvent`s
+pub struct GeyserClient {
+    endpoint: RedactedUrl,
+    x_token: Option<String>,
+    tls: TlsConfig,
+    commitment: Commitment,
+    accounts: Vec<String>,
+    programs: Vec<ProgramFilter>,
+    slot_subscribe: bool,
+    reconnect_attempts: u32,
+    max_reconnect_delay: Duration,
+    reconnect_jitter: f64,
+    alert_after_failures: u32,
+    status: watch::Sender<ConnectionStatus>,
+    connected_before: bool,
+    shutdown: CancellationToken,
+    tx: Option<EventSender>,
+    metrics: Arc<PipelineMetrics>,
+}
+
+impl GeyserClient {
+    /// Create a client for `[rpc.geyser]` at `rpc.commitment`
+    pub fn from_rpc_config(rpc: &RpcConfig, subscriptions: Vec<String>) -> Self {
+        Self {
+            endpoint: RedactedUrl::new(rpc.geyser.endpoint.clone()),
+            x_token: rpc.geyser.x_token.clone(),
+            tls: rpc.tls.clone(),
+            commitment: rpc.commitment,
+            accounts: subscriptions,
+            programs: Vec::new(),
+            slot_subscribe: WebSocketConfig::default().slot_subscribe,
+            reconnect_attempts: 0,
+            max_reconnect_delay: Duration::from_secs(30),
+            reconnect_jitter: WebSocketConfig::default().reconnect_jitter,
+            alert_after_failures: WebSocketConfig::default().alert_after_failures,
+            status: watch::channel(ConnectionStatus::default()).0,
+            connected_before: false,
+            shutdown: CancellationToken::new(),
+            tx: None,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Apply the `[websocket]` options that make sense for a stream
+    pub fn with_config(mut self, config: &WebSocketConfig) -> Self {
+        self.slot_subscribe = config.slot_subscribe;
+        self.reconnect_jitter = config.reconnect_jitter;
+        self.alert_after_failures = config.alert_after_failures.max(1);
+        self
+    }
+
+    /// Add an owner filter for every DEX in program mode
+    pub fn with_programs(mut self, subscriptions: &HashMap<Dex, DexSubscriptionConfig>) -> Self {
+        self.programs = subscriptions
+            .iter()
+            .filter(|(_, s)| s.mode == SubscriptionMode::Program)
+            .filter_map(|(dex, s)| {
+                let size = s.data_size.map(AccountsFilter::Datasize);
+                let memcmp = s.memcmp.iter().map(|m| {
+                    AccountsFilter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                        offset: m.offset as u64,
+                        data: Some(MemcmpData::Base58(m.bytes.clone())),
+                    })
+                });
+                Some(ProgramFilter {
+                    dex: dex.clone(),
+                    program_id: s.program_id(dex)?,
+                    filters: size
+                        .into_iter()
+                        .chain(memcmp)
+                        .map(|filter| SubscribeRequestFilterAccountsFilter { filter: Some(filter) })
+                        .collect(),
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Stop (closing the stream) once `shutdown` fires
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Publish connection status on `status`
+    pub fn with_status(mut self, status: watch::Sender<ConnectionStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Count received updates and their encoded size into `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<PipelineMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Watch connection status changes
+    pub fn status(&self) -> watch::Receiver<ConnectionStatus> {
+        self.status.subscribe()
+    }
+
+    /// Set the channel to send events to
+    pub fn set_sender(&mut self, tx: EventSender) {
+        self.tx = Some(tx);
+    }
+
+    /// Keep a subscription stream open, reconnecting with backoff
+    ///
+    /// Returns after shutdown or once the event receiver is gone.
+    pub async fn run(&mut self) {
+        let shutdown = self.shutdown.clone();
+        while !shutdown.is_cancelled() {
+            if self.reconnect_attempts > 0 {
+                let delay = Duration::from_millis(100 * 2u64.pow(self.reconnect_attempts.min(8)));
+                let delay = jittered(delay.min(self.max_reconnect_delay), self.reconnect_jitter);
+                warn!(attempt = self.reconnect_attempts, delay_ms = delay.as_millis(), "Reconnecting to Geyser...");
+                if shutdown.run_until_cancelled(tokio::time::sleep(delay)).await.is_none() {
+                    break;
+                }
+            }
+
+            self.publish(|status| {
+                if !matches!(status.state, ConnectionState::Failed { .. }) {
+                    status.state = ConnectionState::Connecting;
+                }
+                status.endpoint = ENDPOINT_LABEL.to_string();
+            });
+
+            match self.connect_and_listen().await {
+                Ok(false) => return,
+                Ok(true) if shutdown.is_cancelled() => break,
+                Ok(true) => {
+                    self.reconnect_attempts = 0;
+                    self.set_disconnected(ConnectionState::Connecting);
+                    info!("Geyser stream closed by the server");
+                }
+                Err(e) => {
+                    self.reconnect_attempts += 1;
+                    let failures = self.reconnect_attempts;
+                    error!(failures = failures, error = %self.endpoint.scrub(&format!("{:#}", e)), "Geyser stream failed");
+                    if failures >= self.alert_after_failures {
+                        if failures == self.alert_after_failures {
+                            error!(failures = failures, "Geyser feed down, price updates stopped");
+                        }
+                        self.set_disconnected(ConnectionState::Failed { consecutive_failures: failures });
+                    } else {
+                        self.set_disconnected(ConnectionState::Backoff { attempt: failures });
+                    }
+                }
+            }
+        }
+        info!("Geyser client shut down");
+    }
+
+    /// Open the stream and forward updates until it ends
+    ///
+    /// Returns false once the event receiver is gone.
+    async fn connect_and_listen(&mut self) -> Result<bool> {
+        info!(endpoint = %self.endpoint, "Connecting to Geyser");
+        let endpoint = Endpoint::from_shared(self.endpoint.expose().to_string())
+            .context("Invalid Geyser endpoint")?
+            .connect_timeout(CONNECT_TIMEOUT);
+        let tls = if self.endpoint.expose().starts_with("https://") {
+            Some(crate::net::tls::rustls_config(&self.tls)?)
+        } else {
+            None
+        };
+        let connect = async {
+            let Some(tls) = tls else {
+                return endpoint.connect().await;
+            };
+            // gRPC over TLS negotiates HTTP/2 with ALPN
+            let connector = hyper_rustls::HttpsConnectorBuilder::new()
+                .with_tls_config(tls)
+                .https_only()
+                .enable_http2()
+                .build();
+            endpoint.connect_with_connector(connector).await
+        };
+        let Some(channel) = self.shutdown.run_until_cancelled(connect).await else {
+            return Ok(true);
+        };
+        let mut grpc = tonic::client::Grpc::new(channel.context("Failed to connect")?);
+        grpc.ready().await.context("Geyser service not ready")?;
+
+        // The request stream stays open for ping replies
+        let (requests, rx) = mpsc::channel(8);
+        requests.send(self.subscribe_request()).await.ok();
+        let stream = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|r| (r, rx)) });
+        let mut request = tonic::Request::new(stream);
+        if let Some(token) = &self.x_token {
+            request
+                .metadata_mut()
+                .insert("x-token", token.parse().context("Invalid Geyser x_token")?);
+        }
+
+        let codec = ProstCodec::<SubscribeRequest, SubscribeUpdate>::default();
+        let subscribe = grpc.streaming(request, PathAndQuery::from_static(proto::SUBSCRIBE_PATH), codec);
+        let Some(response) = self.shutdown.run_until_cancelled(subscribe).await else {
+            return Ok(true);
+        };
+        let mut updates = response.context("Subscribe rejected")?.into_inner();
+
+        info!(
+            accounts = self.accounts.len(),
+            programs = self.programs.len(),
+            commitment = self.commitment.as_str(),
+            "Geyser stream open"
+        );
+        self.reconnect_attempts = 0;
+        let active = self.accounts.len() + self.programs.len();
+        self.publish(|status| {
+            status.state = ConnectionState::Connected;
+            status.endpoint = ENDPOINT_LABEL.to_string();
+            status.connected_since = Some(Utc::now());
+            status.active_subscriptions = active;
+            status.pending_subscriptions = 0;
+        });
+        if std::mem::replace(&mut self.connected_before, true) && !self.emit(WsEvent::Reconnected).await {
+            return Ok(false);
+        }
+
+        let shutdown = self.shutdown.clone();
+        let result = loop {
+            let update = tokio::select! {
+                update = updates.message() => update,
+                _ = shutdown.cancelled() => break Ok(true),
+            };
+            let update = match update {
+                Ok(Some(update)) => update,
+                Ok(None) => break Ok(true),
+                Err(status) => break Err(anyhow::anyhow!(status).context("Geyser stream error")),
+            };
+            self.metrics.record_message(update.encoded_len());
+            match update.update_oneof {
+                Some(UpdateOneof::Ping(_)) => {
+                    let ping = SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    };
+                    requests.send(ping).await.ok();
+                }
+                Some(UpdateOneof::Pong(_)) | None => {}
+                Some(UpdateOneof::Slot(slot)) => {
+                    let event = WsEvent::SlotAdvanced {
+                        slot: slot.slot,
+                        timestamp: Utc::now().timestamp_millis() as u64,
+                    };
+                    if !self.emit(event).await {
+                        return Ok(false);
+                    }
+                }
+                Some(UpdateOneof::Account(account)) => {
+                    if let Some(event) = self.account_update(&update.filters, account) {
+                        if !self.emit(event).await {
+                            return Ok(false);
+                        }
+                    }
+                }
+            }
+        };
+
+        if !self.shutdown.is_cancelled() {
+            self.emit(WsEvent::Disconnected).await;
+        }
+        result
+    }
+
+    /// Every filter for a fresh stream
+    fn subscribe_request(&self) -> SubscribeRequest {
+        let mut accounts = HashMap::new();
+        if !self.accounts.is_empty() {
+            accounts.insert(
+                ACCOUNTS_FILTER.to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: self.accounts.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+        for program in &self.programs {
+            accounts.insert(
+                format!("program:{}", program.dex),
+                SubscribeRequestFilterAccounts {
+                    owner: vec![program.program_id.clone()],
+                    filters: program.filters.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut slots = HashMap::new();
+        if self.slot_subscribe {
+            slots.insert(
+                "slots".to_string(),
+                SubscribeRequestFilterSlots { filter_by_commitment: Some(true) },
+            );
+        }
+
+        SubscribeRequest {
+            accounts,
+            slots,
+            commitment: Some(CommitmentLevel::from(self.commitment) as i32),
+            ping: None,
+        }
+    }
+
+    /// Pool accounts take precedence over a program filter matching the
+    /// same account
+    fn account_update(&self, filters: &[String], update: SubscribeUpdateAccount) -> Option<WsEvent> {
+        let Some(account) = update.account else {
+            debug!(slot = update.slot, "Account update without account info");
+            return None;
+        };
+        let pubkey = solana_sdk::bs58::encode(&account.pubkey).into_string();
+
+        if filters.iter().any(|f| f == ACCOUNTS_FILTER) {
+            return Some(WsEvent::AccountUpdate {
+                pubkey,
+                slot: update.slot,
+                data: account.data,
+                commitment: self.commitment,
+            });
+        }
+        let Some(dex) = filters.iter().find_map(|f| f.strip_prefix("program:")) else {
+            debug!(pubkey = pubkey, filters = ?filters, "Account update for an unknown filter");
+            return None;
+        };
+        Some(WsEvent::ProgramAccountUpdate {
+            dex: Dex::from(dex),
+            pubkey,
+            slot: update.slot,
+            data: account.data,
+            commitment: self.commitment,
+        })
+    }
+
+    /// Update the published status; watchers only wake for real changes
+    fn publish(&self, update: impl FnOnce(&mut ConnectionStatus)) {
+        self.status.send_if_modified(|status| {
+            let before = status.clone();
+            update(status);
+            *status != before
+        });
+    }
+
+    fn set_disconnected(&self, state: ConnectionState) {
+        self.publish(|status| {
+            status.state = state;
+            status.connected_since = None;
+            status.active_subscriptions = 0;
+            status.pending_subscriptions = 0;
+        });
+    }
+
+    /// Forward an event; false once the receiver is gone
+    async fn emit(&self, event: WsEvent) -> bool {
+        match &self.tx {
+            Some(tx) => {
+                let sent = tx.send(event).await;
+                if !sent {
+                    error!("Event receiver closed, stopping Geyser client");
+                }
+                sent
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BackpressurePolicy, GeyserConfig};
+    use crate::websocket::event_channel;
+    use proto::{SubscribeUpdateAccountInfo, SubscribeUpdatePing};
+    use std::pin::Pin;
+    use std::task::{Context as TaskContext, Poll};
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+    use tonic::codegen::{http, Body, BoxFuture, Service, StdError};
+    use tonic::{Status, Streaming};
+
+    /// Stand-in Yellowstone server: records requests and the token, then
+    /// streams canned updates and keeps the stream open
+    #[derive(Clone)]
+    struct MockGeyser {
+        updates: Vec<SubscribeUpdate>,
+        requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+        token: Arc<Mutex<Option<String>>>,
+    }
+
+    type UpdateStream = Pin<Box<dyn futures::Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+    impl tonic::server::StreamingService<SubscribeRequest> for MockGeyser {
+        type Response = SubscribeUpdate;
+        type ResponseStream = UpdateStream;
+        type Future = BoxFuture<tonic::Response<UpdateStream>, Status>;
+
+        fn call(&mut self, request: tonic::Request<Streaming<SubscribeRequest>>) -> Self::Future {
+            let mock = self.clone();
+            Box::pin(async move {
+                let token = request.metadata().get("x-token").and_then(|v| v.to_str().ok()).map(str::to_string);
+                *mock.token.lock().await = token;
+                let mut incoming = request.into_inner();
+                let requests = mock.requests.clone();
+                tokio::spawn(async move {
+                    while let Ok(Some(request)) = incoming.message().await {
+                        requests.lock().await.push(request);
+                    }
+                });
+                let updates = futures::stream::iter(mock.updates.into_iter().map(Ok));
+                let stream: UpdateStream = Box::pin(futures::StreamExt::chain(updates, futures::stream::pending()));
+                Ok(tonic::Response::new(stream))
+            })
+        }
+    }
+
+    impl<B> Service<http::Request<B>> for MockGeyser
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<B>) -> Self::Future {
+            let mock = self.clone();
+            Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::<SubscribeUpdate, SubscribeRequest>::default());
+                Ok(grpc.streaming(mock, request).await)
+            })
+        }
+    }
+
+    impl tonic::server::NamedService for MockGeyser {
+        const NAME: &'static str = "geyser.Geyser";
+    }
+
+    fn account_update(pubkey: [u8; 32], slot: u64, data: &[u8], filter: &str) -> SubscribeUpdate {
+        SubscribeUpdate {
+            filters: vec![filter.to_string()],
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: pubkey.to_vec(),
+                    lamports: 1_000_000,
+                    owner: vec![0; 32],
+                    data: data.to_vec(),
+                    ..Default::default()
+                }),
+                slot,
+                is_startup: false,
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_account_updates_from_mock_server() {
+        let pool = [7u8; 32];
+        let pool_key = solana_sdk::bs58::encode(pool).into_string();
+        let mock = MockGeyser {
+            updates: vec![
+                SubscribeUpdate {
+                    filters: Vec::new(),
+                    update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+                },
+                account_update(pool, 250_000_000, &[1, 2, 3], ACCOUNTS_FILTER),
+            ],
+            requests: Arc::default(),
+            token: Arc::default(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming =
+            futures::stream::unfold(listener, |l| async move { Some((l.accept().await.map(|(s, _)| s), l)) });
+        let server = tonic::transport::Server::builder().add_service(mock.clone());
+        tokio::spawn(server.serve_with_incoming(incoming));
+
+        let rpc = RpcConfig {
+            commitment: Commitment::Confirmed,
+            geyser: GeyserConfig {
+                endpoint: format!("http://{}", addr),
+                x_token: Some("test-token".to_string()),
+            },
+            ..RpcConfig::default()
+        };
+        let shutdown = CancellationToken::new();
+        let (tx, mut rx) = event_channel(16, BackpressurePolicy::Block);
+        let metrics = Arc::new(PipelineMetrics::default());
+        let mut client = GeyserClient::from_rpc_config(&rpc, vec![pool_key.clone()])
+            .with_metrics(metrics.clone())
+            .with_shutdown(shutdown.clone());
+        let mut status = client.status();
+        client.set_sender(tx);
+        let handle = tokio::spawn(async move { client.run().await });
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert_eq!(
+            event,
+            Some(WsEvent::AccountUpdate {
+                pubkey: pool_key.clone(),
+                slot: 250_000_000,
+                data: vec![1, 2, 3],
+                commitment: Commitment::Confirmed,
+            })
+        );
+        let connected = status.wait_for(|s| s.is_connected()).await.unwrap().clone();
+        assert_eq!((connected.endpoint.as_str(), connected.active_subscriptions), ("geyser", 1));
+        assert_eq!(mock.token.lock().await.as_deref(), Some("test-token"));
+        assert_eq!(metrics.snapshot().messages_received, 2);
+
+        // The subscription, then the reply to the server's ping
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while mock.requests.lock().await.len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let requests = mock.requests.lock().await.clone();
+        let subscribe = &requests[0];
+        assert_eq!(subscribe.accounts[ACCOUNTS_FILTER].account, vec![pool_key]);
+        assert_eq!(subscribe.commitment, Some(CommitmentLevel::Confirmed as i32));
+        assert!(subscribe.slots.contains_key("slots"));
+        assert_eq!(requests[1].ping, Some(SubscribeRequestPing { id: 1 }));
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_program_filters_map_to_program_updates() {
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(
+            Dex::Orca,
+            DexSubscriptionConfig {
+                mode: SubscriptionMode::Program,
+                data_size: Some(653),
+                ..Default::default()
+            },
+        );
+        let client = GeyserClient::from_rpc_config(&RpcConfig::default(), vec!["Pool".to_string()])
+            .with_programs(&subscriptions);
+
+        let request = client.subscribe_request();
+        let orca = &request.accounts["program:orca"];
+        assert_eq!(orca.owner, vec![crate::decoder::program_id(&Dex::Orca).unwrap().to_string()]);
+        assert_eq!(orca.filters[0].filter, Some(AccountsFilter::Datasize(653)));
+
+        let UpdateOneof::Account(update) = account_update([9; 32], 5, &[4], "program:orca").update_oneof.unwrap() else {
+            unreachable!()
+        };
+        let Some(WsEvent::ProgramAccountUpdate { dex, slot, .. }) =
+            client.account_update(&["program:orca".to_string()], update.clone())
+        else {
+            panic!("expected a program update");
+        };
+        assert_eq!((dex.as_str(), slot), ("orca", 5));
+        assert!(client.account_update(&["elsewhere".to_string()], update).is_none());
+    }
+}
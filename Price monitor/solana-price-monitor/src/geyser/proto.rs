@@ -0,0 +1,163 @@
+//! Yellowstone `geyser.proto` messages used by the client
+//!
+//! Written out by hand rather than generated, so the build doesn't need
+//! `protoc`. Only the fields we send or read are declared; prost skips the
+//! rest of each message on decode. Tags match the upstream proto.
+
+use std::collections::HashMap;
+
+/// Full path of the bidirectional `Subscribe` RPC
+pub const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(map = "string, message", tag = "1")]
+    pub accounts: HashMap<String, SubscribeRequestFilterAccounts>,
+    #[prost(map = "string, message", tag = "2")]
+    pub slots: HashMap<String, SubscribeRequestFilterSlots>,
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+    pub commitment: Option<i32>,
+    #[prost(message, optional, tag = "9")]
+    pub ping: Option<SubscribeRequestPing>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestFilterAccounts {
+    #[prost(string, repeated, tag = "2")]
+    pub account: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub owner: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub filters: Vec<SubscribeRequestFilterAccountsFilter>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestFilterAccountsFilter {
+    #[prost(oneof = "AccountsFilter", tags = "1, 2")]
+    pub filter: Option<AccountsFilter>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum AccountsFilter {
+    #[prost(message, tag = "1")]
+    Memcmp(SubscribeRequestFilterAccountsFilterMemcmp),
+    #[prost(uint64, tag = "2")]
+    Datasize(u64),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestFilterAccountsFilterMemcmp {
+    #[prost(uint64, tag = "1")]
+    pub offset: u64,
+    #[prost(oneof = "MemcmpData", tags = "2, 3")]
+    pub data: Option<MemcmpData>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum MemcmpData {
+    #[prost(bytes, tag = "2")]
+    Bytes(Vec<u8>),
+    #[prost(string, tag = "3")]
+    Base58(String),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestFilterSlots {
+    /// Only report slots once they reach the request's commitment
+    #[prost(bool, optional, tag = "1")]
+    pub filter_by_commitment: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequestPing {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdate {
+    /// Names of the request filters this update matched
+    #[prost(string, repeated, tag = "1")]
+    pub filters: Vec<String>,
+    #[prost(oneof = "UpdateOneof", tags = "2, 3, 6, 9")]
+    pub update_oneof: Option<UpdateOneof>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum UpdateOneof {
+    #[prost(message, tag = "2")]
+    Account(SubscribeUpdateAccount),
+    #[prost(message, tag = "3")]
+    Slot(SubscribeUpdateSlot),
+    /// Keepalive; load balancers drop streams that don't answer
+    #[prost(message, tag = "6")]
+    Ping(SubscribeUpdatePing),
+    #[prost(message, tag = "9")]
+    Pong(SubscribeUpdatePong),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdateAccount {
+    #[prost(message, optional, tag = "1")]
+    pub account: Option<SubscribeUpdateAccountInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+    #[prost(bool, tag = "3")]
+    pub is_startup: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdateAccountInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub pubkey: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub lamports: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub owner: Vec<u8>,
+    #[prost(bool, tag = "4")]
+    pub executable: bool,
+    #[prost(uint64, tag = "5")]
+    pub rent_epoch: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: Vec<u8>,
+    #[prost(uint64, tag = "7")]
+    pub write_version: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdateSlot {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+    #[prost(uint64, optional, tag = "2")]
+    pub parent: Option<u64>,
+    #[prost(enumeration = "CommitmentLevel", tag = "3")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdatePing {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeUpdatePong {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum CommitmentLevel {
+    Processed = 0,
+    Confirmed = 1,
+    Finalized = 2,
+}
+
+impl From<crate::config::Commitment> for CommitmentLevel {
+    fn from(commitment: crate::config::Commitment) -> Self {
+        use crate::config::Commitment;
+        match commitment {
+            Commitment::Processed => CommitmentLevel::Processed,
+            Commitment::Confirmed => CommitmentLevel::Confirmed,
+            Commitment::Finalized => CommitmentLevel::Finalized,
+        }
+    }
+}
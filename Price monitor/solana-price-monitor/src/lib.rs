@@ -3,17 +3,29 @@
 //! This crate provides real-time price monitoring and arbitrage detection
 //! for Solana DEXs including Raydium, Orca, and Meteora.
 
+pub mod api;
 pub mod cache;
 pub mod calculator;
+pub mod cli;
 pub mod config;
+pub mod costs;
 pub mod decoder;
 pub mod detector;
+pub mod discovery;
+#[cfg(feature = "geyser")]
+pub mod geyser;
+pub mod metrics;
 pub mod models;
+pub mod net;
+pub mod notifications;
+pub mod scheduler;
+pub mod simulator;
+pub mod sink;
 pub mod utils;
 pub mod websocket;
 
 // Re-export commonly used types
-pub use cache::PriceCache;
+pub use cache::{CacheEvent, CurrentSlot, PriceCache};
 pub use config::Settings;
 pub use detector::{OpportunityDetector, StatisticalArbitrageDetector, TriangularArbitrageDetector};
 pub use models::{Opportunity, OpportunityType, PriceData};
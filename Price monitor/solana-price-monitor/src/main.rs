@@ -3,57 +3,126 @@
 //! Real-time price monitoring and arbitrage detection for Solana DEXs.
 
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, error, warn, debug};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-mod config;
-mod cache;
-mod calculator;
-mod decoder;
-mod detector;
-mod models;
-mod utils;
-mod websocket;
-mod api; // New module
-
-use config::Settings;
-use cache::PriceCache;
-use websocket::WebSocketManager;
-use detector::{OpportunityDetector, StatisticalArbitrageDetector, TriangularArbitrageDetector, 
-               StatArbConfig, TriangularArbConfig, generate_common_paths};
-use decoder::{PoolDecoder, RaydiumDecoder, OrcaDecoder, MeteoraDecoder, PoolState};
-use calculator::{calculate_amm_price, calculate_clmm_price};
-use models::PriceData;
-use api::ApiMessage; // Import ApiMessage
+use clap::Parser;
+use solana_sdk::pubkey::Pubkey;
+use solana_price_monitor::api;
+use solana_price_monitor::net::{self, Egress};
+use solana_price_monitor::notifications::Notifier;
+use solana_price_monitor::sink::FileSink;
+use solana_price_monitor::cli::{self, Cli, Command};
+use solana_price_monitor::config::{changed_sections, Commitment, ConfigWatcher, FeeSchedule, FiltersConfig, InconsistentPriceAction, PoolDecimals, PoolSelection, RedactedUrl, Settings, SubscriptionMode};
+use solana_price_monitor::costs::CostFeed;
+use solana_price_monitor::metrics::PipelineMetrics;
+use solana_price_monitor::discovery::{self, PoolDiscovery};
+use solana_price_monitor::scheduler::{PauseController, PauseStatus, ScanScheduler};
+use solana_price_monitor::simulator::SimulatedFeed;
+use solana_price_monitor::cache::PriceCache;
+use solana_price_monitor::calculator::{TokenRegistry, UsdPricer};
+use solana_price_monitor::websocket::{event_channel, ConnectionStatus, EventSender, SubscriptionBook, SwapActivity, TapSet, WebSocketManager, WsEvent};
+use solana_price_monitor::detector::{OpportunityDetector, StatisticalArbitrageDetector, TriangularArbitrageDetector,
+               PairUniverse, OpportunityAggregator, ConfidenceCalibrator,
+               TriangularPath, TriangularPathSet, SpreadAlertDetector, SpreadTracker, VolatilityTracker, RiskModel, PairSummaries};
+use solana_price_monitor::decoder::{PoolDecoder, RaydiumDecoder, OrcaDecoder, MeteoraDecoder, PoolState, SpecificPoolData};
+use solana_price_monitor::models::{Dex, Opportunity, PriceData};
+use solana_price_monitor::api::ApiMessage;
+use solana_price_monitor::utils::HealthTracker;
 
 /// Pool metadata for decoding context
 #[derive(Clone)]
 struct PoolInfo {
     pair: String,
-    dex: String,
+    dex: Dex,
     decoder_type: DecoderType,
+    /// Decimals from `[pool_overrides]` / `[tokens]`, the reference decoded
+    /// prices are checked against; `None` when they aren't configured
+    decimals: Option<PoolDecimals>,
 }
 
-#[derive(Clone, Copy)]
+impl PoolInfo {
+    fn new(settings: &Settings, pair: String, dex: Dex, pubkey: &str) -> Self {
+        Self {
+            decoder_type: DecoderType::for_pool(settings, &pair, &dex, pubkey),
+            decimals: settings.pool_decimals(&pair, pubkey),
+            pair,
+            dex,
+        }
+    }
+}
+
+/// Decoder for one pool, carrying that pool's token decimals
+#[derive(Clone)]
 enum DecoderType {
     Raydium,
-    Orca,
-    Meteora,
+    Orca(OrcaDecoder),
+    Meteora(MeteoraDecoder),
 }
 
+impl DecoderType {
+    /// Decoder for a pool, with decimals from `[pool_overrides]` / `[tokens]`
+    fn for_pool(settings: &Settings, pair: &str, dex: &Dex, pubkey: &str) -> Self {
+        match dex {
+            Dex::Raydium => DecoderType::Raydium,
+            Dex::Orca => DecoderType::Orca(
+                settings
+                    .pool_decimals(pair, pubkey)
+                    .map(|d| OrcaDecoder::new(d.token_a_decimals, d.token_b_decimals))
+                    .unwrap_or_default(),
+            ),
+            Dex::Meteora => DecoderType::Meteora(
+                settings
+                    .pool_decimals(pair, pubkey)
+                    .map(|d| MeteoraDecoder::new(d.token_a_decimals, d.token_b_decimals))
+                    .unwrap_or_default(),
+            ),
+            _ => {
+                warn!(dex = %dex, "Unknown DEX type, defaulting to Raydium");
+                DecoderType::Raydium
+            }
+        }
+    }
+
+    fn data_slice(&self) -> Option<(usize, usize)> {
+        match self {
+            DecoderType::Raydium => RaydiumDecoder.data_slice(),
+            DecoderType::Orca(decoder) => decoder.data_slice(),
+            DecoderType::Meteora(decoder) => decoder.data_slice(),
+        }
+    }
+
+    fn decode(&self, raydium_decoder: &RaydiumDecoder, data: &[u8]) -> Result<PoolState> {
+        match self {
+            DecoderType::Raydium => raydium_decoder.decode(data),
+            DecoderType::Orca(decoder) => decoder.decode(data),
+            DecoderType::Meteora(decoder) => decoder.decode(data),
+        }
+    }
+}
+
+/// How long shutdown waits for tasks to unsubscribe, drain and flush
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    init_tracing();
+    let cli = Cli::parse();
+
+    // Initialize logging (to stderr for one-shot commands, keeping stdout parseable)
+    let log_filter = init_tracing(cli.overrides.log_level.as_deref(), cli.command() != Command::Run);
 
     info!("Starting Solana Price Monitor v{}", env!("CARGO_PKG_VERSION"));
 
-    // Load configuration
-    let settings = match Settings::load() {
+    // Load configuration (CLI > env > file > defaults)
+    let settings = match Settings::load_with_overrides(&cli.overrides) {
         Ok(s) => {
             info!("Configuration loaded successfully");
             s
@@ -65,138 +134,802 @@ async fn main() -> Result<()> {
     };
 
     info!(
+        cluster = %settings.cluster,
         max_pools = settings.monitoring.max_pools,
         min_profit = settings.arbitrage.min_profit_percent,
+        rpc_endpoints = ?settings.rpc.endpoints.iter().map(|e| e.name()).collect::<Vec<_>>(),
         "Monitor configured"
     );
+    debug!(settings = ?settings.redacted(), "Effective configuration");
+    net::tls::warn_if_insecure(&settings.rpc.tls);
+
+    let mut settings = settings;
+    if let Command::ValidateConfig { format } = cli.command() {
+        // Registries aren't queried here, so with discovery on --pairs is
+        // only checked at startup
+        if !settings.discovery.enabled {
+            cli.overrides.restrict_pairs(&mut settings.pools)?;
+        }
+        print!("{}", cli::render_config(&settings, format)?);
+        eprintln!("Configuration OK");
+        return Ok(());
+    }
+
+    // Cancelled on Ctrl-C; every long-running task stops on it and is joined
+    let shutdown = CancellationToken::new();
+    let mut tasks: Vec<JoinHandle<()>> = Vec::new();
+
+    // Merge registry pools into the static pool table
+    let mut discovered = Vec::new();
+    if settings.discovery.enabled {
+        let pool_discovery = PoolDiscovery::new(settings.discovery.clone())
+            .with_http_client(Egress::from_rpc_config(&settings.rpc).http_client()?);
+        discovered = pool_discovery.discover().await;
+        settings.pools = discovery::merge_pools(
+            &settings.pools,
+            &discovered,
+            settings.monitoring.max_pools,
+        );
+        info!(discovered = discovered.len(), "Pool discovery complete");
+
+        if settings.discovery.refresh_interval_seconds > 0 {
+            tasks.push(pool_discovery.spawn_refresh_task(discovered.clone(), shutdown.clone()));
+        }
+    }
+    cli.overrides.restrict_pairs(&mut settings.pools)?;
+
+    // Order pools by priority and trim to the max_pools subscription budget
+    let selection = settings.select_pools();
+
+    if cli.command() == Command::ListPools {
+        for pool in &selection.active {
+            println!("{:<16} {:<10} {}", pool.pair, pool.dex, pool.pubkey);
+        }
+        for skipped in &selection.skipped {
+            let pool = &skipped.pool;
+            println!("{:<16} {:<10} {} (skipped: {:?})", pool.pair, pool.dex, pool.pubkey, skipped.reason);
+        }
+        return Ok(());
+    }
+
+    if cli.command() == Command::ExportFees {
+        let (pool_lookup, _) = build_subscriptions(&settings, &selection);
+        let schedule =
+            export_fee_schedule(&settings.rpc.primary().http_url, &Egress::from_rpc_config(&settings.rpc), &pool_lookup).await?;
+        println!("{}", serde_json::to_string_pretty(&schedule)?);
+        eprintln!("Exported fee rates for {} pools", schedule.pools.len());
+        return Ok(());
+    }
 
     // Initialize Broadcast Channel for Frontend API
-    let (api_tx, _) = tokio::sync::broadcast::channel::<ApiMessage>(1000);
-    let api_tx_clone = api_tx.clone();
+    let api_tx = api::Topics::new(settings.api.broadcast_buffer);
 
-    // Spawn API Server
-    tokio::spawn(async move {
-        api::start_server(3001, api_tx_clone).await;
+    // Initialize Confidence Calibrator (restoring any persisted table)
+    let calibration_path = PathBuf::from(&settings.calibration.persist_path);
+    let mut calibrator = ConfidenceCalibrator::new(
+        Duration::from_millis(settings.calibration.outcome_horizon_ms),
+        settings.calibration.min_samples,
+        settings.calibration.apply_correction,
+    );
+    if calibration_path.exists() {
+        match ConfidenceCalibrator::load_table(&calibration_path) {
+            Ok(table) => {
+                info!(samples = table.total_samples(), "Loaded confidence calibration table");
+                calibrator = calibrator.with_table(table);
+            }
+            Err(e) => warn!(error = ?e, "Ignoring unreadable calibration table"),
+        }
+    }
+    let calibrator = Arc::new(tokio::sync::RwLock::new(calibrator));
+
+    // Initialize Volatility Tracker (regime metrics are always collected)
+    let volatility = Arc::new(VolatilityTracker::new(settings.regime.clone()));
+
+    // Build triangular paths for scanning (pruned by liquidity at runtime)
+    let mut path_set = TriangularPathSet::new(
+        settings.triangular_paths(),
+        settings.triangular.min_path_liquidity,
+    );
+    path_set.apply_filters(&settings.filters);
+    let triangular_paths = Arc::new(tokio::sync::RwLock::new(path_set));
+
+    // Pair/token filters shared with periodic tasks (updated on config reload)
+    let filters = Arc::new(tokio::sync::RwLock::new(settings.filters.clone()));
+
+    // Active/skipped pools, shared with the API (updated on config reload)
+    log_pool_selection(&selection);
+    let pool_selection = Arc::new(tokio::sync::RwLock::new(selection));
+
+    // Feed and processing latencies, shared by the feed, main loop, scan worker and health check
+    let pipeline_metrics = Arc::new(PipelineMetrics::default());
+
+    // Pause controller: scheduled windows plus manual pause via the admin API
+    let pause = Arc::new(PauseController::new(settings.schedule.pause_windows.clone()));
+    tasks.push(pause.spawn_clock(Duration::from_secs(1), shutdown.clone()));
+    let mut pause_rx = pause.subscribe();
+    let pause_api_tx = api_tx.clone();
+    // Published by the WebSocket manager (the sender outlives reconnects);
+    // reported in health checks and status broadcasts
+    let (ws_status, _) = tokio::sync::watch::channel(ConnectionStatus {
+        endpoint: settings.rpc.primary().name().to_string(),
+        ..ConnectionStatus::default()
     });
+    // Per-subscription freshness for GET /subscriptions, kept across reconnects too
+    let subscription_book = SubscriptionBook::default();
+    // Raw frame taps for POST /admin/tap, kept across reconnects
+    let taps = TapSet::default();
+    // Swaps per pool from logsSubscribe, for detectors and GET /activity
+    let swap_activity = SwapActivity::default();
+    let pause_ws_status = ws_status.subscribe();
+    let pause_metrics = pipeline_metrics.clone();
+    let pause_shutdown = shutdown.clone();
+    tasks.push(tokio::spawn(async move {
+        let forward = async move {
+            while pause_rx.changed().await.is_ok() {
+                let status = pause_rx.borrow_and_update().clone();
+                if status.paused {
+                    warn!(reason = ?status.reason, "Detection paused");
+                } else {
+                    info!("Detection resumed");
+                }
+                let _ = pause_api_tx.send(system_status(status, &pause_ws_status.borrow(), &pause_metrics));
+            }
+        };
+        pause_shutdown.run_until_cancelled(forward).await;
+    }));
+
+    // Announce connects, disconnects, failovers and failure alerts
+    let mut status_ws_status = ws_status.subscribe();
+    let status_pause = pause.clone();
+    let status_api_tx = api_tx.clone();
+    let status_metrics = pipeline_metrics.clone();
+    let status_shutdown = shutdown.clone();
+    tasks.push(tokio::spawn(async move {
+        let forward = async move {
+            let mut previous = status_ws_status.borrow_and_update().clone();
+            while status_ws_status.changed().await.is_ok() {
+                let current = status_ws_status.borrow_and_update().clone();
+                if current.should_announce(&previous) {
+                    if current.is_connected() && previous.consecutive_failures() > 0 {
+                        info!(endpoint = current.endpoint, "WebSocket feed recovered");
+                    }
+                    let _ = status_api_tx.send(system_status(status_pause.status(), &current, &status_metrics));
+                }
+                previous = current;
+            }
+        };
+        status_shutdown.run_until_cancelled(forward).await;
+    }));
 
     // Initialize Price Cache
-    let cache = Arc::new(PriceCache::new(
-        settings.monitoring.cache_ttl_seconds,
-        settings.monitoring.stale_threshold_ms,
+    let cache = Arc::new(
+        PriceCache::new(
+            settings.monitoring.cache_ttl_seconds,
+            settings.monitoring.stale_threshold_ms,
+        )
+        .with_stale_thresholds(settings.monitoring.stale_thresholds()),
+    );
+
+    // Uptime and the latest cache update per DEX, for the health check and
+    // GET /health, stale by the cache's thresholds
+    let health = Arc::new(HealthTracker::new().with_stale_thresholds(cache.stale_thresholds()));
+
+    // Emitted opportunities for GET /opportunities, fed by the aggregator
+    let opportunity_history = api::OpportunityHistory::new(settings.api.opportunity_history);
+    // Per-pair summaries: dirtied by price batches and emitted groups, rebuilt when read
+    let pair_summaries = Arc::new(PairSummaries::new(
+        cache.clone(),
+        Duration::from_secs(settings.api.pair_summary_window_seconds),
     ));
 
+    // Per-client request rates and the streaming connection cap
+    let api_limits = Arc::new(api::ApiLimits::new(&settings.api.rate_limit));
+
+    // Live feed handle and pool budget for runtime pool changes via the admin API
+    let pool_control = api::PoolControl::new(settings.monitoring.max_pools);
+    pool_control.set_program_dexes(program_dexes(&settings));
+
+    // Latest periodic metrics, sampled once the feed queue exists
+    let system_metrics = api::LatestMetrics::default();
+    let client_lag = Arc::new(api::ClientLag::default());
+    let ws_clients = Arc::new(api::WsClients::default());
+
+    // Record opportunities (and sampled prices) to files when [sink] is enabled;
+    // the aggregator hands it every group it emits
+    let sink = match FileSink::from_settings(&settings)? {
+        Some(sink) => {
+            let (handle, task) = sink.spawn(&api_tx, shutdown.clone());
+            tasks.push(task);
+            Some(handle)
+        }
+        None => None,
+    };
+
+    // Spawn Opportunity Aggregator Task (detectors -> aggregator -> broadcast, sink)
+    let (opp_tx, mut opp_rx) = mpsc::channel::<Opportunity>(1000);
+    let aggregator_api_tx = api_tx.clone();
+    let aggregation_window = Duration::from_millis(settings.arbitrage.aggregation_window_ms);
+    let rank_by = settings.arbitrage.rank_by;
+    let aggregator_calibrator = calibrator.clone();
+    let calibration_enabled = settings.calibration.enabled;
+    let aggregator_shutdown = shutdown.clone();
+    let aggregator_history = opportunity_history.clone();
+    let aggregator_summaries = pair_summaries.clone();
+    let aggregator_usd = UsdPricer::from_tokens(&settings.tokens).with_price_cache(cache.clone());
+    tasks.push(tokio::spawn(async move {
+        let mut aggregator = OpportunityAggregator::new(aggregation_window)
+            .with_rank_by(rank_by)
+            .with_usd_pricer(aggregator_usd);
+        let mut interval = tokio::time::interval((aggregation_window / 2).max(Duration::from_millis(1)));
+        loop {
+            tokio::select! {
+                Some(mut opp) = opp_rx.recv() => {
+                    if calibration_enabled {
+                        opp.confidence = aggregator_calibrator.write().await.track(&opp);
+                    }
+                    aggregator.push(opp);
+                }
+                _ = interval.tick() => {
+                    for group in aggregator.flush() {
+                        aggregator_history.record_group(&group);
+                        aggregator_summaries.record_group(&group);
+                        if let Some(sink) = &sink {
+                            sink.record_group(&group);
+                        }
+                        let _ = aggregator_api_tx.send(ApiMessage::OpportunityGroup(group));
+                    }
+                }
+                _ = aggregator_shutdown.cancelled() => {
+                    // Groups still inside their window go out before exit
+                    for group in aggregator.drain() {
+                        aggregator_history.record_group(&group);
+                        aggregator_summaries.record_group(&group);
+                        if let Some(sink) = &sink {
+                            sink.record_group(&group);
+                        }
+                        let _ = aggregator_api_tx.send(ApiMessage::OpportunityGroup(group));
+                    }
+                    break;
+                }
+            }
+        }
+    }));
+
     // Spawn Cache Cleanup Task
-    PriceCache::spawn_cleanup_task(
+    tasks.push(PriceCache::spawn_cleanup_task(
         cache.clone(),
         Duration::from_secs(settings.monitoring.cleanup_interval_seconds),
-    );
-
-    // Initialize Detectors
-    let spatial_detector = Arc::new(OpportunityDetector::new(
-        cache.clone(),
-        settings.fees.clone(),
-        settings.arbitrage.min_profit_percent,
-        settings.arbitrage.slot_tolerance,
+        shutdown.clone(),
     ));
 
-    let stat_detector = Arc::new(tokio::sync::RwLock::new(StatisticalArbitrageDetector::new(
-        cache.clone(),
-        StatArbConfig::default(),
-    )));
+    // Spawn Calibration Outcome Task
+    if settings.calibration.enabled {
+        let outcome_cache = cache.clone();
+        let outcome_calibrator = calibrator.clone();
+        let outcome_shutdown = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        // Copy the table out so the write doesn't hold up `track`
+                        let resolved = {
+                            let mut calibrator = outcome_calibrator.write().await;
+                            (calibrator.resolve(&outcome_cache) > 0).then(|| calibrator.table().clone())
+                        };
+                        if let Some(table) = resolved {
+                            if let Err(e) = ConfidenceCalibrator::save_table(table, calibration_path.clone()).await {
+                                warn!(error = ?e, "Failed to persist calibration table");
+                            }
+                        }
+                    }
+                    _ = outcome_shutdown.cancelled() => {
+                        // Final flush so a restart picks up every resolved outcome
+                        let table = outcome_calibrator.read().await.table().clone();
+                        if let Err(e) = ConfidenceCalibrator::save_table(table, calibration_path.clone()).await {
+                            warn!(error = ?e, "Failed to persist calibration table");
+                        }
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+
+    // Initialize Detectors (only those enabled under [detectors])
+    let detectors = &settings.detectors;
+    info!(enabled = ?detectors.enabled(), "Detectors configured");
+    let tokens = TokenRegistry::from_tokens(&settings.tokens);
+    // Worst-case profit and validity from the volatility and scan latency measured so far
+    let risk = RiskModel::new(&settings.arbitrage)
+        .with_volatility_tracker(volatility.clone())
+        .with_pipeline_metrics(pipeline_metrics.clone());
 
-    let triangular_detector = Arc::new(TriangularArbitrageDetector::new(
+    let mut spatial_detector = detectors.spatial.enabled.then(|| {
+        OpportunityDetector::new(
+            cache.clone(),
+            settings.fees.clone(),
+            settings.arbitrage.min_profit_percent,
+            settings.arbitrage.slot_tolerance,
+        ).with_confirmation_slots(settings.arbitrage.confirmation_slots)
+        .with_require_confirmed(settings.arbitrage.require_confirmed)
+        .with_non_streamed_confidence(settings.arbitrage.non_streamed_confidence)
+        .with_risk_model(risk.clone())
+        .with_token_registry(tokens.clone())
+    });
+
+    let stat_detector = detectors.statistical.enabled.then(|| {
+        Arc::new(tokio::sync::RwLock::new(StatisticalArbitrageDetector::new(
+            cache.clone(),
+            settings.stat_arb_config(),
+        ).with_risk_model(risk.clone())
+        .with_token_registry(tokens.clone())))
+    });
+
+    let spread_alert_detector = settings.alerts.enabled.then(|| {
+        Arc::new(SpreadAlertDetector::new(cache.clone(), settings.alerts.clone()))
+    });
+
+    let mut triangular_detector = detectors.triangular.enabled.then(|| {
+        TriangularArbitrageDetector::new(
+            cache.clone(),
+            settings.triangular_arb_config(),
+            settings.fees.clone(),
+        ).with_risk_model(risk.clone())
+        .with_token_registry(tokens.clone())
+    });
+
+    let mut cost_feed = None;
+    if settings.costs.enabled && settings.rpc.transport == solana_price_monitor::config::Transport::Simulated {
+        warn!("[costs] needs an RPC endpoint, using static fees with the simulated feed");
+    } else if settings.costs.enabled {
+        let feed = Arc::new(CostFeed::new(settings.costs.clone(), settings.fees.clone()));
+        tasks.push(CostFeed::spawn_polling_task(
+            feed.clone(),
+            settings.rpc.primary().http_url.clone(),
+            &Egress::from_rpc_config(&settings.rpc),
+            shutdown.clone(),
+        )?);
+        spatial_detector = spatial_detector.map(|d| d.with_cost_feed(feed.clone()));
+        triangular_detector = triangular_detector.map(|d| d.with_cost_feed(feed.clone()));
+        cost_feed = Some(feed);
+    }
+
+    if settings.websocket.swap_activity {
+        spatial_detector = spatial_detector.map(|d| d.with_swap_activity(swap_activity.clone()));
+        triangular_detector = triangular_detector.map(|d| d.with_swap_activity(swap_activity.clone()));
+    }
+
+    if settings.regime.enabled {
+        spatial_detector = spatial_detector.map(|d| d.with_volatility_tracker(volatility.clone()));
+        triangular_detector = triangular_detector.map(|d| d.with_volatility_tracker(volatility.clone()));
+    }
+    let spatial_detector = spatial_detector.map(|d| Arc::new(tokio::sync::RwLock::new(d)));
+    let triangular_detector = triangular_detector.map(|d| Arc::new(tokio::sync::RwLock::new(d)));
+    let spread_tracker = Arc::new(SpreadTracker::new(
         cache.clone(),
-        TriangularArbConfig::default(),
-        settings.fees.clone(),
+        Duration::from_millis(settings.api.spread_interval_ms),
+        settings.api.spread_change_bps,
     ));
 
+    // Spawn API Server
+    // What `GET /config` reports: replaced once a reload has been applied
+    let live_settings = tokio::sync::watch::Sender::new(settings.clone());
+    let app_state = api::AppState {
+        tx: api_tx.clone(),
+        calibrator: calibrator.clone(),
+        triangular_paths: triangular_paths.clone(),
+        volatility: volatility.clone(),
+        cache: cache.clone(),
+        pools: pool_selection.clone(),
+        pause: pause.clone(),
+        ws_status: ws_status.subscribe(),
+        settings: live_settings.subscribe(),
+        detectors: api::DetectorHandles {
+            spatial: spatial_detector.clone(),
+            statistical: stat_detector.clone(),
+            triangular: triangular_detector.clone(),
+            spread_alerts: spread_alert_detector.clone(),
+            spreads: Some(spread_tracker.clone()),
+        },
+        log_filter: Some(log_filter),
+        subscriptions: subscription_book.clone(),
+        taps: taps.clone(),
+        activity: swap_activity.clone(),
+        opportunities: opportunity_history.clone(),
+        usd: UsdPricer::from_tokens(&settings.tokens).with_price_cache(cache.clone()),
+        tokens: tokens.clone(),
+        health: health.clone(),
+        pair_summaries: pair_summaries.clone(),
+        limits: api_limits.clone(),
+        pool_control: pool_control.clone(),
+        discovered_pools: Arc::new(discovered.iter().map(|pool| pool.pubkey.clone()).collect()),
+        system_metrics: system_metrics.clone(),
+        client_lag: client_lag.clone(),
+        heartbeat: api::Heartbeat::from_config(&settings.api),
+        ws_clients: ws_clients.clone(),
+        legacy_messages: settings.api.legacy_messages,
+        timestamp_format: settings.api.timestamp_format,
+        tap_storage: Arc::new(api::TapStorage::new(&settings.api.tap_dir, settings.api.tap_max_bytes)),
+        shutdown: shutdown.clone(),
+    };
+    tasks.extend(api::start_server(&settings.api, app_state).await?);
+
+
+    // Spawn Scan Scheduler and Worker (cache events -> coalesced batches -> detectors)
+    let scheduler = ScanScheduler::new(Duration::from_millis(settings.arbitrage.scan_debounce_ms))
+        .with_pause(pause.clone());
+    let scheduler_metrics = scheduler.metrics();
+    let (batch_tx, mut batch_rx) = mpsc::channel(100);
+    tasks.push(scheduler.spawn(cache.subscribe(), batch_tx, shutdown.clone()));
+
+    let worker_metrics = scheduler_metrics.clone();
+    let worker_pipeline_metrics = pipeline_metrics.clone();
+    let worker_api_tx = api_tx.clone();
+    let worker_paths = triangular_paths.clone();
+    let worker_opp_tx = opp_tx.clone();
+    let worker_spatial = spatial_detector.clone();
+    let worker_triangular = triangular_detector.clone();
+    let worker_summaries = pair_summaries.clone();
+    // Ends once the scheduler stops and drops its batch sender
+    tasks.push(tokio::spawn(async move {
+        while let Some(batch) = batch_rx.recv().await {
+            for pair in &batch.pairs {
+                // Alert on wide spreads regardless of profitability
+                if let Some(alert) = spread_alert_detector
+                    .as_ref()
+                    .and_then(|d| d.check(pair))
+                {
+                    info!(
+                        pair = alert.pair,
+                        spread_bps = alert.spread_bps,
+                        "Spread alert"
+                    );
+                    let _ = worker_api_tx.send(ApiMessage::SpreadAlert(alert));
+                }
+                if let Some(spread) = spread_tracker.check(pair) {
+                    let _ = worker_api_tx.send(ApiMessage::Spread(spread));
+                }
+                worker_summaries.mark_dirty(pair);
+            }
+
+            scan_opportunities(
+                &batch.pairs,
+                worker_spatial.as_deref(),
+                worker_triangular.as_deref(),
+                &worker_paths,
+                &worker_opp_tx,
+            ).await;
+            worker_metrics.record_scan_latency(&batch);
+            worker_pipeline_metrics.record_scan(batch.first_update_at.elapsed());
+        }
+    }));
+
     // Initialize Decoders
+    // Raydium reads decimals from pool state; Orca/Meteora are built per pool
     let raydium_decoder = RaydiumDecoder;
-    let orca_decoder = OrcaDecoder::default();
-    let meteora_decoder = MeteoraDecoder::default();
 
     // Build pool lookup map: pubkey -> PoolInfo
-    let mut pool_lookup: HashMap<String, PoolInfo> = HashMap::new();
-    let mut subscriptions = Vec::new();
+    let (mut pool_lookup, mut subscriptions) =
+        build_subscriptions(&settings, &*pool_selection.read().await);
 
-    for (pair, dexes) in &settings.pools {
-        for (dex, pubkey) in dexes {
-            let decoder_type = match dex.to_lowercase().as_str() {
-                "raydium" => DecoderType::Raydium,
-                "orca" => DecoderType::Orca,
-                "meteora" => DecoderType::Meteora,
-                _ => {
-                    warn!(dex = dex, "Unknown DEX type, defaulting to Raydium");
-                    DecoderType::Raydium
-                }
-            };
+    // Initialize WebSocket Manager (bounded queue, see [websocket] backpressure)
+    let (tx, mut rx) = event_channel(settings.websocket.channel_capacity, settings.websocket.backpressure);
+    let ws_queue = rx.metrics();
 
-            pool_lookup.insert(pubkey.clone(), PoolInfo {
-                pair: pair.clone(),
-                dex: dex.clone(),
-                decoder_type,
-            });
+    // Broadcast SystemMetrics every api.metrics_interval_ms
+    let metrics_sampler = api::MetricsSampler::new(
+        cache.clone(),
+        pipeline_metrics.clone(),
+        ws_status.subscribe(),
+        opportunity_history.clone(),
+    )
+    .with_queue(ws_queue.clone())
+    .with_client_lag(client_lag)
+    .with_ws_clients(ws_clients);
+    tasks.push(metrics_sampler.spawn(
+        Duration::from_millis(settings.api.metrics_interval_ms),
+        system_metrics,
+        api_tx.clone(),
+        shutdown.clone(),
+    ));
 
-            subscriptions.push(pubkey.clone());
-            info!(pair = pair, dex = dex, pubkey = pubkey, "Monitoring pool");
-        }
+    // Broadcast the summaries of pairs that changed every api.pair_summary_interval_ms
+    if settings.api.pair_summary_interval_ms > 0 {
+        let summary_api_tx = api_tx.clone();
+        let summary_shutdown = shutdown.clone();
+        let mut interval = tokio::time::interval(Duration::from_millis(settings.api.pair_summary_interval_ms));
+        tasks.push(tokio::spawn(async move {
+            let run = async move {
+                loop {
+                    interval.tick().await;
+                    for set in pair_summaries.take_changed() {
+                        let _ = summary_api_tx.send(ApiMessage::PairSummary(Box::new(set)));
+                    }
+                }
+            };
+            summary_shutdown.run_until_cancelled(run).await;
+        }));
     }
 
-    // Track subscription ID -> pubkey mapping
-    let mut subscription_id_map: HashMap<u64, String> = HashMap::new();
+    // Forward opportunities to Telegram / Discord when [notifications] is enabled
+    if let Some(notifier) = Notifier::from_settings(&settings)? {
+        tasks.push(notifier.with_price_cache(cache.clone()).spawn(&api_tx, shutdown.clone()));
+    }
 
-    // Initialize WebSocket Manager
-    let (tx, mut rx) = mpsc::channel(1000);
-    let mut ws_manager = WebSocketManager::new(
-        settings.rpc.websocket_url.clone(),
-        subscriptions.clone(),
-    );
-    ws_manager.set_sender(tx);
+    // Spawn WebSocket Task (its token is replaced on every reconnect)
+    let mut ws_shutdown = shutdown.child_token();
+    let feed = FeedShared {
+        status: ws_status.clone(),
+        metrics: pipeline_metrics.clone(),
+        subscriptions: subscription_book,
+        taps,
+        activity: swap_activity,
+        cache: cache.clone(),
+        pool_control: pool_control.clone(),
+    };
+    let mut ws_task = spawn_websocket(&settings, &subscriptions, &pool_lookup, tx.clone(), &feed, ws_shutdown.clone());
 
-    // Spawn WebSocket Task
-    tokio::spawn(async move {
-        ws_manager.run().await;
-    });
+    // Spawn Config Watcher (hot reload of config.toml)
+    let mut config_rx = if settings.monitoring.config_reload_seconds > 0 {
+        ConfigWatcher::new("config.toml").with_overrides(cli.overrides.clone()).spawn(
+            settings.clone(),
+            Duration::from_secs(settings.monitoring.config_reload_seconds),
+            shutdown.clone(),
+        )
+    } else {
+        // Sender dropped: the reload branch below never fires
+        tokio::sync::watch::channel(settings.clone()).1
+    };
 
     // Spawn Health Monitor Task
     let health_cache = cache.clone();
-    tokio::spawn(async move {
+    let health_ws_status = ws_status.subscribe();
+    let health_shutdown = shutdown.clone();
+    let health_metrics = pipeline_metrics.clone();
+    let health_api_tx = api_tx.clone();
+    let health_pause = pause.clone();
+    let health_api_limits = api_limits.clone();
+    let health_tracker = health.clone();
+    let receipt_alert = Duration::from_millis(settings.monitoring.receipt_latency_alert_ms);
+    tasks.push(tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(60));
-        loop {
-            interval.tick().await;
-            let entries = health_cache.len(); // DashMap is lock-free, no await needed
-            info!(cache_entries = entries, "System Health Check");
-        }
-    });
+        let mut reported_drops = 0;
+        let mut previous = health_metrics.snapshot();
+        let run = async move {
+            loop {
+                interval.tick().await;
+                let pipeline = health_metrics.snapshot();
+                let rates = pipeline.rates_since(&previous);
+                let receipt_latency = pipeline.receipt_latency_since(&previous);
+                previous = pipeline.clone();
+                if !receipt_alert.is_zero() {
+                    let bound_us = receipt_alert.as_micros() as u64;
+                    let slow: Vec<String> = receipt_latency
+                        .iter()
+                        .filter(|(_, latency)| latency.p95_us.is_some_and(|p95| p95 > bound_us))
+                        .map(|(dex, _)| dex.clone())
+                        .collect();
+                    for dex in &slow {
+                        warn!(
+                            dex = dex,
+                            p95_us = ?receipt_latency[dex].p95_us,
+                            alert_ms = receipt_alert.as_millis() as u64,
+                            "Slot-to-receipt latency above bound"
+                        );
+                    }
+                    if health_metrics.receipt().set_slow(slow) {
+                        let ws = health_ws_status.borrow().clone();
+                        let _ = health_api_tx.send(system_status(health_pause.status(), &ws, &health_metrics));
+                    }
+                }
+                let dropped = ws_queue.dropped();
+                if dropped > reported_drops {
+                    warn!(
+                        dropped = dropped - reported_drops,
+                        "Main loop fell behind the WebSocket feed; oldest account updates were dropped"
+                    );
+                    reported_drops = dropped;
+                }
+                let scans = scheduler_metrics.snapshot();
+                let lag = health_cache.current_slot().lag_metrics();
+                let ws = health_ws_status.borrow().clone();
+                // DashMap is lock-free, no await needed
+                let health = health_tracker.status(health_cache.len(), ws.is_connected());
+                if !health.healthy {
+                    warn!(ws_state = ?ws.state, last_update_ms = health.last_update_ms, "System unhealthy");
+                }
+                for dex in &health.stale_dexes {
+                    warn!(dex = dex, last_update_ms = health.dex_last_update_ms[dex], "No price updates from DEX");
+                }
+                info!(
+                    status = %health,
+                    rpc_endpoint = %ws.endpoint,
+                    ws_connected = ws.is_connected(),
+                    connection_uptime_secs = ?ws.uptime_secs(),
+                    active_subscriptions = ws.active_subscriptions,
+                    chain_slot = ?health_cache.current_slot().get(),
+                    update_lag_slots = lag.last,
+                    mean_update_lag_slots = lag.mean,
+                    updates_received = scans.updates_received,
+                    updates_coalesced = scans.updates_coalesced,
+                    scan_batches = scans.batches_dispatched,
+                    paused_batches = scans.batches_paused,
+                    scan_latency_us = scans.last_scan_latency_us,
+                    ws_events_dropped = dropped,
+                    ws_events_coalesced = ws_queue.coalesced(),
+                    ws_messages_per_sec = rates.messages_per_sec,
+                    ws_bytes_per_sec = rates.bytes_per_sec,
+                    processing_p50_us = ?pipeline.processing_p50_us,
+                    processing_p99_us = ?pipeline.processing_p99_us,
+                    scan_p99_us = ?pipeline.scan_p99_us,
+                    silence_reconnects = pipeline.silence_reconnects,
+                    stale_resubscribes = pipeline.stale_resubscribes,
+                    out_of_order_drops = pipeline.out_of_order_drops,
+                    inconsistent_prices = pipeline.inconsistent_prices,
+                    receipt_latency = ?receipt_latency,
+                    channel_occupancy = ws_queue.occupancy(),
+                    api_rejected = ?health_api_limits.rejections(),
+                    "System Health Check"
+                );
+            }
+        };
+        health_shutdown.run_until_cancelled(run).await;
+    }));
 
-    // Generate triangular paths for scanning
-    let triangular_paths = generate_common_paths("raydium");
-    let pairs: Vec<&str> = settings.pools.keys().map(|s| s.as_str()).collect();
+    // Spawn Statistical Arbitrage Scan Task
+    if let Some(stat_scan_detector) = stat_detector {
+        let stat_cache = cache.clone();
+        let stat_opp_tx = opp_tx.clone();
+        let stat_interval = Duration::from_secs(settings.stat_arb.scan_interval_seconds);
+        let stat_filters = filters.clone();
+        let stat_pause = pause.clone();
+        let mut pair_universe = PairUniverse::new(&settings.stat_arb);
+        let stat_shutdown = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(stat_interval);
+            let run = async move {
+                loop {
+                    interval.tick().await;
+                    pair_universe.set_filters(&*stat_filters.read().await);
+                    pair_universe.refresh(&stat_cache);
+
+                    // Sent once the lock is released, so a full channel doesn't
+                    // hold up the API or reloads
+                    let mut found = Vec::new();
+                    let mut detector = stat_scan_detector.write().await;
+                    for candidate in pair_universe.candidates() {
+                        if let Some(opp) = detector
+                            .detect(&candidate.pair_a, &candidate.pair_b, &candidate.dex)
+                            .await
+                        {
+                            // Detection still runs during a pause to keep the spread history warm
+                            if stat_pause.is_paused() {
+                                continue;
+                            }
+                            info!(
+                                id = %opp.id,
+                                opportunity = %opp,
+                                "📊 STATISTICAL ARBITRAGE DETECTED"
+                            );
+                            found.push(opp);
+                        }
+                    }
+                    drop(detector);
+
+                    for opp in found {
+                        let _ = stat_opp_tx.send(opp).await;
+                    }
+                }
+            };
+            stat_shutdown.run_until_cancelled(run).await;
+        }));
+    }
+
+    // Spawn Triangular Path Pruning Task (paths are only scanned by the triangular detector)
+    if triangular_detector.is_some() {
+        let prune_cache = cache.clone();
+        let prune_paths = triangular_paths.clone();
+        let prune_interval = Duration::from_secs(settings.triangular.prune_interval_seconds);
+        let prune_shutdown = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            // First pass after one interval, once the feed has filled the cache
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + prune_interval, prune_interval);
+            let run = async move {
+                loop {
+                    interval.tick().await;
+                    let metrics = prune_paths.write().await.prune(&prune_cache);
+                    info!(
+                        total = metrics.total,
+                        enabled = metrics.enabled,
+                        disabled = metrics.disabled,
+                        "Triangular path set pruned"
+                    );
+                }
+            };
+            prune_shutdown.run_until_cancelled(run).await;
+        }));
+    }
 
     // Main Event Loop
     info!("Starting main event loop...");
 
     loop {
         tokio::select! {
-            Some(msg_text) = rx.recv() => {
+            Some(event) = rx.recv() => {
+                let received = std::time::Instant::now();
+                let account_update =
+                    matches!(event, WsEvent::AccountUpdate { .. } | WsEvent::ProgramAccountUpdate { .. });
                 if let Err(e) = process_message(
-                    &msg_text,
-                    &pool_lookup,
-                    &mut subscription_id_map,
-                    &subscriptions,
+                    event,
+                    &mut pool_lookup,
                     &raydium_decoder,
-                    &orca_decoder,
-                    &meteora_decoder,
+                    &settings,
                     &cache,
-                    &spatial_detector,
-                    &stat_detector,
-                    &triangular_detector,
-                    &triangular_paths,
-                    &pairs,
+                    &volatility,
                     &api_tx, // Pass broadcast sender
+                    &pipeline_metrics,
+                    &health,
                 ).await {
                     debug!(error = ?e, "Error processing message");
+                } else if account_update {
+                    pipeline_metrics.record_processing(received.elapsed());
+                }
+            }
+            Ok(()) = config_rx.changed() => {
+                let mut new_settings = config_rx.borrow_and_update().clone();
+                if !discovered.is_empty() {
+                    // Keep startup discoveries; the reloaded file only has static pools
+                    new_settings.pools = discovery::merge_pools(
+                        &new_settings.pools,
+                        &discovered,
+                        new_settings.monitoring.max_pools,
+                    );
+                }
+                if let Err(e) = cli.overrides.restrict_pairs(&mut new_settings.pools) {
+                    warn!(error = ?e, "Rejected reloaded configuration, keeping previous settings");
+                    continue;
+                }
+                let rpc_changed = apply_settings(
+                    &settings,
+                    &new_settings,
+                    &cache,
+                    spatial_detector.as_deref(),
+                    triangular_detector.as_deref(),
+                    cost_feed.as_deref(),
+                    &triangular_paths,
+                    &filters,
+                    &pause,
+                ).await;
+                pool_control.set_max_pools(new_settings.monitoring.max_pools);
+                pool_control.set_program_dexes(program_dexes(&new_settings));
+
+                // Re-apply the pool policy (priorities, filters, max_pools)
+                let selection = new_settings.select_pools();
+                let pools_changed = selection != *pool_selection.read().await;
+                let modes_changed = settings.subscriptions != new_settings.subscriptions;
+                if pools_changed || modes_changed {
+                    log_pool_selection(&selection);
+                    (pool_lookup, subscriptions) = build_subscriptions(&new_settings, &selection);
+                    *pool_selection.write().await = selection;
                 }
+
+                if rpc_changed || pools_changed || modes_changed {
+                    info!(rpc_changed, pools_changed, modes_changed, "Reconnecting WebSocket");
+                    // The old manager unsubscribes and closes in the background
+                    ws_shutdown.cancel();
+                    ws_shutdown = shutdown.child_token();
+                    ws_task = spawn_websocket(
+                        &new_settings,
+                        &subscriptions,
+                        &pool_lookup,
+                        tx.clone(),
+                        &feed,
+                        ws_shutdown.clone(),
+                    );
+                }
+                live_settings.send_replace(new_settings.clone());
+                settings = new_settings;
             }
             _ = tokio::signal::ctrl_c() => {
                 info!("Shutdown signal received");
@@ -205,206 +938,579 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Stop every task and wait (bounded) for them to unsubscribe, drain and flush
+    shutdown.cancel();
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Second shutdown signal, exiting immediately");
+            std::process::exit(130);
+        }
+    });
+    tasks.push(ws_task);
+    let running = tasks.len();
+    match tokio::time::timeout(SHUTDOWN_TIMEOUT, futures::future::join_all(tasks)).await {
+        Ok(_) => info!(tasks = running, "Shutdown complete"),
+        Err(_) => warn!(
+            timeout_secs = SHUTDOWN_TIMEOUT.as_secs(),
+            "Tasks still running at shutdown timeout, exiting"
+        ),
+    }
+
     Ok(())
 }
 
-/// Process incoming WebSocket message
-async fn process_message(
-    msg_text: &str,
+/// Decoder lookup (pubkey -> PoolInfo) and subscription list for the active pools
+///
+/// Pools of DEXes in program mode are looked up but not subscribed to
+/// individually; the program subscription delivers them.
+fn build_subscriptions(
+    settings: &Settings,
+    selection: &PoolSelection,
+) -> (HashMap<String, PoolInfo>, Vec<String>) {
+    let mut pool_lookup = HashMap::new();
+    let mut subscriptions = Vec::new();
+
+    for pool in &selection.active {
+        let (pair, dex, pubkey) = (&pool.pair, &pool.dex, &pool.pubkey);
+        pool_lookup.insert(pubkey.clone(), PoolInfo::new(settings, pair.clone(), dex.clone(), pubkey));
+
+        if settings.subscription_mode(dex) == SubscriptionMode::Account {
+            subscriptions.push(pubkey.clone());
+        }
+        info!(pair = pair, dex = %dex, pubkey = pubkey, priority = pool.priority, "Monitoring pool");
+    }
+
+    (pool_lookup, subscriptions)
+}
+
+/// Pool info for an account seen on a program subscription, when its token
+/// mints resolve to a pair the filters allow
+fn resolve_program_pool(settings: &Settings, dex: &Dex, pubkey: &str, data: &[u8]) -> Option<PoolInfo> {
+    let mints = match dex {
+        Dex::Orca => OrcaDecoder::default().token_mints(data),
+        Dex::Meteora => MeteoraDecoder::default().token_mints(data),
+        _ => return None,
+    };
+    let (mint_a, mint_b) = mints.ok()?;
+    let pair = settings.pair_for_mints(&mint_a.to_string(), &mint_b.to_string())?;
+    if !settings.filters.allows_pair(&pair) {
+        return None;
+    }
+    Some(PoolInfo::new(settings, pair, dex.clone(), pubkey))
+}
+
+/// Fetch every active pool once over HTTP RPC and collect its decoded fee
+/// rate, in the `fees.schedule_file` format
+async fn export_fee_schedule(
+    http_url: &str,
+    egress: &Egress,
     pool_lookup: &HashMap<String, PoolInfo>,
-    subscription_id_map: &mut HashMap<u64, String>,
+) -> Result<FeeSchedule> {
+    // getMultipleAccounts accepts at most 100 keys per request
+    const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+    let rpc = egress.rpc_client(http_url)?;
+    let raydium_decoder = RaydiumDecoder;
+    let pubkeys = pool_lookup
+        .keys()
+        .map(|key| Pubkey::from_str(key))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut fee_rates = HashMap::new();
+    for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let accounts = rpc
+            .get_multiple_accounts(chunk)
+            .await
+            .map_err(|e| anyhow::anyhow!(RedactedUrl::new(http_url).scrub(&e.to_string())))?;
+        for (pubkey, account) in chunk.iter().zip(accounts) {
+            let pubkey = pubkey.to_string();
+            let Some(account) = account else {
+                warn!(pubkey = pubkey, "Pool account not found, skipping");
+                continue;
+            };
+            match pool_lookup[&pubkey].decoder_type.decode(&raydium_decoder, &account.data) {
+                Ok(state) => {
+                    fee_rates.insert(pubkey, state.fee_rate);
+                }
+                Err(e) => warn!(pubkey = pubkey, error = ?e, "Failed to decode pool, skipping"),
+            }
+        }
+    }
+
+    Ok(FeeSchedule::from_decoded(fee_rates))
+}
+
+/// Log every pool left out of the subscription list and why
+fn log_pool_selection(selection: &PoolSelection) {
+    for skipped in &selection.skipped {
+        let pool = &skipped.pool;
+        warn!(
+            pair = pool.pair,
+            dex = %pool.dex,
+            pubkey = pool.pubkey,
+            priority = pool.priority,
+            reason = ?skipped.reason,
+            "Pool skipped, not subscribing"
+        );
+    }
+    info!(
+        active = selection.active.len(),
+        skipped = selection.skipped.len(),
+        "Pool selection applied"
+    );
+}
+
+/// Feed outputs that outlive each manager: its status, metrics, subscriptions
+/// and swap counts, plus the pool index and handle the admin API works with
+struct FeedShared {
+    status: tokio::sync::watch::Sender<ConnectionStatus>,
+    metrics: Arc<PipelineMetrics>,
+    subscriptions: SubscriptionBook,
+    taps: TapSet,
+    activity: SwapActivity,
+    cache: Arc<PriceCache>,
+    pool_control: api::PoolControl,
+}
+
+/// DEXes whose pools arrive on a program subscription
+fn program_dexes(settings: &Settings) -> Vec<Dex> {
+    settings
+        .subscriptions
+        .iter()
+        .filter(|(_, subscription)| subscription.mode == SubscriptionMode::Program)
+        .map(|(dex, _)| dex.clone())
+        .collect()
+}
+
+/// Start the feed for `rpc.transport` (a WebSocket manager over the configured
+/// endpoints, the Geyser client or the simulator), streaming events
+/// into `tx` until `shutdown` fires
+fn spawn_websocket(
+    settings: &Settings,
     subscriptions: &[String],
+    pool_lookup: &HashMap<String, PoolInfo>,
+    tx: EventSender,
+    feed: &FeedShared,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    let pools: HashMap<String, (String, Dex)> = pool_lookup
+        .iter()
+        .map(|(pubkey, info)| (pubkey.clone(), (info.pair.clone(), info.dex.clone())))
+        .collect();
+    feed.activity.set_pools(pools.clone());
+    feed.cache.set_pools(pools.clone());
+    feed.subscriptions.set_pools(pools);
+    // Only the WebSocket manager takes subscriptions mid-connection
+    feed.pool_control.set_handle(None);
+
+    if settings.rpc.transport == solana_price_monitor::config::Transport::Simulated {
+        let mut pools: Vec<(&str, &str, &str)> = pool_lookup
+            .iter()
+            .map(|(pubkey, info)| (pubkey.as_str(), info.pair.as_str(), info.dex.as_str()))
+            .collect();
+        pools.sort();
+        let mut simulator = SimulatedFeed::from_settings(settings, pools)
+            .with_status(feed.status.clone())
+            .with_metrics(feed.metrics.clone())
+            .with_shutdown(shutdown);
+        simulator.set_sender(tx);
+        return tokio::spawn(async move {
+            simulator.run().await;
+        });
+    }
+
+    #[cfg(feature = "geyser")]
+    if settings.rpc.transport == solana_price_monitor::config::Transport::Geyser {
+        let mut client = solana_price_monitor::geyser::GeyserClient::from_rpc_config(&settings.rpc, subscriptions.to_vec())
+            .with_config(&settings.websocket)
+            .with_programs(&settings.subscriptions)
+            .with_status(feed.status.clone())
+            .with_metrics(feed.metrics.clone())
+            .with_shutdown(shutdown);
+        client.set_sender(tx);
+        return tokio::spawn(async move {
+            client.run().await;
+        });
+    }
+
+    let data_slices = if settings.websocket.data_slice {
+        subscriptions
+            .iter()
+            .filter_map(|pubkey| Some((pubkey.clone(), pool_lookup.get(pubkey)?.decoder_type.data_slice()?)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let mut ws_manager = WebSocketManager::from_rpc_config(&settings.rpc, subscriptions.to_vec())
+        .with_config(&settings.websocket)
+        .with_data_slices(data_slices)
+        .with_programs(&settings.subscriptions)
+        .with_status(feed.status.clone())
+        .with_metrics(feed.metrics.clone())
+        .with_subscription_book(feed.subscriptions.clone())
+        .with_taps(feed.taps.clone())
+        .with_swap_activity(feed.activity.clone())
+        .with_shutdown(shutdown);
+    ws_manager.set_sender(tx);
+    feed.pool_control.set_handle(Some(ws_manager.handle()));
+    tokio::spawn(async move {
+        ws_manager.run().await;
+    })
+}
+
+/// Status broadcast for API clients: pause state, WebSocket feed health and
+/// DEXes over the receipt latency bound
+fn system_status(pause: PauseStatus, ws: &ConnectionStatus, metrics: &PipelineMetrics) -> ApiMessage {
+    ApiMessage::SystemStatus {
+        paused: pause.paused,
+        reason: pause.reason,
+        rpc_endpoint: ws.endpoint.clone(),
+        ws_connected: ws.is_connected(),
+        consecutive_failures: ws.consecutive_failures(),
+        connection_uptime_secs: ws.uptime_secs(),
+        active_subscriptions: ws.active_subscriptions,
+        slow_dexes: metrics.receipt().slow(),
+    }
+}
+
+/// Apply a reloaded configuration to running components
+///
+/// Thresholds, fees and staleness take effect immediately. Sections that
+/// size or spawn tasks at startup are logged and need a restart. Returns
+/// whether the RPC endpoints or WebSocket options changed, in which case the
+/// caller reconnects.
+#[allow(clippy::too_many_arguments)]
+async fn apply_settings(
+    current: &Settings,
+    new: &Settings,
+    cache: &PriceCache,
+    spatial_detector: Option<&tokio::sync::RwLock<OpportunityDetector>>,
+    triangular_detector: Option<&tokio::sync::RwLock<TriangularArbitrageDetector>>,
+    cost_feed: Option<&CostFeed>,
+    triangular_paths: &tokio::sync::RwLock<TriangularPathSet>,
+    filters: &tokio::sync::RwLock<FiltersConfig>,
+    pause: &PauseController,
+) -> bool {
+    let changed = changed_sections(current, new);
+
+    if changed.contains(&"filters") {
+        *filters.write().await = new.filters.clone();
+        let metrics = triangular_paths.write().await.apply_filters(&new.filters);
+        info!(
+            filters = ?new.filters,
+            filtered_paths = metrics.filtered,
+            "Applied pair and token filters (newly allowed pools need a restart to subscribe)"
+        );
+    }
+
+    if changed.contains(&"arbitrage") || changed.contains(&"fees") {
+        if let Some(detector) = spatial_detector {
+            detector.write().await.reconfigure(&new.arbitrage, &new.fees);
+        }
+        if let Some(detector) = triangular_detector {
+            detector
+                .write()
+                .await
+                .reconfigure(&new.arbitrage, &new.fees);
+        }
+        // Detectors with a cost feed price from its fees, live gas and tips aside
+        if let Some(feed) = cost_feed {
+            feed.set_fallback(new.fees.clone());
+        }
+        info!(
+            min_profit = new.arbitrage.min_profit_percent,
+            slot_tolerance = new.arbitrage.slot_tolerance,
+            confirmation_slots = new.arbitrage.confirmation_slots,
+            fees = ?new.fees,
+            "Applied arbitrage thresholds and fees"
+        );
+    }
+
+    let (queue, new_queue) = (&current.websocket, &new.websocket);
+    if (queue.channel_capacity, queue.backpressure) != (new_queue.channel_capacity, new_queue.backpressure) {
+        warn!("websocket.channel_capacity / backpressure changed, restart required to apply");
+    }
+
+    // max_pools and the staleness thresholds apply live; the rest configure
+    // tasks spawned at startup
+    let (monitoring, new_monitoring) = (&current.monitoring, &new.monitoring);
+    for (field, differs) in [
+        ("cache_ttl_seconds", monitoring.cache_ttl_seconds != new_monitoring.cache_ttl_seconds),
+        ("cleanup_interval_seconds", monitoring.cleanup_interval_seconds != new_monitoring.cleanup_interval_seconds),
+        ("config_reload_seconds", monitoring.config_reload_seconds != new_monitoring.config_reload_seconds),
+        ("receipt_latency_alert_ms", monitoring.receipt_latency_alert_ms != new_monitoring.receipt_latency_alert_ms),
+    ] {
+        if differs {
+            warn!(field = field, "monitoring setting changed, restart required to apply");
+        }
+    }
+
+    if changed.contains(&"schedule") {
+        pause.set_windows(new.schedule.pause_windows.clone());
+        info!(windows = new.schedule.pause_windows.len(), "Applied pause schedule");
+    }
+
+    if current.monitoring.stale_thresholds() != new.monitoring.stale_thresholds() {
+        cache.set_stale_thresholds(new.monitoring.stale_thresholds());
+        info!(
+            stale_threshold_ms = new.monitoring.stale_threshold_ms,
+            overrides = ?new.monitoring.stale_threshold_overrides,
+            "Applied staleness thresholds"
+        );
+    }
+
+    for section in changed
+        .iter()
+        .filter(|s| {
+            !matches!(
+                **s,
+                "cluster" | "rpc" | "websocket" | "subscriptions" | "arbitrage" | "fees" | "filters" | "monitoring" | "pools"
+                    | "pool_priority" | "schedule" | "consistency"
+            )
+        })
+    {
+        warn!(section = section, "Configuration section changed, restart required to apply");
+    }
+
+    changed.contains(&"rpc") || changed.contains(&"websocket")
+}
+
+/// Process an event from the WebSocket manager
+#[allow(clippy::too_many_arguments)]
+async fn process_message(
+    event: WsEvent,
+    pool_lookup: &mut HashMap<String, PoolInfo>,
     raydium_decoder: &RaydiumDecoder,
-    orca_decoder: &OrcaDecoder,
-    meteora_decoder: &MeteoraDecoder,
+    settings: &Settings,
     cache: &Arc<PriceCache>,
-    spatial_detector: &Arc<OpportunityDetector>,
-    stat_detector: &Arc<tokio::sync::RwLock<StatisticalArbitrageDetector>>,
-    triangular_detector: &Arc<TriangularArbitrageDetector>,
-    triangular_paths: &[detector::TriangularPath],
-    pairs: &[&str],
-    api_tx: &tokio::sync::broadcast::Sender<ApiMessage>,
+    volatility: &VolatilityTracker,
+    api_tx: &api::Topics,
+    metrics: &PipelineMetrics,
+    health: &HealthTracker,
 ) -> Result<()> {
-    let value: serde_json::Value = serde_json::from_str(msg_text)?;
-
-    // Handle subscription confirmation: map subscription ID to pubkey
-    if let Some(result) = value.get("result") {
-        if let Some(id) = value.get("id").and_then(|v| v.as_u64()) {
-            let idx = (id - 1) as usize;
-            if idx < subscriptions.len() {
-                if let Some(sub_id) = result.as_u64() {
-                    subscription_id_map.insert(sub_id, subscriptions[idx].clone());
-                    debug!(sub_id = sub_id, pubkey = subscriptions[idx], "Subscription confirmed");
+    let (pubkey, slot, data, commitment) = match event {
+        WsEvent::AccountUpdate { pubkey, slot, data, commitment } => (pubkey, slot, data, commitment),
+        WsEvent::ProgramAccountUpdate { dex, pubkey, slot, data, commitment } => {
+            // Pick up pools trading tracked pairs until max_pools is reached
+            if !pool_lookup.contains_key(&pubkey) && pool_lookup.len() < settings.monitoring.max_pools {
+                if let Some(info) = resolve_program_pool(settings, &dex, &pubkey, &data) {
+                    info!(pair = info.pair, dex = %info.dex, pubkey = pubkey, "Monitoring pool from program subscription");
+                    cache.register_pool(&pubkey, &info.pair, &info.dex);
+                    pool_lookup.insert(pubkey.clone(), info);
                 }
             }
+            (pubkey, slot, data, commitment)
+        }
+        WsEvent::SlotAdvanced { slot, timestamp } => {
+            cache.current_slot().advance(slot, timestamp);
+            metrics.receipt().observe_slot(slot, timestamp);
+            return Ok(());
+        }
+        // Failover is logged by the manager and handled by the main loop
+        WsEvent::SubscriptionConfirmed { .. } | WsEvent::Failover { .. } => return Ok(()),
+        WsEvent::Disconnected => {
+            warn!("WebSocket disconnected, price updates paused");
+            return Ok(());
+        }
+        WsEvent::Reconnected => {
+            info!("WebSocket reconnected, subscriptions restored");
+            return Ok(());
+        }
+        WsEvent::Unhandled { method, .. } => {
+            debug!(method = method, "Ignoring unhandled notification");
+            return Ok(());
         }
+    };
+
+    // Get pool info; the cache's pool index also has pools added or removed
+    // through the admin API since the lookup was built
+    let pool_info = match (pool_lookup.get(&pubkey), cache.pool(&pubkey)) {
+        (Some(info), Some(_)) => info.clone(),
+        (None, Some((pair, dex))) => {
+            let info = PoolInfo::new(settings, pair, dex, &pubkey);
+            pool_lookup.insert(pubkey.clone(), info.clone());
+            info
+        }
+        (Some(_), None) => {
+            debug!(pubkey = pubkey, "Pool removed, dropping update");
+            pool_lookup.remove(&pubkey);
+            return Ok(());
+        }
+        (None, None) => {
+            debug!(pubkey = pubkey, "Pool not found in lookup");
+            return Ok(());
+        }
+    };
+
+    // Drop updates for pairs filtered out since subscribing
+    if !settings.filters.allows_pair(&pool_info.pair) {
         return Ok(());
     }
 
-    // Handle account notification
-    if value.get("method").and_then(|m| m.as_str()) == Some("accountNotification") {
-        if let Some(params) = value.get("params") {
-            let sub_id = params.get("subscription").and_then(|v| v.as_u64()).unwrap_or(0);
-            
-            // Get pubkey from subscription ID
-            let pubkey = match subscription_id_map.get(&sub_id) {
-                Some(pk) => pk.clone(),
-                None => {
-                    debug!(sub_id = sub_id, "Unknown subscription ID");
-                    return Ok(());
-                }
-            };
+    if let Some(lag) = cache.current_slot().observe_update(slot) {
+        debug!(pubkey = pubkey, slot = slot, lag_slots = lag, "Account update lag");
+    }
+    metrics.receipt().observe_update(pool_info.dex.as_str(), slot, chrono::Utc::now().timestamp_millis() as u64);
 
-            // Get pool info
-            let pool_info = match pool_lookup.get(&pubkey) {
-                Some(info) => info.clone(),
-                None => {
-                    debug!(pubkey = pubkey, "Pool not found in lookup");
-                    return Ok(());
-                }
-            };
+    // Decode pool state using appropriate decoder
+    let pool_state = pool_info.decoder_type.decode(raydium_decoder, &data)?;
 
-            // Extract account data
-            if let Some(result) = params.get("result") {
-                if let Some(value_obj) = result.get("value") {
-                    let slot = result.get("context")
-                        .and_then(|c| c.get("slot"))
-                        .and_then(|s| s.as_u64())
-                        .unwrap_or(0);
-
-                    // Decode account data
-                    if let Some(data_array) = value_obj.get("data").and_then(|d| d.as_array()) {
-                        if let Some(data_b64) = data_array.first().and_then(|d| d.as_str()) {
-                            let decoded = base64::Engine::decode(
-                                &base64::engine::general_purpose::STANDARD,
-                                data_b64
-                            )?;
-
-                            // Decode pool state using appropriate decoder
-                            let pool_state: PoolState = match pool_info.decoder_type {
-                                DecoderType::Raydium => raydium_decoder.decode(&decoded)?,
-                                DecoderType::Orca => orca_decoder.decode(&decoded)?,
-                                DecoderType::Meteora => meteora_decoder.decode(&decoded)?,
-                            };
-
-                            // Calculate price based on pool type
-                            let price = match pool_state.specific_data {
-                                decoder::SpecificPoolData::Amm { coin_vault_balance, pc_vault_balance } => {
-                                    calculate_amm_price(
-                                        coin_vault_balance,
-                                        pc_vault_balance,
-                                        pool_state.token_a_decimals,
-                                        pool_state.token_b_decimals,
-                                    )
-                                }
-                                decoder::SpecificPoolData::Clmm { sqrt_price, .. } => {
-                                    // Use helper from OrcaDecoder (or implemented inline)
-                                    // Logic: price = (sqrt_price / 2^64)^2 * decimal_adjustment
-                                    let sqrt_price_f64 = sqrt_price as f64 / (1u128 << 64) as f64;
-                                    let raw_price = sqrt_price_f64 * sqrt_price_f64;
-                                    let decimal_adjustment = 10f64.powi(pool_state.token_a_decimals as i32 - pool_state.token_b_decimals as i32);
-                                    raw_price * decimal_adjustment
-                                }
-                                decoder::SpecificPoolData::Dlmm { active_id, bin_step, .. } => {
-                                    // Logic: price = (1 + bin_step / 10000)^active_id * decimal_adjustment
-                                    let base = 1.0 + (bin_step as f64 / 10000.0);
-                                    let raw_price = base.powi(active_id);
-                                    let decimal_adjustment = 10f64.powi(pool_state.token_a_decimals as i32 - pool_state.token_b_decimals as i32);
-                                    raw_price * decimal_adjustment
-                                }
-                            };
-
-                            if price > 0.0 {
-                                // Update cache
-                                let price_data = PriceData::new(
-                                    price,
-                                    pool_state.liquidity as u64,
-                                    slot,
-                                    pool_state.token_a_reserve,
-                                    pool_state.token_b_reserve,
-                                    pool_state.fee_rate,
-                                );
-
-                                cache.update(&pool_info.pair, &pool_info.dex, price_data).await;
-
-                                debug!(
-                                    pair = pool_info.pair,
-                                    dex = pool_info.dex,
-                                    price = price,
-                                    slot = slot,
-                                    "Price updated"
-                                );
-
-                                // Broadcast price update
-                                let _ = api_tx.send(ApiMessage::PriceUpdate {
-                                    pair: pool_info.pair.clone(),
-                                    dex: pool_info.dex.clone(),
-                                    price,
-                                    slot,
-                                    ts: chrono::Utc::now().timestamp_millis() as u64,
-                                });
-
-                                // Scan for opportunities
-                                scan_opportunities(
-                                    &pool_info.pair,
-                                    spatial_detector,
-                                    stat_detector,
-                                    triangular_detector,
-                                    triangular_paths,
-                                    pairs,
-                                    api_tx,
-                                ).await;
-                            }
-                        }
-                    }
+    let price = pool_state.price();
+
+    if price > 0.0 {
+        // Update cache
+        let mut price_data = PriceData::new(
+            price,
+            pool_state.liquidity as u64,
+            slot,
+            pool_state.token_a_reserve,
+            pool_state.token_b_reserve,
+            settings.fees.pool_fee_rate(&pubkey, &pool_info.pair, &pool_info.dex, Some(pool_state.fee_rate)),
+        );
+
+        price_data.confirmed = commitment >= Commitment::Confirmed;
+        let mut price_data = price_data
+            .with_price_exact(pool_state.price_exact())
+            .with_fixed_cost_rate(settings.fees.pool_fixed_cost_rate(&pubkey, &pool_info.pair, &pool_info.dex))
+            .with_origin(pubkey.as_str(), settings.rpc.transport.price_source());
+
+        // Catch decoder faults (misread decimals) before detectors trade on
+        // them, by repricing the vault balances with the configured decimals
+        // rather than the decoded ones. CLMM and DLMM accounts carry no vault
+        // balances, so only AMM pools with configured decimals are checked.
+        let consistency = &settings.consistency;
+        let checked_decimals = match pool_state.specific_data {
+            SpecificPoolData::Amm { .. } if consistency.enabled => pool_info.decimals,
+            _ => None,
+        };
+        if let Some(decimals) = checked_decimals {
+            let tolerance = consistency.tolerance_percent(&pool_info.dex);
+            if let Err(inconsistency) = price_data.validate_consistency_within(
+                decimals.token_a_decimals,
+                decimals.token_b_decimals,
+                tolerance,
+            ) {
+                let count = metrics.record_inconsistent_price(&pubkey);
+                // On the first and then at each doubling, so a broken decoder
+                // doesn't flood the log
+                if count.is_power_of_two() {
+                    warn!(pair = pool_info.pair, dex = %pool_info.dex, pubkey = pubkey, count = count, %inconsistency, "Price inconsistent with vault balances");
+                }
+                match consistency.action {
+                    InconsistentPriceAction::Drop => return Ok(()),
+                    InconsistentPriceAction::DownWeight => price_data.confidence_weight = consistency.down_weight,
                 }
             }
         }
+
+        // Each account arrives twice; keep the newest, confirmed where possible
+        if settings.rpc.dual_commitment {
+            if !cache.set_if_newer(&pool_info.pair, &pool_info.dex, price_data) {
+                return Ok(());
+            }
+        } else {
+            cache.update(&pool_info.pair, &pool_info.dex, price_data).await;
+        }
+        volatility.observe(&pool_info.pair, pool_info.dex.as_str(), price);
+        health.record_update(&pool_info.dex);
+
+        debug!(
+            pair = pool_info.pair,
+            dex = %pool_info.dex,
+            price = price,
+            slot = slot,
+            "Price updated"
+        );
+
+        // Broadcast price update
+        let _ = api_tx.send(ApiMessage::PriceUpdate {
+            pair: pool_info.pair.clone(),
+            dex: pool_info.dex.clone(),
+            price,
+            slot,
+            ts: chrono::Utc::now(),
+        });
     }
 
     Ok(())
 }
 
-/// Scan for arbitrage opportunities after price update
+/// Scan the pairs in a scheduler batch for arbitrage opportunities
 async fn scan_opportunities(
-    updated_pair: &str,
-    spatial_detector: &Arc<OpportunityDetector>,
-    stat_detector: &Arc<tokio::sync::RwLock<StatisticalArbitrageDetector>>,
-    triangular_detector: &Arc<TriangularArbitrageDetector>,
-    triangular_paths: &[detector::TriangularPath],
-    _pairs: &[&str],
-    api_tx: &tokio::sync::broadcast::Sender<ApiMessage>,
+    pairs: &BTreeSet<String>,
+    spatial_detector: Option<&tokio::sync::RwLock<OpportunityDetector>>,
+    triangular_detector: Option<&tokio::sync::RwLock<TriangularArbitrageDetector>>,
+    triangular_paths: &tokio::sync::RwLock<TriangularPathSet>,
+    opp_tx: &mpsc::Sender<Opportunity>,
 ) {
-    // 1. Spatial Arbitrage (cross-DEX)
-    if let Some(opp) = spatial_detector.scan_pair(updated_pair).await {
-        info!(
-            opportunity = %opp,
-            "🚀 SPATIAL ARBITRAGE DETECTED"
-        );
-        let _ = api_tx.send(ApiMessage::OpportunityFound(opp));
+    // Opportunities are sent once the locks are released, so a full channel
+    // doesn't hold up reloads, resets or path pruning
+    let mut found = Vec::new();
+
+    // 1. Spatial Arbitrage (cross-DEX, affected pairs only)
+    if let Some(detector) = spatial_detector {
+        let detector = detector.read().await;
+        for pair in pairs {
+            if let Some(opp) = detector.scan_pair(pair).await {
+                info!(
+                    id = %opp.id,
+                    opportunity = %opp,
+                    "🚀 SPATIAL ARBITRAGE DETECTED"
+                );
+                found.push(opp);
+            }
+        }
     }
 
-    // 2. Triangular Arbitrage (scan all paths)
-    for path in triangular_paths {
-        if let Some(opp) = triangular_detector.detect(path).await {
-            info!(
-                opportunity = %opp,
-                "🔺 TRIANGULAR ARBITRAGE DETECTED"
-            );
-            let _ = api_tx.send(ApiMessage::OpportunityFound(opp));
+    // 2. Triangular Arbitrage (paths with a leg on an affected pair)
+    if let Some(detector) = triangular_detector {
+        let paths: Vec<TriangularPath> = triangular_paths
+            .read()
+            .await
+            .active_paths_for(pairs.iter().map(String::as_str))
+            .into_iter()
+            .cloned()
+            .collect();
+        let detector = detector.read().await;
+        for path in &paths {
+            if let Some(opp) = detector.detect(path).await {
+                info!(
+                    id = %opp.id,
+                    opportunity = %opp,
+                    "🔺 TRIANGULAR ARBITRAGE DETECTED"
+                );
+                found.push(opp);
+            }
         }
     }
 
-    // 3. Statistical Arbitrage would be scanned periodically, not on every update
-    // This is handled separately due to the need for historical data
+    for opp in found {
+        let _ = opp_tx.send(opp).await;
+    }
+
+    // 3. Statistical Arbitrage is scanned periodically over the pair universe,
+    // not on every update, due to the need for historical data
 }
 
-fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,solana_price_monitor=debug"));
+/// Install the global subscriber, returning the handle `PUT /admin/log-level`
+/// swaps its filter through
+fn init_tracing(log_level: Option<&str>, to_stderr: bool) -> api::LogFilterHandle {
+    let filter = match log_level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("info,solana_price_monitor=debug")),
+    };
 
+    let (filter, handle) = tracing_subscriber::reload::Layer::new(filter);
     tracing_subscriber::registry()
         .with(filter)
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(move || -> Box<dyn std::io::Write> {
+            if to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        }))
         .init();
+    handle
 }
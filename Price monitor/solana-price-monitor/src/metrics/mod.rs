@@ -0,0 +1,501 @@
+//! Feed and processing metrics
+//!
+//! Splits "the node is slow" from "we are slow": the feed reader counts
+//! messages and bytes as they come off the wire, and the main loop records
+//! how long each account update takes to decode and cache, and the scan
+//! worker how long until that update has been scanned. Counters are atomics,
+//! so recording never blocks the hot path.
+//!
+//! Rates are derived by the reader from two snapshots, so several readers
+//! (the health log, API broadcasts) can each use their own interval.
+//!
+//! `ReceiptLatency` measures how far behind the chain each DEX's updates
+//! arrive: the slot subscription stamps when each slot was first seen, and
+//! an account update for that slot is timed against it when processed.
+
+use serde::Serialize;
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Buckets of `LatencyHistogram`: bucket `i` holds latencies up to 2^i µs,
+/// the last one everything longer (~16.8s and up)
+const BUCKETS: usize = 26;
+
+/// Slots whose observation time is kept for receipt latency (~3.5 minutes)
+const SLOT_HISTORY: usize = 512;
+
+/// `LatencyHistogram::percentile` covers the current and previous window
+const LATENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Log-scale latency histogram in microseconds
+///
+/// Counts are kept since startup, but `percentile` only looks at the last
+/// one to two `LATENCY_WINDOW`s, so a slow spell ages out instead of
+/// weighing on the figures for the rest of the run. Percentiles are
+/// reported as the upper bound of the bucket they fall in, so they are at
+/// most 2x pessimistic.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKETS],
+    /// Moved along by readers, so recording stays lock-free
+    windows: Mutex<Windows>,
+}
+
+/// Bucket counts at the start of the previous and current window
+#[derive(Debug)]
+struct Windows {
+    started: Instant,
+    previous: HistogramCounts,
+    current: HistogramCounts,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            windows: Mutex::new(Windows {
+                started: Instant::now(),
+                previous: HistogramCounts::default(),
+                current: HistogramCounts::default(),
+            }),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, latency: Duration) {
+        let micros = latency.as_micros().max(1) as u64;
+        // Smallest i with micros <= 2^i
+        let bucket = (u64::BITS - (micros - 1).leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Latency in µs below which `quantile` (0-1) of the recent samples
+    /// fall; `None` when nothing was recorded in the last two windows
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        self.percentile_at(quantile, Instant::now())
+    }
+
+    fn percentile_at(&self, quantile: f64, now: Instant) -> Option<u64> {
+        self.recent_at(now).percentile(quantile)
+    }
+
+    /// Samples since the previous window started, rolling the windows
+    /// forward once the current one is over
+    fn recent_at(&self, now: Instant) -> HistogramCounts {
+        let counts = self.counts();
+        let mut windows = self.windows.lock().unwrap();
+        if now.saturating_duration_since(windows.started) >= LATENCY_WINDOW {
+            windows.previous = windows.current;
+            windows.current = counts;
+            windows.started = now;
+        }
+        counts.since(&windows.previous)
+    }
+
+    /// Bucket counts since startup, for percentiles over an interval
+    pub fn counts(&self) -> HistogramCounts {
+        HistogramCounts(std::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed)))
+    }
+}
+
+/// Bucket counts of a `LatencyHistogram` at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramCounts([u64; BUCKETS]);
+
+impl Default for HistogramCounts {
+    fn default() -> Self {
+        Self([0; BUCKETS])
+    }
+}
+
+impl HistogramCounts {
+    pub fn total(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    /// Samples recorded after `earlier`
+    pub fn since(&self, earlier: &HistogramCounts) -> HistogramCounts {
+        HistogramCounts(std::array::from_fn(|i| self.0[i].saturating_sub(earlier.0[i])))
+    }
+
+    /// See `LatencyHistogram::percentile`
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in self.0.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(1 << i);
+            }
+        }
+        Some(1 << (BUCKETS - 1))
+    }
+}
+
+/// Slot-to-receipt latency per DEX
+///
+/// Only measured while slot notifications arrive (`websocket.slot_subscribe`);
+/// updates for slots not seen on the slot feed, or seen too long ago, are
+/// skipped rather than guessed.
+#[derive(Debug, Default)]
+pub struct ReceiptLatency {
+    /// Recent slots, ascending, with the local time (ms) each was first seen
+    slots: Mutex<VecDeque<(u64, u64)>>,
+    by_dex: RwLock<HashMap<String, Arc<LatencyHistogram>>>,
+    /// DEXes currently over the alert bound, as last set by the health check
+    slow: RwLock<Vec<String>>,
+}
+
+impl ReceiptLatency {
+    /// `slot` was announced by the slot feed at `at_ms`; repeats and older
+    /// slots keep their first observation
+    pub fn observe_slot(&self, slot: u64, at_ms: u64) {
+        let mut slots = self.slots.lock().unwrap();
+        if slots.back().is_some_and(|(last, _)| *last >= slot) {
+            return;
+        }
+        slots.push_back((slot, at_ms));
+        if slots.len() > SLOT_HISTORY {
+            slots.pop_front();
+        }
+    }
+
+    /// Time an update from `dex` at `slot`, processed at `at_ms`, against
+    /// the slot's observation; `None` when that slot wasn't observed
+    pub fn observe_update(&self, dex: &str, slot: u64, at_ms: u64) -> Option<Duration> {
+        let observed_ms = {
+            let slots = self.slots.lock().unwrap();
+            let index = slots.binary_search_by_key(&slot, |(s, _)| *s).ok()?;
+            slots[index].1
+        };
+        let latency = Duration::from_millis(at_ms.saturating_sub(observed_ms));
+        self.histogram(dex).record(latency);
+        Some(latency)
+    }
+
+    fn histogram(&self, dex: &str) -> Arc<LatencyHistogram> {
+        if let Some(histogram) = self.by_dex.read().unwrap().get(dex) {
+            return histogram.clone();
+        }
+        self.by_dex.write().unwrap().entry(dex.to_string()).or_default().clone()
+    }
+
+    /// Bucket counts per DEX
+    pub fn counts(&self) -> BTreeMap<String, HistogramCounts> {
+        self.by_dex.read().unwrap().iter().map(|(dex, h)| (dex.clone(), h.counts())).collect()
+    }
+
+    /// Record which DEXes are over the alert bound; true when that changed
+    pub fn set_slow(&self, mut dexes: Vec<String>) -> bool {
+        dexes.sort();
+        let mut slow = self.slow.write().unwrap();
+        if *slow == dexes {
+            return false;
+        }
+        *slow = dexes;
+        true
+    }
+
+    /// DEXes over the alert bound at the last health check
+    pub fn slow(&self) -> Vec<String> {
+        self.slow.read().unwrap().clone()
+    }
+}
+
+/// Slot-to-receipt latency of one DEX over an interval, in µs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ReceiptLatencySummary {
+    pub samples: u64,
+    pub p50_us: Option<u64>,
+    pub p95_us: Option<u64>,
+    pub p99_us: Option<u64>,
+}
+
+impl From<HistogramCounts> for ReceiptLatencySummary {
+    fn from(counts: HistogramCounts) -> Self {
+        Self {
+            samples: counts.total(),
+            p50_us: counts.percentile(0.5),
+            p95_us: counts.percentile(0.95),
+            p99_us: counts.percentile(0.99),
+        }
+    }
+}
+
+/// Counters shared by the feed reader, main loop and scan worker
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    messages_received: AtomicU64,
+    bytes_received: AtomicU64,
+    /// Reconnects forced by the silence watchdog
+    silence_reconnects: AtomicU64,
+    /// Single subscriptions renewed after going quiet
+    stale_resubscribes: AtomicU64,
+    /// Notifications older than one already passed on for the account
+    out_of_order_drops: AtomicU64,
+    /// Pool pubkey -> decoded prices that disagreed with the pool's vault
+    /// balances
+    inconsistent_prices: Mutex<HashMap<String, u64>>,
+    /// Account update to cached price (decode + cache write)
+    processing: LatencyHistogram,
+    /// First cached update of a batch to the end of its scan
+    scan: LatencyHistogram,
+    receipt: ReceiptLatency,
+}
+
+impl PipelineMetrics {
+    /// A frame came off the feed
+    pub fn record_message(&self, bytes: usize) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// The feed went quiet and was reconnected
+    pub fn record_silence(&self) {
+        self.silence_reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// One subscription went quiet and was renewed
+    pub fn record_resubscribe(&self) {
+        self.stale_resubscribes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A notification arrived after a newer one for the same account
+    pub fn record_out_of_order(&self) {
+        self.out_of_order_drops.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A price decoded from `pool` failed the vault consistency check;
+    /// returns the pool's count so far
+    pub fn record_inconsistent_price(&self, pool: &str) -> u64 {
+        let mut counts = self.inconsistent_prices.lock().unwrap();
+        let count = counts.entry(pool.to_string()).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// Inconsistent prices per pool account, since startup
+    pub fn inconsistent_prices(&self) -> BTreeMap<String, u64> {
+        self.inconsistent_prices.lock().unwrap().iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    pub fn record_processing(&self, elapsed: Duration) {
+        self.processing.record(elapsed);
+    }
+
+    pub fn record_scan(&self, elapsed: Duration) {
+        self.scan.record(elapsed);
+    }
+
+    pub fn processing(&self) -> &LatencyHistogram {
+        &self.processing
+    }
+
+    pub fn scan(&self) -> &LatencyHistogram {
+        &self.scan
+    }
+
+    pub fn receipt(&self) -> &ReceiptLatency {
+        &self.receipt
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            taken_at: Instant::now(),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            silence_reconnects: self.silence_reconnects.load(Ordering::Relaxed),
+            stale_resubscribes: self.stale_resubscribes.load(Ordering::Relaxed),
+            out_of_order_drops: self.out_of_order_drops.load(Ordering::Relaxed),
+            inconsistent_prices: self.inconsistent_prices.lock().unwrap().values().sum(),
+            processed: self.processing.count(),
+            processing_p50_us: self.processing.percentile(0.5),
+            processing_p99_us: self.processing.percentile(0.99),
+            scan_p50_us: self.scan.percentile(0.5),
+            scan_p99_us: self.scan.percentile(0.99),
+            receipt: self.receipt.counts(),
+        }
+    }
+}
+
+/// Point-in-time totals and percentiles
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    #[serde(skip)]
+    pub taken_at: Instant,
+    pub messages_received: u64,
+    pub bytes_received: u64,
+    pub silence_reconnects: u64,
+    pub stale_resubscribes: u64,
+    /// Notifications dropped before decoding for arriving out of slot order
+    pub out_of_order_drops: u64,
+    /// Decoded prices that failed the vault consistency check, all pools
+    pub inconsistent_prices: u64,
+    /// Account updates decoded and cached
+    pub processed: u64,
+    /// Percentiles over the last one to two minutes
+    pub processing_p50_us: Option<u64>,
+    pub processing_p99_us: Option<u64>,
+    pub scan_p50_us: Option<u64>,
+    pub scan_p99_us: Option<u64>,
+    /// Slot-to-receipt histograms per DEX; see `receipt_latency_since`
+    #[serde(skip)]
+    pub receipt: BTreeMap<String, HistogramCounts>,
+}
+
+impl MetricsSnapshot {
+    /// Messages and bytes per second between `earlier` and this snapshot
+    pub fn rates_since(&self, earlier: &MetricsSnapshot) -> FeedRates {
+        let secs = self.taken_at.duration_since(earlier.taken_at).as_secs_f64();
+        if secs <= 0.0 {
+            return FeedRates::default();
+        }
+        FeedRates {
+            messages_per_sec: self.messages_received.saturating_sub(earlier.messages_received) as f64 / secs,
+            bytes_per_sec: self.bytes_received.saturating_sub(earlier.bytes_received) as f64 / secs,
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Slot-to-receipt latency per DEX between `earlier` and this snapshot
+    pub fn receipt_latency_since(&self, earlier: &MetricsSnapshot) -> BTreeMap<String, ReceiptLatencySummary> {
+        self.receipt
+            .iter()
+            .map(|(dex, counts)| {
+                let interval = earlier.receipt.get(dex).map_or(*counts, |before| counts.since(before));
+                (dex.clone(), interval.into())
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct FeedRates {
+    pub messages_per_sec: f64,
+    pub bytes_per_sec: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(0.5), None);
+
+        // 98 fast samples and two slow outliers
+        for _ in 0..98 {
+            histogram.record(Duration::from_micros(100));
+        }
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_secs(60));
+
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.percentile(0.5), Some(128));
+        assert_eq!(histogram.counts().percentile(0.5), Some(128));
+        assert_eq!(histogram.percentile(0.99), Some(8192));
+        assert_eq!(histogram.percentile(1.0), Some(1 << (BUCKETS - 1)));
+        // Exact powers of two land in their own bucket
+        let exact = LatencyHistogram::default();
+        exact.record(Duration::from_micros(64));
+        assert_eq!(exact.percentile(0.5), Some(64));
+    }
+
+    #[test]
+    fn test_slow_samples_age_out_of_percentiles() {
+        let histogram = LatencyHistogram::default();
+        let start = histogram.windows.lock().unwrap().started;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        for _ in 0..10 {
+            histogram.record(Duration::from_secs(2));
+        }
+        assert_eq!(histogram.percentile_at(0.5, at(30)), Some(1 << 21));
+        // Still counted through the following window
+        assert_eq!(histogram.percentile_at(0.5, at(60)), Some(1 << 21));
+        for _ in 0..10 {
+            histogram.record(Duration::from_micros(100));
+        }
+        assert_eq!(histogram.percentile_at(0.99, at(90)), Some(1 << 21));
+
+        // Two windows on, only the fast samples are left
+        assert_eq!(histogram.percentile_at(0.99, at(120)), Some(128));
+        assert_eq!(histogram.percentile_at(0.99, at(180)), None);
+        // Lifetime counts keep everything
+        assert_eq!(histogram.count(), 20);
+        assert_eq!(histogram.counts().percentile(0.99), Some(1 << 21));
+    }
+
+    #[test]
+    fn test_receipt_latency_is_timed_from_slot_observation() {
+        let metrics = PipelineMetrics::default();
+        let receipt = metrics.receipt();
+        assert_eq!(receipt.observe_update("orca", 100, 1_000), None);
+
+        receipt.observe_slot(100, 1_000);
+        receipt.observe_slot(101, 1_400);
+        // A repeated slot keeps its first observation
+        receipt.observe_slot(101, 1_900);
+        let earlier = metrics.snapshot();
+
+        assert_eq!(receipt.observe_update("orca", 100, 1_050), Some(Duration::from_millis(50)));
+        assert_eq!(receipt.observe_update("raydium", 101, 2_000), Some(Duration::from_millis(600)));
+        // Processed before the slot feed announced it
+        assert_eq!(receipt.observe_update("raydium", 100, 900), Some(Duration::ZERO));
+        assert_eq!(receipt.observe_update("meteora", 102, 2_000), None);
+
+        for slot in 102..102 + SLOT_HISTORY as u64 {
+            receipt.observe_slot(slot, 2_000);
+        }
+        assert_eq!(receipt.observe_update("orca", 100, 5_000), None);
+
+        let latency = metrics.snapshot().receipt_latency_since(&earlier);
+        assert_eq!(latency.keys().collect::<Vec<_>>(), ["orca", "raydium"]);
+        // 50ms falls in the 2^16µs (65.5ms) bucket
+        assert_eq!((latency["orca"].samples, latency["orca"].p95_us), (1, Some(1 << 16)));
+        assert_eq!((latency["raydium"].samples, latency["raydium"].p95_us), (2, Some(1 << 20)));
+        assert!(metrics.snapshot().receipt_latency_since(&metrics.snapshot())["orca"].p95_us.is_none());
+    }
+
+    #[test]
+    fn test_rates_between_snapshots() {
+        let metrics = PipelineMetrics::default();
+        let mut earlier = metrics.snapshot();
+        for _ in 0..50 {
+            metrics.record_message(200);
+        }
+        let mut later = metrics.snapshot();
+        later.taken_at = earlier.taken_at + Duration::from_secs(2);
+
+        let rates = later.rates_since(&earlier);
+        assert_eq!(rates, FeedRates { messages_per_sec: 25.0, bytes_per_sec: 5000.0 });
+        // Snapshots out of order don't produce garbage
+        earlier.taken_at = later.taken_at;
+        assert_eq!(later.rates_since(&earlier), FeedRates::default());
+    }
+
+    #[test]
+    fn test_inconsistent_prices_per_pool() {
+        let metrics = PipelineMetrics::default();
+        assert_eq!(metrics.record_inconsistent_price("PoolA"), 1);
+        assert_eq!(metrics.record_inconsistent_price("PoolA"), 2);
+        assert_eq!(metrics.record_inconsistent_price("PoolB"), 1);
+
+        assert_eq!(metrics.inconsistent_prices(), BTreeMap::from([("PoolA".to_string(), 2), ("PoolB".to_string(), 1)]));
+        assert_eq!(metrics.snapshot().inconsistent_prices, 3);
+    }
+}
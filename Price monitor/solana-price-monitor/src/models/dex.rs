@@ -0,0 +1,180 @@
+//! DEX identifiers
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A DEX (or one of its pool programs) prices are quoted on
+///
+/// Names are normalized when parsed, so "Raydium " and "raydium" are the
+/// same venue, and serialize as the lowercase name ("raydium_clmm").
+/// Unrecognized names are kept as `Other` rather than rejected, so a DEX
+/// added to the config or a newer record still round-trips.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Dex {
+    Raydium,
+    RaydiumClmm,
+    Orca,
+    OrcaLegacy,
+    Meteora,
+    MeteoraAmm,
+    /// Any other DEX, by normalized name
+    Other(String),
+}
+
+impl Dex {
+    /// Every DEX with its own variant
+    pub const KNOWN: [Dex; 6] = [Dex::Raydium, Dex::RaydiumClmm, Dex::Orca, Dex::OrcaLegacy, Dex::Meteora, Dex::MeteoraAmm];
+
+    /// Normalized name, as used in config keys, cache keys and JSON
+    pub fn as_str(&self) -> &str {
+        match self {
+            Dex::Raydium => "raydium",
+            Dex::RaydiumClmm => "raydium_clmm",
+            Dex::Orca => "orca",
+            Dex::OrcaLegacy => "orca_legacy",
+            Dex::Meteora => "meteora",
+            Dex::MeteoraAmm => "meteora_amm",
+            Dex::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for Dex {
+    /// Trims, lowercases and treats `-` and spaces as `_`; only names
+    /// without a variant allocate
+    fn from(name: &str) -> Self {
+        let name = name.trim();
+        match Dex::KNOWN.iter().find(|dex| normalizes_to(name, dex.as_str())) {
+            Some(known) => known.clone(),
+            None => Dex::Other(name.to_lowercase().replace(['-', ' '], "_")),
+        }
+    }
+}
+
+/// Whether `name` normalizes to the (ASCII, normalized) `canonical` name
+fn normalizes_to(name: &str, canonical: &str) -> bool {
+    name.len() == canonical.len()
+        && name.bytes().zip(canonical.bytes()).all(|(byte, expected)| match byte {
+            b'-' | b' ' => expected == b'_',
+            _ => byte.to_ascii_lowercase() == expected,
+        })
+}
+
+impl From<&Dex> for Dex {
+    fn from(dex: &Dex) -> Self {
+        dex.clone()
+    }
+}
+
+impl From<&String> for Dex {
+    fn from(name: &String) -> Self {
+        Dex::from(name.as_str())
+    }
+}
+
+impl From<String> for Dex {
+    fn from(name: String) -> Self {
+        Dex::from(name.as_str())
+    }
+}
+
+impl From<Dex> for String {
+    fn from(dex: Dex) -> Self {
+        match dex {
+            Dex::Other(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl FromStr for Dex {
+    type Err = std::convert::Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(Dex::from(name))
+    }
+}
+
+impl fmt::Display for Dex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// By name, so maps keyed by `Dex` list venues alphabetically
+impl Ord for Dex {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialOrd for Dex {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for Dex {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Dex {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl JsonSchema for Dex {
+    fn schema_name() -> String {
+        "Dex".to_string()
+    }
+
+    /// A string rather than an enum, since unknown names are accepted
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some("Normalized DEX name; names other than the examples are kept as given".to_string()),
+                examples: Dex::KNOWN.iter().map(|dex| dex.as_str().into()).collect(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_normalizes_names() {
+        assert_eq!("Raydium ".parse::<Dex>().unwrap(), Dex::Raydium);
+        assert_eq!(Dex::from("raydium-clmm"), Dex::RaydiumClmm);
+        assert_eq!(Dex::from(" ORCA legacy"), Dex::OrcaLegacy);
+        assert_eq!(Dex::from("Meteora_AMM"), Dex::MeteoraAmm);
+        assert_eq!(Dex::from(" Phoenix"), Dex::Other("phoenix".to_string()));
+        assert_eq!(Dex::from("Lifinity V2"), Dex::Other("lifinity_v2".to_string()));
+        assert_eq!(Dex::from("orcas"), Dex::Other("orcas".to_string()));
+        assert_eq!(Dex::Other("phoenix".to_string()), "phoenix");
+    }
+
+    #[test]
+    fn test_serializes_as_lowercase_name() {
+        for dex in Dex::KNOWN.into_iter().chain([Dex::Other("phoenix".to_string())]) {
+            let json = serde_json::to_value(&dex).unwrap();
+            assert_eq!(json, dex.as_str());
+            assert_eq!(serde_json::from_value::<Dex>(json).unwrap(), dex);
+        }
+        assert_eq!(serde_json::to_string(&Dex::RaydiumClmm).unwrap(), r#""raydium_clmm""#);
+        assert_eq!(serde_json::from_str::<Dex>(r#""Orca""#).unwrap(), Dex::Orca);
+    }
+}
@@ -1,7 +1,19 @@
 //! Data models for the price monitoring system
 
+mod dex;
 mod price;
 mod opportunity;
+mod opportunity_set;
+mod ranking;
+mod spread;
+pub mod timestamp;
 
-pub use price::PriceData;
-pub use opportunity::{Opportunity, OpportunityType};
+pub use dex::Dex;
+pub use price::{PriceData, PriceInconsistency, PriceSource, DEFAULT_CONSISTENCY_TOLERANCE_PERCENT};
+pub use timestamp::TimestampFormat;
+pub use opportunity_set::{OpportunitySet, VenueFreshness};
+pub use ranking::{ByProfitPct, EvKey, Score, TopK};
+pub use spread::{Spread, SpreadVenue};
+pub use opportunity::{
+    LegSide, Opportunity, OpportunityBuilder, OpportunityError, OpportunityType, RouteLeg, VolatilityRegime,
+};
@@ -2,21 +2,186 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use uuid::Uuid;
+
+use super::{timestamp, Dex, PriceData};
+
+mod builder;
+
+pub use builder::{OpportunityBuilder, OpportunityError};
 
 /// Type of arbitrage opportunity
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as its snake_case name ("spatial", "four_leg_cycle"); the
+/// capitalized names written by earlier versions ("Spatial") still parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, JsonSchema)]
 pub enum OpportunityType {
     /// Price difference between two DEXs for same pair
+    #[serde(rename = "spatial")]
     Spatial,
     /// Mean reversion based on statistical analysis
+    #[serde(rename = "statistical")]
     Statistical,
     /// Circular path through three tokens
+    #[serde(rename = "triangular")]
     Triangular,
+    /// Cross-DEX spread past an alert threshold, reported whether or not it
+    /// clears costs
+    #[serde(rename = "spread_alert")]
+    SpreadAlert,
+    /// Spatial trade whose buy and sell pairs are bridged through a third
+    /// token (e.g., buy SOL-USDC, sell SOL-USDT)
+    #[serde(rename = "bridged_spatial")]
+    BridgedSpatial,
+    /// Three-token cycle with legs on different DEXs
+    #[serde(rename = "cross_dex_triangular")]
+    CrossDexTriangular,
+    /// Circular path through four tokens
+    #[serde(rename = "four_leg_cycle")]
+    FourLegCycle,
+}
+
+impl OpportunityType {
+    pub const ALL: [OpportunityType; 7] = [
+        OpportunityType::Spatial,
+        OpportunityType::Statistical,
+        OpportunityType::Triangular,
+        OpportunityType::SpreadAlert,
+        OpportunityType::BridgedSpatial,
+        OpportunityType::CrossDexTriangular,
+        OpportunityType::FourLegCycle,
+    ];
+
+    /// Name in JSON, query parameters, topics and config
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OpportunityType::Spatial => "spatial",
+            OpportunityType::Statistical => "statistical",
+            OpportunityType::Triangular => "triangular",
+            OpportunityType::SpreadAlert => "spread_alert",
+            OpportunityType::BridgedSpatial => "bridged_spatial",
+            OpportunityType::CrossDexTriangular => "cross_dex_triangular",
+            OpportunityType::FourLegCycle => "four_leg_cycle",
+        }
+    }
+
+    /// Two legs on one pair, or on two pairs bridged through a third token
+    pub fn is_spatial(self) -> bool {
+        matches!(self, OpportunityType::Spatial | OpportunityType::BridgedSpatial)
+    }
+
+    /// A route back to the token it started from
+    pub fn is_cycle(self) -> bool {
+        matches!(
+            self,
+            OpportunityType::Triangular | OpportunityType::CrossDexTriangular | OpportunityType::FourLegCycle
+        )
+    }
+}
+
+impl std::fmt::Display for OpportunityType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for OpportunityType {
+    type Err = anyhow::Error;
+
+    /// Case-insensitive, accepting the snake_case names as well as the
+    /// legacy capitalized ones ("CrossDexTriangular")
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let folded = name.trim().replace('_', "");
+        OpportunityType::ALL
+            .into_iter()
+            .find(|t| t.as_str().replace('_', "").eq_ignore_ascii_case(&folded))
+            .ok_or_else(|| {
+                let expected: Vec<&str> = OpportunityType::ALL.iter().map(|t| t.as_str()).collect();
+                anyhow::anyhow!("unknown opportunity type \"{}\" (expected one of {})", name, expected.join(", "))
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for OpportunityType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Realized-volatility regime of the pairs an opportunity trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VolatilityRegime {
+    Low,
+    Normal,
+    High,
+}
+
+/// Direction of a route leg, relative to the base token of its pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LegSide {
+    Buy,
+    Sell,
+}
+
+/// One swap of an opportunity's route
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RouteLeg {
+    /// Pair the swap trades (e.g., "SOL-USDC")
+    pub pair: String,
+    pub dex: Dex,
+    pub side: LegSide,
+    /// Price the leg was evaluated at: the ask or bid on orderbook venues,
+    /// the pool price on AMMs
+    pub price: f64,
+    pub fee_rate: f64,
+    /// Pool account quoting the price, when known
+    #[serde(default)]
+    pub pool_pubkey: Option<String>,
+    /// Slot of the price
+    pub slot: u64,
+}
+
+impl RouteLeg {
+    /// A leg priced at the side of the book it trades against
+    pub fn new(pair: &str, dex: impl Into<Dex>, side: LegSide, data: &PriceData) -> Self {
+        Self {
+            pair: pair.to_string(),
+            dex: dex.into(),
+            side,
+            price: match side {
+                LegSide::Buy => data.buy_price(),
+                LegSide::Sell => data.sell_price(),
+            },
+            fee_rate: data.fee_rate,
+            pool_pubkey: data.pool_pubkey.clone(),
+            slot: data.slot,
+        }
+    }
+}
+
+impl std::fmt::Display for RouteLeg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = match self.side {
+            LegSide::Buy => "buy",
+            LegSide::Sell => "sell",
+        };
+        // Shortest exact form, so sub-cent prices like BONK's stay readable
+        write!(f, "{} {} on {} @ {}", side, self.pair, self.dex, self.price)
+    }
 }
 
 /// Represents a detected arbitrage opportunity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Opportunity {
+    /// Unique per emission, correlating the opportunity across messages,
+    /// logs and storage (nil in records written before ids existed)
+    #[serde(default)]
+    pub id: Uuid,
+
     /// Type of arbitrage
     pub opportunity_type: OpportunityType,
 
@@ -24,10 +189,10 @@ pub struct Opportunity {
     pub token_pair: String,
 
     /// DEX to buy from (lower price)
-    pub buy_dex: String,
+    pub buy_dex: Dex,
 
     /// DEX to sell on (higher price)
-    pub sell_dex: String,
+    pub sell_dex: Dex,
 
     /// Price on buy DEX
     pub buy_price: f64,
@@ -35,17 +200,89 @@ pub struct Opportunity {
     /// Price on sell DEX
     pub sell_price: f64,
 
+    /// Slot of the price quoted on the buy side
+    #[serde(default)]
+    pub buy_slot: u64,
+
+    /// Slot of the price quoted on the sell side
+    #[serde(default)]
+    pub sell_slot: u64,
+
+    /// Swaps to execute, in order; the buy/sell fields above summarize them
+    /// for single-pair routes
+    #[serde(default)]
+    pub legs: Vec<RouteLeg>,
+
     /// Net profit after all costs (percentage)
     pub net_profit_percent: f64,
 
-    /// Recommended trade size in base units
+    /// Recommended trade size in base units of `size_token`
     pub recommended_size: u64,
 
+    /// Token the size is denominated in; this and the other amount fields
+    /// are empty when a token's decimals aren't in `[tokens]`
+    #[serde(default)]
+    pub size_token: String,
+
+    /// Decimals of `size_token`
+    #[serde(default)]
+    pub size_decimals: u8,
+
+    /// Net profit at the recommended size in base units of `profit_token`
+    #[serde(default)]
+    pub expected_profit_base_units: u64,
+
+    /// Token the profit is realized in: the quote token of a single-pair
+    /// route, the start token of a cycle
+    #[serde(default)]
+    pub profit_token: String,
+
+    /// Decimals of `profit_token`
+    #[serde(default)]
+    pub profit_decimals: u8,
+
+    /// Recommended size in whole tokens, like "12.5 SOL"
+    #[serde(default)]
+    pub size_amount: String,
+
+    /// Expected profit in whole tokens, like "0.11 USDC"
+    #[serde(default)]
+    pub expected_profit_amount: String,
+
     /// Confidence score (0.0 - 1.0)
     pub confidence: f64,
 
+    /// Estimated price impact per leg at the recommended size (percentage)
+    #[serde(default)]
+    pub leg_slippage_percent: Vec<f64>,
+
+    /// Slippage already deducted from `net_profit_percent`, all legs
+    /// together (percentage)
+    #[serde(default)]
+    pub assumed_slippage_pct: f64,
+
+    /// Net profit if every leg moves against the trade by its pair's
+    /// realized volatility before execution (percentage, at most
+    /// `net_profit_percent`; 0 in records written before it existed)
+    #[serde(default)]
+    pub worst_case_profit_percent: f64,
+
+    /// Consecutive slots the spread persisted before being emitted
+    #[serde(default)]
+    pub persisted_slots: u32,
+
+    /// Volatility regime the detection thresholds were scaled for
+    #[serde(default)]
+    pub volatility_regime: Option<VolatilityRegime>,
+
     /// When the opportunity was detected
+    #[serde(serialize_with = "timestamp::serialize", deserialize_with = "timestamp::deserialize")]
     pub detected_at: DateTime<Utc>,
+
+    /// Until when the prices behind it can be trusted: the configured max
+    /// age, or sooner when volatility would erase the profit first
+    #[serde(default, serialize_with = "timestamp::serialize", deserialize_with = "timestamp::deserialize")]
+    pub valid_until: DateTime<Utc>,
 }
 
 impl Opportunity {
@@ -63,18 +300,95 @@ impl Opportunity {
         age.num_milliseconds() as u64 <= max_age_ms
     }
 
+    /// Whether `now` is past `valid_until`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        now > self.valid_until
+    }
+
+    /// Pairs the route trades: its legs' pairs, or `token_pair` for
+    /// opportunities without legs
+    pub fn pairs(&self) -> Vec<&str> {
+        if self.legs.is_empty() {
+            return vec![&self.token_pair];
+        }
+        let mut pairs: Vec<&str> = Vec::with_capacity(self.legs.len());
+        for leg in &self.legs {
+            if !pairs.contains(&leg.pair.as_str()) {
+                pairs.push(&leg.pair);
+            }
+        }
+        pairs
+    }
+
+    /// Set the amount fields for a size in `size_token`, at `size_price`
+    /// profit tokens per size token (1.0 when they're the same token)
+    pub fn denominate(
+        &mut self,
+        size_token: &str,
+        size_decimals: u8,
+        profit_token: &str,
+        profit_decimals: u8,
+        size_price: f64,
+    ) {
+        let profit = self.profit_at(size_decimals, size_price);
+        // `as` saturates, so a negative or non-finite estimate reads as zero
+        let profit_base_units = (profit * 10f64.powi(profit_decimals as i32)).round() as u64;
+
+        self.size_token = size_token.to_string();
+        self.size_decimals = size_decimals;
+        self.expected_profit_base_units = profit_base_units;
+        self.profit_token = profit_token.to_string();
+        self.profit_decimals = profit_decimals;
+        self.size_amount = format!("{} {}", format_base_units(self.recommended_size, size_decimals), size_token);
+        self.expected_profit_amount =
+            format!("{} {}", format_base_units(profit_base_units, profit_decimals), profit_token);
+    }
+
+    /// Net profit at the recommended size, in whatever `size_price` quotes
+    /// one size token in, for a size token with `size_decimals`
+    pub fn profit_at(&self, size_decimals: u8, size_price: f64) -> f64 {
+        let size = self.recommended_size as f64 / 10f64.powi(size_decimals as i32);
+        size * size_price * self.net_profit_percent / 100.0
+    }
+
+    /// Legs in execution order, like `sell A-B on orca @ 1.02 -> sell B-C on orca @ 0.99`
+    pub fn route(&self) -> String {
+        self.legs.iter().map(RouteLeg::to_string).collect::<Vec<_>>().join(" -> ")
+    }
+
     /// Get a human-readable summary
     pub fn summary(&self) -> String {
-        format!(
-            "{:?}: {} | Buy {} @ {:.4} -> Sell {} @ {:.4} | Net: {:.2}%",
-            self.opportunity_type,
-            self.token_pair,
-            self.buy_dex,
-            self.buy_price,
-            self.sell_dex,
-            self.sell_price,
-            self.net_profit_percent
-        )
+        // Buy/sell fields tell a single-pair route; others are spelled out leg by leg
+        let route = if self.legs.iter().all(|leg| leg.pair == self.token_pair) {
+            format!(
+                "Buy {} @ {:.4} -> Sell {} @ {:.4}",
+                self.buy_dex, self.buy_price, self.sell_dex, self.sell_price
+            )
+        } else {
+            self.route()
+        };
+        let mut summary = format!(
+            "{:?}: {} | {} | Net: {:.2}%",
+            self.opportunity_type, self.token_pair, route, self.net_profit_percent
+        );
+        if !self.size_token.is_empty() {
+            summary.push_str(&format!(" | Size: {} | Profit: {}", self.size_amount, self.expected_profit_amount));
+        }
+        summary
+    }
+}
+
+/// `amount` base units as a decimal with `decimals` places, trailing zeros
+/// trimmed (12_500_000_000 with 9 decimals is "12.5")
+fn format_base_units(amount: u64, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
     }
 }
 
@@ -90,19 +404,136 @@ mod tests {
 
     #[test]
     fn test_gross_profit_calculation() {
-        let opp = Opportunity {
-            opportunity_type: OpportunityType::Spatial,
-            token_pair: "SOL-USDC".to_string(),
-            buy_dex: "raydium".to_string(),
-            sell_dex: "orca".to_string(),
-            buy_price: 100.0,
-            sell_price: 101.0,
-            net_profit_percent: 0.5,
-            recommended_size: 1000,
-            confidence: 0.85,
-            detected_at: Utc::now(),
-        };
+        let opp = Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 10)
+            .sell("orca", 101.0, 11)
+            .net_profit_percent(0.5)
+            .recommended_size(1000)
+            .confidence(0.85)
+            .build()
+            .unwrap();
 
         assert!((opp.gross_profit_percent() - 1.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_opportunity_types_round_trip() {
+        for opportunity_type in OpportunityType::ALL {
+            let json = serde_json::to_value(opportunity_type).unwrap();
+            assert_eq!(json, opportunity_type.as_str());
+            assert_eq!(serde_json::from_value::<OpportunityType>(json).unwrap(), opportunity_type);
+            assert_eq!(opportunity_type.to_string().parse::<OpportunityType>().unwrap(), opportunity_type);
+        }
+        assert_eq!(serde_json::to_string(&OpportunityType::FourLegCycle).unwrap(), r#""four_leg_cycle""#);
+
+        // Names written before snake_case, and any casing
+        assert_eq!("CrossDexTriangular".parse::<OpportunityType>().unwrap(), OpportunityType::CrossDexTriangular);
+        assert_eq!("Spread_Alert".parse::<OpportunityType>().unwrap(), OpportunityType::SpreadAlert);
+        let error = serde_json::from_str::<OpportunityType>(r#""pentagonal""#).unwrap_err().to_string();
+        assert!(error.contains("\"pentagonal\"") && error.contains("four_leg_cycle"), "{}", error);
+    }
+
+    #[test]
+    fn test_records_without_ids_or_slots_still_deserialize() {
+        let json = r#"{
+            "opportunity_type": "Spatial",
+            "token_pair": "SOL-USDC",
+            "buy_dex": "raydium",
+            "sell_dex": "orca",
+            "buy_price": 100.0,
+            "sell_price": 101.0,
+            "net_profit_percent": 0.5,
+            "recommended_size": 1000,
+            "confidence": 0.85,
+            "detected_at": "2026-01-01T00:00:00Z"
+        }"#;
+        let opp: Opportunity = serde_json::from_str(json).unwrap();
+        assert_eq!(opp.opportunity_type, OpportunityType::Spatial);
+        assert!(opp.id.is_nil());
+        assert_eq!((opp.buy_slot, opp.sell_slot), (0, 0));
+        assert!(opp.legs.is_empty());
+        assert_eq!(opp.worst_case_profit_percent, 0.0);
+        assert_eq!(opp.valid_until, DateTime::<Utc>::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_triangular_route_round_trips() {
+        let leg = |pair: &str, price, slot| RouteLeg {
+            pair: pair.to_string(),
+            dex: Dex::Orca,
+            side: LegSide::Sell,
+            price,
+            fee_rate: 0.003,
+            pool_pubkey: Some(format!("{}-pool", pair)),
+            slot,
+        };
+        let opp = Opportunity::builder()
+            .opportunity_type(OpportunityType::Triangular)
+            .token_pair("SOL->USDC->BONK->SOL")
+            .buy("orca", 1.0, 7)
+            .sell("orca", 1.012, 8)
+            .legs(vec![leg("SOL-USDC", 150.0, 7), leg("USDC-BONK", 50_000.0, 8), leg("BONK-SOL", 0.000000135, 8)])
+            .net_profit_percent(0.4)
+            .recommended_size(1000)
+            .confidence(0.7)
+            .leg_slippage_percent(vec![0.01, 0.02, 0.01])
+            .assumed_slippage_pct(0.04)
+            .worst_case_profit_percent(0.1)
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_value(&opp).unwrap();
+        assert_eq!(json["legs"][1]["side"], "sell");
+        assert_eq!(json["legs"][1]["pool_pubkey"], "USDC-BONK-pool");
+        let back: Opportunity = serde_json::from_value(json).unwrap();
+        assert_eq!(back.legs, opp.legs);
+        assert_eq!(back.id, opp.id);
+        assert_eq!(
+            opp.summary(),
+            "Triangular: SOL->USDC->BONK->SOL | sell SOL-USDC on orca @ 150 -> \
+             sell USDC-BONK on orca @ 50000 -> sell BONK-SOL on orca @ 0.000000135 | Net: 0.40%"
+        );
+    }
+
+    #[test]
+    fn test_amounts_scale_by_token_decimals() {
+        let mut opp: Opportunity = serde_json::from_value(serde_json::json!({
+            "opportunity_type": "Spatial",
+            "token_pair": "SOL-USDC",
+            "buy_dex": "raydium",
+            "sell_dex": "orca",
+            "buy_price": 100.0,
+            "sell_price": 101.0,
+            "net_profit_percent": 0.088,
+            "recommended_size": 12_500_000_000u64,
+            "confidence": 0.85,
+            "detected_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        assert!(opp.size_token.is_empty() && opp.expected_profit_base_units == 0);
+
+        // 12.5 SOL (9 decimals) bought at $100 and 0.088% net is 1.1 USDC (6 decimals)
+        opp.denominate("SOL", 9, "USDC", 6, 100.0);
+        assert_eq!(opp.expected_profit_base_units, 1_100_000);
+        assert_eq!((opp.size_amount.as_str(), opp.expected_profit_amount.as_str()), ("12.5 SOL", "1.1 USDC"));
+        assert!(opp.summary().ends_with("| Net: 0.09% | Size: 12.5 SOL | Profit: 1.1 USDC"), "{}", opp.summary());
+
+        // A 6-decimal cycle token: 2.5 USDC at 0.4% returns 0.01 USDC
+        opp.recommended_size = 2_500_000;
+        opp.net_profit_percent = 0.4;
+        opp.denominate("USDC", 6, "USDC", 6, 1.0);
+        assert_eq!(opp.expected_profit_base_units, 10_000);
+        assert_eq!((opp.size_amount.as_str(), opp.expected_profit_amount.as_str()), ("2.5 USDC", "0.01 USDC"));
+
+        // Losses don't wrap around
+        opp.net_profit_percent = -0.2;
+        opp.denominate("USDC", 6, "USDC", 6, 1.0);
+        assert_eq!(opp.expected_profit_base_units, 0);
+
+        assert_eq!(format_base_units(5, 9), "0.000000005");
+        assert_eq!(format_base_units(42, 0), "42");
+        assert_eq!(format_base_units(3_000_000, 6), "3");
+    }
 }
@@ -0,0 +1,427 @@
+//! Validated construction of `Opportunity`
+//!
+//! Detectors fill in what they measured and `build` checks the result is
+//! self-consistent before it is published:
+//!
+//! ```
+//! use solana_price_monitor::models::{Opportunity, OpportunityType};
+//!
+//! let opportunity = Opportunity::builder()
+//!     .opportunity_type(OpportunityType::Spatial)
+//!     .token_pair("SOL-USDC")
+//!     .buy("raydium", 100.0, 10)
+//!     .sell("orca", 101.0, 10)
+//!     .net_profit_percent(0.4)
+//!     .recommended_size(1_000_000_000)
+//!     .confidence(1.3)
+//!     .build()?;
+//!
+//! assert_eq!(opportunity.confidence, 1.0);
+//! # Ok::<(), solana_price_monitor::models::OpportunityError>(())
+//! ```
+
+use super::*;
+
+/// Why `OpportunityBuilder::build` rejected an opportunity
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OpportunityError {
+    #[error("opportunity is missing {0}")]
+    Missing(&'static str),
+
+    #[error("{field} is not finite ({value})")]
+    NonFinite { field: &'static str, value: f64 },
+
+    #[error("{field} is negative ({value})")]
+    Negative { field: &'static str, value: f64 },
+
+    #[error("sell price {sell} is not above buy price {buy}, yet net profit is {net_profit_percent}%")]
+    InvertedPrices { buy: f64, sell: f64, net_profit_percent: f64 },
+
+    #[error("{side} leg {field} doesn't match the opportunity's {side} {field}")]
+    LegMismatch { side: &'static str, field: &'static str },
+
+    #[error("worst-case profit {worst_case}% is above the expected {expected}%")]
+    WorstCaseAboveExpected { worst_case: f64, expected: f64 },
+
+    #[error("valid_until {valid_until} is before detected_at {detected_at}")]
+    ExpiresBeforeDetection { detected_at: DateTime<Utc>, valid_until: DateTime<Utc> },
+}
+
+/// Fluent constructor for `Opportunity`, see the module docs
+///
+/// `id` and `detected_at` default to a fresh id and now, and
+/// `persisted_slots` to 1. Without a risk assessment, assumed slippage is
+/// the sum of `leg_slippage_percent`, worst-case profit the expected profit
+/// and `valid_until` `DEFAULT_VALIDITY` after detection. The token amount
+/// fields are left for `Opportunity::denominate`.
+#[derive(Debug, Clone, Default)]
+pub struct OpportunityBuilder {
+    id: Option<Uuid>,
+    opportunity_type: Option<OpportunityType>,
+    token_pair: Option<String>,
+    buy: Option<(Dex, f64, u64)>,
+    sell: Option<(Dex, f64, u64)>,
+    legs: Vec<RouteLeg>,
+    net_profit_percent: Option<f64>,
+    recommended_size: u64,
+    confidence: Option<f64>,
+    leg_slippage_percent: Vec<f64>,
+    assumed_slippage_pct: Option<f64>,
+    worst_case_profit_percent: Option<f64>,
+    persisted_slots: Option<u32>,
+    volatility_regime: Option<VolatilityRegime>,
+    detected_at: Option<DateTime<Utc>>,
+    valid_until: Option<DateTime<Utc>>,
+}
+
+/// How long after detection an opportunity built without `valid_until` stays
+/// valid (`[arbitrage] max_opportunity_age_ms` by default)
+pub const DEFAULT_VALIDITY: chrono::Duration = chrono::Duration::milliseconds(2000);
+
+impl Opportunity {
+    pub fn builder() -> OpportunityBuilder {
+        OpportunityBuilder::default()
+    }
+}
+
+impl OpportunityBuilder {
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn opportunity_type(mut self, opportunity_type: OpportunityType) -> Self {
+        self.opportunity_type = Some(opportunity_type);
+        self
+    }
+
+    pub fn token_pair(mut self, token_pair: impl Into<String>) -> Self {
+        self.token_pair = Some(token_pair.into());
+        self
+    }
+
+    /// Where the route buys: DEX, price and the slot it was quoted at
+    pub fn buy(mut self, dex: impl Into<Dex>, price: f64, slot: u64) -> Self {
+        self.buy = Some((dex.into(), price, slot));
+        self
+    }
+
+    /// Where the route sells: DEX, price and the slot it was quoted at
+    pub fn sell(mut self, dex: impl Into<Dex>, price: f64, slot: u64) -> Self {
+        self.sell = Some((dex.into(), price, slot));
+        self
+    }
+
+    pub fn legs(mut self, legs: Vec<RouteLeg>) -> Self {
+        self.legs = legs;
+        self
+    }
+
+    pub fn net_profit_percent(mut self, net_profit_percent: f64) -> Self {
+        self.net_profit_percent = Some(net_profit_percent);
+        self
+    }
+
+    pub fn recommended_size(mut self, recommended_size: u64) -> Self {
+        self.recommended_size = recommended_size;
+        self
+    }
+
+    /// Confidence score, clamped to 0.0 - 1.0 by `build`
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    pub fn leg_slippage_percent(mut self, leg_slippage_percent: Vec<f64>) -> Self {
+        self.leg_slippage_percent = leg_slippage_percent;
+        self
+    }
+
+    /// Slippage deducted from the net profit, all legs together
+    pub fn assumed_slippage_pct(mut self, assumed_slippage_pct: f64) -> Self {
+        self.assumed_slippage_pct = Some(assumed_slippage_pct);
+        self
+    }
+
+    /// Net profit after adverse moves before execution, at most the net profit
+    pub fn worst_case_profit_percent(mut self, worst_case_profit_percent: f64) -> Self {
+        self.worst_case_profit_percent = Some(worst_case_profit_percent);
+        self
+    }
+
+    pub fn persisted_slots(mut self, persisted_slots: u32) -> Self {
+        self.persisted_slots = Some(persisted_slots);
+        self
+    }
+
+    pub fn volatility_regime(mut self, volatility_regime: Option<VolatilityRegime>) -> Self {
+        self.volatility_regime = volatility_regime;
+        self
+    }
+
+    pub fn detected_at(mut self, detected_at: DateTime<Utc>) -> Self {
+        self.detected_at = Some(detected_at);
+        self
+    }
+
+    pub fn valid_until(mut self, valid_until: DateTime<Utc>) -> Self {
+        self.valid_until = Some(valid_until);
+        self
+    }
+
+    /// Check the opportunity is self-consistent and assemble it
+    ///
+    /// Prices, profit, confidence and slippage must be finite, prices and
+    /// slippage non-negative, and a route whose buy and sell prices quote
+    /// the same thing can't profit unless it sells higher than it buys.
+    /// Legs, when given, must agree with the buy and sell fields summarizing
+    /// them: the first buy leg (or first leg of a cycle) with the buy side,
+    /// the last sell leg with the sell side, on DEX and slot, and on price
+    /// when the leg trades `token_pair` itself. Worst-case profit can't exceed
+    /// the net profit, nor `valid_until` precede `detected_at`.
+    pub fn build(self) -> Result<Opportunity, OpportunityError> {
+        let opportunity_type = self.opportunity_type.ok_or(OpportunityError::Missing("opportunity_type"))?;
+        let token_pair = self.token_pair.ok_or(OpportunityError::Missing("token_pair"))?;
+        let (buy_dex, buy_price, buy_slot) = self.buy.ok_or(OpportunityError::Missing("buy side"))?;
+        let (sell_dex, sell_price, sell_slot) = self.sell.ok_or(OpportunityError::Missing("sell side"))?;
+        let net_profit_percent = self.net_profit_percent.ok_or(OpportunityError::Missing("net_profit_percent"))?;
+        let confidence = self.confidence.ok_or(OpportunityError::Missing("confidence"))?;
+
+        finite("buy_price", buy_price)?;
+        finite("sell_price", sell_price)?;
+        finite("net_profit_percent", net_profit_percent)?;
+        finite("confidence", confidence)?;
+        non_negative("buy_price", buy_price)?;
+        non_negative("sell_price", sell_price)?;
+        for slippage in &self.leg_slippage_percent {
+            finite("leg_slippage_percent", *slippage)?;
+            non_negative("leg_slippage_percent", *slippage)?;
+        }
+        for leg in &self.legs {
+            finite("leg price", leg.price)?;
+            non_negative("leg price", leg.price)?;
+        }
+        let assumed_slippage_pct =
+            self.assumed_slippage_pct.unwrap_or_else(|| self.leg_slippage_percent.iter().sum());
+        finite("assumed_slippage_pct", assumed_slippage_pct)?;
+        non_negative("assumed_slippage_pct", assumed_slippage_pct)?;
+        let worst_case_profit_percent = self.worst_case_profit_percent.unwrap_or(net_profit_percent);
+        finite("worst_case_profit_percent", worst_case_profit_percent)?;
+        if worst_case_profit_percent > net_profit_percent {
+            return Err(OpportunityError::WorstCaseAboveExpected {
+                worst_case: worst_case_profit_percent,
+                expected: net_profit_percent,
+            });
+        }
+        let detected_at = self.detected_at.unwrap_or_else(Utc::now);
+        let valid_until = self.valid_until.unwrap_or(detected_at + DEFAULT_VALIDITY);
+        if valid_until < detected_at {
+            return Err(OpportunityError::ExpiresBeforeDetection { detected_at, valid_until });
+        }
+
+        if prices_comparable(opportunity_type) && net_profit_percent > 0.0 && sell_price <= buy_price {
+            return Err(OpportunityError::InvertedPrices { buy: buy_price, sell: sell_price, net_profit_percent });
+        }
+
+        let buy_leg = self.legs.iter().find(|leg| leg.side == LegSide::Buy).or(self.legs.first());
+        if let Some(leg) = buy_leg {
+            check_leg("buy", leg, &token_pair, (&buy_dex, buy_price, buy_slot))?;
+        }
+        if let Some(leg) = self.legs.iter().rev().find(|leg| leg.side == LegSide::Sell) {
+            check_leg("sell", leg, &token_pair, (&sell_dex, sell_price, sell_slot))?;
+        }
+
+        Ok(Opportunity {
+            id: self.id.unwrap_or_else(Uuid::new_v4),
+            opportunity_type,
+            token_pair,
+            buy_dex,
+            sell_dex,
+            buy_price,
+            sell_price,
+            buy_slot,
+            sell_slot,
+            legs: self.legs,
+            net_profit_percent,
+            recommended_size: self.recommended_size,
+            size_token: String::new(),
+            size_decimals: 0,
+            expected_profit_base_units: 0,
+            profit_token: String::new(),
+            profit_decimals: 0,
+            size_amount: String::new(),
+            expected_profit_amount: String::new(),
+            confidence: confidence.clamp(0.0, 1.0),
+            leg_slippage_percent: self.leg_slippage_percent,
+            assumed_slippage_pct,
+            worst_case_profit_percent,
+            persisted_slots: self.persisted_slots.unwrap_or(1),
+            volatility_regime: self.volatility_regime,
+            detected_at,
+            valid_until,
+        })
+    }
+}
+
+/// Buy and sell prices quote the same thing (one pair's price, or one unit
+/// of a cycle's start token against what it returns), unlike statistical
+/// and bridged routes whose sides are different pairs
+fn prices_comparable(opportunity_type: OpportunityType) -> bool {
+    matches!(
+        opportunity_type,
+        OpportunityType::Spatial
+            | OpportunityType::SpreadAlert
+            | OpportunityType::Triangular
+            | OpportunityType::CrossDexTriangular
+            | OpportunityType::FourLegCycle
+    )
+}
+
+fn finite(field: &'static str, value: f64) -> Result<(), OpportunityError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(OpportunityError::NonFinite { field, value })
+    }
+}
+
+fn non_negative(field: &'static str, value: f64) -> Result<(), OpportunityError> {
+    if value < 0.0 {
+        Err(OpportunityError::Negative { field, value })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_leg(side: &'static str, leg: &RouteLeg, token_pair: &str, (dex, price, slot): (&Dex, f64, u64)) -> Result<(), OpportunityError> {
+    let mismatch = |field| Err(OpportunityError::LegMismatch { side, field });
+    if leg.dex != *dex {
+        return mismatch("dex");
+    }
+    if leg.slot != slot {
+        return mismatch("slot");
+    }
+    if leg.pair == token_pair && leg.price != price {
+        return mismatch("price");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(dex: &str, side: LegSide, price: f64, slot: u64) -> RouteLeg {
+        let data = PriceData { bid: Some(price), ask: Some(price), ..PriceData::new(price, 1, slot, 1, 1, 0.003) };
+        RouteLeg::new("SOL-USDC", dex, side, &data)
+    }
+
+    fn spatial() -> OpportunityBuilder {
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair("SOL-USDC")
+            .buy("raydium", 100.0, 10)
+            .sell("orca", 101.0, 11)
+            .legs(vec![leg("raydium", LegSide::Buy, 100.0, 10), leg("orca", LegSide::Sell, 101.0, 11)])
+            .net_profit_percent(0.4)
+            .recommended_size(1_000)
+            .confidence(0.8)
+    }
+
+    #[test]
+    fn test_build_fills_defaults() {
+        let opp = spatial().build().unwrap();
+        assert!(!opp.id.is_nil());
+        assert!((Utc::now() - opp.detected_at).num_seconds() < 5);
+        assert_eq!(opp.persisted_slots, 1);
+        assert_eq!((opp.buy_dex.as_str(), opp.sell_dex.as_str()), ("raydium", "orca"));
+        assert_eq!((opp.buy_slot, opp.sell_slot, opp.recommended_size), (10, 11, 1_000));
+        assert!(opp.size_token.is_empty());
+        assert_ne!(spatial().build().unwrap().id, opp.id);
+
+        assert_eq!(opp.valid_until - opp.detected_at, DEFAULT_VALIDITY);
+        assert_eq!(opp.worst_case_profit_percent, opp.net_profit_percent);
+        let sloppy = spatial().leg_slippage_percent(vec![0.1, 0.2]).build().unwrap();
+        assert!((sloppy.assumed_slippage_pct - 0.3).abs() < 1e-12);
+
+        assert_eq!(spatial().confidence(1.7).build().unwrap().confidence, 1.0);
+        assert_eq!(spatial().confidence(-0.2).build().unwrap().confidence, 0.0);
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let missing = |builder: OpportunityBuilder| builder.build().unwrap_err();
+        assert_eq!(missing(Opportunity::builder()), OpportunityError::Missing("opportunity_type"));
+        assert_eq!(
+            missing(Opportunity::builder().opportunity_type(OpportunityType::Spatial)),
+            OpportunityError::Missing("token_pair")
+        );
+        let mut no_sell = spatial();
+        no_sell.sell = None;
+        assert_eq!(missing(no_sell), OpportunityError::Missing("sell side"));
+    }
+
+    #[test]
+    fn test_rejects_non_finite_and_negative_values() {
+        assert_eq!(
+            spatial().net_profit_percent(f64::NAN).build().unwrap_err().to_string(),
+            "net_profit_percent is not finite (NaN)"
+        );
+        assert!(matches!(
+            spatial().confidence(f64::INFINITY).build(),
+            Err(OpportunityError::NonFinite { field: "confidence", .. })
+        ));
+        assert!(matches!(
+            spatial().leg_slippage_percent(vec![0.1, -0.2]).build(),
+            Err(OpportunityError::Negative { field: "leg_slippage_percent", .. })
+        ));
+        assert!(matches!(
+            spatial().legs(Vec::new()).buy("raydium", -1.0, 10).build(),
+            Err(OpportunityError::Negative { field: "buy_price", .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_risk_fields() {
+        assert_eq!(
+            spatial().worst_case_profit_percent(0.5).build().unwrap_err(),
+            OpportunityError::WorstCaseAboveExpected { worst_case: 0.5, expected: 0.4 }
+        );
+        let detected_at = Utc::now();
+        assert!(matches!(
+            spatial().detected_at(detected_at).valid_until(detected_at - chrono::Duration::seconds(1)).build(),
+            Err(OpportunityError::ExpiresBeforeDetection { .. })
+        ));
+        assert!(spatial().worst_case_profit_percent(-0.2).valid_until(detected_at).detected_at(detected_at).build().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_profit_from_inverted_prices() {
+        let inverted = spatial().legs(Vec::new()).buy("raydium", 101.0, 10).sell("orca", 100.0, 11);
+        assert_eq!(
+            inverted.clone().build().unwrap_err(),
+            OpportunityError::InvertedPrices { buy: 101.0, sell: 100.0, net_profit_percent: 0.4 }
+        );
+        // A losing spread alert, or sides on different pairs, are fine
+        assert!(inverted.clone().opportunity_type(OpportunityType::SpreadAlert).net_profit_percent(-0.6).build().is_ok());
+        assert!(inverted.opportunity_type(OpportunityType::Statistical).build().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_legs_disagreeing_with_summary() {
+        let mismatch = |builder: OpportunityBuilder| builder.build().unwrap_err();
+        assert_eq!(
+            mismatch(spatial().buy("meteora", 100.0, 10)),
+            OpportunityError::LegMismatch { side: "buy", field: "dex" }
+        );
+        assert_eq!(
+            mismatch(spatial().sell("orca", 101.0, 12)),
+            OpportunityError::LegMismatch { side: "sell", field: "slot" }
+        );
+        assert_eq!(
+            mismatch(spatial().sell("orca", 101.5, 11)).to_string(),
+            "sell leg price doesn't match the opportunity's sell price"
+        );
+    }
+}
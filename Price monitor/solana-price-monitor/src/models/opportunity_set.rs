@@ -0,0 +1,54 @@
+//! Per-pair summary of prices and opportunities
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use schemars::JsonSchema;
+
+use super::{timestamp, Dex, Opportunity, Spread};
+
+/// Everything known about one pair in a single object, served on
+/// `GET /pairs/:pair/summary` and streamed as `pair_summary` messages
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct OpportunitySet {
+    pub pair: String,
+    /// Most profitable spatial (or bridged spatial) opportunity on the pair
+    /// within the window that is still valid
+    pub best_spatial: Option<Opportunity>,
+    /// Most profitable still-valid cycle with a leg on the pair within the window
+    pub best_triangular: Option<Opportunity>,
+    /// Live spread; `None` with fewer than two fresh venues
+    pub spread: Option<Spread>,
+    /// Every cached venue of the pair, by DEX name
+    pub venues: Vec<VenueFreshness>,
+    /// Opportunities of any type touching the pair within the window
+    pub recent_opportunities: usize,
+    pub window_seconds: u64,
+    /// When the set was assembled; ages below are as of then
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How recent one DEX's cached quote of the pair is
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct VenueFreshness {
+    pub dex: Dex,
+    pub price: f64,
+    pub slot: u64,
+    /// When the quote was decoded
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub timestamp: DateTime<Utc>,
+    pub age_ms: u64,
+    /// Past the venue's stale threshold, so left out of `spread`
+    pub stale: bool,
+}
+
+impl OpportunitySet {
+    /// Whether a best opportunity has passed its `valid_until` since the set
+    /// was built
+    pub fn has_expired_best(&self, now: DateTime<Utc>) -> bool {
+        [&self.best_spatial, &self.best_triangular]
+            .into_iter()
+            .flatten()
+            .any(|opportunity| opportunity.is_expired_at(now))
+    }
+}
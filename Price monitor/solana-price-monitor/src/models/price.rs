@@ -1,10 +1,39 @@
 //! Price data structures
 
+use super::timestamp;
+use crate::calculator::calculate_amm_price;
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+/// Feed a price update arrived through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// Account subscription on an RPC WebSocket
+    #[default]
+    #[serde(rename = "websocket")]
+    WebSocket,
+    /// Yellowstone gRPC stream
+    Geyser,
+    /// Periodic HTTP RPC fetch
+    HttpPoll,
+    /// One-off fetch at startup, before the stream catches up
+    Bootstrap,
+    /// Generated by the simulator
+    Simulated,
+}
+
+impl PriceSource {
+    /// Pushed by the chain as it changes, rather than fetched or generated
+    pub fn is_streamed(self) -> bool {
+        matches!(self, PriceSource::WebSocket | PriceSource::Geyser)
+    }
+}
 
 /// Represents price data for a token pair on a specific DEX
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PriceData {
     /// Normalized price (output tokens per input token)
     pub price: f64,
@@ -16,6 +45,7 @@ pub struct PriceData {
     pub slot: u64,
 
     /// Timestamp of the price update
+    #[serde(serialize_with = "timestamp::serialize", deserialize_with = "timestamp::deserialize")]
     pub timestamp: DateTime<Utc>,
 
     /// Vault A balance (for slippage calculation)
@@ -26,6 +56,69 @@ pub struct PriceData {
 
     /// DEX fee rate (e.g., 0.003 for 0.3%)
     pub fee_rate: f64,
+
+    /// Flat per-swap cost (e.g. a transfer-hook fee) as a fraction of the
+    /// trade size; paid once per swap rather than on every unit like
+    /// `fee_rate`
+    #[serde(default)]
+    pub fixed_cost_rate: f64,
+
+    /// Seen at `confirmed` commitment or higher (false for `processed`
+    /// data, which a fork can still roll back)
+    #[serde(default)]
+    pub confirmed: bool,
+
+    /// Pool account the price was decoded from
+    #[serde(default)]
+    pub pool_pubkey: Option<String>,
+
+    /// Feed that delivered the update
+    #[serde(default)]
+    pub source: PriceSource,
+
+    /// Local time the update was received, unlike `timestamp` which is when
+    /// the price was valid (the Unix epoch in data written before this field)
+    #[serde(default, serialize_with = "timestamp::serialize", deserialize_with = "timestamp::deserialize")]
+    pub received_at: DateTime<Utc>,
+
+    /// Best bid on an orderbook venue (`None` for AMM pools)
+    #[serde(default)]
+    pub bid: Option<f64>,
+
+    /// Best ask on an orderbook venue (`None` for AMM pools)
+    #[serde(default)]
+    pub ask: Option<f64>,
+
+    /// `price` computed exactly from integer pool state, when the decoder
+    /// can (built with `precise-math`); `price` stays the value published
+    /// on the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_exact: Option<Decimal>,
+
+    /// Multiplier on the confidence of opportunities priced from this update:
+    /// 1.0, or less when the price disagreed with the pool's vault balances
+    #[serde(default = "full_weight")]
+    pub confidence_weight: f64,
+}
+
+fn full_weight() -> f64 {
+    1.0
+}
+
+/// Tolerance of `PriceData::validate_consistency`, in percent
+pub const DEFAULT_CONSISTENCY_TOLERANCE_PERCENT: f64 = 5.0;
+
+/// A price too far from the one its pool's vault balances imply
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error(
+    "price {price} is {deviation_percent:.2}% off the {implied_price} implied by vault balances \
+     (tolerance {tolerance_percent}%)"
+)]
+pub struct PriceInconsistency {
+    pub price: f64,
+    pub implied_price: f64,
+    pub deviation_percent: f64,
+    pub tolerance_percent: f64,
 }
 
 impl PriceData {
@@ -38,15 +131,109 @@ impl PriceData {
         vault_b_balance: u64,
         fee_rate: f64,
     ) -> Self {
+        let now = Utc::now();
         Self {
             price,
             liquidity,
             slot,
-            timestamp: Utc::now(),
+            timestamp: now,
             vault_a_balance,
             vault_b_balance,
             fee_rate,
+            fixed_cost_rate: 0.0,
+            confirmed: false,
+            pool_pubkey: None,
+            source: PriceSource::default(),
+            received_at: now,
+            bid: None,
+            ask: None,
+            price_exact: None,
+            confidence_weight: 1.0,
+        }
+    }
+
+    /// Create PriceData from an orderbook's top of book, priced at the mid
+    pub fn from_quote(bid: f64, ask: f64, liquidity: u64, slot: u64, fee_rate: f64) -> Self {
+        Self {
+            bid: Some(bid),
+            ask: Some(ask),
+            ..Self::new((bid + ask) / 2.0, liquidity, slot, 0, 0, fee_rate)
+        }
+    }
+
+    /// Price paid buying the base token: the ask when quoted, else `price`
+    pub fn buy_price(&self) -> f64 {
+        self.ask.unwrap_or(self.price)
+    }
+
+    /// Price received selling the base token: the bid when quoted, else `price`
+    pub fn sell_price(&self) -> f64 {
+        self.bid.unwrap_or(self.price)
+    }
+
+    /// Exact price of an AMM pool: `price_exact`, unless the venue is
+    /// quoted (bid and ask are trade prices, not the mid it was taken from)
+    pub fn exact_price(&self) -> Option<Decimal> {
+        if self.bid.is_some() || self.ask.is_some() {
+            return None;
+        }
+        self.price_exact
+    }
+
+    /// Record the pool's flat per-swap cost, as a fraction of the trade
+    pub fn with_fixed_cost_rate(mut self, fixed_cost_rate: f64) -> Self {
+        self.fixed_cost_rate = fixed_cost_rate;
+        self
+    }
+
+    /// Record the exact price alongside the f64 one
+    pub fn with_price_exact(mut self, price_exact: Option<Decimal>) -> Self {
+        self.price_exact = price_exact;
+        self
+    }
+
+    /// Bid-ask spread relative to the mid, in basis points; `None` without
+    /// both sides of the book
+    pub fn spread_bps(&self) -> Option<f64> {
+        let (bid, ask) = (self.bid?, self.ask?);
+        let mid = (bid + ask) / 2.0;
+        (mid > 0.0).then(|| (ask - bid) / mid * 10_000.0)
+    }
+
+    /// Record the pool account and feed the price came from
+    pub fn with_origin(mut self, pool_pubkey: impl Into<String>, source: PriceSource) -> Self {
+        self.pool_pubkey = Some(pool_pubkey.into());
+        self.source = source;
+        self
+    }
+
+    /// Check the price against the one the vault balances imply, within
+    /// `DEFAULT_CONSISTENCY_TOLERANCE_PERCENT`
+    ///
+    /// Catches decoder bugs such as misread decimals. Passes when either
+    /// vault balance is unknown (zero).
+    pub fn validate_consistency(&self, decimals_a: u8, decimals_b: u8) -> Result<(), PriceInconsistency> {
+        self.validate_consistency_within(decimals_a, decimals_b, DEFAULT_CONSISTENCY_TOLERANCE_PERCENT)
+    }
+
+    /// `validate_consistency` with its own tolerance, for pools whose vaults
+    /// legitimately drift from the spot price (concentrated liquidity)
+    pub fn validate_consistency_within(
+        &self,
+        decimals_a: u8,
+        decimals_b: u8,
+        tolerance_percent: f64,
+    ) -> Result<(), PriceInconsistency> {
+        if self.vault_a_balance == 0 || self.vault_b_balance == 0 {
+            return Ok(());
         }
+        let implied_price = calculate_amm_price(self.vault_a_balance, self.vault_b_balance, decimals_a, decimals_b);
+        let deviation_percent = (self.price - implied_price).abs() / implied_price * 100.0;
+        // NaN (a non-finite price) fails too
+        if deviation_percent <= tolerance_percent {
+            return Ok(());
+        }
+        Err(PriceInconsistency { price: self.price, implied_price, deviation_percent, tolerance_percent })
     }
 
     /// Check if price data is stale (older than threshold)
@@ -75,6 +262,15 @@ impl Default for PriceData {
             vault_a_balance: 0,
             vault_b_balance: 0,
             fee_rate: 0.003,
+            fixed_cost_rate: 0.0,
+            confirmed: false,
+            pool_pubkey: None,
+            source: PriceSource::default(),
+            received_at: Utc::now(),
+            bid: None,
+            ask: None,
+            price_exact: None,
+            confidence_weight: 1.0,
         }
     }
 }
@@ -96,4 +292,57 @@ mod tests {
         let impact = price.calculate_price_impact(1_000);
         assert!((impact - 1.0).abs() < 0.001); // 1% impact
     }
+
+    #[test]
+    fn test_origin_serializes_and_defaults() {
+        let price = PriceData::new(100.0, 1_000_000, 1, 1, 1, 0.003).with_origin("pool", PriceSource::HttpPoll);
+        let json = serde_json::to_value(&price).unwrap();
+        assert_eq!((json["pool_pubkey"].as_str(), json["source"].as_str()), (Some("pool"), Some("http_poll")));
+        assert_eq!(serde_json::to_value(PriceSource::WebSocket).unwrap(), "websocket");
+
+        // Entries written before the fields existed
+        let old = r#"{"price":1.0,"liquidity":1,"slot":1,"timestamp":"2026-01-01T00:00:00Z",
+            "vault_a_balance":1,"vault_b_balance":1,"fee_rate":0.003}"#;
+        let old: PriceData = serde_json::from_str(old).unwrap();
+        assert_eq!((old.pool_pubkey, old.source), (None, PriceSource::WebSocket));
+        assert_eq!(old.received_at, DateTime::<Utc>::UNIX_EPOCH);
+        assert_eq!((old.bid, old.ask), (None, None));
+        assert_eq!(old.confidence_weight, 1.0);
+    }
+
+    #[test]
+    fn test_quote_prices_at_mid() {
+        let quote = PriceData::from_quote(99.5, 100.5, 1_000_000, 7, 0.0005);
+        assert_eq!(quote.price, 100.0);
+        assert_eq!((quote.buy_price(), quote.sell_price()), (100.5, 99.5));
+        assert!((quote.spread_bps().unwrap() - 100.0).abs() < 1e-9);
+
+        // AMM pools trade both ways at their price
+        let pool = PriceData::new(100.0, 1_000_000, 7, 1, 1, 0.003);
+        assert_eq!((pool.buy_price(), pool.sell_price(), pool.spread_bps()), (100.0, 100.0, None));
+    }
+
+    #[test]
+    fn test_consistency_with_vault_balances() {
+        // 1000 SOL (9 decimals) against 100000 USDC (6 decimals)
+        let price = PriceData::new(100.0, 1_000_000, 7, 1_000_000_000_000, 100_000_000_000, 0.003);
+        assert_eq!(price.validate_consistency(9, 6), Ok(()));
+
+        // Both mints read as 6 decimals: a price 1000x off what was decoded
+        let error = price.validate_consistency(6, 6).unwrap_err();
+        assert!((error.implied_price - 0.1).abs() < 1e-12);
+        assert!(error.deviation_percent > 99_000.0);
+        assert!(error.to_string().contains("implied by vault balances"), "{}", error);
+
+        // 4% off passes the default 5% but not a tighter tolerance
+        let drifted = PriceData { price: 104.0, ..price.clone() };
+        assert_eq!(drifted.validate_consistency(9, 6), Ok(()));
+        assert_eq!(drifted.validate_consistency_within(9, 6, 1.0).unwrap_err().tolerance_percent, 1.0);
+        assert!(PriceData { price: f64::NAN, ..price }.validate_consistency(9, 6).is_err());
+
+        // Nothing to check against without both balances
+        let no_vaults = PriceData::new(100.0, 1_000_000, 7, 0, 100_000_000_000, 0.003);
+        assert_eq!(no_vaults.validate_consistency(6, 6), Ok(()));
+        assert_eq!(no_vaults.confidence_weight, 1.0);
+    }
 }
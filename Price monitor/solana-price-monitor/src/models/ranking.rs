@@ -0,0 +1,312 @@
+//! Ordering opportunities
+//!
+//! Scores are floats, so opportunities can't derive `Ord`. `Score` gives an
+//! `f64` a total order in which NaN ranks below every number, so a broken
+//! estimate never comes out on top (`f64::total_cmp` would rank a positive
+//! NaN above infinity). `EvKey` ranks routes priced in USD above those whose
+//! expected value is only a percentage. `TopK` keeps the best few of a
+//! stream by score.
+
+use super::Opportunity;
+use crate::calculator::UsdPricer;
+use std::cmp::Ordering;
+
+/// An `f64` ordered totally: NaN lowest, then numbers as usual, with
+/// `-0.0 == 0.0`
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub f64);
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.0.is_nan(), other.0.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+/// Ranking key of an expected value: every route priced in USD ranks above
+/// every route that fell back to a percentage, then by value
+///
+/// Dollars and percent aren't comparable, so a $0.50 route must not lose to
+/// an unpriced 2% one just because 2 > 0.5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EvKey {
+    pub priced: bool,
+    pub value: Score,
+}
+
+/// An opportunity ordered by `net_profit_percent`, e.g. for `sort_by_key`
+/// or a `BinaryHeap`
+#[derive(Debug, Clone, Copy)]
+pub struct ByProfitPct<'a>(pub &'a Opportunity);
+
+impl Ord for ByProfitPct<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_by_profit_pct(other.0)
+    }
+}
+
+impl PartialOrd for ByProfitPct<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ByProfitPct<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByProfitPct<'_> {}
+
+impl Opportunity {
+    /// Expected profit, discounted by confidence
+    ///
+    /// USD profit at the recommended size × confidence. When `usd` can't
+    /// price the route (base token decimals or USD price unknown), falls
+    /// back to `net_profit_percent` × confidence: the expected value of a
+    /// $100 trade, so unpriced routes still rank among themselves by return
+    /// and confidence. Rank with `ev_key`, which keeps the two apart.
+    pub fn expected_value(&self, usd: &UsdPricer) -> f64 {
+        self.ev_key(usd).value.0
+    }
+
+    /// `expected_value` as a ranking key: priced routes above unpriced ones
+    pub fn ev_key(&self, usd: &UsdPricer) -> EvKey {
+        match usd.profit(self) {
+            Some(profit) => EvKey { priced: true, value: Score(profit * self.confidence) },
+            None => EvKey { priced: false, value: Score(self.net_profit_percent * self.confidence) },
+        }
+    }
+
+    /// Compare by `ev_key`; NaN ranks lowest among priced or unpriced routes
+    pub fn cmp_by_ev(&self, other: &Opportunity, usd: &UsdPricer) -> Ordering {
+        self.ev_key(usd).cmp(&other.ev_key(usd))
+    }
+
+    /// Compare by `net_profit_percent`; NaN ranks lowest
+    pub fn cmp_by_profit_pct(&self, other: &Opportunity) -> Ordering {
+        Score(self.net_profit_percent).cmp(&Score(other.net_profit_percent))
+    }
+}
+
+/// The `k` highest scored items pushed so far, best first
+///
+/// Scores are `Score`s unless another key is given. Among equal scores the
+/// item pushed first ranks higher, so a newcomer never evicts an incumbent
+/// it only ties with.
+#[derive(Debug, Clone)]
+pub struct TopK<T, K = Score> {
+    k: usize,
+    items: Vec<(K, T)>,
+}
+
+impl<T, K: Ord> TopK<T, K> {
+    pub fn new(k: usize) -> Self {
+        Self { k, items: Vec::with_capacity(k) }
+    }
+
+    /// Keep `item` if it ranks among the best `k` by `key`; returns
+    /// whichever item dropped out (the pushed one when it didn't make it)
+    pub fn push_by(&mut self, key: K, item: T) -> Option<T> {
+        let at = self.position(&key);
+        if at >= self.k {
+            return Some(item);
+        }
+        self.items.insert(at, (key, item));
+        (self.items.len() > self.k).then(|| self.items.pop().map(|(_, item)| item)).flatten()
+    }
+
+    /// Whether an item keyed `key` would make the best `k`
+    pub fn admits(&self, key: &K) -> bool {
+        self.position(key) < self.k
+    }
+
+    /// After every item keyed at least as high
+    fn position(&self, key: &K) -> usize {
+        self.items.partition_point(|(kept, _)| kept >= key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn best(&self) -> Option<&T> {
+        self.items.first().map(|(_, item)| item)
+    }
+
+    /// Kept items, best first
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().map(|(_, item)| item)
+    }
+
+    /// Kept items, best first
+    pub fn into_vec(self) -> Vec<T> {
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+impl<T> TopK<T> {
+    /// Keep `item` if it ranks among the best `k`; returns whichever item
+    /// dropped out (the pushed one when it didn't make it)
+    pub fn push(&mut self, score: f64, item: T) -> Option<T> {
+        self.push_by(Score(score), item)
+    }
+
+    /// Kept items with their scores, best first
+    pub fn into_scored(self) -> Vec<(f64, T)> {
+        self.items.into_iter().map(|(Score(score), item)| (score, item)).collect()
+    }
+}
+
+impl TopK<Opportunity, EvKey> {
+    /// The best `k` of `opportunities` by `Opportunity::ev_key`, cloning
+    /// only the ones kept
+    pub fn by_ev<'a>(k: usize, opportunities: impl IntoIterator<Item = &'a Opportunity>, usd: &UsdPricer) -> Self {
+        let mut top = TopK::new(k);
+        for opportunity in opportunities {
+            top.push_by(opportunity.ev_key(usd), opportunity);
+        }
+        Self { k, items: top.items.into_iter().map(|(key, opportunity)| (key, opportunity.clone())).collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PriceCache;
+    use crate::models::{Dex, OpportunityType, PriceData};
+    use std::sync::Arc;
+
+    fn opportunity(pair: &str, net_profit_percent: f64, confidence: f64) -> Opportunity {
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair(pair)
+            .buy("raydium", 100.0, 1)
+            .sell("orca", 101.0, 1)
+            .net_profit_percent(net_profit_percent)
+            .recommended_size(2_000_000_000)
+            .confidence(confidence)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_nan_ranks_lowest() {
+        let mut scores = [Score(1.0), Score(f64::NAN), Score(f64::NEG_INFINITY), Score(-f64::NAN), Score(0.5)];
+        scores.sort();
+        assert!(scores[0].0.is_nan() && scores[1].0.is_nan());
+        assert_eq!(scores[2..].iter().map(|s| s.0).collect::<Vec<_>>(), vec![f64::NEG_INFINITY, 0.5, 1.0]);
+        assert_eq!(Score(-0.0), Score(0.0));
+
+        let mut top = TopK::new(2);
+        assert_eq!(top.push(f64::NAN, "nan"), None);
+        assert_eq!(top.push(-1.0, "negative"), None);
+        assert_eq!(top.push(0.1, "small"), Some("nan"));
+        assert_eq!(top.into_vec(), vec!["small", "negative"]);
+
+        let mut broken = opportunity("SOL-USDC", 1.0, 0.9);
+        broken.net_profit_percent = f64::NAN;
+        let fine = opportunity("SOL-USDC", 0.1, 0.9);
+        assert_eq!(broken.cmp_by_profit_pct(&fine), Ordering::Less);
+        assert_eq!(broken.cmp_by_ev(&fine, &UsdPricer::default()), Ordering::Less);
+        assert!(ByProfitPct(&fine) > ByProfitPct(&broken));
+    }
+
+    #[test]
+    fn test_ties_keep_the_first_pushed() {
+        let mut top = TopK::new(2);
+        top.push(1.0, "first");
+        top.push(1.0, "second");
+        // Ties with both, so it doesn't make it
+        assert_eq!(top.push(1.0, "third"), Some("third"));
+        assert_eq!(top.iter().copied().collect::<Vec<_>>(), vec!["first", "second"]);
+
+        let (a, b) = (opportunity("SOL-USDC", 0.5, 0.8), opportunity("SOL-USDC", 0.5, 0.8));
+        assert_eq!(a.cmp_by_profit_pct(&b), Ordering::Equal);
+        assert_eq!(a.cmp_by_ev(&b, &UsdPricer::default()), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_eviction_keeps_the_best_k() {
+        let mut top = TopK::new(3);
+        for (score, item) in [(0.2, 'a'), (0.9, 'b'), (0.5, 'c')] {
+            assert_eq!(top.push(score, item), None);
+        }
+        // Evicts the lowest
+        assert_eq!(top.push(0.7, 'd'), Some('a'));
+        // Too low to enter
+        assert_eq!(top.push(0.1, 'e'), Some('e'));
+        assert_eq!(top.len(), 3);
+        assert_eq!(top.best(), Some(&'b'));
+        assert_eq!(top.into_scored(), vec![(0.9, 'b'), (0.7, 'd'), (0.5, 'c')]);
+
+        let mut empty = TopK::new(0);
+        assert_eq!(empty.push(1.0, 'x'), Some('x'));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_expected_value_prefers_usd_profit() {
+        let cache = Arc::new(PriceCache::new(60, 5_000));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(150.0, 1_000_000, 1, 1, 1, 0.003));
+        let usd = UsdPricer::new([("SOL".to_string(), 9)]).with_price_cache(cache);
+
+        // 2 SOL at $150, 1% -> $3, at 0.5 confidence
+        let sol = opportunity("SOL-USDC", 1.0, 0.5);
+        assert!((sol.expected_value(&usd) - 1.5).abs() < 1e-9);
+        // No USD price for JUP: 2% x 0.5 as on a $100 trade
+        let jup = opportunity("JUP-USDC", 2.0, 0.5);
+        assert!((jup.expected_value(&usd) - 1.0).abs() < 1e-9);
+
+        let top = TopK::by_ev(1, [&jup, &sol], &usd);
+        assert_eq!(top.best().unwrap().token_pair, "SOL-USDC");
+    }
+
+    #[test]
+    fn test_priced_routes_rank_above_unpriced() {
+        let cache = Arc::new(PriceCache::new(60, 5_000));
+        cache.set("SOL-USDC", &Dex::Orca, PriceData::new(150.0, 1_000_000, 1, 1, 1, 0.003));
+        let usd = UsdPricer::new([("SOL".to_string(), 9)]).with_price_cache(cache);
+
+        // $0.15 x 0.5 = 0.075 in USD against 20% x 0.9 = 18 unpriced
+        let sol = opportunity("SOL-USDC", 0.05, 0.5);
+        let jup = opportunity("JUP-USDC", 20.0, 0.9);
+        assert!(sol.expected_value(&usd) < jup.expected_value(&usd));
+        assert_eq!(sol.cmp_by_ev(&jup, &usd), Ordering::Greater);
+
+        let top = TopK::by_ev(2, [&jup, &sol], &usd);
+        let pairs: Vec<_> = top.iter().map(|o| o.token_pair.as_str()).collect();
+        assert_eq!(pairs, vec!["SOL-USDC", "JUP-USDC"]);
+
+        // Unpriced routes still rank among themselves by return
+        let bonk = opportunity("BONK-USDC", 30.0, 0.9);
+        assert_eq!(bonk.cmp_by_ev(&jup, &usd), Ordering::Greater);
+        let mut keyed = TopK::new(1);
+        keyed.push_by(jup.ev_key(&usd), "JUP");
+        assert!(keyed.admits(&bonk.ev_key(&usd)));
+        assert!(!keyed.admits(&jup.ev_key(&usd)));
+    }
+}
@@ -0,0 +1,60 @@
+//! Live cross-DEX spread of a pair, as computed by `detector::pair_spread`
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use schemars::JsonSchema;
+
+use super::{Dex, PriceData};
+
+/// Best venues of one pair and the gap between them
+///
+/// The ask side is the DEX cheapest to buy on, the bid side the other DEX
+/// best to sell on. Orderbook venues are taken at their ask and bid, AMM
+/// pools at their pool price.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Spread {
+    pub pair: String,
+    pub bid_dex: Dex,
+    pub bid_price: f64,
+    pub ask_dex: Dex,
+    pub ask_price: f64,
+    /// `bid_price - ask_price`
+    pub spread: f64,
+    /// Spread relative to the ask price
+    pub spread_percent: f64,
+    /// Every fresh venue, cheapest first
+    pub venues: Vec<SpreadVenue>,
+}
+
+impl Spread {
+    pub fn spread_bps(&self) -> f64 {
+        self.spread_percent * 100.0
+    }
+}
+
+/// One DEX's quote in a spread
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SpreadVenue {
+    pub dex: Dex,
+    pub price: f64,
+    /// Top of book, on orderbook venues only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ask: Option<f64>,
+    pub slot: u64,
+    pub age_ms: u64,
+}
+
+impl SpreadVenue {
+    pub(crate) fn new(dex: &Dex, data: &PriceData, now: DateTime<Utc>) -> Self {
+        Self {
+            dex: dex.clone(),
+            price: data.price,
+            bid: data.bid,
+            ask: data.ask,
+            slot: data.slot,
+            age_ms: (now - data.timestamp).num_milliseconds().max(0) as u64,
+        }
+    }
+}
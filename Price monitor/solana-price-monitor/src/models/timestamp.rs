@@ -0,0 +1,120 @@
+//! Wire formats of timestamps
+//!
+//! Model timestamps are `DateTime<Utc>` fields serialized with `serialize`:
+//! RFC 3339 strings (chrono's default) unless a `TimestampFormat::scope`
+//! asks for epoch milliseconds. Models read them back with `deserialize`
+//! from either form, so records written in either format load too.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::cell::Cell;
+
+thread_local! {
+    /// Format `serialize` writes on this thread, set by `TimestampFormat::scope`
+    static FORMAT: Cell<TimestampFormat> = const { Cell::new(TimestampFormat::Rfc3339) };
+}
+
+/// `#[serde(serialize_with = "serialize")]`: in the format of the enclosing
+/// `TimestampFormat::scope`, RFC 3339 outside any
+pub fn serialize<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match FORMAT.with(Cell::get) {
+        TimestampFormat::Rfc3339 => at.serialize(serializer),
+        TimestampFormat::Millis => serializer.serialize_i64(at.timestamp_millis()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyTimestamp {
+    Millis(i64),
+    Rfc3339(DateTime<Utc>),
+}
+
+/// `#[serde(deserialize_with = "deserialize")]`: epoch milliseconds or an
+/// RFC 3339 string
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    match AnyTimestamp::deserialize(deserializer)? {
+        AnyTimestamp::Rfc3339(at) => Ok(at),
+        AnyTimestamp::Millis(ms) => DateTime::from_timestamp_millis(ms)
+            .ok_or_else(|| serde::de::Error::custom(format!("timestamp {} ms is out of range", ms))),
+    }
+}
+
+/// How API messages carry timestamps (`[api] timestamp_format`), the
+/// envelope and price `ts` included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    /// RFC 3339 strings, e.g. "2026-01-01T00:00:00Z"
+    #[default]
+    Rfc3339,
+    /// Epoch milliseconds
+    Millis,
+}
+
+impl TimestampFormat {
+    /// Run `f`, serializing timestamps on this thread in this format
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        /// Restores the outer format, even if `f` panics
+        struct Restore(TimestampFormat);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                FORMAT.with(|format| format.set(self.0));
+            }
+        }
+
+        let _restore = Restore(FORMAT.with(|format| format.replace(self)));
+        f()
+    }
+
+    /// `at` as a JSON value in this format
+    pub fn to_value(self, at: DateTime<Utc>) -> Value {
+        self.scope(|| serialize(&at, serde_json::value::Serializer)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Deserialize, Serialize)]
+    struct Record {
+        #[serde(with = "super")]
+        at: DateTime<Utc>,
+        /// Not a timestamp, whatever it looks like
+        reason: String,
+    }
+
+    #[test]
+    fn test_scope_picks_the_format() {
+        let at: DateTime<Utc> = "2026-01-01T00:00:01.500Z".parse().unwrap();
+        let record = Record { at, reason: "2026-01-01T00:00:00Z".to_string() };
+        let rfc3339 = json!({ "at": "2026-01-01T00:00:01.500Z", "reason": "2026-01-01T00:00:00Z" });
+        assert_eq!(serde_json::to_value(&record).unwrap(), rfc3339);
+
+        let millis = TimestampFormat::Millis.scope(|| serde_json::to_value(&record)).unwrap();
+        assert_eq!(millis, json!({ "at": 1_767_225_601_500i64, "reason": "2026-01-01T00:00:00Z" }));
+        // Nested scopes restore the outer format
+        let nested = TimestampFormat::Millis.scope(|| {
+            let inner = TimestampFormat::Rfc3339.scope(|| serde_json::to_value(&record).unwrap());
+            (inner, serde_json::to_value(&record).unwrap())
+        });
+        assert_eq!(nested, (rfc3339.clone(), millis));
+        assert_eq!(serde_json::to_value(&record).unwrap(), rfc3339);
+
+        assert_eq!(TimestampFormat::Millis.to_value(at), json!(1_767_225_601_500i64));
+        assert_eq!(TimestampFormat::Rfc3339.to_value(at), json!("2026-01-01T00:00:01.500Z"));
+    }
+
+    #[test]
+    fn test_reads_either_form() {
+        let at: DateTime<Utc> = "2026-01-01T00:00:00.250Z".parse().unwrap();
+        for ts in [json!(1_767_225_600_250i64), json!("2026-01-01T00:00:00.250Z")] {
+            assert_eq!(serde_json::from_value::<Record>(json!({ "at": ts, "reason": "" })).unwrap().at, at);
+        }
+        assert!(serde_json::from_value::<Record>(json!({ "at": "yesterday", "reason": "" })).is_err());
+    }
+}
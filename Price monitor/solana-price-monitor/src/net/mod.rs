@@ -0,0 +1,217 @@
+//! Outbound connections to RPC providers
+//!
+//! Everything that talks to an RPC endpoint goes through an `Egress` built
+//! from `[rpc]`. The WebSocket feed opens its own TCP connection, directly
+//! or through `rpc.proxy`, and runs TLS over it with the `rpc.tls` trust
+//! settings; HTTP helpers (registry discovery, the cost feed, fee export)
+//! get a `reqwest::Client` configured the same way. `rpc.headers` go on
+//! the WebSocket handshake and on every HTTP request.
+
+pub mod proxy;
+pub mod tls;
+
+use anyhow::{Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_rpc_client::http_sender::HttpSender;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::Error as WsError;
+use tokio_tungstenite::{client_async_tls_with_config, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+use crate::config::{RedactedUrl, RpcConfig, TlsConfig};
+pub use proxy::Proxy;
+
+/// Timeout of HTTP requests, as `RpcClient::new`
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to reach RPC endpoints: proxy, TLS trust and extra headers
+///
+/// Deliberately not `Debug`: header values are credentials.
+#[derive(Clone, Default)]
+pub struct Egress {
+    proxy: Option<String>,
+    tls: TlsConfig,
+    headers: BTreeMap<String, String>,
+}
+
+impl Egress {
+    pub fn from_rpc_config(rpc: &RpcConfig) -> Self {
+        Self {
+            proxy: rpc.proxy.clone(),
+            tls: rpc.tls.clone(),
+            headers: rpc.headers.clone(),
+        }
+    }
+
+    fn client_builder(&self) -> Result<reqwest::ClientBuilder> {
+        let mut builder = reqwest::Client::builder().timeout(HTTP_TIMEOUT);
+        if let Some(proxy) = &self.proxy {
+            Proxy::parse(proxy)?;
+            let proxy =
+                reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy URL {}", RedactedUrl::new(proxy)))?;
+            builder = builder.proxy(proxy);
+        }
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name rpc.headers.{}", name))?;
+            let mut value = reqwest::header::HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for rpc.headers.{}", name))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        tls::configure_http(builder.default_headers(headers), &self.tls)
+    }
+
+    /// `reqwest` client for HTTP helpers
+    pub fn http_client(&self) -> Result<reqwest::Client> {
+        self.client_builder()?.build().context("Failed to build HTTP client")
+    }
+
+    /// Nonblocking `RpcClient` for `http_url`
+    pub fn rpc_client(&self, http_url: &str) -> Result<RpcClient> {
+        let client = self
+            .client_builder()?
+            .default_headers(HttpSender::default_headers())
+            .build()
+            .context("Failed to build RPC client")?;
+        Ok(RpcClient::new_sender(HttpSender::new_with_client(http_url, client), RpcClientConfig::default()))
+    }
+
+    /// Open a WebSocket to `url`
+    ///
+    /// TLS failures are reported as such, apart from unreachable hosts and
+    /// rejected handshakes.
+    pub async fn connect_websocket(&self, url: Url) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let host = url.host_str().context("WebSocket URL has no host")?.to_string();
+        let port = url.port_or_known_default().context("WebSocket URL has no port")?;
+        let stream = match &self.proxy {
+            Some(proxy) => Proxy::parse(proxy)?.connect(&host, port).await?,
+            None => TcpStream::connect((host.as_str(), port))
+                .await
+                .with_context(|| format!("Failed to reach {}:{}", host, port))?,
+        };
+        stream.set_nodelay(true).ok();
+
+        let mut request = url.as_str().into_client_request().context("Invalid WebSocket URL")?;
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name rpc.headers.{}", name))?;
+            let mut value =
+                HeaderValue::from_str(value).with_context(|| format!("Invalid value for rpc.headers.{}", name))?;
+            value.set_sensitive(true);
+            request.headers_mut().insert(name, value);
+        }
+
+        let connector = tls::websocket_connector(&self.tls)?;
+        match client_async_tls_with_config(request, stream, None, connector).await {
+            Ok((ws, _)) => Ok(ws),
+            Err(WsError::Tls(e)) => Err(anyhow::anyhow!(
+                "TLS handshake with {}:{} failed: {} (private CA? set rpc.tls.ca_bundle)",
+                host,
+                port,
+                e
+            )),
+            Err(e) => Err(e).context("WebSocket handshake failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::net::TcpListener;
+
+    /// Test CA, and a `localhost` certificate it signed as a PKCS#8 identity
+    fn test_ca() -> (String, native_tls::Identity) {
+        let mut ca_params = rcgen::CertificateParams::new(Vec::new());
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(rcgen::DnType::CommonName, "Test RPC CA");
+        let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+
+        let leaf = rcgen::Certificate::from_params(rcgen::CertificateParams::new(vec!["localhost".to_string()])).unwrap();
+        let leaf_pem = leaf.serialize_pem_with_signer(&ca).unwrap();
+        let identity =
+            native_tls::Identity::from_pkcs8(leaf_pem.as_bytes(), leaf.serialize_private_key_pem().as_bytes()).unwrap();
+        (ca.serialize_pem().unwrap(), identity)
+    }
+
+    #[tokio::test]
+    async fn test_websocket_tls_trust() {
+        let (ca_pem, identity) = test_ca();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("wss://localhost:{}", listener.local_addr().unwrap().port())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(tls) = acceptor.accept(stream).await else { return };
+                    if let Ok(mut ws) = tokio_tungstenite::accept_async(tls).await {
+                        while ws.next().await.is_some() {}
+                    }
+                });
+            }
+        });
+
+        // The system roots don't know the test CA
+        let error = Egress::default().connect_websocket(url.clone()).await.unwrap_err();
+        assert!(error.to_string().contains("TLS handshake with localhost"), "{:#}", error);
+
+        let bundle = std::env::temp_dir().join(format!("rpc-ca-{}.pem", std::process::id()));
+        std::fs::write(&bundle, &ca_pem).unwrap();
+        let trusted = Egress {
+            tls: TlsConfig { ca_bundle: Some(bundle.to_str().unwrap().to_string()), insecure_skip_verify: false },
+            ..Egress::default()
+        };
+        trusted.connect_websocket(url.clone()).await.unwrap();
+        std::fs::remove_file(&bundle).ok();
+
+        let insecure = Egress {
+            tls: TlsConfig { ca_bundle: None, insecure_skip_verify: true },
+            ..Egress::default()
+        };
+        insecure.connect_websocket(url).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_handshake_carries_headers() {
+        use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port())).unwrap();
+        let (seen_tx, seen_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            #[allow(clippy::result_large_err)]
+            let callback = |request: &Request, response: Response| {
+                let header = |name| request.headers().get(name).map(|v| v.to_str().unwrap().to_string());
+                seen_tx.send((header("authorization"), header("x-token"))).unwrap();
+                Ok(response)
+            };
+            let mut ws = tokio_tungstenite::accept_hdr_async(stream, callback).await.unwrap();
+            while ws.next().await.is_some() {}
+        });
+
+        let egress = Egress {
+            headers: BTreeMap::from([
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("x-token".to_string(), "abc".to_string()),
+            ]),
+            ..Egress::default()
+        };
+        egress.connect_websocket(url).await.unwrap();
+        let (authorization, token) = seen_rx.await.unwrap();
+        assert_eq!(authorization.as_deref(), Some("Bearer secret"));
+        assert_eq!(token.as_deref(), Some("abc"));
+
+        let bad = Egress { headers: BTreeMap::from([("bad header".to_string(), "x".to_string())]), ..Egress::default() };
+        assert!(bad.http_client().unwrap_err().to_string().contains("Invalid header name"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Trust settings for RPC endpoints (`rpc.tls`)
+//!
+//! Extra root CAs are trusted alongside the system roots, so a bundle for
+//! a private CA doesn't break public providers configured next to it.
+
+use anyhow::{Context, Result};
+use tokio_tungstenite::Connector;
+use tracing::warn;
+
+use crate::config::TlsConfig;
+
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// The PEM bundle at `path`, checked to hold at least one certificate
+pub fn load_bundle(path: &str) -> Result<String> {
+    let bundle = std::fs::read_to_string(path).with_context(|| format!("Failed to read CA bundle {}", path))?;
+    if pem_certificates(&bundle).is_empty() {
+        anyhow::bail!("CA bundle {} holds no PEM certificates", path);
+    }
+    Ok(bundle)
+}
+
+/// Each `BEGIN`..`END CERTIFICATE` block of a bundle
+fn pem_certificates(bundle: &str) -> Vec<&str> {
+    bundle
+        .split_inclusive(PEM_END)
+        .filter(|block| block.contains("-----BEGIN CERTIFICATE-----") && block.ends_with(PEM_END))
+        .map(str::trim)
+        .collect()
+}
+
+/// Connector for `wss://` endpoints; `None` keeps tungstenite's default
+pub fn websocket_connector(tls: &TlsConfig) -> Result<Option<Connector>> {
+    if tls.ca_bundle.is_none() && !tls.insecure_skip_verify {
+        return Ok(None);
+    }
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = &tls.ca_bundle {
+        for pem in pem_certificates(&load_bundle(path)?) {
+            let certificate = native_tls::Certificate::from_pem(pem.as_bytes())
+                .with_context(|| format!("Invalid certificate in CA bundle {}", path))?;
+            builder.add_root_certificate(certificate);
+        }
+    }
+    if tls.insecure_skip_verify {
+        builder.danger_accept_invalid_certs(true).danger_accept_invalid_hostnames(true);
+    }
+    let connector = builder.build().context("Failed to build TLS connector")?;
+    Ok(Some(Connector::NativeTls(connector)))
+}
+
+/// Apply the trust settings to an HTTP client
+pub fn configure_http(mut builder: reqwest::ClientBuilder, tls: &TlsConfig) -> Result<reqwest::ClientBuilder> {
+    if let Some(path) = &tls.ca_bundle {
+        let certificates = reqwest::Certificate::from_pem_bundle(load_bundle(path)?.as_bytes())
+            .with_context(|| format!("Invalid certificate in CA bundle {}", path))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if tls.insecure_skip_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// rustls client config for the Geyser gRPC channel: system roots plus the
+/// bundle, or no verification at all with `insecure_skip_verify`
+#[cfg(feature = "geyser")]
+pub fn rustls_config(tls: &TlsConfig) -> Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    // Unreadable system stores are skipped, as reqwest and native-tls do
+    for certificate in rustls_native_certs::load_native_certs().unwrap_or_default() {
+        roots.add(&rustls::Certificate(certificate.0)).ok();
+    }
+    if let Some(path) = &tls.ca_bundle {
+        let certificates = rustls_pemfile::certs(&mut load_bundle(path)?.as_bytes())
+            .with_context(|| format!("Invalid certificate in CA bundle {}", path))?;
+        let (_, rejected) = roots.add_parsable_certificates(&certificates);
+        if rejected > 0 {
+            anyhow::bail!("Invalid certificate in CA bundle {}", path);
+        }
+    }
+
+    let mut config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    if tls.insecure_skip_verify {
+        config.dangerous().set_certificate_verifier(std::sync::Arc::new(AcceptAnyCertificate));
+    }
+    Ok(config)
+}
+
+/// Verifier behind `insecure_skip_verify` for rustls
+#[cfg(feature = "geyser")]
+struct AcceptAnyCertificate;
+
+#[cfg(feature = "geyser")]
+impl rustls::client::ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+/// Log loudly when verification is off
+pub fn warn_if_insecure(tls: &TlsConfig) {
+    if tls.insecure_skip_verify {
+        warn!("rpc.tls.insecure_skip_verify is on: RPC server certificates are NOT verified");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_splits_into_certificates() {
+        let bundle = "# private CAs\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\ntrailing";
+        let certificates = pem_certificates(bundle);
+        assert_eq!(certificates.len(), 2);
+        assert!(certificates[1].starts_with("-----BEGIN") && certificates[1].contains("BBB"));
+
+        let dir = std::env::temp_dir().join(format!("tls-bundle-{}", std::process::id()));
+        std::fs::write(&dir, "not a certificate").unwrap();
+        let error = load_bundle(dir.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("holds no PEM certificates"), "{}", error);
+        std::fs::remove_file(&dir).ok();
+        assert!(load_bundle("/nonexistent/ca.pem").unwrap_err().to_string().contains("Failed to read CA bundle"));
+    }
+
+    #[cfg(feature = "geyser")]
+    #[test]
+    fn test_rustls_config_alpn_is_left_to_the_connector() {
+        let config = rustls_config(&TlsConfig { insecure_skip_verify: true, ..Default::default() }).unwrap();
+        assert!(config.alpn_protocols.is_empty());
+
+        let missing = TlsConfig { ca_bundle: Some("/nonexistent/ca.pem".to_string()), ..Default::default() };
+        assert!(rustls_config(&missing).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Telegram and Discord opportunity alerts
+//!
+//! Subscribes to the opportunity topics of the API broadcast and forwards the
+//! ones that pass `[notifications]` filtering to every configured channel.
+//! Each channel sends
+//! at most one message per pair within `per_pair_interval_seconds`, so a
+//! persistent dislocation does not flood a chat. Sends use their own HTTP
+//! client: the egress client carries RPC credentials that must not reach
+//! third-party endpoints.
+
+use crate::api::{ApiMessage, TopicPattern, Topics};
+use crate::cache::PriceCache;
+use crate::calculator::UsdPricer;
+use crate::config::{NotificationsConfig, OpportunityFilter, Settings};
+use crate::detector::AggregatedOpportunity;
+use crate::models::{Opportunity, TopK};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Per-request timeout for alert deliveries
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a channel delivers its messages
+#[derive(Debug, Clone)]
+enum Target {
+    Telegram { url: String, chat_id: String },
+    Discord { webhook_url: String },
+}
+
+impl Target {
+    fn name(&self) -> &'static str {
+        match self {
+            Target::Telegram { .. } => "telegram",
+            Target::Discord { .. } => "discord",
+        }
+    }
+}
+
+/// Drops messages for a pair sent again within `interval`
+#[derive(Debug)]
+struct PairLimiter {
+    interval: Duration,
+    last: HashMap<String, Instant>,
+}
+
+impl PairLimiter {
+    fn new(interval: Duration) -> Self {
+        Self { interval, last: HashMap::new() }
+    }
+
+    fn allow(&mut self, pair: &str, now: Instant) -> bool {
+        match self.last.get(pair) {
+            Some(last) if now.duration_since(*last) < self.interval => false,
+            _ => {
+                self.last.insert(pair.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+struct Channel {
+    target: Target,
+    limiter: PairLimiter,
+}
+
+/// Sends filtered opportunities to Telegram and/or Discord
+pub struct Notifier {
+    filter: OpportunityFilter,
+    channels: Vec<Channel>,
+    client: reqwest::Client,
+    usd: UsdPricer,
+}
+
+impl Notifier {
+    /// Build from `[notifications]`; `None` when alerts are disabled
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>> {
+        let config = &settings.notifications;
+        if !config.enabled {
+            return Ok(None);
+        }
+        Self::new(config, UsdPricer::from_tokens(&settings.tokens)).map(Some)
+    }
+
+    fn new(config: &NotificationsConfig, usd: UsdPricer) -> Result<Self> {
+        let interval = Duration::from_secs(config.per_pair_interval_seconds);
+        let mut channels = Vec::new();
+        if let Some(telegram) = &config.telegram {
+            let url = format!("{}/bot{}/sendMessage", telegram.api_url.trim_end_matches('/'), telegram.bot_token);
+            channels.push(Channel {
+                target: Target::Telegram { url, chat_id: telegram.chat_id.clone() },
+                limiter: PairLimiter::new(interval),
+            });
+        }
+        if let Some(discord) = &config.discord {
+            channels.push(Channel {
+                target: Target::Discord { webhook_url: discord.webhook_url.clone() },
+                limiter: PairLimiter::new(interval),
+            });
+        }
+        let client = reqwest::Client::builder()
+            .timeout(SEND_TIMEOUT)
+            .build()
+            .context("Failed to build notification HTTP client")?;
+        Ok(Self {
+            filter: config.filter.clone(),
+            channels,
+            client,
+            usd,
+        })
+    }
+
+    /// Use live prices to quote profit in USD
+    pub fn with_price_cache(mut self, cache: Arc<PriceCache>) -> Self {
+        self.usd = self.usd.with_price_cache(cache);
+        self
+    }
+
+    /// Send `opportunity` to every channel that is not rate-limited for its
+    /// pair; returns how many channels accepted the message
+    pub async fn notify(&mut self, opportunity: &Opportunity) -> usize {
+        if !self.filter.matches(opportunity) {
+            return 0;
+        }
+        let text = format_message(opportunity, self.usd.profit(opportunity), Utc::now());
+        let now = Instant::now();
+        let mut sent = 0;
+        for channel in &mut self.channels {
+            if !channel.limiter.allow(&opportunity.token_pair, now) {
+                debug!("{} alert for {} suppressed (rate limit)", channel.target.name(), opportunity.token_pair);
+                continue;
+            }
+            match send(&self.client, &channel.target, &text).await {
+                Ok(()) => sent += 1,
+                Err(e) => warn!("{} alert failed: {:#}", channel.target.name(), e),
+            }
+        }
+        sent
+    }
+
+    /// Send the group's strategy with the highest expected value among
+    /// those passing the filter, which may not be the group's `best`
+    pub async fn notify_group(&mut self, group: &AggregatedOpportunity) -> usize {
+        let top = TopK::by_ev(1, group.strategies.iter().filter(|o| self.filter.matches(o)), &self.usd);
+        match top.best() {
+            Some(opportunity) => self.notify(opportunity).await,
+            None => 0,
+        }
+    }
+
+    /// Forward opportunities from the API broadcast until shutdown
+    pub fn spawn(mut self, topics: &Topics, shutdown: CancellationToken) -> JoinHandle<()> {
+        let mut rx = topics.subscribe_to([TopicPattern::kind("opportunity"), TopicPattern::kind("opportunity_group")]);
+        let names: Vec<_> = self.channels.iter().map(|c| c.target.name()).collect();
+        info!("Opportunity alerts enabled: {}", names.join(", "));
+        tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    message = rx.recv() => message,
+                };
+                match message {
+                    Ok(ApiMessage::OpportunityFound(opportunity)) => {
+                        self.notify(&opportunity).await;
+                    }
+                    Ok(ApiMessage::OpportunityGroup(group)) => {
+                        self.notify_group(&group).await;
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => warn!("Notifier lagged, skipped {} messages", n),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// Summary line, USD estimate and how fresh the signal is
+fn format_message(opportunity: &Opportunity, usd_profit: Option<f64>, now: DateTime<Utc>) -> String {
+    let mut text = opportunity.summary();
+    if let Some(usd) = usd_profit {
+        text.push_str(&format!("\n~${:.2} profit at recommended size", usd));
+    }
+    let age_ms = (now - opportunity.detected_at).num_milliseconds().max(0);
+    text.push_str(&format!(
+        "\nDetected {:.1}s ago, persisted {} slots, confidence {:.2}",
+        age_ms as f64 / 1000.0,
+        opportunity.persisted_slots,
+        opportunity.confidence
+    ));
+    text
+}
+
+async fn send(client: &reqwest::Client, target: &Target, text: &str) -> Result<()> {
+    let request = match target {
+        Target::Telegram { url, chat_id } => {
+            client.post(url).json(&serde_json::json!({ "chat_id": chat_id, "text": text }))
+        }
+        Target::Discord { webhook_url } => client.post(webhook_url).json(&serde_json::json!({ "content": text })),
+    };
+    // Both URLs embed a secret, keep them out of logged errors
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.without_url())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DiscordConfig, TelegramConfig};
+    use crate::models::{Dex, OpportunityType, PriceData};
+    use axum::{extract::State, routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    fn opportunity(pair: &str, net: f64) -> Opportunity {
+        Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair(pair)
+            .buy("raydium", 100.0, 0)
+            .sell("orca", 102.0, 0)
+            .net_profit_percent(net)
+            .recommended_size(2_000_000_000)
+            .confidence(0.8)
+            .persisted_slots(3)
+            .build()
+            .unwrap()
+    }
+
+    type Captured = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Local stand-in for the Telegram and Discord endpoints
+    async fn mock_server() -> (String, Captured) {
+        async fn capture(
+            State(captured): State<Captured>,
+            uri: axum::http::Uri,
+            Json(body): Json<Value>,
+        ) -> &'static str {
+            captured.lock().unwrap().push((uri.path().to_string(), body));
+            "{}"
+        }
+        let captured = Captured::default();
+        let app = Router::new()
+            .route("/bottoken/sendMessage", post(capture))
+            .route("/webhook", post(capture))
+            .with_state(captured.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}", addr), captured)
+    }
+
+    #[tokio::test]
+    async fn test_alerts_are_sent_once_per_pair_per_channel() {
+        let (base, captured) = mock_server().await;
+        let config = NotificationsConfig {
+            enabled: true,
+            telegram: Some(TelegramConfig {
+                bot_token: "token".to_string(),
+                chat_id: "42".to_string(),
+                api_url: base.clone(),
+            }),
+            discord: Some(DiscordConfig { webhook_url: format!("{}/webhook", base) }),
+            ..NotificationsConfig::default()
+        };
+        let cache = Arc::new(PriceCache::new(60, 5_000));
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(150.0, 1_000_000, 1, 1, 1, 0.003));
+        let mut notifier = Notifier::new(&config, UsdPricer::new([("SOL".to_string(), 9)]))
+            .unwrap()
+            .with_price_cache(cache);
+
+        assert_eq!(notifier.notify(&opportunity("SOL-USDC", 1.5)).await, 2);
+        // Same pair inside the window: suppressed on both channels
+        assert_eq!(notifier.notify(&opportunity("SOL-USDC", 2.0)).await, 0);
+        assert_eq!(notifier.notify(&opportunity("RAY-USDC", 1.5)).await, 2);
+        // Below min_net_profit_percent
+        assert_eq!(notifier.notify(&opportunity("JUP-USDC", 0.5)).await, 0);
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 4);
+        let (path, body) = &captured[0];
+        assert_eq!(path, "/bottoken/sendMessage");
+        assert_eq!(body["chat_id"], "42");
+        let text = body["text"].as_str().unwrap();
+        assert!(text.starts_with("Spatial: SOL-USDC | Buy raydium"), "{}", text);
+        // 2 SOL * $150 * 1.5%
+        assert!(text.contains("~$4.50 profit"), "{}", text);
+        assert!(text.contains("persisted 3 slots, confidence 0.80"), "{}", text);
+        let (path, body) = &captured[1];
+        assert_eq!(path, "/webhook");
+        assert_eq!(body["content"].as_str().unwrap(), text);
+        // No decimals configured for RAY: no USD estimate
+        assert!(!captured[2].1["text"].as_str().unwrap().contains('$'));
+    }
+
+    #[tokio::test]
+    async fn test_group_alert_picks_the_best_matching_strategy() {
+        let (base, captured) = mock_server().await;
+        let config = NotificationsConfig {
+            enabled: true,
+            filter: OpportunityFilter { types: vec!["spatial".to_string()], ..OpportunityFilter::default() },
+            discord: Some(DiscordConfig { webhook_url: format!("{}/webhook", base) }),
+            ..NotificationsConfig::default()
+        };
+        let mut notifier = Notifier::new(&config, UsdPricer::default()).unwrap();
+
+        let mut triangular = opportunity("SOL-USDC", 3.0);
+        triangular.opportunity_type = OpportunityType::Triangular;
+        let group = |strategies: Vec<Opportunity>| AggregatedOpportunity {
+            fingerprint: vec!["SOL-USDC@raydium".to_string()],
+            best: strategies[0].clone(),
+            strategies,
+        };
+        let strategies = vec![triangular.clone(), opportunity("SOL-USDC", 1.2), opportunity("SOL-USDC", 1.8)];
+        assert_eq!(notifier.notify_group(&group(strategies)).await, 1);
+        // Nothing in the group passes the filter
+        assert_eq!(notifier.notify_group(&group(vec![triangular])).await, 0);
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 1);
+        let text = captured[0].1["content"].as_str().unwrap();
+        assert!(text.starts_with("Spatial: SOL-USDC") && text.contains("1.80%"), "{}", text);
+    }
+
+    #[test]
+    fn test_pair_limiter_window() {
+        let mut limiter = PairLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(limiter.allow("SOL-USDC", start));
+        assert!(!limiter.allow("SOL-USDC", start + Duration::from_secs(59)));
+        assert!(limiter.allow("RAY-USDC", start + Duration::from_secs(59)));
+        assert!(limiter.allow("SOL-USDC", start + Duration::from_secs(60)));
+
+        let mut unlimited = PairLimiter::new(Duration::ZERO);
+        assert!(unlimited.allow("SOL-USDC", start));
+        assert!(unlimited.allow("SOL-USDC", start));
+    }
+
+    #[test]
+    fn test_filter_matches_pairs_and_types() {
+        let filter = OpportunityFilter {
+            min_net_profit_percent: 1.0,
+            min_confidence: 0.5,
+            pairs: vec!["sol_usdc".to_string()],
+            types: vec!["Spatial".to_string()],
+        };
+        assert!(filter.matches(&opportunity("SOL-USDC", 1.2)));
+        assert!(!filter.matches(&opportunity("RAY-USDC", 1.2)));
+        let mut triangular = opportunity("SOL-USDC", 1.2);
+        triangular.opportunity_type = OpportunityType::Triangular;
+        assert!(!filter.matches(&triangular));
+        let mut unsure = opportunity("SOL-USDC", 1.2);
+        unsure.confidence = 0.2;
+        assert!(!filter.matches(&unsure));
+    }
+}
@@ -0,0 +1,242 @@
+//! Event-driven scan scheduling
+//!
+//! Decoding and cache updates happen on the main loop; detection runs on a
+//! separate worker. The scheduler listens for cache update events,
+//! coalesces bursts of updates to the same pair within a debounce window,
+//! and hands the worker one batch of affected pairs at a time. While a
+//! [`PauseController`] reports a pause, batches are dropped instead.
+
+mod pause;
+
+pub use pause::{parse_time, window_contains, PauseController, PauseStatus};
+
+use crate::cache::CacheEvent;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// Pairs to scan, coalesced from one debounce window
+#[derive(Debug, Clone)]
+pub struct ScanBatch {
+    pub pairs: BTreeSet<String>,
+    /// When the first update in the batch arrived (for scan latency)
+    pub first_update_at: Instant,
+}
+
+/// Scheduler counters, shared with the scan worker
+#[derive(Debug, Default)]
+pub struct SchedulerMetrics {
+    updates_received: AtomicU64,
+    updates_coalesced: AtomicU64,
+    batches_dispatched: AtomicU64,
+    batches_paused: AtomicU64,
+    last_scan_latency_us: AtomicU64,
+}
+
+/// Point-in-time copy of the scheduler counters
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SchedulerMetricsSnapshot {
+    pub updates_received: u64,
+    pub updates_coalesced: u64,
+    pub batches_dispatched: u64,
+    /// Batches dropped because detection was paused
+    pub batches_paused: u64,
+    pub last_scan_latency_us: u64,
+}
+
+impl SchedulerMetrics {
+    /// Record time from the batch's first update until its scan completed
+    pub fn record_scan_latency(&self, batch: &ScanBatch) {
+        let latency = batch.first_update_at.elapsed().as_micros() as u64;
+        self.last_scan_latency_us.store(latency, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> SchedulerMetricsSnapshot {
+        SchedulerMetricsSnapshot {
+            updates_received: self.updates_received.load(Ordering::Relaxed),
+            updates_coalesced: self.updates_coalesced.load(Ordering::Relaxed),
+            batches_dispatched: self.batches_dispatched.load(Ordering::Relaxed),
+            batches_paused: self.batches_paused.load(Ordering::Relaxed),
+            last_scan_latency_us: self.last_scan_latency_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Coalesces cache updates into scan batches
+pub struct ScanScheduler {
+    debounce: Duration,
+    metrics: Arc<SchedulerMetrics>,
+    pause: Option<Arc<PauseController>>,
+}
+
+impl ScanScheduler {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            metrics: Arc::new(SchedulerMetrics::default()),
+            pause: None,
+        }
+    }
+
+    /// Drop batches while the controller reports a pause
+    pub fn with_pause(mut self, pause: Arc<PauseController>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    pub fn metrics(&self) -> Arc<SchedulerMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Spawn the scheduler loop; dropping `batches` on shutdown lets the
+    /// scan worker finish its last batch and exit
+    pub fn spawn(
+        self,
+        events: broadcast::Receiver<CacheEvent>,
+        batches: mpsc::Sender<ScanBatch>,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            shutdown.run_until_cancelled(self.run(events, batches)).await;
+        })
+    }
+
+    /// Consume cache events until the cache or the scan worker goes away
+    pub async fn run(
+        self,
+        mut events: broadcast::Receiver<CacheEvent>,
+        batches: mpsc::Sender<ScanBatch>,
+    ) {
+        loop {
+            // Wait for the first update of the next batch
+            let first = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped = skipped, "Scan scheduler lagged behind cache updates");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            let mut batch = ScanBatch {
+                pairs: BTreeSet::new(),
+                first_update_at: Instant::now(),
+            };
+            self.add(&mut batch, first);
+
+            // Coalesce everything arriving within the debounce window
+            let deadline = tokio::time::Instant::now() + self.debounce;
+            loop {
+                match tokio::time::timeout_at(deadline, events.recv()).await {
+                    Ok(Ok(event)) => self.add(&mut batch, event),
+                    Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                    Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+                }
+            }
+
+            if self.pause.as_ref().is_some_and(|p| p.is_paused()) {
+                self.metrics.batches_paused.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            self.metrics.batches_dispatched.fetch_add(1, Ordering::Relaxed);
+            if batches.send(batch).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn add(&self, batch: &mut ScanBatch, event: CacheEvent) {
+        self.metrics.updates_received.fetch_add(1, Ordering::Relaxed);
+        let CacheEvent::Updated { pair, .. } = event;
+        if !batch.pairs.insert(pair) {
+            self.metrics.updates_coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PriceCache;
+    use crate::models::{Dex, PriceData};
+
+    #[tokio::test]
+    async fn test_rapid_updates_coalesce_into_one_scan() {
+        let cache = PriceCache::new(60, 2000);
+        let scheduler = ScanScheduler::new(Duration::from_millis(20));
+        let metrics = scheduler.metrics();
+        let (batch_tx, mut batch_rx) = mpsc::channel(10);
+        scheduler.spawn(cache.subscribe(), batch_tx, CancellationToken::new());
+
+        for slot in 0..5 {
+            cache.set("SOL-USDC", &Dex::Raydium, PriceData::new(100.0, 1_000_000, slot, 500_000, 500_000, 0.003));
+        }
+
+        let batch = batch_rx.recv().await.unwrap();
+        assert_eq!(batch.pairs.len(), 1);
+        assert!(batch.pairs.contains("SOL-USDC"));
+
+        // Nothing else is dispatched for the burst
+        let next = tokio::time::timeout(Duration::from_millis(50), batch_rx.recv()).await;
+        assert!(next.is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.updates_received, 5);
+        assert_eq!(snapshot.updates_coalesced, 4);
+        assert_eq!(snapshot.batches_dispatched, 1);
+    }
+
+    #[tokio::test]
+    async fn test_paused_batches_are_dropped() {
+        let cache = PriceCache::new(60, 2000);
+        let pause = Arc::new(PauseController::new(Vec::new()));
+        let scheduler = ScanScheduler::new(Duration::from_millis(10)).with_pause(pause.clone());
+        let metrics = scheduler.metrics();
+        let (batch_tx, mut batch_rx) = mpsc::channel(10);
+        scheduler.spawn(cache.subscribe(), batch_tx, CancellationToken::new());
+
+        pause.pause("maintenance");
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::default());
+        let dropped = tokio::time::timeout(Duration::from_millis(50), batch_rx.recv()).await;
+        assert!(dropped.is_err());
+        assert_eq!(metrics.snapshot().batches_paused, 1);
+        // The cache itself kept updating
+        assert!(cache.get("SOL-USDC", &Dex::Raydium).is_some());
+
+        pause.resume();
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::default());
+        assert!(batch_rx.recv().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_batch_contains_every_affected_pair() {
+        let cache = PriceCache::new(60, 2000);
+        let (batch_tx, mut batch_rx) = mpsc::channel(10);
+        ScanScheduler::new(Duration::from_millis(20)).spawn(cache.subscribe(), batch_tx, CancellationToken::new());
+
+        cache.set("SOL-USDC", &Dex::Raydium, PriceData::default());
+        cache.set("JUP-USDC", &Dex::Orca, PriceData::default());
+
+        let batch = batch_rx.recv().await.unwrap();
+        assert_eq!(batch.pairs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_scheduler_and_closes_batches() {
+        let cache = PriceCache::new(60, 2000);
+        let (batch_tx, mut batch_rx) = mpsc::channel(10);
+        let shutdown = CancellationToken::new();
+        let task = ScanScheduler::new(Duration::from_millis(10)).spawn(cache.subscribe(), batch_tx, shutdown.clone());
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), task).await.unwrap().unwrap();
+        // The scan worker sees the channel close and exits its loop
+        assert!(batch_rx.recv().await.is_none());
+    }
+}
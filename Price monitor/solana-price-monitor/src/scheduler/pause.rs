@@ -0,0 +1,241 @@
+//! Detection pause windows
+//!
+//! During a pause the cache keeps updating and statistics stay warm, but no
+//! opportunities are emitted. A pause comes from a scheduled UTC window in
+//! `[schedule]` or from a manual pause via the admin API; transitions are
+//! published on a watch channel.
+
+use crate::config::PauseWindow;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, Utc, Weekday};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Whether detection is paused, and why
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PauseStatus {
+    pub paused: bool,
+    pub reason: Option<String>,
+}
+
+/// Decides whether detection is paused right now
+pub struct PauseController {
+    windows: RwLock<Vec<PauseWindow>>,
+    manual: RwLock<Option<String>>,
+    status: watch::Sender<PauseStatus>,
+}
+
+impl PauseController {
+    pub fn new(windows: Vec<PauseWindow>) -> Self {
+        let controller = Self {
+            windows: RwLock::new(windows),
+            manual: RwLock::new(None),
+            status: watch::channel(PauseStatus::default()).0,
+        };
+        controller.refresh(Utc::now());
+        controller
+    }
+
+    /// Replace the scheduled windows (config reload)
+    pub fn set_windows(&self, windows: Vec<PauseWindow>) {
+        *self.windows.write().unwrap_or_else(|e| e.into_inner()) = windows;
+        self.refresh(Utc::now());
+    }
+
+    /// Pause until `resume` is called
+    pub fn pause(&self, reason: impl Into<String>) -> PauseStatus {
+        *self.manual.write().unwrap_or_else(|e| e.into_inner()) = Some(reason.into());
+        self.refresh(Utc::now());
+        self.status()
+    }
+
+    /// Lift a manual pause; scheduled windows still apply
+    pub fn resume(&self) -> PauseStatus {
+        *self.manual.write().unwrap_or_else(|e| e.into_inner()) = None;
+        self.refresh(Utc::now());
+        self.status()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status.borrow().paused
+    }
+
+    pub fn status(&self) -> PauseStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Receive the status on every transition
+    pub fn subscribe(&self) -> watch::Receiver<PauseStatus> {
+        self.status.subscribe()
+    }
+
+    /// Re-evaluate at `now`, publishing the status if it changed
+    ///
+    /// Returns whether it changed.
+    pub fn refresh(&self, now: DateTime<Utc>) -> bool {
+        let manual = self.manual.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let next = match manual {
+            Some(reason) => PauseStatus {
+                paused: true,
+                reason: Some(reason),
+            },
+            None => {
+                let windows = self.windows.read().unwrap_or_else(|e| e.into_inner());
+                match windows.iter().find(|w| window_contains(w, now)) {
+                    Some(window) => PauseStatus {
+                        paused: true,
+                        reason: Some(window_reason(window)),
+                    },
+                    None => PauseStatus::default(),
+                }
+            }
+        };
+
+        self.status.send_if_modified(|status| {
+            if *status == next {
+                return false;
+            }
+            *status = next;
+            true
+        })
+    }
+
+    /// Re-evaluate the windows on an interval
+    pub fn spawn_clock(
+        self: &Arc<Self>,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let controller = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let clock = async move {
+                loop {
+                    ticker.tick().await;
+                    controller.refresh(Utc::now());
+                }
+            };
+            shutdown.run_until_cancelled(clock).await;
+        })
+    }
+}
+
+/// Whether `now` falls inside the window
+///
+/// Windows are `[start, end)` in UTC. A window whose end is before its start
+/// runs past midnight and belongs to the day it starts on.
+pub fn window_contains(window: &PauseWindow, now: DateTime<Utc>) -> bool {
+    let (Some(start), Some(end)) = (parse_time(&window.start), parse_time(&window.end)) else {
+        return false;
+    };
+    let time = now.time();
+    let applies_on = |day: Weekday| {
+        window.days.is_empty()
+            || window
+                .days
+                .iter()
+                .any(|d| d.parse::<Weekday>().is_ok_and(|d| d == day))
+    };
+
+    if start <= end {
+        applies_on(now.weekday()) && time >= start && time < end
+    } else {
+        (time >= start && applies_on(now.weekday()))
+            || (time < end && applies_on((now - ChronoDuration::days(1)).weekday()))
+    }
+}
+
+/// "HH:MM" in UTC
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+fn window_reason(window: &PauseWindow) -> String {
+    if window.reason.is_empty() {
+        format!("scheduled pause {}-{} UTC", window.start, window.end)
+    } else {
+        window.reason.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(start: &str, end: &str, days: &[&str]) -> PauseWindow {
+        PauseWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            reason: String::new(),
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2026-06-01 is a Monday
+        Utc.with_ymd_and_hms(2026, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_window_boundaries() {
+        let maintenance = window("02:00", "03:30", &[]);
+        assert!(!window_contains(&maintenance, at(1, 1, 59)));
+        assert!(window_contains(&maintenance, at(1, 2, 0)));
+        assert!(window_contains(&maintenance, at(1, 3, 29)));
+        // End is exclusive
+        assert!(!window_contains(&maintenance, at(1, 3, 30)));
+    }
+
+    #[test]
+    fn test_overnight_window_belongs_to_start_day() {
+        let friday_night = window("22:00", "02:00", &["fri"]);
+        // Friday 23:00 and the following Saturday 01:00
+        assert!(window_contains(&friday_night, at(5, 23, 0)));
+        assert!(window_contains(&friday_night, at(6, 1, 0)));
+        // Thursday night and Saturday night are not covered
+        assert!(!window_contains(&friday_night, at(4, 23, 0)));
+        assert!(!window_contains(&friday_night, at(6, 23, 0)));
+        assert!(!window_contains(&friday_night, at(6, 2, 0)));
+    }
+
+    #[test]
+    fn test_transitions_are_published() {
+        let controller = PauseController::new(vec![PauseWindow {
+            reason: "RPC maintenance".to_string(),
+            ..window("02:00", "03:00", &[])
+        }]);
+        let mut rx = controller.subscribe();
+
+        assert!(controller.refresh(at(1, 2, 0)));
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(
+            *rx.borrow_and_update(),
+            PauseStatus {
+                paused: true,
+                reason: Some("RPC maintenance".to_string()),
+            }
+        );
+
+        // Still inside the window: nothing new to publish
+        assert!(!controller.refresh(at(1, 2, 30)));
+        assert!(!rx.has_changed().unwrap());
+
+        assert!(controller.refresh(at(1, 3, 0)));
+        assert!(!rx.borrow_and_update().paused);
+    }
+
+    #[test]
+    fn test_manual_pause_and_resume() {
+        let controller = PauseController::new(Vec::new());
+        assert!(!controller.is_paused());
+
+        let status = controller.pause("token unlock");
+        assert_eq!(status.reason.as_deref(), Some("token unlock"));
+        assert!(controller.is_paused());
+
+        assert!(!controller.resume().paused);
+    }
+}
@@ -0,0 +1,536 @@
+//! Synthetic feed for development without an RPC provider
+//!
+//! `rpc.transport = "simulated"` (or `--simulate`) replaces the WebSocket
+//! manager with a generator producing the same `WsEvent`s for the
+//! configured pools. Account bytes are built with the inverse of each
+//! decoder's math, so decoding, the cache, the detectors and the API see
+//! what a live feed would give them.
+//!
+//! Every slot each token's USD price takes a small random step (stablecoins
+//! stay pinned), a `SlotAdvanced` is emitted and every pool gets an update
+//! at the new price. Divergences are injected on a fixed schedule so each
+//! detector has something to find, alternating between:
+//! - a venue: one pool of a pair listed on several DEXes moves away from
+//!   the others (spatial)
+//! - a pair: one pair moves on every DEX, breaking the triangles through it
+//!   and its spread to pairs quoted in the same token (triangular,
+//!   statistical)
+//!
+//! Targets rotate through the pools and pairs, moving up the first time
+//! round and down the next.
+
+use crate::config::{Commitment, Settings, SimulatorConfig};
+use crate::decoder::{MeteoraDecoder, OrcaDecoder, RaydiumDecoder};
+use crate::metrics::PipelineMetrics;
+use crate::websocket::{ConnectionState, ConnectionStatus, EventSender, WsEvent};
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+/// Label the feed is reported under in `ConnectionStatus`
+const ENDPOINT_LABEL: &str = "simulated";
+
+/// First slot produced, in the range of mainnet slots
+const START_SLOT: u64 = 250_000_000;
+
+/// Starting USD prices; other tokens start at 1
+const START_PRICES: &[(&str, f64)] = &[
+    ("SOL", 150.0),
+    ("USDC", 1.0),
+    ("USDT", 1.0),
+    ("JUP", 0.9),
+    ("BONK", 0.00002),
+    ("JTO", 2.5),
+    ("RAY", 2.0),
+    ("W", 0.3),
+];
+
+/// Tokens whose price doesn't walk
+const STABLECOINS: &[&str] = &["USDC", "USDT"];
+
+/// USD value of each side of a simulated Raydium pool
+const RAYDIUM_DEPTH_USD: f64 = 1_000_000.0;
+
+/// Orca fee as the decoder reads it (`fee_rate / 10_000`): 0.3%
+const ORCA_FEE_RATE: u16 = 30;
+const ORCA_LIQUIDITY: u128 = 1_000_000_000_000;
+
+/// One basis point per bin keeps quantization below the random walk
+const METEORA_BIN_STEP: u16 = 1;
+const METEORA_BASE_FACTOR: u16 = 10_000;
+
+/// A pool the simulator writes accounts for
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedPool {
+    pub pubkey: String,
+    pub pair: String,
+    pub dex: String,
+    pub base: String,
+    pub quote: String,
+    pub token_a_decimals: u8,
+    pub token_b_decimals: u8,
+}
+
+impl SimulatedPool {
+    /// `None` when the pair name isn't `BASE-QUOTE` / `base_quote`
+    pub fn new(pubkey: &str, pair: &str, dex: &str, (token_a_decimals, token_b_decimals): (u8, u8)) -> Option<Self> {
+        let (base, quote) = pair.split_once(['-', '_'])?;
+        Some(Self {
+            pubkey: pubkey.to_string(),
+            pair: pair.to_string(),
+            dex: dex.to_lowercase(),
+            base: base.to_uppercase(),
+            quote: quote.to_uppercase(),
+            token_a_decimals,
+            token_b_decimals,
+        })
+    }
+}
+
+/// What a divergence moves
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// One pool, by index
+    Pool(usize),
+    /// Every pool of a pair
+    Pair(String),
+}
+
+#[derive(Debug, Clone)]
+struct Divergence {
+    target: Target,
+    factor: f64,
+    until_slot: u64,
+}
+
+/// Generator of account and slot updates for the configured pools
+pub struct SimulatedFeed {
+    config: SimulatorConfig,
+    commitment: Commitment,
+    pools: Vec<SimulatedPool>,
+    /// Token -> USD price, ordered so a seed reproduces the walk
+    usd_prices: BTreeMap<String, f64>,
+    rng: StdRng,
+    slot: u64,
+    next_divergence_slot: u64,
+    divergences_started: u64,
+    divergence: Option<Divergence>,
+    status: watch::Sender<ConnectionStatus>,
+    shutdown: CancellationToken,
+    tx: Option<EventSender>,
+    metrics: Arc<PipelineMetrics>,
+}
+
+impl SimulatedFeed {
+    pub fn new(config: SimulatorConfig, pools: Vec<SimulatedPool>) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let usd_prices = pools
+            .iter()
+            .flat_map(|pool| [pool.base.clone(), pool.quote.clone()])
+            .map(|token| {
+                let price = START_PRICES.iter().find(|(t, _)| *t == token).map_or(1.0, |(_, p)| *p);
+                (token, price)
+            })
+            .collect();
+        let mut feed = Self {
+            config,
+            commitment: Commitment::default(),
+            pools,
+            usd_prices,
+            rng,
+            slot: START_SLOT,
+            next_divergence_slot: 0,
+            divergences_started: 0,
+            divergence: None,
+            status: watch::channel(ConnectionStatus::default()).0,
+            shutdown: CancellationToken::new(),
+            tx: None,
+            metrics: Arc::default(),
+        };
+        feed.next_divergence_slot = START_SLOT + feed.divergence_interval_slots();
+        feed
+    }
+
+    /// Feed for `(pubkey, pair, dex)` pools with `[simulator]` settings and
+    /// decimals from `[pool_overrides]` / `[tokens]`, falling back to the
+    /// decoders' SOL/USDC defaults like the main loop does
+    pub fn from_settings<'a>(settings: &Settings, pools: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>) -> Self {
+        let pools = pools
+            .into_iter()
+            .filter_map(|(pubkey, pair, dex)| {
+                let decimals = settings
+                    .pool_decimals(pair, pubkey)
+                    .map_or((9, 6), |d| (d.token_a_decimals, d.token_b_decimals));
+                let pool = SimulatedPool::new(pubkey, pair, dex, decimals);
+                if pool.is_none() {
+                    warn!(pair = pair, pubkey = pubkey, "Pair name has no BASE-QUOTE tokens, not simulating it");
+                }
+                pool
+            })
+            .collect();
+        let mut feed = Self::new(settings.simulator.clone(), pools);
+        feed.commitment = settings.rpc.commitment;
+        feed
+    }
+
+    /// Publish connection status on an existing channel
+    pub fn with_status(mut self, status: watch::Sender<ConnectionStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Count generated updates in shared pipeline metrics
+    pub fn with_metrics(mut self, metrics: Arc<PipelineMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Stop when `shutdown` is cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn set_sender(&mut self, tx: EventSender) {
+        self.tx = Some(tx);
+    }
+
+    pub fn pools(&self) -> &[SimulatedPool] {
+        &self.pools
+    }
+
+    /// Produce updates every `update_interval_ms` until shutdown
+    pub async fn run(&mut self) {
+        info!(
+            pools = self.pools.len(),
+            update_interval_ms = self.config.update_interval_ms,
+            seed = ?self.config.seed,
+            "Simulated feed started; prices are synthetic"
+        );
+        let active = self.pools.len();
+        self.status.send_modify(|status| {
+            status.state = ConnectionState::Connected;
+            status.endpoint = ENDPOINT_LABEL.to_string();
+            status.connected_since = Some(Utc::now());
+            status.active_subscriptions = active;
+            status.pending_subscriptions = 0;
+        });
+
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.update_interval_ms.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.shutdown.cancelled() => break,
+            }
+            for event in self.tick() {
+                if let WsEvent::AccountUpdate { data, .. } = &event {
+                    self.metrics.record_message(data.len());
+                }
+                if !self.emit(event).await {
+                    return;
+                }
+            }
+        }
+        info!("Simulated feed stopped");
+    }
+
+    /// Advance one slot: move prices, start or end a divergence, and return
+    /// the slot event followed by an update for every pool
+    pub fn tick(&mut self) -> Vec<WsEvent> {
+        self.slot += 1;
+        self.walk();
+        self.schedule_divergence();
+
+        let mut events = Vec::with_capacity(self.pools.len() + 1);
+        events.push(WsEvent::SlotAdvanced { slot: self.slot, timestamp: Utc::now().timestamp_millis() as u64 });
+        for index in 0..self.pools.len() {
+            events.push(WsEvent::AccountUpdate {
+                pubkey: self.pools[index].pubkey.clone(),
+                slot: self.slot,
+                data: self.encode(index),
+                commitment: self.commitment,
+            });
+        }
+        events
+    }
+
+    /// One random step per non-stable token
+    fn walk(&mut self) {
+        let step = self.config.volatility_bps.max(0.0) / 10_000.0;
+        for (token, price) in self.usd_prices.iter_mut() {
+            if !STABLECOINS.contains(&token.as_str()) {
+                *price *= 1.0 + self.rng.gen_range(-step..=step);
+            }
+        }
+    }
+
+    fn divergence_interval_slots(&self) -> u64 {
+        (self.config.divergence_interval_seconds * 1000 / self.config.update_interval_ms.max(1)).max(1)
+    }
+
+    fn schedule_divergence(&mut self) {
+        if self.divergence.as_ref().is_some_and(|d| self.slot >= d.until_slot) {
+            debug!(slot = self.slot, "Simulated divergence over");
+            self.divergence = None;
+        }
+        if self.config.divergence_interval_seconds == 0 || self.slot < self.next_divergence_slot {
+            return;
+        }
+        self.next_divergence_slot = self.slot + self.divergence_interval_slots();
+        self.divergence = self.next_divergence();
+        if let Some(divergence) = &self.divergence {
+            let target = match &divergence.target {
+                Target::Pool(index) => format!("{} on {}", self.pools[*index].pair, self.pools[*index].dex),
+                Target::Pair(pair) => format!("{} on every DEX", pair),
+            };
+            info!(
+                slot = self.slot,
+                target = target,
+                move_percent = (divergence.factor - 1.0) * 100.0,
+                slots = self.config.divergence_slots,
+                "Simulated divergence"
+            );
+        }
+    }
+
+    /// Next target in the rotation: venues and pairs alternate while there
+    /// are pairs on several DEXes
+    fn next_divergence(&mut self) -> Option<Divergence> {
+        let mut dexes_per_pair: BTreeMap<&str, usize> = BTreeMap::new();
+        for pool in &self.pools {
+            *dexes_per_pair.entry(&pool.pair).or_default() += 1;
+        }
+        let venues: Vec<usize> = (0..self.pools.len()).filter(|&i| dexes_per_pair[self.pools[i].pair.as_str()] > 1).collect();
+        let pairs: Vec<&str> = dexes_per_pair.keys().copied().collect();
+        if pairs.is_empty() {
+            return None;
+        }
+
+        let n = self.divergences_started as usize;
+        self.divergences_started += 1;
+        let (target, round) = if venues.is_empty() {
+            (Target::Pair(pairs[n % pairs.len()].to_string()), n / pairs.len())
+        } else if n % 2 == 0 {
+            (Target::Pool(venues[n / 2 % venues.len()]), n / 2 / venues.len())
+        } else {
+            (Target::Pair(pairs[n / 2 % pairs.len()].to_string()), n / 2 / pairs.len())
+        };
+
+        let step = 1.0 + self.config.divergence_percent / 100.0;
+        Some(Divergence {
+            target,
+            factor: if round % 2 == 0 { step } else { 1.0 / step },
+            until_slot: self.slot + self.config.divergence_slots,
+        })
+    }
+
+    /// Quote-per-base price of a pool, divergence included
+    pub fn pool_price(&self, index: usize) -> f64 {
+        let pool = &self.pools[index];
+        let usd = |token: &str| self.usd_prices.get(token).copied().unwrap_or(1.0);
+        let price = usd(&pool.base) / usd(&pool.quote);
+        match &self.divergence {
+            Some(Divergence { target: Target::Pool(i), factor, .. }) if *i == index => price * factor,
+            Some(Divergence { target: Target::Pair(pair), factor, .. }) if *pair == pool.pair => price * factor,
+            _ => price,
+        }
+    }
+
+    /// Account bytes that decode to the pool's current price
+    fn encode(&self, index: usize) -> Vec<u8> {
+        let pool = &self.pools[index];
+        let price = self.pool_price(index);
+        let (a, b) = (pool.token_a_decimals, pool.token_b_decimals);
+        match pool.dex.as_str() {
+            "orca" => {
+                let sqrt_price = OrcaDecoder::new(a, b).sqrt_price_for(price);
+                OrcaDecoder::encode_slice(ORCA_FEE_RATE, ORCA_LIQUIDITY, sqrt_price)
+            }
+            "meteora" => {
+                let active_id = MeteoraDecoder::new(a, b).bin_for_price(price, METEORA_BIN_STEP);
+                MeteoraDecoder::encode_slice(active_id, METEORA_BIN_STEP, METEORA_BASE_FACTOR)
+            }
+            // Unknown DEXes are decoded as Raydium by the main loop
+            _ => {
+                let base_amount = RAYDIUM_DEPTH_USD / self.usd_prices.get(&pool.base).copied().unwrap_or(1.0);
+                let coin = base_amount * 10f64.powi(a as i32);
+                let pc = base_amount * price * 10f64.powi(b as i32);
+                RaydiumDecoder::encode(a, b, coin as u64, pc as u64)
+            }
+        }
+    }
+
+    async fn emit(&self, event: WsEvent) -> bool {
+        match &self.tx {
+            Some(tx) => {
+                let sent = tx.send(event).await;
+                if !sent {
+                    error!("Event receiver closed, stopping simulated feed");
+                }
+                sent
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PriceCache;
+    use crate::config::FeesConfig;
+    use crate::decoder::{PoolDecoder, PoolState};
+    use crate::detector::{StatArbConfig, TriangularArbConfig, TriangularPath};
+    use crate::models::{Dex, OpportunityType, PriceData};
+    use crate::{OpportunityDetector, StatisticalArbitrageDetector, TriangularArbitrageDetector};
+
+    fn decode(pool: &SimulatedPool, data: &[u8]) -> PoolState {
+        let (a, b) = (pool.token_a_decimals, pool.token_b_decimals);
+        match pool.dex.as_str() {
+            "orca" => OrcaDecoder::new(a, b).decode(data),
+            "meteora" => MeteoraDecoder::new(a, b).decode(data),
+            _ => RaydiumDecoder.decode(data),
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encoded_accounts_decode_to_pool_price() {
+        let pools = ["raydium", "orca", "meteora"]
+            .iter()
+            .enumerate()
+            .map(|(i, dex)| SimulatedPool::new(&format!("Pool{}", i), "BONK-SOL", dex, (5, 9)).unwrap())
+            .collect();
+        let mut feed = SimulatedFeed::new(SimulatorConfig { seed: Some(1), ..SimulatorConfig::default() }, pools);
+
+        for (index, event) in feed.tick().into_iter().skip(1).enumerate() {
+            let WsEvent::AccountUpdate { data, .. } = event else { panic!("expected an account update") };
+            let expected = feed.pool_price(index);
+            let decoded = decode(&feed.pools()[index], &data).price();
+            // Meteora prices are quantized to half a bin (0.5 bp)
+            assert!((decoded / expected - 1.0).abs() < 0.6e-4, "{}: {} vs {}", feed.pools()[index].dex, decoded, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulated_minute_triggers_every_detector() {
+        let pools = [
+            ("SOL-USDC", "raydium"),
+            ("SOL-USDC", "orca"),
+            ("SOL-USDC", "meteora"),
+            ("USDC-RAY", "raydium"),
+            ("RAY-SOL", "raydium"),
+            ("JUP-USDC", "raydium"),
+        ];
+        let mut settings = Settings::default();
+        settings.simulator = SimulatorConfig { divergence_interval_seconds: 4, seed: Some(7), ..SimulatorConfig::default() };
+        let pubkeys: Vec<String> = (0..pools.len()).map(|i| format!("Pool{}", i)).collect();
+        let mut feed = SimulatedFeed::from_settings(
+            &settings,
+            pools.iter().zip(&pubkeys).map(|((pair, dex), pubkey)| (pubkey.as_str(), *pair, *dex)),
+        );
+
+        let cache = Arc::new(PriceCache::new(60, 2000));
+        let spatial = OpportunityDetector::new(cache.clone(), FeesConfig::default(), 0.5, 2);
+        let triangular =
+            TriangularArbitrageDetector::new(cache.clone(), TriangularArbConfig::default(), FeesConfig::default());
+        let path = TriangularPath::new("SOL", "USDC", "RAY", "raydium");
+        let mut statistical = StatisticalArbitrageDetector::new(cache.clone(), StatArbConfig::default());
+
+        let mut found = Vec::new();
+        let slots = 60_000 / settings.simulator.update_interval_ms;
+        for _ in 0..slots {
+            for event in feed.tick() {
+                let WsEvent::AccountUpdate { pubkey, slot, data, .. } = event else { continue };
+                let pool = feed.pools().iter().find(|p| p.pubkey == pubkey).unwrap();
+                let state = decode(pool, &data);
+                let data = PriceData::new(
+                    state.price(),
+                    state.liquidity as u64,
+                    slot,
+                    state.token_a_reserve,
+                    state.token_b_reserve,
+                    state.fee_rate,
+                );
+                cache.update(&pool.pair, &Dex::from(&pool.dex), data).await;
+            }
+            let opportunities = [
+                spatial.scan_pair("SOL-USDC").await,
+                triangular.detect(&path).await,
+                statistical.detect("JUP-USDC", "SOL-USDC", &Dex::Raydium).await,
+            ];
+            found.extend(opportunities.into_iter().flatten().map(|o| o.opportunity_type));
+        }
+
+        for expected in [OpportunityType::Spatial, OpportunityType::Triangular, OpportunityType::Statistical] {
+            assert!(found.contains(&expected), "no {:?} opportunity in a simulated minute: {:?}", expected, found);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_burst_through_the_pipeline_fills_metrics() {
+        use crate::config::BackpressurePolicy;
+        use crate::websocket::event_channel;
+
+        let pools = (0..4)
+            .map(|i| SimulatedPool::new(&format!("Pool{}", i), "SOL-USDC", ["raydium", "orca"][i % 2], (9, 6)).unwrap())
+            .collect();
+        let metrics = Arc::new(PipelineMetrics::default());
+        let shutdown = CancellationToken::new();
+        let config = SimulatorConfig { update_interval_ms: 1, seed: Some(3), ..SimulatorConfig::default() };
+        let mut feed = SimulatedFeed::new(config, pools).with_metrics(metrics.clone()).with_shutdown(shutdown.clone());
+        let (tx, mut rx) = event_channel(256, BackpressurePolicy::Block);
+        feed.set_sender(tx);
+        let queue = rx.metrics();
+        let pools = feed.pools().to_vec();
+        let producer = tokio::spawn(async move { feed.run().await });
+
+        // Let a burst pile up before the consumer starts
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while queue.queued() < 200 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(queue.occupancy() >= 200.0 / 256.0);
+        shutdown.cancel();
+
+        // What the main loop does per account update: decode, cache, time it
+        let cache = PriceCache::new(60, 2000);
+        let mut processed = 0;
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+            let received = std::time::Instant::now();
+            let WsEvent::AccountUpdate { pubkey, slot, data, .. } = event else { continue };
+            let pool = pools.iter().find(|p| p.pubkey == pubkey).unwrap();
+            let state = decode(pool, &data);
+            let price = PriceData::new(state.price(), state.liquidity as u64, slot, 1, 1, state.fee_rate);
+            cache.update(&pool.pair, &Dex::from(&pool.dex), price).await;
+            metrics.record_processing(received.elapsed());
+            processed += 1;
+        }
+        producer.await.unwrap();
+
+        let snapshot = metrics.snapshot();
+        // Every fifth event is a slot
+        assert!(processed >= 160);
+        assert_eq!(snapshot.processed, processed);
+        assert!(snapshot.messages_received >= processed);
+        assert!(snapshot.bytes_received >= processed * 100);
+        let (p50, p99) = (snapshot.processing_p50_us.unwrap(), snapshot.processing_p99_us.unwrap());
+        assert!(p50 <= p99);
+        assert_eq!(queue.queued(), 0);
+        assert_eq!(cache.get_all_dexes("SOL-USDC").len(), 2);
+    }
+}
@@ -0,0 +1,573 @@
+//! Flat-file record of opportunities and prices for offline analysis
+//!
+//! The aggregator hands every group it emits to a `SinkHandle`, so no
+//! opportunity is lost the way a lagging broadcast receiver would lose
+//! them. With `include_prices`, price updates from the API broadcast are
+//! sampled per pair and DEX; a lag there only thins the sample. Records are
+//! appended one per line to files under `[sink] path`: JSON objects, or CSV
+//! rows under a header with a fixed column order. Every strategy of an
+//! aggregated group is written, each with its own id. Files are named
+//! `<kind>-<UTC date>.<ext>`, so a new one starts every day; a size rotation
+//! adds `<kind>-<date>.<n>.<ext>` files once the current one is full.
+//!
+//! Writes happen on a blocking thread fed through an unbounded queue, so a
+//! slow disk only backs up that queue, never detection. Sampling prices
+//! before they are queued keeps its growth down to the opportunity rate.
+
+use crate::api::{ApiMessage, TopicPattern, Topics};
+use crate::config::{Settings, SinkConfig, SinkFormat, SinkRotation};
+use crate::detector::AggregatedOpportunity;
+use crate::models::{Dex, Opportunity};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+const OPPORTUNITY_COLUMNS: &[&str] = &[
+    "id",
+    "detected_at",
+    "type",
+    "pair",
+    "buy_dex",
+    "sell_dex",
+    "buy_price",
+    "sell_price",
+    "buy_slot",
+    "sell_slot",
+    "net_profit_percent",
+    "recommended_size",
+    "size_token",
+    "size_decimals",
+    "expected_profit_base_units",
+    "profit_token",
+    "profit_decimals",
+    "confidence",
+    "persisted_slots",
+    "route",
+];
+
+const PRICE_COLUMNS: &[&str] = &["timestamp", "pair", "dex", "price", "slot"];
+
+/// One price update as written to `prices-*` files
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceRecord {
+    pub timestamp: DateTime<Utc>,
+    pub pair: String,
+    pub dex: Dex,
+    pub price: f64,
+    pub slot: u64,
+}
+
+/// What the writer thread is handed
+#[derive(Debug, Clone)]
+pub enum SinkRecord {
+    Opportunity(Box<Opportunity>),
+    Price(PriceRecord),
+}
+
+/// Queues records for a running `FileSink`; dropping every handle (and
+/// shutting the sink down) lets it drain the queue and flush
+#[derive(Debug, Clone)]
+pub struct SinkHandle {
+    tx: mpsc::UnboundedSender<SinkRecord>,
+}
+
+impl SinkHandle {
+    /// Queue every strategy of an emitted group
+    pub fn record_group(&self, group: &AggregatedOpportunity) {
+        for opportunity in &group.strategies {
+            // Only fails once the writer is gone, at shutdown
+            let _ = self.tx.send(SinkRecord::Opportunity(Box::new(opportunity.clone())));
+        }
+    }
+}
+
+/// Keeps at most one price per pair and DEX every `sample_ms`
+struct PriceSampler {
+    sample_ms: i64,
+    /// (pair, DEX) -> timestamp (ms) of its last kept price
+    last: HashMap<(String, Dex), i64>,
+}
+
+impl PriceSampler {
+    fn new(sample_ms: u64) -> Self {
+        Self { sample_ms: sample_ms as i64, last: HashMap::new() }
+    }
+
+    /// Whether to keep `price`, remembering it if so
+    fn keep(&mut self, price: &PriceRecord) -> bool {
+        let ts = price.timestamp.timestamp_millis();
+        let key = (price.pair.clone(), price.dex.clone());
+        if self.last.get(&key).is_some_and(|last| ts - last < self.sample_ms) {
+            return false;
+        }
+        self.last.insert(key, ts);
+        true
+    }
+}
+
+/// Writes records to rotating files; see the module docs
+pub struct FileSink {
+    format: SinkFormat,
+    opportunities: RotatingFile,
+    prices: Option<RotatingFile>,
+    price_sample_ms: u64,
+}
+
+impl FileSink {
+    /// Build from `[sink]`; `None` when the sink is disabled
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>> {
+        if !settings.sink.enabled {
+            return Ok(None);
+        }
+        Self::new(&settings.sink).map(Some)
+    }
+
+    pub fn new(config: &SinkConfig) -> Result<Self> {
+        let dir = PathBuf::from(&config.path);
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create sink directory {}", dir.display()))?;
+        let rotation = config.rotation()?;
+        let file = |stem, columns| RotatingFile::new(dir.clone(), stem, config.format, rotation, columns);
+        Ok(Self {
+            format: config.format,
+            opportunities: file("opportunities", OPPORTUNITY_COLUMNS),
+            prices: config.include_prices.then(|| file("prices", PRICE_COLUMNS)),
+            price_sample_ms: config.price_sample_ms,
+        })
+    }
+
+    /// Append a record, rotating by `now`; prices are skipped without
+    /// `include_prices`
+    pub fn write(&mut self, record: &SinkRecord, now: DateTime<Utc>) -> Result<()> {
+        match record {
+            SinkRecord::Opportunity(opportunity) => {
+                let line = match self.format {
+                    SinkFormat::Jsonl => serde_json::to_string(opportunity)?,
+                    SinkFormat::Csv => opportunity_row(opportunity),
+                };
+                self.opportunities.write_line(&line, now)
+            }
+            SinkRecord::Price(price) => {
+                let Some(file) = &mut self.prices else { return Ok(()) };
+                let line = match self.format {
+                    SinkFormat::Jsonl => serde_json::to_string(price)?,
+                    SinkFormat::Csv => csv_row([
+                        price.timestamp.to_rfc3339(),
+                        price.pair.clone(),
+                        price.dex.to_string(),
+                        price.price.to_string(),
+                        price.slot.to_string(),
+                    ]),
+                };
+                file.write_line(&line, now)
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.opportunities.flush()?;
+        if let Some(prices) = &mut self.prices {
+            prices.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Record what the returned handle is given, and sampled prices from
+    /// the API broadcast, until shutdown and every handle is dropped; then
+    /// flush
+    pub fn spawn(mut self, topics: &Topics, shutdown: CancellationToken) -> (SinkHandle, JoinHandle<()>) {
+        let (tx, mut records) = mpsc::unbounded_channel::<SinkRecord>();
+        let handle = SinkHandle { tx: tx.clone() };
+        let mut prices = self.prices.is_some().then(|| topics.subscribe_to([TopicPattern::kind("price")]));
+        let mut sampler = PriceSampler::new(self.price_sample_ms);
+        info!(dir = %self.opportunities.dir.display(), prices = self.prices.is_some(), "Recording opportunities to files");
+
+        let writer = tokio::task::spawn_blocking(move || {
+            while let Some(record) = records.blocking_recv() {
+                if let Err(e) = self.write(&record, Utc::now()) {
+                    warn!("Sink write failed: {:#}", e);
+                }
+                // Flush once the queue is drained rather than per record
+                if records.is_empty() {
+                    if let Err(e) = self.flush() {
+                        warn!("Sink flush failed: {:#}", e);
+                    }
+                }
+            }
+            if let Err(e) = self.flush() {
+                warn!("Sink flush failed: {:#}", e);
+            }
+        });
+
+        let task = tokio::spawn(async move {
+            while let Some(rx) = &mut prices {
+                let message = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    message = rx.recv() => message,
+                };
+                match message {
+                    Ok(ApiMessage::PriceUpdate { pair, dex, price, slot, ts }) => {
+                        let record = PriceRecord { timestamp: ts, pair, dex, price, slot };
+                        if sampler.keep(&record) && tx.send(SinkRecord::Price(record)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => debug!("Sink skipped {} price messages", n),
+                    Err(RecvError::Closed) => prices = None,
+                }
+            }
+            shutdown.cancelled().await;
+            // The queue closes, letting the writer drain it and flush, once
+            // the aggregator drops its handle too
+            drop(tx);
+            if let Err(e) = writer.await {
+                warn!("Sink writer panicked: {}", e);
+            }
+        });
+        (handle, task)
+    }
+}
+
+/// One record kind's current file and where the next one goes
+struct RotatingFile {
+    dir: PathBuf,
+    stem: &'static str,
+    format: SinkFormat,
+    rotation: SinkRotation,
+    columns: &'static [&'static str],
+    current: Option<OpenFile>,
+}
+
+struct OpenFile {
+    day: NaiveDate,
+    /// Number of the file within the day, 0 for the first
+    index: u32,
+    bytes: u64,
+    writer: BufWriter<File>,
+}
+
+impl RotatingFile {
+    fn new(
+        dir: PathBuf,
+        stem: &'static str,
+        format: SinkFormat,
+        rotation: SinkRotation,
+        columns: &'static [&'static str],
+    ) -> Self {
+        Self { dir, stem, format, rotation, columns, current: None }
+    }
+
+    fn path(&self, day: NaiveDate, index: u32) -> PathBuf {
+        let ext = match self.format {
+            SinkFormat::Jsonl => "jsonl",
+            SinkFormat::Csv => "csv",
+        };
+        let name = match index {
+            0 => format!("{}-{}.{}", self.stem, day, ext),
+            n => format!("{}-{}.{}.{}", self.stem, day, n, ext),
+        };
+        self.dir.join(name)
+    }
+
+    fn is_full(&self, bytes: u64) -> bool {
+        matches!(self.rotation, SinkRotation::Size(max) if bytes >= max)
+    }
+
+    fn write_line(&mut self, line: &str, now: DateTime<Utc>) -> Result<()> {
+        let day = now.date_naive();
+        let next = match &self.current {
+            Some(file) if file.day != day => Some(0),
+            Some(file) if self.is_full(file.bytes) => Some(file.index + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+        if let Some(index) = next {
+            self.open(day, index)?;
+        }
+        let Some(file) = &mut self.current else { unreachable!("opened above") };
+        file.writer.write_all(line.as_bytes())?;
+        file.writer.write_all(b"\n")?;
+        file.bytes += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Open the first file from `index` on that still has room, appending
+    /// to it after a restart
+    fn open(&mut self, day: NaiveDate, mut index: u32) -> Result<()> {
+        self.flush()?;
+        let (path, bytes) = loop {
+            let path = self.path(day, index);
+            let bytes = file_len(&path);
+            if !self.is_full(bytes) {
+                break (path, bytes);
+            }
+            index += 1;
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut open = OpenFile { day, index, bytes, writer: BufWriter::new(file) };
+        if self.format == SinkFormat::Csv && bytes == 0 {
+            let header = self.columns.join(",");
+            open.writer.write_all(header.as_bytes())?;
+            open.writer.write_all(b"\n")?;
+            open.bytes += header.len() as u64 + 1;
+        }
+        self.current = Some(open);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(file) = &mut self.current {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn file_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |meta| meta.len())
+}
+
+/// Columns of `OPPORTUNITY_COLUMNS`, in order
+fn opportunity_row(opportunity: &Opportunity) -> String {
+    csv_row([
+        opportunity.id.to_string(),
+        opportunity.detected_at.to_rfc3339(),
+        opportunity.opportunity_type.to_string(),
+        opportunity.token_pair.clone(),
+        opportunity.buy_dex.to_string(),
+        opportunity.sell_dex.to_string(),
+        opportunity.buy_price.to_string(),
+        opportunity.sell_price.to_string(),
+        opportunity.buy_slot.to_string(),
+        opportunity.sell_slot.to_string(),
+        opportunity.net_profit_percent.to_string(),
+        opportunity.recommended_size.to_string(),
+        opportunity.size_token.clone(),
+        opportunity.size_decimals.to_string(),
+        opportunity.expected_profit_base_units.to_string(),
+        opportunity.profit_token.clone(),
+        opportunity.profit_decimals.to_string(),
+        opportunity.confidence.to_string(),
+        opportunity.persisted_slots.to_string(),
+        opportunity.route(),
+    ])
+}
+
+/// Fields joined with commas, quoting those that need it (RFC 4180)
+fn csv_row<const N: usize>(fields: [String; N]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::models::{LegSide, OpportunityType, PriceData, RouteLeg};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sink-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn config(dir: &Path, format: SinkFormat, rotate: &str) -> SinkConfig {
+        SinkConfig {
+            enabled: true,
+            path: dir.display().to_string(),
+            format,
+            rotate: rotate.to_string(),
+            include_prices: true,
+            price_sample_ms: 1000,
+        }
+    }
+
+    fn opportunity(pair: &str) -> SinkRecord {
+        let leg = |dex: &str, side, price| RouteLeg::new(pair, dex, side, &PriceData::new(price, 1, 7, 1, 1, 0.003));
+        let at = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let mut opportunity = Opportunity::builder()
+            .opportunity_type(OpportunityType::Spatial)
+            .token_pair(pair)
+            .buy("raydium", 100.0, 7)
+            .sell("orca", 101.5, 7)
+            .legs(vec![leg("raydium", LegSide::Buy, 100.0), leg("orca", LegSide::Sell, 101.5)])
+            .net_profit_percent(0.75)
+            .recommended_size(2_000_000_000)
+            .confidence(0.5)
+            .worst_case_profit_percent(0.0)
+            .detected_at(at)
+            .valid_until(at)
+            .build()
+            .unwrap();
+        opportunity.denominate("SOL", 9, "USDC", 6, 100.0);
+        SinkRecord::Opportunity(Box::new(opportunity))
+    }
+
+    fn price(ms: i64) -> SinkRecord {
+        SinkRecord::Price(PriceRecord {
+            timestamp: Utc.timestamp_millis_opt(ms).unwrap(),
+            pair: "SOL-USDC".to_string(),
+            dex: Dex::Orca,
+            price: 100.25,
+            slot: 9,
+        })
+    }
+
+    #[test]
+    fn test_csv_header_and_rows() {
+        let dir = temp_dir("csv");
+        let mut sink = FileSink::new(&config(&dir, SinkFormat::Csv, "daily")).unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let SinkRecord::Opportunity(first) = opportunity("SOL-USDC") else { unreachable!() };
+        sink.write(&SinkRecord::Opportunity(first.clone()), now).unwrap();
+        sink.write(&opportunity("SOL,USDC"), now).unwrap();
+        for ms in [0, 1000] {
+            sink.write(&price(ms), now).unwrap();
+        }
+        sink.flush().unwrap();
+
+        let text = std::fs::read_to_string(dir.join("opportunities-2026-03-01.csv")).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], OPPORTUNITY_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},2026-03-01T12:00:00+00:00,spatial,SOL-USDC,raydium,orca,100,101.5,7,7,0.75,2000000000,\
+                 SOL,9,1500000,USDC,6,0.5,1,\
+                 buy SOL-USDC on raydium @ 100 -> sell SOL-USDC on orca @ 101.5",
+                first.id
+            )
+        );
+        // Fields with commas are quoted
+        assert!(lines[2].contains(",\"SOL,USDC\","), "{}", lines[2]);
+        assert_eq!(lines.len(), 3);
+
+        let prices = std::fs::read_to_string(dir.join("prices-2026-03-01.csv")).unwrap();
+        assert_eq!(
+            prices.lines().collect::<Vec<_>>(),
+            vec![
+                "timestamp,pair,dex,price,slot",
+                "1970-01-01T00:00:00+00:00,SOL-USDC,orca,100.25,9",
+                "1970-01-01T00:00:01+00:00,SOL-USDC,orca,100.25,9",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prices_are_sampled_per_pair_and_dex() {
+        let mut sampler = PriceSampler::new(1000);
+        let SinkRecord::Price(mut other_dex) = price(400) else { unreachable!() };
+        other_dex.dex = Dex::Raydium;
+        let kept: Vec<bool> = [price(0), price(400), SinkRecord::Price(other_dex), price(1000)]
+            .iter()
+            .map(|record| matches!(record, SinkRecord::Price(price) if sampler.keep(price)))
+            .collect();
+        assert_eq!(kept, [true, false, true, true]);
+    }
+
+    #[tokio::test]
+    async fn test_every_strategy_of_a_burst_is_recorded() {
+        use crate::detector::AggregatedOpportunity;
+
+        let dir = temp_dir("burst");
+        let sink = FileSink::new(&config(&dir, SinkFormat::Jsonl, "daily")).unwrap();
+        // Far more than a broadcast receiver would hold
+        let topics = Topics::new(16);
+        let shutdown = CancellationToken::new();
+        let (handle, task) = sink.spawn(&topics, shutdown.clone());
+        let SinkRecord::Opportunity(best) = opportunity("SOL-USDC") else { unreachable!() };
+        let group = AggregatedOpportunity {
+            fingerprint: vec!["SOL-USDC@orca".to_string()],
+            best: (*best).clone(),
+            strategies: vec![(*best).clone(), (*best).clone()],
+        };
+        for _ in 0..1000 {
+            handle.record_group(&group);
+        }
+        shutdown.cancel();
+        drop(handle);
+        task.await.unwrap();
+
+        let lines: usize = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap().lines().count())
+            .sum();
+        assert_eq!(lines, 2000);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_files_rotate_by_size_and_day() {
+        let dir = temp_dir("rotate");
+        // A 1-byte limit leaves exactly one record per file
+        let mut sink = FileSink::new(&config(&dir, SinkFormat::Jsonl, "1")).unwrap();
+        let day_1 = Utc.with_ymd_and_hms(2026, 3, 1, 23, 59, 0).unwrap();
+        for _ in 0..3 {
+            sink.write(&opportunity("SOL-USDC"), day_1).unwrap();
+        }
+        sink.write(&opportunity("SOL-USDC"), day_1 + chrono::Duration::minutes(2)).unwrap();
+        sink.flush().unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "opportunities-2026-03-01.1.jsonl",
+                "opportunities-2026-03-01.2.jsonl",
+                "opportunities-2026-03-01.jsonl",
+                "opportunities-2026-03-02.jsonl",
+            ]
+        );
+        let first = std::fs::read_to_string(dir.join("opportunities-2026-03-01.jsonl")).unwrap();
+        let record: serde_json::Value = serde_json::from_str(first.lines().next().unwrap()).unwrap();
+        assert_eq!(record["token_pair"], "SOL-USDC");
+
+        // A restart skips the day's full files instead of overwriting them
+        drop(sink);
+        let mut sink = FileSink::new(&config(&dir, SinkFormat::Jsonl, "1")).unwrap();
+        let day_2 = day_1 + chrono::Duration::minutes(3);
+        sink.write(&opportunity("SOL-USDC"), day_2).unwrap();
+        sink.flush().unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("opportunities-2026-03-02.jsonl").lines().count(), 1);
+        assert_eq!(read("opportunities-2026-03-02.1.jsonl").lines().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_parses_sizes() {
+        assert_eq!("daily".parse::<SinkRotation>().unwrap(), SinkRotation::Daily);
+        assert_eq!("64MB".parse::<SinkRotation>().unwrap(), SinkRotation::Size(64 << 20));
+        assert_eq!("512 kb".parse::<SinkRotation>().unwrap(), SinkRotation::Size(512 << 10));
+        assert_eq!("4096".parse::<SinkRotation>().unwrap(), SinkRotation::Size(4096));
+        assert!("0MB".parse::<SinkRotation>().is_err());
+        assert!("weekly".parse::<SinkRotation>().is_err());
+    }
+}
@@ -1,15 +1,37 @@
 //! Health check utilities
+//!
+//! `HealthTracker` is created at startup and told about every price written
+//! to the cache, so uptime and update recency are measured rather than
+//! passed in, overall and per DEX. A DEX is stale past the cache's own
+//! staleness threshold for it.
 
+use crate::cache::StaleThresholds;
+use crate::models::Dex;
+use dashmap::DashMap;
 use serde::Serialize;
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Staleness threshold of a tracker not sharing the cache's
+const DEFAULT_STALE_THRESHOLD_MS: u64 = 5000;
 
 /// Health status of the system
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct HealthStatus {
     pub healthy: bool,
     pub cache_entries: usize,
     pub websocket_connected: bool,
     pub last_update_ms: u64,
     pub uptime_seconds: u64,
+    /// DEX -> ms since its latest cache update
+    #[serde(default)]
+    pub dex_last_update_ms: BTreeMap<String, u64>,
+    /// DEXes with no update within their stale threshold, quietest first
+    #[serde(default)]
+    pub stale_dexes: Vec<String>,
 }
 
 impl HealthStatus {
@@ -20,7 +42,32 @@ impl HealthStatus {
             websocket_connected: false,
             last_update_ms: 0,
             uptime_seconds: 0,
+            dex_last_update_ms: BTreeMap::new(),
+            stale_dexes: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for HealthStatus {
+    /// One line for logs, like "unhealthy: ws connected, 42 entries, last
+    /// update 7000ms ago, up 3600s, meteora stale 45s"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: ws {}, {} entries, ",
+            if self.healthy { "healthy" } else { "unhealthy" },
+            if self.websocket_connected { "connected" } else { "disconnected" },
+            self.cache_entries
+        )?;
+        match self.last_update_ms {
+            u64::MAX => write!(f, "no updates yet")?,
+            ms => write!(f, "last update {}ms ago", ms)?,
+        }
+        write!(f, ", up {}s", self.uptime_seconds)?;
+        for dex in &self.stale_dexes {
+            write!(f, ", {} stale {}s", dex, self.dex_last_update_ms[dex] / 1000)?;
         }
+        Ok(())
     }
 }
 
@@ -36,10 +83,9 @@ pub fn check_health(
     websocket_connected: bool,
     last_update_ms: u64,
     uptime_seconds: u64,
+    stale_threshold_ms: u64,
 ) -> HealthStatus {
-    let stale_threshold = 5000; // 5 seconds
-
-    let healthy = websocket_connected && last_update_ms < stale_threshold;
+    let healthy = websocket_connected && last_update_ms < stale_threshold_ms;
 
     HealthStatus {
         healthy,
@@ -47,6 +93,114 @@ pub fn check_health(
         websocket_connected,
         last_update_ms,
         uptime_seconds,
+        ..HealthStatus::default()
+    }
+}
+
+/// Process uptime and when prices last reached the cache, overall and per DEX
+///
+/// Times are stored as ms since start + 1 (0 = never), so `record_update`
+/// is a lock-free store on the hot path once a DEX has been seen.
+#[derive(Debug)]
+pub struct HealthTracker {
+    started: Instant,
+    last_update: AtomicU64,
+    dexes: DashMap<Dex, AtomicU64>,
+    stale_thresholds: Arc<RwLock<StaleThresholds>>,
+}
+
+impl Default for HealthTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthTracker {
+    pub fn new() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    fn started_at(started: Instant) -> Self {
+        Self {
+            started,
+            last_update: AtomicU64::new(0),
+            dexes: DashMap::new(),
+            stale_thresholds: Arc::new(RwLock::new(StaleThresholds::new(DEFAULT_STALE_THRESHOLD_MS))),
+        }
+    }
+
+    /// Judge staleness by these thresholds, e.g. `PriceCache::stale_thresholds`
+    pub fn with_stale_thresholds(mut self, stale_thresholds: Arc<RwLock<StaleThresholds>>) -> Self {
+        self.stale_thresholds = stale_thresholds;
+        self
+    }
+
+    /// A price from `dex` was written to the cache
+    pub fn record_update(&self, dex: &Dex) {
+        self.record_update_at(dex, Instant::now());
+    }
+
+    fn record_update_at(&self, dex: &Dex, at: Instant) {
+        let tick = at.saturating_duration_since(self.started).as_millis() as u64 + 1;
+        self.last_update.fetch_max(tick, Ordering::Relaxed);
+        match self.dexes.get(dex) {
+            Some(last) => {
+                last.fetch_max(tick, Ordering::Relaxed);
+            }
+            None => {
+                self.dexes.entry(dex.clone()).or_default().fetch_max(tick, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Time since the latest update from any DEX; `None` before the first
+    pub fn last_update_age(&self) -> Option<Duration> {
+        self.age_at(self.last_update.load(Ordering::Relaxed), Instant::now())
+    }
+
+    fn age_at(&self, tick: u64, now: Instant) -> Option<Duration> {
+        let at = self.started + Duration::from_millis(tick.checked_sub(1)?);
+        Some(now.saturating_duration_since(at))
+    }
+
+    pub fn status(&self, cache_entries: usize, websocket_connected: bool) -> HealthStatus {
+        self.status_at(cache_entries, websocket_connected, Instant::now())
+    }
+
+    fn status_at(&self, cache_entries: usize, websocket_connected: bool, now: Instant) -> HealthStatus {
+        let ms = |tick: u64| self.age_at(tick, now).map(|age| age.as_millis() as u64);
+        let last_update_ms = ms(self.last_update.load(Ordering::Relaxed)).unwrap_or(u64::MAX);
+        let uptime_seconds = now.saturating_duration_since(self.started).as_secs();
+        let ages: Vec<(Dex, u64)> = self
+            .dexes
+            .iter()
+            .filter_map(|entry| Some((entry.key().clone(), ms(entry.value().load(Ordering::Relaxed))?)))
+            .collect();
+        let mut stale_dexes: Vec<String> = {
+            let thresholds = self.stale_thresholds.read().unwrap_or_else(|e| e.into_inner());
+            ages.iter()
+                .filter(|(dex, ms)| *ms >= thresholds.dex_threshold_ms(dex))
+                .map(|(dex, _)| dex.to_string())
+                .collect()
+        };
+        let dex_last_update_ms: BTreeMap<String, u64> =
+            ages.into_iter().map(|(dex, ms)| (dex.to_string(), ms)).collect();
+        stale_dexes.sort_by_key(|dex| std::cmp::Reverse(dex_last_update_ms[dex]));
+        // Healthy while any DEX is within its threshold
+        let fresh = dex_last_update_ms.len() > stale_dexes.len();
+        HealthStatus {
+            healthy: websocket_connected && fresh,
+            cache_entries,
+            websocket_connected,
+            last_update_ms,
+            uptime_seconds,
+            dex_last_update_ms,
+            stale_dexes,
+        }
     }
 }
 
@@ -56,14 +210,74 @@ mod tests {
 
     #[test]
     fn test_health_check() {
-        let status = check_health(10, true, 100, 3600);
+        let status = check_health(10, true, 100, 3600, 5000);
         assert!(status.healthy);
+        assert!(!check_health(10, true, 5000, 3600, 5000).healthy);
         assert_eq!(status.cache_entries, 10);
     }
 
     #[test]
     fn test_unhealthy_when_disconnected() {
-        let status = check_health(0, false, 100, 60);
+        let status = check_health(0, false, 100, 60, 5000);
+        assert!(!status.healthy);
+    }
+
+    #[test]
+    fn test_tracker_measures_uptime_and_updates() {
+        let start = Instant::now();
+        let tracker = HealthTracker::started_at(start);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let status = tracker.status_at(0, true, at(2_500));
+        assert_eq!((status.uptime_seconds, status.last_update_ms), (2, u64::MAX));
+        assert!(!status.healthy);
+        assert!(status.dex_last_update_ms.is_empty());
+
+        tracker.record_update_at(&Dex::Raydium, at(3_000));
+        tracker.record_update_at(&Dex::Meteora, at(4_000));
+        tracker.record_update_at(&Dex::Raydium, at(10_000));
+        // Out of order: doesn't move the clock back
+        tracker.record_update_at(&Dex::Raydium, at(9_000));
+
+        let status = tracker.status_at(12, true, at(11_000));
+        assert!(status.healthy);
+        assert_eq!((status.cache_entries, status.uptime_seconds, status.last_update_ms), (12, 11, 1_000));
+        assert_eq!(
+            status.dex_last_update_ms,
+            BTreeMap::from([("meteora".to_string(), 7_000), ("raydium".to_string(), 1_000)])
+        );
+        assert_eq!(status.stale_dexes, vec!["meteora"]);
+        assert!(status.to_string().ends_with("up 11s, meteora stale 7s"), "{}", status);
+
+        // Everything goes quiet
+        let status = tracker.status_at(12, true, at(55_000));
         assert!(!status.healthy);
+        assert_eq!(status.last_update_ms, 45_000);
+        assert_eq!(status.stale_dexes, vec!["meteora", "raydium"]);
+        assert_eq!(
+            status.to_string(),
+            "unhealthy: ws connected, 12 entries, last update 45000ms ago, up 55s, meteora stale 51s, raydium stale 45s"
+        );
+    }
+
+    #[test]
+    fn test_staleness_follows_the_shared_thresholds() {
+        let start = Instant::now();
+        let thresholds = Arc::new(RwLock::new(StaleThresholds::new(2_000)));
+        let tracker = HealthTracker::started_at(start).with_stale_thresholds(thresholds.clone());
+        let at = |ms| start + Duration::from_millis(ms);
+        tracker.record_update_at(&Dex::Raydium, at(1_000));
+        tracker.record_update_at(&Dex::Meteora, at(1_000));
+
+        let status = tracker.status_at(2, true, at(4_000));
+        assert!(!status.healthy);
+        assert_eq!(status.stale_dexes.len(), 2);
+
+        // A reload gives meteora longer
+        let overrides = std::collections::HashMap::from([("meteora".to_string(), 10_000)]);
+        *thresholds.write().unwrap() = StaleThresholds::from_overrides(2_000, &overrides);
+        let status = tracker.status_at(2, true, at(4_000));
+        assert!(status.healthy);
+        assert_eq!(status.stale_dexes, vec!["raydium"]);
     }
 }
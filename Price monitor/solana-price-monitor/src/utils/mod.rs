@@ -2,4 +2,4 @@
 
 mod health;
 
-pub use health::{HealthStatus, check_health};
+pub use health::{HealthStatus, HealthTracker, check_health};
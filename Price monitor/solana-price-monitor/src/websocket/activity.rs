@@ -0,0 +1,341 @@
+//! Realized swap activity per pool, from `logsSubscribe`
+//!
+//! With `websocket.swap_activity` on, the manager adds a `logsSubscribe`
+//! mentioning each pool account. Filtering on the DEX program id instead
+//! would carry every swap on the DEX without saying which pool it went
+//! through; a mention names the pool by construction. Each successful
+//! transaction whose logs show a swap by the pool's program counts once;
+//! amounts aren't decoded.
+//!
+//! Detectors weigh swaps per minute into confidence once a full window has
+//! been observed, and `GET /activity` reports them.
+
+use base64::Engine;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::decoder::program_id;
+use crate::models::Dex;
+
+/// Swaps are counted over this trailing window
+pub const ACTIVITY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Swaps per minute at which a pool counts as fully active
+const ACTIVE_SWAPS_PER_MINUTE: f64 = 10.0;
+
+/// Share of an opportunity's confidence given to pool activity
+const CONFIDENCE_WEIGHT: f64 = 0.2;
+
+/// `ray_log` event types of Raydium AMM v4 swaps (`SwapBaseIn`, `SwapBaseOut`)
+const RAYDIUM_SWAP_LOGS: [u8; 2] = [3, 4];
+
+/// Whether a transaction's logs show a swap executed by `dex`'s program
+///
+/// Log lines are attributed to the program whose invocation is innermost
+/// when they're written. Anchor programs (Orca, Meteora) log the
+/// instruction name; Raydium AMM v4 logs a `ray_log` whose first byte is
+/// the event type.
+pub fn is_swap(dex: &Dex, logs: &[String]) -> bool {
+    let Some(program) = program_id(dex) else {
+        return false;
+    };
+    let mut stack: Vec<&str> = Vec::new();
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(message) = rest.strip_prefix("log: ") {
+            if stack.last() == Some(&program) && is_swap_message(dex, message) {
+                return true;
+            }
+        } else if let Some((id, tail)) = rest.split_once(' ') {
+            if tail.starts_with("invoke [") {
+                stack.push(id);
+            } else if tail == "success" || tail.starts_with("failed") {
+                stack.pop();
+            }
+        }
+    }
+    false
+}
+
+fn is_swap_message(dex: &Dex, message: &str) -> bool {
+    if *dex == Dex::Raydium {
+        return message
+            .strip_prefix("ray_log: ")
+            .and_then(|log| base64::engine::general_purpose::STANDARD.decode(log.trim()).ok())
+            .is_some_and(|event| event.first().is_some_and(|kind| RAYDIUM_SWAP_LOGS.contains(kind)));
+    }
+    message.strip_prefix("Instruction: ").is_some_and(|name| name.contains("Swap"))
+}
+
+/// Confidence with `factor` (0-1 activity) blended in
+pub fn weigh_confidence(confidence: f64, factor: f64) -> f64 {
+    (confidence * (1.0 - CONFIDENCE_WEIGHT) + factor * CONFIDENCE_WEIGHT).clamp(0.0, 1.0)
+}
+
+/// Activity of one pool, as reported by `GET /activity`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolActivity {
+    pub pubkey: String,
+    pub pair: String,
+    pub dex: Dex,
+    /// Swaps in the trailing `ACTIVITY_WINDOW`
+    pub swaps_per_minute: u64,
+    /// Swaps since tracking started
+    pub total_swaps: u64,
+    /// `None` before the first swap
+    pub ms_since_swap: Option<u64>,
+    /// Swaps per minute scaled to 0-1; `None` until a full window was observed
+    pub activity_factor: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct PoolSwaps {
+    recent: VecDeque<Instant>,
+    total: u64,
+}
+
+impl PoolSwaps {
+    /// `recent` is in arrival order, so the swaps still in the window are a suffix
+    fn per_minute(&self, now: Instant) -> u64 {
+        let expired = self.recent.partition_point(|at| now.duration_since(*at) >= ACTIVITY_WINDOW);
+        (self.recent.len() - expired) as u64
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Pubkey -> (pair, dex) of monitored pools
+    pools: HashMap<String, (String, Dex)>,
+    swaps: HashMap<String, PoolSwaps>,
+    /// When logs subscriptions were first requested
+    started: Option<Instant>,
+}
+
+impl Inner {
+    fn factor(&self, pubkey: &str, now: Instant) -> Option<f64> {
+        let started = self.started?;
+        if now.duration_since(started) < ACTIVITY_WINDOW {
+            return None;
+        }
+        let per_minute = self.swaps.get(pubkey).map_or(0, |swaps| swaps.per_minute(now));
+        Some((per_minute as f64 / ACTIVE_SWAPS_PER_MINUTE).min(1.0))
+    }
+}
+
+/// Swap counts per pool, shared between the feed, detectors and the API
+///
+/// Like the subscription book, one instance outlives reconnects and
+/// manager restarts.
+#[derive(Debug, Clone, Default)]
+pub struct SwapActivity {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl SwapActivity {
+    /// Name the pair and DEX of each pool account
+    pub fn set_pools(&self, pools: HashMap<String, (String, Dex)>) {
+        self.inner.write().unwrap().pools = pools;
+    }
+
+    /// DEX of a monitored pool
+    pub(crate) fn dex_of(&self, pubkey: &str) -> Option<Dex> {
+        self.inner.read().unwrap().pools.get(pubkey).map(|(_, dex)| dex.clone())
+    }
+
+    /// Logs subscriptions were requested; the first call starts the clock
+    pub(crate) fn start(&self) {
+        self.start_at(Instant::now());
+    }
+
+    fn start_at(&self, at: Instant) {
+        self.inner.write().unwrap().started.get_or_insert(at);
+    }
+
+    /// Whether swaps are being tracked at all
+    pub fn is_tracking(&self) -> bool {
+        self.inner.read().unwrap().started.is_some()
+    }
+
+    /// Count a swap through `pubkey`
+    pub(crate) fn record(&self, pubkey: &str) {
+        self.record_at(pubkey, Instant::now());
+    }
+
+    fn record_at(&self, pubkey: &str, at: Instant) {
+        let mut inner = self.inner.write().unwrap();
+        let swaps = inner.swaps.entry(pubkey.to_string()).or_default();
+        while swaps.recent.front().is_some_and(|first| at.duration_since(*first) >= ACTIVITY_WINDOW) {
+            swaps.recent.pop_front();
+        }
+        swaps.recent.push_back(at);
+        swaps.total += 1;
+    }
+
+    /// Lowest activity factor across `(pair, dex)` legs; `None` while
+    /// warming up or when a leg's pool isn't monitored
+    pub fn factor(&self, legs: &[(&str, &Dex)]) -> Option<f64> {
+        self.factor_at(legs, Instant::now())
+    }
+
+    fn factor_at(&self, legs: &[(&str, &Dex)], now: Instant) -> Option<f64> {
+        let inner = self.inner.read().unwrap();
+        legs.iter()
+            .map(|(pair, dex)| {
+                let (pubkey, _) = inner.pools.iter().find(|(_, (p, d))| p == pair && d == *dex)?;
+                inner.factor(pubkey, now)
+            })
+            .try_fold(1.0f64, |lowest, factor| Some(lowest.min(factor?)))
+    }
+
+    /// Every monitored pool, busiest first; empty unless tracking
+    pub fn report(&self) -> Vec<PoolActivity> {
+        let inner = self.inner.read().unwrap();
+        if inner.started.is_none() {
+            return Vec::new();
+        }
+        let now = Instant::now();
+        let mut report: Vec<PoolActivity> = inner
+            .pools
+            .iter()
+            .map(|(pubkey, (pair, dex))| {
+                let swaps = inner.swaps.get(pubkey);
+                PoolActivity {
+                    pubkey: pubkey.clone(),
+                    pair: pair.clone(),
+                    dex: dex.clone(),
+                    swaps_per_minute: swaps.map_or(0, |s| s.per_minute(now)),
+                    total_swaps: swaps.map_or(0, |s| s.total),
+                    ms_since_swap: swaps
+                        .and_then(|s| s.recent.back())
+                        .map(|at| now.duration_since(*at).as_millis() as u64),
+                    activity_factor: inner.factor(pubkey, now),
+                }
+            })
+            .collect();
+        report.sort_by(|a, b| b.swaps_per_minute.cmp(&a.swaps_per_minute).then_with(|| a.pubkey.cmp(&b.pubkey)));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::websocket::messages::{self, Incoming, Notification};
+
+    const ORCA: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+    const RAYDIUM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+    const JUPITER: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+    /// A `logsNotification` as the node sends it
+    fn fixture(err: &str, logs: &[String]) -> Vec<String> {
+        let frame = format!(
+            r#"{{"jsonrpc":"2.0","method":"logsNotification","params":{{"subscription":7,"result":{{"context":{{"slot":281000000}},"value":{{"signature":"5h6x","err":{},"logs":{}}}}}}}}}"#,
+            err,
+            serde_json::to_string(logs).unwrap()
+        );
+        let Incoming::Notification(Notification::LogsNotification(params)) = messages::parse(&frame).unwrap() else {
+            panic!("not a logs notification");
+        };
+        assert_eq!(params.result.value.err.is_some(), err != "null");
+        params.result.value.logs
+    }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_swaps_are_recognised_in_log_fixtures() {
+        // Jupiter route through a whirlpool
+        let orca_swap = fixture(
+            "null",
+            &lines(&[
+                &format!("Program {} invoke [1]", JUPITER),
+                "Program log: Instruction: Route",
+                &format!("Program {} invoke [2]", ORCA),
+                "Program log: Instruction: Swap",
+                "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]",
+                "Program log: Instruction: Transfer",
+                "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+                &format!("Program {} consumed 41230 of 180000 compute units", ORCA),
+                &format!("Program {} success", ORCA),
+                &format!("Program {} success", JUPITER),
+            ]),
+        );
+        assert!(is_swap(&Dex::Orca, &orca_swap));
+        assert!(!is_swap(&Dex::Meteora, &orca_swap));
+
+        // A position change mentions the pool but isn't a swap
+        let orca_liquidity = lines(&[
+            &format!("Program {} invoke [1]", ORCA),
+            "Program log: Instruction: IncreaseLiquidity",
+            &format!("Program {} success", ORCA),
+        ]);
+        assert!(!is_swap(&Dex::Orca, &orca_liquidity));
+
+        // Raydium's ray_log: event type 3 (SwapBaseIn) vs 1 (Deposit)
+        let ray_log = |kind: u8| {
+            let mut event = vec![kind];
+            event.extend_from_slice(&[0; 56]);
+            base64::engine::general_purpose::STANDARD.encode(event)
+        };
+        let raydium = |kind| {
+            fixture(
+                "null",
+                &lines(&[
+                    &format!("Program {} invoke [1]", RAYDIUM),
+                    &format!("Program log: ray_log: {}", ray_log(kind)),
+                    &format!("Program {} success", RAYDIUM),
+                ]),
+            )
+        };
+        assert!(is_swap(&Dex::Raydium, &raydium(3)));
+        assert!(!is_swap(&Dex::Raydium, &raydium(1)));
+
+        // Swap instructions logged by another program don't count
+        let other = lines(&[&format!("Program {} invoke [1]", JUPITER), "Program log: Instruction: Swap"]);
+        assert!(!is_swap(&Dex::Orca, &other));
+        assert!(!fixture(r#"{"InstructionError":[2,{"Custom":6001}]}"#, &orca_swap).is_empty());
+    }
+
+    #[test]
+    fn test_activity_factor_after_a_full_window() {
+        let activity = SwapActivity::default();
+        activity.set_pools(HashMap::from([
+            ("PoolA".to_string(), ("SOL-USDC".to_string(), Dex::Orca)),
+            ("PoolB".to_string(), ("SOL-USDC".to_string(), Dex::Raydium)),
+        ]));
+        assert!(activity.report().is_empty());
+
+        let now = Instant::now();
+        let start = now - Duration::from_secs(90);
+        activity.start_at(start);
+        // Only swaps within the last minute count
+        activity.record_at("PoolA", start);
+        for _ in 0..5 {
+            activity.record_at("PoolA", now);
+        }
+        let legs = [("SOL-USDC", &Dex::Orca), ("SOL-USDC", &Dex::Raydium)];
+        assert_eq!(activity.factor_at(&legs[..1], now), Some(0.5));
+        assert_eq!(activity.factor_at(&legs, now), Some(0.0));
+        assert_eq!(activity.factor_at(&[("SOL-USDC", &Dex::Meteora)], now), None);
+        assert_eq!(activity.factor_at(&legs[..1], start + Duration::from_secs(30)), None);
+
+        // The burst ages out a full window later
+        let later = now + ACTIVITY_WINDOW;
+        assert_eq!(activity.factor_at(&legs[..1], later - Duration::from_millis(1)), Some(0.5));
+        assert_eq!(activity.factor_at(&legs[..1], later), Some(0.0));
+
+        let report = activity.report();
+        assert_eq!((report[0].pubkey.as_str(), report[0].swaps_per_minute, report[0].total_swaps), ("PoolA", 5, 6));
+
+        // A route through the idle pool loses more confidence than one through the busy pool
+        let busy = weigh_confidence(0.8, activity.factor_at(&legs[..1], now).unwrap());
+        let idle = weigh_confidence(0.8, activity.factor_at(&legs, now).unwrap());
+        assert!(idle < busy && busy < 0.8, "idle {idle}, busy {busy}");
+    }
+}
@@ -0,0 +1,206 @@
+//! Typed JSON-RPC messages from the Solana PubSub API
+//!
+//! Responses carry the `id` of our request; notifications carry a `method`
+//! and `params`. Notifications we don't model still parse, as `Unknown`, so
+//! new methods can be surfaced without breaking the reader.
+
+use anyhow::{Context as _, Result};
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Any message received on the PubSub connection
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Incoming {
+    Response(Response),
+    Notification(Notification),
+    /// A notification whose method (or shape) isn't modelled here
+    Unknown { method: String, params: Value },
+}
+
+/// Parse one text frame
+pub fn parse(text: &str) -> Result<Incoming> {
+    serde_json::from_str(text).context("Unrecognised PubSub message")
+}
+
+/// Reply to a request we sent (subscribe or unsubscribe)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Response {
+    pub id: u64,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    /// Subscription id of a successful `*Subscribe` response
+    pub fn subscription_id(&self) -> Option<u64> {
+        self.result.as_ref().and_then(Value::as_u64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+/// Notifications for the subscriptions the manager makes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "camelCase")]
+pub enum Notification {
+    AccountNotification(NotificationParams<WithContext<Account>>),
+    ProgramNotification(NotificationParams<WithContext<KeyedAccount>>),
+    SlotNotification(NotificationParams<SlotInfo>),
+    LogsNotification(NotificationParams<WithContext<Logs>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NotificationParams<T> {
+    pub subscription: u64,
+    pub result: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WithContext<T> {
+    pub context: RpcContext,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RpcContext {
+    pub slot: u64,
+}
+
+/// Account contents as sent with `encoding: "base64"`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Account {
+    pub data: AccountData,
+    pub owner: String,
+    pub lamports: u64,
+}
+
+/// `[data, encoding]`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AccountData(pub String, pub String);
+
+impl AccountData {
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        if self.1 != "base64" {
+            anyhow::bail!("unsupported account encoding \"{}\"", self.1);
+        }
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.0)
+            .context("Invalid base64 account data")
+    }
+}
+
+/// A program notification's account, named by its pubkey
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeyedAccount {
+    pub pubkey: String,
+    pub account: Account,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SlotInfo {
+    pub slot: u64,
+    pub parent: u64,
+    pub root: u64,
+}
+
+/// Log messages of one transaction, from `logsSubscribe`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Logs {
+    pub signature: String,
+    /// Set when the transaction failed
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subscription_confirmation() {
+        let message = parse(r#"{"jsonrpc":"2.0","result":23784,"id":1}"#).unwrap();
+        let Incoming::Response(response) = message else { panic!("{:?}", message) };
+        assert_eq!((response.id, response.subscription_id()), (1, Some(23784)));
+        assert!(response.error.is_none());
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let message = parse(
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid param: WrongSize"},"id":3}"#,
+        )
+        .unwrap();
+        let Incoming::Response(response) = message else { panic!("{:?}", message) };
+        assert_eq!(response.id, 3);
+        assert_eq!(response.subscription_id(), None);
+        assert_eq!(response.error.unwrap().to_string(), "Invalid param: WrongSize (-32602)");
+    }
+
+    #[test]
+    fn test_parse_account_notification() {
+        let fixture = r#"{
+            "jsonrpc": "2.0",
+            "method": "accountNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": 5199307 },
+                    "value": {
+                        "data": ["AQID", "base64"],
+                        "executable": false,
+                        "lamports": 33594,
+                        "owner": "11111111111111111111111111111111",
+                        "rentEpoch": 635,
+                        "space": 3
+                    }
+                },
+                "subscription": 23784
+            }
+        }"#;
+        let Incoming::Notification(Notification::AccountNotification(params)) = parse(fixture).unwrap() else {
+            panic!("expected an account notification");
+        };
+        assert_eq!(params.subscription, 23784);
+        assert_eq!(params.result.context.slot, 5199307);
+        let account = params.result.value;
+        assert_eq!((account.owner.as_str(), account.lamports), ("11111111111111111111111111111111", 33594));
+        assert_eq!(account.data.decode().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_slot_notification() {
+        let fixture = r#"{"jsonrpc":"2.0","method":"slotNotification","params":{"result":{"parent":75,"root":44,"slot":76},"subscription":0}}"#;
+        let Incoming::Notification(Notification::SlotNotification(params)) = parse(fixture).unwrap() else {
+            panic!("expected a slot notification");
+        };
+        assert_eq!(params.result, SlotInfo { slot: 76, parent: 75, root: 44 });
+    }
+
+    #[test]
+    fn test_unknown_methods_are_kept_raw() {
+        let fixture = r#"{"jsonrpc":"2.0","method":"rootNotification","params":{"result":42,"subscription":0}}"#;
+        assert_eq!(
+            parse(fixture).unwrap(),
+            Incoming::Unknown {
+                method: "rootNotification".to_string(),
+                params: serde_json::json!({ "result": 42, "subscription": 0 }),
+            }
+        );
+        assert!(parse("not json").is_err());
+        // Only base64 is requested; anything else is refused rather than misread
+        assert!(AccountData("AQID".to_string(), "base58".to_string()).decode().is_err());
+    }
+}
@@ -1,129 +1,780 @@
 //! WebSocket connection management
+//!
+//! The manager owns the JSON-RPC bookkeeping: request ids and subscription
+//! ids are only valid for one connection, so both maps are rebuilt on every
+//! reconnect and the main loop only sees typed `WsEvent`s keyed by pubkey.
+//! Frames are parsed into the structs in `messages`.
+//!
+//! With several RPC endpoints configured the manager fails over to the next
+//! one after `rpc.failover_after_failures` consecutive failures, and while
+//! on a fallback periodically probes the higher-priority endpoints to fail
+//! back.
+//!
+//! DEXes in program mode get one `programSubscribe` instead of per-pool
+//! subscriptions; their notifications name the account, so they arrive as
+//! `ProgramAccountUpdate`s that may be for pools the main loop hasn't seen.
+//!
+//! Each connection runs a reader task and a writer task around the
+//! manager's command loop, so frames can go out mid-connection: a
+//! `WsHandle` adds or drops subscriptions without a reconnect.
+//!
+//! The manager also mirrors its subscriptions into a `SubscriptionBook`,
+//! which `WsHandle::subscription_report` and the API read for per-pool
+//! freshness. `WsHandle::tap` copies raw frames for one pool or method,
+//! for debugging decoders without extra logging.
+//!
+//! Events reach the main loop through a bounded `queue` whose backpressure
+//! policy sheds stale account data rather than stalling the reader.
+//!
+//! With `websocket.swap_activity` on, each subscribed pool also gets a
+//! `logsSubscribe`; swaps seen in those logs are counted in `activity`.
+//!
+//! On shutdown the manager unsubscribes everything confirmed on the current
+//! connection and sends a close frame before `run` returns.
 
+use crate::config::{Commitment, DexSubscriptionConfig, RedactedUrl, RpcConfig, SubscriptionMode, WebSocketConfig};
+use crate::metrics::PipelineMetrics;
+use crate::models::Dex;
+use crate::net::Egress;
 use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
+use rand::Rng;
 use serde::Serialize;
-use serde_json::json;
-use std::collections::HashSet;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::Message;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tracing::{info, warn, error, debug};
 use url::Url;
 
+pub mod activity;
+pub mod messages;
+pub mod queue;
+pub mod report;
+pub mod tap;
+
+use messages::{Account, Incoming, KeyedAccount, Logs, Notification, NotificationParams, Response, RpcError, WithContext};
+pub use activity::{PoolActivity, SwapActivity};
+pub use queue::{event_channel, EventReceiver, EventSender, QueueMetrics};
+pub use report::{SubscriptionBook, SubscriptionInfo, SubscriptionKind};
+pub use tap::{TapFilter, TapSet};
+
+/// Event delivered to the main loop
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsEvent {
+    /// New account data for a subscribed pubkey
+    AccountUpdate {
+        pubkey: String,
+        slot: u64,
+        data: Vec<u8>,
+        /// Commitment of the subscription that delivered it
+        commitment: Commitment,
+    },
+    /// The RPC node acknowledged a subscription
+    SubscriptionConfirmed {
+        pubkey: String,
+        subscription_id: u64,
+    },
+    /// The connection dropped; no updates arrive until `Reconnected`
+    Disconnected,
+    /// A later connection is up and subscriptions were re-sent
+    Reconnected,
+    /// The manager moved to another endpoint (failover or fail-back)
+    Failover { from: String, to: String },
+    /// New data for an account owned by a subscribed program
+    ProgramAccountUpdate {
+        dex: Dex,
+        pubkey: String,
+        slot: u64,
+        data: Vec<u8>,
+        commitment: Commitment,
+    },
+    /// The chain reached a new slot (`slotSubscribe`); `timestamp` is the
+    /// local receive time in ms
+    SlotAdvanced { slot: u64, timestamp: u64 },
+    /// A notification this version doesn't model, passed through raw
+    Unhandled { method: String, params: Value },
+}
+
+/// Connection lifecycle, published on a `watch` channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// Opening a connection (first attempt, or after a clean close)
+    Connecting,
+    Connected,
+    /// Waiting before reconnect attempt `attempt`
+    Backoff { attempt: u32 },
+    /// `alert_after_failures` or more attempts failed in a row; still retrying
+    Failed { consecutive_failures: u32 },
+}
+
+impl ConnectionState {
+    pub fn is_connected(&self) -> bool {
+        *self == ConnectionState::Connected
+    }
+
+    /// Failed attempts since the last successful connect
+    pub fn consecutive_failures(&self) -> u32 {
+        match *self {
+            ConnectionState::Backoff { attempt } => attempt,
+            ConnectionState::Failed { consecutive_failures } => consecutive_failures,
+            _ => 0,
+        }
+    }
+}
+
+/// Connection state plus the details reported by `/health` and status broadcasts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionStatus {
+    #[serde(flatten)]
+    pub state: ConnectionState,
+    /// Label of the endpoint in use
+    pub endpoint: String,
+    /// When the current connection came up
+    pub connected_since: Option<DateTime<Utc>>,
+    /// Account and program subscriptions confirmed on this connection
+    pub active_subscriptions: usize,
+    /// Subscription requests not yet answered
+    pub pending_subscriptions: usize,
+}
+
+impl ConnectionStatus {
+    pub fn is_connected(&self) -> bool {
+        self.state.is_connected()
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.state.consecutive_failures()
+    }
+
+    /// Seconds the current connection has been up
+    pub fn uptime_secs(&self) -> Option<u64> {
+        self.connected_since
+            .map(|since| (Utc::now() - since).num_seconds().max(0) as u64)
+    }
+
+    /// Whether API clients should hear about this change: connects,
+    /// disconnects, endpoint switches, failure alerts, and the point where
+    /// every subscription on a new connection has been answered
+    pub fn should_announce(&self, previous: &ConnectionStatus) -> bool {
+        let failed = matches!(self.state, ConnectionState::Failed { .. }) && self.state != previous.state;
+        let subscribed = self.is_connected() && self.pending_subscriptions == 0 && previous.pending_subscriptions > 0;
+        self.is_connected() != previous.is_connected() || self.endpoint != previous.endpoint || failed || subscribed
+    }
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        Self {
+            state: ConnectionState::Connecting,
+            endpoint: String::new(),
+            connected_since: None,
+            active_subscriptions: 0,
+            pending_subscriptions: 0,
+        }
+    }
+}
+
+/// Request from a `WsHandle`, carried out on the live connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsCommand {
+    /// Add an account subscription (kept across reconnects)
+    Subscribe(String),
+    /// Drop an account subscription
+    Unsubscribe(String),
+    /// Send a ping frame
+    Ping,
+}
+
+/// Cloneable handle to a running `WebSocketManager`
+///
+/// Commands sent while disconnected wait for the next connection.
+#[derive(Debug, Clone)]
+pub struct WsHandle {
+    commands: mpsc::UnboundedSender<WsCommand>,
+    book: SubscriptionBook,
+    taps: TapSet,
+}
+
+impl WsHandle {
+    /// A handle not attached to any manager, with the receiving end of its
+    /// commands (for exercising callers without a connection)
+    pub fn detached() -> (Self, mpsc::UnboundedReceiver<WsCommand>) {
+        let (commands, rx) = mpsc::unbounded_channel();
+        let handle = Self { commands, book: SubscriptionBook::default(), taps: TapSet::default() };
+        (handle, rx)
+    }
+
+    /// Queue a command; false once the manager is gone
+    pub fn send(&self, command: WsCommand) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    pub fn subscribe(&self, pubkey: impl Into<String>) -> bool {
+        self.send(WsCommand::Subscribe(pubkey.into()))
+    }
+
+    pub fn unsubscribe(&self, pubkey: impl Into<String>) -> bool {
+        self.send(WsCommand::Unsubscribe(pubkey.into()))
+    }
+
+    /// Every subscription of the current connection with its freshness;
+    /// empty while disconnected
+    pub fn subscription_report(&self) -> Vec<SubscriptionInfo> {
+        self.book.report()
+    }
+
+    /// Copy raw frames matching `filter` to the returned receiver until it
+    /// is dropped; at most `tap::TAP_BUFFER` wait unread
+    pub fn tap(&self, filter: TapFilter) -> mpsc::Receiver<String> {
+        self.taps.tap(filter, tap::TAP_BUFFER)
+    }
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Frames buffered between the socket tasks and the command loop
+const FRAME_BUFFER: usize = 256;
+
+/// How long a closing connection gets to flush its last frames
+const WRITER_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// One WebSocket URL and the label it is logged under
+struct WsEndpoint {
+    label: String,
+    url: RedactedUrl,
+}
+
+/// A `programSubscribe` covering every pool account of one DEX
+struct ProgramSubscription {
+    dex: Dex,
+    program_id: String,
+    filters: Vec<Value>,
+}
+
+/// Handshakes slower than this fail a fail-back probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// WebSocket connection manager for Helius Geyser / RPC
 pub struct WebSocketManager {
-    url: String,
+    /// Endpoints in priority order
+    endpoints: Vec<WsEndpoint>,
+    active: usize,
+    /// Proxy and TLS settings, applied on each connect so bad ones fail
+    /// the attempt
+    egress: Egress,
+    /// Failures on the active endpoint since it last connected
+    consecutive_failures: u32,
+    failover_after_failures: u32,
+    failback_interval: Option<Duration>,
     reconnect_attempts: u32,
     max_reconnect_delay: Duration,
+    reconnect_jitter: f64,
+    /// Failures across all endpoints since the last successful connect
+    failures_since_connected: u32,
+    alert_after_failures: u32,
+    /// Reconnect after this long without an account notification
+    silence_timeout: Option<Duration>,
+    /// Resubscribe an account quiet this long while others notify
+    stale_timeout: Option<Duration>,
+    status: watch::Sender<ConnectionStatus>,
     subscriptions: HashSet<String>,
-    tx: Option<mpsc::Sender<String>>, // Channel to send raw messages to main loop
-}
-
-#[derive(Serialize)]
-struct SubscriptionRequest {
-    jsonrpc: String,
-    id: u64,
-    method: String,
-    params: (String, SubscriptionConfig),
-}
-
-#[derive(Serialize)]
-struct SubscriptionConfig {
-    encoding: String,
-    commitment: String,
+    /// Pubkey -> (offset, length) requested as `dataSlice`
+    data_slices: HashMap<String, (usize, usize)>,
+    commitment: Commitment,
+    /// Subscribe every account at `confirmed` as well as `commitment`
+    dual_commitment: bool,
+    /// Request id -> (pubkey, commitment), for subscriptions awaiting confirmation
+    pending: HashMap<u64, (String, Commitment)>,
+    /// Pending account requests whose pubkey was unsubscribed meanwhile
+    cancelled: HashSet<u64>,
+    /// Requests produced while handling a frame, sent right after it
+    outbox: Vec<String>,
+    /// Last request id used on the current connection
+    next_request_id: u64,
+    /// Subscription id -> (pubkey, commitment), for the current connection
+    subscription_ids: HashMap<u64, (String, Commitment)>,
+    /// Subscription id -> last notification (or confirmation)
+    last_notified: HashMap<u64, tokio::time::Instant>,
+    /// Latest account notification on the current connection
+    latest_notification: Option<tokio::time::Instant>,
+    /// (pubkey, commitment) -> newest slot passed on; kept across
+    /// reconnects and failovers, where a node may replay older state
+    last_slots: HashMap<(String, Commitment), u64>,
+    programs: Vec<ProgramSubscription>,
+    /// Request id -> (index into `programs`, commitment), awaiting confirmation
+    pending_programs: HashMap<u64, (usize, Commitment)>,
+    /// Subscription id -> (index into `programs`, commitment), for the current connection
+    program_subscription_ids: HashMap<u64, (usize, Commitment)>,
+    slot_subscribe: bool,
+    /// Request id, then subscription id, of the slot subscription
+    slot_request: Option<u64>,
+    slot_subscription: Option<u64>,
+    swap_activity: bool,
+    activity: SwapActivity,
+    /// Request id -> pubkey, for logs subscriptions awaiting confirmation
+    pending_logs: HashMap<u64, String>,
+    /// Subscription id -> pubkey, for logs subscriptions on this connection
+    logs_subscription_ids: HashMap<u64, String>,
+    connected_before: bool,
+    shutdown: CancellationToken,
+    tx: Option<EventSender>,
+    metrics: Arc<PipelineMetrics>,
+    book: SubscriptionBook,
+    taps: TapSet,
+    /// Kept so `handle()` can hand out senders
+    command_tx: mpsc::UnboundedSender<WsCommand>,
+    commands: mpsc::UnboundedReceiver<WsCommand>,
 }
 
 impl WebSocketManager {
-    /// Create a new WebSocket manager
+    /// Create a manager for a single endpoint
     pub fn new(url: String, subscriptions: Vec<String>) -> Self {
+        Self::with_endpoints(vec![("default".to_string(), url)], subscriptions)
+    }
+
+    /// Create a manager for every configured endpoint, with its failover policy
+    pub fn from_rpc_config(rpc: &RpcConfig, subscriptions: Vec<String>) -> Self {
+        let endpoints = rpc
+            .endpoints
+            .iter()
+            .map(|e| (e.name().to_string(), e.websocket_url.clone()))
+            .collect();
+        let mut manager = Self::with_endpoints(endpoints, subscriptions);
+        manager.failover_after_failures = rpc.failover_after_failures.max(1);
+        manager.failback_interval =
+            (rpc.failback_interval_seconds > 0).then(|| Duration::from_secs(rpc.failback_interval_seconds));
+        manager.commitment = rpc.commitment;
+        manager.dual_commitment = rpc.dual_commitment;
+        manager.egress = Egress::from_rpc_config(rpc);
+        manager
+    }
+
+    fn with_endpoints(endpoints: Vec<(String, String)>, subscriptions: Vec<String>) -> Self {
+        let (command_tx, commands) = mpsc::unbounded_channel();
         Self {
-            url,
+            endpoints: endpoints
+                .into_iter()
+                .map(|(label, url)| WsEndpoint {
+                    label,
+                    url: RedactedUrl::new(url),
+                })
+                .collect(),
+            active: 0,
+            egress: Egress::default(),
+            consecutive_failures: 0,
+            failover_after_failures: RpcConfig::default().failover_after_failures,
+            failback_interval: None,
             reconnect_attempts: 0,
             max_reconnect_delay: Duration::from_secs(30),
+            reconnect_jitter: WebSocketConfig::default().reconnect_jitter,
+            failures_since_connected: 0,
+            alert_after_failures: WebSocketConfig::default().alert_after_failures,
+            silence_timeout: None,
+            stale_timeout: None,
+            status: watch::channel(ConnectionStatus::default()).0,
             subscriptions: subscriptions.into_iter().collect(),
+            data_slices: HashMap::new(),
+            commitment: Commitment::default(),
+            dual_commitment: false,
+            pending: HashMap::new(),
+            cancelled: HashSet::new(),
+            outbox: Vec::new(),
+            next_request_id: 0,
+            subscription_ids: HashMap::new(),
+            last_notified: HashMap::new(),
+            latest_notification: None,
+            last_slots: HashMap::new(),
+            programs: Vec::new(),
+            pending_programs: HashMap::new(),
+            program_subscription_ids: HashMap::new(),
+            slot_subscribe: WebSocketConfig::default().slot_subscribe,
+            slot_request: None,
+            slot_subscription: None,
+            swap_activity: WebSocketConfig::default().swap_activity,
+            activity: SwapActivity::default(),
+            pending_logs: HashMap::new(),
+            logs_subscription_ids: HashMap::new(),
+            connected_before: false,
+            shutdown: CancellationToken::new(),
             tx: None,
+            metrics: Arc::default(),
+            book: SubscriptionBook::default(),
+            taps: TapSet::default(),
+            command_tx,
+            commands,
         }
     }
 
-    /// Set the channel to send received messages to
-    pub fn set_sender(&mut self, tx: mpsc::Sender<String>) {
+    /// Apply `[websocket]` options
+    pub fn with_config(mut self, config: &WebSocketConfig) -> Self {
+        self.slot_subscribe = config.slot_subscribe;
+        self.swap_activity = config.swap_activity;
+        self.reconnect_jitter = config.reconnect_jitter;
+        self.alert_after_failures = config.alert_after_failures.max(1);
+        self.silence_timeout =
+            (config.silence_timeout_seconds > 0).then(|| Duration::from_secs(config.silence_timeout_seconds));
+        self.stale_timeout =
+            (config.stale_subscription_seconds > 0).then(|| Duration::from_secs(config.stale_subscription_seconds));
+        self
+    }
+
+    /// Add a `programSubscribe` for every DEX in program mode
+    pub fn with_programs(mut self, subscriptions: &HashMap<Dex, DexSubscriptionConfig>) -> Self {
+        self.programs = subscriptions
+            .iter()
+            .filter(|(_, s)| s.mode == SubscriptionMode::Program)
+            .filter_map(|(dex, s)| {
+                Some(ProgramSubscription {
+                    dex: dex.clone(),
+                    program_id: s.program_id(dex)?,
+                    filters: s.rpc_filters(),
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Subscribe these accounts to only the given `(offset, length)` of
+    /// their data; their decoders must accept the slice
+    pub fn with_data_slices(mut self, data_slices: HashMap<String, (usize, usize)>) -> Self {
+        self.data_slices = data_slices;
+        self
+    }
+
+    /// Mirror subscriptions into `book` (shared across reconnects)
+    pub fn with_subscription_book(mut self, book: SubscriptionBook) -> Self {
+        self.book = book;
+        self
+    }
+
+    /// Copy raw frames to the taps registered in `taps` (shared across
+    /// reconnects)
+    pub fn with_taps(mut self, taps: TapSet) -> Self {
+        self.taps = taps;
+        self
+    }
+
+    /// Count swaps into `activity` (shared across reconnects); only used
+    /// with `swap_activity` on, and only for pools it names
+    pub fn with_swap_activity(mut self, activity: SwapActivity) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    /// Stop (unsubscribing and closing the connection) once `shutdown` fires
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Publish connection status on `status` (shared across managers on reconnect)
+    pub fn with_status(mut self, status: watch::Sender<ConnectionStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Count received frames and bytes into `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<PipelineMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Watch connection status changes
+    pub fn status(&self) -> watch::Receiver<ConnectionStatus> {
+        self.status.subscribe()
+    }
+
+    /// Label of the endpoint currently in use
+    pub fn active_endpoint(&self) -> &str {
+        &self.endpoints[self.active].label
+    }
+
+    /// Set the channel to send events to
+    pub fn set_sender(&mut self, tx: EventSender) {
         self.tx = Some(tx);
     }
 
+    /// Handle for changing subscriptions while `run` is going
+    pub fn handle(&self) -> WsHandle {
+        WsHandle {
+            commands: self.command_tx.clone(),
+            book: self.book.clone(),
+            taps: self.taps.clone(),
+        }
+    }
+
     /// Connect to WebSocket with exponential backoff and maintain connection
+    ///
+    /// Returns after shutdown or once the event receiver is gone.
     pub async fn run(&mut self) {
-        loop {
+        let shutdown = self.shutdown.clone();
+        while !shutdown.is_cancelled() {
             let delay = Duration::from_millis(
-                100 * 2u64.pow(self.reconnect_attempts.min(8)) as u64
+                100 * 2u64.pow(self.reconnect_attempts.min(8))
             );
-            let actual_delay = delay.min(self.max_reconnect_delay);
+            let actual_delay = jittered(delay.min(self.max_reconnect_delay), self.reconnect_jitter);
 
             if self.reconnect_attempts > 0 {
                 warn!(
+                    endpoint = self.active_endpoint(),
                     attempt = self.reconnect_attempts,
                     delay_ms = actual_delay.as_millis(),
                     "Reconnecting to WebSocket..."
                 );
-                tokio::time::sleep(actual_delay).await;
+                if shutdown.run_until_cancelled(tokio::time::sleep(actual_delay)).await.is_none() {
+                    break;
+                }
             }
 
+            // A failed feed stays reported as failed until it connects
+            let endpoint = self.active_endpoint().to_string();
+            self.publish(|status| {
+                if !matches!(status.state, ConnectionState::Failed { .. }) {
+                    status.state = ConnectionState::Connecting;
+                }
+                status.endpoint = endpoint;
+            });
+
             match self.connect_and_listen().await {
+                Ok(_) if shutdown.is_cancelled() => break,
                 Ok(_) => {
                     self.reconnect_attempts = 0;
-                    info!("WebSocket connection closed gracefully");
+                    self.set_disconnected(ConnectionState::Connecting);
+                    info!(endpoint = self.active_endpoint(), "WebSocket connection closed gracefully");
                 }
                 Err(e) => {
                     self.reconnect_attempts += 1;
-                    error!(error = ?e, "WebSocket connection failed/terminated");
+                    self.consecutive_failures += 1;
+                    self.failures_since_connected += 1;
+                    error!(
+                        endpoint = self.active_endpoint(),
+                        failures = self.consecutive_failures,
+                        error = ?e,
+                        "WebSocket connection failed/terminated"
+                    );
+                    if self.failures_since_connected >= self.alert_after_failures {
+                        if self.failures_since_connected == self.alert_after_failures {
+                            error!(
+                                failures = self.failures_since_connected,
+                                "WebSocket feed down, price updates stopped"
+                            );
+                        }
+                        self.set_disconnected(ConnectionState::Failed {
+                            consecutive_failures: self.failures_since_connected,
+                        });
+                    } else {
+                        self.set_disconnected(ConnectionState::Backoff {
+                            attempt: self.failures_since_connected,
+                        });
+                    }
+                    if self.consecutive_failures >= self.failover_after_failures && self.endpoints.len() > 1 {
+                        let next = (self.active + 1) % self.endpoints.len();
+                        if !self.switch_to(next).await {
+                            return;
+                        }
+                    }
                 }
             }
         }
+        info!(endpoint = self.active_endpoint(), "WebSocket manager shut down");
+    }
+
+    /// Make another endpoint active and retry right away
+    ///
+    /// Returns false once the event receiver is gone.
+    async fn switch_to(&mut self, index: usize) -> bool {
+        let from = self.active_endpoint().to_string();
+        self.active = index;
+        self.consecutive_failures = 0;
+        self.reconnect_attempts = 0;
+        let to = self.active_endpoint().to_string();
+        warn!(from = from, to = to, "Switching WebSocket endpoint");
+        let endpoint = to.clone();
+        self.publish(|status| status.endpoint = endpoint);
+        self.emit(WsEvent::Failover { from, to }).await
     }
 
     /// Internal connection and event loop
     async fn connect_and_listen(&mut self) -> Result<()> {
-        let url = Url::parse(&self.url).context("Invalid WebSocket URL")?;
-        info!(url = %url, "Connecting to WebSocket");
+        let endpoint = &self.endpoints[self.active];
+        let url = Url::parse(endpoint.url.expose()).context("Invalid WebSocket URL")?;
+        info!(endpoint = endpoint.label, url = %endpoint.url, "Connecting to WebSocket");
 
-        let (ws_stream, _) = connect_async(url).await.context("Failed to connect")?;
-        info!("WebSocket connected");
+        let Some(connected) = self.shutdown.run_until_cancelled(self.egress.connect_websocket(url)).await else {
+            return Ok(());
+        };
+        let ws_stream = connected
+            .map_err(|e| anyhow::anyhow!(endpoint.url.scrub(&format!("{:#}", e))))
+            .context("Failed to connect")?;
+        info!(endpoint = endpoint.label, "WebSocket connected");
+        self.consecutive_failures = 0;
+        self.failures_since_connected = 0;
+        let label = endpoint.label.clone();
+        self.publish(|status| {
+            status.state = ConnectionState::Connected;
+            status.endpoint = label;
+            status.connected_since = Some(Utc::now());
+        });
 
-        let (mut write, mut read) = ws_stream.split();
+        let result = self.listen(ws_stream).await;
+        if !self.shutdown.is_cancelled() {
+            self.emit(WsEvent::Disconnected).await;
+        }
+        result
+    }
 
-        // Subscribe to accounts
-        for (id, pubkey) in self.subscriptions.iter().enumerate() {
-            let request = json!({
-                "jsonrpc": "2.0",
-                "id": id + 1,
-                "method": "accountSubscribe",
-                "params": [
-                    pubkey,
-                    {
-                        "encoding": "base64",
-                        "commitment": "processed"
-                    }
-                ]
-            });
+    /// First higher-priority endpoint that completes a handshake
+    async fn probe_preferred(&self) -> Option<usize> {
+        for (index, endpoint) in self.endpoints[..self.active].iter().enumerate() {
+            let Ok(url) = Url::parse(endpoint.url.expose()) else { continue };
+            if let Ok(Ok(mut ws)) = tokio::time::timeout(PROBE_TIMEOUT, self.egress.connect_websocket(url)).await {
+                ws.close(None).await.ok();
+                return Some(index);
+            }
+            debug!(endpoint = endpoint.label, "Fail-back probe failed");
+        }
+        None
+    }
+
+    /// Subscribe, then forward events until the connection ends
+    ///
+    /// The socket is split between a reader task and a writer task; this
+    /// loop owns the bookkeeping, turns frames into events and commands into
+    /// frames. Whichever side fails first tears both down.
+    async fn listen(&mut self, ws_stream: WsStream) -> Result<()> {
+        let (write, read) = ws_stream.split();
+        let (frames, frames_rx) = mpsc::channel(FRAME_BUFFER);
+        let (incoming_tx, mut incoming) = mpsc::channel(FRAME_BUFFER);
+        let mut writer = tokio::spawn(write_frames(write, frames_rx));
+        let reader = tokio::spawn(read_frames(read, incoming_tx));
+
+        let result = self.command_loop(&frames, &mut writer, &mut incoming).await;
+        reader.abort();
+        if result.is_ok() {
+            // Let queued unsubscribes and the close frame go out
+            drop(frames);
+            tokio::time::timeout(WRITER_FLUSH_TIMEOUT, &mut writer).await.ok();
+        }
+        writer.abort();
+        result
+    }
 
-            let msg = Message::Text(request.to_string());
-            write.send(msg).await.context("Failed to send subscription")?;
-            debug!(pubkey = pubkey, "Sent subscription request");
+    async fn command_loop(
+        &mut self,
+        frames: &mpsc::Sender<Message>,
+        writer: &mut JoinHandle<Result<()>>,
+        incoming: &mut mpsc::Receiver<Result<Message, WsError>>,
+    ) -> Result<()> {
+        for request in self.subscription_requests() {
+            send_frame(frames, Message::Text(request)).await?;
         }
+        debug!(
+            accounts = self.pending.len(),
+            programs = self.pending_programs.len(),
+            "Sent subscription requests"
+        );
+        self.publish_subscription_counts();
+
+        if std::mem::replace(&mut self.connected_before, true) && !self.emit(WsEvent::Reconnected).await {
+            return Ok(());
+        }
+
+        // Only armed while on a fallback endpoint
+        let mut failback = self
+            .failback_interval
+            .filter(|_| self.active > 0)
+            .map(|interval| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval));
+
+        // Silence is measured from the connect or the last account notification
+        let mut last_notification = tokio::time::Instant::now();
+        let mut stale_check = self.stale_timeout.map(|timeout| tokio::time::interval(timeout / 4));
 
-        // Process messages
-        while let Some(msg) = read.next().await {
+        let shutdown = self.shutdown.clone();
+        loop {
+            let silence_deadline = self.silence_timeout.map(|timeout| last_notification + timeout);
+            let msg = tokio::select! {
+                msg = incoming.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                Some(command) = self.commands.recv() => {
+                    self.handle_command(command, frames).await?;
+                    continue;
+                }
+                written = &mut *writer => {
+                    return match written {
+                        Ok(Err(e)) => Err(e),
+                        _ => Err(anyhow::anyhow!("WebSocket writer stopped")),
+                    };
+                }
+                _ = shutdown.cancelled() => {
+                    for request in self.unsubscribe_requests() {
+                        send_frame(frames, Message::Text(request)).await.ok();
+                    }
+                    send_frame(frames, Message::Close(None)).await.ok();
+                    debug!(endpoint = self.active_endpoint(), "Unsubscribed and closed WebSocket");
+                    break;
+                }
+                _ = async { failback.as_mut().unwrap().tick().await }, if failback.is_some() => {
+                    if let Some(index) = self.probe_preferred().await {
+                        send_frame(frames, Message::Close(None)).await.ok();
+                        if !self.switch_to(index).await {
+                            return Ok(());
+                        }
+                        break;
+                    }
+                    continue;
+                }
+                _ = async { stale_check.as_mut().unwrap().tick().await }, if stale_check.is_some() => {
+                    for request in self.resubscribe_stale() {
+                        send_frame(frames, Message::Text(request)).await?;
+                    }
+                    continue;
+                }
+                _ = async { tokio::time::sleep_until(silence_deadline.unwrap()).await }, if silence_deadline.is_some() => {
+                    if self.subscription_ids.is_empty() && self.program_subscription_ids.is_empty() {
+                        last_notification = tokio::time::Instant::now();
+                        continue;
+                    }
+                    let silent = last_notification.elapsed();
+                    warn!(
+                        endpoint = self.active_endpoint(),
+                        silent_secs = silent.as_secs(),
+                        "No account notifications despite active subscriptions, forcing reconnect"
+                    );
+                    self.metrics.record_silence();
+                    send_frame(frames, Message::Close(None)).await.ok();
+                    anyhow::bail!("no account notifications for {:?}", silent);
+                }
+            };
             match msg {
                 Ok(Message::Text(text)) => {
-                    if let Some(tx) = &self.tx {
-                        if let Err(e) = tx.send(text).await {
-                            error!("Failed to send message to channel: {}", e);
+                    self.metrics.record_message(text.len());
+                    let event = self.handle_text(&text);
+                    for request in std::mem::take(&mut self.outbox) {
+                        send_frame(frames, Message::Text(request)).await?;
+                    }
+                    if matches!(event, Some(WsEvent::AccountUpdate { .. } | WsEvent::ProgramAccountUpdate { .. })) {
+                        last_notification = tokio::time::Instant::now();
+                    }
+                    if let Some(event) = event {
+                        if !self.emit(event).await {
                             break;
                         }
                     }
                 }
                 Ok(Message::Binary(bin)) => {
+                    self.metrics.record_message(bin.len());
                     // Handle binary if needed, usually RPC sends Text JSON
                     debug!("Received binary message: {} bytes", bin.len());
                 }
@@ -144,11 +795,585 @@ impl WebSocketManager {
 
         Ok(())
     }
+
+    /// Carry out a `WsHandle` request on the live connection
+    async fn handle_command(&mut self, command: WsCommand, frames: &mpsc::Sender<Message>) -> Result<()> {
+        match command {
+            WsCommand::Subscribe(pubkey) => {
+                if !self.subscriptions.insert(pubkey.clone()) {
+                    return Ok(());
+                }
+                for commitment in self.commitments() {
+                    let request = self.account_subscribe_request(&pubkey, commitment);
+                    send_frame(frames, Message::Text(request)).await?;
+                }
+                if let Some(request) = self.logs_subscribe_request(&pubkey) {
+                    send_frame(frames, Message::Text(request)).await?;
+                }
+                debug!(pubkey = pubkey, "Subscribing mid-connection");
+            }
+            WsCommand::Unsubscribe(pubkey) => {
+                if !self.subscriptions.remove(&pubkey) {
+                    return Ok(());
+                }
+                // Requests still in flight are unsubscribed once confirmed
+                let in_flight: Vec<u64> =
+                    self.pending.iter().filter(|(_, (p, _))| *p == pubkey).map(|(id, _)| *id).collect();
+                self.cancelled.extend(in_flight);
+                let logs_in_flight: Vec<u64> =
+                    self.pending_logs.iter().filter(|(_, p)| **p == pubkey).map(|(id, _)| *id).collect();
+                self.cancelled.extend(logs_in_flight);
+                let confirmed: Vec<u64> =
+                    self.subscription_ids.iter().filter(|(_, (p, _))| *p == pubkey).map(|(id, _)| *id).collect();
+                for subscription_id in confirmed {
+                    self.subscription_ids.remove(&subscription_id);
+                    self.last_notified.remove(&subscription_id);
+                    let request = self.request("accountUnsubscribe", json!([subscription_id]));
+                    send_frame(frames, Message::Text(request)).await?;
+                }
+                let logs: Vec<u64> =
+                    self.logs_subscription_ids.iter().filter(|(_, p)| **p == pubkey).map(|(id, _)| *id).collect();
+                for subscription_id in logs {
+                    self.logs_subscription_ids.remove(&subscription_id);
+                    let request = self.request("logsUnsubscribe", json!([subscription_id]));
+                    send_frame(frames, Message::Text(request)).await?;
+                }
+                self.last_slots.retain(|(p, _), _| *p != pubkey);
+                debug!(pubkey = pubkey, "Unsubscribed mid-connection");
+            }
+            WsCommand::Ping => send_frame(frames, Message::Ping(Vec::new())).await?,
+        }
+        self.publish_subscription_counts();
+        Ok(())
+    }
+
+    /// Update the published status; watchers only wake for real changes
+    fn publish(&self, update: impl FnOnce(&mut ConnectionStatus)) {
+        self.status.send_if_modified(|status| {
+            let before = status.clone();
+            update(status);
+            *status != before
+        });
+    }
+
+    /// The connection is gone (or never came up); nothing is subscribed
+    fn set_disconnected(&self, state: ConnectionState) {
+        self.publish(|status| {
+            status.state = state;
+            status.connected_since = None;
+            status.active_subscriptions = 0;
+            status.pending_subscriptions = 0;
+        });
+        self.book.sync([]);
+    }
+
+    /// Publish subscription progress on the current connection
+    fn publish_subscription_counts(&self) {
+        let active = self.subscription_ids.len() + self.program_subscription_ids.len() + self.logs_subscription_ids.len();
+        let pending = self.pending.len() + self.pending_programs.len() + self.pending_logs.len();
+        self.publish(|status| {
+            status.active_subscriptions = active;
+            status.pending_subscriptions = pending;
+        });
+
+        let pending_accounts = self
+            .pending
+            .iter()
+            .filter(|(id, _)| !self.cancelled.contains(id))
+            .map(|(_, (pubkey, commitment))| (pubkey, *commitment, None));
+        let accounts = self
+            .subscription_ids
+            .iter()
+            .map(|(id, (pubkey, commitment))| (pubkey, *commitment, Some(*id)))
+            .chain(pending_accounts)
+            .map(|(pubkey, commitment, id)| (SubscriptionKind::Account, pubkey.as_str(), None, commitment, id));
+        let programs = self
+            .program_subscription_ids
+            .iter()
+            .map(|(id, program)| (*program, Some(*id)))
+            .chain(self.pending_programs.values().map(|program| (*program, None)))
+            .map(|((index, commitment), id)| {
+                let program = &self.programs[index];
+                (SubscriptionKind::Program, program.program_id.as_str(), Some(&program.dex), commitment, id)
+            });
+        let logs = self
+            .logs_subscription_ids
+            .iter()
+            .map(|(id, pubkey)| (pubkey, Some(*id)))
+            .chain(
+                self.pending_logs
+                    .iter()
+                    .filter(|(id, _)| !self.cancelled.contains(id))
+                    .map(|(_, pubkey)| (pubkey, None)),
+            )
+            .map(|(pubkey, id)| (SubscriptionKind::Logs, pubkey.as_str(), None, self.commitment, id));
+        self.book.sync(accounts.chain(programs).chain(logs));
+    }
+
+    /// Forward an event; false once the receiver is gone
+    async fn emit(&self, event: WsEvent) -> bool {
+        match &self.tx {
+            Some(tx) => {
+                let sent = tx.send(event).await;
+                if !sent {
+                    error!("Event receiver closed, stopping WebSocket manager");
+                }
+                sent
+            }
+            None => true,
+        }
+    }
+
+    /// `accountSubscribe`, `programSubscribe`, `logsSubscribe` and
+    /// `slotSubscribe` requests for a fresh connection
+    ///
+    /// Ids from a previous connection are meaningless to the new one, so
+    /// all maps start over.
+    fn subscription_requests(&mut self) -> Vec<String> {
+        self.next_request_id = 0;
+        self.pending.clear();
+        self.subscription_ids.clear();
+        self.last_notified.clear();
+        self.latest_notification = None;
+        self.cancelled.clear();
+        self.outbox.clear();
+        self.pending_programs.clear();
+        self.program_subscription_ids.clear();
+        self.slot_request = None;
+        self.slot_subscription = None;
+        self.pending_logs.clear();
+        self.logs_subscription_ids.clear();
+
+        let mut requests: Vec<String> = Vec::new();
+        let pubkeys: Vec<String> = self.subscriptions.iter().cloned().collect();
+        for commitment in self.commitments() {
+            for pubkey in &pubkeys {
+                requests.push(self.account_subscribe_request(pubkey, commitment));
+            }
+        }
+
+        for commitment in self.commitments() {
+            for index in 0..self.programs.len() {
+                let program = &self.programs[index];
+                let mut options = json!({ "encoding": "base64", "commitment": commitment.as_str() });
+                if !program.filters.is_empty() {
+                    options["filters"] = Value::Array(program.filters.clone());
+                }
+                let params = json!([program.program_id, options]);
+                let request = self.request("programSubscribe", params);
+                self.pending_programs.insert(self.next_request_id, (index, commitment));
+                requests.push(request);
+            }
+        }
+
+        for pubkey in &pubkeys {
+            requests.extend(self.logs_subscribe_request(pubkey));
+        }
+
+        if self.slot_subscribe {
+            let request = self.request("slotSubscribe", Value::Null);
+            self.slot_request = Some(self.next_request_id);
+            requests.push(request);
+        }
+        requests
+    }
+
+    /// Commitments each account is subscribed at
+    fn commitments(&self) -> Vec<Commitment> {
+        let mut commitments = vec![self.commitment];
+        if self.dual_commitment {
+            commitments.push(Commitment::Confirmed);
+        }
+        commitments
+    }
+
+    /// A JSON-RPC request with the next id on this connection
+    fn request(&mut self, method: &str, params: Value) -> String {
+        self.next_request_id += 1;
+        let mut request = json!({ "jsonrpc": "2.0", "id": self.next_request_id, "method": method });
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        request.to_string()
+    }
+
+    fn account_subscribe_request(&mut self, pubkey: &str, commitment: Commitment) -> String {
+        let mut config = json!({ "encoding": "base64", "commitment": commitment.as_str() });
+        if let Some((offset, length)) = self.data_slices.get(pubkey) {
+            config["dataSlice"] = json!({ "offset": offset, "length": length });
+        }
+        let params = json!([pubkey, config]);
+        let request = self.request("accountSubscribe", params);
+        self.pending.insert(self.next_request_id, (pubkey.to_string(), commitment));
+        request
+    }
+
+    /// `logsSubscribe` for transactions mentioning a pool, when swap
+    /// activity is tracked and the pool's DEX is known
+    fn logs_subscribe_request(&mut self, pubkey: &str) -> Option<String> {
+        if !self.swap_activity {
+            return None;
+        }
+        self.activity.dex_of(pubkey)?;
+        self.activity.start();
+        let params = json!([{ "mentions": [pubkey] }, { "commitment": self.commitment.as_str() }]);
+        let request = self.request("logsSubscribe", params);
+        self.pending_logs.insert(self.next_request_id, pubkey.to_string());
+        Some(request)
+    }
+
+    /// Unsubscribe and subscribe again every account that has been quiet
+    /// for `stale_timeout` while others kept notifying
+    ///
+    /// When all of them are quiet the feed itself is suspect, which the
+    /// silence watchdog handles with a reconnect.
+    fn resubscribe_stale(&mut self) -> Vec<String> {
+        let Some(timeout) = self.stale_timeout else {
+            return Vec::new();
+        };
+        if !self.latest_notification.is_some_and(|at| at.elapsed() < timeout) {
+            return Vec::new();
+        }
+        let stale: Vec<u64> = self
+            .last_notified
+            .iter()
+            .filter(|(_, at)| at.elapsed() >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut requests = Vec::new();
+        for subscription_id in stale {
+            let silent = self.last_notified.remove(&subscription_id).map(|at| at.elapsed()).unwrap_or_default();
+            let Some((pubkey, commitment)) = self.subscription_ids.remove(&subscription_id) else {
+                continue;
+            };
+            warn!(
+                pubkey = pubkey,
+                sub_id = subscription_id,
+                silent_secs = silent.as_secs(),
+                "Subscription stopped notifying while others still do, resubscribing"
+            );
+            self.metrics.record_resubscribe();
+            requests.push(self.request("accountUnsubscribe", json!([subscription_id])));
+            requests.push(self.account_subscribe_request(&pubkey, commitment));
+        }
+        if !requests.is_empty() {
+            self.publish_subscription_counts();
+        }
+        requests
+    }
+
+    /// Unsubscribe requests for every subscription confirmed on this connection
+    fn unsubscribe_requests(&mut self) -> Vec<String> {
+        let accounts = self.subscription_ids.keys().map(|id| ("accountUnsubscribe", *id));
+        let programs = self.program_subscription_ids.keys().map(|id| ("programUnsubscribe", *id));
+        let logs = self.logs_subscription_ids.keys().map(|id| ("logsUnsubscribe", *id));
+        let slot = self.slot_subscription.map(|id| ("slotUnsubscribe", id));
+        let unsubscribes: Vec<(&str, u64)> = accounts.chain(programs).chain(logs).chain(slot).collect();
+
+        unsubscribes
+            .into_iter()
+            .map(|(method, subscription_id)| self.request(method, json!([subscription_id])))
+            .collect()
+    }
+
+    /// Turn a JSON-RPC message into an event, updating the id maps
+    fn handle_text(&mut self, text: &str) -> Option<WsEvent> {
+        if self.taps.is_active() {
+            self.tap_frame(text);
+        }
+        let message = match messages::parse(text) {
+            Ok(message) => message,
+            Err(e) => {
+                debug!(error = %e, "Ignoring unparseable message");
+                return None;
+            }
+        };
+
+        match message {
+            Incoming::Response(response) => self.handle_response(response),
+            Incoming::Notification(Notification::AccountNotification(params)) => self.account_update(params),
+            Incoming::Notification(Notification::ProgramNotification(params)) => {
+                self.program_account_update(params)
+            }
+            Incoming::Notification(Notification::SlotNotification(params)) => {
+                if Some(params.subscription) != self.slot_subscription {
+                    return None;
+                }
+                Some(WsEvent::SlotAdvanced {
+                    slot: params.result.slot,
+                    timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                })
+            }
+            Incoming::Notification(Notification::LogsNotification(params)) => {
+                self.count_swap(params);
+                None
+            }
+            Incoming::Unknown { method, params } => Some(WsEvent::Unhandled { method, params }),
+        }
+    }
+
+    /// Offer a raw frame to the taps, under its method and the pool it
+    /// concerns; responses are attributed through the pending requests, so
+    /// this runs before they are handled
+    fn tap_frame(&self, text: &str) {
+        let Ok(frame) = serde_json::from_str::<Value>(text) else {
+            self.taps.offer(text, None, None);
+            return;
+        };
+        let method = frame["method"].as_str();
+        let params = &frame["params"];
+        let pubkey = match method {
+            Some("accountNotification") => params["subscription"]
+                .as_u64()
+                .and_then(|id| self.subscription_ids.get(&id))
+                .map(|(pubkey, _)| pubkey.as_str()),
+            Some("programNotification") => params["result"]["value"]["pubkey"].as_str(),
+            Some("logsNotification") => params["subscription"]
+                .as_u64()
+                .and_then(|id| self.logs_subscription_ids.get(&id))
+                .map(String::as_str),
+            Some(_) => None,
+            None => frame["id"].as_u64().and_then(|id| {
+                self.pending
+                    .get(&id)
+                    .map(|(pubkey, _)| pubkey.as_str())
+                    .or_else(|| self.pending_logs.get(&id).map(String::as_str))
+            }),
+        };
+        self.taps.offer(text, method, pubkey);
+    }
+
+    /// Response to one of our subscription requests
+    fn handle_response(&mut self, response: Response) -> Option<WsEvent> {
+        let id = response.id;
+        if self.slot_request == Some(id) {
+            self.slot_request = None;
+            match response.subscription_id() {
+                Some(subscription_id) => {
+                    debug!(sub_id = subscription_id, "Slot subscription confirmed");
+                    self.slot_subscription = Some(subscription_id);
+                }
+                None => warn!(error = ?response.error, "Slot subscription rejected"),
+            }
+            return None;
+        }
+
+        if let Some((index, commitment)) = self.pending_programs.remove(&id) {
+            let program = &self.programs[index];
+            match response.subscription_id() {
+                Some(subscription_id) => {
+                    info!(
+                        dex = %program.dex,
+                        program = program.program_id,
+                        sub_id = subscription_id,
+                        commitment = commitment.as_str(),
+                        "Program subscription confirmed"
+                    );
+                    self.program_subscription_ids.insert(subscription_id, (index, commitment));
+                }
+                None => {
+                    warn!(dex = %program.dex, error = ?response.error, "Program subscription rejected");
+                    self.book.rejected(&program.program_id, rejection(response.error));
+                }
+            }
+            self.publish_subscription_counts();
+            return None;
+        }
+
+        if let Some(pubkey) = self.pending_logs.remove(&id) {
+            if self.cancelled.remove(&id) {
+                if let Some(subscription_id) = response.subscription_id() {
+                    let request = self.request("logsUnsubscribe", json!([subscription_id]));
+                    self.outbox.push(request);
+                }
+                self.publish_subscription_counts();
+                return None;
+            }
+            match response.subscription_id() {
+                Some(subscription_id) => {
+                    debug!(sub_id = subscription_id, pubkey = pubkey, "Logs subscription confirmed");
+                    self.logs_subscription_ids.insert(subscription_id, pubkey);
+                }
+                None => {
+                    let error = rejection(response.error);
+                    warn!(pubkey = pubkey, error = %error, "Logs subscription rejected");
+                    self.book.rejected(&pubkey, error);
+                }
+            }
+            self.publish_subscription_counts();
+            return None;
+        }
+
+        let (pubkey, commitment) = self.pending.remove(&id)?;
+        if self.cancelled.remove(&id) {
+            // Unsubscribed while the request was in flight
+            if let Some(subscription_id) = response.subscription_id() {
+                let request = self.request("accountUnsubscribe", json!([subscription_id]));
+                self.outbox.push(request);
+            }
+            self.publish_subscription_counts();
+            return None;
+        }
+        let Some(subscription_id) = response.subscription_id() else {
+            let error = rejection(response.error);
+            warn!(pubkey = pubkey, error = %error, "Subscription rejected");
+            self.book.rejected(&pubkey, error);
+            self.publish_subscription_counts();
+            return None;
+        };
+        debug!(sub_id = subscription_id, pubkey = pubkey, commitment = commitment.as_str(), "Subscription confirmed");
+        self.subscription_ids.insert(subscription_id, (pubkey.clone(), commitment));
+        self.last_notified.insert(subscription_id, tokio::time::Instant::now());
+        self.publish_subscription_counts();
+        Some(WsEvent::SubscriptionConfirmed { pubkey, subscription_id })
+    }
+
+    fn account_update(&mut self, params: NotificationParams<WithContext<Account>>) -> Option<WsEvent> {
+        let Some((pubkey, commitment)) = self.subscription_ids.get(&params.subscription).cloned() else {
+            debug!(sub_id = params.subscription, "Unknown subscription ID");
+            return None;
+        };
+        let now = tokio::time::Instant::now();
+        self.last_notified.insert(params.subscription, now);
+        self.latest_notification = Some(now);
+        self.book.notified(params.subscription);
+        if !self.in_slot_order(&pubkey, commitment, params.result.context.slot) {
+            return None;
+        }
+
+        let data = match params.result.value.data.decode() {
+            Ok(data) => data,
+            Err(e) => {
+                debug!(pubkey = pubkey, error = %e, "Undecodable account data");
+                return None;
+            }
+        };
+
+        Some(WsEvent::AccountUpdate {
+            pubkey,
+            slot: params.result.context.slot,
+            data,
+            commitment,
+        })
+    }
+
+    /// Count a successful transaction through the pool once if its logs
+    /// show the pool's program swapping
+    fn count_swap(&mut self, params: NotificationParams<WithContext<Logs>>) {
+        let Some(pubkey) = self.logs_subscription_ids.get(&params.subscription) else {
+            debug!(sub_id = params.subscription, "Unknown logs subscription ID");
+            return;
+        };
+        self.book.notified(params.subscription);
+        let logs = params.result.value;
+        if logs.err.is_some() {
+            return;
+        }
+        if let Some(dex) = self.activity.dex_of(pubkey) {
+            if activity::is_swap(&dex, &logs.logs) {
+                self.activity.record(pubkey);
+            }
+        }
+    }
+
+    /// Record `slot` for the account, or count the notification as out of
+    /// order when an update from a later slot already went through
+    ///
+    /// Checked before decoding, so replays after a reconnect or failover
+    /// cost a map lookup rather than a base64 decode and a pool decode.
+    fn in_slot_order(&mut self, pubkey: &str, commitment: Commitment, slot: u64) -> bool {
+        let last = self.last_slots.entry((pubkey.to_string(), commitment)).or_insert(slot);
+        if slot < *last {
+            debug!(pubkey = pubkey, slot, last = *last, "Dropping out-of-order notification");
+            self.metrics.record_out_of_order();
+            self.book.out_of_order(pubkey);
+            return false;
+        }
+        *last = slot;
+        true
+    }
+
+    /// The account is named in the notification itself
+    fn program_account_update(&mut self, params: NotificationParams<WithContext<KeyedAccount>>) -> Option<WsEvent> {
+        let Some(&(index, commitment)) = self.program_subscription_ids.get(&params.subscription) else {
+            debug!(sub_id = params.subscription, "Unknown program subscription ID");
+            return None;
+        };
+        self.book.notified(params.subscription);
+
+        let KeyedAccount { pubkey, account } = params.result.value;
+        if !self.in_slot_order(&pubkey, commitment, params.result.context.slot) {
+            return None;
+        }
+        let data = match account.data.decode() {
+            Ok(data) => data,
+            Err(e) => {
+                debug!(pubkey = pubkey, error = %e, "Undecodable account data");
+                return None;
+            }
+        };
+
+        Some(WsEvent::ProgramAccountUpdate {
+            dex: self.programs[index].dex.clone(),
+            pubkey,
+            slot: params.result.context.slot,
+            data,
+            commitment,
+        })
+    }
+}
+
+/// Writer task: owns the sink, sends frames until the channel closes or a
+/// close frame went out
+async fn write_frames(mut write: SplitSink<WsStream, Message>, mut frames: mpsc::Receiver<Message>) -> Result<()> {
+    while let Some(frame) = frames.recv().await {
+        let close = matches!(frame, Message::Close(_));
+        write.send(frame).await.context("WebSocket write failed")?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reader task: owns the stream, forwards frames until it ends or errors
+async fn read_frames(mut read: SplitStream<WsStream>, incoming: mpsc::Sender<Result<Message, WsError>>) {
+    while let Some(frame) = read.next().await {
+        let failed = frame.is_err();
+        if incoming.send(frame).await.is_err() || failed {
+            break;
+        }
+    }
+}
+
+/// Queue a frame for the writer
+async fn send_frame(frames: &mpsc::Sender<Message>, frame: Message) -> Result<()> {
+    frames.send(frame).await.map_err(|_| anyhow::anyhow!("WebSocket writer stopped"))
+}
+
+/// Why the node refused a subscription, for reports
+fn rejection(error: Option<RpcError>) -> String {
+    error.map_or_else(|| "no subscription id in response".to_string(), |e| e.to_string())
+}
+
+/// `delay` shortened by a random fraction of up to `jitter`
+pub(crate) fn jittered(delay: Duration, jitter: f64) -> Duration {
+    if jitter <= 0.0 {
+        return delay;
+    }
+    delay.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..=jitter.min(1.0)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use base64::Engine;
+    use crate::config::{BackpressurePolicy, RpcEndpoint};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
 
     #[tokio::test]
     async fn test_websocket_manager_creation() {
@@ -159,4 +1384,858 @@ mod tests {
         assert_eq!(manager.reconnect_attempts, 0);
         assert_eq!(manager.subscriptions.len(), 1);
     }
+
+    fn notification(subscription_id: u64, slot: u64, data: &[u8]) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "accountNotification",
+            "params": {
+                "subscription": subscription_id,
+                "result": {
+                    "context": { "slot": slot },
+                    "value": {
+                        "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                        "owner": "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+                        "lamports": 6124800
+                    }
+                }
+            }
+        })
+        .to_string()
+    }
+
+    /// Mock RPC node: confirms every subscription, sends one notification per
+    /// pubkey, then closes. Subscription ids differ on each connection.
+    async fn serve(listener: TcpListener, connections: u64) {
+        for connection in 0..connections {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let mut confirmed = Vec::new();
+            while confirmed.len() < 2 {
+                let Some(Ok(Message::Text(text))) = ws.next().await else { return };
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let pubkey = request["params"][0].as_str().unwrap().to_string();
+                // Reverse the order on reconnect so stale mappings would misattribute
+                let subscription_id = if (pubkey == "PoolA") == (connection == 0) { 100 } else { 200 };
+                let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": subscription_id });
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+                confirmed.push((pubkey, subscription_id));
+            }
+
+            confirmed.sort();
+            for (pubkey, subscription_id) in confirmed {
+                let slot = connection * 1000 + subscription_id;
+                ws.send(Message::Text(notification(subscription_id, slot, pubkey.as_bytes()))).await.unwrap();
+            }
+            ws.close(None).await.ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_updates_are_attributed_after_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, 2));
+
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let metrics = Arc::new(PipelineMetrics::default());
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string(), "PoolB".to_string()])
+            .with_metrics(metrics.clone());
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        let mut events = Vec::new();
+        while events.iter().filter(|e| **e == WsEvent::Disconnected).count() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+            events.push(event);
+        }
+        task.abort();
+
+        let updates: Vec<(String, u64, Vec<u8>)> = events
+            .iter()
+            .filter_map(|e| match e {
+                WsEvent::AccountUpdate { pubkey, slot, data, .. } => Some((pubkey.clone(), *slot, data.clone())),
+                _ => None,
+            })
+            .collect();
+        // Data carries the pubkey it was sent for; ids swapped between connections
+        assert_eq!(updates.len(), 4);
+        for (pubkey, _, data) in &updates {
+            assert_eq!(data, pubkey.as_bytes());
+        }
+        let slots: Vec<(&str, u64)> = updates.iter().map(|(pubkey, slot, _)| (pubkey.as_str(), *slot)).collect();
+        assert_eq!(slots, vec![("PoolA", 100), ("PoolB", 200), ("PoolA", 1200), ("PoolB", 1100)]);
+        assert!(events.contains(&WsEvent::Reconnected));
+        assert_eq!(events.iter().filter(|e| matches!(e, WsEvent::SubscriptionConfirmed { .. })).count(), 4);
+
+        // Four confirmations and four notifications came off the wire
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.messages_received, 8);
+        assert!(snapshot.bytes_received > 4 * notification(100, 100, b"PoolA").len() as u64);
+    }
+
+    /// Mock RPC node that confirms subscriptions and stays connected;
+    /// drops connections before the handshake while `accepting` is false
+    async fn flaky_node(listener: TcpListener, accepting: Arc<AtomicBool>) {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            if !accepting.load(Ordering::SeqCst) {
+                continue;
+            }
+            tokio::spawn(async move {
+                let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else { return };
+                while let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let request: Value = serde_json::from_str(&text).unwrap();
+                    let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": 7 });
+                    if ws.send(Message::Text(response.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    }
+
+    async fn next_failover(rx: &mut EventReceiver) -> (String, String) {
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+            if let WsEvent::Failover { from, to } = event {
+                return (from, to);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_failback() {
+        let primary_up = Arc::new(AtomicBool::new(false));
+        let mut urls = Vec::new();
+        for accepting in [primary_up.clone(), Arc::new(AtomicBool::new(true))] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            urls.push(format!("ws://{}", listener.local_addr().unwrap()));
+            tokio::spawn(flaky_node(listener, accepting));
+        }
+
+        let rpc = RpcConfig {
+            endpoints: vec![
+                RpcEndpoint::new("primary", urls[0].clone(), String::new()),
+                RpcEndpoint::new("backup", urls[1].clone(), String::new()),
+            ],
+            failover_after_failures: 2,
+            failback_interval_seconds: 1,
+            ..RpcConfig::default()
+        };
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let mut manager = WebSocketManager::from_rpc_config(&rpc, vec!["PoolA".to_string()]);
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        assert_eq!(next_failover(&mut rx).await, ("primary".to_string(), "backup".to_string()));
+        let confirmed = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(confirmed, WsEvent::SubscriptionConfirmed { .. }), "{:?}", confirmed);
+
+        // The primary recovers; the next probe moves back to it
+        primary_up.store(true, Ordering::SeqCst);
+        assert_eq!(next_failover(&mut rx).await, ("backup".to_string(), "primary".to_string()));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_through_failures_and_recovery() {
+        let up = Arc::new(AtomicBool::new(false));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(flaky_node(listener, up.clone()));
+
+        let config = WebSocketConfig {
+            reconnect_jitter: 0.0,
+            alert_after_failures: 2,
+            ..WebSocketConfig::default()
+        };
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string()]).with_config(&config);
+        let mut status = manager.status();
+        let task = tokio::spawn(async move { manager.run().await });
+
+        let mut seen = vec![status.borrow_and_update().state];
+        while seen.last() != Some(&ConnectionState::Connected) {
+            tokio::time::timeout(Duration::from_secs(5), status.changed()).await.unwrap().unwrap();
+            let current = status.borrow_and_update().state;
+            if matches!(current, ConnectionState::Failed { .. }) {
+                up.store(true, Ordering::SeqCst);
+            }
+            if seen.last() != Some(&current) {
+                seen.push(current);
+            }
+        }
+        // Details follow once the node answers the subscription
+        let confirmed = status.wait_for(|s| s.pending_subscriptions == 0 && s.active_subscriptions == 1);
+        let current = tokio::time::timeout(Duration::from_secs(5), confirmed).await.unwrap().unwrap().clone();
+        assert_eq!(current.endpoint, "default");
+        assert_eq!(current.uptime_secs(), Some(0));
+        task.abort();
+
+        assert_eq!(
+            seen,
+            vec![
+                ConnectionState::Connecting,
+                ConnectionState::Backoff { attempt: 1 },
+                ConnectionState::Connecting,
+                // Stays failed while retrying, until a connect succeeds
+                ConnectionState::Failed { consecutive_failures: 2 },
+                ConnectionState::Connected,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_status_announcements() {
+        let connecting = ConnectionStatus { endpoint: "primary".to_string(), ..ConnectionStatus::default() };
+        let connected = ConnectionStatus {
+            state: ConnectionState::Connected,
+            connected_since: Some(Utc::now()),
+            pending_subscriptions: 3,
+            ..connecting.clone()
+        };
+        let partly = ConnectionStatus { active_subscriptions: 1, pending_subscriptions: 2, ..connected.clone() };
+        let subscribed = ConnectionStatus { active_subscriptions: 3, pending_subscriptions: 0, ..connected.clone() };
+        let backoff = ConnectionStatus { state: ConnectionState::Backoff { attempt: 1 }, ..connecting.clone() };
+        let failed = ConnectionStatus { state: ConnectionState::Failed { consecutive_failures: 5 }, ..connecting.clone() };
+        let failover = ConnectionStatus { endpoint: "backup".to_string(), ..failed.clone() };
+
+        assert!(connected.should_announce(&connecting));
+        assert!(!partly.should_announce(&connected));
+        assert!(subscribed.should_announce(&partly));
+        assert!(backoff.should_announce(&subscribed));
+        assert!(!connecting.should_announce(&backoff));
+        assert!(failed.should_announce(&connecting));
+        assert!(failover.should_announce(&failed));
+    }
+
+    #[tokio::test]
+    async fn test_connects_through_socks5_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://localhost:{}", listener.local_addr().unwrap().port());
+        tokio::spawn(flaky_node(listener, Arc::new(AtomicBool::new(true))));
+        let proxy_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("socks5://svc:hunter2@{}", proxy_listener.local_addr().unwrap());
+        tokio::spawn(crate::net::proxy::tests::socks5_proxy(proxy_listener, Some(("svc", "hunter2"))));
+
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string()]);
+        manager.egress = Egress::from_rpc_config(&RpcConfig { proxy: Some(proxy), ..RpcConfig::default() });
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        let confirmed = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(confirmed, WsEvent::SubscriptionConfirmed { .. }), "{:?}", confirmed);
+        task.abort();
+
+        // A proxy that isn't listening fails the attempt with a clear reason
+        let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let egress = Egress::from_rpc_config(&RpcConfig {
+            proxy: Some(format!("http://127.0.0.1:{}", port)),
+            ..RpcConfig::default()
+        });
+        let error = egress.connect_websocket(Url::parse("ws://localhost:1").unwrap()).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Proxy unreachable"), "{:#}", error);
+    }
+
+    #[tokio::test]
+    async fn test_silent_feed_is_reconnected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Confirms every subscription, never notifies
+        tokio::spawn(flaky_node(listener, Arc::new(AtomicBool::new(true))));
+
+        let metrics = Arc::new(PipelineMetrics::default());
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string()]).with_metrics(metrics.clone());
+        manager.silence_timeout = Some(Duration::from_millis(200));
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        let mut events = Vec::new();
+        while !events.contains(&WsEvent::Reconnected) {
+            events.push(tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap());
+        }
+        task.abort();
+
+        assert!(matches!(events[0], WsEvent::SubscriptionConfirmed { .. }));
+        assert!(events.contains(&WsEvent::Disconnected));
+        assert_eq!(metrics.snapshot().silence_reconnects, 1);
+    }
+
+    #[test]
+    fn test_jitter_only_shortens_delays() {
+        let delay = Duration::from_secs(10);
+        assert_eq!(jittered(delay, 0.0), delay);
+        for _ in 0..100 {
+            let shortened = jittered(delay, 0.3);
+            assert!(shortened <= delay && shortened >= Duration::from_secs(7), "{:?}", shortened);
+        }
+    }
+
+    #[test]
+    fn test_slot_notifications_track_chain_slot_and_lag() {
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string()]);
+        let requests = manager.subscription_requests();
+        assert!(requests[1].contains("slotSubscribe"));
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":10}"#);
+        assert_eq!(manager.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":20}"#), None);
+
+        let slot_update = |subscription: u64, slot: u64| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "slotNotification",
+                "params": { "subscription": subscription, "result": { "parent": slot - 1, "root": slot - 32, "slot": slot } }
+            })
+            .to_string()
+        };
+        // Only the confirmed slot subscription counts
+        assert_eq!(manager.handle_text(&slot_update(10, 499)), None);
+
+        let current_slot = crate::cache::CurrentSlot::default();
+        for slot in [500, 498] {
+            match manager.handle_text(&slot_update(20, slot)) {
+                Some(WsEvent::SlotAdvanced { slot, timestamp }) => current_slot.advance(slot, timestamp),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(current_slot.get(), Some(500));
+
+        let Some(WsEvent::AccountUpdate { slot, .. }) = manager.handle_text(&notification(10, 497, b"data")) else {
+            panic!("expected an account update");
+        };
+        assert_eq!(current_slot.observe_update(slot), Some(3));
+        assert_eq!(current_slot.lag_metrics().last, 3);
+        assert_eq!(current_slot.lag_metrics().samples, 1);
+
+        let mut disabled = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() });
+        assert_eq!(disabled.subscription_requests().len(), 1);
+    }
+
+    #[test]
+    fn test_commitment_params_and_dual_subscriptions() {
+        let rpc = |commitment, dual_commitment| RpcConfig {
+            endpoints: vec![RpcEndpoint::new("primary", "ws://unused".to_string(), String::new())],
+            commitment,
+            dual_commitment,
+            ..RpcConfig::default()
+        };
+        let account_commitments = |requests: Vec<String>| -> Vec<String> {
+            requests
+                .iter()
+                .map(|r| serde_json::from_str::<Value>(r).unwrap())
+                .filter(|r| r["method"] == "accountSubscribe")
+                .map(|r| r["params"][1]["commitment"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let mut manager = WebSocketManager::from_rpc_config(&rpc(Commitment::Confirmed, false), vec!["PoolA".to_string()]);
+        assert_eq!(account_commitments(manager.subscription_requests()), ["confirmed"]);
+
+        let mut dual = WebSocketManager::from_rpc_config(&rpc(Commitment::Processed, true), vec!["PoolA".to_string()]);
+        assert_eq!(account_commitments(dual.subscription_requests()), ["processed", "confirmed"]);
+        dual.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":10}"#);
+        dual.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":11}"#);
+
+        let commitment_of = |event: Option<WsEvent>| match event {
+            Some(WsEvent::AccountUpdate { commitment, .. }) => commitment,
+            other => panic!("expected an account update, got {:?}", other),
+        };
+        assert_eq!(commitment_of(dual.handle_text(&notification(10, 5, b"data"))), Commitment::Processed);
+        assert_eq!(commitment_of(dual.handle_text(&notification(11, 5, b"data"))), Commitment::Confirmed);
+    }
+
+    #[test]
+    fn test_program_subscriptions_follow_dual_commitment() {
+        let rpc = RpcConfig {
+            endpoints: vec![RpcEndpoint::new("primary", "ws://unused".to_string(), String::new())],
+            dual_commitment: true,
+            ..RpcConfig::default()
+        };
+        let programs = HashMap::from([(
+            Dex::Orca,
+            DexSubscriptionConfig { mode: SubscriptionMode::Program, ..Default::default() },
+        )]);
+        let mut manager = WebSocketManager::from_rpc_config(&rpc, vec![])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() })
+            .with_programs(&programs);
+
+        let commitments: Vec<Value> = manager
+            .subscription_requests()
+            .iter()
+            .map(|r| serde_json::from_str::<Value>(r).unwrap())
+            .filter(|r| r["method"] == "programSubscribe")
+            .map(|r| r["params"][1]["commitment"].clone())
+            .collect();
+        assert_eq!(commitments, [json!("processed"), json!("confirmed")]);
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":30}"#);
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":31}"#);
+
+        let program_notification = |subscription: u64, slot: u64| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "programNotification",
+                "params": {
+                    "subscription": subscription,
+                    "result": {
+                        "context": { "slot": slot },
+                        "value": {
+                            "pubkey": "PoolA",
+                            "account": { "data": ["ZGF0YQ==", "base64"], "executable": false, "lamports": 1, "owner": "x" }
+                        }
+                    }
+                }
+            })
+            .to_string()
+        };
+        let commitment_of = |event: Option<WsEvent>| match event {
+            Some(WsEvent::ProgramAccountUpdate { commitment, .. }) => commitment,
+            other => panic!("expected a program account update, got {:?}", other),
+        };
+        assert_eq!(commitment_of(manager.handle_text(&program_notification(30, 7))), Commitment::Processed);
+        // Confirmed trails processed without being dropped as out of order
+        assert_eq!(commitment_of(manager.handle_text(&program_notification(31, 6))), Commitment::Confirmed);
+    }
+
+    #[test]
+    fn test_out_of_order_slots_are_dropped_before_decoding() {
+        let rpc = RpcConfig {
+            endpoints: vec![RpcEndpoint::new("primary", "ws://unused".to_string(), String::new())],
+            dual_commitment: true,
+            ..RpcConfig::default()
+        };
+        let metrics = Arc::new(PipelineMetrics::default());
+        let book = SubscriptionBook::default();
+        let mut manager = WebSocketManager::from_rpc_config(&rpc, vec!["PoolA".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() })
+            .with_metrics(metrics.clone())
+            .with_subscription_book(book.clone());
+        manager.subscription_requests();
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":10}"#);
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":11}"#);
+
+        let passed: Vec<u64> = [100, 103, 101, 103, 104, 102, 110]
+            .into_iter()
+            .filter_map(|slot| match manager.handle_text(&notification(10, slot, b"data")) {
+                Some(WsEvent::AccountUpdate { slot, .. }) => Some(slot),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(passed, vec![100, 103, 103, 104, 110]);
+
+        // Confirmed trails processed on its own subscription and isn't dropped
+        assert!(manager.handle_text(&notification(11, 101, b"data")).is_some());
+        assert_eq!(metrics.snapshot().out_of_order_drops, 2);
+        assert_eq!(book.out_of_order_drops(), BTreeMap::from([("PoolA".to_string(), 2)]));
+
+        // Slots survive a reconnect: a replay of older state is dropped
+        manager.subscription_requests();
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":20}"#);
+        assert_eq!(manager.handle_text(&notification(20, 105, b"data")), None);
+        assert_eq!(metrics.snapshot().out_of_order_drops, 3);
+    }
+
+    #[test]
+    fn test_taps_receive_only_matching_frames() {
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string(), "PoolB".to_string()]);
+        let handle = manager.handle();
+        let mut pool_a = handle.tap(TapFilter::pubkey("PoolA"));
+        let mut slots = handle.tap(TapFilter::method("slotNotification"));
+
+        // PoolA gets subscription 10, PoolB 11 and the slot subscription 12
+        let confirmations: Vec<String> = manager
+            .subscription_requests()
+            .iter()
+            .map(|request| {
+                let request: Value = serde_json::from_str(request).unwrap();
+                let subscription_id = match request["params"][0].as_str() {
+                    Some("PoolA") => 10,
+                    Some(_) => 11,
+                    None => 12,
+                };
+                json!({ "jsonrpc": "2.0", "id": request["id"], "result": subscription_id }).to_string()
+            })
+            .collect();
+        for confirmation in &confirmations {
+            manager.handle_text(confirmation);
+        }
+        let pool_a_confirmation = confirmations.iter().find(|c| c.contains(":10")).unwrap().clone();
+        let slot = r#"{"jsonrpc":"2.0","method":"slotNotification","params":{"subscription":12,"result":{"parent":1,"root":0,"slot":2}}}"#;
+        let frames = [notification(10, 5, b"a"), notification(11, 5, b"b"), slot.to_string(), notification(10, 6, b"a")];
+        for frame in &frames {
+            manager.handle_text(frame);
+        }
+
+        let drain = |rx: &mut mpsc::Receiver<String>| std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<_>>();
+        assert_eq!(drain(&mut pool_a), vec![pool_a_confirmation, frames[0].clone(), frames[3].clone()]);
+        assert_eq!(drain(&mut slots), vec![slot.to_string()]);
+
+        // Dropped taps stop costing anything
+        drop((pool_a, slots));
+        manager.handle_text(&frames[0]);
+        assert!(!manager.taps.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_unsubscribes_and_closes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Confirms subscriptions, then records everything up to the close frame
+        let node = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut received = Vec::new();
+            while let Some(Ok(message)) = ws.next().await {
+                let Message::Text(text) = message else {
+                    received.push("close".to_string());
+                    break;
+                };
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let method = request["method"].as_str().unwrap().to_string();
+                if method.ends_with("Unsubscribe") {
+                    received.push(format!("{}:{}", method, request["params"][0]));
+                    continue;
+                }
+                let subscription_id = if method == "slotSubscribe" { 200 } else { 100 };
+                let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": subscription_id });
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+            }
+            received
+        });
+
+        let shutdown = CancellationToken::new();
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string()]).with_shutdown(shutdown.clone());
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        let confirmed = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(confirmed, WsEvent::SubscriptionConfirmed { .. }), "{:?}", confirmed);
+        // Let the slot confirmation land before shutting down
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(5), node).await.unwrap().unwrap();
+        assert_eq!(received, vec!["accountUnsubscribe:100", "slotUnsubscribe:200", "close"]);
+        // A deliberate shutdown isn't reported as a disconnect
+        assert_eq!(rx.recv().await, None);
+    }
+
+    async fn next_request(requests: &mut mpsc::UnboundedReceiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(5), requests.recv()).await.unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_account_subscribe_requests_data_slice() {
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec![])
+            .with_data_slices(HashMap::from([("PoolA".to_string(), (13, 36))]));
+
+        let sliced: Value = serde_json::from_str(&manager.account_subscribe_request("PoolA", Commitment::Processed)).unwrap();
+        assert_eq!(sliced["params"][1]["dataSlice"], json!({ "offset": 13, "length": 36 }));
+        assert_eq!(sliced["params"][1]["encoding"], "base64");
+        let whole: Value = serde_json::from_str(&manager.account_subscribe_request("PoolB", Commitment::Processed)).unwrap();
+        assert!(whole["params"][1].get("dataSlice").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_commands_are_sent_mid_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Confirms each subscription with a fresh id and reports every request
+        let (requests_tx, mut requests) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut next_subscription = 100;
+            while let Some(Ok(message)) = ws.next().await {
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Ping(_) => {
+                        requests_tx.send("ping".to_string()).ok();
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let method = request["method"].as_str().unwrap();
+                requests_tx.send(format!("{}:{}", method, request["params"][0])).ok();
+                if method.ends_with("Unsubscribe") {
+                    continue;
+                }
+                next_subscription += 1;
+                let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": next_subscription });
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+            }
+        });
+
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::Block);
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() });
+        let handle = manager.handle();
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+
+        assert_eq!(next_request(&mut requests).await, "accountSubscribe:\"PoolA\"");
+        let confirmed = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert!(matches!(confirmed, WsEvent::SubscriptionConfirmed { subscription_id: 101, .. }));
+
+        // Same connection: new subscription, its removal, and a ping
+        assert!(handle.subscribe("PoolB"));
+        assert_eq!(next_request(&mut requests).await, "accountSubscribe:\"PoolB\"");
+        let confirmed = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(confirmed, WsEvent::SubscriptionConfirmed { pubkey: "PoolB".to_string(), subscription_id: 102 });
+        assert!(handle.unsubscribe("PoolB"));
+        assert_eq!(next_request(&mut requests).await, "accountUnsubscribe:102");
+        assert!(handle.send(WsCommand::Ping));
+        assert_eq!(next_request(&mut requests).await, "ping");
+
+        task.abort();
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_quiet_subscription_is_resubscribed_alone() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Confirms every subscription, then keeps notifying PoolA only
+        let (requests_tx, mut requests) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (mut sink, mut stream) = tokio_tungstenite::accept_async(stream).await.unwrap().split();
+            let mut subscription_ids = HashMap::new();
+            let mut tick = tokio::time::interval(Duration::from_millis(50));
+            loop {
+                tokio::select! {
+                    message = stream.next() => {
+                        let Some(Ok(Message::Text(text))) = message else { break };
+                        let request: Value = serde_json::from_str(&text).unwrap();
+                        let method = request["method"].as_str().unwrap();
+                        requests_tx.send(format!("{}:{}", method, request["params"][0])).ok();
+                        if method.ends_with("Unsubscribe") {
+                            continue;
+                        }
+                        let subscription_id = 100 + request["id"].as_u64().unwrap();
+                        subscription_ids.insert(request["params"][0].as_str().unwrap().to_string(), subscription_id);
+                        let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": subscription_id });
+                        sink.send(Message::Text(response.to_string())).await.unwrap();
+                    }
+                    _ = tick.tick() => {
+                        if let Some(&subscription_id) = subscription_ids.get("PoolA") {
+                            sink.send(Message::Text(notification(subscription_id, 1, &[1]))).await.unwrap();
+                        }
+                    }
+                }
+            }
+        });
+
+        let metrics = Arc::new(PipelineMetrics::default());
+        let (tx, mut rx) = event_channel(100, BackpressurePolicy::DropOldest);
+        let mut manager = WebSocketManager::new(url, vec!["PoolA".to_string(), "PoolB".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() })
+            .with_metrics(metrics.clone());
+        manager.stale_timeout = Some(Duration::from_millis(300));
+        manager.set_sender(tx);
+        let task = tokio::spawn(async move { manager.run().await });
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+
+        // Request ids, and so subscription ids, follow the send order
+        let first = next_request(&mut requests).await;
+        let second = next_request(&mut requests).await;
+        let pool_b_subscription = if first.contains("PoolB") { 101 } else { 102 };
+        assert!(second.contains(if pool_b_subscription == 101 { "PoolA" } else { "PoolB" }), "{}", second);
+
+        // Only PoolB is renewed, while PoolA keeps its subscription
+        assert_eq!(next_request(&mut requests).await, format!("accountUnsubscribe:{}", pool_b_subscription));
+        assert_eq!(next_request(&mut requests).await, "accountSubscribe:\"PoolB\"");
+        task.abort();
+        assert_eq!(metrics.snapshot().stale_resubscribes, 1);
+    }
+
+    #[test]
+    fn test_subscription_report_tracks_confirmations_and_notifications() {
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string(), "PoolB".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() });
+        let handle = manager.handle();
+        manager.subscription_requests();
+        manager.publish_subscription_counts();
+        let report = handle.subscription_report();
+        assert_eq!(report.len(), 2);
+        assert!(report.iter().all(|s| !s.confirmed && s.subscription_id.is_none()));
+
+        let first_pubkey = manager.pending[&1].0.clone();
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":41}"#);
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":42}"#);
+        assert!(manager.handle_text(&notification(41, 9, &[1])).is_some());
+
+        let report = handle.subscription_report();
+        let notified = report.iter().find(|s| s.pubkey == first_pubkey).unwrap();
+        assert_eq!((notified.subscription_id, notified.confirmed), (Some(41), true));
+        assert!(notified.ms_since_notification.is_some());
+        let quiet = report.iter().find(|s| s.pubkey != first_pubkey).unwrap();
+        assert_eq!((quiet.subscription_id, quiet.ms_since_notification), (Some(42), None));
+
+        manager.set_disconnected(ConnectionState::Connecting);
+        assert!(handle.subscription_report().is_empty());
+    }
+
+    #[test]
+    fn test_unsubscribing_in_flight_request_unsubscribes_on_confirmation() {
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() });
+        manager.subscription_requests();
+        manager.subscriptions.remove("PoolA");
+        manager.cancelled.insert(1);
+
+        assert_eq!(manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":55}"#), None);
+        let request: Value = serde_json::from_str(&manager.outbox[0]).unwrap();
+        assert_eq!((request["method"].as_str(), request["params"][0].as_u64()), (Some("accountUnsubscribe"), Some(55)));
+        assert!(manager.subscription_ids.is_empty());
+    }
+
+    #[test]
+    fn test_logs_notifications_count_swaps_per_pool() {
+        let activity = SwapActivity::default();
+        activity.set_pools(HashMap::from([("PoolA".to_string(), ("SOL-USDC".to_string(), Dex::Orca))]));
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec!["PoolA".to_string()])
+            .with_config(&WebSocketConfig { slot_subscribe: false, swap_activity: true, ..Default::default() })
+            .with_swap_activity(activity.clone());
+        let requests = manager.subscription_requests();
+        assert_eq!(requests.len(), 2);
+        let request: Value = serde_json::from_str(&requests[1]).unwrap();
+        assert_eq!(request["method"], "logsSubscribe");
+        assert_eq!(request["params"][0], json!({ "mentions": ["PoolA"] }));
+        manager.handle_text(r#"{"jsonrpc":"2.0","id":2,"result":77}"#);
+
+        let logs = |err: Value| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "logsNotification",
+                "params": {
+                    "subscription": 77,
+                    "result": {
+                        "context": { "slot": 5208469 },
+                        "value": {
+                            "signature": "5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv",
+                            "err": err,
+                            "logs": [
+                                "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc invoke [1]",
+                                "Program log: Instruction: Swap",
+                                "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc success"
+                            ]
+                        }
+                    }
+                }
+            })
+            .to_string()
+        };
+        assert_eq!(manager.handle_text(&logs(Value::Null)), None);
+        // Failed transactions didn't swap
+        assert_eq!(manager.handle_text(&logs(json!({ "InstructionError": [0, { "Custom": 6001 }] }))), None);
+        let report = activity.report();
+        assert_eq!((report[0].swaps_per_minute, report[0].total_swaps), (1, 1));
+
+        let unsubscribes = manager.unsubscribe_requests();
+        assert_eq!(unsubscribes.len(), 1);
+        let request: Value = serde_json::from_str(&unsubscribes[0]).unwrap();
+        assert_eq!((request["method"].as_str(), request["params"][0].as_u64()), (Some("logsUnsubscribe"), Some(77)));
+    }
+
+    #[test]
+    fn test_program_notification_resolves_pool() {
+        use crate::config::{Settings, TokenConfig};
+        use crate::decoder::orca::WhirlpoolState;
+        use crate::decoder::{OrcaDecoder, PoolDecoder, SpecificPoolData};
+        use borsh::BorshSerialize;
+        use solana_sdk::pubkey::Pubkey;
+
+        const SOL: &str = "So11111111111111111111111111111111111111112";
+        const USDC: &str = "EPjFWdd5AufqSSqeM2qJxvKNN9aU8BLF6yWyc1zsMuvu";
+
+        let mut settings = Settings::default();
+        for (symbol, mint, decimals) in [("SOL", SOL, 9), ("USDC", USDC, 6)] {
+            settings.tokens.insert(symbol.to_string(), TokenConfig { mint: mint.to_string(), decimals });
+        }
+        settings.subscriptions.insert(
+            Dex::Orca,
+            DexSubscriptionConfig { mode: SubscriptionMode::Program, data_size: Some(653), ..Default::default() },
+        );
+        // Account-mode entries don't subscribe to the program
+        settings.subscriptions.insert(Dex::Meteora, DexSubscriptionConfig::default());
+
+        let mut manager = WebSocketManager::new("ws://unused".to_string(), vec![])
+            .with_config(&WebSocketConfig { slot_subscribe: false, ..Default::default() })
+            .with_programs(&settings.subscriptions);
+        let requests = manager.subscription_requests();
+        assert_eq!(requests.len(), 1);
+        let request: Value = serde_json::from_str(&requests[0]).unwrap();
+        assert_eq!(request["method"], "programSubscribe");
+        assert_eq!(request["params"][0], "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+        assert_eq!(request["params"][1]["filters"], json!([{ "dataSize": 653 }]));
+        assert_eq!(manager.handle_text(r#"{"jsonrpc":"2.0","id":1,"result":24040}"#), None);
+
+        let whirlpool = WhirlpoolState {
+            whirlpool_bump: [255],
+            tick_spacing: 64,
+            tick_spacing_seed: [64, 0],
+            fee_rate: 3000,
+            protocol_fee_rate: 300,
+            liquidity: 1_000_000,
+            sqrt_price: 1 << 64,
+            tick_current_index: 0,
+            protocol_fee_owed_a: 0,
+            protocol_fee_owed_b: 0,
+            token_mint_a: SOL.parse().unwrap(),
+            token_vault_a: Pubkey::new_unique(),
+            fee_growth_global_a: 0,
+            token_mint_b: USDC.parse().unwrap(),
+            token_vault_b: Pubkey::new_unique(),
+            fee_growth_global_b: 0,
+            reward_last_updated_timestamp: 0,
+        };
+        let mut account = vec![0u8; 8];
+        whirlpool.serialize(&mut account).unwrap();
+        let fixture = json!({
+            "jsonrpc": "2.0",
+            "method": "programNotification",
+            "params": {
+                "subscription": 24040,
+                "result": {
+                    "context": { "slot": 5208469 },
+                    "value": {
+                        "pubkey": "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ",
+                        "account": {
+                            "data": [base64::engine::general_purpose::STANDARD.encode(&account), "base64"],
+                            "executable": false,
+                            "lamports": 33594,
+                            "owner": "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+                            "rentEpoch": 636
+                        }
+                    }
+                }
+            }
+        })
+        .to_string();
+
+        let Some(WsEvent::ProgramAccountUpdate { dex, pubkey, slot, data, .. }) = manager.handle_text(&fixture) else {
+            panic!("expected a program account update");
+        };
+        assert_eq!((dex.as_str(), pubkey.as_str(), slot), ("orca", "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ", 5208469));
+
+        // Unlisted pool: the pair comes from its mints, decimals from [tokens]
+        let (mint_a, mint_b) = OrcaDecoder::default().token_mints(&data).unwrap();
+        let pair = settings.pair_for_mints(&mint_a.to_string(), &mint_b.to_string()).unwrap();
+        assert_eq!(pair, "SOL-USDC");
+        let decimals = settings.pool_decimals(&pair, &pubkey).unwrap();
+        let decoder = OrcaDecoder::new(decimals.token_a_decimals, decimals.token_b_decimals);
+        let SpecificPoolData::Clmm { sqrt_price, .. } = decoder.decode(&data).unwrap().specific_data else {
+            panic!("expected CLMM state");
+        };
+        assert!((decoder.calculate_price_from_sqrt(sqrt_price) - 1000.0).abs() < 1e-9);
+
+        // Other subscriptions' notifications are ignored
+        assert_eq!(manager.handle_text(&fixture.replace("24040", "1")), None);
+    }
 }
@@ -0,0 +1,364 @@
+//! Bounded event queue between the WebSocket reader and the main loop
+//!
+//! A blocking send stalls the read loop behind a slow consumer until the RPC
+//! node disconnects us. Instead, when the queue is full the configured
+//! `BackpressurePolicy` sheds account data, which is safe because only the
+//! latest state of each account matters, and unmodelled notifications.
+//! Connection events are always kept.
+
+use super::WsEvent;
+use crate::config::{BackpressurePolicy, Commitment};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Create a queue holding up to `capacity` events
+pub fn event_channel(capacity: usize, policy: BackpressurePolicy) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            events: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+        }),
+        capacity: capacity.max(1),
+        policy,
+        items: Notify::new(),
+        space: Notify::new(),
+        metrics: Arc::new(QueueMetrics {
+            capacity: capacity.max(1),
+            ..QueueMetrics::default()
+        }),
+    });
+    (EventSender { shared: shared.clone() }, EventReceiver { shared })
+}
+
+/// Events shed by the backpressure policy since startup, and how full the
+/// queue is now
+#[derive(Debug, Default)]
+pub struct QueueMetrics {
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+    queued: AtomicUsize,
+    capacity: usize,
+}
+
+impl QueueMetrics {
+    /// Updates discarded to make room for newer ones
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Queued updates replaced by a newer one for the same account
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Events waiting for the main loop
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of the queue in use (connection events can push it past 1)
+    pub fn occupancy(&self) -> f64 {
+        self.queued() as f64 / self.capacity.max(1) as f64
+    }
+}
+
+/// What a queued event may be merged with
+#[derive(PartialEq)]
+enum CoalesceKey {
+    /// Dual-commitment feeds deliver each account at two commitments
+    Account(String, Commitment),
+    Slot,
+}
+
+impl CoalesceKey {
+    /// `None` for connection events and unmodelled notifications, which
+    /// are never merged
+    fn of(event: &WsEvent) -> Option<Self> {
+        match event {
+            WsEvent::AccountUpdate { pubkey, commitment, .. }
+            | WsEvent::ProgramAccountUpdate { pubkey, commitment, .. } => {
+                Some(CoalesceKey::Account(pubkey.clone(), *commitment))
+            }
+            WsEvent::SlotAdvanced { .. } => Some(CoalesceKey::Slot),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a full queue may shed the event: everything but connection events
+fn droppable(event: &WsEvent) -> bool {
+    !matches!(
+        event,
+        WsEvent::SubscriptionConfirmed { .. } | WsEvent::Disconnected | WsEvent::Reconnected | WsEvent::Failover { .. }
+    )
+}
+
+struct Entry {
+    key: Option<CoalesceKey>,
+    droppable: bool,
+    event: WsEvent,
+}
+
+struct State {
+    events: VecDeque<Entry>,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    policy: BackpressurePolicy,
+    /// Signalled when an event is queued or the last sender goes away
+    items: Notify,
+    /// Signalled when an event is taken or the receiver goes away
+    space: Notify,
+    metrics: Arc<QueueMetrics>,
+}
+
+impl Shared {
+    /// Queue the event unless `Block` has to wait, handing it back then
+    fn push(&self, state: &mut State, event: WsEvent) -> Result<(), WsEvent> {
+        let key = CoalesceKey::of(&event);
+        let droppable = droppable(&event);
+
+        if self.policy == BackpressurePolicy::Coalesce && key.is_some() {
+            if let Some(queued) = state.events.iter_mut().find(|e| e.key == key) {
+                queued.event = event;
+                self.metrics.coalesced.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+
+        if state.events.len() >= self.capacity {
+            if self.policy == BackpressurePolicy::Block {
+                return Err(event);
+            }
+            // Connection events may overfill the queue rather than be lost
+            match state.events.iter().position(|e| e.droppable) {
+                Some(oldest) => {
+                    state.events.remove(oldest);
+                    self.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                // Only connection events queued: shed the newcomer instead
+                None if droppable => {
+                    self.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                None => {}
+            }
+        }
+
+        state.events.push_back(Entry { key, droppable, event });
+        self.metrics.queued.store(state.events.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Writing half, held by the WebSocket manager
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl EventSender {
+    /// Queue an event; false once the receiver is gone
+    ///
+    /// Only waits under `BackpressurePolicy::Block`.
+    pub async fn send(&self, mut event: WsEvent) -> bool {
+        loop {
+            let space = self.shared.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if !state.receiver_alive {
+                    return false;
+                }
+                match self.shared.push(&mut state, event) {
+                    Ok(()) => {
+                        drop(state);
+                        self.shared.items.notify_one();
+                        return true;
+                    }
+                    Err(returned) => event = returned,
+                }
+            }
+            space.await;
+        }
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let last = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.senders -= 1;
+            state.senders == 0
+        };
+        if last {
+            self.shared.items.notify_one();
+        }
+    }
+}
+
+/// Reading half, drained by the main loop
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    /// Next event; `None` once every sender is gone and the queue is empty
+    pub async fn recv(&mut self) -> Option<WsEvent> {
+        loop {
+            let items = self.shared.items.notified();
+            tokio::pin!(items);
+            items.as_mut().enable();
+
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(entry) = state.events.pop_front() {
+                    self.shared.metrics.queued.store(state.events.len(), Ordering::Relaxed);
+                    drop(state);
+                    self.shared.space.notify_one();
+                    return Some(entry.event);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+            items.await;
+        }
+    }
+
+    /// Drop and coalesce counters, for health reporting
+    pub fn metrics(&self) -> Arc<QueueMetrics> {
+        self.shared.metrics.clone()
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receiver_alive = false;
+        self.shared.space.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn update(pubkey: &str, slot: u64) -> WsEvent {
+        WsEvent::AccountUpdate {
+            pubkey: pubkey.to_string(),
+            slot,
+            data: Vec::new(),
+            commitment: Commitment::Processed,
+        }
+    }
+
+    /// Queue `events` while nothing reads, then drain what is left
+    async fn fill_then_drain(policy: BackpressurePolicy, events: Vec<WsEvent>) -> (Vec<WsEvent>, Arc<QueueMetrics>) {
+        let (tx, mut rx) = event_channel(4, policy);
+        for event in events {
+            assert!(tx.send(event).await);
+        }
+        drop(tx);
+
+        let mut drained = Vec::new();
+        while let Some(event) = rx.recv().await {
+            drained.push(event);
+        }
+        (drained, rx.metrics())
+    }
+
+    fn slots_of(events: &[WsEvent], pubkey: &str) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                WsEvent::AccountUpdate { pubkey: p, slot, .. } if p == pubkey => Some(*slot),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_keeps_newest_update_per_pubkey() {
+        let burst = || (1..=10).map(|slot| update(if slot % 2 == 0 { "PoolA" } else { "PoolB" }, slot)).collect();
+
+        let (drained, metrics) = fill_then_drain(BackpressurePolicy::DropOldest, burst()).await;
+        assert_eq!(metrics.queued(), 0);
+        assert_eq!(slots_of(&drained, "PoolA"), vec![8, 10]);
+        assert_eq!(slots_of(&drained, "PoolB"), vec![7, 9]);
+        assert_eq!(metrics.dropped(), 6);
+
+        let (drained, metrics) = fill_then_drain(BackpressurePolicy::Coalesce, burst()).await;
+        assert_eq!(slots_of(&drained, "PoolA"), vec![10]);
+        assert_eq!(slots_of(&drained, "PoolB"), vec![9]);
+        assert_eq!((metrics.coalesced(), metrics.dropped()), (8, 0));
+    }
+
+    #[tokio::test]
+    async fn test_connection_events_are_never_dropped() {
+        let mut events = vec![WsEvent::Disconnected, WsEvent::Reconnected];
+        events.extend((1..=6).map(|slot| update("PoolA", slot)));
+
+        let (drained, _) = fill_then_drain(BackpressurePolicy::DropOldest, events).await;
+        assert_eq!(&drained[..2], &[WsEvent::Disconnected, WsEvent::Reconnected]);
+        assert_eq!(slots_of(&drained, "PoolA"), vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_unhandled_notifications_count_against_capacity() {
+        let unhandled = |n: u64| WsEvent::Unhandled { method: "voteNotification".to_string(), params: n.into() };
+        for policy in [BackpressurePolicy::DropOldest, BackpressurePolicy::Coalesce] {
+            let events = (1..=10).map(unhandled).collect();
+            let (drained, metrics) = fill_then_drain(policy, events).await;
+            assert_eq!(drained, (7..=10).map(unhandled).collect::<Vec<_>>());
+            assert_eq!(metrics.dropped(), 6);
+        }
+
+        // Behind a full queue of connection events, the newcomer is shed
+        let mut events = vec![WsEvent::Disconnected, WsEvent::Reconnected, WsEvent::Disconnected, WsEvent::Reconnected];
+        events.push(unhandled(1));
+        let (drained, metrics) = fill_then_drain(BackpressurePolicy::DropOldest, events).await;
+        assert_eq!((drained.len(), metrics.dropped()), (4, 1));
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_the_consumer() {
+        let (tx, mut rx) = event_channel(1, BackpressurePolicy::Block);
+        assert!(tx.send(update("PoolA", 1)).await);
+
+        let blocked = tokio::spawn(async move { tx.send(update("PoolA", 2)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+        assert_eq!((rx.metrics().queued(), rx.metrics().occupancy()), (1, 1.0));
+
+        assert_eq!(rx.recv().await, Some(update("PoolA", 1)));
+        assert!(blocked.await.unwrap());
+        assert_eq!(rx.recv().await, Some(update("PoolA", 2)));
+        assert_eq!(rx.metrics().dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_once_receiver_is_gone() {
+        let (tx, rx) = event_channel(1, BackpressurePolicy::Block);
+        assert!(tx.send(update("PoolA", 1)).await);
+        let blocked = tokio::spawn(async move { tx.send(update("PoolA", 2)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(rx);
+        assert!(!blocked.await.unwrap());
+    }
+}
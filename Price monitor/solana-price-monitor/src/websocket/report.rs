@@ -0,0 +1,237 @@
+//! Per-subscription bookkeeping shared with readers outside the manager
+//!
+//! The manager mirrors its id maps into a `SubscriptionBook` whenever they
+//! change and stamps entries as notifications arrive; dashboards read a
+//! `SubscriptionInfo` per subscription from it without a round trip
+//! through the command loop, so reports work while disconnected too.
+//!
+//! Confirmed entries are keyed by subscription id and stamped atomically,
+//! so a notification only takes the read lock.
+
+use crate::config::Commitment;
+use crate::models::Dex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// What a subscription covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionKind {
+    /// One pool account (`accountSubscribe`)
+    Account,
+    /// Every pool account of a DEX program (`programSubscribe`)
+    Program,
+    /// Transactions mentioning one pool account (`logsSubscribe`)
+    Logs,
+}
+
+/// One subscription on the current connection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubscriptionInfo {
+    pub kind: SubscriptionKind,
+    /// Pool account, or program id for program subscriptions
+    pub pubkey: String,
+    pub pair: Option<String>,
+    pub dex: Option<Dex>,
+    pub commitment: Commitment,
+    /// `None` until the node confirms the request
+    pub subscription_id: Option<u64>,
+    pub confirmed: bool,
+    /// `None` before the first notification on this subscription
+    pub ms_since_notification: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    kind: SubscriptionKind,
+    pubkey: String,
+    dex: Option<Dex>,
+    commitment: Commitment,
+    /// Ms after the book's `started` + 1 of the last notification (0 = none)
+    last_notification: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Book {
+    started: Instant,
+    /// Pubkey -> (pair, dex) of monitored pools
+    pools: HashMap<String, (String, Dex)>,
+    /// Confirmed subscriptions by subscription id
+    confirmed: HashMap<u64, Entry>,
+    /// Requested, awaiting confirmation
+    pending: Vec<Entry>,
+    /// Pubkey -> notifications dropped for arriving out of slot order;
+    /// kept across connections
+    out_of_order: HashMap<String, u64>,
+    /// Pubkey (or program id) -> error of its last rejected subscription,
+    /// until it is requested again
+    rejected: HashMap<String, String>,
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            pools: HashMap::new(),
+            confirmed: HashMap::new(),
+            pending: Vec::new(),
+            out_of_order: HashMap::new(),
+            rejected: HashMap::new(),
+        }
+    }
+}
+
+impl Book {
+    fn tick(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.started).as_millis() as u64 + 1
+    }
+}
+
+/// Subscriptions of the current connection, cheap to clone and share
+///
+/// Outlives reconnects and manager restarts when handed to each new
+/// manager, like the status channel.
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionBook {
+    inner: Arc<RwLock<Book>>,
+}
+
+/// Subscription as the manager tracks it: kind, pubkey, dex (programs
+/// only), commitment and id once confirmed
+pub(crate) type Tracked<'a> = (SubscriptionKind, &'a str, Option<&'a Dex>, Commitment, Option<u64>);
+
+impl SubscriptionBook {
+    /// Name the pair and DEX of each pool account, for reports
+    pub fn set_pools(&self, pools: HashMap<String, (String, Dex)>) {
+        self.inner.write().unwrap().pools = pools;
+    }
+
+    /// Replace the entries with what the manager tracks now, keeping the
+    /// notification times of subscriptions that are still there
+    pub(crate) fn sync<'a>(&self, tracked: impl IntoIterator<Item = Tracked<'a>>) {
+        let mut book = self.inner.write().unwrap();
+        let tracked: Vec<Tracked<'a>> = tracked.into_iter().collect();
+        for (_, pubkey, ..) in &tracked {
+            book.rejected.remove(*pubkey);
+        }
+        let mut previous = std::mem::take(&mut book.confirmed);
+        book.pending.clear();
+        for (kind, pubkey, dex, commitment, subscription_id) in tracked {
+            let last_notification = subscription_id
+                .and_then(|id| previous.remove(&id))
+                .map_or(0, |entry| entry.last_notification.into_inner());
+            let entry = Entry {
+                kind,
+                pubkey: pubkey.to_string(),
+                dex: dex.cloned(),
+                commitment,
+                last_notification: AtomicU64::new(last_notification),
+            };
+            match subscription_id {
+                Some(id) => {
+                    book.confirmed.insert(id, entry);
+                }
+                None => book.pending.push(entry),
+            }
+        }
+    }
+
+    /// The node refused the subscription for `pubkey`
+    pub(crate) fn rejected(&self, pubkey: &str, error: String) {
+        self.inner.write().unwrap().rejected.insert(pubkey.to_string(), error);
+    }
+
+    /// Pubkey (or program id) -> error, for subscriptions the node refused
+    pub fn rejections(&self) -> BTreeMap<String, String> {
+        self.inner.read().unwrap().rejected.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    /// A notification arrived on `subscription_id`
+    pub(crate) fn notified(&self, subscription_id: u64) {
+        let book = self.inner.read().unwrap();
+        if let Some(entry) = book.confirmed.get(&subscription_id) {
+            entry.last_notification.fetch_max(book.tick(Instant::now()), Ordering::Relaxed);
+        }
+    }
+
+    /// A notification for `pubkey` was dropped as out of slot order
+    pub(crate) fn out_of_order(&self, pubkey: &str) {
+        let mut book = self.inner.write().unwrap();
+        *book.out_of_order.entry(pubkey.to_string()).or_default() += 1;
+    }
+
+    /// Out-of-order drops per pool account, since startup
+    pub fn out_of_order_drops(&self) -> BTreeMap<String, u64> {
+        self.inner.read().unwrap().out_of_order.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    /// Every subscription, confirmed or pending, sorted by kind and pubkey
+    pub fn report(&self) -> Vec<SubscriptionInfo> {
+        let book = self.inner.read().unwrap();
+        let now = book.tick(Instant::now());
+        let confirmed = book.confirmed.iter().map(|(id, entry)| (Some(*id), entry));
+        let pending = book.pending.iter().map(|entry| (None, entry));
+        let mut report: Vec<SubscriptionInfo> = confirmed
+            .chain(pending)
+            .map(|(subscription_id, entry)| {
+                let pool = book.pools.get(&entry.pubkey);
+                let last_notification = entry.last_notification.load(Ordering::Relaxed);
+                SubscriptionInfo {
+                    kind: entry.kind,
+                    pubkey: entry.pubkey.clone(),
+                    pair: pool.map(|(pair, _)| pair.clone()),
+                    dex: entry.dex.clone().or_else(|| pool.map(|(_, dex)| dex.clone())),
+                    commitment: entry.commitment,
+                    subscription_id,
+                    confirmed: subscription_id.is_some(),
+                    ms_since_notification: (last_notification > 0).then(|| now.saturating_sub(last_notification)),
+                }
+            })
+            .collect();
+        report.sort_by(|a, b| (a.kind, &a.pubkey, a.commitment).cmp(&(b.kind, &b.pubkey, b.commitment)));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_keeps_notification_times() {
+        let book = SubscriptionBook::default();
+        book.set_pools(HashMap::from([("PoolA".to_string(), ("SOL/USDC".to_string(), Dex::Orca))]));
+        book.sync([(SubscriptionKind::Account, "PoolA", None, Commitment::Processed, Some(7))]);
+        book.notified(7);
+        // Ids the book doesn't know are ignored
+        book.notified(8);
+
+        book.sync([
+            (SubscriptionKind::Account, "PoolA", None, Commitment::Processed, Some(7)),
+            (SubscriptionKind::Account, "PoolB", None, Commitment::Processed, None),
+        ]);
+        let report = book.report();
+        assert_eq!(report[0].pair.as_deref(), Some("SOL/USDC"));
+        assert_eq!(report[0].dex, Some(Dex::Orca));
+        assert!(report[0].confirmed && report[0].ms_since_notification.is_some());
+        assert_eq!((report[1].pubkey.as_str(), report[1].confirmed), ("PoolB", false));
+        assert_eq!(report[1].ms_since_notification, None);
+
+        book.sync([]);
+        assert!(book.report().is_empty());
+    }
+
+    #[test]
+    fn test_rejections_clear_when_requested_again() {
+        let book = SubscriptionBook::default();
+        book.rejected("PoolA", "Invalid param".to_string());
+        book.sync([(SubscriptionKind::Account, "PoolB", None, Commitment::Processed, None)]);
+        assert_eq!(book.rejections().get("PoolA").map(String::as_str), Some("Invalid param"));
+
+        book.sync([(SubscriptionKind::Account, "PoolA", None, Commitment::Processed, None)]);
+        assert!(book.rejections().is_empty());
+    }
+}
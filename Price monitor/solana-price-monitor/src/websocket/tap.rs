@@ -0,0 +1,111 @@
+//! Raw frame taps for debugging decoders and recording fixtures
+//!
+//! A tap gets a copy of every JSON frame the feed reads that matches its
+//! filter, exactly as it came off the wire. Taps are bounded: frames that
+//! don't fit are dropped for that tap rather than slowing the reader.
+//! While no tap is registered the reader checks one atomic and moves on.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Frames a tap buffers before dropping new ones
+pub const TAP_BUFFER: usize = 1024;
+
+/// Which frames a tap receives; unset fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TapFilter {
+    /// Pool account: its notifications and subscription responses
+    pub pubkey: Option<String>,
+    /// JSON-RPC method, e.g. `accountNotification`
+    pub method: Option<String>,
+}
+
+impl TapFilter {
+    pub fn pubkey(pubkey: impl Into<String>) -> Self {
+        Self { pubkey: Some(pubkey.into()), method: None }
+    }
+
+    pub fn method(method: impl Into<String>) -> Self {
+        Self { pubkey: None, method: Some(method.into()) }
+    }
+
+    fn matches(&self, method: Option<&str>, pubkey: Option<&str>) -> bool {
+        self.pubkey.as_deref().map_or(true, |p| pubkey == Some(p))
+            && self.method.as_deref().map_or(true, |m| method == Some(m))
+    }
+}
+
+#[derive(Debug)]
+struct Tap {
+    filter: TapFilter,
+    tx: mpsc::Sender<String>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Any tap registered; read on every frame
+    active: AtomicBool,
+    taps: Mutex<Vec<Tap>>,
+}
+
+/// Registered taps, shared across reconnects and manager restarts like
+/// the subscription book
+#[derive(Debug, Clone, Default)]
+pub struct TapSet {
+    inner: Arc<Inner>,
+}
+
+impl TapSet {
+    /// Receive matching frames until the receiver is dropped
+    pub fn tap(&self, filter: TapFilter, buffer: usize) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel(buffer.max(1));
+        let mut taps = self.inner.taps.lock().unwrap();
+        taps.push(Tap { filter, tx });
+        self.inner.active.store(true, Ordering::Relaxed);
+        rx
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.inner.active.load(Ordering::Relaxed)
+    }
+
+    /// Hand `frame` to every tap it matches, forgetting taps whose
+    /// receiver is gone
+    pub(crate) fn offer(&self, frame: &str, method: Option<&str>, pubkey: Option<&str>) {
+        let mut taps = self.inner.taps.lock().unwrap();
+        taps.retain(|tap| !tap.tx.is_closed());
+        for tap in taps.iter().filter(|tap| tap.filter.matches(method, pubkey)) {
+            // Full: this tap misses the frame, the feed doesn't wait
+            let _ = tap.tx.try_send(frame.to_string());
+        }
+        if taps.is_empty() {
+            self.inner.active.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taps_are_bounded_and_forgotten_when_dropped() {
+        let taps = TapSet::default();
+        assert!(!taps.is_active());
+
+        let mut rx = taps.tap(TapFilter::default(), 2);
+        assert!(taps.is_active());
+        for frame in ["a", "b", "c"] {
+            taps.offer(frame, None, None);
+        }
+        assert_eq!((rx.try_recv().unwrap(), rx.try_recv().unwrap()), ("a".to_string(), "b".to_string()));
+        assert!(rx.try_recv().is_err());
+
+        drop(rx);
+        taps.offer("d", None, None);
+        assert!(!taps.is_active());
+    }
+}
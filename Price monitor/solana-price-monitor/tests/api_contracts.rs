@@ -0,0 +1,267 @@
+//! Wire format of the API-facing models, pinned against `tests/contracts/`
+//!
+//! Each test serializes a canonical instance and compares it with the
+//! checked-in JSON, so a renamed, retyped or dropped field fails here
+//! before it reaches the frontend. After an intended change, regenerate
+//! the files with `UPDATE_CONTRACTS=1 cargo test --test api_contracts` and
+//! review the diff.
+//!
+//! Timestamps, `ts` fields included, are RFC 3339 strings by default and
+//! epoch milliseconds with `api.timestamp_format = "millis"`. Both shapes
+//! are pinned, and models read either.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use solana_price_monitor::api::{api_schemas, required_fields, ApiMessage, PriceEntry, Snapshot, SystemMetrics};
+use solana_price_monitor::detector::{AggregatedOpportunity, Spread, SpreadAlert, SpreadVenue};
+use solana_price_monitor::metrics::ReceiptLatencySummary;
+use solana_price_monitor::models::{
+    Dex, LegSide, Opportunity, OpportunitySet, OpportunityType, PriceData, PriceSource, RouteLeg, TimestampFormat,
+    VenueFreshness, VolatilityRegime,
+};
+use solana_price_monitor::utils::{check_health, HealthStatus};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Compare `value` with `tests/contracts/{name}.json`, or rewrite the file
+/// when `UPDATE_CONTRACTS` is set
+///
+/// Policy for changing an API-facing model:
+/// - Add a field as optional for readers: `#[serde(default)]`, plus
+///   `skip_serializing_if` when leaving it out means "not known".
+///   `assert_optional_fields_default` holds every model that deserializes to
+///   this, and the field then stays out of the schema's `required` list.
+/// - Give the canonical instance below a non-default value for the new
+///   field, so the contract file shows it.
+/// - Renaming, retyping or removing a field breaks existing clients. Don't;
+///   if it can't be avoided, bump `api::PROTOCOL_VERSION` in the same change.
+fn assert_contract(name: &str, value: &Value) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/contracts").join(format!("{}.json", name));
+    if std::env::var_os("UPDATE_CONTRACTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string_pretty(value).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let contract = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let contract: Value = serde_json::from_str(&contract).unwrap();
+    assert_eq!(
+        value, &contract,
+        "{} no longer matches {}; if the change is intended, regenerate with UPDATE_CONTRACTS=1",
+        name,
+        path.display()
+    );
+}
+
+/// A reader of an older version still parses `json` from this one, and this
+/// version parses records from before its optional fields existed: with
+/// every field `name`'s schema doesn't require removed, `json` deserializes
+fn assert_optional_fields_default<T: DeserializeOwned>(name: &str, json: &Value) {
+    let required = required_fields(&api_schemas(), name);
+    let mut minimal = json.as_object().unwrap().clone();
+    minimal.retain(|field, _| required.contains(field));
+    assert!(minimal.len() < json.as_object().unwrap().len(), "{} has no optional fields to drop", name);
+
+    if let Err(e) = serde_json::from_value::<T>(Value::Object(minimal)) {
+        panic!("{} with only its required fields {:?} doesn't deserialize: {}", name, required, e);
+    }
+}
+
+fn at() -> DateTime<Utc> {
+    "2026-01-01T00:00:00Z".parse().unwrap()
+}
+
+fn price_data() -> PriceData {
+    let mut data = PriceData::new(101.25, 5_000_000, 250_000_000, 1_000_000_000_000, 101_250_000_000, 0.0025)
+        .with_price_exact(Some(Decimal::new(10_125, 2)))
+        .with_origin("58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2", PriceSource::Geyser);
+    data.timestamp = at();
+    data.received_at = at();
+    data.confirmed = true;
+    data.confidence_weight = 0.5;
+    data
+}
+
+fn opportunity() -> Opportunity {
+    let buy = price_data();
+    let sell = PriceData { price: 102.0, slot: 250_000_001, pool_pubkey: None, ..price_data() };
+    let mut opportunity = Opportunity::builder()
+        .id(Uuid::from_u128(0x6a3f_0000_0000_4000_8000_0000_0000_0001))
+        .opportunity_type(OpportunityType::Spatial)
+        .token_pair("SOL-USDC")
+        .buy("raydium", 101.25, 250_000_000)
+        .sell("orca", 102.0, 250_000_001)
+        .legs(vec![
+            RouteLeg::new("SOL-USDC", "raydium", LegSide::Buy, &buy),
+            RouteLeg::new("SOL-USDC", "orca", LegSide::Sell, &sell),
+        ])
+        .net_profit_percent(0.25)
+        .recommended_size(12_500_000_000)
+        .confidence(0.875)
+        .leg_slippage_percent(vec![0.0625, 0.125])
+        .assumed_slippage_pct(0.3)
+        .worst_case_profit_percent(0.125)
+        .persisted_slots(2)
+        .volatility_regime(Some(VolatilityRegime::Normal))
+        .detected_at(at())
+        .valid_until(at() + chrono::Duration::milliseconds(1500))
+        .build()
+        .unwrap();
+    opportunity.denominate("SOL", 9, "USDC", 6, 101.25);
+    opportunity
+}
+
+fn api_messages() -> Vec<ApiMessage> {
+    let spread = Spread {
+        pair: "SOL-USDC".to_string(),
+        bid_dex: Dex::Orca,
+        bid_price: 102.0,
+        ask_dex: Dex::Raydium,
+        ask_price: 101.25,
+        spread: 0.75,
+        spread_percent: 0.75,
+        venues: vec![
+            SpreadVenue { dex: Dex::Raydium, price: 101.25, bid: None, ask: None, slot: 250_000_000, age_ms: 40 },
+            SpreadVenue { dex: Dex::from("phoenix"), price: 101.5, bid: Some(101.5), ask: Some(101.5), slot: 250_000_000, age_ms: 10 },
+        ],
+    };
+    let entry = PriceEntry {
+        price: 101.25,
+        slot: 250_000_000,
+        age_ms: 40,
+        liquidity: 5_000_000,
+        fee_rate: 0.0025,
+        stale: false,
+        confirmed: true,
+        pool_pubkey: Some("58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2".to_string()),
+        source: PriceSource::Geyser,
+        received_at: at(),
+    };
+    let metrics = SystemMetrics {
+        fps: 120,
+        cache_entries: 8,
+        messages_per_sec: 250.5,
+        updates_per_sec: 110.25,
+        processing_p50_us: Some(85),
+        receipt_latency: BTreeMap::from([(
+            "raydium".to_string(),
+            ReceiptLatencySummary { samples: 400, p50_us: Some(900), p95_us: Some(2_100), p99_us: None },
+        )]),
+        ws_connected: true,
+        active_subscriptions: 8,
+        opportunities: BTreeMap::from([("spatial".to_string(), 3)]),
+        ..SystemMetrics::default()
+    };
+
+    vec![
+        ApiMessage::PriceUpdate { pair: "SOL-USDC".to_string(), dex: Dex::Raydium, price: 101.25, slot: 250_000_000, ts: at() },
+        ApiMessage::OpportunityFound(opportunity()),
+        ApiMessage::OpportunityGroup(AggregatedOpportunity {
+            fingerprint: vec!["SOL-USDC@orca".to_string(), "SOL-USDC@raydium".to_string()],
+            best: opportunity(),
+            strategies: vec![opportunity()],
+        }),
+        ApiMessage::SpreadAlert(SpreadAlert {
+            pair: "SOL-USDC".to_string(),
+            low_dex: Dex::Raydium,
+            high_dex: Dex::Orca,
+            low_price: 101.25,
+            high_price: 102.0,
+            spread_bps: 74.07,
+            threshold_bps: 50.0,
+        }),
+        ApiMessage::Spread(spread.clone()),
+        ApiMessage::PairSummary(Box::new(OpportunitySet {
+            pair: "SOL-USDC".to_string(),
+            best_spatial: Some(opportunity()),
+            best_triangular: None,
+            spread: Some(spread),
+            venues: vec![VenueFreshness {
+                dex: Dex::Raydium,
+                price: 101.25,
+                slot: 250_000_000,
+                timestamp: at(),
+                age_ms: 40,
+                stale: false,
+            }],
+            recent_opportunities: 3,
+            window_seconds: 300,
+            updated_at: at(),
+        })),
+        ApiMessage::Snapshot(Snapshot {
+            prices: BTreeMap::from([("SOL-USDC".to_string(), BTreeMap::from([("raydium".to_string(), entry)]))]),
+            opportunities: vec![opportunity()],
+        }),
+        ApiMessage::SystemStatus {
+            paused: false,
+            reason: None,
+            rpc_endpoint: "primary".to_string(),
+            ws_connected: true,
+            consecutive_failures: 0,
+            connection_uptime_secs: Some(3_600),
+            active_subscriptions: 8,
+            slow_dexes: vec!["meteora".to_string()],
+        },
+        ApiMessage::SystemMetrics(metrics),
+    ]
+}
+
+#[test]
+fn price_data_contract() {
+    let json = serde_json::to_value(price_data()).unwrap();
+    assert_contract("price_data", &json);
+    assert_optional_fields_default::<PriceData>("PriceData", &json);
+}
+
+#[test]
+fn opportunity_contract() {
+    let json = serde_json::to_value(opportunity()).unwrap();
+    assert_contract("opportunity", &json);
+    assert_optional_fields_default::<Opportunity>("Opportunity", &json);
+}
+
+#[test]
+fn opportunity_type_contract() {
+    assert_contract("opportunity_type", &serde_json::to_value(OpportunityType::ALL).unwrap());
+}
+
+#[test]
+fn api_message_contract() {
+    assert_contract("api_message", &serde_json::to_value(api_messages()).unwrap());
+}
+
+#[test]
+fn api_message_millis_contract() {
+    let json = TimestampFormat::Millis.scope(|| serde_json::to_value(api_messages())).unwrap();
+    assert_contract("api_message_millis", &json);
+}
+
+#[test]
+fn models_read_either_timestamp_format() {
+    let json = TimestampFormat::Millis.scope(|| serde_json::to_value(price_data())).unwrap();
+    assert_eq!(json["timestamp"], 1_767_225_600_000i64);
+    let price: PriceData = serde_json::from_value(json).unwrap();
+    assert_eq!((price.timestamp, price.received_at), (at(), at()));
+
+    let json = TimestampFormat::Millis.scope(|| serde_json::to_value(opportunity())).unwrap();
+    let read: Opportunity = serde_json::from_value(json).unwrap();
+    assert_eq!(serde_json::to_value(read).unwrap(), serde_json::to_value(opportunity()).unwrap());
+}
+
+#[test]
+fn health_status_contract() {
+    let status = HealthStatus {
+        dex_last_update_ms: BTreeMap::from([("meteora".to_string(), 45_000), ("raydium".to_string(), 120)]),
+        stale_dexes: vec!["meteora".to_string()],
+        ..check_health(42, true, 120, 3_600, 5_000)
+    };
+    assert_contract("health_status", &serde_json::to_value(status).unwrap());
+}
+
+#[test]
+fn schema_contract() {
+    assert_contract("schema", &api_schemas());
+}
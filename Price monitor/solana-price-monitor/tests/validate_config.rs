@@ -0,0 +1,70 @@
+//! `validate-config` end to end: exit status and redacted output
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const CONFIG: &str = r#"
+[rpc]
+websocket_url = "wss://mainnet.helius-rpc.com/?api-key=0123456789abcdef"
+http_url = "https://mainnet.helius-rpc.com/?api-key=0123456789abcdef"
+
+[monitoring]
+max_pools = 10
+cache_ttl_seconds = 60
+cleanup_interval_seconds = 10
+stale_threshold_ms = 2000
+
+[arbitrage]
+min_profit_percent = MIN_PROFIT
+max_trade_size_percent = 5.0
+slot_tolerance = 2
+
+[fees]
+default_dex_fee = 0.25
+estimated_slippage = 0.3
+gas_cost_percent = 0.01
+jito_tip_percent = 0.05
+
+[pools.sol_usdc]
+orca = "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ"
+"#;
+
+/// Run `validate-config` in a scratch directory holding `config.toml`
+fn validate(name: &str, min_profit: &str) -> Output {
+    let dir: PathBuf = std::env::temp_dir().join(format!("validate-config-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), CONFIG.replace("MIN_PROFIT", min_profit)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-price-monitor"))
+        .args(["validate-config", "--format", "json"])
+        .current_dir(&dir)
+        .env_remove("RPC_WS_URL")
+        .env_remove("RPC_HTTP_URL")
+        .env_remove("ALCHEMY_API_KEY")
+        .env_remove("HELIUS_API_KEY")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+    output
+}
+
+#[test]
+fn valid_config_prints_redacted_settings() {
+    let output = validate("valid", "0.5");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let printed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(
+        printed["rpc"]["endpoints"][0]["http_url"],
+        "https://mainnet.helius-rpc.com/?api-key=****cdef"
+    );
+    assert!(!stdout.contains("0123456789abcdef"));
+}
+
+#[test]
+fn invalid_config_exits_non_zero() {
+    let output = validate("invalid", "-1.0");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("min_profit_percent must be positive"));
+}